@@ -0,0 +1,75 @@
+#!/usr/bin/env python3
+# Built-in token diff checker, used when task.yaml contains `checker: { type: token_diff }`.
+#
+# Usage: token_diff.py correct test tolerance mode
+#
+# The two files are split into whitespace-separated tokens. The tokens that are
+# both decimal numbers are compared with the tolerance, where mode is one of:
+#  - absolute: |test - correct| <= tolerance
+#  - relative: |test - correct| <= tolerance * |correct|
+#  - either: at least one of the two
+# All the other tokens are compared exactly.
+#
+# Exits with 0 if the output is correct, with 1 if it's not, printing the reason
+# to stdout.
+
+import re
+import sys
+
+NUMBER = re.compile(r"[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?")
+
+
+def tokens(path):
+    with open(path, "rb") as f:
+        for line in f:
+            yield from line.split()
+
+
+def numbers_match(correct, test, tolerance, mode):
+    diff = abs(correct - test)
+    absolute = diff <= tolerance
+    relative = diff <= tolerance * abs(correct)
+    if mode == "absolute":
+        return absolute
+    if mode == "relative":
+        return relative
+    return absolute or relative
+
+
+def tokens_match(correct, test, tolerance, mode):
+    if correct == test:
+        return True
+    try:
+        correct, test = correct.decode(), test.decode()
+    except UnicodeDecodeError:
+        return False
+    if not NUMBER.fullmatch(correct) or not NUMBER.fullmatch(test):
+        return False
+    return numbers_match(float(correct), float(test), tolerance, mode)
+
+
+def main():
+    correct_path, test_path, tolerance, mode = sys.argv[1:]
+    tolerance = float(tolerance)
+    correct_tokens = tokens(correct_path)
+    test_tokens = tokens(test_path)
+    index = 0
+    while True:
+        correct = next(correct_tokens, None)
+        test = next(test_tokens, None)
+        index += 1
+        if correct is None and test is None:
+            return 0
+        if correct is None:
+            print("Output is incorrect: too many tokens")
+            return 1
+        if test is None:
+            print("Output is incorrect: too few tokens")
+            return 1
+        if not tokens_match(correct, test, tolerance, mode):
+            print(f"Output is incorrect: token {index} is wrong")
+            return 1
+
+
+if __name__ == "__main__":
+    sys.exit(main())
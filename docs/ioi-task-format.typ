@@ -32,7 +32,10 @@ A task's folder should contain the following:
 
 == `task.toml`
 
-This file serves a similar role to CMS's `task.yaml` file.
+This file serves a similar role to CMS's `task.yaml` file, and it accepts the
+same keys. When a task has both files, `task.toml` takes precedence and
+`task.yaml` is generated from it; a `task.yaml` written by hand (i.e. without
+the `tm-allow-delete` marker of the generated files) is never overwritten.
 
 The following are the most commonly set keys in this file:
 - `title`: the title of this task, as will be shown in CMS.
@@ -41,6 +44,34 @@ The following are the most commonly set keys in this file:
   seconds.
 - `memory_limit`: the maximum amount of memory that a solution can use, in
   mebibytes.
+- `relative_time_limit`: if set, the CPU time limit of the solutions on a
+  testcase is this multiple of the CPU time used by the official solution on
+  it, when lower than `time_limit`. The official solution is run also on the
+  testcases with a static output file, and its time is cached. Without an
+  official solution only `time_limit` is enforced. Only supported by batch
+  tasks.
+- `extra_output_files`: a list of other files that the solutions of a batch task
+  write besides the main output. The official ones are stored next to the
+  output files, e.g. `output/output0.second.txt` for `second.txt`. The checker
+  is run on each file separately, with `TM_OUTPUT_FILE` set to its name, and
+  the score of the testcase is the average of the scores. A missing file
+  scores zero. Not supported by CMS.
+- `compilation_time_limit`: the maximum amount of CPU time that the compilation
+  of a source file can take, in seconds (defaults to 60). A compilation
+  exceeding it is reported as timed out instead of as a compilation error. Not
+  supported by CMS.
+- `compilation_memory_limit`: the maximum amount of memory that the compilation
+  of a source file can use, in mebibytes (unlimited by default). Not supported
+  by CMS.
+- `generation_wall_time_limit`: the maximum amount of wall time that a
+  generator or a validator can run for, in seconds (defaults to 60). A
+  generator exceeding it is reported as timed out instead of as failed; raise
+  it for the generators of very large testcases. Not supported by CMS.
+- `normalize_files`: if `true`, the UTF-8 BOM is removed and the CRLF line
+  endings are converted to LF in the input and output files written by the
+  generators and by the official solution, before they are validated and
+  stored (defaults to `false`). The files containing a NUL byte are considered
+  binary and left untouched. Not supported by CMS.
 - `controller_time_limit`: the maximum amount of time that the controller can run
   for, in seconds (defaults to `time_limit + 1.0`).
 - `controller_wall_time_limit`: the maximum amount of wall time that the
@@ -54,6 +85,9 @@ The following are the most commonly set keys in this file:
   memory max-ed). Defaults to `true`.
 - `score_precision`: the number of decimal digits to round scores for this task
   to (defaults to 0, i.e. integers).
+- `score_rounding`: how the displayed scores are rounded to `score_precision`
+  digits, either `half_up` or `truncate` (defaults to `half_up`). Scores are
+  always computed with full precision.
 - `user_io`: set this value to `fifo_io` to have solutions in communication
   tasks communicate via FIFOs (by default they communicate via standard I/O,
   which is `std_io`).
@@ -95,6 +129,12 @@ This folder may contain files called `grader.<ext>` or `stub.<ext>`, which will
 not be considered as solutions. Instead, they will be compiled together with
 the contestant's solution, in batch and communication tasks respectively.
 
+Other files that are not solutions, like notes or drafts, can be excluded by
+listing them in `sol/.tmignore`, one glob pattern per line, like in a
+`.gitignore` file. The patterns containing a `/` are relative to the task
+root (e.g. `sol/draft_*.cpp`), the others match the file name (e.g. `*.txt`),
+and the ones starting with `!` include the files back.
+
 A solution can contain assertions about the behaviour of that solution on
 various subtasks. An assertion is a string starting by a string identifying a
 supported check type, followed by a `:`, followed by a space-separated list of
@@ -142,9 +182,34 @@ define checks that require the solution to get a full score on subtasks named
   caption: [Some subtask checks from "patricians" from WEOI 2025.],
 )
 
+The checks above look at the outcomes of the testcases. The score of a
+solution on a subtask can be checked as well:
+- `@check-pass`: the solution achieves the full score on these subtasks.
+- `@check-fail`: the solution does not achieve the full score on these
+  subtasks.
+- `@check-score`: followed by a range `<min>-<max>` and by the subtask names,
+  the score of the solution on these subtasks (in points) is between `<min>`
+  and `<max>`, inclusive.
+
+For example `// @check-score: 10-25 sizetwo` requires the solution to get
+between 10 and 25 points on the subtask `sizetwo`. An error is reported for
+each subtask whose score does not satisfy its checks.
+
 `task-maker-rust` warns when solutions do not contain checks. The tool
 `add-solution-checks` in `task-maker-tools` can be used to add checks that
-match the behaviour of solutions on the current machine.
+match the behaviour of solutions on the current machine, both on the outcomes
+of the testcases and on the scores of the subtasks.
+
+Solutions can also declare the maximum resources they are expected to use on
+any testcase, with `@max-time` (in `ms` or `s`, measured as CPU time) and
+`@max-memory` (in `KiB`, `MiB` or `GiB`). For example
+
+```cpp
+// @max-time: 800ms @max-memory: 200MiB
+```
+
+makes `task-maker-rust` report an error if the solution uses more than 800
+milliseconds or more than 200 MiB on at least one testcase.
 
 = `gen` folder
 
@@ -386,6 +451,13 @@ and the logo of the contest (`logo.png`) as auxiliary files.
 
 // TODO: make the statement template a typst package to simplify this part.
 
+Only the figures (`.pdf`, `.png`, `.jpg`, `.jpeg`, `.svg`, `.eps` and `.gif` files)
+referenced by the statement, or by the files it includes, are used to compile
+it, together with the logo of the contest. If a figure is referenced with a path
+that is computed during the compilation (e.g. `image(path)`), or its name is
+passed to another command (e.g. a custom macro) or used by another file in the
+directory (e.g. a `.sty` file), all the figures are used and a warning is emitted.
+
 We recommend using the `cetz` typst package for drawing figures. Given the
 easy-to-use scripting capabilities of Typst, it is often easy to produce
 reasonable looking figures directly with `cetz`, and this results in higher
@@ -513,6 +585,21 @@ task, for which details are given in #ref(<communication>).
 If it contains `controller.<ext>`, the task is interpreted as an interactive
 task, for which details are given in #ref(<interactive>).
 
+If the folder is empty, the output of the solution is compared with the one of
+the master solution ignoring the white spaces. For numeric outputs, a built-in
+token diff checker can be configured in `task.yaml` instead:
+```yaml
+checker:
+  type: token_diff
+  float_tolerance: 1e-6     # defaults to 0
+  tolerance_mode: either    # absolute, relative or either (default)
+```
+The outputs are split into tokens separated by white spaces. Two tokens that
+are both decimal numbers are equal if `|test - correct| <= float_tolerance`
+(`absolute`), if `|test - correct| <= float_tolerance * |correct|`
+(`relative`), or if either holds (`either`). All the other tokens must be
+identical. This checker requires `python3` and is not supported by CMS.
+
 The `checker.<ext>` file will be compiled by `task-maker-rust` (if necessary),
 and gets executed both by `task-maker-rust` and by CMS with three command line
 arguments, in order:
@@ -525,6 +612,10 @@ between `0.0` and `1.0`, and on standard error a message for the contestant.
 The special messages `translate:success`, `translate:wrong` and `translate:partial`
 are shown by CMS as translated strings in the UI.
 
+When evaluating a solution, `task-maker-rust` sets the `TM_SUBTASK` environment
+variable to the index of the subtask of the testcase, and `TM_SUBTASK_NAME` to
+its name (if the subtask has one). Note that CMS does not set these variables.
+
 A checker should *never* return a non-zero error code or crash, as CMS will
 mark evaluation as failed in that case.
 
@@ -555,6 +646,23 @@ state in the checker, so try to avoid global variables.
   caption: [A checker that always gives $0$ points.],
 )
 
+== `scorer.<ext>`
+
+The `check` folder can also contain a scoring script, `scorer.<ext>`, that
+replaces the `score_type` of the task when computing the score of the subtasks.
+It can be written in any supported language, like the checker, and it is run
+in the sandbox by `task-maker-rust` once for each solution, after all its
+testcases are scored.
+Note that CMS does not support it.
+
+The scorer receives on standard input a JSON object with the scores of the
+testcases, like
+`{"subtasks": {"0": {"max_score": 10.0, "testcases": {"0": 1.0, "1": 0.5}}}}`,
+and it should write on standard output a JSON object with the score of each
+subtask, between `0` and its `max_score`, like `{"0": 5.0}`. If the scorer
+fails or its output is not valid an error is reported, and all the subtasks of the solution get $0$
+points.
+
 = Communication tasks
 <communication>
 
@@ -574,6 +682,15 @@ FIFOs if `user_io: fifo_io`; this last mode is only recommended when using a
 stub.
 // TODO: std_io seems broken in tmr.
 
+If `num_processes` is set in `task.yaml`, the solution is run in that many
+parallel processes: the manager receives two FIFOs for each of them, and each
+process receives its index as the last command line argument. The number of
+processes can be overridden with `task-maker --num-processes`. Since the
+protocol may support only some numbers of processes, the manager should check
+the number of FIFOs it receives and exit with an error message on standard
+error if it's not supported: the message is shown as the outcome of the
+testcases.
+
 Note that it is very easy to deadlock execution in communication tasks. You
 should take care to ensure that FIFOs are opened in the correct order, and that
 all writes are flushed.
@@ -2,22 +2,29 @@
 //!
 //! The structs here follow a multi-step builder pattern, moving from a struct to the next adding
 //! more and more context.
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 
 use anyhow::{anyhow, bail, Context, Error};
-use task_maker_cache::Cache;
 use task_maker_dag::CacheMode;
+use task_maker_diagnostics::Diagnostic;
 use task_maker_exec::ductile::{new_local_channel, ChannelReceiver, ChannelSender};
 use task_maker_exec::executors::{LocalExecutor, RemoteEntityMessage, RemoteEntityMessageResponse};
 use task_maker_exec::proto::{ExecutorClientMessage, ExecutorServerMessage};
-use task_maker_exec::ExecutorClient;
-use task_maker_format::ui::{UIChannelReceiver, UIMessage, UIType, UI};
+use task_maker_exec::{ExecutorClient, SandboxRunner};
+use task_maker_format::ioi::JUnitCriterion;
+use task_maker_format::ui::{
+    DiagnosticsReport, UIChannelReceiver, UIMessage, UIMessageTransform, UIType, UI,
+};
 use task_maker_format::{EvaluationData, TaskFormat, UISender, VALID_TAGS};
 use task_maker_store::FileStore;
 
 use crate::remote::connect_to_remote_server;
-use crate::{render_dag, ExecutionOpt, StorageOpt, ToolsSandboxRunner};
+use crate::{
+    bind_execution_logs, randomize_order, render_dag, ExecutionOpt, MetricsPusher,
+    SandboxRunnerFactory, StorageOpt, ToolsSandboxRunner,
+};
 
 /// Version of task-maker.
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -28,7 +35,8 @@ pub struct RuntimeContext {
     pub task: TaskFormat,
     pub eval: EvaluationData,
     pub ui_receiver: UIChannelReceiver,
-    pub sandbox_runner: ToolsSandboxRunner,
+    pub sandbox_runner: Option<SandboxRunnerFactory>,
+    pub metrics: Option<MetricsPusher>,
 }
 
 /// Second step: connect to an executor (either local or remote). This opens the local store and
@@ -38,12 +46,15 @@ pub struct ConnectedExecutor {
     pub task: TaskFormat,
     pub eval: EvaluationData,
     pub ui_receiver: UIChannelReceiver,
+    pub metrics: Option<MetricsPusher>,
 
     // new fields
     pub file_store: Arc<FileStore>,
     pub tx: ChannelSender<ExecutorClientMessage>,
     pub rx: ChannelReceiver<ExecutorServerMessage>,
     pub local_executor: Option<JoinHandle<Result<(), Error>>>,
+    pub ui_transforms: Vec<Box<dyn UIMessageTransform>>,
+    pub ui_reports: Vec<Box<dyn UI>>,
 }
 
 /// Third step: start the UI thread.
@@ -55,6 +66,7 @@ pub struct ConnectedExecutorWithUI {
     pub tx: ChannelSender<ExecutorClientMessage>,
     pub rx: ChannelReceiver<ExecutorServerMessage>,
     pub local_executor: Option<JoinHandle<Result<(), Error>>>,
+    pub metrics: Option<MetricsPusher>,
 
     // new fields
     pub ui_thread: JoinHandle<()>,
@@ -85,6 +97,9 @@ impl RuntimeContext {
             )
             .copy_exe(opt.copy_exe)
             .copy_logs(opt.copy_logs)
+            .explain_cache_miss(opt.explain_cache_miss)
+            .show_workers(opt.show_workers)
+            .stream_chunk_size(opt.stream_dag.map(|size| size as usize))
             .priority(opt.priority);
         if let Some(extra_time) = opt.extra_time {
             if extra_time < 0.0 {
@@ -96,8 +111,29 @@ impl RuntimeContext {
             config.extra_memory(extra_memory);
         }
 
+        if opt.explain_cache_miss {
+            let sender = eval.sender.clone();
+            eval.dag.on_cache_miss(move |description, reason| {
+                sender.add_diagnostic(
+                    Diagnostic::warning(format!("Cache miss for {description}"))
+                        .with_note(reason.to_string()),
+                )
+            });
+        }
+
         // build the execution dag
         build_dag(&mut task, &mut eval)?;
+        if let Some(seed) = opt.randomize_order {
+            let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+            info!("Randomizing the order of the executions with seed {seed}");
+            randomize_order(&mut eval.dag, seed);
+            eval.add_diagnostic(
+                Diagnostic::warning(format!(
+                    "The order of the executions is randomized with seed {seed}"
+                ))
+                .with_help(format!("Use --randomize-order={seed} to reproduce it")),
+            )?;
+        }
 
         trace!("The DAG is: {:#?}", eval.dag);
         if opt.copy_dag {
@@ -106,18 +142,43 @@ impl RuntimeContext {
             std::fs::create_dir_all(&bin).context("Failed to create bin/ directory")?;
             std::fs::write(bin.join("DAG.dot"), dot).context("Failed to write bin/DAG.dot")?;
         }
+        if let Some(dir) = &opt.execution_logs {
+            bind_execution_logs(&mut eval.dag, dir).context("Failed to bind execution logs")?;
+        }
+        let metrics = opt.push_metrics.as_ref().map(|gateway| {
+            let task_name = match &task {
+                TaskFormat::IOI(task) => task.name.as_str(),
+                TaskFormat::Terry(task) => task.name.as_str(),
+                TaskFormat::Custom(task) => task.name(),
+            };
+            MetricsPusher::new(gateway, task_name, &mut eval.dag)
+        });
 
-        Ok(Self {
+        let mut context = Self {
             task,
             eval,
             ui_receiver,
-            sandbox_runner: ToolsSandboxRunner::default(),
-        })
+            sandbox_runner: None,
+            metrics,
+        };
+        if let Some(command) = &opt.sandbox_runner {
+            context.sandbox_runner(ToolsSandboxRunner::from_command(command)?);
+        }
+        Ok(context)
     }
 
     /// Change the default sandbox runner for the local executor.
-    pub fn sandbox_runner(&mut self, sandbox_runner: ToolsSandboxRunner) {
-        self.sandbox_runner = sandbox_runner;
+    pub fn sandbox_runner<R: SandboxRunner + 'static>(&mut self, sandbox_runner: R) {
+        self.sandbox_runner_factory(move || Ok(Box::new(sandbox_runner)));
+    }
+
+    /// Change the default sandbox runner for the local executor, building it only if the executor
+    /// is actually local. Connecting to a remote executor fails if a custom runner is set.
+    pub fn sandbox_runner_factory<F>(&mut self, factory: F)
+    where
+        F: FnOnce() -> Result<Box<dyn SandboxRunner>, Error> + 'static,
+    {
+        self.sandbox_runner = Some(Box::new(factory));
     }
 
     /// Start the local executor or connect to a remote one.
@@ -127,20 +188,18 @@ impl RuntimeContext {
         storage_opt: &StorageOpt,
     ) -> Result<ConnectedExecutor, Error> {
         // setup the file store
-        let store_path = storage_opt.store_dir();
-        let file_store = Arc::new(
-            FileStore::new(
-                store_path.join("store"),
-                storage_opt.max_cache * 1024 * 1024,
-                storage_opt.min_cache * 1024 * 1024,
-            )
-            .context(
-                "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
-            )?,
-        );
+        let file_store = Arc::new(storage_opt.file_store().context(
+            "Cannot create the file store (You can try wiping it with task-maker-tools reset)",
+        )?);
 
         // connect either to the remote executor or spawn a local one
         let (tx, rx, local_executor) = if let Some(evaluate_on) = &opt.evaluate_on {
+            if self.sandbox_runner.is_some() {
+                bail!(
+                    "A custom sandbox runner cannot be used with a remote executor, the sandboxes \
+                    are run by the remote workers (use task-maker-tools worker --sandbox-runner)"
+                );
+            }
             let (tx, rx) = connect_to_remote_server(evaluate_on, 27182)
                 .context("Cannot connect to the remote server")?;
             let name = opt.name.clone().unwrap_or_else(|| {
@@ -167,18 +226,21 @@ impl RuntimeContext {
             let (tx_remote, rx) = new_local_channel();
 
             // setup the local cache
-            let cache_path = store_path.join("cache");
-            let cache = Cache::new(cache_path).context("Cannot create the cache")?;
+            let cache = storage_opt.cache().context("Cannot create the cache")?;
 
             // setup the local executor
             let num_cores = opt.num_cores.unwrap_or_else(num_cpus::get_physical);
             let sandbox_path = storage_opt.store_dir().join("sandboxes");
+            let sandbox_runner = match self.sandbox_runner {
+                Some(factory) => factory().context("Failed to create the sandbox runner")?,
+                None => Box::new(ToolsSandboxRunner::default()),
+            };
             let executor = LocalExecutor::new(
                 file_store.clone(),
                 cache,
                 num_cores,
                 sandbox_path,
-                self.sandbox_runner,
+                sandbox_runner,
             )?;
             let local_executor = std::thread::Builder::new()
                 .name("Executor thread".into())
@@ -191,16 +253,53 @@ impl RuntimeContext {
             task: self.task,
             eval: self.eval,
             ui_receiver: self.ui_receiver,
+            metrics: self.metrics,
 
             file_store,
             tx,
             rx,
             local_executor,
+            ui_transforms: vec![],
+            ui_reports: vec![],
         })
     }
 }
 
 impl ConnectedExecutor {
+    /// Register a transformation to apply to each `UIMessage` before it reaches the UI. The
+    /// transformations are applied in the order they are registered.
+    pub fn add_ui_transform<T: UIMessageTransform + 'static>(&mut self, transform: T) {
+        self.ui_transforms.push(Box::new(transform));
+    }
+
+    /// Write the results of the solutions to `path` as a JUnit XML report, at the end of the
+    /// evaluation.
+    pub fn add_junit_report(
+        &mut self,
+        path: &Path,
+        criterion: JUnitCriterion,
+    ) -> Result<(), Error> {
+        let config = self.eval.dag.config_mut().clone();
+        let report = self.task.junit_report(path, criterion, config)?;
+        self.ui_reports.push(report);
+        Ok(())
+    }
+
+    /// Write the summary of the results of the evaluation to `path` as JSON, at the end of the
+    /// evaluation.
+    pub fn add_summary_report(&mut self, path: &Path) -> Result<(), Error> {
+        let config = self.eval.dag.config_mut().clone();
+        let report = self.task.summary_report(path, config)?;
+        self.ui_reports.push(report);
+        Ok(())
+    }
+
+    /// Write the diagnostics emitted during the evaluation to `path` as JSON, at the end of the
+    /// evaluation.
+    pub fn add_diagnostics_report(&mut self, path: &Path) {
+        self.ui_reports.push(Box::new(DiagnosticsReport::new(path)));
+    }
+
     /// Now that we are connected to an executor, we can start the UI thread in background. This
     /// thread will run until the execution is completed or until it is stopped.
     ///
@@ -220,6 +319,8 @@ impl ConnectedExecutor {
             .ui(ui_type, config)
             .context("This UI is not supported on this task type")?;
         let ui_receiver = self.ui_receiver;
+        let mut ui_transforms = self.ui_transforms;
+        let mut ui_reports = self.ui_reports;
         let ui_thread = std::thread::Builder::new()
             .name("UI".to_owned())
             .spawn(move || {
@@ -227,9 +328,18 @@ impl ConnectedExecutor {
                     if let UIMessage::StopUI = message {
                         break;
                     }
+                    let message = ui_transforms
+                        .iter_mut()
+                        .fold(message, |message, transform| transform.transform(message));
+                    for report in ui_reports.iter_mut() {
+                        report.on_message(message.clone());
+                    }
                     on_message(ui.as_mut(), message);
                 }
                 ui.finish();
+                for report in ui_reports.iter_mut() {
+                    report.finish();
+                }
             })
             .context("Failed to spawn UI thread")?;
 
@@ -260,6 +370,7 @@ impl ConnectedExecutor {
             tx: self.tx,
             rx: self.rx,
             local_executor: self.local_executor,
+            metrics: self.metrics,
 
             ui_thread,
             client_sender,
@@ -298,18 +409,26 @@ impl ConnectedExecutorWithUI {
 
         // run the actual computation and block until it ends
         let client_sender = self.client_sender;
-        ExecutorClient::evaluate(dag, self.tx, &self.rx, self.file_store, move |status| {
-            ui_sender.send(UIMessage::ServerStatus { status })
-        })
-        .with_context(|| {
-            if let Some(tx) = client_sender.lock().unwrap().as_ref() {
-                let _ = tx.send(ExecutorClientMessage::Stop);
-            }
-            "Client failed"
-        })?;
+        let cache_stats =
+            ExecutorClient::evaluate(dag, self.tx, &self.rx, self.file_store, move |status| {
+                ui_sender.send(UIMessage::ServerStatus { status })
+            })
+            .with_context(|| {
+                if let Some(tx) = client_sender.lock().unwrap().as_ref() {
+                    let _ = tx.send(ExecutorClientMessage::Stop);
+                }
+                "Client failed"
+            })?;
+        if let Some(stats) = cache_stats {
+            self.eval.sender.send(UIMessage::CacheStats { stats })?;
+        }
         // disable the ctrl-c handler dropping the owned clone of the sender, letting the client exit
         client_sender.lock().unwrap().take();
 
+        if let Some(metrics) = self.metrics {
+            metrics.push();
+        }
+
         self.task
             .sanity_check_post_hook(&mut self.eval)
             .context("Sanity checks failed")?;
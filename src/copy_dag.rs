@@ -23,7 +23,10 @@ pub fn render_dag(dag: &ExecutionDAG) -> String {
     let mut files = HashMap::new();
     for file in dag.data.provided_files.values() {
         match file {
-            ProvidedFile::LocalFile { file, .. } | ProvidedFile::Content { file, .. } => {
+            ProvidedFile::LocalFile { file, .. }
+            | ProvidedFile::Content { file, .. }
+            | ProvidedFile::StoredFile { file, .. }
+            | ProvidedFile::Late { file } => {
                 files.insert(file.uuid, file.clone());
             }
         }
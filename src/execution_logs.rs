@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use task_maker_dag::{ExecutionDAG, ExecutionOutputBehaviour};
+
+/// Write the captured stdout/stderr of every execution of the DAG inside `dir`, after the
+/// execution completes.
+///
+/// The files are named after the description of the execution followed by the uuid of its group
+/// (and by the index of the execution inside the group, if the group has more than one), with the
+/// `.stdout` or `.stderr` extension, so the executions with the same description don't overwrite
+/// each other's logs. The streams that are not captured produce no file, and if a captured stream reached
+/// its size limit a note is appended to the file.
+pub fn bind_execution_logs(dag: &mut ExecutionDAG, dir: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut callbacks = vec![];
+    for group in dag.data.execution_groups.values() {
+        let streams: Vec<_> = group
+            .executions
+            .iter()
+            .enumerate()
+            .map(|(index, exec)| {
+                let mut name = format!("{}.{}", sanitize_file_name(&exec.description), group.uuid);
+                if group.executions.len() > 1 {
+                    name += &format!(".{index}");
+                }
+                (
+                    dir.join(format!("{name}.stdout")),
+                    size_limit(&exec.stdout),
+                    dir.join(format!("{name}.stderr")),
+                    size_limit(&exec.stderr),
+                )
+            })
+            .collect();
+        callbacks.push((group.uuid, streams));
+    }
+    for (group, streams) in callbacks {
+        dag.on_execution_done(&group, move |results| {
+            for (result, (stdout_path, stdout_limit, stderr_path, stderr_limit)) in
+                results.iter().zip(streams)
+            {
+                write_log(stdout_path, result.stdout.as_deref(), stdout_limit);
+                write_log(stderr_path, result.stderr.as_deref(), stderr_limit);
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// The size limit of a captured stream, `None` if the stream is not captured or unlimited.
+fn size_limit(behaviour: &ExecutionOutputBehaviour) -> Option<usize> {
+    match behaviour {
+        ExecutionOutputBehaviour::Capture { size_limit, .. } => *size_limit,
+        _ => None,
+    }
+}
+
+/// Write the content of a stream to `path`, noting if the content may have been truncated. Failing
+/// to write the log is not fatal for the evaluation.
+fn write_log(path: PathBuf, content: Option<&[u8]>, size_limit: Option<usize>) {
+    let Some(content) = content else {
+        return;
+    };
+    let mut content = content.to_vec();
+    if let Some(limit) = size_limit {
+        if content.len() >= limit {
+            content.extend_from_slice(
+                format!("\n[task-maker: output truncated to {limit} bytes]\n").as_bytes(),
+            );
+        }
+    }
+    if let Err(e) = std::fs::write(&path, content) {
+        warn!("Failed to write execution log {}: {:?}", path.display(), e);
+    }
+}
+
+/// Replace the characters that are not safe in a file name with underscores.
+fn sanitize_file_name(description: &str) -> String {
+    description
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -12,15 +12,21 @@ extern crate lazy_static;
 extern crate scopeguard;
 
 pub use copy_dag::*;
+pub use execution_logs::*;
 pub use local::*;
+pub use metrics::*;
 pub use opt::*;
+pub use randomize_order::*;
 pub use sandbox::*;
 
 pub mod context;
 pub mod copy_dag;
 pub mod error;
+pub mod execution_logs;
 pub mod local;
+pub mod metrics;
 pub mod opt;
+pub mod randomize_order;
 pub mod remote;
 pub mod sandbox;
 pub mod tools;
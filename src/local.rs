@@ -1,5 +1,7 @@
 use anyhow::{bail, Context, Error};
-use task_maker_format::ui::{UIMessage, UI};
+use task_maker_diagnostics::DiagnosticLevel;
+use task_maker_format::ui::{SolutionAnonymizer, UIMessage, UI};
+use task_maker_format::{EvaluationConfig, EvaluationData, TaskFormat};
 
 use crate::context::RuntimeContext;
 use crate::error::NiceError;
@@ -11,6 +13,8 @@ pub enum Evaluation {
     Done,
     /// The task directory has been cleaned.
     Clean,
+    /// Only the sanity checks have been run.
+    SanityChecks,
 }
 
 /// Run the local evaluation of some actions (either building a task or cleaning its directory).
@@ -48,6 +52,11 @@ where
         return Ok(Evaluation::Clean);
     }
 
+    if opt.sanity_only {
+        run_sanity_checks(&task, &eval_config)?;
+        return Ok(Evaluation::SanityChecks);
+    }
+
     // setup the configuration and the evaluation metadata
     let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
         // build the DAG for the task
@@ -56,13 +65,53 @@ where
     })?;
 
     // start the execution
-    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let mut executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    if opt.ui.anonymize_solutions {
+        executor.add_ui_transform(SolutionAnonymizer::new());
+    }
+    if let Some(path) = &opt.ui.junit {
+        executor.add_junit_report(path, opt.ui.junit_criterion)?;
+    }
+    if let Some(path) = &opt.ui.result_json {
+        executor.add_summary_report(path)?;
+    }
+    if let Some(path) = &opt.ui.diagnostics_json {
+        executor.add_diagnostics_report(path);
+    }
     let executor = executor.start_ui(&opt.ui.ui, on_message)?;
     executor.execute()?;
 
     Ok(Evaluation::Done)
 }
 
+/// Run the sanity checks that do not require the evaluation of the task, printing the diagnostics
+/// they produce. Fails if some of them is an error.
+fn run_sanity_checks(task: &TaskFormat, eval_config: &EvaluationConfig) -> Result<(), Error> {
+    let (mut eval, receiver) = EvaluationData::new(task.path());
+    let skipped = task
+        .static_sanity_checks(&mut eval, eval_config)
+        .context("Failed to run the sanity checks")?;
+    let mut errors = 0;
+    for message in receiver.try_iter() {
+        if let UIMessage::Diagnostic { diagnostic } = message {
+            if diagnostic.level() == DiagnosticLevel::Error {
+                errors += 1;
+            }
+            println!("{diagnostic}");
+        }
+    }
+    if !skipped.is_empty() {
+        println!(
+            "Skipped the sanity checks that require the evaluation of the task: {}",
+            skipped.join(", ")
+        );
+    }
+    if errors > 0 {
+        bail!("The sanity checks found {errors} errors");
+    }
+    Ok(())
+}
+
 /// Entry point of the local execution.
 pub fn main_local(opt: Opt) {
     run_evaluation(opt, |ui, mex| ui.on_message(mex)).nice_unwrap();
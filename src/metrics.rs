@@ -0,0 +1,232 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Error};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use task_maker_dag::ExecutionDAG;
+use url::Url;
+
+/// How long to wait for the pushgateway before giving up.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The statistics of the executions of an evaluation.
+#[derive(Debug, Clone, Default)]
+pub struct EvaluationMetrics {
+    /// The number of executions that have been run.
+    pub executions: u64,
+    /// The number of executions whose result came from the cache.
+    pub cached_executions: u64,
+    /// The total CPU time (user + system) of the executions that have been run, in seconds.
+    pub cpu_time: f64,
+}
+
+/// Collect the metrics of an evaluation and push them to a Prometheus pushgateway at the end.
+#[derive(Debug)]
+pub struct MetricsPusher {
+    /// The base URL of the pushgateway.
+    gateway: String,
+    /// The name of the task, used as a label of the metrics.
+    task: String,
+    /// The metrics collected during the evaluation.
+    metrics: Arc<Mutex<EvaluationMetrics>>,
+    /// When the evaluation started.
+    start: Instant,
+}
+
+impl MetricsPusher {
+    /// Start collecting the metrics of the executions of the DAG, to be pushed to `gateway`.
+    pub fn new(gateway: &str, task: &str, dag: &mut ExecutionDAG) -> MetricsPusher {
+        MetricsPusher {
+            gateway: gateway.to_string(),
+            task: task.to_string(),
+            metrics: bind_metrics(dag),
+            start: Instant::now(),
+        }
+    }
+
+    /// Push the metrics to the pushgateway. Failing to push is not fatal for the evaluation, a
+    /// warning is logged instead.
+    pub fn push(self) {
+        let body = render_metrics(
+            &self.task,
+            &self.metrics.lock().unwrap(),
+            self.start.elapsed(),
+        );
+        if let Err(e) = push_to_gateway(&self.gateway, &self.task, &body) {
+            warn!("Failed to push the metrics to {}: {:?}", self.gateway, e);
+        }
+    }
+}
+
+/// Register the callbacks that collect the metrics of all the executions of the DAG.
+pub fn bind_metrics(dag: &mut ExecutionDAG) -> Arc<Mutex<EvaluationMetrics>> {
+    let metrics = Arc::new(Mutex::new(EvaluationMetrics::default()));
+    let groups: Vec<_> = dag.data.execution_groups.keys().cloned().collect();
+    for group in groups {
+        let metrics = metrics.clone();
+        dag.on_execution_done(&group, move |results| {
+            let mut metrics = metrics.lock().unwrap();
+            for result in results {
+                if result.was_cached {
+                    metrics.cached_executions += 1;
+                } else {
+                    metrics.executions += 1;
+                    metrics.cpu_time += result.resources.cpu_time + result.resources.sys_time;
+                }
+            }
+            Ok(())
+        });
+    }
+    metrics
+}
+
+/// Render the metrics in the Prometheus text format, labelled with the name of the task.
+fn render_metrics(task: &str, metrics: &EvaluationMetrics, duration: Duration) -> String {
+    let task = escape_label(task);
+    let total = metrics.executions + metrics.cached_executions;
+    let hit_rate = if total == 0 {
+        0.0
+    } else {
+        metrics.cached_executions as f64 / total as f64
+    };
+    let values = [
+        (
+            "task_maker_executions_total",
+            "counter",
+            "Number of executions run.",
+            metrics.executions.to_string(),
+        ),
+        (
+            "task_maker_cached_executions_total",
+            "counter",
+            "Number of executions fetched from the cache.",
+            metrics.cached_executions.to_string(),
+        ),
+        (
+            "task_maker_cache_hit_rate",
+            "gauge",
+            "Fraction of the executions fetched from the cache.",
+            hit_rate.to_string(),
+        ),
+        (
+            "task_maker_cpu_seconds_total",
+            "counter",
+            "CPU time used by the executions run, in seconds.",
+            metrics.cpu_time.to_string(),
+        ),
+        (
+            "task_maker_evaluation_duration_seconds",
+            "gauge",
+            "Duration of the evaluation of the task, in seconds.",
+            duration.as_secs_f64().to_string(),
+        ),
+    ];
+    let mut body = String::new();
+    for (name, kind, help, value) in values {
+        body += &format!("# HELP {name} {help}\n# TYPE {name} {kind}\n");
+        body += &format!("{name}{{task=\"{task}\"}} {value}\n");
+    }
+    body
+}
+
+/// Escape a label value of the Prometheus text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Send the metrics to the pushgateway, replacing the ones previously pushed for the same task.
+fn push_to_gateway(gateway: &str, task: &str, body: &str) -> Result<(), Error> {
+    let mut url = Url::parse(gateway).context("Invalid pushgateway URL")?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("Invalid pushgateway URL"))?
+        .pop_if_empty()
+        .extend(["metrics", "job", "task-maker", "task", task]);
+    let client = Client::builder()
+        .timeout(PUSH_TIMEOUT)
+        .build()
+        .context("Failed to build the HTTP client")?;
+    let response = client
+        .put(url)
+        .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+        .body(body.to_string())
+        .send()
+        .context("Failed to send the metrics")?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().unwrap_or_default();
+        bail!("The pushgateway replied with {status}: {}", text.trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    /// Start a server that answers a single request with `status`, returning its URL and the
+    /// request line and body it received.
+    fn serve_once(status: &'static str) -> (String, JoinHandle<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            (
+                request_line.trim().to_string(),
+                String::from_utf8(body).unwrap(),
+            )
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_push_to_gateway() {
+        let (url, server) = serve_once("200 OK");
+        push_to_gateway(&url, "the task", "metric 1\n").unwrap();
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!(
+            request_line,
+            "PUT /metrics/job/task-maker/task/the%20task HTTP/1.1"
+        );
+        assert_eq!(body, "metric 1\n");
+    }
+
+    #[test]
+    fn test_push_to_gateway_error_status() {
+        let (url, server) = serve_once("500 Internal Server Error");
+        let err = push_to_gateway(&url, "task", "metric 1\n").unwrap_err();
+        assert!(err.to_string().contains("500"), "{err:?}");
+        server.join().unwrap();
+    }
+}
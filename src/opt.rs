@@ -1,14 +1,19 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use clap::{ArgAction, Parser};
 use itertools::Itertools;
+use task_maker_cache::{Cache, DirectoryRemoteCache};
 use task_maker_dag::DagPriority;
 use task_maker_format::terry::Seed;
 use task_maker_format::{
-    find_task, get_sanity_check_list, EvaluationConfig, TaskFormat, VALID_TAGS,
+    find_task_with_retries, get_sanity_check_list, EvaluationConfig, TaskFormat, VALID_TAGS,
 };
+use task_maker_lang::LanguageManager;
+use task_maker_store::{FileStore, IntegrityMode};
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -41,6 +46,10 @@ pub struct Opt {
     #[clap(flatten, next_help_heading = Some("BOOKLET"))]
     pub booklet: BookletOpt,
 
+    /// Run only the sanity checks that do not require the evaluation of the task, then exit
+    #[clap(long = "sanity-only")]
+    pub sanity_only: bool,
+
     /// List of sanity checks to skip (--help for the list).
     #[clap(short = 'W', long = "skip-checks", long_help = skip_sanity_checks_long_help())]
     pub skip_sanity_checks: Vec<String>,
@@ -60,6 +69,20 @@ pub struct LoggerOpt {
     /// Verbose mode (-v, -vv, -vvv, etc.). Note that it does not play well with curses ui.
     #[clap(short, long, action = ArgAction::Count)]
     pub verbose: u8,
+
+    /// When to use the colors in the output: auto, always, never.
+    #[clap(long = "color", default_value = "auto")]
+    pub color: task_maker_format::ui::ColorMode,
+
+    /// Never use the colors in the output, same as `--color never`. Useful when the output is
+    /// redirected to a file and the detection of the terminal is wrong.
+    #[clap(long = "no-color", conflicts_with = "color")]
+    pub no_color: bool,
+
+    /// The encoding to try for the output of the compilers and of the checkers that is not valid
+    /// UTF-8: utf8, latin1.
+    #[clap(long = "output-encoding", default_value = "utf8")]
+    pub output_encoding: task_maker_format::ui::OutputEncoding,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -71,15 +94,48 @@ pub struct FindTaskOpt {
     /// Look at most for this number of parents for searching the task
     #[clap(long = "max-depth", default_value = "3")]
     pub max_depth: u32,
+
+    /// Retry parsing the task this number of times if it fails because of a transient I/O error
+    /// (e.g. on a networked filesystem)
+    #[clap(long = "parse-retries", default_value = "0")]
+    pub parse_retries: u32,
+
+    /// Milliseconds to wait before retrying to parse the task
+    #[clap(long = "parse-retry-delay", default_value = "500")]
+    pub parse_retry_delay: u64,
 }
 
 #[derive(Parser, Debug, Clone)]
 pub struct UIOpt {
-    /// Which UI to use, available UIs are: print, raw, curses, json.
+    /// Which UI to use, available UIs are: print, raw, curses, json, tap.
     ///
     /// Note that the JSON api is not stable yet.
     #[clap(long = "ui", default_value = "curses")]
     pub ui: task_maker_format::ui::UIType,
+
+    /// Replace the paths of the solutions with anonymized names in the UI
+    #[clap(long = "anonymize-solutions")]
+    pub anonymize_solutions: bool,
+
+    /// Write the results of the solutions to this file as a JUnit XML report
+    #[clap(long = "junit")]
+    pub junit: Option<PathBuf>,
+
+    /// When a solution passes in the JUnit report: "full-score" if it gets the maximum score,
+    /// "checks" if it satisfies its @check annotations
+    #[clap(long = "junit-criterion", default_value = "full-score")]
+    pub junit_criterion: task_maker_format::ioi::JUnitCriterion,
+
+    /// Write the summary of the results of the evaluation to this file as a JSON document, with
+    /// the score, the subtasks and the testcases of each solution and the outcome of the
+    /// compilations
+    #[clap(long = "result-json")]
+    pub result_json: Option<PathBuf>,
+
+    /// Write the diagnostics (warnings and errors) emitted during the evaluation to this file as a
+    /// JSON document, with the file, line, column and byte offsets of the code they refer to
+    #[clap(long = "diagnostics-json")]
+    pub diagnostics_json: Option<PathBuf>,
 }
 
 #[derive(Parser, Default, Debug, Clone)]
@@ -97,6 +153,14 @@ pub struct ExecutionOpt {
     #[allow(clippy::option_option)]
     pub no_cache: Option<Option<String>>,
 
+    /// Explain, with a diagnostic, why each execution is not found in the cache
+    #[clap(long = "explain-cache-miss")]
+    pub explain_cache_miss: bool,
+
+    /// Show in the final results which worker executed each solution on each testcase
+    #[clap(long = "show-workers")]
+    pub show_workers: bool,
+
     /// Give to the solution some extra time before being killed
     #[clap(long = "extra-time")]
     pub extra_time: Option<f64>,
@@ -117,6 +181,59 @@ pub struct ExecutionOpt {
     #[clap(long = "copy-dag")]
     pub copy_dag: bool,
 
+    /// Write the captured stdout/stderr of each execution inside this directory
+    #[clap(long = "execution-logs")]
+    pub execution_logs: Option<PathBuf>,
+
+    /// Push the metrics of the evaluation to this Prometheus pushgateway (e.g.
+    /// http://localhost:9091)
+    #[clap(long = "push-metrics")]
+    pub push_metrics: Option<String>,
+
+    /// Shuffle the order in which the executions are scheduled, to find the checkers and managers
+    /// whose outcome depends on the order of the testcases. A seed can be provided to reproduce a
+    /// previous order.
+    #[clap(long = "randomize-order", require_equals = true)]
+    #[allow(clippy::option_option)]
+    pub randomize_order: Option<Option<u64>>,
+
+    /// Abort if the evaluation requires more than this number of executions, to avoid exhausting
+    /// the memory with a huge DAG. Use 0 for no limit
+    #[clap(long = "max-executions", default_value = "200000")]
+    pub max_executions: usize,
+
+    /// Send the DAG to the executor in chunks of this number of executions
+    ///
+    /// The next chunk is sent only when the executor has fewer executions to do, reducing the
+    /// memory the executor needs for a huge DAG. This does not reduce the memory of task-maker
+    /// itself: the whole DAG is still built, and kept until it's sent, before the evaluation
+    /// starts.
+    #[clap(long = "stream-dag", value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub stream_dag: Option<u64>,
+
+    /// Take the input files of some testcases from the store instead of generating them
+    ///
+    /// The manifest contains a line for each pinned testcase, with the testcase id and the hash of
+    /// its input file, separated by a space. The evaluation fails if a file is not in the store.
+    /// The snapshots written by `task-maker-tools freeze` also pin the output files and skip the
+    /// validation.
+    #[clap(long = "pinned-inputs")]
+    pub pinned_inputs: Option<PathBuf>,
+
+    /// Run the solutions of a communication task with this number of processes, instead of the
+    /// one in task.yaml
+    ///
+    /// The manager must support the requested number of processes.
+    #[clap(long = "num-processes", value_parser = clap::value_parser!(u8).range(1..))]
+    pub num_processes: Option<u8>,
+
+    /// Stop evaluating a solution after this number of testcases without full score
+    ///
+    /// The remaining testcases of the solution are skipped and scored zero, so its score is a
+    /// lower bound of the real one.
+    #[clap(long = "stop-after", value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub stop_after: Option<u64>,
+
     /// The number of CPU cores to use.
     #[clap(long = "num-cores")]
     pub num_cores: Option<usize>,
@@ -125,6 +242,12 @@ pub struct ExecutionOpt {
     #[clap(long = "evaluate-on")]
     pub evaluate_on: Option<String>,
 
+    /// Run the sandboxes of the local executions with this command instead of task-maker-tools
+    /// itself (e.g. to run them inside a container). The command is called with
+    /// `internal-sandbox <config> <output>` appended. Cannot be used with --evaluate-on
+    #[clap(long = "sandbox-runner")]
+    pub sandbox_runner: Option<String>,
+
     /// The name to use for the client in remote executions
     #[clap(long)]
     pub name: Option<String>,
@@ -138,9 +261,16 @@ pub struct ExecutionOpt {
 #[derive(Parser, Debug, Clone)]
 pub struct StorageOpt {
     /// Where to store the storage files, including the cache
-    #[clap(long = "store-dir")]
+    ///
+    /// The directory is kept between the runs, so that the cache can be reused. If not specified,
+    /// the cache directory of the user is used.
+    #[clap(long = "store-dir", env = "TM_STORE_DIR")]
     pub store_dir: Option<PathBuf>,
 
+    /// Fail instead of waiting if the store is in use by another instance of task-maker
+    #[clap(long = "no-wait-store")]
+    pub no_wait_store: bool,
+
     /// Maximum size of the storage directory, in MiB
     #[clap(long = "max-cache", default_value = "3072")]
     pub max_cache: u64,
@@ -148,6 +278,36 @@ pub struct StorageOpt {
     /// When the storage is flushed, this is the new maximum size, in MiB.
     #[clap(long = "min-cache", default_value = "2048")]
     pub min_cache: u64,
+
+    /// Maximum number of executions kept in the cache, the least recently used are evicted.
+    #[clap(long = "max-cache-entries")]
+    pub max_cache_entries: Option<usize>,
+
+    /// How to check that the files in the store are not corrupted before using them: off, fast
+    /// (size and checksum) or full (hash of the content).
+    #[clap(long = "integrity-checks", default_value = "off")]
+    pub integrity_mode: IntegrityMode,
+
+    /// Compress the new files of the store with zstd at this level (e.g. 3), trading some CPU time
+    /// for disk space.
+    #[clap(long = "store-compression")]
+    pub store_compression: Option<i32>,
+
+    /// Block size of the file system of the storage directory, in bytes. The size of each file is
+    /// rounded up to a multiple of it when computing the size of the storage.
+    #[clap(long = "store-block-size", default_value = "4096")]
+    pub store_block_size: u64,
+
+    /// Directory of a cache shared with other machines (e.g. on a networked filesystem)
+    ///
+    /// The executions not in the local cache are searched there, and the new ones are published
+    /// there.
+    #[clap(long = "remote-cache", env = "TM_REMOTE_CACHE")]
+    pub remote_cache: Option<PathBuf>,
+
+    /// Milliseconds after which a lookup in the remote cache is considered a miss
+    #[clap(long = "remote-cache-timeout", default_value = "5000")]
+    pub remote_cache_timeout: u64,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -163,6 +323,36 @@ pub struct FilterOpt {
     /// The solution can reside anywhere in the filesystem.
     #[clap(long, short)]
     pub solution: Vec<PathBuf>,
+
+    /// Evaluate only the solutions in these languages, separated by commas
+    ///
+    /// The names are the ones of the languages known to task-maker (e.g. C++,Python3). The
+    /// solutions in the other languages are skipped with a warning.
+    #[clap(long, value_delimiter = ',', value_parser = parse_language_name)]
+    pub languages: Vec<String>,
+
+    /// Compile the solutions whose names start with this with the address and undefined behaviour
+    /// sanitizers
+    ///
+    /// Only C and C++ are supported. The time limits of those solutions are relaxed and their
+    /// memory limit is removed, the reports of the sanitizers are shown as errors.
+    #[clap(long)]
+    pub sanitize: Vec<String>,
+}
+
+/// Check that the language name is known to task-maker.
+fn parse_language_name(name: &str) -> Result<String, String> {
+    if LanguageManager::from_name(name).is_some() {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "unknown language, the known ones are: {}",
+            LanguageManager::languages()
+                .iter()
+                .map(|lang| lang.name())
+                .join(", ")
+        ))
+    }
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -181,6 +371,10 @@ pub struct BookletOpt {
     /// Do not build the statement files and the booklets
     #[clap(long = "no-statement")]
     pub no_statement: bool,
+
+    /// Validate the statement sources without compiling the booklets
+    #[clap(long = "check-statement", conflicts_with = "no_statement")]
+    pub check_statement: bool,
 }
 
 /// Returns the long-help for the "skip sanity checks" option.
@@ -225,10 +419,18 @@ impl Opt {
             solution_filter: self.filter.filter.clone(),
             booklet_solutions: self.booklet.booklet_solutions,
             no_statement: self.booklet.no_statement,
+            check_statement: self.booklet.check_statement,
             solution_paths: self.filter.solution.clone(),
+            allowed_languages: self.filter.languages.clone(),
             disabled_sanity_checks: self.skip_sanity_checks.clone(),
             seed: self.terry.seed,
             dry_run: self.execution.dry_run,
+            max_executions: Some(self.execution.max_executions).filter(|&limit| limit > 0),
+            pinned_inputs: self.execution.pinned_inputs.clone(),
+            sanitized_solutions: self.filter.sanitize.clone(),
+            num_processes: self.execution.num_processes,
+            stop_after: self.execution.stop_after,
+            opt_level: None,
         }
     }
 
@@ -271,11 +473,72 @@ impl StorageOpt {
             }
         }
     }
+
+    /// Open the file store inside the store directory. Only one instance of task-maker at a time can
+    /// use it: if it's in use, either wait for it to be released or fail, as requested.
+    pub fn file_store(&self) -> Result<FileStore, Error> {
+        let path = self.store_dir().join("store");
+        let max_size = self.max_cache * 1024 * 1024;
+        let min_size = self.min_cache * 1024 * 1024;
+        if let Some(store) = FileStore::try_new(
+            &path,
+            max_size,
+            min_size,
+            self.integrity_mode,
+            self.store_compression,
+            self.store_block_size,
+        )? {
+            return Ok(store);
+        }
+        if self.no_wait_store {
+            bail!(
+                "The store at {} is in use by another instance of task-maker",
+                path.display()
+            );
+        }
+        eprintln!(
+            "The store at {} is in use by another instance of task-maker, waiting for it...",
+            path.display()
+        );
+        FileStore::new(
+            path,
+            max_size,
+            min_size,
+            self.integrity_mode,
+            self.store_compression,
+            self.store_block_size,
+        )
+    }
+
+    /// Open the cache inside the store directory, limiting its size and adding the remote cache if
+    /// requested.
+    pub fn cache(&self) -> Result<Cache, Error> {
+        let cache_path = self.store_dir().join("cache");
+        let mut cache = match self.max_cache_entries {
+            Some(max_entries) => Cache::with_max_entries(cache_path, max_entries)?,
+            None => Cache::new(cache_path)?,
+        };
+        if let Some(remote_cache) = &self.remote_cache {
+            let remote = DirectoryRemoteCache::new(remote_cache)
+                .with_context(|| format!("Cannot open the remote cache at {remote_cache:?}"))?;
+            cache.set_remote(
+                Arc::new(remote),
+                Duration::from_millis(self.remote_cache_timeout),
+            );
+        }
+        Ok(cache)
+    }
 }
 
 impl LoggerOpt {
     /// Enable the logs according to the specified configuration.
     pub fn enable_log(&self) {
+        task_maker_format::ui::set_color_mode(if self.no_color {
+            task_maker_format::ui::ColorMode::Never
+        } else {
+            self.color
+        });
+        task_maker_format::ui::set_output_encoding(self.output_encoding);
         if self.verbose > 0 || std::env::var("RUST_LOG").is_err() {
             if self.verbose > 0 {
                 std::env::set_var("RUST_BACKTRACE", "1");
@@ -303,7 +566,13 @@ impl LoggerOpt {
 impl FindTaskOpt {
     /// Use the specified options to find a task.
     pub fn find_task(&self, eval_config: &EvaluationConfig) -> Result<TaskFormat, Error> {
-        find_task(self.task_dir.clone(), self.max_depth, eval_config)
-            .context("Invalid task directory")
+        find_task_with_retries(
+            self.task_dir.clone(),
+            self.max_depth,
+            eval_config,
+            self.parse_retries,
+            Duration::from_millis(self.parse_retry_delay),
+        )
+        .context("Invalid task directory")
     }
 }
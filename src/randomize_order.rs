@@ -0,0 +1,90 @@
+use task_maker_dag::{ExecutionDAG, ExecutionGroup, Priority};
+
+/// Shuffle the order in which the ready executions of the DAG are scheduled, using the specified
+/// seed.
+///
+/// This replaces the priority of every group with a random one. The dependencies between the
+/// executions are still honored by the scheduler, since the priority is followed only between
+/// ready executions. The groups are sorted by their content before being shuffled, so the same seed
+/// produces the same order even if the DAG is built again.
+pub fn randomize_order(dag: &mut ExecutionDAG, seed: u64) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut groups: Vec<_> = dag.data.execution_groups.values_mut().collect();
+    // the uuid is random, it's used only as the last resort for groups with the same content
+    groups.sort_by_cached_key(|group| (sort_key(group), group.uuid));
+    for group in groups {
+        group.priority = rng.i64(..);
+    }
+}
+
+/// The key for sorting the groups deterministically: it doesn't depend on the uuids of the groups
+/// and of the files, which change each time the DAG is built.
+fn sort_key(group: &ExecutionGroup) -> (String, Priority, Vec<(String, String, Vec<String>)>) {
+    let executions = group
+        .executions
+        .iter()
+        .map(|exec| {
+            (
+                exec.description.clone(),
+                format!("{:?}", exec.command),
+                exec.args.clone(),
+            )
+        })
+        .collect();
+    (group.description.clone(), group.priority, executions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use task_maker_dag::{Execution, ExecutionCommand};
+
+    use super::*;
+
+    /// Build a DAG with some groups with the same description.
+    fn make_dag() -> ExecutionDAG {
+        let mut dag = ExecutionDAG::new();
+        for (description, priority, arg) in
+            [("a", 1, "x"), ("a", 1, "y"), ("a", 2, "x"), ("b", 0, "x")]
+        {
+            let mut exec = Execution::new(description, ExecutionCommand::system("true"));
+            exec.args(vec![arg]);
+            let mut group = exec.into_group();
+            group.priority = priority;
+            dag.add_execution_group(group);
+        }
+        dag
+    }
+
+    /// The new priority of each group, identified by its original content.
+    fn priorities(dag: &mut ExecutionDAG, seed: u64) -> HashMap<(String, i64, String), i64> {
+        let original: HashMap<_, _> = dag
+            .data
+            .execution_groups
+            .iter()
+            .map(|(uuid, group)| {
+                let key = (
+                    group.description.clone(),
+                    group.priority,
+                    group.executions[0].args[0].clone(),
+                );
+                (*uuid, key)
+            })
+            .collect();
+        randomize_order(dag, seed);
+        dag.data
+            .execution_groups
+            .iter()
+            .map(|(uuid, group)| (original[uuid].clone(), group.priority))
+            .collect()
+    }
+
+    #[test]
+    fn test_randomize_order_same_seed() {
+        let first = priorities(&mut make_dag(), 42);
+        let second = priorities(&mut make_dag(), 42);
+        assert_eq!(first.len(), 4);
+        assert_eq!(first, second);
+    }
+}
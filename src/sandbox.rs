@@ -7,16 +7,23 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use anyhow::{bail, Context, Error};
-use tabox::configuration::SandboxConfiguration;
 use tabox::result::SandboxExecutionResult;
 use tabox::{Sandbox, SandboxImplementation};
 use task_maker_exec::find_tools::find_tools_path;
-use task_maker_exec::{RawSandboxResult, SandboxRunner};
+use task_maker_exec::{RawSandboxConfig, RawSandboxResult, SandboxRunner};
 use tempfile::NamedTempFile;
 
 fn run_sandbox(config: &str) -> Result<SandboxExecutionResult, Error> {
-    let config = serde_json::from_str(config).context("Cannot parse configuration")?;
-    let sandbox = SandboxImplementation::run(config).context("Failed to create sandbox")?;
+    let config: RawSandboxConfig =
+        serde_json::from_str(config).context("Cannot parse configuration")?;
+    // The limits set here are inherited by the sandboxed process.
+    if let Some(nofile) = config.nofile {
+        let nofile = nofile as u64;
+        rlimit::Resource::NOFILE
+            .set(nofile, nofile)
+            .with_context(|| format!("Failed to limit the file descriptors to {nofile}"))?;
+    }
+    let sandbox = SandboxImplementation::run(config.tabox).context("Failed to create sandbox")?;
     let res = sandbox.wait().context("Failed to wait sandbox")?;
     Ok(res)
 }
@@ -40,37 +47,61 @@ pub fn main_sandbox() {
     serde_json::to_writer(BufWriter::new(f), &result).expect("Failed to print result");
 }
 
+/// Builds the sandbox runner of the local workers. It is called only when the executions are run
+/// locally: the remote workers use the runner they are started with.
+pub type SandboxRunnerFactory = Box<dyn FnOnce() -> Result<Box<dyn SandboxRunner>, Error>>;
+
 /// Run the sandbox integrated in the task-maker-tools binary.
 #[derive(Clone, Debug)]
 pub struct ToolsSandboxRunner {
     /// Path to the tools executable.
     tools_path: PathBuf,
+    /// Arguments to pass to the executable before the ones of the sandbox.
+    args: Vec<String>,
+}
+
+impl ToolsSandboxRunner {
+    /// Run the sandbox using a custom command instead of the task-maker-tools binary. The command
+    /// is split like a shell would do, and it's called with `internal-sandbox <config> <output>`
+    /// appended. It should end up running `task-maker-tools` with those arguments, for example
+    /// inside a container, and the output file should be shared with it.
+    pub fn from_command(command: &str) -> Result<Self, Error> {
+        let mut args = shell_words::split(command).context("Invalid sandbox runner command")?;
+        if args.is_empty() {
+            bail!("The sandbox runner command is empty");
+        }
+        let tools_path = args.remove(0).into();
+        Ok(ToolsSandboxRunner { tools_path, args })
+    }
 }
 
 impl Default for ToolsSandboxRunner {
     fn default() -> Self {
         ToolsSandboxRunner {
             tools_path: find_tools_path(),
+            args: vec![],
         }
     }
 }
 
 impl SandboxRunner for ToolsSandboxRunner {
-    fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
-        tools_sandbox_internal(&self.tools_path, config, pid).into()
+    fn run(&self, config: RawSandboxConfig, pid: Arc<AtomicU32>) -> RawSandboxResult {
+        tools_sandbox_internal(&self.tools_path, &self.args, config, pid).into()
     }
 }
 
 /// Actually run the sandbox, but with a return type that supports the `?` operator.
 fn tools_sandbox_internal(
     tools_path: &Path,
-    config: SandboxConfiguration,
+    args: &[String],
+    config: RawSandboxConfig,
     pid: Arc<AtomicU32>,
 ) -> Result<RawSandboxResult, Error> {
     let config = serde_json::to_string(&config).context("Failed to serialize config")?;
     // TODO(veluca): it would be nice to write the result in the sandbox.
     let outfile = NamedTempFile::new().context("Failed creating output tempfile")?;
     let mut cmd = Command::new(tools_path)
+        .args(args)
         .arg("internal-sandbox")
         .arg(config)
         .arg(outfile.path().as_os_str())
@@ -0,0 +1,116 @@
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use task_maker_exec::ductile::{ChannelReceiver, ChannelSender};
+use task_maker_exec::executors::{RemoteEntityMessage, RemoteEntityMessageResponse};
+use task_maker_exec::proto::{ExecutorClientMessage, ExecutorServerMessage};
+use task_maker_exec::ClientUuid;
+
+use crate::remote::connect_to_remote_server;
+
+#[derive(Parser, Debug, Clone)]
+pub struct AbortClientOpt {
+    /// Address of the server, the same used with --evaluate-on
+    pub server_addr: String,
+
+    /// The uuid of the client whose evaluation should be aborted. If omitted, the clients with an
+    /// execution running on a worker are listed
+    pub client: Option<ClientUuid>,
+
+    /// The name to use for this client in the server
+    #[clap(long)]
+    pub name: Option<String>,
+
+    /// The operator password of the server, as set with its --operator-password. Required for
+    /// aborting a client
+    #[clap(long = "operator-password")]
+    pub operator_password: Option<String>,
+}
+
+/// Version of task-maker
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Entry point for the tool that aborts the evaluation of a client of a server.
+pub fn main_abort_client(opt: AbortClientOpt) -> Result<(), Error> {
+    if opt.client.is_some() && opt.operator_password.is_none() {
+        bail!("The operator password of the server is required for aborting a client");
+    }
+    let (sender, receiver) = connect_to_remote_server(&opt.server_addr, 27182)
+        .context("Failed to connect to the server")?;
+    let name = opt.name.clone().unwrap_or_else(|| {
+        format!(
+            "{}@{} (abort-client)",
+            whoami::username(),
+            whoami::fallible::hostname().unwrap()
+        )
+    });
+    sender
+        .send(RemoteEntityMessage::Welcome {
+            name,
+            version: VERSION.into(),
+        })
+        .context("Cannot send welcome to the server")?;
+    if let RemoteEntityMessageResponse::Rejected(err) = receiver
+        .recv()
+        .context("Failed to receive welcome response")?
+    {
+        bail!("The server rejected the client connection: {}", err);
+    }
+    let sender: ChannelSender<ExecutorClientMessage> = sender.change_type();
+    let receiver: ChannelReceiver<ExecutorServerMessage> = receiver.change_type();
+
+    match (opt.client, opt.operator_password) {
+        (Some(client), Some(password)) => {
+            sender
+                .send(ExecutorClientMessage::AbortClient {
+                    target: client,
+                    password,
+                })
+                .context("Failed to send AbortClient to the server")?;
+            loop {
+                match receiver
+                    .recv()
+                    .context("Failed to receive from the server")?
+                {
+                    ExecutorServerMessage::ClientAborted(true) => {
+                        println!("The evaluation of client {client} has been aborted");
+                        break;
+                    }
+                    ExecutorServerMessage::ClientAborted(false) => {
+                        bail!("The client {client} is not evaluating anything on the server")
+                    }
+                    ExecutorServerMessage::Error(error) => bail!("The server failed: {error}"),
+                    message => debug!("Ignoring message from the server: {message:?}"),
+                }
+            }
+        }
+        _ => {
+            sender
+                .send(ExecutorClientMessage::Status)
+                .context("Failed to send Status to the server")?;
+            let status = loop {
+                match receiver
+                    .recv()
+                    .context("Failed to receive from the server")?
+                {
+                    ExecutorServerMessage::Status(status) => break status,
+                    message => debug!("Ignoring message from the server: {message:?}"),
+                }
+            };
+            let mut jobs = status
+                .connected_workers
+                .iter()
+                .filter_map(|worker| Some((worker, worker.current_job.as_ref()?)))
+                .peekable();
+            if jobs.peek().is_none() {
+                println!("No client has an execution running on a worker");
+            }
+            for (worker, job) in jobs {
+                println!(
+                    "{} ({}): {} on worker {}",
+                    job.client.name, job.client.uuid, job.job, worker.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -11,7 +11,7 @@ use itertools::Itertools;
 use task_maker_format::ioi::UIState;
 use task_maker_format::ui::{StdoutPrinter, UIStateT, BLUE, BOLD, YELLOW};
 use task_maker_format::{
-    cwrite, cwriteln, EvaluationConfig, SolutionCheck, SolutionCheckResult, TaskFormat,
+    cwrite, cwriteln, EvaluationConfig, SolutionCheckResult, SubtaskScoreExpectation, TaskFormat,
     TestcaseEvaluationResult,
 };
 use task_maker_lang::LanguageManager;
@@ -44,7 +44,8 @@ pub struct AddSolutionChecksOpt {
 
     /// Overwrite existing @check rules.
     ///
-    /// Warning: this will remove the existing lines containing the @check rules.
+    /// Warning: this will remove the existing lines containing the @check rules, including the
+    /// checks on the subtask scores (@check-pass, @check-fail and @check-score).
     #[clap(long, short)]
     pub overwrite: bool,
 }
@@ -58,10 +59,18 @@ pub fn main_add_solution_checks(
         solution_filter: opt.filter.filter,
         booklet_solutions: false,
         no_statement: true,
+        check_statement: false,
         solution_paths: opt.filter.solution,
+        allowed_languages: opt.filter.languages,
         disabled_sanity_checks: Default::default(),
         seed: Default::default(),
         dry_run: true,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
     let task = opt
         .find_task
@@ -112,21 +121,27 @@ pub fn main_add_solution_checks(
         if solution.path.is_symlink() {
             continue;
         }
-        if !opt.overwrite && !solution.checks.is_empty() {
+        if !opt.overwrite && (!solution.checks.is_empty() || !solution.score_checks.is_empty()) {
             skipped.push(&solution.name);
             continue;
         }
-        let checks_to_remove = if opt.overwrite {
-            &solution.checks[..]
+        let lines_to_remove = if opt.overwrite {
+            solution
+                .checks
+                .iter()
+                .map(|c| &c.code_span)
+                .chain(solution.score_checks.iter().map(|c| &c.code_span))
+                .map(|span| span.line_number())
+                .collect()
         } else {
-            &[]
+            HashSet::new()
         };
         let has_changes = process_solution(
             &ui_state,
             solution_name,
             &mut printer,
             opt.in_place,
-            checks_to_remove,
+            &lines_to_remove,
         );
         if has_changes && !opt.in_place {
             changes_to_write = true;
@@ -149,13 +164,14 @@ pub fn main_add_solution_checks(
     Ok(())
 }
 
-/// Generate (and add with in_place) the @check comments to this solution.
+/// Generate (and add with in_place) the @check comments to this solution, both the ones on the
+/// outcomes of the testcases and the ones on the subtask scores.
 fn process_solution(
     state: &UIState,
     solution_name: &Path,
     printer: &mut StdoutPrinter,
     in_place: bool,
-    checks_to_remove: &[SolutionCheck],
+    lines_to_remove: &HashSet<usize>,
 ) -> bool {
     let solution = &state.solutions[solution_name];
     let language = LanguageManager::detect_language(solution_name);
@@ -168,8 +184,17 @@ fn process_solution(
     };
 
     let mut checks: HashMap<_, Vec<_>> = HashMap::new();
+    let mut subtask_scores = vec![];
     for st_num in solution_results.subtasks.keys().sorted() {
         let subtask = &state.task.subtasks[st_num];
+        let Some(score) = solution_results.subtasks[st_num].score else {
+            println!(
+                "Solution '{}' not scored on all the subtasks, skipping.",
+                solution.name
+            );
+            return false;
+        };
+        subtask_scores.push((subtask.name.as_deref().unwrap(), score, subtask.max_score));
 
         let testcase_results: Option<Vec<TestcaseEvaluationResult>> = state.task.subtasks[st_num]
             .testcases
@@ -191,21 +216,22 @@ fn process_solution(
             .push(subtask.name.as_deref().unwrap());
     }
 
-    let comments = checks
+    let prefix = language
+        .as_ref()
+        .and_then(|lang| lang.inline_comment_prefix())
+        .unwrap_or_default();
+    let mut comments = checks
         .into_iter()
         .sorted_by_key(|(result, _)| *result)
         .map(|(result, subtasks)| {
-            let prefix = language
-                .as_ref()
-                .and_then(|lang| lang.inline_comment_prefix())
-                .unwrap_or_default();
             let subtasks = subtasks.iter().join(" ");
             format!("{} @check-{}: {}", prefix, result.as_str(), subtasks)
         })
         .collect_vec();
+    comments.extend(score_check_comments(prefix, &subtask_scores));
     let mut written = "";
     if in_place && !comments.is_empty() {
-        if let Err(e) = write_comments_to_file(&solution.path, &comments, checks_to_remove)
+        if let Err(e) = write_comments_to_file(&solution.path, &comments, lines_to_remove)
             .with_context(|| {
                 format!(
                     "Failed to write @check comments to '{}'",
@@ -257,10 +283,31 @@ fn choose_check_for_subtask(
     unreachable!("failed to find check for subtask")
 }
 
+/// Generate the @check-pass, @check-fail and @check-score comments with the scores of the subtasks,
+/// given as `(name, score, max_score)`. The subtasks with the same expectation share the comment.
+fn score_check_comments(prefix: &str, subtask_scores: &[(&str, f64, f64)]) -> Vec<String> {
+    let mut expectations: Vec<(SubtaskScoreExpectation, Vec<&str>)> = vec![];
+    for &(name, score, max_score) in subtask_scores {
+        let expectation = SubtaskScoreExpectation::from_score(score, max_score);
+        match expectations.iter_mut().find(|(exp, _)| *exp == expectation) {
+            Some((_, subtasks)) => subtasks.push(name),
+            None => expectations.push((expectation, vec![name])),
+        }
+    }
+    expectations
+        .into_iter()
+        .map(|(expectation, subtasks)| {
+            format!("{} {}", prefix, expectation.annotation(&subtasks.join(" ")))
+        })
+        .collect()
+}
+
+/// Write the comments at the beginning of the solution, removing the lines (1-based) with the
+/// checks to replace.
 fn write_comments_to_file(
     path: &Path,
     comments: &[String],
-    checks_to_remove: &[SolutionCheck],
+    lines_to_remove: &HashSet<usize>,
 ) -> Result<(), Error> {
     let mut file =
         File::open(path).with_context(|| format!("Failed to open '{}'", path.display()))?;
@@ -271,13 +318,8 @@ fn write_comments_to_file(
 
     // Remove existing @check rules.
     let mut lines: Vec<_> = content.lines().collect();
-    let lines_to_remove: HashSet<_> = checks_to_remove
-        .iter()
-        .map(|c| c.code_span.line_number() - 1)
-        .collect();
-    let lines_to_remove = lines_to_remove.iter().sorted().rev();
-    for line in lines_to_remove {
-        lines.remove(*line);
+    for line in lines_to_remove.iter().sorted().rev() {
+        lines.remove(*line - 1);
     }
 
     // If the source file starts with the shebang, we cannot simply add the comments at the
@@ -297,3 +339,64 @@ fn write_comments_to_file(
     })?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use task_maker_format::{EvaluationData, SolutionScoreCheck};
+
+    use super::*;
+
+    #[test]
+    fn test_score_checks_round_trip() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("sol.py");
+        std::fs::write(
+            &path,
+            "#!/usr/bin/env python3\n# @check-pass: old\nprint(42)\n",
+        )
+        .unwrap();
+        let comments = score_check_comments(
+            "#",
+            &[
+                ("st1", 10.0, 10.0),
+                ("st2", 0.0, 20.0),
+                ("st3", 12.5, 30.0),
+                ("st4", 40.0, 40.0),
+            ],
+        );
+        assert_eq!(
+            comments,
+            vec![
+                "# @check-pass: st1 st4",
+                "# @check-fail: st2",
+                "# @check-score: 12.5-12.5 st3",
+            ]
+        );
+        write_comments_to_file(&path, &comments, &[2].into_iter().collect()).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("#!/usr/bin/env python3\n"));
+        assert!(content.ends_with("print(42)\n"));
+        let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+        let checks = SolutionScoreCheck::extract_list(&path, &mut eval).unwrap();
+        let checks = checks
+            .iter()
+            .map(|c| (c.subtask_name_pattern.as_str(), c.expectation))
+            .collect_vec();
+        assert_eq!(
+            checks,
+            vec![
+                ("st1", SubtaskScoreExpectation::Pass),
+                ("st4", SubtaskScoreExpectation::Pass),
+                ("st2", SubtaskScoreExpectation::Fail),
+                (
+                    "st3",
+                    SubtaskScoreExpectation::Range {
+                        min: 12.5,
+                        max: 12.5
+                    }
+                ),
+            ]
+        );
+    }
+}
@@ -6,7 +6,7 @@ use task_maker_format::ioi::{make_contest_booklets, Booklet, BookletConfig, IOIT
 use task_maker_format::{find_task, EvaluationConfig};
 
 use crate::context::RuntimeContext;
-use crate::{ExecutionOpt, LoggerOpt, StorageOpt, ToolsSandboxRunner, UIOpt};
+use crate::{ExecutionOpt, LoggerOpt, StorageOpt, UIOpt};
 
 #[derive(Parser, Debug, Clone)]
 pub struct BookletOpt {
@@ -46,10 +46,18 @@ pub fn main_booklet(mut opt: BookletOpt, logger_opt: LoggerOpt) -> Result<(), Er
         solution_filter: vec![],
         booklet_solutions: opt.booklet_solutions,
         no_statement: false,
+        check_statement: false,
         solution_paths: vec![],
+        allowed_languages: vec![],
         disabled_sanity_checks: vec![],
         seed: None,
         dry_run: opt.execution.dry_run,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
 
     if opt.contest_dir.is_some() && !opt.task_dir.is_empty() {
@@ -68,13 +76,12 @@ pub fn main_booklet(mut opt: BookletOpt, logger_opt: LoggerOpt) -> Result<(), Er
     task.subtasks.clear();
 
     // setup the configuration and the evaluation metadata
-    let mut context = RuntimeContext::new(task.into(), &opt.execution, |_task, eval| {
+    let context = RuntimeContext::new(task.into(), &opt.execution, |_task, eval| {
         for booklet in booklets {
             booklet.build(eval)?;
         }
         Ok(())
     })?;
-    context.sandbox_runner(ToolsSandboxRunner::default());
 
     // start the execution
     let executor = context.connect_executor(&opt.execution, &opt.storage)?;
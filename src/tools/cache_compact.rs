@@ -0,0 +1,27 @@
+use anyhow::{Context, Error};
+use clap::Parser;
+
+use crate::StorageOpt;
+
+#[derive(Parser, Debug)]
+pub struct CacheCompactOpt {
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+}
+
+pub fn main_cache_compact(opt: CacheCompactOpt) -> Result<(), Error> {
+    // the store lock prevents other instances of task-maker from using the cache meanwhile
+    let file_store = opt.storage.file_store()?;
+    let mut cache = opt.storage.cache().context("Cannot load the cache")?;
+    let stats = cache
+        .compact(&file_store)
+        .context("Cannot compact the cache")?;
+    println!(
+        "Removed {} cache entries, {} kept. Cache file size: {:.1} MiB -> {:.1} MiB",
+        stats.removed,
+        stats.kept,
+        stats.size_before as f64 / 1024.0 / 1024.0,
+        stats.size_after as f64 / 1024.0 / 1024.0
+    );
+    Ok(())
+}
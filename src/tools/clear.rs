@@ -1,15 +1,59 @@
+use std::time::Duration;
+
 use anyhow::{Context, Error};
 use clap::Parser;
 
-use crate::FindTaskOpt;
+use crate::{FindTaskOpt, StorageOpt};
 
 #[derive(Parser, Debug)]
 pub struct ClearOpt {
     #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
     pub find_task: FindTaskOpt,
+
+    /// Instead of clearing the task directory, remove from the cache the executions not used in
+    /// the last <days> days
+    #[clap(long = "prune-cache", value_name = "days")]
+    pub prune_cache: Option<u64>,
+
+    /// Instead of clearing the task directory, list how many executions are in the cache and how
+    /// much space their files use, for each tag
+    #[clap(long = "list-cache-tags")]
+    pub list_cache_tags: bool,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
 }
 
 pub fn main_clear(opt: ClearOpt) -> Result<(), Error> {
+    if let Some(days) = opt.prune_cache {
+        // the store lock prevents other instances of task-maker from using the cache meanwhile
+        let _file_store = opt.storage.file_store()?;
+        let mut cache = opt.storage.cache().context("Cannot load the cache")?;
+        let stats = cache
+            .prune(Duration::from_secs(days * 24 * 60 * 60))
+            .context("Cannot prune the cache")?;
+        println!(
+            "Removed {} cache entries, {} kept",
+            stats.removed, stats.kept
+        );
+        return Ok(());
+    }
+    if opt.list_cache_tags {
+        let file_store = opt.storage.file_store()?;
+        let cache = opt.storage.cache().context("Cannot load the cache")?;
+        let stats = cache.tag_stats(&file_store);
+        if stats.is_empty() {
+            println!("The cache is empty");
+        }
+        for (tag, stats) in stats {
+            println!(
+                "{tag:<12} {:>8} entries {:>10.1} MiB",
+                stats.entries,
+                stats.bytes as f64 / 1024.0 / 1024.0
+            );
+        }
+        return Ok(());
+    }
     let task = opt.find_task.find_task(&Default::default())?;
     task.clean().context("Cannot clear the task directory")?;
     Ok(())
@@ -7,7 +7,7 @@ use task_maker_format::ioi::{Booklet, BookletConfig, IOITask, IOITaskInfo};
 use task_maker_format::{find_task, EvaluationConfig, TaskInfo};
 
 use crate::context::RuntimeContext;
-use crate::{ExecutionOpt, LoggerOpt, StorageOpt, ToolsSandboxRunner, UIOpt};
+use crate::{ExecutionOpt, LoggerOpt, StorageOpt, UIOpt};
 
 #[derive(Parser, Debug, Clone)]
 pub struct CopyCompetitionFilesOpt {
@@ -36,10 +36,18 @@ pub fn copy_competition_files_main(
         solution_filter: vec![],
         booklet_solutions: false,
         no_statement: false,
+        check_statement: false,
         solution_paths: vec![],
+        allowed_languages: vec![],
         disabled_sanity_checks: vec![],
         seed: None,
         dry_run: opt.execution.dry_run,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
 
     // create folder for competition files
@@ -53,14 +61,12 @@ pub fn copy_competition_files_main(
         task.subtasks.clear();
 
         // setup the configuration and the evaluation metadata
-        let mut context =
-            RuntimeContext::new(task.clone().into(), &opt.execution, |_task, eval| {
-                for booklet in booklets {
-                    booklet.build(eval)?;
-                }
-                Ok(())
-            })?;
-        context.sandbox_runner(ToolsSandboxRunner::default());
+        let context = RuntimeContext::new(task.clone().into(), &opt.execution, |_task, eval| {
+            for booklet in booklets {
+                booklet.build(eval)?;
+            }
+            Ok(())
+        })?;
 
         // start the execution
         let executor = context.connect_executor(&opt.execution, &opt.storage)?;
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::Value;
+use task_maker_format::ioi::SubtaskId;
+use task_maker_format::ui::{StdoutPrinter, BLUE, BOLD, GREEN, RED, YELLOW};
+use task_maker_format::{cwrite, cwriteln};
+
+/// Scores that differ less than this are considered equal.
+const EPSILON: f64 = 1e-6;
+
+#[derive(Parser, Debug, Clone)]
+pub struct DiffRunsOpt {
+    /// Output of the JSON UI (`--ui json`) of the baseline run.
+    pub baseline: PathBuf,
+
+    /// Output of the JSON UI (`--ui json`) of the run to compare with the baseline.
+    pub new: PathBuf,
+
+    /// Print also the subtasks whose score didn't change.
+    #[clap(long)]
+    pub all: bool,
+}
+
+/// The relevant information extracted from the output of the JSON UI of a run.
+#[derive(Debug, Default)]
+struct RunSummary {
+    /// The number of decimal digits of the scores of the task.
+    score_precision: Option<usize>,
+    /// The subtasks of the task.
+    subtasks: BTreeMap<SubtaskId, SubtaskSummary>,
+    /// The scores of each solution.
+    solutions: BTreeMap<PathBuf, SolutionSummary>,
+}
+
+/// The structure of a subtask in a run.
+#[derive(Debug, Deserialize)]
+struct SubtaskSummary {
+    /// The name of the subtask.
+    name: Option<String>,
+    /// The maximum score of the subtask.
+    max_score: f64,
+}
+
+/// The scores of a solution in a run.
+#[derive(Debug, Default)]
+struct SolutionSummary {
+    /// The score of the solution on the task, if it was computed.
+    score: Option<f64>,
+    /// The score of the solution on each subtask.
+    subtasks: BTreeMap<SubtaskId, f64>,
+}
+
+/// The content of a `UIMessage::IOISubtaskScore`.
+#[derive(Debug, Deserialize)]
+struct SubtaskScoreMessage {
+    /// The id of the subtask.
+    subtask: SubtaskId,
+    /// The path of the solution.
+    solution: PathBuf,
+    /// The score of the subtask.
+    score: f64,
+}
+
+/// The content of a `UIMessage::IOITaskScore`.
+#[derive(Debug, Deserialize)]
+struct TaskScoreMessage {
+    /// The path of the solution.
+    solution: PathBuf,
+    /// The score of the task.
+    score: f64,
+}
+
+/// Handler of the `diff-runs` tool. This tool compares the scores of two evaluations of a task,
+/// printing how the score of each solution changed.
+pub fn main_diff_runs(opt: DiffRunsOpt) -> Result<(), Error> {
+    let baseline = RunSummary::load(&opt.baseline)
+        .with_context(|| format!("Failed to load baseline run {}", opt.baseline.display()))?;
+    let new = RunSummary::load(&opt.new)
+        .with_context(|| format!("Failed to load new run {}", opt.new.display()))?;
+    let precision = new
+        .score_precision
+        .or(baseline.score_precision)
+        .unwrap_or_default();
+
+    let mut printer = StdoutPrinter::default();
+    print_subtask_changes(&mut printer, &baseline, &new);
+
+    cwriteln!(printer, BLUE, "Solutions");
+    let solutions: BTreeSet<_> = baseline
+        .solutions
+        .keys()
+        .chain(new.solutions.keys())
+        .collect();
+    for solution in solutions {
+        let (old, new) = match (
+            baseline.solutions.get(solution),
+            new.solutions.get(solution),
+        ) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(_), None) => {
+                cwrite!(printer, BOLD, "{}", solution.display());
+                cwrite!(printer, YELLOW, " only in the baseline");
+                println!();
+                continue;
+            }
+            (None, _) => {
+                cwrite!(printer, BOLD, "{}", solution.display());
+                cwrite!(printer, YELLOW, " only in the new run");
+                println!();
+                continue;
+            }
+        };
+        cwrite!(printer, BOLD, "{}", solution.display());
+        print!(" ");
+        print_delta(&mut printer, old.score, new.score, precision);
+        println!();
+
+        let subtasks: BTreeSet<_> = old.subtasks.keys().chain(new.subtasks.keys()).collect();
+        for subtask in subtasks {
+            let old = old.subtasks.get(subtask).copied();
+            let new = new.subtasks.get(subtask).copied();
+            let unchanged =
+                matches!((old, new), (Some(old), Some(new)) if (old - new).abs() < EPSILON);
+            if unchanged && !opt.all {
+                continue;
+            }
+            print!("    Subtask {subtask} ");
+            print_delta(&mut printer, old, new, precision);
+            println!();
+        }
+    }
+    Ok(())
+}
+
+impl RunSummary {
+    /// Load the summary of a run from the file with the output of the JSON UI, one message per
+    /// line. Only the messages with the task and the scores are considered, the others are ignored.
+    fn load(path: &Path) -> Result<RunSummary, Error> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut summary = RunSummary::default();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            summary
+                .add_message(line)
+                .with_context(|| format!("Invalid message at line {}", index + 1))?;
+        }
+        Ok(summary)
+    }
+
+    /// Update the summary with a message of the JSON UI.
+    fn add_message(&mut self, line: &str) -> Result<(), Error> {
+        let message: Value = serde_json::from_str(line).context("Invalid JSON")?;
+        if let Some(task) = message.get("IOITask").and_then(|m| m.get("task")) {
+            self.score_precision = task
+                .get("score_precision")
+                .and_then(Value::as_u64)
+                .map(|precision| precision as usize);
+            if let Some(subtasks) = task.get("subtasks") {
+                self.subtasks = serde_json::from_value(subtasks.clone())
+                    .context("Invalid subtasks in IOITask")?;
+            }
+        } else if let Some(score) = message.get("IOISubtaskScore") {
+            let score: SubtaskScoreMessage =
+                serde_json::from_value(score.clone()).context("Invalid IOISubtaskScore")?;
+            self.solutions
+                .entry(score.solution)
+                .or_default()
+                .subtasks
+                .insert(score.subtask, score.score);
+        } else if let Some(score) = message.get("IOITaskScore") {
+            let score: TaskScoreMessage =
+                serde_json::from_value(score.clone()).context("Invalid IOITaskScore")?;
+            self.solutions.entry(score.solution).or_default().score = Some(score.score);
+        }
+        Ok(())
+    }
+}
+
+/// Print the subtasks that have been added, removed or changed between the two runs.
+fn print_subtask_changes(printer: &mut StdoutPrinter, baseline: &RunSummary, new: &RunSummary) {
+    let subtasks: BTreeSet<_> = baseline
+        .subtasks
+        .keys()
+        .chain(new.subtasks.keys())
+        .collect();
+    let mut changes = vec![];
+    for subtask in subtasks {
+        match (baseline.subtasks.get(subtask), new.subtasks.get(subtask)) {
+            (Some(old), Some(new)) => {
+                if (old.max_score - new.max_score).abs() >= EPSILON {
+                    changes.push(format!(
+                        "Subtask {subtask}: max score changed from {} to {}",
+                        old.max_score, new.max_score
+                    ));
+                }
+                if old.name != new.name {
+                    changes.push(format!(
+                        "Subtask {subtask}: name changed from {} to {}",
+                        old.name.as_deref().unwrap_or("(none)"),
+                        new.name.as_deref().unwrap_or("(none)")
+                    ));
+                }
+            }
+            (Some(_), None) => changes.push(format!("Subtask {subtask}: only in the baseline")),
+            (None, _) => changes.push(format!("Subtask {subtask}: only in the new run")),
+        }
+    }
+    if changes.is_empty() {
+        return;
+    }
+    cwriteln!(printer, YELLOW, "The subtasks changed between the runs");
+    for change in changes {
+        println!("    {change}");
+    }
+    println!();
+}
+
+/// Print how a score changed, highlighting the regressions.
+fn print_delta(printer: &mut StdoutPrinter, old: Option<f64>, new: Option<f64>, precision: usize) {
+    match (old, new) {
+        (Some(old), Some(new)) => {
+            let delta = new - old;
+            print!("{old:.precision$} -> {new:.precision$}");
+            if delta <= -EPSILON {
+                cwrite!(printer, RED, " ({delta:.precision$})");
+            } else if delta >= EPSILON {
+                cwrite!(printer, GREEN, " (+{delta:.precision$})");
+            }
+        }
+        (Some(old), None) => {
+            print!("{old:.precision$} -> ");
+            cwrite!(printer, YELLOW, "missing");
+        }
+        (None, Some(new)) => {
+            cwrite!(printer, YELLOW, "missing");
+            print!(" -> {new:.precision$}");
+        }
+        (None, None) => cwrite!(printer, YELLOW, "missing"),
+    }
+}
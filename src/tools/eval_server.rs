@@ -127,6 +127,7 @@ struct ExecutionResult {
     stderr: Content,
     time: f64,
     memory: f64,
+    final_memory: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -261,6 +262,7 @@ async fn evaluate(
     let source_file = if let Some(lang_name) = &payload.language {
         let lang = LanguageManager::from_name(lang_name)
             .ok_or_else(|| (StatusCode::BAD_REQUEST, "Unknown language".to_string()))?;
+        let warnings = lang.source_warnings(&main_path);
         SourceFile {
             path: main_path.clone(),
             base_path: temp_dir.path().to_owned(),
@@ -270,6 +272,9 @@ async fn evaluate(
             copy_exe: false,
             write_bin_to: None,
             link_static: false,
+            sanitize: false,
+            opt_level: None,
+            warnings,
         }
     } else {
         SourceFile::new(&main_path, temp_dir.path(), None, None::<PathBuf>).ok_or_else(|| {
@@ -434,6 +439,7 @@ async fn evaluate(
             stderr: Content::from(comp_stderr_capture.lock().unwrap().clone()),
             time: r.resources.cpu_time,
             memory: r.resources.memory as f64 / 1024.0,
+            final_memory: r.resources.final_memory as f64 / 1024.0,
         });
 
     let execution = if let Some(res) = final_result {
@@ -447,6 +453,7 @@ async fn evaluate(
             stderr: Content::from(stderr_capture.lock().unwrap().clone()),
             time: res.resources.cpu_time,
             memory: res.resources.memory as f64 / 1024.0,
+            final_memory: res.resources.final_memory as f64 / 1024.0,
         })
     } else {
         None
@@ -41,10 +41,18 @@ pub fn main_export_booklet(opt: ExportBookletOpt) -> Result<(), Error> {
         solution_filter: vec![],
         booklet_solutions: opt.booklet_solutions,
         no_statement: false,
+        check_statement: false,
         solution_paths: vec![],
+        allowed_languages: vec![],
         disabled_sanity_checks: vec![],
         seed: None,
         dry_run: true,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
 
     if opt.contest_dir.is_some() && !opt.task_dir.is_empty() {
@@ -87,6 +95,15 @@ pub fn main_export_booklet(opt: ExportBookletOpt) -> Result<(), Error> {
                 let content = match file {
                     ProvidedFile::Content { content, .. } => content.to_owned(),
                     ProvidedFile::LocalFile { local_path, .. } => fs::read(local_path)?,
+                    ProvidedFile::StoredFile { key, .. } => {
+                        bail!("Cannot export the file {key} from the store")
+                    }
+                    ProvidedFile::Late { file } => {
+                        bail!(
+                            "Cannot export '{}', it's known only during the evaluation",
+                            file.description
+                        )
+                    }
                 };
 
                 zip.start_file(
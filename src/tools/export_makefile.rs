@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use task_maker_format::ioi::generate_makefile;
+use task_maker_format::{EvaluationConfig, EvaluationData, TaskFormat};
+
+use crate::FindTaskOpt;
+
+#[derive(Parser, Debug, Clone)]
+pub struct ExportMakefileOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    /// Where to write the Makefile.
+    #[clap(long, short, default_value = "Makefile")]
+    pub output: PathBuf,
+}
+
+pub fn main_export_makefile(opt: ExportMakefileOpt) -> Result<(), Error> {
+    let eval_config = EvaluationConfig {
+        solution_filter: vec![],
+        booklet_solutions: false,
+        no_statement: true,
+        check_statement: false,
+        solution_paths: vec![],
+        allowed_languages: vec![],
+        disabled_sanity_checks: Default::default(),
+        seed: Default::default(),
+        // the files written to input/ and output/ are needed to build the Makefile
+        dry_run: false,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+
+    let TaskFormat::IOI(mut task) = task else {
+        bail!("Exporting a Makefile is only supported for IOI tasks")
+    };
+
+    // the receiver must be kept alive, building the DAG sends some UI messages
+    let (mut eval, _receiver) = EvaluationData::new(task.path());
+    task.build_dag(&mut eval, &eval_config)
+        .context("Cannot build the task DAG")?;
+    let makefile =
+        generate_makefile(task.path(), &mut eval.dag).context("Cannot export the Makefile")?;
+    std::fs::write(&opt.output, makefile)
+        .with_context(|| format!("Failed to write {}", opt.output.display()))?;
+    Ok(())
+}
@@ -6,7 +6,8 @@ use clap::Parser;
 use serde::{Deserialize, Serialize};
 use task_maker_format::ioi::IOITask;
 use task_maker_format::{
-    EvaluationConfig, EvaluationData, Solution, SolutionCheckResult, TaskFormat,
+    EvaluationConfig, EvaluationData, Solution, SolutionCheckResult, SubtaskScoreExpectation,
+    TaskFormat,
 };
 
 use crate::{FilterOpt, FindTaskOpt};
@@ -24,6 +25,7 @@ pub struct ExportSolutionChecksOpt {
 struct SolutionWithChecks {
     path: PathBuf,
     checks: Vec<Option<SolutionCheckResult>>,
+    score_checks: Vec<Option<SubtaskScoreExpectation>>,
     min_score: f64,
     max_score: f64,
 }
@@ -33,10 +35,18 @@ pub fn main_export_solution_checks(opt: ExportSolutionChecksOpt) -> Result<(), E
         solution_filter: opt.filter.filter,
         booklet_solutions: false,
         no_statement: true,
+        check_statement: false,
         solution_paths: opt.filter.solution,
+        allowed_languages: opt.filter.languages,
         disabled_sanity_checks: Default::default(),
         seed: Default::default(),
         dry_run: true,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
     let task = opt
         .find_task
@@ -108,21 +118,56 @@ fn extract_solution_checks(
         }
     }
 
+    let mut score_checks = vec![None; task.subtasks.len()];
+    for check in &solution.score_checks {
+        let indices = if let Some(&idx) = subtasks.get(&check.subtask_name_pattern) {
+            vec![idx.try_into()?]
+        } else if check.subtask_name_pattern == "*" {
+            (0..score_checks.len()).collect()
+        } else {
+            bail!(
+                "Found invalid subtask score check {} in solution {}",
+                check.subtask_name_pattern,
+                solution.source_file.path.display()
+            );
+        };
+        for idx in indices {
+            if score_checks[idx].is_some() {
+                bail!(
+                    "Found multiple score checks for subtask {} in solution {}",
+                    check.subtask_name_pattern,
+                    solution.source_file.path.display()
+                );
+            }
+            score_checks[idx] = Some(check.expectation);
+        }
+    }
+
     let mut min_score = 0.;
     let mut max_score = 0.;
 
-    for (i, check) in checks.iter().enumerate() {
-        if *check == Some(SolutionCheckResult::Accepted) {
-            min_score += task.subtasks[&i.try_into()?].max_score;
-            max_score += task.subtasks[&i.try_into()?].max_score;
+    for (i, (check, score_check)) in checks.iter().zip(&score_checks).enumerate() {
+        let subtask_max_score = task.subtasks[&i.try_into()?].max_score;
+        let (mut subtask_min, mut subtask_max) = if *check == Some(SolutionCheckResult::Accepted) {
+            (subtask_max_score, subtask_max_score)
         } else if *check == Some(SolutionCheckResult::PartialScore) || check.is_none() {
-            max_score += task.subtasks[&i.try_into()?].max_score;
+            (0., subtask_max_score)
+        } else {
+            (0., 0.)
+        };
+        if let Some(score_check) = score_check {
+            let (low, high) = score_check.score_range(subtask_max_score);
+            subtask_min = f64::max(subtask_min, low);
+            subtask_max = f64::min(subtask_max, high);
         }
+        min_score += subtask_min;
+        max_score += subtask_max;
     }
 
     Ok(SolutionWithChecks {
         path: solution.source_file.path.clone(),
         checks,
+        score_checks,
         min_score,
         max_score,
     })
@@ -4,13 +4,15 @@ use std::sync::Arc;
 
 use anyhow::{anyhow, bail, Context, Error};
 use task_maker_format::ioi::{
-    InputGenerator, SubtaskInfo, TestcaseId, TestcaseInfo, GENERATION_PRIORITY,
+    InputGenerator, SubtaskInfo, TestcaseId, TestcaseInfo, INPUT_GENERATION_PRIORITY,
 };
 use task_maker_format::{EvaluationData, SourceFile, TaskFormat};
 
 /// The information about a testcase to generate.
 #[derive(Debug, Clone, Default)]
 pub struct TestcaseData {
+    /// The path to the source file of the generator.
+    pub generator_path: PathBuf,
     /// The arguments to pass to the generator for producing this input file.
     pub generator_args: Vec<String>,
     /// The seed used.
@@ -63,17 +65,18 @@ pub fn patch_task_for_batch(
 
                 let generator_args = generator_args_for_testcase(generator_args, seed);
                 let mut input_generator = testcase_template.input_generator.clone();
-                match &mut input_generator {
-                    InputGenerator::StaticFile(_) => {
-                        unreachable!("The generator cannot be StaticFile")
+                let generator_path = match &mut input_generator {
+                    InputGenerator::StaticFile(_) | InputGenerator::StoredFile(_) => {
+                        unreachable!("The generator must be Custom")
                     }
                     InputGenerator::Custom(g, args) => {
                         args.clone_from(&generator_args);
                         if let Some(generator) = generator {
                             *g = generator.clone();
                         }
+                        g.path.clone()
                     }
-                }
+                };
 
                 let testcase = TestcaseInfo::new(
                     testcase_id,
@@ -82,6 +85,7 @@ pub fn patch_task_for_batch(
                 );
 
                 let data = TestcaseData {
+                    generator_path,
                     generator_args,
                     seed,
                     input_path: working_directory.join(format!("testcase-{seed}/input.txt")),
@@ -109,6 +113,9 @@ pub fn patch_task_for_batch(
         TaskFormat::Terry(_) => {
             bail!("Terry tasks are not currently supported")
         }
+        TaskFormat::Custom(_) => {
+            bail!("Custom task formats are not currently supported")
+        }
     }
     Ok(batch)
 }
@@ -188,8 +195,8 @@ pub fn patch_dag(eval: &mut EvaluationData, batch_size: usize, batch: &Batch) ->
     for group in eval.dag.data.execution_groups.values_mut() {
         if let Some(tag) = &mut group.tag {
             if tag.name == "evaluation" {
-                // The priority of generation is GENERATION_PRIORITY - testcase id.
-                group.priority = GENERATION_PRIORITY + 1;
+                // The priority of generation is INPUT_GENERATION_PRIORITY - testcase id.
+                group.priority = INPUT_GENERATION_PRIORITY + 1;
                 processed += 1;
                 for exec in &group.executions {
                     if let task_maker_dag::ExecutionOutputBehaviour::Capture {
@@ -213,8 +220,8 @@ pub fn patch_dag(eval: &mut EvaluationData, batch_size: usize, batch: &Batch) ->
                 }
             }
             if tag.name == "checking" {
-                // The priority of the checker is GENERATION_PRIORITY - testcase id.
-                group.priority = GENERATION_PRIORITY + 1;
+                // The priority of the checker is INPUT_GENERATION_PRIORITY - testcase id.
+                group.priority = INPUT_GENERATION_PRIORITY + 1;
                 processed += 1;
             }
         }
@@ -3,23 +3,31 @@ use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, bail, Context, Error};
 use clap::{Parser, ValueHint};
+use task_maker_dag::shell_quote;
 use task_maker_exec::ductile::ChannelSender;
 use task_maker_exec::proto::ExecutorClientMessage;
 use task_maker_exec::ExecutorClient;
 use task_maker_format::ioi::get_generator;
-use task_maker_format::ui::{CursesUI, StdoutPrinter, UIMessage, BLUE, BOLD, RED, UI, YELLOW};
+use task_maker_format::ui::{
+    CursesUI, StdoutPrinter, UIMessage, BLUE, BOLD, GREEN, RED, UI, YELLOW,
+};
 use task_maker_format::{cwrite, cwriteln, get_sanity_check_list, EvaluationConfig};
 
 use crate::context::RuntimeContext;
 use crate::tools::find_bad_case::dag::{patch_dag, patch_task_for_batch, TestcaseData};
+use crate::tools::find_bad_case::diff::{diff_lines, DiffLine};
 use crate::tools::find_bad_case::state::{SharedUIState, UIState};
 use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
 
 mod curses_ui;
 mod dag;
+mod diff;
 mod finish_ui;
 mod state;
 
+/// Maximum number of bytes of the files to print and to compare.
+const MAX_CONTENT_LEN: usize = 256;
+
 #[derive(Parser, Debug, Clone)]
 #[clap(trailing_var_arg = true)]
 pub struct FindBadCaseOpt {
@@ -60,13 +68,21 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
         solution_filter: vec![],
         booklet_solutions: false,
         no_statement: true,
+        check_statement: false,
         solution_paths: vec![opt.solution.clone()],
+        allowed_languages: vec![],
         disabled_sanity_checks: get_sanity_check_list()
             .into_iter()
             .map(|check| check.0.into())
             .collect(),
         seed: None,
         dry_run: false,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
     let working_directory =
         tempfile::TempDir::new().context("Failed to create working directory")?;
@@ -247,22 +263,27 @@ pub fn main_find_bad_case(opt: FindBadCaseOpt) -> Result<(), Error> {
     println!("{message}");
     println!();
     print_file("Input file", &task_path, &input_path, &mut printer)?;
-    if let Some(correct_output_path) = correct_output_path {
+    if let Some(correct_output_path) = &correct_output_path {
         print_file(
             "Correct output file",
             &task_path,
-            &correct_output_path,
+            correct_output_path,
             &mut printer,
         )?;
     }
-    if let Some(failing_output_path) = failing_output_path {
+    if let Some(failing_output_path) = &failing_output_path {
         print_file(
             "Failing output file",
             &task_path,
-            &failing_output_path,
+            failing_output_path,
             &mut printer,
         )?;
     }
+    print_diff(
+        correct_output_path.as_deref(),
+        failing_output_path.as_deref(),
+        &mut printer,
+    )?;
 
     print_failures(&shared_state, &mut printer);
     Ok(())
@@ -277,6 +298,7 @@ fn copy_testcase(
         .with_context(|| format!("Failed to create {}", target_dir.display()))?;
 
     let input_target = target_dir.join("input.txt");
+    let command_target = target_dir.join("command.txt");
     let correct_output_target = target_dir.join("correct-output.txt");
     let failing_output_target = target_dir.join("failing-output.txt");
 
@@ -287,6 +309,8 @@ fn copy_testcase(
             input_target.display()
         )
     })?;
+    std::fs::write(&command_target, generator_command(testcase, task_path))
+        .with_context(|| format!("Failed to write {}", command_target.display()))?;
     // FIXME: the output files may not be produced, or not be present in the write_to because we
     //        stop the execution before it ends normally. This means that some executions may be
     //        skipped and their output not produced.
@@ -321,6 +345,22 @@ fn copy_testcase(
     Ok((input_target, correct_output_target, failing_output_target))
 }
 
+/// The shell command that regenerates the input file of the testcase, with the path of the
+/// generator relative to the task root. The seed is written in a comment since the generator
+/// arguments may not contain it.
+fn generator_command(testcase: &TestcaseData, task_path: &Path) -> String {
+    let generator = testcase
+        .generator_path
+        .strip_prefix(task_path)
+        .unwrap_or(&testcase.generator_path);
+    let command = std::iter::once(generator.to_string_lossy().into_owned())
+        .chain(testcase.generator_args.iter().cloned())
+        .map(|arg| shell_quote(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("# seed: {}\n{command}\n", testcase.seed)
+}
+
 fn print_file(
     title: &str,
     base_path: &Path,
@@ -332,20 +372,77 @@ fn print_file(
         "(at {})",
         path.strip_prefix(base_path).unwrap_or(path).display()
     );
+    let content = read_file(path)?;
+    match truncate(&content) {
+        (prefix, true) => println!("{}...\n", prefix.trim_end()),
+        (content, false) => println!("{}\n", content.trim_end()),
+    }
+    Ok(())
+}
+
+/// Print the line by line diff between the correct and the failing output, limited to their first
+/// `MAX_CONTENT_LEN` bytes.
+fn print_diff(
+    correct_output_path: Option<&Path>,
+    failing_output_path: Option<&Path>,
+    printer: &mut StdoutPrinter,
+) -> Result<(), Error> {
+    cwriteln!(
+        printer,
+        BLUE,
+        "Diff between the correct and the failing output"
+    );
+    let (correct, failing) = match (correct_output_path, failing_output_path) {
+        (Some(correct), Some(failing)) => (read_file(correct)?, read_file(failing)?),
+        (None, _) => {
+            println!("The correct output is missing\n");
+            return Ok(());
+        }
+        (_, None) => {
+            println!("The failing output is missing\n");
+            return Ok(());
+        }
+    };
+    let (correct, correct_truncated) = truncate(&correct);
+    let (failing, failing_truncated) = truncate(&failing);
+    for line in diff_lines(correct, failing) {
+        match line {
+            DiffLine::Same(line) => println!("  {line}"),
+            DiffLine::Removed(line) => cwriteln!(printer, RED, "- {}", line),
+            DiffLine::Added(line) => cwriteln!(printer, GREEN, "+ {}", line),
+        }
+    }
+    if correct_truncated || failing_truncated {
+        cwriteln!(
+            printer,
+            YELLOW,
+            "Only the first {} bytes of the outputs are compared",
+            MAX_CONTENT_LEN
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// Read the content of a file, replacing the invalid UTF-8 sequences.
+fn read_file(path: &Path) -> Result<String, Error> {
     let file = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
-    let content = String::from_utf8_lossy(&file);
-    const MAX_CONTENT_LEN: usize = 256;
+    Ok(String::from_utf8_lossy(&file).into_owned())
+}
+
+/// Truncate the content to at most `MAX_CONTENT_LEN` bytes, without splitting a character. Returns
+/// also whether the content has been truncated.
+fn truncate(content: &str) -> (&str, bool) {
     if content.len() > MAX_CONTENT_LEN {
         let prefix = (0..MAX_CONTENT_LEN)
             .rev()
             .filter_map(|idx| content.get(..idx))
             .next()
             .unwrap();
-        println!("{}...\n", prefix.trim_end());
+        (prefix, true)
     } else {
-        println!("{}\n", content.trim_end());
+        (content, false)
     }
-    Ok(())
 }
 
 fn print_failures(shared: &SharedUIState, printer: &mut StdoutPrinter) {
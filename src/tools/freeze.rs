@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use task_maker_format::ioi::{
+    snapshot_dir, write_frozen_manifest, OutputGenerator, TestcaseGenerationStatus, UIState,
+};
+use task_maker_format::ui::{StdoutPrinter, UIStateT, BLUE};
+use task_maker_format::{cwrite, EvaluationConfig, TaskFormat};
+use task_maker_store::FileStoreKey;
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FindTaskOpt, LoggerOpt, StorageOpt, UIOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct FreezeOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("UI"))]
+    pub ui: UIOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    /// Where to write the manifest of the snapshot, to pass to --pinned-inputs. The files of the
+    /// snapshot are copied in a directory next to it, with the `files` extension.
+    #[clap(long, short, default_value = "frozen.txt")]
+    pub output: PathBuf,
+}
+
+pub fn main_freeze(mut opt: FreezeOpt, logger_opt: LoggerOpt) -> Result<(), Error> {
+    opt.ui.disable_if_needed(&logger_opt);
+    let eval_config = EvaluationConfig {
+        // only the testset is needed, the solutions are evaluated against the snapshot later
+        solution_filter: vec!["do not evaluate the solutions!!".into()],
+        booklet_solutions: false,
+        no_statement: true,
+        check_statement: false,
+        solution_paths: vec![],
+        allowed_languages: vec![],
+        disabled_sanity_checks: Default::default(),
+        seed: Default::default(),
+        dry_run: false,
+        max_executions: None,
+        pinned_inputs: opt.execution.pinned_inputs.clone(),
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config)
+        .context("Failed to locate the task")?;
+
+    // Same as in add-solution-checks: the state is updated by the UI thread and read at the end.
+    let ui_state = Arc::new(Mutex::new(None::<UIState>));
+
+    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        task.build_dag(eval, &eval_config)
+            .context("Cannot build the task DAG")?;
+        let TaskFormat::IOI(ioi_task) = &task else {
+            bail!("The freeze tool only supports IOI-tasks");
+        };
+        *ui_state.lock().unwrap() = Some(UIState::new(ioi_task, eval.dag.data.config.clone()));
+        Ok(())
+    })?;
+
+    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let executor = executor.start_ui(&opt.ui.ui, {
+        let ui_state = ui_state.clone();
+        move |ui, message| {
+            ui.on_message(message.clone());
+            ui_state.lock().unwrap().as_mut().unwrap().apply(message);
+        }
+    })?;
+    executor.execute()?;
+
+    let ui_state = ui_state.lock().unwrap().take().unwrap();
+    let task = &ui_state.task;
+    if !task.task_type.extra_output_files().is_empty() {
+        bail!("The freeze tool doesn't support tasks with extra output files");
+    }
+    let snapshot = snapshot_dir(&opt.output);
+    std::fs::create_dir_all(&snapshot)
+        .with_context(|| format!("Failed to create {}", snapshot.display()))?;
+    // the files are copied by hash, so the snapshot doesn't depend on the store being kept
+    let freeze_file = |path: &PathBuf| -> Result<FileStoreKey, Error> {
+        let key = FileStoreKey::from_file(path)
+            .with_context(|| format!("Failed to hash {}", path.display()))?;
+        let dest = snapshot.join(key.to_string());
+        if !dest.exists() {
+            std::fs::copy(path, &dest).with_context(|| {
+                format!("Failed to copy {} to {}", path.display(), dest.display())
+            })?;
+        }
+        Ok(key)
+    };
+    let mut testcases = BTreeMap::new();
+    for (subtask_id, subtask) in &ui_state.generations {
+        for (testcase_id, testcase) in &subtask.testcases {
+            if matches!(
+                testcase.status,
+                TestcaseGenerationStatus::Failed
+                    | TestcaseGenerationStatus::Skipped
+                    | TestcaseGenerationStatus::Pending
+            ) {
+                bail!(
+                    "The generation of testcase {testcase_id} of subtask {subtask_id} failed, \
+                    cannot freeze the testset"
+                );
+            }
+            let input = task
+                .path
+                .join("input")
+                .join(format!("input{testcase_id}.txt"));
+            let output = match &task.testcases[testcase_id].output_generator {
+                OutputGenerator::NotAvailable => None,
+                OutputGenerator::StaticFile(path) => Some(path.clone()),
+                _ => Some(
+                    task.path
+                        .join("output")
+                        .join(format!("output{testcase_id}.txt")),
+                ),
+            };
+            let input = freeze_file(&input)?;
+            let output = output.as_ref().map(&freeze_file).transpose()?;
+            testcases.insert(*testcase_id, (input, output));
+        }
+    }
+
+    std::fs::write(&opt.output, write_frozen_manifest(&testcases))
+        .with_context(|| format!("Failed to write {}", opt.output.display()))?;
+    let mut printer = StdoutPrinter::default();
+    cwrite!(printer, BLUE, "Frozen");
+    println!(
+        " {} testcases in {} and {}, evaluate against them with --pinned-inputs {}",
+        testcases.len(),
+        opt.output.display(),
+        snapshot.display(),
+        opt.output.display()
+    );
+    Ok(())
+}
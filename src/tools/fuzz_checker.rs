@@ -145,6 +145,7 @@ pub fn main_fuzz_checker(opt: FuzzCheckerOpt) -> Result<(), Error> {
         let eval_config = EvaluationConfig {
             solution_filter: vec!["do not evaluate the solutions!!".into()],
             no_statement: true,
+            check_statement: false,
             ..Default::default()
         };
 
@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+use task_maker_format::ioi::italian_yaml::import_cms_task;
+use walkdir::WalkDir;
+
+/// The files of the cms task that are rewritten by the importer, instead of being copied.
+const REWRITTEN_FILES: &[&str] = &["task.yaml", "input", "output", "gen/GEN"];
+
+#[derive(Parser, Debug, Clone)]
+pub struct ImportCmsOpt {
+    /// Directory of the task exported from cms.
+    pub cms_dir: PathBuf,
+
+    /// Directory where to create the task, it must not already contain a task.
+    pub task_dir: PathBuf,
+}
+
+/// Handler of the `import-cms` tool. The testcases and the task.yaml are converted by
+/// `import_cms_task`, all the other files (e.g. the checker, the solutions and the statement) are
+/// copied as they are.
+pub fn main_import_cms(opt: ImportCmsOpt) -> Result<(), Error> {
+    if !opt.cms_dir.join("task.yaml").exists() {
+        bail!("No task.yaml found in {}", opt.cms_dir.display());
+    }
+    if opt.task_dir.join("task.yaml").exists() {
+        bail!("There is already a task in {}", opt.task_dir.display());
+    }
+    for entry in WalkDir::new(&opt.cms_dir).min_depth(1) {
+        let entry = entry.context("Failed to list the files of the cms task")?;
+        let relative = entry
+            .path()
+            .strip_prefix(&opt.cms_dir)
+            .context("Invalid path in the cms task")?;
+        if REWRITTEN_FILES.iter().any(|f| relative.starts_with(f)) {
+            continue;
+        }
+        copy_entry(entry.path(), &opt.task_dir.join(relative))?;
+    }
+    import_cms_task(&opt.cms_dir, &opt.task_dir).context("Failed to import the cms task")?;
+    println!("Task imported in {}", opt.task_dir.display());
+    Ok(())
+}
+
+/// Copy a file or create a directory, in the same place inside the new task.
+fn copy_entry(from: &Path, to: &Path) -> Result<(), Error> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)
+            .with_context(|| format!("Failed to create {}", to.display()))?;
+    } else {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    }
+    Ok(())
+}
@@ -3,19 +3,27 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use task_maker_rust::error::NiceError;
+use task_maker_rust::tools::abort_client::main_abort_client;
 use task_maker_rust::tools::add_solution_checks::main_add_solution_checks;
 use task_maker_rust::tools::booklet::main_booklet;
+use task_maker_rust::tools::cache_compact::main_cache_compact;
 use task_maker_rust::tools::clear::main_clear;
 use task_maker_rust::tools::copy_competition_files::copy_competition_files_main;
+use task_maker_rust::tools::diff_runs::main_diff_runs;
 use task_maker_rust::tools::eval_server::main_eval_server;
 use task_maker_rust::tools::export_booklet::main_export_booklet;
+use task_maker_rust::tools::export_makefile::main_export_makefile;
 use task_maker_rust::tools::export_solution_checks::main_export_solution_checks;
 use task_maker_rust::tools::find_bad_case::main_find_bad_case;
+use task_maker_rust::tools::freeze::main_freeze;
 use task_maker_rust::tools::fuzz_checker::main_fuzz_checker;
 use task_maker_rust::tools::gen_autocompletion::main_get_autocompletion;
+use task_maker_rust::tools::import_cms::main_import_cms;
 use task_maker_rust::tools::opt::{Opt, Tool};
+use task_maker_rust::tools::opt_levels::main_opt_levels;
 use task_maker_rust::tools::reset::main_reset;
 use task_maker_rust::tools::sandbox::main_sandbox;
+use task_maker_rust::tools::selftest::main_selftest;
 use task_maker_rust::tools::server::main_server;
 use task_maker_rust::tools::task_controller::main_task_controller;
 use task_maker_rust::tools::task_info::main_task_info;
@@ -45,8 +53,10 @@ fn main() {
         Tool::GenAutocompletion(opt) => main_get_autocompletion(opt),
         Tool::Server(opt) => main_server(opt),
         Tool::Worker(opt) => main_worker(opt),
+        Tool::AbortClient(opt) => main_abort_client(opt),
         Tool::Reset(opt) => main_reset(opt),
         Tool::Sandbox(opt) => main_sandbox(opt),
+        Tool::Selftest(opt) => main_selftest(opt),
         Tool::TaskInfo(opt) => main_task_info(opt),
         Tool::Booklet(opt) => main_booklet(opt, base_opt.logger),
         Tool::TerryStatement(opt) => main_terry_statement(opt, base_opt.logger),
@@ -56,8 +66,14 @@ fn main() {
         Tool::AddSolutionChecks(opt) => main_add_solution_checks(opt, base_opt.logger),
         Tool::ExportSolutionChecks(opt) => main_export_solution_checks(opt),
         Tool::ExportBooklet(opt) => main_export_booklet(opt),
+        Tool::ExportMakefile(opt) => main_export_makefile(opt),
         Tool::EvalServer(opt) => main_eval_server(opt),
         Tool::TaskController(opt) => main_task_controller(opt),
+        Tool::DiffRuns(opt) => main_diff_runs(opt),
+        Tool::ImportCms(opt) => main_import_cms(opt),
+        Tool::Freeze(opt) => main_freeze(opt, base_opt.logger),
+        Tool::OptLevels(opt) => main_opt_levels(opt),
+        Tool::CacheCompact(opt) => main_cache_compact(opt),
     }
     .nice_unwrap()
 }
@@ -1,16 +1,24 @@
+pub mod abort_client;
 pub mod add_solution_checks;
 pub mod booklet;
+pub mod cache_compact;
 pub mod clear;
 pub mod copy_competition_files;
+pub mod diff_runs;
 pub mod eval_server;
 pub mod export_booklet;
+pub mod export_makefile;
 pub mod export_solution_checks;
 pub mod find_bad_case;
+pub mod freeze;
 pub mod fuzz_checker;
 pub mod gen_autocompletion;
+pub mod import_cms;
 pub mod opt;
+pub mod opt_levels;
 pub mod reset;
 pub mod sandbox;
+pub mod selftest;
 pub mod server;
 pub mod task_controller;
 pub mod task_info;
@@ -1,17 +1,25 @@
 use clap::Parser;
 
+use crate::tools::abort_client::AbortClientOpt;
 use crate::tools::add_solution_checks::AddSolutionChecksOpt;
 use crate::tools::booklet::BookletOpt;
+use crate::tools::cache_compact::CacheCompactOpt;
 use crate::tools::clear::ClearOpt;
 use crate::tools::copy_competition_files::CopyCompetitionFilesOpt;
+use crate::tools::diff_runs::DiffRunsOpt;
 use crate::tools::eval_server::EvalServerOpt;
 use crate::tools::export_booklet::ExportBookletOpt;
+use crate::tools::export_makefile::ExportMakefileOpt;
 use crate::tools::export_solution_checks::ExportSolutionChecksOpt;
 use crate::tools::find_bad_case::FindBadCaseOpt;
+use crate::tools::freeze::FreezeOpt;
 use crate::tools::fuzz_checker::FuzzCheckerOpt;
 use crate::tools::gen_autocompletion::GenAutocompletionOpt;
+use crate::tools::import_cms::ImportCmsOpt;
+use crate::tools::opt_levels::OptLevelsOpt;
 use crate::tools::reset::ResetOpt;
 use crate::tools::sandbox::SandboxOpt;
+use crate::tools::selftest::SelftestOpt;
 use crate::tools::server::ServerOpt;
 use crate::tools::task_controller::TaskControllerOpt;
 use crate::tools::task_info::TaskInfoOpt;
@@ -40,12 +48,16 @@ pub enum Tool {
     Server(ServerOpt),
     /// Spawn an instance of a worker
     Worker(WorkerOpt),
+    /// Abort the evaluation of a client of a server, or list the clients using the workers
+    AbortClient(AbortClientOpt),
     /// Wipe the internal storage of task-maker
     ///
     /// Warning: no other instances of task-maker should be running when this flag is provided.
     Reset(ResetOpt),
     /// Run a command inside a sandbox similar to the one used by task-maker
     Sandbox(SandboxOpt),
+    /// Verify that the sandbox is configured correctly on this machine
+    Selftest(SelftestOpt),
     /// Obtain the information about a task.
     TaskInfo(TaskInfoOpt),
     /// Compile just the booklet for a task or a contest.
@@ -64,8 +76,20 @@ pub enum Tool {
     ExportSolutionChecks(ExportSolutionChecksOpt),
     /// Exports internal booklet structure as a zip.
     ExportBooklet(ExportBookletOpt),
+    /// Exports a Makefile that generates the input and output files of a task.
+    ExportMakefile(ExportMakefileOpt),
     /// Start a web server for evaluating arbitrary code.
     EvalServer(EvalServerOpt),
     /// Run a solution with an interactor.
     TaskController(TaskControllerOpt),
+    /// Compare the scores of two evaluations saved from the JSON UI.
+    DiffRuns(DiffRunsOpt),
+    /// Convert a task exported from cms into a task-maker task.
+    ImportCms(ImportCmsOpt),
+    /// Generate the testset once and write a snapshot to evaluate the solutions against.
+    Freeze(FreezeOpt),
+    /// Compare the time of a solution compiled at different optimization levels.
+    OptLevels(OptLevelsOpt),
+    /// Remove from the cache the entries whose files are gone from the store, reclaiming space.
+    CacheCompact(CacheCompactOpt),
 }
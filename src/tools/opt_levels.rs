@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Context, Error};
+use clap::Parser;
+use itertools::Itertools;
+use task_maker_format::ioi::{TestcaseEvaluationStatus, TestcaseId, UIState};
+use task_maker_format::ui::{StdoutPrinter, UIStateT, UIType, BLUE, BOLD, RED};
+use task_maker_format::{cwrite, cwriteln, get_sanity_check_list, EvaluationConfig, TaskFormat};
+use task_maker_lang::LanguageManager;
+
+use crate::context::RuntimeContext;
+use crate::{ExecutionOpt, FindTaskOpt, StorageOpt};
+
+#[derive(Parser, Debug, Clone)]
+pub struct OptLevelsOpt {
+    #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
+    pub find_task: FindTaskOpt,
+
+    #[clap(flatten, next_help_heading = Some("STORAGE"))]
+    pub storage: StorageOpt,
+
+    #[clap(flatten, next_help_heading = Some("EXECUTION"))]
+    pub execution: ExecutionOpt,
+
+    /// Path to the solution to evaluate. Its language must support choosing the optimization level
+    /// (e.g. C or C++).
+    pub solution: PathBuf,
+
+    /// The optimization levels to compare, comma separated.
+    #[clap(long, value_delimiter = ',', default_value = "0,1,2,3")]
+    pub levels: Vec<u8>,
+}
+
+/// The time used by the solution on a testcase.
+#[derive(Debug, Clone, Copy)]
+struct TestcaseTime {
+    /// The CPU time used by the solution, in seconds.
+    cpu_time: f64,
+    /// Whether the solution exceeded the time limit.
+    timed_out: bool,
+}
+
+pub fn main_opt_levels(opt: OptLevelsOpt) -> Result<(), Error> {
+    if !opt.solution.exists() {
+        bail!("Cannot find solution at {}", opt.solution.display());
+    }
+    let language = LanguageManager::detect_language(&opt.solution)
+        .ok_or_else(|| anyhow!("Unknown language of {}", opt.solution.display()))?;
+    if !language.supports_opt_level() {
+        bail!(
+            "Solution {} is written in {}, which doesn't support choosing the optimization level",
+            opt.solution.display(),
+            language.name()
+        );
+    }
+    if opt.levels.is_empty() {
+        bail!("No optimization level to compare");
+    }
+
+    let eval_config = |opt_level| EvaluationConfig {
+        solution_filter: vec![],
+        booklet_solutions: false,
+        no_statement: true,
+        check_statement: false,
+        solution_paths: vec![opt.solution.clone()],
+        allowed_languages: vec![],
+        disabled_sanity_checks: get_sanity_check_list()
+            .into_iter()
+            .map(|check| check.0.into())
+            .collect(),
+        seed: None,
+        dry_run: true,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level,
+    };
+    let task = opt
+        .find_task
+        .find_task(&eval_config(None))
+        .context("Failed to locate the task")?;
+
+    // The levels are evaluated one after the other, so they don't compete for the CPU. The
+    // generation of the testcases is cached after the first level, while the compilations at the
+    // different levels have different flags, hence different cache keys.
+    let mut times = vec![];
+    for &level in &opt.levels {
+        let state = evaluate(task.clone(), &opt, &eval_config(Some(level)))
+            .with_context(|| format!("Failed to evaluate the solution at -O{level}"))?;
+        times.push(testcase_times(&state));
+    }
+
+    print_table(&opt.levels, &times);
+    Ok(())
+}
+
+/// Evaluate the solution with the given configuration, returning the final state of the UI.
+fn evaluate(
+    task: TaskFormat,
+    opt: &OptLevelsOpt,
+    eval_config: &EvaluationConfig,
+) -> Result<UIState, Error> {
+    // This is a mutex because this state is updated in the UI thread, but it will later be used by
+    // this main thread.
+    let ui_state = Arc::new(Mutex::new(None::<UIState>));
+
+    let context = RuntimeContext::new(task, &opt.execution, |task, eval| {
+        task.build_dag(eval, eval_config)
+            .context("Cannot build the task DAG")?;
+        let TaskFormat::IOI(ioi_task) = &task else {
+            bail!("The opt-levels tool only supports IOI-tasks for now");
+        };
+        *ui_state.lock().unwrap() = Some(UIState::new(ioi_task, eval.dag.data.config.clone()));
+        Ok(())
+    })?;
+
+    let executor = context.connect_executor(&opt.execution, &opt.storage)?;
+    let executor = executor.start_ui(&UIType::Silent, {
+        let ui_state = ui_state.clone();
+        move |_, message| {
+            ui_state.lock().unwrap().as_mut().unwrap().apply(message);
+        }
+    })?;
+    executor.execute()?;
+
+    let state = ui_state.lock().unwrap().take().unwrap();
+    Ok(state)
+}
+
+/// The time used by the only evaluated solution on each testcase it completed.
+fn testcase_times(state: &UIState) -> BTreeMap<TestcaseId, TestcaseTime> {
+    let Some(evaluation) = state.evaluations.values().next() else {
+        return BTreeMap::new();
+    };
+    evaluation
+        .testcases
+        .iter()
+        .filter(|(_, testcase)| {
+            testcase.status.has_completed() && testcase.results.iter().any(Option::is_some)
+        })
+        .map(|(&id, testcase)| {
+            let cpu_time = testcase
+                .results
+                .iter()
+                .flatten()
+                .map(|result| result.resources.cpu_time)
+                .sum();
+            let timed_out = matches!(
+                testcase.status,
+                TestcaseEvaluationStatus::TimeLimitExceeded
+                    | TestcaseEvaluationStatus::WallTimeLimitExceeded
+            );
+            (
+                id,
+                TestcaseTime {
+                    cpu_time,
+                    timed_out,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Print the time used on each testcase at each level, with the spread between the fastest and
+/// the slowest level.
+fn print_table(levels: &[u8], times: &[BTreeMap<TestcaseId, TestcaseTime>]) {
+    let mut printer = StdoutPrinter::default();
+    cwriteln!(printer, BLUE, "Time at the optimization levels");
+    cwrite!(printer, BOLD, "{:>10}", "Testcase");
+    for level in levels {
+        cwrite!(printer, BOLD, "{:>10}", format!("-O{level}"));
+    }
+    cwriteln!(printer, BOLD, "{:>20}", "Spread");
+
+    let testcases: BTreeSet<_> = times.iter().flat_map(|times| times.keys()).collect();
+    for testcase in testcases {
+        print!("{testcase:>10}");
+        let row = times.iter().map(|times| times.get(testcase)).collect_vec();
+        for time in &row {
+            match time {
+                Some(time) if time.timed_out => {
+                    cwrite!(printer, RED, "{:>9.3}s", time.cpu_time)
+                }
+                Some(time) => print!("{:>9.3}s", time.cpu_time),
+                None => print!("{:>10}", "-"),
+            }
+        }
+        let row = row.iter().flatten().map(|time| time.cpu_time).collect_vec();
+        println!("{:>20}", spread(&row));
+    }
+
+    print!("{:>10}", "Total");
+    let totals = times
+        .iter()
+        .map(|times| times.values().map(|time| time.cpu_time).sum::<f64>())
+        .collect_vec();
+    for total in &totals {
+        print!("{total:>9.3}s");
+    }
+    println!("{:>20}", spread(&totals));
+}
+
+/// Format the difference between the slowest and the fastest time, and their ratio.
+fn spread(times: &[f64]) -> String {
+    let min = times.iter().copied().reduce(f64::min);
+    let max = times.iter().copied().reduce(f64::max);
+    match (min, max) {
+        (Some(min), Some(max)) if min > 0.0 => {
+            format!("{:.3}s (x{:.2})", max - min, max / min)
+        }
+        (Some(min), Some(max)) => format!("{:.3}s", max - min),
+        _ => "-".into(),
+    }
+}
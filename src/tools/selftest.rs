@@ -0,0 +1,129 @@
+use std::net::SocketAddr;
+#[cfg(not(target_os = "macos"))]
+use std::net::TcpStream;
+#[cfg(not(target_os = "macos"))]
+use std::path::Path;
+#[cfg(not(target_os = "macos"))]
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error};
+use clap::Parser;
+#[cfg(not(target_os = "macos"))]
+use tabox::configuration::SandboxConfiguration;
+#[cfg(not(target_os = "macos"))]
+use tabox::result::ExitStatus;
+#[cfg(not(target_os = "macos"))]
+use tabox::syscall_filter::SyscallFilter;
+#[cfg(not(target_os = "macos"))]
+use tabox::{Sandbox, SandboxImplementation};
+#[cfg(not(target_os = "macos"))]
+use task_maker_exec::execution_unit::sandbox::READABLE_DIRS;
+use task_maker_exec::SandboxUser;
+
+#[derive(Parser, Debug, Clone)]
+pub struct SelftestOpt {
+    /// Address (ip:port) of the host the sandbox should not be able to connect to.
+    #[clap(long, default_value = "1.1.1.1:80")]
+    pub target: SocketAddr,
+
+    /// Timeout of the connection attempt outside the sandbox, in seconds.
+    #[clap(long, default_value = "5")]
+    pub timeout: u64,
+
+    /// The user id the processes are run as inside the sandbox, it cannot be root
+    #[clap(long, default_value = "1000")]
+    pub sandbox_uid: u32,
+
+    /// The group id the processes are run as inside the sandbox, it cannot be root
+    #[clap(long, default_value = "1000")]
+    pub sandbox_gid: u32,
+}
+
+/// Handler of the `selftest` tool. This tool verifies that the sandbox is configured correctly on
+/// this machine, for now checking that the executions inside the sandbox have no network access.
+pub fn main_selftest(opt: SelftestOpt) -> Result<(), Error> {
+    let sandbox_user = SandboxUser::new(opt.sandbox_uid, opt.sandbox_gid);
+    sandbox_user
+        .validate()
+        .context("Invalid sandbox user configuration")?;
+    print!("Network isolation: ");
+    check_network_isolation(&opt, sandbox_user)?;
+    Ok(())
+}
+
+/// Check that a process inside the sandbox cannot open an outbound connection.
+#[cfg(target_os = "macos")]
+fn check_network_isolation(_opt: &SelftestOpt, _user: SandboxUser) -> Result<(), Error> {
+    println!("UNSUPPORTED");
+    bail!("The sandbox does not support network isolation on this platform");
+}
+
+/// Check that a process inside the sandbox cannot open an outbound connection.
+#[cfg(not(target_os = "macos"))]
+fn check_network_isolation(opt: &SelftestOpt, user: SandboxUser) -> Result<(), Error> {
+    // Make sure the sandbox works at all, otherwise a failure of the connection attempt proves
+    // nothing.
+    let status = run_in_sandbox("true", user).context("Failed to run the sandbox")?;
+    if !matches!(status, ExitStatus::ExitCode(0)) {
+        println!("ERROR");
+        bail!(
+            "A trivial execution failed inside the sandbox: {:?}",
+            status
+        );
+    }
+
+    let command = format!("exec 3<>/dev/tcp/{}/{}", opt.target.ip(), opt.target.port());
+    let status = run_in_sandbox(&command, user).context("Failed to run the sandbox")?;
+    if matches!(status, ExitStatus::ExitCode(0)) {
+        println!("FAIL");
+        bail!(
+            "A process inside the sandbox managed to connect to {}",
+            opt.target
+        );
+    }
+
+    // The connection inside the sandbox failed, but it's meaningful only if it's possible to
+    // connect from outside.
+    let timeout = Duration::from_secs(opt.timeout);
+    if let Err(e) = TcpStream::connect_timeout(&opt.target, timeout) {
+        println!("INCONCLUSIVE");
+        bail!(
+            "Cannot connect to {} from outside the sandbox either ({}), try with a different --target",
+            opt.target,
+            e
+        );
+    }
+    println!("OK");
+    Ok(())
+}
+
+/// Run `bash -c <command>` inside a sandbox configured like the ones of the evaluations, as the
+/// given user, returning its exit status.
+#[cfg(not(target_os = "macos"))]
+fn run_in_sandbox(command: &str, user: SandboxUser) -> Result<ExitStatus, Error> {
+    let bash = which::which("bash").context("Cannot find bash")?;
+    let mut config = SandboxConfiguration::default();
+    for dir in READABLE_DIRS {
+        if Path::new(dir).is_dir() {
+            config.mount(dir, dir, false);
+        }
+    }
+    if let Some(dir) = bash.parent() {
+        config.mount(dir, dir, false);
+    }
+    config.syscall_filter(SyscallFilter::build(true, true));
+    config.executable(&bash);
+    config.arg("-c");
+    config.arg(command);
+    config.wall_time_limit(10);
+    config.uid(user.uid as usize);
+    config.gid(user.gid as usize);
+
+    debug!("Config: {config:#?}");
+
+    let sandbox = SandboxImplementation::run(config).context("Failed to create sandbox")?;
+    let res = sandbox.wait().context("Failed to wait sandbox")?;
+
+    debug!("Result: {res:#?}");
+    Ok(res.status)
+}
@@ -2,9 +2,7 @@ use std::sync::Arc;
 
 use anyhow::{Context, Error};
 use clap::Parser;
-use task_maker_cache::Cache;
 use task_maker_exec::executors::RemoteExecutor;
-use task_maker_store::FileStore;
 
 use crate::StorageOpt;
 
@@ -26,6 +24,11 @@ pub struct ServerOpt {
     #[clap(long = "worker-password")]
     pub worker_password: Option<String>,
 
+    /// Password required by abort-client for aborting the evaluation of a client. If not set, no
+    /// client can be aborted
+    #[clap(long = "operator-password")]
+    pub operator_password: Option<String>,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
 }
@@ -33,16 +36,12 @@ pub struct ServerOpt {
 /// Entry point for the server.
 pub fn main_server(opt: ServerOpt) -> Result<(), Error> {
     // setup the executor
-    let store_path = opt.storage.store_dir();
     let file_store = Arc::new(
-        FileStore::new(
-            store_path.join("store"),
-            opt.storage.max_cache * 1024 * 1024,
-            opt.storage.min_cache * 1024 * 1024,
-        )
-        .context("Cannot create the file store")?,
+        opt.storage
+            .file_store()
+            .context("Cannot create the file store")?,
     );
-    let cache = Cache::new(store_path.join("cache")).context("Cannot create the cache")?;
+    let cache = opt.storage.cache().context("Cannot create the cache")?;
 
     let remote_executor = RemoteExecutor::new(file_store);
 
@@ -51,6 +50,7 @@ pub fn main_server(opt: ServerOpt) -> Result<(), Error> {
         &opt.worker_addr,
         opt.client_password,
         opt.worker_password,
+        opt.operator_password,
         cache,
     )
 }
@@ -230,12 +230,13 @@ pub fn main_task_controller(opt: TaskControllerOpt) -> Result<(), Error> {
         Some(Ok(results)) => {
             for (i, result) in results.iter().enumerate() {
                 println!(
-                    "Execution {}: {:?} (cpu: {:.3}s, wall: {:.3}s, mem: {:.1}MiB)",
+                    "Execution {}: {:?} (cpu: {:.3}s, wall: {:.3}s, mem: {:.1}MiB, final mem: {:.1}MiB)",
                     i,
                     result.status,
                     result.resources.cpu_time,
                     result.resources.wall_time,
-                    result.resources.memory as f64 / 1024.0
+                    result.resources.memory as f64 / 1024.0,
+                    result.resources.final_memory as f64 / 1024.0
                 );
             }
         }
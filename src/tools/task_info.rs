@@ -1,8 +1,17 @@
 use anyhow::{Context, Error};
 use clap::Parser;
+use itertools::Itertools;
+
+use task_maker_format::ioi::IOITaskInfo;
+use task_maker_format::terry::TerryTaskInfo;
+use task_maker_format::ui::{StdoutPrinter, BLUE, BOLD, YELLOW};
+use task_maker_format::{cwrite, cwriteln, TaskInfo};
 
 use crate::FindTaskOpt;
 
+/// Width of the labels column of the task info table.
+const LABEL_WIDTH: usize = 14;
+
 #[derive(Parser, Debug, Clone)]
 pub struct TaskInfoOpt {
     #[clap(flatten, next_help_heading = Some("TASK SEARCH"))]
@@ -19,7 +28,110 @@ pub fn main_task_info(opt: TaskInfoOpt) -> Result<(), Error> {
         let json = serde_json::to_string(&info).context("Non-serializable task info")?;
         println!("{json}");
     } else {
-        println!("{info:#?} ");
+        let mut printer = StdoutPrinter::default();
+        match &info {
+            TaskInfo::IOI(info) => print_ioi_task_info(&mut printer, info),
+            TaskInfo::Terry(info) => print_terry_task_info(&mut printer, info),
+        }
     }
     Ok(())
 }
+
+/// Print the information about an IOI task as a human-readable table.
+fn print_ioi_task_info(printer: &mut StdoutPrinter, info: &IOITaskInfo) {
+    print_title(printer, &info.title, &info.name);
+    let task_type = if info.task_type.is_empty() {
+        "unknown"
+    } else {
+        &info.task_type
+    };
+    print_row(printer, "Type", &format!("IOI ({task_type})"));
+    let time_limit = match info.limits.time {
+        Some(time) => format!("{time}s"),
+        None => "unlimited".into(),
+    };
+    print_row(printer, "Time limit", &time_limit);
+    let memory_limit = match info.limits.memory {
+        Some(memory) => format!("{memory} MiB"),
+        None => "unlimited".into(),
+    };
+    print_row(printer, "Memory limit", &memory_limit);
+    print_row(printer, "Max score", &info.scoring.max_score.to_string());
+
+    println!();
+    if info.scoring.subtasks.is_empty() {
+        cwriteln!(printer, YELLOW, "No subtasks");
+    } else {
+        cwriteln!(printer, BLUE, "Subtasks");
+        cwriteln!(
+            printer,
+            BOLD,
+            "{:>5}  {:>8}  {:>9}",
+            "#",
+            "Score",
+            "Testcases"
+        );
+        for (index, subtask) in info.scoring.subtasks.iter().enumerate() {
+            println!(
+                "{index:>5}  {:>8}  {:>9}",
+                subtask.max_score, subtask.testcases
+            );
+        }
+    }
+    if info
+        .scoring
+        .subtasks
+        .iter()
+        .any(|subtask| !subtask.dependencies.is_empty())
+    {
+        println!();
+        cwriteln!(printer, BLUE, "Subtask dependencies");
+        let tree = info.scoring.dependency_tree();
+        for line in &tree.lines {
+            println!("    {line}");
+        }
+        for cycle in &tree.cycles {
+            let cycle = cycle.iter().chain(cycle.first()).join(" -> ");
+            cwriteln!(printer, YELLOW, "Cycle between the subtasks: {cycle}");
+        }
+    }
+
+    if !info.statements.is_empty() {
+        println!();
+        cwriteln!(printer, BLUE, "Statements");
+        for statement in &info.statements {
+            println!("    {:<6} {}", statement.language, statement.path.display());
+        }
+    }
+    if !info.attachments.is_empty() {
+        println!();
+        cwriteln!(printer, BLUE, "Attachments");
+        for attachment in &info.attachments {
+            println!("    {}", attachment.path.display());
+        }
+    }
+}
+
+/// Print the information about a Terry task as a human-readable table.
+fn print_terry_task_info(printer: &mut StdoutPrinter, info: &TerryTaskInfo) {
+    print_title(printer, &info.description, &info.name);
+    print_row(printer, "Type", "Terry");
+    print_row(printer, "Max score", &info.max_score.to_string());
+}
+
+/// Print the title of the task, falling back to its name if the title is empty.
+fn print_title(printer: &mut StdoutPrinter, title: &str, name: &str) {
+    if title.is_empty() {
+        cwriteln!(printer, BOLD, "{name}");
+    } else {
+        cwrite!(printer, BOLD, "{title}");
+        println!(" ({name})");
+    }
+    println!();
+}
+
+/// Print a row of the table, with the label aligned to the others.
+fn print_row(printer: &mut StdoutPrinter, label: &str, value: &str) {
+    cwrite!(printer, BOLD, "{:<LABEL_WIDTH$}", format!("{label}:"));
+    println!("{value}");
+}
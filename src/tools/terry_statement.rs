@@ -44,10 +44,18 @@ pub fn main_terry_statement(opt: TerryStatementOpt, _logger_opt: LoggerOpt) -> R
         solution_filter: vec![],
         booklet_solutions: false,
         no_statement: false,
+        check_statement: false,
         solution_paths: vec![],
+        allowed_languages: vec![],
         disabled_sanity_checks: vec![],
         seed: None,
         dry_run: false,
+        max_executions: None,
+        pinned_inputs: None,
+        sanitized_solutions: vec![],
+        num_processes: None,
+        stop_after: None,
+        opt_level: None,
     };
 
     let (statement_path, subtasks_path, output_path) =
@@ -1,9 +1,12 @@
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{bail, Context, Error};
 use clap::Parser;
+use task_maker_cache::Cache;
 use task_maker_exec::executors::{RemoteEntityMessage, RemoteEntityMessageResponse};
-use task_maker_exec::Worker;
+use task_maker_exec::{SandboxUser, Worker};
 use task_maker_store::FileStore;
 
 use crate::remote::connect_to_remote_server;
@@ -22,6 +25,33 @@ pub struct WorkerOpt {
     #[clap(long)]
     pub name: Option<String>,
 
+    /// The user id the processes are run as inside the sandbox, it cannot be root
+    #[clap(long, default_value = "1000")]
+    pub sandbox_uid: u32,
+
+    /// The group id the processes are run as inside the sandbox, it cannot be root
+    #[clap(long, default_value = "1000")]
+    pub sandbox_gid: u32,
+
+    /// Run the sandboxes with this command instead of task-maker-tools itself (e.g. to run them
+    /// inside a container). The command is called with `internal-sandbox <config> <output>`
+    /// appended
+    #[clap(long)]
+    pub sandbox_runner: Option<String>,
+
+    /// Connect again to the server when the connection is lost, instead of exiting. The files and
+    /// the executions cached by the worker are kept across the connections
+    #[clap(long)]
+    pub reconnect: bool,
+
+    /// Seconds to wait before connecting again to the server, with --reconnect
+    #[clap(long, default_value = "5")]
+    pub reconnect_delay: u64,
+
+    /// Do not keep a cache of the executions run by the worker
+    #[clap(long)]
+    pub no_worker_cache: bool,
+
     #[clap(flatten, next_help_heading = Some("STORAGE"))]
     pub storage: StorageOpt,
 }
@@ -31,29 +61,79 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Entry point for the worker.
 pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
+    let sandbox_user = SandboxUser::new(opt.sandbox_uid, opt.sandbox_gid);
+    sandbox_user
+        .validate()
+        .context("Invalid sandbox user configuration")?;
     let store_path = opt.storage.store_dir();
     let file_store = Arc::new(
-        FileStore::new(
-            store_path.join("store"),
-            opt.storage.max_cache * 1024 * 1024,
-            opt.storage.min_cache * 1024 * 1024,
-        )
-        .context("Cannot create the file store")?,
+        opt.storage
+            .file_store()
+            .context("Cannot create the file store")?,
     );
     let sandbox_path = store_path.join("sandboxes");
+    let sandbox_runner = match &opt.sandbox_runner {
+        Some(command) => ToolsSandboxRunner::from_command(command)?,
+        None => ToolsSandboxRunner::default(),
+    };
 
-    let name = opt.name.unwrap_or_else(|| {
+    let name = opt.name.clone().unwrap_or_else(|| {
         format!(
             "{}@{}",
             whoami::username(),
             whoami::fallible::hostname().unwrap()
         )
     });
+    let cache = if opt.no_worker_cache {
+        None
+    } else {
+        Some(Arc::new(Mutex::new(
+            opt.storage.cache().context("Cannot create the cache")?,
+        )))
+    };
+    let name = if let Some(wid) = opt.worker_id {
+        format!("{name} {wid}")
+    } else {
+        name
+    };
+    let sandbox_runner = Arc::new(sandbox_runner);
+
+    loop {
+        let res = connect_and_work(
+            &opt,
+            &name,
+            file_store.clone(),
+            &sandbox_path,
+            sandbox_runner.clone(),
+            sandbox_user,
+            cache.clone(),
+        );
+        if !opt.reconnect {
+            return res;
+        }
+        if let Err(e) = res {
+            warn!("Worker disconnected: {e:?}");
+        }
+        info!("Connecting again in {} seconds", opt.reconnect_delay);
+        std::thread::sleep(Duration::from_secs(opt.reconnect_delay));
+    }
+}
+
+/// Connect to the server and work until the connection is closed.
+fn connect_and_work(
+    opt: &WorkerOpt,
+    name: &str,
+    file_store: Arc<FileStore>,
+    sandbox_path: &Path,
+    sandbox_runner: Arc<ToolsSandboxRunner>,
+    sandbox_user: SandboxUser,
+    cache: Option<Arc<Mutex<Cache>>>,
+) -> Result<(), Error> {
     let (executor_tx, executor_rx) = connect_to_remote_server(&opt.server_addr, 27183)
         .context("Failed to connect to the server")?;
     executor_tx
         .send(RemoteEntityMessage::Welcome {
-            name: name.clone(),
+            name: name.into(),
             version: VERSION.into(),
         })
         .context("Cannot send welcome to the server")?;
@@ -64,20 +144,18 @@ pub fn main_worker(opt: WorkerOpt) -> Result<(), Error> {
         bail!("The server rejected the worker connection: {}", err);
     }
 
-    let name = if let Some(wid) = opt.worker_id {
-        format!("{name} {wid}")
-    } else {
-        name
-    };
-
-    let worker = Worker::new_with_channel(
+    let mut worker = Worker::new_with_channel(
         name,
         file_store,
         sandbox_path,
         executor_tx.change_type(),
         executor_rx.change_type(),
-        Arc::new(ToolsSandboxRunner::default()),
+        sandbox_runner,
+        sandbox_user,
     )
     .context("Failed to start worker")?;
+    if let Some(cache) = cache {
+        worker.set_cache(cache);
+    }
     worker.work()
 }
@@ -1,10 +1,11 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 use task_maker_dag::{
     Execution, ExecutionGroup, ExecutionLimits, ExecutionOutputBehaviour, ExecutionResult,
-    ExecutionStatus, FileUuid,
+    ExecutionStatus, ExecutionTag, FileUuid,
 };
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
@@ -37,6 +38,10 @@ pub struct CacheEntry {
     pub extra_time: f64,
     /// The extra memory for this execution group.
     pub extra_memory: u64,
+    /// When this entry has been inserted or last hit, in seconds since the UNIX epoch.
+    pub last_access: u64,
+    /// The tag of the execution group, if any.
+    pub tag: Option<ExecutionTag>,
 }
 
 impl CacheEntryItem {
@@ -67,6 +72,7 @@ impl CacheEntryItem {
             result: ExecutionResult {
                 stdout: None,
                 stderr: None,
+                cache_hit: None,
                 ..result
             },
             limits: execution.limits.clone(),
@@ -92,9 +98,16 @@ impl CacheEntry {
             items,
             extra_time: group.config.extra_time,
             extra_memory: group.config.extra_memory,
+            last_access: unix_now(),
+            tag: group.tag.clone(),
         }
     }
 
+    /// Mark this entry as accessed now.
+    pub fn touch(&mut self) {
+        self.last_access = unix_now();
+    }
+
     pub fn same_limits(&self, other: &CacheEntry) -> bool {
         if self.items.len() != other.items.len() {
             return false;
@@ -107,6 +120,86 @@ impl CacheEntry {
         true
     }
 
+    /// Checks whether the limits of this entry are exactly the same as the ones of the executions
+    /// in the group, including the extra time and memory.
+    pub fn is_exact(&self, group: &ExecutionGroup) -> bool {
+        self.items.len() == group.executions.len()
+            && self.extra_time == group.config.extra_time
+            && self.extra_memory == group.config.extra_memory
+            && group
+                .executions
+                .iter()
+                .zip(self.items.iter())
+                .all(|(exec, item)| exec.limits == item.limits)
+    }
+
+    /// Describe the limits of the executions in the group that differ from the ones of this entry,
+    /// including the extra time and memory.
+    pub fn limit_differences(&self, group: &ExecutionGroup) -> Vec<String> {
+        fn show<T: std::fmt::Display>(value: Option<T>) -> String {
+            value.map_or_else(|| "unlimited".into(), |v| v.to_string())
+        }
+        let mut differences = vec![];
+        let multiple = group.executions.len() > 1;
+        for (index, (exec, item)) in group.executions.iter().zip(self.items.iter()).enumerate() {
+            let prefix = if multiple {
+                format!("execution {index}: ")
+            } else {
+                String::new()
+            };
+            let (cached, current) = (&item.limits, &exec.limits);
+            macro_rules! compare {
+                ($name:literal, $field:ident) => {
+                    if cached.$field != current.$field {
+                        differences.push(format!(
+                            "{prefix}{} changed from {} to {}",
+                            $name,
+                            show(cached.$field),
+                            show(current.$field)
+                        ));
+                    }
+                };
+            }
+            compare!("cpu time", cpu_time);
+            compare!("sys time", sys_time);
+            compare!("wall time", wall_time);
+            compare!("memory", memory);
+            compare!("stack", stack);
+            compare!("file size", fsize);
+            compare!("open files", nofile);
+            if cached.allow_multiprocess != current.allow_multiprocess
+                || cached.read_only != current.read_only
+                || cached.mount_tmpfs != current.mount_tmpfs
+                || cached.extra_readable_dirs != current.extra_readable_dirs
+            {
+                differences.push(format!("{prefix}the sandbox permissions changed"));
+            }
+        }
+        if self.extra_time != group.config.extra_time {
+            differences.push(format!(
+                "extra time changed from {} to {}",
+                self.extra_time, group.config.extra_time
+            ));
+        }
+        if self.extra_memory != group.config.extra_memory {
+            differences.push(format!(
+                "extra memory changed from {} to {}",
+                self.extra_memory, group.config.extra_memory
+            ));
+        }
+        differences
+    }
+
+    /// The keys of all the files produced by the executions of this entry.
+    pub fn file_keys(&self) -> impl Iterator<Item = &FileStoreKey> {
+        self.items.iter().flat_map(|item| {
+            item.stdout
+                .iter()
+                .chain(item.stderr.iter())
+                .chain(item.outputs.values())
+        })
+    }
+
     /// Search in the file store the handles of all the output files. Will return `None` if at least
     /// one of them is missing.
     pub fn outputs(
@@ -135,25 +228,13 @@ impl CacheEntry {
 
         for (exec, item) in group.executions.iter().zip(self.items.iter()) {
             if let ExecutionOutputBehaviour::Capture { file, .. } = &exec.stdout {
-                if let Some(handle) = try_get!(item.stdout) {
-                    outputs.insert(file.uuid, handle);
-                } else {
-                    return None;
-                }
+                outputs.insert(file.uuid, try_get!(item.stdout)?);
             }
             if let ExecutionOutputBehaviour::Capture { file, .. } = &exec.stderr {
-                if let Some(handle) = try_get!(item.stderr) {
-                    outputs.insert(file.uuid, handle);
-                } else {
-                    return None;
-                }
+                outputs.insert(file.uuid, try_get!(item.stderr)?);
             }
             for (path, file) in exec.output_files.iter() {
-                if let Some(handle) = try_get!(item.outputs.get(path)) {
-                    outputs.insert(file.uuid, handle);
-                } else {
-                    return None;
-                }
+                outputs.insert(file.uuid, try_get!(item.outputs.get(path))?);
             }
         }
         Some(outputs)
@@ -234,6 +315,13 @@ impl CacheEntry {
     }
 }
 
+/// The current time, in seconds since the UNIX epoch.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -269,11 +357,14 @@ mod tests {
                         status: ExecutionStatus::Success,
                         was_killed: false,
                         was_cached: false,
+                        cache_hit: None,
+                        missing_outputs: vec![],
                         resources: ExecutionResourcesUsage {
                             cpu_time: 0.0,
                             sys_time: 0.0,
                             wall_time: 0.0,
                             memory: 0,
+                            final_memory: 0,
                         },
                         stdout: None,
                         stderr: None,
@@ -285,6 +376,8 @@ mod tests {
                 }],
                 extra_time: default_config.extra_time,
                 extra_memory: default_config.extra_memory,
+                last_access: 0,
+                tag: None,
             },
             exec,
         )
@@ -293,7 +386,7 @@ mod tests {
     #[test]
     fn test_outputs_empty() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
         let (entry, exec) = empty_entry();
         assert_eq!(entry.outputs(&store, &exec.into()), Some(HashMap::new()));
     }
@@ -301,7 +394,7 @@ mod tests {
     #[test]
     fn test_outputs_stdout() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
 
         let (mut entry, mut exec) = empty_entry();
         let file = exec.capture_stdout(None);
@@ -317,7 +410,7 @@ mod tests {
     #[test]
     fn test_outputs_stdout_missing() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
 
         let (mut entry, mut exec) = empty_entry();
         exec.capture_stdout(None);
@@ -330,7 +423,7 @@ mod tests {
     #[test]
     fn test_outputs_stderr() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
 
         let (mut entry, mut exec) = empty_entry();
         let file = exec.capture_stderr(None);
@@ -346,7 +439,7 @@ mod tests {
     #[test]
     fn test_outputs_stderr_missing() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
 
         let (mut entry, mut exec) = empty_entry();
         exec.capture_stderr(None);
@@ -359,7 +452,7 @@ mod tests {
     #[test]
     fn test_outputs_file() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
 
         let (mut entry, mut exec) = empty_entry();
         let file = exec.output("file");
@@ -377,7 +470,7 @@ mod tests {
     #[test]
     fn test_outputs_file_missing() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
 
         let (mut entry, mut exec) = empty_entry();
         exec.output("file");
@@ -429,6 +522,19 @@ mod tests {
         assert!(entry.is_compatible(&exec4.into()));
     }
 
+    #[test]
+    fn test_is_exact() {
+        let (mut entry, mut exec1) = empty_entry();
+        exec1.limits.cpu_time = Some(1.0);
+        entry.items[0].limits.cpu_time = Some(1.0);
+        assert!(entry.is_exact(&exec1.into()));
+
+        let mut exec2 = Execution::new("exec", ExecutionCommand::local("foo"));
+        exec2.limits.cpu_time = Some(2.0);
+        assert!(entry.is_compatible(&exec2.clone().into()));
+        assert!(!entry.is_exact(&exec2.into()));
+    }
+
     #[test]
     fn test_compatible_success_read_only() {
         let (mut entry, mut exec1) = empty_entry();
@@ -24,6 +24,11 @@ struct CacheKeyItem {
     pub inputs: Vec<(PathBuf, FileStoreKey, bool)>,
     /// The list of environment variables to set. Sorted by the variable name.
     pub env: Vec<(String, String)>,
+    /// The paths of the output files inside the sandbox, sorted. An execution that declares a
+    /// different set of output files may write different files, so it's a different key.
+    pub outputs: Vec<PathBuf>,
+    /// Whether the outputs are normalized as text files, since it changes their content.
+    pub normalize_outputs: bool,
 }
 
 /// The cache key used to address the cache entries. It is composed by a key item for each execution
@@ -54,6 +59,12 @@ impl CacheKeyItem {
             .sorted()
             .collect_vec();
         let env = execution.env.clone().into_iter().sorted().collect_vec();
+        let outputs = execution
+            .output_files
+            .keys()
+            .cloned()
+            .sorted()
+            .collect_vec();
         let args = if let Some(group) = group {
             let mut fifos = HashMap::new();
             for (i, fifo) in group.fifo.iter().enumerate() {
@@ -78,6 +89,8 @@ impl CacheKeyItem {
             stdin,
             inputs,
             env,
+            outputs,
+            normalize_outputs: execution.normalize_outputs,
         }
     }
 }
@@ -97,6 +110,94 @@ impl CacheKey {
                 .collect(),
         }
     }
+
+    /// Describe how this key differs from `other`, the key of a cached execution. Returns `None` if
+    /// `other` is not a near-match of this key, i.e. it doesn't run the same commands: in that case
+    /// the keys are not comparable.
+    pub fn differences(&self, other: &CacheKey) -> Option<Vec<String>> {
+        if self.items.len() != other.items.len() {
+            return None;
+        }
+        let mut differences = vec![];
+        for (index, (item, cached)) in self.items.iter().zip(other.items.iter()).enumerate() {
+            if item.command != cached.command {
+                return None;
+            }
+            let prefix = if self.items.len() > 1 {
+                format!("execution {index}: ")
+            } else {
+                String::new()
+            };
+            for difference in item.differences(cached) {
+                differences.push(format!("{prefix}{difference}"));
+            }
+        }
+        Some(differences)
+    }
+}
+
+impl CacheKeyItem {
+    /// Describe how this item differs from the `cached` one, that runs the same command.
+    fn differences(&self, cached: &CacheKeyItem) -> Vec<String> {
+        let mut differences = vec![];
+        if self.args != cached.args {
+            differences.push(format!(
+                "the arguments changed from `{}` to `{}`",
+                cached.args.join(" "),
+                self.args.join(" ")
+            ));
+        }
+        match (&cached.stdin, &self.stdin) {
+            (Some(cached), Some(stdin)) if cached != stdin => {
+                differences.push("the stdin changed hash".into())
+            }
+            (None, Some(_)) => differences.push("the stdin is now provided".into()),
+            (Some(_), None) => differences.push("the stdin is not provided anymore".into()),
+            _ => {}
+        }
+        let cached_inputs: HashMap<_, _> = cached
+            .inputs
+            .iter()
+            .map(|(path, key, executable)| (path, (key, executable)))
+            .collect();
+        for (path, key, executable) in &self.inputs {
+            match cached_inputs.get(path) {
+                None => differences.push(format!("the input file {} is new", path.display())),
+                Some((cached_key, _)) if *cached_key != key => {
+                    differences.push(format!("the input file {} changed hash", path.display()))
+                }
+                Some((_, cached_executable)) if *cached_executable != executable => differences
+                    .push(format!(
+                        "the input file {} changed executable flag",
+                        path.display()
+                    )),
+                _ => {}
+            }
+        }
+        for (path, _, _) in &cached.inputs {
+            if !self.inputs.iter().any(|(p, _, _)| p == path) {
+                differences.push(format!("the input file {} is gone", path.display()));
+            }
+        }
+        if self.env != cached.env {
+            let changed = self
+                .env
+                .iter()
+                .chain(cached.env.iter())
+                .filter(|var| !self.env.contains(var) || !cached.env.contains(var))
+                .map(|(name, _)| name)
+                .unique()
+                .join(", ");
+            differences.push(format!("the environment variables changed: {changed}"));
+        }
+        if self.outputs != cached.outputs {
+            differences.push("the set of output files changed".into());
+        }
+        if self.normalize_outputs != cached.normalize_outputs {
+            differences.push("the normalization of the output files changed".into());
+        }
+        differences
+    }
 }
 
 #[cfg(test)]
@@ -171,7 +272,7 @@ mod tests {
     #[test]
     fn test_stdin() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
         let handle1 = fake_file(tmpdir.path().join("file1"), "foo", &store);
         let handle2 = fake_file(tmpdir.path().join("file2"), "bar", &store);
         let file1 = task_maker_dag::File::new("file1");
@@ -202,7 +303,7 @@ mod tests {
     #[test]
     fn test_inputs() {
         let tmpdir = tempfile::TempDir::new().unwrap();
-        let store = FileStore::new(tmpdir.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
         let handle1 = fake_file(tmpdir.path().join("file1"), "foo", &store);
         let handle2 = fake_file(tmpdir.path().join("file2"), "bar", &store);
         let file1 = task_maker_dag::File::new("file1");
@@ -259,6 +360,25 @@ mod tests {
         assert_ne!(hash(&key1), hash(&key4));
     }
 
+    #[test]
+    fn test_outputs() {
+        let mut exec1 = Execution::new("exec1", ExecutionCommand::local("foo"));
+        exec1.output("foo");
+        exec1.output("bar");
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::local("foo"));
+        exec2.output("bar");
+        exec2.output("foo");
+        let mut exec3 = Execution::new("exec3", ExecutionCommand::local("foo"));
+        exec3.output("foo");
+        let key1 = CacheKeyItem::from_execution(&exec1, &HashMap::new(), None);
+        let key2 = CacheKeyItem::from_execution(&exec2, &HashMap::new(), None);
+        let key3 = CacheKeyItem::from_execution(&exec3, &HashMap::new(), None);
+        assert_eq!(key1, key2);
+        assert_ne!(key1, key3);
+        assert_eq!(hash(&key1), hash(&key2));
+        assert_ne!(hash(&key1), hash(&key3));
+    }
+
     #[test]
     fn test_fifo_arg_replace() {
         let mut group = ExecutionGroup::new("group");
@@ -303,4 +423,37 @@ mod tests {
         let key2 = CacheKey::from_execution_group(&group2, &HashMap::new());
         assert_eq!(key1, key2);
     }
+
+    #[test]
+    fn test_differences() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(tmpdir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
+        let handle1 = fake_file(tmpdir.path().join("file1"), "foo", &store);
+        let handle2 = fake_file(tmpdir.path().join("file2"), "bar", &store);
+        let file1 = task_maker_dag::File::new("file1");
+        let file2 = task_maker_dag::File::new("file2");
+        let map: HashMap<_, _> = [(file1.uuid, handle1), (file2.uuid, handle2)]
+            .iter()
+            .cloned()
+            .collect();
+        let key = |exec: Execution| CacheKey::from_execution_group(&exec.into(), &map);
+
+        let mut exec1 = Execution::new("exec1", ExecutionCommand::local("foo"));
+        exec1.input(file1.uuid, "input", false);
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::local("foo"));
+        exec2.input(file2.uuid, "input", false);
+        exec2.args(vec!["bar"]);
+        let exec3 = Execution::new("exec3", ExecutionCommand::local("bar"));
+
+        let (key1, key2, key3) = (key(exec1), key(exec2), key(exec3));
+        assert_eq!(key1.differences(&key1), Some(vec![]));
+        assert_eq!(
+            key2.differences(&key1),
+            Some(vec![
+                "the arguments changed from `` to `bar`".to_string(),
+                "the input file input changed hash".to_string()
+            ])
+        );
+        assert_eq!(key1.differences(&key3), None);
+    }
 }
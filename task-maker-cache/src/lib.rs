@@ -17,6 +17,17 @@
 //! than the corresponding one in `L2`. If a limit is not present, its value is assumed to be
 //! _infinite_.
 //!
+//! A remote cache, shared between multiple machines, can be added with `Cache::set_remote`: it is
+//! consulted only when the local cache misses, and its hits are copied in the local cache. Since
+//! the remote lookups are slow, they can be run in another thread with `Cache::remote_lookup`.
+//!
+//! The entries not used for a while, for example the ones of solutions that no longer exist, can be
+//! removed with `Cache::prune`, while `Cache::compact` removes the entries whose files are no
+//! longer in the `FileStore`.
+//!
+//! More processes can share the same cache directory: the cache file is locked while it's read or
+//! written, and the entries stored by the other processes are merged in when the cache is stored.
+//!
 //! # Example
 //!
 //! ```
@@ -24,12 +35,12 @@
 //! use task_maker_cache::{Cache, CacheResult};
 //! use std::collections::HashMap;
 //! use task_maker_dag::{Execution, ExecutionCommand, ExecutionResult, ExecutionStatus, ExecutionResourcesUsage, File};
-//! use task_maker_store::{FileStore, FileStoreKey, ReadFileIterator};
+//! use task_maker_store::{FileStore, FileStoreKey, ReadFileIterator, DEFAULT_BLOCK_SIZE};
 //!
 //! // make a new store and a new cache in a testing environment
 //! let dir = TempDir::new().unwrap();
 //! let mut cache = Cache::new(dir.path()).expect("Cannot create the cache");
-//! let mut store = FileStore::new(dir.path(), 1000, 1000).expect("Cannot create the store");
+//! let mut store = FileStore::new(dir.path(), 1000, 1000, Default::default(), None, DEFAULT_BLOCK_SIZE).expect("Cannot create the store");
 //!
 //! // setup a testing file
 //! let path = dir.path().join("file.txt");
@@ -47,10 +58,13 @@
 //!         cpu_time: 1.123,
 //!         sys_time: 0.2,
 //!         wall_time: 1.5,
-//!         memory: 12345
+//!         memory: 12345,
+//!         final_memory: 12345,
 //!     },
 //!     was_killed: false,
 //!     was_cached: false,
+//!     cache_hit: None,
+//!     missing_outputs: vec![],
 //!     stderr: None,
 //!     stdout: None,
 //! };
@@ -67,7 +81,7 @@
 //! let res = cache.get(&exec.into(), &file_keys, &mut store);
 //! match res {
 //!     CacheResult::Miss => panic!("Expecting a hit"),
-//!     CacheResult::Hit { result, outputs } => {
+//!     CacheResult::Hit { result, outputs, .. } => {
 //!         assert_eq!(result[0].status, ExecutionStatus::Success);
 //!         assert_eq!(result[0].resources.memory, 12345);
 //!     }
@@ -82,19 +96,29 @@ extern crate log;
 
 mod entry;
 mod key;
+mod remote;
 mod storage;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs::create_dir_all;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Error};
 use entry::CacheEntry;
 use itertools::Itertools;
 use key::CacheKey;
+use remote::RemoteCache;
+pub use remote::{
+    DirectoryRemoteCache, RemoteCacheBackend, RemoteEntries, RemoteLookup, RemotePublish,
+    MAX_CONSECUTIVE_TIMEOUTS,
+};
+use serde::{Deserialize, Serialize};
 use storage::CacheFile;
+pub use task_maker_dag::CacheHitReason;
 use task_maker_dag::{ExecutionGroup, ExecutionResult, ExecutionStatus, FileUuid};
-use task_maker_store::{FileStore, FileStoreHandle};
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
 /// The name of the file which holds the cache data.
 const CACHE_FILE: &str = "cache.bin";
@@ -104,6 +128,13 @@ const CACHE_FILE: &str = "cache.bin";
 pub struct Cache {
     /// Cache entries.
     file: CacheFile,
+    /// The remote cache to consult when an execution is not in the local cache, if any.
+    remote: Option<RemoteCache>,
+    /// The last key whose compatible entries have been evicted because their outputs were gone,
+    /// so that the miss that followed can still be explained after the eviction.
+    outputs_gone: Option<CacheKey>,
+    /// The outcomes of the queries made to this cache.
+    stats: CacheStats,
 }
 
 /// The result of a cache query, can be either successful (`Hit`) or unsuccessful (`Miss`).
@@ -116,9 +147,73 @@ pub enum CacheResult {
         result: Vec<ExecutionResult>,
         /// The outputs of the execution.
         outputs: HashMap<FileUuid, FileStoreHandle>,
+        /// Why the cached entry has been considered valid for the query.
+        reason: CacheHitReason,
     },
 }
 
+/// The outcome of [`Cache::prune`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruneStats {
+    /// The number of entries removed from the cache.
+    pub removed: usize,
+    /// The number of entries still in the cache.
+    pub kept: usize,
+}
+
+/// The outcome of [`Cache::compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactStats {
+    /// The number of entries removed from the cache.
+    pub removed: usize,
+    /// The number of entries still in the cache.
+    pub kept: usize,
+    /// The size of the cache file before the compaction, in bytes.
+    pub size_before: u64,
+    /// The size of the cache file after the compaction, in bytes.
+    pub size_after: u64,
+}
+
+/// The number of hits and misses of the queries made to the cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// The number of queries that found a valid entry, including the ones in
+    /// `hits_extended_limits`.
+    pub hits: u64,
+    /// The number of queries that didn't find a valid entry.
+    pub misses: u64,
+    /// The number of hits of an entry with different limits, that are compatible with the ones of
+    /// the query following the rules described in the crate documentation.
+    pub hits_extended_limits: u64,
+}
+
+/// How many entries of the cache have a given tag, computed by [`Cache::tag_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheTagStats {
+    /// The number of entries with the tag.
+    pub entries: usize,
+    /// The space used in the `FileStore` by the files referenced by the entries with the tag. A
+    /// file referenced by more entries is counted once.
+    pub bytes: u64,
+}
+
+/// Why an execution is not in the cache, computed by [`Cache::explain_miss`]. This is only
+/// informative and it's meant to debug unexpected cache misses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheMissReason {
+    /// There is no cached execution running the same commands: the execution is new.
+    NoNearMatch,
+    /// The closest cached execution runs the same commands, but its key differs in the listed
+    /// components (e.g. the hash of an input file).
+    DifferentKey(Vec<String>),
+    /// There are cached executions with the same key, but their limits are not compatible with
+    /// the ones of the query. The differences with the limits of the closest one are listed.
+    IncompatibleLimits(Vec<String>),
+    /// There is a compatible cached execution, but some of its output files are not in the file
+    /// store anymore.
+    OutputsGone,
+}
+
 impl Cache {
     /// Make a new `Cache` stored in the specified cache directory. Returns an error if the cache
     /// directory cannot be created.
@@ -129,7 +224,92 @@ impl Cache {
         })?;
         let path = cache_dir.join(CACHE_FILE);
         let file = CacheFile::load(path).context("Failed to load cache file")?;
-        Ok(Self { file })
+        Ok(Self {
+            file,
+            remote: None,
+            outputs_gone: None,
+            stats: CacheStats::default(),
+        })
+    }
+
+    /// Make a new `Cache` like [`Cache::new`], but keeping at most `max_entries` entries. When the
+    /// limit is exceeded the least recently used entries are evicted.
+    pub fn with_max_entries<P: Into<PathBuf>>(
+        cache_dir: P,
+        max_entries: usize,
+    ) -> Result<Cache, Error> {
+        let mut cache = Self::new(cache_dir)?;
+        cache.file.set_max_entries(Some(max_entries));
+        Ok(cache)
+    }
+
+    /// Consult also a remote cache when an execution is not present in the local one. The entries
+    /// found remotely, and the files they reference, are copied locally.
+    ///
+    /// A remote lookup that takes longer than `timeout` is considered a miss, and after
+    /// [`MAX_CONSECUTIVE_TIMEOUTS`] of them in a row the remote cache is no longer used.
+    pub fn set_remote(&mut self, backend: Arc<dyn RemoteCacheBackend>, timeout: Duration) {
+        self.remote = Some(RemoteCache::new(backend, timeout));
+    }
+
+    /// Whether a remote cache is set.
+    pub fn has_remote(&self) -> bool {
+        self.remote.is_some()
+    }
+
+    /// Remove from the cache all the entries not inserted nor hit in the last `max_age`, storing
+    /// the pruned cache to disk.
+    pub fn prune(&mut self, max_age: Duration) -> Result<PruneStats, Error> {
+        let min_last_access = entry::unix_now().saturating_sub(max_age.as_secs());
+        let (removed, kept) = self.file.prune(min_last_access);
+        self.file
+            .store()
+            .context("Failed to store the pruned cache")?;
+        Ok(PruneStats { removed, kept })
+    }
+
+    /// Remove from the cache the entries referencing files no longer in the `FileStore`, and the
+    /// duplicated entries, rewriting the cache file to reclaim their space. The file is replaced
+    /// atomically, so an interrupted compaction leaves the previous file intact.
+    pub fn compact(&mut self, file_store: &FileStore) -> Result<CompactStats, Error> {
+        let size_before = self.file.disk_size();
+        let (removed, kept) = self
+            .file
+            .compact(|key| file_store.stored_size(key).is_some());
+        // Rewrite the file even if no entry has been removed, it may have been written with an
+        // older format.
+        self.file.mark_dirty();
+        self.file
+            .store()
+            .context("Failed to store the compacted cache")?;
+        Ok(CompactStats {
+            removed,
+            kept,
+            size_before,
+            size_after: self.file.disk_size(),
+        })
+    }
+
+    /// Count the entries of the cache and the size of the files they reference, grouped by the name
+    /// of the tag of their executions. The entries without a tag are counted as `"unknown"`.
+    pub fn tag_stats(&self, file_store: &FileStore) -> BTreeMap<String, CacheTagStats> {
+        let mut stats: BTreeMap<String, CacheTagStats> = BTreeMap::new();
+        let mut counted: HashMap<String, HashSet<&FileStoreKey>> = HashMap::new();
+        for entry in self.file.entries() {
+            let tag = entry
+                .tag
+                .as_ref()
+                .map_or_else(|| "unknown".to_string(), |tag| tag.name.clone());
+            let counted = counted.entry(tag.clone()).or_default();
+            let stats = stats.entry(tag).or_default();
+            stats.entries += 1;
+            for key in entry.file_keys() {
+                if counted.insert(key) {
+                    stats.bytes += file_store.stored_size(key).unwrap_or(0);
+                }
+            }
+        }
+        stats
     }
 
     /// Insert a new entry inside the cache. They key is computed based on the execution's metadata
@@ -142,16 +322,8 @@ impl Cache {
         result: Vec<ExecutionResult>,
     ) {
         let key = CacheKey::from_execution_group(group, file_keys);
-        let set = self.file.entry(key).or_default();
         let entry = CacheEntry::from_execution_group(group, file_keys, result);
-        // Do not insert duplicated keys, replace if the limits are the same.
-        let pos = set.iter().find_position(|e| e.same_limits(&entry));
-        if let Some((pos, _)) = pos {
-            set[pos] = entry;
-        } else {
-            set.push(entry);
-        }
-        self.file.mark_dirty();
+        self.file.insert(key, entry);
     }
 
     /// Search in the cache for a valid entry, returning a cache hit if it's found or a cache miss
@@ -159,6 +331,9 @@ impl Cache {
     ///
     /// The result contains the handles to the files in the `FileStore`, preventing the flushing
     /// from erasing them.
+    ///
+    /// If a remote cache is set, it is consulted on a local miss, populating the local cache. This
+    /// blocks until the remote lookup completes, or its timeout expires.
     pub fn get(
         &mut self,
         group: &ExecutionGroup,
@@ -166,50 +341,251 @@ impl Cache {
         file_store: &FileStore,
     ) -> CacheResult {
         let key = CacheKey::from_execution_group(group, file_keys);
-        let entry = self.file.entry(key);
-        let entry = match &entry {
-            Entry::Vacant(_) => return CacheResult::Miss,
-            Entry::Occupied(entry) => entry.get(),
+        self.outputs_gone = None;
+        let result = self.get_local_key(&key, group, file_store);
+        let result = match (result, self.remote_lookup(group, file_keys)) {
+            (CacheResult::Miss, Some(lookup)) => {
+                let remote = lookup.run(file_store);
+                self.add_remote_entries(&key, group, file_store, remote)
+            }
+            (result, _) => result,
+        };
+        self.stats.record(&result);
+        result
+    }
+
+    /// Search in the local cache for a valid entry, like [`Cache::get`] but without consulting
+    /// the remote cache. If a remote cache is set, a miss is not counted in the stats: it's counted
+    /// by [`Cache::get_with_remote`] after the remote lookup.
+    pub fn get_local(
+        &mut self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        file_store: &FileStore,
+    ) -> CacheResult {
+        let key = CacheKey::from_execution_group(group, file_keys);
+        self.outputs_gone = None;
+        let result = self.get_local_key(&key, group, file_store);
+        if self.remote.is_none() || matches!(result, CacheResult::Hit { .. }) {
+            self.stats.record(&result);
+        }
+        result
+    }
+
+    /// Prepare the lookup of an execution group in the remote cache, to run after a miss of
+    /// [`Cache::get_local`]. Returns `None` if there is no remote cache.
+    pub fn remote_lookup(
+        &self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+    ) -> Option<RemoteLookup> {
+        let remote = self.remote.clone()?;
+        Some(RemoteLookup {
+            remote,
+            key: CacheKey::from_execution_group(group, file_keys),
+        })
+    }
+
+    /// Copy in the local cache the entries found by the remote lookup of the execution group, and
+    /// search it again in the local cache.
+    pub fn get_with_remote(
+        &mut self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        file_store: &FileStore,
+        remote: RemoteEntries,
+    ) -> CacheResult {
+        let key = CacheKey::from_execution_group(group, file_keys);
+        let result = self.add_remote_entries(&key, group, file_store, remote);
+        self.stats.record(&result);
+        result
+    }
+
+    /// Prepare the publication to the remote cache of the local entries of an execution group,
+    /// usually just inserted. Returns `None` if there is no remote cache or no local entry.
+    pub fn remote_publish(
+        &self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        file_store: &FileStore,
+    ) -> Option<RemotePublish> {
+        let remote = self.remote.clone()?;
+        let key = CacheKey::from_execution_group(group, file_keys);
+        let entries = self.file.peek(&key)?;
+        let files = entries
+            .iter()
+            .flat_map(|entry| entry.file_keys())
+            .unique()
+            .filter_map(|key| file_store.get(key))
+            .collect();
+        let serialized_key = match bincode::serialize(&key) {
+            Ok(key) => key,
+            Err(e) => {
+                warn!("Failed to serialize cache key: {e:?}");
+                return None;
+            }
+        };
+        let entries = match bincode::serialize(entries) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to serialize cache entries: {e:?}");
+                return None;
+            }
+        };
+        Some(RemotePublish {
+            remote,
+            key: serialized_key,
+            entries,
+            files,
+        })
+    }
+
+    /// The number of hits and misses of the queries made to this cache since it was loaded.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Add the entries found by a remote lookup to the local cache, and search the key again.
+    fn add_remote_entries(
+        &mut self,
+        key: &CacheKey,
+        group: &ExecutionGroup,
+        file_store: &FileStore,
+        remote: RemoteEntries,
+    ) -> CacheResult {
+        if remote.key != *key || remote.entries.is_empty() {
+            return CacheResult::Miss;
+        }
+        for entry in remote.entries {
+            self.file.insert(key.clone(), entry);
+        }
+        // the handles keep the fetched files in the store until the local lookup is done
+        let result = self.get_local_key(key, group, file_store);
+        drop(remote.handles);
+        result
+    }
+
+    /// Search in the local cache for a valid entry of the key.
+    fn get_local_key(
+        &mut self,
+        key: &CacheKey,
+        group: &ExecutionGroup,
+        file_store: &FileStore,
+    ) -> CacheResult {
+        let Some(entries) = self.file.get_mut(key) else {
+            return CacheResult::Miss;
         };
 
-        for entry in entry.iter() {
+        // the entries whose outputs are not in the store anymore will never be hit again
+        let mut stale = Vec::new();
+        let mut stale_compatible = false;
+        let mut hit = None;
+        for (index, entry) in entries.iter_mut().enumerate() {
             match entry.outputs(file_store, group) {
                 None => {
-                    // TODO: remove the entry because it's not valid anymore
+                    stale.push(index);
+                    stale_compatible |= entry.is_compatible(group);
                 }
                 Some(outputs) if entry.is_compatible(group) => {
+                    let reason = if entry.is_exact(group) {
+                        CacheHitReason::Exact
+                    } else {
+                        CacheHitReason::LimitExtended
+                    };
                     let mut results = Vec::new();
                     for (exec, item) in group.executions.iter().zip(entry.items.iter()) {
                         let stdout = item.stdout.as_ref().and_then(|key| {
                             file_store
                                 .get(key)
-                                .and_then(|handle| std::fs::read(handle.path()).ok())
+                                .and_then(|handle| handle.read_all().ok())
                         });
 
                         let stderr = item.stderr.as_ref().and_then(|key| {
                             file_store
                                 .get(key)
-                                .and_then(|handle| std::fs::read(handle.path()).ok())
+                                .and_then(|handle| handle.read_all().ok())
                         });
 
                         results.push(ExecutionResult {
                             status: exec.status(&item.result.status, &item.result.resources),
                             was_killed: item.result.was_killed,
                             was_cached: true,
+                            cache_hit: Some(reason),
+                            missing_outputs: item.result.missing_outputs.clone(),
                             resources: item.result.resources.clone(),
                             stdout,
                             stderr,
                         });
                     }
-                    return CacheResult::Hit {
+                    entry.touch();
+                    hit = Some(CacheResult::Hit {
                         result: results,
                         outputs,
-                    };
+                        reason,
+                    });
+                    break;
                 }
                 _ => {}
             }
         }
-        CacheResult::Miss
+        if hit.is_some() {
+            // the access time of the entry has changed
+            self.file.mark_dirty();
+        }
+        if !stale.is_empty() {
+            debug!(
+                "Evicting {} cache entries of {} whose outputs are gone",
+                stale.len(),
+                group.description
+            );
+            self.file.remove_entries(key, &stale);
+            if hit.is_none() && stale_compatible {
+                self.outputs_gone = Some(key.clone());
+            }
+        }
+        hit.unwrap_or(CacheResult::Miss)
+    }
+
+    /// Explain why the execution group is not in the local cache. The closest cached entry is
+    /// searched among the ones running the same commands, reporting the components that differ.
+    ///
+    /// This scans the whole cache, so it's meant to be used only for debugging the cache misses.
+    pub fn explain_miss(
+        &self,
+        group: &ExecutionGroup,
+        file_keys: &HashMap<FileUuid, FileStoreHandle>,
+        file_store: &FileStore,
+    ) -> CacheMissReason {
+        let key = CacheKey::from_execution_group(group, file_keys);
+        if self.outputs_gone.as_ref() == Some(&key) {
+            return CacheMissReason::OutputsGone;
+        }
+        if let Some(entries) = self.file.peek(&key) {
+            let compatible = entries
+                .iter()
+                .filter(|entry| entry.is_compatible(group))
+                .collect_vec();
+            if compatible.is_empty() {
+                let closest = entries
+                    .iter()
+                    .map(|entry| entry.limit_differences(group))
+                    .min_by_key(|differences| differences.len());
+                if let Some(differences) = closest {
+                    return CacheMissReason::IncompatibleLimits(differences);
+                }
+            } else if compatible
+                .iter()
+                .all(|entry| entry.outputs(file_store, group).is_none())
+            {
+                return CacheMissReason::OutputsGone;
+            }
+        }
+        self.file
+            .keys()
+            .filter_map(|cached| key.differences(cached))
+            .filter(|differences| !differences.is_empty())
+            .min_by_key(|differences| differences.len())
+            .map_or(CacheMissReason::NoNearMatch, CacheMissReason::DifferentKey)
     }
 
     /// Checks whether a result is allowed in the cache.
@@ -218,6 +594,57 @@ impl Cache {
     }
 }
 
+impl CacheStats {
+    /// Count the outcome of a query.
+    pub fn record(&mut self, result: &CacheResult) {
+        match result {
+            CacheResult::Miss => self.misses += 1,
+            CacheResult::Hit { reason, .. } => {
+                self.hits += 1;
+                if *reason == CacheHitReason::LimitExtended {
+                    self.hits_extended_limits += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} hits", self.hits)?;
+        if self.hits_extended_limits > 0 {
+            write!(f, " ({} with extended limits)", self.hits_extended_limits)?;
+        }
+        write!(f, ", {} misses", self.misses)
+    }
+}
+
+impl Display for CacheMissReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheMissReason::NoNearMatch => {
+                write!(f, "no cached execution runs the same commands")
+            }
+            CacheMissReason::DifferentKey(differences) => write!(
+                f,
+                "the closest cached execution differs: {}",
+                differences.join(", ")
+            ),
+            CacheMissReason::IncompatibleLimits(differences) if differences.is_empty() => {
+                write!(f, "the cached execution has incompatible limits")
+            }
+            CacheMissReason::IncompatibleLimits(differences) => write!(
+                f,
+                "the cached execution has incompatible limits: {}",
+                differences.join(", ")
+            ),
+            CacheMissReason::OutputsGone => {
+                write!(f, "the output files of the cached execution are gone")
+            }
+        }
+    }
+}
+
 impl Drop for Cache {
     fn drop(&mut self) {
         if let Err(e) = self.file.store() {
@@ -225,3 +652,243 @@ impl Drop for Cache {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionTag};
+
+    use super::*;
+
+    #[test]
+    fn test_evict_entry_with_outputs_gone() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(
+            tmpdir.path().join("store"),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let stdout = exec.capture_stdout(None);
+        let group: ExecutionGroup = exec.into();
+
+        let handle = store
+            .store(&FileStoreKey::from_content(b"hello"), [b"hello".to_vec()])
+            .unwrap();
+        let path = handle.path().to_owned();
+        let mut file_keys = HashMap::new();
+        file_keys.insert(stdout.uuid, handle);
+        cache.insert(&group, &file_keys, vec![ExecutionResult::default()]);
+        drop(file_keys);
+        let key = CacheKey::from_execution_group(&group, &HashMap::new());
+        assert!(cache.file.peek(&key).is_some());
+
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Miss
+        ));
+        assert!(cache.file.peek(&key).is_none());
+        assert_eq!(
+            cache.explain_miss(&group, &HashMap::new(), &store),
+            CacheMissReason::OutputsGone
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut cache = Cache::new(tmpdir.path()).unwrap();
+        let group: ExecutionGroup = Execution::new("exec", ExecutionCommand::system("true")).into();
+        cache.insert(&group, &HashMap::new(), vec![ExecutionResult::default()]);
+        let key = CacheKey::from_execution_group(&group, &HashMap::new());
+
+        let stats = cache.prune(Duration::from_secs(3600)).unwrap();
+        assert_eq!(
+            stats,
+            PruneStats {
+                removed: 0,
+                kept: 1
+            }
+        );
+
+        cache.file.get_mut(&key).unwrap()[0].last_access -= 7200;
+        let stats = cache.prune(Duration::from_secs(3600)).unwrap();
+        assert_eq!(
+            stats,
+            PruneStats {
+                removed: 1,
+                kept: 0
+            }
+        );
+        drop(cache);
+
+        let cache = Cache::new(tmpdir.path()).unwrap();
+        assert!(cache.file.peek(&key).is_none());
+    }
+
+    #[test]
+    fn test_compact() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(
+            tmpdir.path().join("store"),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let content = b"hello".to_vec();
+        let handle = store
+            .store(&FileStoreKey::from_content(&content), [content])
+            .unwrap();
+        let mut keys = vec![];
+        for command in ["foo", "bar"] {
+            let mut exec = Execution::new("exec", ExecutionCommand::system(command));
+            let stdout = exec.capture_stdout(None);
+            let group: ExecutionGroup = exec.into();
+            let file_keys = HashMap::from([(stdout.uuid, handle.clone())]);
+            cache.insert(&group, &file_keys, vec![ExecutionResult::default()]);
+            keys.push(CacheKey::from_execution_group(&group, &file_keys));
+        }
+        let missing = FileStoreKey::from_content(b"missing");
+        cache.file.get_mut(&keys[1]).unwrap()[0].items[0].stdout = Some(missing);
+        cache.file.store().unwrap();
+
+        let stats = cache.compact(&store).unwrap();
+        assert_eq!((stats.removed, stats.kept), (1, 1));
+        assert!(stats.size_after < stats.size_before);
+        drop(cache);
+
+        let cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        assert!(cache.file.peek(&keys[0]).is_some());
+        assert!(cache.file.peek(&keys[1]).is_none());
+    }
+
+    #[test]
+    fn test_tag_stats() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(
+            tmpdir.path().join("store"),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let content = b"hello".to_vec();
+        let handle = store
+            .store(&FileStoreKey::from_content(&content), [content])
+            .unwrap();
+        for command in ["foo", "bar", "baz"] {
+            let mut exec = Execution::new("exec", ExecutionCommand::system(command));
+            let stdout = exec.capture_stdout(None);
+            let mut group: ExecutionGroup = exec.into();
+            if command != "baz" {
+                group.tag = Some(ExecutionTag::from("generation"));
+            }
+            let file_keys = HashMap::from([(stdout.uuid, handle.clone())]);
+            cache.insert(&group, &file_keys, vec![ExecutionResult::default()]);
+        }
+
+        let stats = cache.tag_stats(&store);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats["generation"],
+            CacheTagStats {
+                entries: 2,
+                bytes: 5
+            }
+        );
+        assert_eq!(
+            stats["unknown"],
+            CacheTagStats {
+                entries: 1,
+                bytes: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(
+            tmpdir.path().join("store"),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let group: ExecutionGroup = Execution::new("exec", ExecutionCommand::system("true")).into();
+
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Miss
+        ));
+        cache.insert(&group, &HashMap::new(), vec![ExecutionResult::default()]);
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Hit { .. }
+        ));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                hits_extended_limits: 0
+            }
+        );
+        assert_eq!(cache.stats().to_string(), "1 hits, 1 misses");
+    }
+
+    #[test]
+    fn test_cache_hit_reason() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = FileStore::new(
+            tmpdir.path().join("store"),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap();
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        exec.limits.cpu_time = Some(1.0);
+        cache.insert(
+            &exec.clone().into(),
+            &HashMap::new(),
+            vec![ExecutionResult::default()],
+        );
+
+        let hit_reason = |cache: &mut Cache, exec: &Execution| match cache.get(
+            &exec.clone().into(),
+            &HashMap::new(),
+            &store,
+        ) {
+            CacheResult::Hit { result, reason, .. } => {
+                assert_eq!(result[0].cache_hit, Some(reason));
+                Some(reason)
+            }
+            CacheResult::Miss => None,
+        };
+        assert_eq!(hit_reason(&mut cache, &exec), Some(CacheHitReason::Exact));
+        exec.limits.cpu_time = Some(2.0);
+        assert_eq!(
+            hit_reason(&mut cache, &exec),
+            Some(CacheHitReason::LimitExtended)
+        );
+    }
+}
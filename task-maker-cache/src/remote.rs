@@ -0,0 +1,492 @@
+use std::fmt::{Debug, Formatter};
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Error};
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
+
+use crate::entry::CacheEntry;
+use crate::key::CacheKey;
+
+/// The number of lookups in a row that have to time out before the remote cache is no longer used.
+pub const MAX_CONSECUTIVE_TIMEOUTS: usize = 5;
+
+/// A remote cache shared between multiple machines, consulted when an execution is not present in
+/// the local cache.
+///
+/// The cache keys and entries are exchanged serialized, so the backend does not need to know their
+/// format: it only has to store the blobs and the content of the files referenced by the entries.
+///
+/// The requests are not interrupted when the timeout of the lookup expires, so the backends that
+/// can hang, like the network ones, should bound the duration of each request on their own.
+pub trait RemoteCacheBackend: Send + Sync {
+    /// Get the serialized entries associated with the serialized cache key, if any.
+    fn get_entries(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Get the content of the file with the specified key, if any.
+    fn get_file(&self, key: &FileStoreKey) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Store the serialized entries associated with the serialized cache key, replacing the
+    /// previous ones. The read-only backends can keep the default implementation, which does
+    /// nothing.
+    fn put_entries(&self, _key: &[u8], _entries: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Store the content of the file with the specified key. The read-only backends can keep the
+    /// default implementation, which does nothing.
+    fn put_file(&self, _key: &FileStoreKey, _content: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A remote cache kept in a directory, usually on a network file system shared between the
+/// machines. The entries are stored in the `entries` subdirectory, named after the hash of their
+/// key, and the files in the `files` subdirectory, named after their key.
+#[derive(Debug, Clone)]
+pub struct DirectoryRemoteCache {
+    /// The directory of the remote cache.
+    path: PathBuf,
+}
+
+impl DirectoryRemoteCache {
+    /// Make a new `DirectoryRemoteCache` in the specified directory, creating it if needed.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Result<DirectoryRemoteCache, Error> {
+        let path = path.into();
+        for dir in ["entries", "files"] {
+            create_dir_all(path.join(dir)).with_context(|| {
+                format!("Failed to create remote cache directory {}", path.display())
+            })?;
+        }
+        Ok(DirectoryRemoteCache { path })
+    }
+
+    /// The path of the file with the entries of the serialized key.
+    fn entries_path(&self, key: &[u8]) -> PathBuf {
+        self.path
+            .join("entries")
+            .join(FileStoreKey::from_content(key).to_string())
+    }
+
+    /// The path of the file with the specified key.
+    fn file_path(&self, key: &FileStoreKey) -> PathBuf {
+        self.path.join("files").join(key.to_string())
+    }
+}
+
+/// Read a file, returning `None` if it doesn't exist.
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Write a file atomically, so that the other machines never read it partially written.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+impl RemoteCacheBackend for DirectoryRemoteCache {
+    fn get_entries(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        read_if_exists(&self.entries_path(key))
+    }
+
+    fn get_file(&self, key: &FileStoreKey) -> Result<Option<Vec<u8>>, Error> {
+        read_if_exists(&self.file_path(key))
+    }
+
+    fn put_entries(&self, key: &[u8], entries: &[u8]) -> Result<(), Error> {
+        write_atomically(&self.entries_path(key), entries)
+    }
+
+    fn put_file(&self, key: &FileStoreKey, content: &[u8]) -> Result<(), Error> {
+        let path = self.file_path(key);
+        // the content of a file never changes, given its key
+        if path.exists() {
+            return Ok(());
+        }
+        write_atomically(&path, content)
+    }
+}
+
+/// A remote cache backend with the timeout for the lookups.
+///
+/// After [`MAX_CONSECUTIVE_TIMEOUTS`] lookups in a row have timed out the remote cache is considered
+/// down, and all the following lookups, also the ones of its clones, miss without consulting it.
+#[derive(Clone)]
+pub(crate) struct RemoteCache {
+    /// The backend of the remote cache.
+    backend: Arc<dyn RemoteCacheBackend>,
+    /// The maximum time a lookup can take, after which it is considered a miss.
+    timeout: Duration,
+    /// The number of the last lookups that have timed out, shared between the clones.
+    consecutive_timeouts: Arc<AtomicUsize>,
+}
+
+impl RemoteCache {
+    /// Make a new `RemoteCache` with the specified backend and lookup timeout.
+    pub fn new(backend: Arc<dyn RemoteCacheBackend>, timeout: Duration) -> RemoteCache {
+        RemoteCache {
+            backend,
+            timeout,
+            consecutive_timeouts: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Whether too many lookups in a row have timed out, and the remote cache is no longer used.
+    pub fn is_disabled(&self) -> bool {
+        self.consecutive_timeouts.load(Ordering::Relaxed) >= MAX_CONSECUTIVE_TIMEOUTS
+    }
+
+    /// Fail if the deadline of the lookup has expired, counting the timeout.
+    fn check_deadline(&self, deadline: Instant) -> Result<(), Error> {
+        if Instant::now() <= deadline {
+            return Ok(());
+        }
+        let timeouts = self.consecutive_timeouts.fetch_add(1, Ordering::Relaxed) + 1;
+        if timeouts == MAX_CONSECUTIVE_TIMEOUTS {
+            warn!("The last {timeouts} lookups in the remote cache timed out, it won't be used anymore");
+        }
+        bail!("Remote cache lookup timed out");
+    }
+
+    /// Look up the entries of the key in the remote cache, fetching all the files they reference
+    /// into the local `FileStore`. The returned handles keep the fetched files alive.
+    ///
+    /// The entries with some missing file are skipped. An error is returned if the lookup takes
+    /// longer than the timeout: the deadline is checked after each request to the backend.
+    pub fn get(
+        &self,
+        key: &CacheKey,
+        file_store: &FileStore,
+    ) -> Result<(Vec<CacheEntry>, Vec<FileStoreHandle>), Error> {
+        let deadline = Instant::now() + self.timeout;
+        let serialized = bincode::serialize(key).context("Failed to serialize cache key")?;
+        let entries = self.backend.get_entries(&serialized)?;
+        self.check_deadline(deadline)?;
+        let Some(entries) = entries else {
+            return Ok((vec![], vec![]));
+        };
+        let entries: Vec<CacheEntry> =
+            bincode::deserialize(&entries).context("Failed to deserialize remote cache entries")?;
+
+        let mut valid_entries = Vec::new();
+        let mut handles = Vec::new();
+        'entries: for entry in entries {
+            for file_key in entry.file_keys() {
+                if let Some(handle) = file_store.get(file_key) {
+                    handles.push(handle);
+                    continue;
+                }
+                let content = self.backend.get_file(file_key)?;
+                self.check_deadline(deadline)?;
+                let Some(content) = content else {
+                    debug!("File {file_key} is missing from the remote cache");
+                    continue 'entries;
+                };
+                if FileStoreKey::from_content(&content) != *file_key {
+                    warn!("File {file_key} from the remote cache is corrupted");
+                    continue 'entries;
+                }
+                let handle = file_store
+                    .store(file_key, [content])
+                    .context("Failed to store file from the remote cache")?;
+                handles.push(handle);
+            }
+            valid_entries.push(entry);
+        }
+        self.consecutive_timeouts.store(0, Ordering::Relaxed);
+        Ok((valid_entries, handles))
+    }
+}
+
+/// A lookup in the remote cache of an execution group that is not in the local cache. It doesn't
+/// borrow the [`Cache`](crate::Cache), so it can run in another thread: its outcome is then given
+/// to [`Cache::get_with_remote`](crate::Cache::get_with_remote).
+#[derive(Debug)]
+pub struct RemoteLookup {
+    /// The remote cache to consult.
+    pub(crate) remote: RemoteCache,
+    /// The key of the execution group.
+    pub(crate) key: CacheKey,
+}
+
+/// The entries found by a [`RemoteLookup`], with the handles that keep the files they reference in
+/// the local `FileStore`.
+#[derive(Debug)]
+pub struct RemoteEntries {
+    /// The key of the execution group.
+    pub(crate) key: CacheKey,
+    /// The entries found in the remote cache.
+    pub(crate) entries: Vec<CacheEntry>,
+    /// The handles to the files referenced by the entries.
+    pub(crate) handles: Vec<FileStoreHandle>,
+}
+
+impl RemoteLookup {
+    /// Run the lookup. The failed lookups, and the ones run after the remote cache has been
+    /// disabled for timing out too many times, are reported as finding no entries.
+    pub fn run(self, file_store: &FileStore) -> RemoteEntries {
+        let result = if self.remote.is_disabled() {
+            Ok((vec![], vec![]))
+        } else {
+            self.remote.get(&self.key, file_store)
+        };
+        let (entries, handles) = match result {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Remote cache lookup failed: {e:?}");
+                (vec![], vec![])
+            }
+        };
+        RemoteEntries {
+            key: self.key,
+            entries,
+            handles,
+        }
+    }
+}
+
+/// The publication to the remote cache of the entries of an execution group. It doesn't borrow the
+/// [`Cache`](crate::Cache), so it can run in another thread.
+#[derive(Debug)]
+pub struct RemotePublish {
+    /// The remote cache to publish to.
+    pub(crate) remote: RemoteCache,
+    /// The serialized key of the execution group.
+    pub(crate) key: Vec<u8>,
+    /// The serialized entries of the execution group.
+    pub(crate) entries: Vec<u8>,
+    /// The handles to the files referenced by the entries.
+    pub(crate) files: Vec<FileStoreHandle>,
+}
+
+impl RemotePublish {
+    /// Upload the files and then the entries that reference them. The failures are only logged.
+    /// Nothing is uploaded if the remote cache has been disabled for timing out too many times.
+    pub fn run(self) {
+        if self.remote.is_disabled() {
+            return;
+        }
+        let backend = &self.remote.backend;
+        for file in &self.files {
+            let result = file
+                .read_all()
+                .and_then(|content| backend.put_file(file.key(), &content));
+            if let Err(e) = result {
+                warn!(
+                    "Failed to publish file {} to the remote cache: {e:?}",
+                    file.key()
+                );
+                return;
+            }
+        }
+        if let Err(e) = backend.put_entries(&self.key, &self.entries) {
+            warn!("Failed to publish cache entries to the remote cache: {e:?}");
+        }
+    }
+}
+
+impl Debug for RemoteCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteCache")
+            .field("timeout", &self.timeout)
+            .field("consecutive_timeouts", &self.consecutive_timeouts)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionGroup, ExecutionResult};
+
+    use super::*;
+    use crate::{Cache, CacheResult};
+
+    /// A remote cache kept in memory, that replies after a delay.
+    struct MemoryBackend {
+        entries: HashMap<Vec<u8>, Vec<u8>>,
+        files: HashMap<FileStoreKey, Vec<u8>>,
+        delay: Duration,
+        /// The number of lookups of entries received.
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl RemoteCacheBackend for MemoryBackend {
+        fn get_entries(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            std::thread::sleep(self.delay);
+            Ok(self.entries.get(key).cloned())
+        }
+
+        fn get_file(&self, key: &FileStoreKey) -> Result<Option<Vec<u8>>, Error> {
+            std::thread::sleep(self.delay);
+            Ok(self.files.get(key).cloned())
+        }
+    }
+
+    /// Make an empty `FileStore` in the `name` subdirectory of the temporary directory.
+    fn new_store(tmpdir: &tempfile::TempDir, name: &str) -> FileStore {
+        FileStore::new(
+            tmpdir.path().join(name),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap()
+    }
+
+    /// Build a group with a captured stdout and a remote backend with its cached result.
+    fn make_remote(delay: Duration) -> (ExecutionGroup, MemoryBackend) {
+        let remote_dir = tempfile::TempDir::new().unwrap();
+        let remote_store =
+            FileStore::new(remote_dir.path(), 1000, 1000, Default::default(), None, 1).unwrap();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let stdout = exec.capture_stdout(None);
+        let group: ExecutionGroup = exec.into();
+
+        let content = b"hello".to_vec();
+        let stdout_key = FileStoreKey::from_content(&content);
+        let handle = remote_store.store(&stdout_key, [content.clone()]).unwrap();
+        let mut file_keys = HashMap::new();
+        file_keys.insert(stdout.uuid, handle);
+        let key = CacheKey::from_execution_group(&group, &HashMap::new());
+        let entry =
+            CacheEntry::from_execution_group(&group, &file_keys, vec![ExecutionResult::default()]);
+
+        let mut backend = MemoryBackend {
+            entries: HashMap::new(),
+            files: HashMap::new(),
+            delay,
+            lookups: Arc::new(AtomicUsize::new(0)),
+        };
+        backend.entries.insert(
+            bincode::serialize(&key).unwrap(),
+            bincode::serialize(&vec![entry]).unwrap(),
+        );
+        backend.files.insert(stdout_key, content);
+        (group, backend)
+    }
+
+    #[test]
+    fn test_remote_hit() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = new_store(&tmpdir, "store");
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let (group, backend) = make_remote(Duration::ZERO);
+        cache.set_remote(Arc::new(backend), Duration::from_secs(10));
+
+        match cache.get(&group, &HashMap::new(), &store) {
+            CacheResult::Miss => panic!("Expecting a remote hit"),
+            CacheResult::Hit { outputs, .. } => {
+                let stdout = outputs.values().next().unwrap();
+                assert_eq!(stdout.read_all().unwrap(), b"hello");
+            }
+        }
+        // the entry is now in the local cache
+        cache.remote = None;
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Hit { .. }
+        ));
+    }
+
+    #[test]
+    fn test_remote_missing_file() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = new_store(&tmpdir, "store");
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let (group, mut backend) = make_remote(Duration::ZERO);
+        backend.files.clear();
+        cache.set_remote(Arc::new(backend), Duration::from_secs(10));
+
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Miss
+        ));
+    }
+
+    #[test]
+    fn test_remote_timeout() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = new_store(&tmpdir, "store");
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let (group, backend) = make_remote(Duration::from_millis(200));
+        cache.set_remote(Arc::new(backend), Duration::from_millis(50));
+
+        assert!(matches!(
+            cache.get(&group, &HashMap::new(), &store),
+            CacheResult::Miss
+        ));
+    }
+
+    #[test]
+    fn test_remote_disabled_after_timeouts() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let store = new_store(&tmpdir, "store");
+        let mut cache = Cache::new(tmpdir.path().join("cache")).unwrap();
+        let (group, backend) = make_remote(Duration::from_millis(10));
+        let lookups = backend.lookups.clone();
+        cache.set_remote(Arc::new(backend), Duration::ZERO);
+
+        for _ in 0..MAX_CONSECUTIVE_TIMEOUTS + 2 {
+            let lookup = cache.remote_lookup(&group, &HashMap::new()).unwrap();
+            assert!(lookup.run(&store).entries.is_empty());
+        }
+        // the remote cache is no longer consulted after the timeouts
+        assert_eq!(lookups.load(Ordering::Relaxed), MAX_CONSECUTIVE_TIMEOUTS);
+    }
+
+    #[test]
+    fn test_directory_publish_and_lookup() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let backend = Arc::new(DirectoryRemoteCache::new(tmpdir.path().join("remote")).unwrap());
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let stdout = exec.capture_stdout(None);
+        let group: ExecutionGroup = exec.into();
+
+        // a first machine runs the execution and publishes its result
+        let store1 = new_store(&tmpdir, "store1");
+        let mut cache1 = Cache::new(tmpdir.path().join("cache1")).unwrap();
+        cache1.set_remote(backend.clone(), Duration::from_secs(10));
+        let content = b"hello".to_vec();
+        let stdout_key = FileStoreKey::from_content(&content);
+        let handle = store1.store(&stdout_key, [content]).unwrap();
+        let mut outputs = HashMap::new();
+        outputs.insert(stdout.uuid, handle);
+        cache1.insert(&group, &outputs, vec![ExecutionResult::default()]);
+        cache1
+            .remote_publish(&group, &outputs, &store1)
+            .expect("Expecting something to publish")
+            .run();
+
+        // a second machine finds it with a lookup run outside the cache
+        let store2 = new_store(&tmpdir, "store2");
+        let mut cache2 = Cache::new(tmpdir.path().join("cache2")).unwrap();
+        cache2.set_remote(backend, Duration::from_secs(10));
+        assert!(matches!(
+            cache2.get_local(&group, &HashMap::new(), &store2),
+            CacheResult::Miss
+        ));
+        let lookup = cache2.remote_lookup(&group, &HashMap::new()).unwrap();
+        let entries = std::thread::scope(|s| s.spawn(|| lookup.run(&store2)).join().unwrap());
+        match cache2.get_with_remote(&group, &HashMap::new(), &store2, entries) {
+            CacheResult::Miss => panic!("Expecting a remote hit"),
+            CacheResult::Hit { outputs, .. } => {
+                let stdout = outputs.values().next().unwrap();
+                assert_eq!(stdout.read_all().unwrap(), b"hello");
+            }
+        }
+    }
+}
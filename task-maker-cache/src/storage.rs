@@ -1,29 +1,73 @@
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use const_format::formatcp;
+use fslock::LockFile;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use task_maker_store::FileStoreKey;
 
 use crate::entry::CacheEntry;
 use crate::key::CacheKey;
 
-/// Magic string that is prepended to the cache file to avoid accidental loading of invalid cache
-/// files.
+/// The version of the format of the cache files. It must be bumped every time the serialization of
+/// `CacheKey` or `CacheEntry` changes (including the types they contain, like the limits and the
+/// results of the executions), so that the cache files written with the old format are discarded
+/// instead of being misinterpreted.
+const CACHE_FORMAT_VERSION: u32 = 6;
+
+/// Magic string at the beginning of every cache file, it identifies a file as a cache file of any
+/// version.
+const MAGIC: &[u8] = b"task-maker-cache ";
+
+/// The first line of the cache files: the magic string followed by the version of task-maker and
+/// the version of the format. A cache file with a different header has been written by an
+/// incompatible version and is discarded.
 ///
 /// The newline at the end of the string is required. For example, let's say there are 2 versions:
-/// v0.1 and v0.11; running v0.11 first, and then v0.1, without the newline the magic of the old
-/// version is a prefix of the magic of the new version.
-const MAGIC: &[u8] = formatcp!("task-maker-cache v{}\n", env!("CARGO_PKG_VERSION")).as_bytes();
+/// v0.1 and v0.11; running v0.11 first, and then v0.1, without the newline the header of the old
+/// version is a prefix of the header of the new version.
+const HEADER: &[u8] = formatcp!(
+    "task-maker-cache v{} format {}\n",
+    env!("CARGO_PKG_VERSION"),
+    CACHE_FORMAT_VERSION
+)
+.as_bytes();
+
+/// The maximum length of the header of a cache file that is read, to avoid reading a whole file
+/// without newlines.
+const MAX_HEADER_LEN: u64 = 256;
 
 /// A cache file.
+///
+/// The keys are stored on disk sorted from the least recently used to the most recently used, this
+/// allows to keep track of the access order without changing the format of the file.
+///
+/// The same cache file can be shared by more processes: it's read and written while holding a lock
+/// next to it, and before being written the entries stored in the meantime by the other processes
+/// are merged in, instead of being overwritten.
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct CacheFile {
     /// The set of entries in this cache file.
     entries: HashMap<CacheKey, Vec<CacheEntry>>,
+    /// The time of the last access of each key.
+    last_access: HashMap<CacheKey, u64>,
+    /// The keys sorted by their last access time.
+    access_order: BTreeMap<u64, CacheKey>,
+    /// The time of the next access, it's a counter incremented at each access.
+    clock: u64,
+    /// The value of `clock` after the file has been synchronized with the disk: the keys accessed
+    /// after this time have been used by this process.
+    synced_clock: u64,
+    /// The keys whose entries have been removed, or evicted, by this process since the file has
+    /// been synchronized with the disk. Their entries on disk are not merged back.
+    removed: HashSet<CacheKey>,
+    /// The total number of entries, summing the entries of all the keys.
+    num_entries: usize,
+    /// The maximum number of entries to keep, `None` for no limit.
+    max_entries: Option<usize>,
     /// Where this file is stored.
     path: PathBuf,
     /// Whether this file should be flushed.
@@ -31,60 +75,176 @@ pub(crate) struct CacheFile {
 }
 
 impl CacheFile {
-    /// Read the cache file, check the magic string and deserialize all the entries in it.
+    /// Make an empty cache file stored at the given path.
+    fn empty(path: PathBuf) -> CacheFile {
+        Self {
+            entries: Default::default(),
+            last_access: Default::default(),
+            access_order: Default::default(),
+            clock: 0,
+            synced_clock: 0,
+            removed: Default::default(),
+            num_entries: 0,
+            max_entries: None,
+            path,
+            dirty: false,
+        }
+    }
+
+    /// Read the cache file, check its header and deserialize all the entries in it. A cache file
+    /// written by an incompatible version is treated as empty.
     pub fn load(path: PathBuf) -> Result<CacheFile, Error> {
         if !path.exists() {
-            return Ok(Self {
-                entries: Default::default(),
-                path,
-                dirty: false,
-            });
+            return Ok(Self::empty(path));
+        }
+
+        let _lock = Self::lock(&path)?;
+        let entries = Self::read_entries(&path)?;
+        let mut file = Self::empty(path);
+        // The entries are stored from the least recently used.
+        for (key, entries) in entries {
+            file.touch(&key);
+            file.num_entries += entries.len();
+            file.entries.insert(key, entries);
         }
+        file.synced_clock = file.clock;
+        Ok(file)
+    }
+
+    /// Acquire the lock of the cache file at the given path, waiting if another process is holding
+    /// it. The lock is released when the returned `LockFile` is dropped.
+    fn lock(path: &Path) -> Result<LockFile, Error> {
+        let lock_path = path.with_extension("lock");
+        let mut lock = LockFile::open(&lock_path)
+            .with_context(|| format!("Failed to create lock file at {}", lock_path.display()))?;
+        if !lock
+            .try_lock()
+            .context("Failed to obtain the lock on the cache file")?
+        {
+            debug!("Cache file locked... waiting");
+            lock.lock()
+                .context("Failed to obtain the lock on the cache file")?;
+        }
+        Ok(lock)
+    }
 
-        let file = std::fs::File::open(&path)
+    /// Read the entries of the cache file at the given path, from the least recently used. A
+    /// missing cache file, or one written by an incompatible version, has no entries.
+    fn read_entries(path: &Path) -> Result<Vec<(CacheKey, Vec<CacheEntry>)>, Error> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let file = std::fs::File::open(path)
             .with_context(|| format!("Cannot open cache file at {}", path.display()))?;
         let mut reader = BufReader::new(file);
-        let mut magic = [0u8; MAGIC.len()];
+        let mut header = Vec::new();
+        (&mut reader)
+            .take(MAX_HEADER_LEN)
+            .read_until(b'\n', &mut header)
+            .context("Failed to read cache header")?;
+        if !header.starts_with(MAGIC) {
+            bail!("{} is not a cache file", path.display());
+        }
+        if header != HEADER {
+            info!(
+                "Cache version mismatch, discarding {}:\nExpected: {:?}\nFound: {:?}",
+                path.display(),
+                String::from_utf8_lossy(HEADER),
+                String::from_utf8_lossy(&header)
+            );
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize_from(reader).context("Failed to deserialize cache content")
+    }
 
-        if reader.read_exact(&mut magic).is_ok_and(|_| magic != MAGIC) {
-            info!("Cache version mismatch:\nExpected: {MAGIC:?}\nFound: {magic:?}");
-            return Ok(Self {
-                entries: Default::default(),
-                path,
-                dirty: false,
-            });
+    /// Merge in this file the entries read from disk, which may have been written by other
+    /// processes after this file has been loaded.
+    ///
+    /// The entries of the keys removed by this process are not merged back, and when both have an
+    /// entry with the same limits the one of this process is kept. The keys not used by this
+    /// process that are not on disk anymore have been removed by another process, so they are
+    /// dropped. The keys used by this process are the most recently used ones, the others keep the
+    /// order they have on disk.
+    fn merge(&mut self, disk: Vec<(CacheKey, Vec<CacheEntry>)>) {
+        let on_disk: HashSet<CacheKey> = disk.iter().map(|(key, _)| key.clone()).collect();
+        let (used, unused): (Vec<_>, Vec<_>) = self
+            .access_order
+            .values()
+            .cloned()
+            .partition(|key| self.last_access[key] >= self.synced_clock);
+        for key in unused {
+            if !on_disk.contains(&key) {
+                if let Some(entries) = self.entries.remove(&key) {
+                    self.num_entries -= entries.len();
+                }
+            }
+        }
+        let used_set: HashSet<CacheKey> = used.iter().cloned().collect();
+        let mut order = vec![];
+        for (key, entries) in disk {
+            if !self.removed.contains(&key) {
+                let set = self.entries.entry(key.clone()).or_default();
+                for entry in entries {
+                    if !set.iter().any(|e| e.same_limits(&entry)) {
+                        set.push(entry);
+                        self.num_entries += 1;
+                    }
+                }
+            }
+            if !used_set.contains(&key) && self.entries.contains_key(&key) {
+                order.push(key);
+            }
         }
+        order.extend(used);
 
-        let entries = bincode::deserialize_from::<_, HashMap<CacheKey, Vec<CacheEntry>>>(reader)
-            .context("Failed to deserialize cache content")?;
+        self.last_access.clear();
+        self.access_order.clear();
+        self.clock = 0;
+        for key in &order {
+            self.touch(key);
+        }
+        self.evict();
+    }
 
-        Ok(Self {
-            entries,
-            path,
-            dirty: false,
-        })
+    /// Set the maximum number of entries to keep, evicting the least recently used ones if needed.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+        self.evict();
     }
 
-    /// Store the content of the cache to the cache file, including the magic string.
-    pub fn store(&self) -> Result<(), Error> {
+    /// Store the content of the cache to the cache file, including the header. The entries written
+    /// to the file by other processes since it has been loaded are merged in, not overwritten.
+    pub fn store(&mut self) -> Result<(), Error> {
         // Do not write the file if it's not dirty.
         if !self.dirty {
             return Ok(());
         }
 
-        let path = &self.path;
+        let path = self.path.clone();
         std::fs::create_dir_all(path.parent().context("Invalid cache file")?)
             .with_context(|| format!("Failed to create cache directory for {}", path.display()))?;
+        let _lock = Self::lock(&path)?;
+        match Self::read_entries(&path) {
+            Ok(disk) => self.merge(disk),
+            Err(e) => warn!("Overwriting the cache file at {}: {e:?}", path.display()),
+        }
+
         let tmp = path.with_extension("tmp");
         let file = std::fs::File::create(&tmp).context("Failed to create cache file")?;
         let mut writer = BufWriter::new(file);
 
         writer
-            .write_all(MAGIC)
-            .context("Failed to write cache magic number")?;
+            .write_all(HEADER)
+            .context("Failed to write cache header")?;
 
-        bincode::serialize_into(writer, &self.entries.iter().collect_vec())
-            .context("Failed to write cache content")?;
+        let entries = self
+            .access_order
+            .values()
+            .filter_map(|key| self.entries.get_key_value(key))
+            .collect_vec();
+        bincode::serialize_into(writer, &entries).context("Failed to write cache content")?;
         std::fs::rename(&tmp, &self.path).with_context(|| {
             format!(
                 "Failed to move {} -> {}",
@@ -92,11 +252,162 @@ impl CacheFile {
                 self.path.display()
             )
         })?;
+        self.dirty = false;
+        self.synced_clock = self.clock;
+        self.removed.clear();
         Ok(())
     }
 
-    pub fn entry(&mut self, key: CacheKey) -> Entry<'_, CacheKey, Vec<CacheEntry>> {
-        self.entries.entry(key)
+    /// Get the mutable entries of a key, marking the key as recently used.
+    pub fn get_mut(&mut self, key: &CacheKey) -> Option<&mut Vec<CacheEntry>> {
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+        self.touch(key);
+        self.entries.get_mut(key)
+    }
+
+    /// Get the entries of a key, without marking the key as recently used.
+    pub fn peek(&self, key: &CacheKey) -> Option<&Vec<CacheEntry>> {
+        self.entries.get(key)
+    }
+
+    /// Iterate over all the entries in the cache, without marking them as recently used.
+    pub fn entries(&self) -> impl Iterator<Item = &CacheEntry> {
+        self.entries.values().flatten()
+    }
+
+    /// Iterate over all the keys in the cache, without marking them as recently used.
+    pub fn keys(&self) -> impl Iterator<Item = &CacheKey> {
+        self.entries.keys()
+    }
+
+    /// Insert an entry for the given key, replacing the one with the same limits, if any. If the
+    /// cache becomes too big, the least recently used keys are evicted.
+    pub fn insert(&mut self, key: CacheKey, entry: CacheEntry) {
+        self.touch(&key);
+        let set = self.entries.entry(key).or_default();
+        // Do not insert duplicated keys, replace if the limits are the same.
+        let pos = set.iter().find_position(|e| e.same_limits(&entry));
+        if let Some((pos, _)) = pos {
+            set[pos] = entry;
+        } else {
+            set.push(entry);
+            self.num_entries += 1;
+        }
+        self.evict();
+        self.mark_dirty();
+    }
+
+    /// Remove the entries of a key at the given indices, which must be sorted. If no entry of the
+    /// key is left, the key is removed as well.
+    pub fn remove_entries(&mut self, key: &CacheKey, indices: &[usize]) {
+        let Some(set) = self.entries.get_mut(key) else {
+            return;
+        };
+        for &index in indices.iter().rev() {
+            set.remove(index);
+        }
+        self.num_entries -= indices.len();
+        if set.is_empty() {
+            self.entries.remove(key);
+            if let Some(last_access) = self.last_access.remove(key) {
+                self.access_order.remove(&last_access);
+            }
+        }
+        self.removed.insert(key.clone());
+        self.mark_dirty();
+    }
+
+    /// Remove all the entries last accessed before `min_last_access`, in seconds since the UNIX
+    /// epoch, dropping the keys left without entries. Returns the number of removed and kept
+    /// entries.
+    pub fn prune(&mut self, min_last_access: u64) -> (usize, usize) {
+        self.retain(|set| set.retain(|entry| entry.last_access >= min_last_access))
+    }
+
+    /// Remove the entries referencing a file for which `exists` returns `false`, and the entries
+    /// with the same limits of a more recently accessed entry of the same key, dropping the keys
+    /// left without entries. Returns the number of removed and kept entries.
+    pub fn compact<F: Fn(&FileStoreKey) -> bool>(&mut self, exists: F) -> (usize, usize) {
+        self.retain(|set| {
+            set.retain(|entry| entry.file_keys().all(&exists));
+            // Among the entries with the same limits keep only the most recent one, picking the
+            // first one in case of ties.
+            let keep = (0..set.len())
+                .map(|i| {
+                    !(0..set.len()).any(|j| {
+                        j != i
+                            && set[j].same_limits(&set[i])
+                            && (set[j].last_access, i) > (set[i].last_access, j)
+                    })
+                })
+                .collect_vec();
+            let mut keep = keep.into_iter();
+            set.retain(|_| keep.next().unwrap_or(true));
+        })
+    }
+
+    /// Filter in place the entries of each key with `filter`, dropping the keys left without
+    /// entries. Returns the number of removed and kept entries.
+    fn retain<F: FnMut(&mut Vec<CacheEntry>)>(&mut self, mut filter: F) -> (usize, usize) {
+        let mut removed = 0;
+        let mut empty = vec![];
+        for (key, set) in self.entries.iter_mut() {
+            let len = set.len();
+            filter(set);
+            if set.len() != len {
+                removed += len - set.len();
+                self.removed.insert(key.clone());
+            }
+            if set.is_empty() {
+                empty.push(key.clone());
+            }
+        }
+        for key in empty {
+            self.entries.remove(&key);
+            if let Some(last_access) = self.last_access.remove(&key) {
+                self.access_order.remove(&last_access);
+            }
+        }
+        if removed > 0 {
+            self.num_entries -= removed;
+            self.mark_dirty();
+        }
+        (removed, self.num_entries)
+    }
+
+    /// Mark the key as the most recently used.
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(previous) = self.last_access.insert(key.clone(), self.clock) {
+            self.access_order.remove(&previous);
+        }
+        self.access_order.insert(self.clock, key.clone());
+        self.clock += 1;
+    }
+
+    /// Remove the least recently used keys until the number of entries is within the limit. All the
+    /// entries of a key are evicted together.
+    fn evict(&mut self) {
+        let Some(max_entries) = self.max_entries else {
+            return;
+        };
+        while self.num_entries > max_entries {
+            let Some((_, key)) = self.access_order.pop_first() else {
+                break;
+            };
+            self.last_access.remove(&key);
+            if let Some(entries) = self.entries.remove(&key) {
+                self.num_entries -= entries.len();
+            }
+            self.removed.insert(key);
+            self.dirty = true;
+        }
+    }
+
+    /// The size of the cache file on disk, zero if it doesn't exist yet.
+    pub fn disk_size(&self) -> u64 {
+        std::fs::metadata(&self.path).map_or(0, |metadata| metadata.len())
     }
 
     pub fn mark_dirty(&mut self) {
@@ -108,8 +419,202 @@ impl CacheFile {
 mod tests {
     use std::fs::File;
 
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionGroup, ExecutionResult};
+
     use super::*;
 
+    fn make_entry(command: &str) -> (CacheKey, CacheEntry) {
+        let group: ExecutionGroup =
+            Execution::new("exec", ExecutionCommand::system(command)).into();
+        let key = CacheKey::from_execution_group(&group, &HashMap::new());
+        let entry = CacheEntry::from_execution_group(
+            &group,
+            &HashMap::new(),
+            vec![ExecutionResult::default()],
+        );
+        (key, entry)
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut file = CacheFile::load(tmpdir.path().join("cache")).unwrap();
+        file.set_max_entries(Some(2));
+        let (key1, entry1) = make_entry("foo");
+        let (key2, entry2) = make_entry("bar");
+        let (key3, entry3) = make_entry("baz");
+        file.insert(key1.clone(), entry1);
+        file.insert(key2.clone(), entry2);
+        assert!(file.get_mut(&key1).is_some());
+        file.insert(key3.clone(), entry3);
+        assert!(file.get_mut(&key1).is_some());
+        assert!(file.get_mut(&key2).is_none());
+        assert!(file.get_mut(&key3).is_some());
+    }
+
+    #[test]
+    fn test_store_keeps_access_order() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        let (key1, entry1) = make_entry("foo");
+        let (key2, entry2) = make_entry("bar");
+        {
+            let mut file = CacheFile::load(path.clone()).unwrap();
+            file.insert(key1.clone(), entry1);
+            file.insert(key2.clone(), entry2);
+            assert!(file.get_mut(&key1).is_some());
+            file.store().unwrap();
+        }
+        let mut file = CacheFile::load(path).unwrap();
+        file.set_max_entries(Some(1));
+        assert!(file.get_mut(&key1).is_some());
+        assert!(file.get_mut(&key2).is_none());
+    }
+
+    #[test]
+    fn test_remove_entries() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut file = CacheFile::load(tmpdir.path().join("cache")).unwrap();
+        let (key1, entry1) = make_entry("foo");
+        let (key2, entry2) = make_entry("bar");
+        file.insert(key1.clone(), entry1);
+        file.insert(key2.clone(), entry2);
+        file.dirty = false;
+
+        file.remove_entries(&key1, &[0]);
+        assert!(file.dirty);
+        assert_eq!(file.num_entries, 1);
+        assert!(file.peek(&key1).is_none());
+        assert!(!file.last_access.contains_key(&key1));
+        assert_eq!(file.access_order.len(), 1);
+        assert!(file.get_mut(&key2).is_some());
+    }
+
+    #[test]
+    fn test_prune() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut file = CacheFile::load(tmpdir.path().join("cache")).unwrap();
+        let (key1, mut entry1) = make_entry("foo");
+        let (key2, mut entry2) = make_entry("bar");
+        entry1.last_access = 100;
+        entry2.last_access = 200;
+        file.insert(key1.clone(), entry1);
+        file.insert(key2.clone(), entry2);
+        file.dirty = false;
+
+        assert_eq!(file.prune(50), (0, 2));
+        assert!(!file.dirty);
+        assert_eq!(file.prune(150), (1, 1));
+        assert!(file.dirty);
+        assert!(file.peek(&key1).is_none());
+        assert!(!file.last_access.contains_key(&key1));
+        assert!(file.peek(&key2).is_some());
+    }
+
+    #[test]
+    fn test_compact() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut file = CacheFile::load(tmpdir.path().join("cache")).unwrap();
+        let (key1, mut entry1) = make_entry("foo");
+        let (key2, mut entry2) = make_entry("bar");
+        let (_, mut old_entry1) = make_entry("foo");
+        let (_, mut new_entry1) = make_entry("foo");
+        let missing = FileStoreKey::from_content(b"missing");
+        entry1.last_access = 100;
+        old_entry1.last_access = 50;
+        new_entry1.last_access = 200;
+        new_entry1.items[0].stdout = Some(missing.clone());
+        entry2.last_access = 100;
+        entry2.items[0].stdout = Some(missing.clone());
+        file.insert(key1.clone(), entry1);
+        file.insert(key2.clone(), entry2);
+        // `insert` replaces the entries with the same limits, but the files written by older
+        // versions may contain duplicates.
+        let set = file.get_mut(&key1).unwrap();
+        set.push(old_entry1);
+        set.push(new_entry1);
+        file.num_entries += 2;
+        file.dirty = false;
+
+        assert_eq!(file.compact(|_| true), (2, 2));
+        assert!(file.dirty);
+        assert_eq!(file.peek(&key1).unwrap()[0].last_access, 200);
+
+        file.dirty = false;
+        assert_eq!(file.compact(|_| true), (0, 2));
+        assert!(!file.dirty);
+        assert_eq!(file.compact(|key| key != &missing), (2, 0));
+        assert!(file.peek(&key1).is_none());
+        assert!(file.peek(&key2).is_none());
+        assert!(!file.last_access.contains_key(&key2));
+        assert!(file.access_order.is_empty());
+    }
+
+    #[test]
+    fn test_store_merges_concurrent_inserts() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        let (key1, entry1) = make_entry("foo");
+        let (key2, entry2) = make_entry("bar");
+        let mut file1 = CacheFile::load(path.clone()).unwrap();
+        let mut file2 = CacheFile::load(path.clone()).unwrap();
+        file1.insert(key1.clone(), entry1);
+        file2.insert(key2.clone(), entry2);
+        file1.store().unwrap();
+        file2.store().unwrap();
+        assert!(file2.peek(&key1).is_some());
+
+        let mut file = CacheFile::load(path).unwrap();
+        assert_eq!(file.num_entries, 2);
+        assert!(file.get_mut(&key1).is_some());
+        assert!(file.get_mut(&key2).is_some());
+    }
+
+    #[test]
+    fn test_store_keeps_local_removals() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        let (key1, entry1) = make_entry("foo");
+        let (key2, entry2) = make_entry("bar");
+        {
+            let mut file = CacheFile::load(path.clone()).unwrap();
+            file.insert(key1.clone(), entry1);
+            file.store().unwrap();
+        }
+        let mut file1 = CacheFile::load(path.clone()).unwrap();
+        let mut file2 = CacheFile::load(path.clone()).unwrap();
+        file1.remove_entries(&key1, &[0]);
+        file1.store().unwrap();
+        file2.insert(key2.clone(), entry2);
+        file2.store().unwrap();
+        assert!(file2.peek(&key1).is_none());
+        assert!(file2.peek(&key2).is_some());
+        assert_eq!(file2.num_entries, 1);
+
+        let file = CacheFile::load(path).unwrap();
+        assert!(file.peek(&key1).is_none());
+        assert!(file.peek(&key2).is_some());
+    }
+
+    #[test]
+    fn test_store_used_keys_are_most_recent() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        let (key1, entry1) = make_entry("foo");
+        let (key2, entry2) = make_entry("bar");
+        let mut file1 = CacheFile::load(path.clone()).unwrap();
+        let mut file2 = CacheFile::load(path.clone()).unwrap();
+        file2.insert(key2.clone(), entry2);
+        file2.store().unwrap();
+        file1.insert(key1.clone(), entry1);
+        file1.store().unwrap();
+
+        let mut file = CacheFile::load(path).unwrap();
+        file.set_max_entries(Some(1));
+        assert!(file.get_mut(&key1).is_some());
+        assert!(file.get_mut(&key2).is_none());
+    }
+
     #[test]
     fn test_load_reject_wrong_magic() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -125,9 +630,43 @@ mod tests {
         let tmpdir = tempfile::TempDir::new().unwrap();
         let path = tmpdir.path().join("cache");
         let mut f = File::create(&path).unwrap();
-        f.write_all(MAGIC).unwrap();
+        f.write_all(HEADER).unwrap();
         f.write_all(b"wrong-version").unwrap();
 
         assert!(CacheFile::load(path).is_err());
     }
+
+    #[test]
+    fn test_load_discard_old_format() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        let (key, entry) = make_entry("foo");
+        let mut file = CacheFile::load(path.clone()).unwrap();
+        file.insert(key.clone(), entry);
+        file.store().unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let old_header = format!(
+            "task-maker-cache v{} format {}\n",
+            env!("CARGO_PKG_VERSION"),
+            CACHE_FORMAT_VERSION - 1
+        );
+        let mut old_content = old_header.into_bytes();
+        old_content.extend_from_slice(&content[HEADER.len()..]);
+        std::fs::write(&path, old_content).unwrap();
+
+        let mut file = CacheFile::load(path).unwrap();
+        assert!(file.get_mut(&key).is_none());
+    }
+
+    #[test]
+    fn test_load_discard_old_magic() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("cache");
+        // the header used before the format version was introduced
+        std::fs::write(&path, b"task-maker-cache v0.1\nsome old content").unwrap();
+
+        let file = CacheFile::load(path).unwrap();
+        assert_eq!(file.num_entries, 0);
+    }
 }
@@ -1,5 +1,6 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
@@ -41,6 +42,28 @@ pub struct ExecutionDAGConfig {
     pub copy_logs: bool,
     /// Priority of this DAG.
     pub priority: DagPriority,
+    /// CPU time limit of the compilations, in seconds.
+    pub compilation_time_limit: f64,
+    /// Memory limit of the compilations, in KiB. Some compilers reserve a lot of virtual memory,
+    /// so by default the memory is not limited.
+    pub compilation_memory_limit: Option<u64>,
+    /// Wall time limit of the generators and of the validators of the input files, in seconds.
+    /// It stops a generator stuck in an infinite loop, so it's generous by default.
+    pub generation_wall_time_limit: f64,
+    /// Whether the generators of the input and output files normalize them as text files, see
+    /// [`Execution::normalize_outputs()`].
+    pub normalize_generated_files: bool,
+    /// Whether the executor should explain why the executions are not found in the cache.
+    pub explain_cache_miss: bool,
+    /// Whether the UI should show which worker executed each solution on each testcase.
+    pub show_workers: bool,
+    /// Send the DAG to the executor in chunks of at most this number of execution groups, the next
+    /// chunk is sent only when the executor is running out of work. See
+    /// [`ExecutionDAGData::split_chunks()`].
+    ///
+    /// This bounds the memory used by the executor for the DAG, not the one of the client: the
+    /// whole DAG is built and split before the evaluation starts.
+    pub stream_chunk_size: Option<usize>,
 }
 
 /// A wrapper around a `File` provided by the client, this means that the client knows the
@@ -65,6 +88,21 @@ pub enum ProvidedFile {
         /// The content of the file.
         content: Vec<u8>,
     },
+    /// A file that is already inside the `FileStore` of the client, knowing only its key. The
+    /// evaluation fails if the file is not in the store.
+    StoredFile {
+        /// The file handle.
+        file: File,
+        /// The key of the file for the lookup in the `FileStore`.
+        key: FileStoreKey,
+    },
+    /// A file whose content is known only during the evaluation, for example from the callbacks of
+    /// the other executions. The client sends it when it's given to the `ProvideHandle` of the DAG.
+    /// The executions that depend on it are skipped if it's never provided.
+    Late {
+        /// The file handle.
+        file: File,
+    },
 }
 
 /// Serializable part of the execution DAG: everything except the callbacks (which are not
@@ -80,7 +118,6 @@ pub struct ExecutionDAGData {
 }
 
 /// The set of callbacks of a DAG.
-#[derive(Debug)]
 pub struct ExecutionDAGCallbacks {
     /// The callbacks of the executions.
     pub execution_callbacks: HashMap<ExecutionGroupUuid, ExecutionCallbacks>,
@@ -90,6 +127,33 @@ pub struct ExecutionDAGCallbacks {
     /// others will be sent at the end of the evaluation. Note that sending big files during the
     /// evaluation can cause performance degradations.
     pub urgent_files: HashSet<FileUuid>,
+    /// The callbacks called when an execution is not found in the cache. They are called only if
+    /// `explain_cache_miss` is set in the config.
+    pub on_cache_miss: Vec<OnCacheMissCallback>,
+    /// The executions the callbacks asked to skip, still to be sent to the executor.
+    pub skip_handle: SkipHandle,
+    /// The content of the late files given by the callbacks, still to be sent to the executor.
+    pub provide_handle: ProvideHandle,
+}
+
+/// A handle for asking the executor to skip some executions of the DAG during the evaluation, for
+/// example from the callbacks of the other executions. The executions that are already running or
+/// completed are not affected.
+#[derive(Debug, Clone, Default)]
+pub struct SkipHandle {
+    /// The executions to skip that have not been sent to the executor yet.
+    requests: Arc<Mutex<Vec<ExecutionGroupUuid>>>,
+}
+
+/// The content of a file added with `ExecutionDAG::provide_later`, with its uuid.
+type LateFileContent = (FileUuid, Vec<u8>);
+
+/// A handle for giving the content of the files added with `ExecutionDAG::provide_later` during
+/// the evaluation, for example from the callbacks of the other executions.
+#[derive(Debug, Clone, Default)]
+pub struct ProvideHandle {
+    /// The content of the files that have not been sent to the executor yet.
+    contents: Arc<Mutex<Vec<LateFileContent>>>,
 }
 
 /// A computation DAG, this is not serializable because it contains the callbacks of the client.
@@ -117,6 +181,9 @@ impl ExecutionDAG {
                 execution_callbacks: HashMap::new(),
                 file_callbacks: HashMap::new(),
                 urgent_files: HashSet::new(),
+                on_cache_miss: Vec::new(),
+                skip_handle: SkipHandle::default(),
+                provide_handle: ProvideHandle::default(),
             }),
         }
     }
@@ -148,6 +215,22 @@ impl ExecutionDAG {
         );
     }
 
+    /// Provide a file for the computation that is already inside the `FileStore` of the client,
+    /// addressed by its key.
+    pub fn provide_stored_file(&mut self, file: File, key: FileStoreKey) {
+        self.data
+            .provided_files
+            .insert(file.uuid, ProvidedFile::StoredFile { file, key });
+    }
+
+    /// Provide a file whose content will be given during the evaluation, using the
+    /// [`ProvideHandle`] of the DAG. The executions that depend on it wait for it.
+    pub fn provide_later(&mut self, file: File) {
+        self.data
+            .provided_files
+            .insert(file.uuid, ProvidedFile::Late { file });
+    }
+
     /// Add an execution to the DAG.
     pub fn add_execution(&mut self, execution: Execution) -> ExecutionGroupUuid {
         let mut group = ExecutionGroup::new(execution.description.clone());
@@ -321,6 +404,157 @@ impl ExecutionDAG {
     pub fn urgent_files(&mut self) -> &mut HashSet<FileUuid> {
         &mut self.callbacks.as_mut().unwrap().urgent_files
     }
+
+    /// Add a callback that will be called, with the description of the execution and the
+    /// explanation, when an execution is not found in the cache. The executor explains the cache
+    /// misses only if `explain_cache_miss` is set in the config.
+    pub fn on_cache_miss<F>(&mut self, callback: F)
+    where
+        F: (FnMut(&str, &str) -> Result<(), Error>) + Send + 'static,
+    {
+        self.callbacks
+            .as_mut()
+            .expect("Cannot change callbacks after cloning")
+            .on_cache_miss
+            .push(Box::new(callback));
+    }
+
+    /// Get the list of callbacks called when an execution is not found in the cache.
+    pub fn cache_miss_callbacks(&mut self) -> &mut Vec<OnCacheMissCallback> {
+        &mut self.callbacks.as_mut().unwrap().on_cache_miss
+    }
+
+    /// Get a handle for skipping some executions of this DAG during the evaluation.
+    pub fn skip_handle(&self) -> SkipHandle {
+        self.callbacks
+            .as_ref()
+            .expect("Cannot skip executions after cloning")
+            .skip_handle
+            .clone()
+    }
+
+    /// Get a handle for giving the content of the files added with `provide_later`.
+    pub fn provide_handle(&self) -> ProvideHandle {
+        self.callbacks
+            .as_ref()
+            .expect("Cannot provide files after cloning")
+            .provide_handle
+            .clone()
+    }
+}
+
+impl SkipHandle {
+    /// Ask the executor to skip these executions, and all the ones that depend on them.
+    pub fn skip<I: IntoIterator<Item = ExecutionGroupUuid>>(&self, executions: I) {
+        self.requests.lock().unwrap().extend(executions);
+    }
+
+    /// Take the executions to skip that have been requested since the last call.
+    pub fn take(&self) -> Vec<ExecutionGroupUuid> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+}
+
+impl ProvideHandle {
+    /// Give the content of a file added with `ExecutionDAG::provide_later`.
+    pub fn provide<F: Into<FileUuid>>(&self, file: F, content: Vec<u8>) {
+        self.contents.lock().unwrap().push((file.into(), content));
+    }
+
+    /// Take the content of the files that have been given since the last call.
+    pub fn take(&self) -> Vec<(FileUuid, Vec<u8>)> {
+        std::mem::take(&mut *self.contents.lock().unwrap())
+    }
+}
+
+impl ExecutionDAGData {
+    /// Split the DAG in chunks of at most `max_groups` execution groups each, to be sent to the
+    /// executor one after the other. The chunks are in topological order: the dependencies of the
+    /// groups of a chunk are produced by the same or by the previous chunks, or are provided files.
+    /// Between the groups that are ready, the ones with the highest priority come first.
+    ///
+    /// Each provided file is put in the chunk of its first consumer, the unused ones in the first
+    /// chunk. The groups in a dependency cycle are put at the end, where the executor will detect
+    /// the cycle. All the chunks have the same config of this DAG, and there is always at least
+    /// one chunk.
+    pub fn split_chunks(self, max_groups: usize) -> Vec<ExecutionDAGData> {
+        assert!(max_groups > 0);
+        let ExecutionDAGData {
+            mut provided_files,
+            mut execution_groups,
+            config,
+        } = self;
+        let mut producers: HashMap<FileUuid, ExecutionGroupUuid> = HashMap::new();
+        for group in execution_groups.values() {
+            for output in group.outputs() {
+                producers.insert(output, group.uuid);
+            }
+        }
+        // the number of dependencies not produced yet, for each group waiting for them
+        let mut missing_deps: HashMap<ExecutionGroupUuid, usize> = HashMap::new();
+        let mut dependents: HashMap<ExecutionGroupUuid, Vec<ExecutionGroupUuid>> = HashMap::new();
+        let mut ready = BinaryHeap::new();
+        for group in execution_groups.values() {
+            let mut count = 0;
+            for dep in group.dependencies() {
+                if let Some(producer) = producers.get(&dep) {
+                    dependents.entry(*producer).or_default().push(group.uuid);
+                    count += 1;
+                }
+            }
+            if count == 0 {
+                ready.push((group.priority, group.uuid));
+            } else {
+                missing_deps.insert(group.uuid, count);
+            }
+        }
+        let mut order = Vec::with_capacity(execution_groups.len());
+        while let Some((_, uuid)) = ready.pop() {
+            order.push(uuid);
+            for dependent in dependents.remove(&uuid).unwrap_or_default() {
+                let count = missing_deps
+                    .get_mut(&dependent)
+                    .expect("Dependent group is not waiting");
+                *count -= 1;
+                if *count == 0 {
+                    missing_deps.remove(&dependent);
+                    ready.push((execution_groups[&dependent].priority, dependent));
+                }
+            }
+        }
+        // the groups still waiting are in a cycle
+        order.extend(missing_deps.into_keys());
+
+        let mut chunks: Vec<_> = order
+            .chunks(max_groups)
+            .map(|uuids| ExecutionDAGData {
+                provided_files: HashMap::new(),
+                execution_groups: uuids
+                    .iter()
+                    .map(|uuid| (*uuid, execution_groups.remove(uuid).expect("Unknown group")))
+                    .collect(),
+                config: config.clone(),
+            })
+            .collect();
+        if chunks.is_empty() {
+            chunks.push(ExecutionDAGData {
+                provided_files: HashMap::new(),
+                execution_groups: HashMap::new(),
+                config,
+            });
+        }
+        for chunk in chunks.iter_mut() {
+            for group in chunk.execution_groups.values() {
+                for dep in group.dependencies() {
+                    if let Some(file) = provided_files.remove(&dep) {
+                        chunk.provided_files.insert(dep, file);
+                    }
+                }
+            }
+        }
+        chunks[0].provided_files.extend(provided_files);
+        chunks
+    }
 }
 
 impl Clone for ExecutionDAG {
@@ -346,6 +580,13 @@ impl ExecutionDAGConfig {
             copy_exe: false,
             copy_logs: false,
             priority: 0,
+            compilation_time_limit: 60.0,
+            compilation_memory_limit: None,
+            generation_wall_time_limit: 60.0,
+            normalize_generated_files: false,
+            explain_cache_miss: false,
+            show_workers: false,
+            stream_chunk_size: None,
         }
     }
 
@@ -361,6 +602,26 @@ impl ExecutionDAGConfig {
         self
     }
 
+    /// Set whether the executor should explain why the executions are not found in the cache.
+    pub fn explain_cache_miss(&mut self, explain_cache_miss: bool) -> &mut Self {
+        self.explain_cache_miss = explain_cache_miss;
+        self
+    }
+
+    /// Set whether the UI should show which worker executed each solution on each testcase.
+    pub fn show_workers(&mut self, show_workers: bool) -> &mut Self {
+        self.show_workers = show_workers;
+        self
+    }
+
+    /// Set the number of execution groups to send to the executor at a time, `None` for sending
+    /// the whole DAG at once. The DAG is still built whole by the client.
+    pub fn stream_chunk_size(&mut self, size: Option<usize>) -> &mut Self {
+        assert!(size != Some(0));
+        self.stream_chunk_size = size;
+        self
+    }
+
     /// Set the cache mode for the executions of this DAG.
     pub fn cache_mode(&mut self, cache_mode: CacheMode) -> &mut Self {
         self.cache_mode = cache_mode;
@@ -397,6 +658,46 @@ impl ExecutionDAGConfig {
         self.priority = priority;
         self
     }
+
+    /// Set the CPU time limit of the compilations.
+    pub fn compilation_time_limit(&mut self, limit: f64) -> &mut Self {
+        assert!(limit > 0.0);
+        self.compilation_time_limit = limit;
+        self
+    }
+
+    /// Set the memory limit of the compilations, in KiB.
+    pub fn compilation_memory_limit(&mut self, limit: Option<u64>) -> &mut Self {
+        self.compilation_memory_limit = limit;
+        self
+    }
+
+    /// Set the wall time limit of the generators and of the validators of the input files.
+    pub fn generation_wall_time_limit(&mut self, limit: f64) -> &mut Self {
+        assert!(limit > 0.0);
+        self.generation_wall_time_limit = limit;
+        self
+    }
+
+    /// Set whether the generators of the input and output files normalize them as text files.
+    pub fn normalize_generated_files(&mut self, normalize: bool) -> &mut Self {
+        self.normalize_generated_files = normalize;
+        self
+    }
+}
+
+impl std::fmt::Debug for ExecutionDAGCallbacks {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter
+            .debug_struct("ExecutionDAGCallbacks")
+            .field("execution_callbacks", &self.execution_callbacks)
+            .field("file_callbacks", &self.file_callbacks)
+            .field("urgent_files", &self.urgent_files)
+            .field("on_cache_miss", &self.on_cache_miss.len())
+            .field("skip_handle", &self.skip_handle)
+            .field("provide_handle", &self.provide_handle)
+            .finish()
+    }
 }
 
 impl Default for ExecutionDAGConfig {
@@ -487,6 +788,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_provide_stored_file() {
+        let mut dag = ExecutionDAG::new();
+        let file = File::new("file");
+        let key = FileStoreKey::from_content(b"ciao");
+        dag.provide_stored_file(file.clone(), key.clone());
+        match &dag.data.provided_files[&file.uuid] {
+            ProvidedFile::StoredFile { file, key: stored } => {
+                assert_eq!("file", &file.description);
+                assert_eq!(&key, stored);
+            }
+            _ => panic!("Invalid provided file type"),
+        }
+    }
+
+    #[test]
+    fn test_provide_later() {
+        let mut dag = ExecutionDAG::new();
+        let file = File::new("file");
+        dag.provide_later(file.clone());
+        assert!(matches!(
+            &dag.data.provided_files[&file.uuid],
+            ProvidedFile::Late { .. }
+        ));
+        dag.provide_handle().provide(&file, b"ciao".to_vec());
+        assert_eq!(
+            dag.provide_handle().take(),
+            vec![(file.uuid, b"ciao".to_vec())]
+        );
+        assert!(dag.provide_handle().take().is_empty());
+    }
+
     #[test]
     fn test_add_execution() {
         let mut dag = ExecutionDAG::new();
@@ -611,6 +944,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_skip_handle() {
+        let dag = ExecutionDAG::new();
+        let exec = Execution::new("exec", ExecutionCommand::local("foo")).into_group();
+        dag.skip_handle().skip([exec.uuid]);
+        assert_eq!(dag.skip_handle().take(), vec![exec.uuid]);
+        assert!(dag.skip_handle().take().is_empty());
+    }
+
     #[test]
     fn test_config_mut() {
         let mut dag = ExecutionDAG::new();
@@ -618,6 +960,51 @@ mod tests {
         assert_abs_diff_eq!(123.0, dag.data.config.extra_time);
     }
 
+    #[test]
+    fn test_split_chunks() {
+        let mut dag = ExecutionDAG::new();
+        let input = File::new("input");
+        dag.provide_content(input.clone(), b"ciao".to_vec());
+        let unused = File::new("unused");
+        dag.provide_content(unused.clone(), b"ciao".to_vec());
+        let mut uuids = Vec::new();
+        let mut last = input.clone();
+        for i in 0..5 {
+            let mut exec = Execution::new(format!("exec{i}"), ExecutionCommand::local("foo"));
+            exec.stdin(last);
+            last = exec.capture_stdout(None);
+            uuids.push(dag.add_execution(exec));
+        }
+        let mut other = Execution::new("other", ExecutionCommand::local("foo"));
+        other.stdin(input.clone());
+        dag.add_execution(other);
+
+        let chunks = dag.data.split_chunks(2);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| chunk.execution_groups.len() <= 2));
+        let chunk_of = |uuid: &ExecutionGroupUuid| {
+            chunks
+                .iter()
+                .position(|chunk| chunk.execution_groups.contains_key(uuid))
+                .unwrap()
+        };
+        for pair in uuids.windows(2) {
+            assert!(chunk_of(&pair[0]) <= chunk_of(&pair[1]));
+        }
+        assert!(chunks[chunk_of(&uuids[0])]
+            .provided_files
+            .contains_key(&input.uuid));
+        assert!(chunks[0].provided_files.contains_key(&unused.uuid));
+    }
+
+    #[test]
+    fn test_split_chunks_empty() {
+        let dag = ExecutionDAG::new();
+        let chunks = dag.data.split_chunks(10);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].execution_groups.is_empty());
+    }
+
     #[test]
     fn test_urgent_files() {
         let mut dag = ExecutionDAG::new();
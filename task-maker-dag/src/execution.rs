@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
@@ -21,6 +21,10 @@ pub type OnDoneCallback = Box<dyn FnOnce(&[ExecutionResult]) -> Result<(), Error
 /// Type of the callback called when an [`Execution`](struct.Execution.html) is skipped.
 pub type OnSkipCallback = Box<dyn FnOnce() -> Result<(), Error> + Send + 'static>;
 
+/// Type of the callback called when an [`ExecutionGroup`](struct.ExecutionGroup.html) is not
+/// found in the cache, with its description and the explanation of the miss.
+pub type OnCacheMissCallback = Box<dyn FnMut(&str, &str) -> Result<(), Error> + Send + 'static>;
+
 /// Type of the priority value of an `Execution`.
 pub type Priority = i64;
 
@@ -31,6 +35,10 @@ pub type DagPriority = Priority;
 /// executions that became available after a previous execution finished, or retries.
 pub const HIGH_PRIORITY: DagPriority = 1_000_000_000_000;
 
+/// The lowest CPU time limit, in seconds, that a [`RelativeTimeLimit`] can set. Without it a
+/// reference execution that is too fast to be measured would leave no time to the others.
+pub const MIN_RELATIVE_TIME_LIMIT: f64 = 0.1;
+
 /// A tag on an `Execution`. Can be used to classify the executions into groups and refer to them,
 /// for example for splitting the cache scopes.
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -201,6 +209,20 @@ pub struct Execution {
 
     /// Limits on the execution.
     pub limits: ExecutionLimits,
+    /// The CPU time limit relative to the time of another execution. The scheduler lowers
+    /// `limits.cpu_time` accordingly before running this execution.
+    #[serde(default)]
+    pub relative_time_limit: Option<RelativeTimeLimit>,
+
+    /// Whether the result of this execution can be stored in and fetched from the cache. The
+    /// executions that depend on this one can still be cached, since their cache key includes the
+    /// hash of the files produced by this execution.
+    pub cacheable: bool,
+
+    /// Whether the captured stdout and the output files are text files to normalize before storing
+    /// them: the UTF-8 BOM is removed and the CRLF line endings are converted to LF. The files that
+    /// look binary (i.e. that contain a NUL byte) are left untouched.
+    pub normalize_outputs: bool,
 }
 
 /// Limits on an [`Execution`](struct.Execution.html). On some worker platforms some of the fields
@@ -219,7 +241,8 @@ pub struct ExecutionLimits {
     pub memory: Option<u64>,
     /// Allow multiple processes (i.e. forking/threads).
     pub allow_multiprocess: bool,
-    /// Limit on the number of file descriptors the process can keep open.
+    /// Limit on the number of file descriptors the process can keep open (`RLIMIT_NOFILE`). When
+    /// it is reached, opening a new file fails with `EMFILE`.
     pub nofile: Option<u32>,
     /// Maximum size of the files (in bytes) the process can write/create.
     pub fsize: Option<u64>,
@@ -240,6 +263,17 @@ pub struct ExecutionLimits {
     pub permissive: bool,
 }
 
+/// A CPU time limit expressed as a multiple of the CPU time used by another execution. The other
+/// execution is identified by one of the files it produces, which becomes a dependency of the
+/// execution with this limit. The limit is never lower than [`MIN_RELATIVE_TIME_LIMIT`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RelativeTimeLimit {
+    /// A file produced by the reference execution.
+    pub reference: FileUuid,
+    /// The multiple of the CPU time of the reference execution that is allowed.
+    pub factor: f64,
+}
+
 /// Status of a completed [`Execution`](struct.Execution.html).
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub enum ExecutionStatus {
@@ -274,8 +308,23 @@ pub struct ExecutionResourcesUsage {
     pub sys_time: f64,
     /// Number of seconds from the start to the end of the process.
     pub wall_time: f64,
-    /// Number of KiB used _at most_ by the process.
+    /// Number of KiB used _at most_ by the process, i.e. its peak memory usage. The memory limit is
+    /// checked against this value.
     pub memory: u64,
+    /// Number of KiB used by the process when it exited. This may be lower than `memory` if the
+    /// process freed some memory before exiting. If the sandbox only reports the peak memory usage,
+    /// this is equal to `memory`.
+    pub final_memory: u64,
+}
+
+/// The reason why a cached result has been used for an execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheHitReason {
+    /// The cached result has exactly the same limits of the execution.
+    Exact,
+    /// The cached result has different limits, but they are compatible with the ones of the
+    /// execution.
+    LimitExtended,
 }
 
 /// The result of an [`Execution`](struct.Execution.html).
@@ -287,6 +336,14 @@ pub struct ExecutionResult {
     pub was_killed: bool,
     /// Whether the execution result come from the cache.
     pub was_cached: bool,
+    /// Why the cached result has been considered valid for the execution, if the result come from
+    /// the cache.
+    #[serde(default)]
+    pub cache_hit: Option<CacheHitReason>,
+    /// The output files, with their paths inside the sandbox, that the execution didn't create.
+    /// They are produced anyway, as empty files.
+    #[serde(default)]
+    pub missing_outputs: Vec<PathBuf>,
     /// Resources used by the execution.
     pub resources: ExecutionResourcesUsage,
     /// Captured standard output of the execution, if the capture was requested.
@@ -479,6 +536,11 @@ impl Execution {
             copy_env: Vec::new(),
 
             limits: ExecutionLimits::default(),
+            relative_time_limit: None,
+
+            cacheable: true,
+
+            normalize_outputs: false,
         }
     }
 
@@ -647,6 +709,70 @@ impl Execution {
         &mut self.limits
     }
 
+    /// Limit the CPU time of the execution to `factor` times the CPU time of the execution that
+    /// produces `reference`. The lowest between this limit and `limits.cpu_time` is enforced.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand, File};
+    ///
+    /// let official_output = File::new("official output");
+    /// let mut exec = Execution::new("solution", ExecutionCommand::local("sol"));
+    /// exec.relative_time_limit(official_output.uuid, 2.0);
+    /// assert_eq!(exec.relative_time_limit.unwrap().factor, 2.0);
+    /// ```
+    pub fn relative_time_limit(&mut self, reference: FileUuid, factor: f64) -> &mut Self {
+        self.relative_time_limit = Some(RelativeTimeLimit { reference, factor });
+        self
+    }
+
+    /// Set whether the execution can be cached. Non-cacheable executions are always executed, for
+    /// example because they use some external source of randomness.
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("random generator", ExecutionCommand::local("foo"));
+    /// assert!(exec.cacheable);
+    /// exec.cacheable(false);
+    /// assert!(!exec.cacheable);
+    /// ```
+    pub fn cacheable(&mut self, cacheable: bool) -> &mut Self {
+        self.cacheable = cacheable;
+        self
+    }
+
+    /// Set whether the captured stdout and the output files are normalized as text files before
+    /// being stored, see [`Execution::normalize_outputs`](#structfield.normalize_outputs).
+    ///
+    /// ```
+    /// use task_maker_dag::{Execution, ExecutionCommand};
+    ///
+    /// let mut exec = Execution::new("generator", ExecutionCommand::local("gen"));
+    /// assert!(!exec.normalize_outputs);
+    /// exec.normalize_outputs(true);
+    /// assert!(exec.normalize_outputs);
+    /// ```
+    pub fn normalize_outputs(&mut self, normalize: bool) -> &mut Self {
+        self.normalize_outputs = normalize;
+        self
+    }
+
+    /// List of all the [File](struct.File.html) produced by the execution, including `stdout` and
+    /// `stderr`.
+    pub fn outputs(&self) -> Vec<FileUuid> {
+        let mut outs = vec![];
+        if let ExecutionOutputBehaviour::Capture { file: stdout, .. } = &self.stdout {
+            outs.push(stdout.uuid);
+        }
+        if let ExecutionOutputBehaviour::Capture { file: stderr, .. } = &self.stderr {
+            outs.push(stderr.uuid);
+        }
+        for output in self.output_files.values() {
+            outs.push(output.uuid);
+        }
+        outs
+    }
+
     /// Compute the [`ExecutionStatus`](struct.ExecutionStatus.html) based on the result of the
     /// execution, checking the signals, the return code and the time/memory constraints.
     pub fn status(
@@ -671,6 +797,7 @@ impl Execution {
                 return ExecutionStatus::WallTimeLimitExceeded;
             }
         }
+        // the peak memory usage is what matters, even if some memory has been freed later
         if let Some(memory_limit) = self.limits.memory {
             if resources.memory > memory_limit {
                 return ExecutionStatus::MemoryLimitExceeded;
@@ -679,12 +806,43 @@ impl Execution {
         status.clone()
     }
 
+    /// The command line of this execution, as it would be typed in a shell inside the sandbox
+    /// directory: the environment variables set for it, the command and its arguments. The input
+    /// and output redirections are not included.
+    pub fn command_line(&self) -> String {
+        let mut env: Vec<_> = self.env.iter().collect();
+        env.sort();
+        let command = match &self.command {
+            ExecutionCommand::System(path) => path.to_string_lossy().into_owned(),
+            ExecutionCommand::Local(path) => {
+                Path::new(".").join(path).to_string_lossy().into_owned()
+            }
+            ExecutionCommand::TypstCompilation { .. } => "typst".into(),
+        };
+        env.into_iter()
+            .map(|(key, value)| format!("{key}={}", shell_quote(value)))
+            .chain(std::iter::once(shell_quote(&command)))
+            .chain(self.args.iter().map(|arg| shell_quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     /// Converts this execution into an execution group.
     pub fn into_group(self) -> ExecutionGroup {
         self.into()
     }
 }
 
+/// Quote an argument for the shell, if it contains characters that would be interpreted.
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
 impl std::fmt::Debug for ExecutionCallbacks {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         formatter
@@ -697,12 +855,23 @@ impl std::fmt::Debug for ExecutionCallbacks {
     }
 }
 
+impl std::fmt::Display for CacheHitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheHitReason::Exact => write!(f, "exact match"),
+            CacheHitReason::LimitExtended => write!(f, "matched with different limits"),
+        }
+    }
+}
+
 impl std::fmt::Debug for ExecutionResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ExecutionResult")
             .field("status", &self.status)
             .field("was_killed", &self.was_killed)
             .field("was_cached", &self.was_cached)
+            .field("cache_hit", &self.cache_hit)
+            .field("missing_outputs", &self.missing_outputs)
             .field("resources", &self.resources)
             .field(
                 "stdout",
@@ -742,6 +911,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                final_memory: 0,
             },
         );
         assert_eq!(ExecutionStatus::Success, status);
@@ -758,6 +928,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                final_memory: 0,
             },
         );
         assert_eq!(ExecutionStatus::TimeLimitExceeded, status);
@@ -774,6 +945,7 @@ mod tests {
                 sys_time: 1.1,
                 wall_time: 0.0,
                 memory: 0,
+                final_memory: 0,
             },
         );
         assert_eq!(ExecutionStatus::SysTimeLimitExceeded, status);
@@ -790,6 +962,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 1.1,
                 memory: 0,
+                final_memory: 0,
             },
         );
         assert_eq!(ExecutionStatus::WallTimeLimitExceeded, status);
@@ -806,6 +979,24 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 1235,
+                final_memory: 1235,
+            },
+        );
+        assert_eq!(ExecutionStatus::MemoryLimitExceeded, status);
+    }
+
+    #[test]
+    fn test_status_memory_peak() {
+        let mut exec = Execution::new("foo", ExecutionCommand::local("foo"));
+        exec.limits_mut().memory(1234);
+        let status = exec.status(
+            &ExecutionStatus::Success,
+            &ExecutionResourcesUsage {
+                cpu_time: 0.0,
+                sys_time: 0.0,
+                wall_time: 0.0,
+                memory: 1235,
+                final_memory: 100,
             },
         );
         assert_eq!(ExecutionStatus::MemoryLimitExceeded, status);
@@ -821,6 +1012,7 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                final_memory: 0,
             },
         );
         assert_eq!(ExecutionStatus::Signal(11, "Killed".into()), status);
@@ -836,8 +1028,28 @@ mod tests {
                 sys_time: 0.0,
                 wall_time: 0.0,
                 memory: 0,
+                final_memory: 0,
             },
         );
         assert_eq!(ExecutionStatus::ReturnCode(1), status);
     }
+
+    #[test]
+    fn test_command_line() {
+        let mut exec = Execution::new("exec", ExecutionCommand::local("generator"));
+        exec.args(vec!["1", "two words", "it's"])
+            .env("TM_SUBTASK", "2")
+            .env("TM_SUBTASK_NAME", "st 2");
+        assert_eq!(
+            exec.command_line(),
+            "TM_SUBTASK=2 TM_SUBTASK_NAME='st 2' ./generator 1 'two words' 'it'\\''s'"
+        );
+    }
+
+    #[test]
+    fn test_command_line_system() {
+        let mut exec = Execution::new("exec", ExecutionCommand::system("python3"));
+        exec.args(vec!["gen.py"]);
+        assert_eq!(exec.command_line(), "python3 gen.py");
+    }
 }
@@ -4,8 +4,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    Execution, ExecutionDAGConfig, ExecutionInputBehaviour, ExecutionOutputBehaviour, ExecutionTag,
-    FileUuid, Priority,
+    Execution, ExecutionDAGConfig, ExecutionInputBehaviour, ExecutionTag, FileUuid, Priority,
 };
 
 /// Directory inside the sandbox where to place all the pipes of the group. This is used to allow
@@ -112,7 +111,7 @@ impl ExecutionGroup {
     }
 
     /// List of all the [File](struct.File.html) dependencies of the execution
-    /// group, including `stdin`.
+    /// group, including `stdin` and the reference of the relative time limits.
     pub fn dependencies(&self) -> Vec<FileUuid> {
         let mut deps = vec![];
         for exec in &self.executions {
@@ -122,6 +121,9 @@ impl ExecutionGroup {
             for input in exec.input_files.values() {
                 deps.push(input.file);
             }
+            if let Some(relative) = &exec.relative_time_limit {
+                deps.push(relative.reference);
+            }
         }
         deps
     }
@@ -129,19 +131,16 @@ impl ExecutionGroup {
     /// List of all the [File](struct.File.html) produced by the execution
     /// group, including `stdout` and `stderr`.
     pub fn outputs(&self) -> Vec<FileUuid> {
-        let mut outs = vec![];
-        for exec in &self.executions {
-            if let ExecutionOutputBehaviour::Capture { file: stdout, .. } = &exec.stdout {
-                outs.push(stdout.uuid);
-            }
-            if let ExecutionOutputBehaviour::Capture { file: stderr, .. } = &exec.stderr {
-                outs.push(stderr.uuid);
-            }
-            for output in exec.output_files.values() {
-                outs.push(output.uuid);
-            }
-        }
-        outs
+        self.executions
+            .iter()
+            .flat_map(Execution::outputs)
+            .collect()
+    }
+
+    /// Whether the results of the group can be cached: this is true only if all the executions of
+    /// the group are cacheable.
+    pub fn is_cacheable(&self) -> bool {
+        self.executions.iter().all(|exec| exec.cacheable)
     }
 }
 
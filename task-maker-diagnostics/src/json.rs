@@ -0,0 +1,93 @@
+//! The machine-readable representation of the diagnostics, produced by
+//! [`DiagnosticContext::to_json`](crate::DiagnosticContext::to_json).
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CodeSpan, Diagnostic, DiagnosticLevel};
+
+/// The version of the JSON document of the diagnostics. It must be bumped every time a field is
+/// removed or changes meaning, so that the consumers can detect the incompatible documents.
+pub const DIAGNOSTICS_JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The JSON document with all the diagnostics of a [`DiagnosticContext`](crate::DiagnosticContext).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsJson {
+    /// The version of this document, see [`DIAGNOSTICS_JSON_SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// The diagnostics, in the order they have been emitted.
+    pub diagnostics: Vec<DiagnosticJson>,
+}
+
+/// The JSON representation of a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticJson {
+    /// The level of the diagnostic.
+    pub level: DiagnosticLevel,
+    /// The main message of the diagnostic.
+    pub message: String,
+    /// The additional notes of the diagnostic, if any.
+    pub note: Option<String>,
+    /// The help for fixing the problem, if any.
+    pub help: Option<String>,
+    /// The spans of code the diagnostic refers to.
+    pub spans: Vec<CodeSpanJson>,
+}
+
+/// The JSON representation of a [`CodeSpan`]. The end position is exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeSpanJson {
+    /// The path of the file the span comes from.
+    pub file: PathBuf,
+    /// Where the span starts.
+    pub start: PositionJson,
+    /// Where the span ends, just after its last byte.
+    pub end: PositionJson,
+    /// The label of the span, telling the role of that part of the code, if any.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// A position inside a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionJson {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 0-based offset, in bytes, from the start of the line.
+    pub column: usize,
+    /// The 0-based offset, in bytes, from the start of the file.
+    pub byte: usize,
+}
+
+impl From<&Diagnostic> for DiagnosticJson {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Self {
+            level: diagnostic.level(),
+            message: diagnostic.message().into(),
+            note: diagnostic.note().map(Into::into),
+            help: diagnostic.help().map(Into::into),
+            spans: diagnostic.code_spans().iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&CodeSpan> for CodeSpanJson {
+    fn from(span: &CodeSpan) -> Self {
+        let start = PositionJson {
+            line: span.line_number(),
+            column: span.line_offset(),
+            byte: span.file_offset(),
+        };
+        Self {
+            file: span.file_name().into(),
+            start,
+            end: PositionJson {
+                column: start.column + span.len(),
+                byte: start.byte + span.len(),
+                ..start
+            },
+            label: span.label().map(Into::into),
+        }
+    }
+}
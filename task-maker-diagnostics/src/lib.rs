@@ -3,14 +3,46 @@
 
 #![deny(missing_docs)]
 
+mod json;
 mod span;
 
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use colored::{Color, Colorize};
+pub use json::{
+    CodeSpanJson, DiagnosticJson, DiagnosticsJson, PositionJson, DIAGNOSTICS_JSON_SCHEMA_VERSION,
+};
 use serde::{Deserialize, Serialize};
 pub use span::CodeSpan;
 
+/// Whether the colors have been forced off, and the diagnostics are printed with
+/// [`Diagnostic::print_plain`].
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Force the colors of the printed diagnostics on (`Some(true)`) or off (`Some(false)`), or detect
+/// whether to use them from the environment (`None`).
+///
+/// When the colors are forced off the diagnostics are printed without any escape sequence, instead
+/// of relying on the detection of the terminal.
+pub fn set_color_override(colors: Option<bool>) {
+    PLAIN.store(colors == Some(false), Ordering::Relaxed);
+    match colors {
+        Some(colors) => colored::control::set_override(colors),
+        None => colored::control::unset_override(),
+    }
+}
+
+/// Make `text` bold and, if provided, of the given color. When `plain` is set the text is left
+/// as it is.
+pub(crate) fn style(text: &str, color: Option<Color>, plain: bool) -> String {
+    match (plain, color) {
+        (true, _) => text.to_string(),
+        (false, Some(color)) => text.color(color).bold().to_string(),
+        (false, None) => text.bold().to_string(),
+    }
+}
+
 /// The level of the message.
 ///
 /// This influences the color of the output, and the order in which the diagnostics are shown.
@@ -114,19 +146,37 @@ impl Diagnostic {
         self
     }
 
+    /// Attach a [`CodeSpan`] to the diagnostic, with a label telling the role of that part of the
+    /// code, useful when the diagnostic refers to more places.
+    pub fn with_labeled_code_span(self, code_span: CodeSpan, label: impl Into<String>) -> Self {
+        self.with_code_span(code_span.with_label(label))
+    }
+
     /// Print this diagnostic to the formatter. This is used by the [`std::fmt::Display`] trait.
+    ///
+    /// If the colors have been forced off with [`set_color_override`] this is the same as
+    /// [`Diagnostic::print_plain`].
     pub fn print(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // TODO: additional printing options (e.g. no colors, compact, ...)
+        self.print_with(f, PLAIN.load(Ordering::Relaxed))
+    }
+
+    /// Print this diagnostic to the formatter without colors or any other escape sequence.
+    pub fn print_plain(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.print_with(f, true)
+    }
+
+    /// Print this diagnostic to the formatter, with the colors unless `plain` is set.
+    fn print_with(&self, f: &mut Formatter<'_>, plain: bool) -> std::fmt::Result {
         let level = self.level.as_str();
         let pad = level.len();
         writeln!(
             f,
             "{}: {}",
-            level.color(self.level.color()).bold(),
+            style(level, Some(self.level.color()), plain),
             self.message
         )?;
         if let Some(note) = &self.note {
-            write!(f, "{:>pad$}: ", "Note".bold(), pad = pad)?;
+            write!(f, "{:>pad$}: ", style("Note", None, plain), pad = pad)?;
             let mut lines = note.lines();
             if let Some(line) = lines.next() {
                 writeln!(f, "{line}")?;
@@ -136,7 +186,13 @@ impl Diagnostic {
             }
         }
         if let Some(help) = &self.help {
-            writeln!(f, "{:>pad$}: {}", "Help".bold(), help, pad = pad)?;
+            writeln!(
+                f,
+                "{:>pad$}: {}",
+                style("Help", None, plain),
+                help,
+                pad = pad
+            )?;
         }
         if let Some(attachment) = &self.help_attachment {
             let attachment = String::from_utf8_lossy(attachment);
@@ -157,7 +213,7 @@ impl Diagnostic {
             }
         }
         for code_span in &self.code_spans {
-            for line in code_span.to_string(self.level).lines() {
+            for line in code_span.render(self.level, plain).lines() {
                 writeln!(f, "{:>pad$} {}", "", line, pad = pad + 1)?;
             }
         }
@@ -173,6 +229,21 @@ impl Diagnostic {
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// Get the note attached to this diagnostic, if any.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    /// Get the help message attached to this diagnostic, if any.
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    /// Get the spans of code attached to this diagnostic.
+    pub fn code_spans(&self) -> &[CodeSpan] {
+        &self.code_spans
+    }
 }
 
 impl Display for Diagnostic {
@@ -181,6 +252,9 @@ impl Display for Diagnostic {
     }
 }
 
+/// A [`Diagnostic`] can be returned as an error, printing its code spans alongside the message.
+impl std::error::Error for Diagnostic {}
+
 /// The context that contains all the emitted diagnostic messages.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DiagnosticContext {
@@ -203,4 +277,102 @@ impl DiagnosticContext {
     pub fn diagnostics(&self) -> &[Diagnostic] {
         &self.diagnostics
     }
+
+    /// Build the machine-readable representation of the diagnostics, with the positions of their
+    /// spans of code, to be serialized as JSON.
+    pub fn to_json(&self) -> DiagnosticsJson {
+        DiagnosticsJson {
+            schema_version: DIAGNOSTICS_JSON_SCHEMA_VERSION,
+            diagnostics: self.diagnostics.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Display a diagnostic with [`Diagnostic::print_plain`].
+    struct Plain<'a>(&'a Diagnostic);
+
+    impl Display for Plain<'_> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            self.0.print_plain(f)
+        }
+    }
+
+    #[test]
+    fn test_print_plain() {
+        colored::control::set_override(true);
+        let diagnostic = Diagnostic::error("Something failed")
+            .with_note("A note")
+            .with_help("Some help")
+            .with_code_span(CodeSpan::from_str("file.txt", "content", 0, 2).unwrap());
+        assert!(diagnostic.to_string().contains('\x1b'));
+
+        let plain = Plain(&diagnostic).to_string();
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.starts_with("Error: Something failed\n"));
+        assert!(plain.contains(" Note: A note\n"));
+        assert!(plain.contains(" Help: Some help\n"));
+        assert!(plain.contains("^^\n"));
+    }
+
+    #[test]
+    fn test_labeled_code_spans() {
+        let content = "first\nsecond";
+        let diagnostic = Diagnostic::error("Defined twice")
+            .with_labeled_code_span(
+                CodeSpan::from_str("file.txt", content, 0, 5).unwrap(),
+                "first defined here",
+            )
+            .with_labeled_code_span(
+                CodeSpan::from_str("file.txt", content, 6, 6).unwrap(),
+                "redefined here",
+            );
+        let plain = Plain(&diagnostic).to_string();
+        assert!(plain.contains("^^^^^ first defined here\n"));
+        assert!(plain.contains("^^^^^^ redefined here\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut context = DiagnosticContext::new();
+        context.add_diagnostic(
+            Diagnostic::warning("Bad line")
+                .with_help("Fix it")
+                .with_labeled_code_span(
+                    CodeSpan::from_str("file.txt", "content\nnope", 9, 2).unwrap(),
+                    "here",
+                ),
+        );
+        context.add_diagnostic(Diagnostic::error("Something failed"));
+        let json = context.to_json();
+        assert_eq!(json.schema_version, DIAGNOSTICS_JSON_SCHEMA_VERSION);
+        assert_eq!(json.diagnostics.len(), 2);
+        assert_eq!(
+            json.diagnostics[0],
+            DiagnosticJson {
+                level: DiagnosticLevel::Warning,
+                message: "Bad line".into(),
+                note: None,
+                help: Some("Fix it".into()),
+                spans: vec![CodeSpanJson {
+                    file: "file.txt".into(),
+                    start: PositionJson {
+                        line: 2,
+                        column: 1,
+                        byte: 9
+                    },
+                    end: PositionJson {
+                        line: 2,
+                        column: 3,
+                        byte: 11
+                    },
+                    label: Some("here".into()),
+                }],
+            }
+        );
+        assert!(json.diagnostics[1].spans.is_empty());
+    }
 }
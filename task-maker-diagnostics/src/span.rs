@@ -1,12 +1,11 @@
 use std::fmt::Write;
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Error};
-use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
-use crate::DiagnosticLevel;
+use crate::{style, DiagnosticLevel};
 
 /// A [`CodeSpan`] represent a slice of code.
 ///
@@ -25,6 +24,9 @@ pub struct CodeSpan {
     len: usize,
     /// The content of the line.
     line: String,
+    /// The label to show next to the span, telling what this part of the code is.
+    #[serde(default)]
+    label: Option<String>,
 }
 
 impl CodeSpan {
@@ -59,11 +61,18 @@ impl CodeSpan {
                 file_offset: offset,
                 line_offset,
                 len,
+                label: None,
             });
         }
         bail!("The offset exceeds the length of the file")
     }
 
+    /// Attach a label to this span, shown next to it.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
     /// Get the content of the span as a `&str`.
     pub fn as_str(&self) -> &str {
         #[allow(clippy::string_slice)] // All the offsets are byte-related
@@ -72,6 +81,11 @@ impl CodeSpan {
 
     /// Obtain a string (with colors) of this span.
     pub fn to_string(&self, level: DiagnosticLevel) -> String {
+        self.render(level, false)
+    }
+
+    /// Obtain a string of this span, with the colors of the level unless `plain` is set.
+    pub(crate) fn render(&self, level: DiagnosticLevel, plain: bool) -> String {
         let mut result = format!(
             "{}:{}:{}\n",
             self.file_name.display(),
@@ -85,9 +99,13 @@ impl CodeSpan {
         let pad = line_number.len() + 3 + self.line_offset;
         result += &" ".repeat(pad);
 
-        let color = level.color();
+        let marker = style("^", Some(level.color()), plain);
         for _ in 0..(self.len.max(1)) {
-            let _ = write!(result, "{}", "^".color(color).bold());
+            result += &marker;
+        }
+        if let Some(label) = &self.label {
+            result += " ";
+            result += &style(label, Some(level.color()), plain);
         }
         result += "\n";
         result
@@ -97,11 +115,46 @@ impl CodeSpan {
     pub fn line_number(&self) -> usize {
         self.line_number.get()
     }
+
+    /// Get the path of the file where this span comes from.
+    pub fn file_name(&self) -> &Path {
+        &self.file_name
+    }
+
+    /// Get the offset of the first byte of the span, relative to the start of the file.
+    pub fn file_offset(&self) -> usize {
+        self.file_offset
+    }
+
+    /// Get the offset of the first byte of the span, relative to the start of the line.
+    pub fn line_offset(&self) -> usize {
+        self.line_offset
+    }
+
+    /// Get the length of the span, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the span is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the content of the line of the span.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Get the label of the span, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::CodeSpan;
+    use crate::{CodeSpan, DiagnosticLevel};
 
     #[test]
     fn test_empty() {
@@ -120,7 +173,8 @@ mod tests {
                 line: "content".to_string(),
                 file_offset: 0,
                 line_offset: 0,
-                len: 2
+                len: 2,
+                label: None,
             }
         );
         assert_eq!(span.as_str(), "co");
@@ -137,7 +191,8 @@ mod tests {
                 line: "content".to_string(),
                 file_offset: 0,
                 line_offset: 0,
-                len: 7
+                len: 7,
+                label: None,
             }
         );
         assert_eq!(span.as_str(), "content");
@@ -154,7 +209,8 @@ mod tests {
                 line: "content".to_string(),
                 file_offset: 0,
                 line_offset: 0,
-                len: 7
+                len: 7,
+                label: None,
             }
         );
         assert_eq!(span.as_str(), "content");
@@ -171,7 +227,8 @@ mod tests {
                 line: "nope".to_string(),
                 file_offset: 9,
                 line_offset: 1,
-                len: 2
+                len: 2,
+                label: None,
             }
         );
         assert_eq!(span.as_str(), "op");
@@ -188,9 +245,22 @@ mod tests {
                 line: "nope".to_string(),
                 file_offset: 9,
                 line_offset: 1,
-                len: 2
+                len: 2,
+                label: None,
             }
         );
         assert_eq!(span.as_str(), "op");
     }
+
+    #[test]
+    fn test_render_label() {
+        let span = CodeSpan::from_str("file.txt", "content\nnope", 9, 2)
+            .unwrap()
+            .with_label("here");
+        assert_eq!(span.label(), Some("here"));
+        assert_eq!(
+            span.render(DiagnosticLevel::Error, true),
+            "file.txt:2:1\n2 | nope\n     ^^ here\n"
+        );
+    }
 }
@@ -58,10 +58,18 @@ pub enum DAGError {
 /// Validate the DAG checking if all the required pieces are present and they actually make a DAG.
 /// It's checked that no duplicated UUID are present, no files are missing, all the executions are
 /// reachable and no cycles are present.
-pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Result<(), DAGError> {
+///
+/// When the DAG is sent in chunks, `previous_files` are the files of the chunks already sent
+/// (provided or produced by them): the executions of this chunk can depend on them, but they cannot
+/// be provided or produced again.
+pub fn check_dag(
+    dag: &ExecutionDAGData,
+    callbacks: &ExecutionDAGWatchSet,
+    previous_files: &HashSet<FileUuid>,
+) -> Result<(), DAGError> {
     let mut dependencies: HashMap<FileUuid, Vec<ExecutionGroupUuid>> = HashMap::new();
     let mut num_dependencies: HashMap<ExecutionGroupUuid, usize> = HashMap::new();
-    let mut known_files: HashSet<FileUuid> = HashSet::new();
+    let mut known_files: HashSet<FileUuid> = previous_files.clone();
     let mut known_execs: HashSet<ExecutionGroupUuid> = HashSet::new();
     let mut ready_groups: VecDeque<ExecutionGroupUuid> = VecDeque::new();
     let mut ready_files: VecDeque<FileUuid> = VecDeque::new();
@@ -100,6 +108,12 @@ pub fn check_dag(dag: &ExecutionDAGData, callbacks: &ExecutionDAGWatchSet) -> Re
             ready_groups.push_back(*group_uuid);
         }
     }
+    // the files of the previous chunks are already available
+    for uuid in previous_files {
+        if dependencies.contains_key(uuid) {
+            ready_files.push_back(*uuid);
+        }
+    }
     // add the provided files
     for uuid in dag.provided_files.keys() {
         ready_files.push_back(*uuid);
@@ -188,7 +202,28 @@ mod tests {
         let file = File::new("file");
         exec.stdin(file);
         dag.add_execution(exec);
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &HashSet::new()).is_err());
+    }
+
+    #[test]
+    fn test_previous_file() {
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        let file = File::new("file");
+        exec.stdin(file.clone());
+        dag.add_execution(exec);
+        let previous = [file.uuid].iter().cloned().collect();
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &previous).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_previous_file() {
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::local("foo"));
+        let file = exec.capture_stdout(None);
+        dag.add_execution(exec);
+        let previous = [file.uuid].iter().cloned().collect();
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &previous).is_err());
     }
 
     #[test]
@@ -200,7 +235,7 @@ mod tests {
             files: [file.uuid].iter().cloned().collect(),
             urgent_files: Default::default(),
         };
-        assert!(check_dag(&dag.data, &watch).is_err());
+        assert!(check_dag(&dag.data, &watch, &HashSet::new()).is_err());
     }
 
     #[test]
@@ -213,7 +248,7 @@ mod tests {
             files: Default::default(),
             urgent_files: Default::default(),
         };
-        assert!(check_dag(&dag.data, &watch).is_err());
+        assert!(check_dag(&dag.data, &watch, &HashSet::new()).is_err());
     }
 
     #[test]
@@ -223,7 +258,7 @@ mod tests {
         let stdout = exec.capture_stdout(None);
         exec.stdin(stdout);
         dag.add_execution(exec);
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &HashSet::new()).is_err());
     }
 
     #[test]
@@ -235,7 +270,7 @@ mod tests {
         exec2.stdin(exec1.capture_stdout(None));
         dag.add_execution(exec1);
         dag.add_execution(exec2);
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &HashSet::new()).is_err());
     }
 
     #[test]
@@ -251,7 +286,7 @@ mod tests {
         exec2.stdout = behaviour;
         dag.add_execution(exec1);
         dag.add_execution(exec2);
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &HashSet::new()).is_err());
     }
 
     #[test]
@@ -261,6 +296,6 @@ mod tests {
         let file = exec.capture_stdout(None);
         dag.add_execution(exec);
         dag.provide_file(file, "/dev/null").unwrap();
-        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default()).is_err());
+        assert!(check_dag(&dag.data, &ExecutionDAGWatchSet::default(), &HashSet::new()).is_err());
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
@@ -7,9 +7,13 @@ use std::thread;
 use std::thread::JoinHandle;
 use std::time::{Duration, SystemTime};
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use ductile::{ChannelReceiver, ChannelSender};
-use task_maker_dag::{ExecutionDAG, FileCallbacks, FileUuid, ProvidedFile, WriteToCallback};
+use task_maker_cache::CacheStats;
+use task_maker_dag::{
+    ExecutionDAG, ExecutionDAGData, ExecutionGroupUuid, FileCallbacks, FileUuid, ProvidedFile,
+    WriteToCallback,
+};
 use task_maker_store::*;
 
 use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, ExecutorWorkerStatus};
@@ -27,7 +31,16 @@ impl ExecutorClient {
     /// Begin the evaluation sending the DAG to the server, sending the files as needed and storing
     /// the files from the server.
     ///
-    /// This method is blocking until the server ends the computation.
+    /// This method is blocking until the server ends the computation. Returns the hits and misses
+    /// of the cache lookups of the executions, if the server sent them.
+    ///
+    /// If the DAG has a `stream_chunk_size`, it's sent in chunks: the next chunk is sent when the
+    /// server has fewer executions to do than the size of a chunk. All the chunks are prepared
+    /// before the first one is sent, so only the memory of the server is reduced.
+    ///
+    /// The late files of the DAG are sent as soon as the callbacks give their content. If the
+    /// server is waiting only for them, the executions that depend on the ones not given yet are
+    /// skipped.
     ///
     /// * `eval` - The EvaluationData to evaluate.
     /// * `sender` - A channel that sends messages to the server.
@@ -35,7 +48,7 @@ impl ExecutorClient {
     ///
     /// ```
     /// use task_maker_dag::ExecutionDAG;
-    /// use task_maker_store::FileStore;
+    /// use task_maker_store::{FileStore, DEFAULT_BLOCK_SIZE};
     /// use task_maker_exec::{executors::LocalExecutor, ExecutorClient, SuccessSandboxRunner};
     /// use std::sync::mpsc::channel;
     /// use std::sync::{Arc, Mutex};
@@ -53,7 +66,7 @@ impl ExecutorClient {
     /// # let tmpdir = TempDir::new().unwrap();
     /// # let path = tmpdir.path().to_owned();
     /// # let sandbox_runner = SuccessSandboxRunner::default();
-    /// let file_store = Arc::new(FileStore::new(&path, 1000, 1000).expect("Cannot create the file store"));
+    /// let file_store = Arc::new(FileStore::new(&path, 1000, 1000, Default::default(), None, DEFAULT_BLOCK_SIZE).expect("Cannot create the file store"));
     /// let server_file_store = file_store.clone();
     /// // make a new local executor in a second thread
     /// let server = thread::spawn(move || {
@@ -73,12 +86,24 @@ impl ExecutorClient {
         receiver: &ChannelReceiver<ExecutorServerMessage>,
         file_store: Arc<FileStore>,
         mut status_callback: F,
-    ) -> Result<(), Error>
+    ) -> Result<Option<CacheStats>, Error>
     where
         F: FnMut(ExecutorStatus<SystemTime>) -> Result<(), Error>,
     {
         trace!("ExecutorClient started");
-        ExecutorClient::start_evaluation(&mut dag, &sender)?;
+        // the executions may be dropped after being sent, keep their descriptions
+        let descriptions: HashMap<_, _> = if dag.data.config.explain_cache_miss {
+            dag.data
+                .execution_groups
+                .values()
+                .map(|group| (group.uuid, group.description.clone()))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        // the late files not provided yet, with the executions that depend on them
+        let mut late_files = late_file_consumers(&dag.data);
+        let mut pending_chunks = ExecutorClient::start_evaluation(&mut dag, &sender, &file_store)?;
 
         // setup the status poller that will send to the server a Status message every
         // STATUS_POLL_INTERVAL_MS milliseconds.
@@ -96,6 +121,9 @@ impl ExecutorClient {
         }}
 
         let mut missing_files = None;
+        let mut cache_stats = None;
+        let skip_handle = dag.skip_handle();
+        let provide_handle = dag.provide_handle();
         while missing_files.unwrap_or(1) > 0 {
             match receiver.recv() {
                 Ok(ExecutorServerMessage::AskFile(uuid)) => {
@@ -105,9 +133,10 @@ impl ExecutorClient {
                         .lock()
                         .map_err(|_| anyhow!("Failed to obtain file_mode lock"))?;
                     let provided_files = &dag.data.provided_files;
-                    handle_server_ask_file(uuid, provided_files, &sender).with_context(|| {
-                        format!("Failed to process AskFile({uuid}) from the server")
-                    })?;
+                    handle_server_ask_file(uuid, provided_files, &file_store, &sender)
+                        .with_context(|| {
+                            format!("Failed to process AskFile({uuid}) from the server")
+                        })?;
                 }
                 Ok(ExecutorServerMessage::ProvideFile(uuid, success)) => {
                     info!("Server sent the file {uuid}, success: {success}");
@@ -155,6 +184,16 @@ impl ExecutorClient {
                         }
                     }
                 }
+                Ok(ExecutorServerMessage::NotifyCacheMiss(uuid, reason)) => {
+                    info!("Execution {uuid} is not cached: {reason}");
+                    let description = descriptions
+                        .get(&uuid)
+                        .cloned()
+                        .unwrap_or_else(|| uuid.to_string());
+                    for callback in dag.cache_miss_callbacks() {
+                        callback(&description, &reason)?;
+                    }
+                }
                 Ok(ExecutorServerMessage::Error(error)) => {
                     error!("Error occurred: {error}");
                     sender
@@ -164,20 +203,50 @@ impl ExecutorClient {
                 }
                 Ok(ExecutorServerMessage::Status(status)) => {
                     info!("Server status: {status:?}");
+                    // send the next chunk of the DAG when the server is running out of work
+                    if let Some(chunk_size) = dag.data.config.stream_chunk_size {
+                        if status.ready_execs + status.waiting_execs < chunk_size {
+                            if let Some(chunk) = pending_chunks.pop_front() {
+                                info!("Sending the next chunk of the DAG");
+                                sender
+                                    .send(chunk)
+                                    .context("Failed to send the next chunk of the DAG")?;
+                            }
+                        }
+                    }
                     handle_server_status(status, &mut status_callback)
                         .context("Failed to process Status() from the server")?;
                 }
+                Ok(ExecutorServerMessage::WaitingForClient) => {
+                    // all the callbacks before this message have been called, so the late files
+                    // not given yet will never be
+                    let to_skip: Vec<_> = late_files
+                        .drain()
+                        .flat_map(|(_, consumers)| consumers)
+                        .collect();
+                    if !to_skip.is_empty() {
+                        warn!(
+                            "Skipping {} executions waiting for late files",
+                            to_skip.len()
+                        );
+                        skip_handle.skip(to_skip);
+                    }
+                }
+                Ok(ExecutorServerMessage::ClientAborted(_)) => {
+                    warn!("Unexpected ClientAborted from the server");
+                }
+                Ok(ExecutorServerMessage::CacheStats(stats)) => {
+                    info!("Cache stats: {stats}");
+                    cache_stats = Some(stats);
+                }
                 Ok(ExecutorServerMessage::Done(result)) => {
                     info!("Execution completed producing {} files!", result.len());
                     let mut missing = 0;
                     for (uuid, key, success) in result {
                         if let Some(handle) = file_store.get(&key) {
-                            let iterator =
-                                ReadFileIterator::new(handle.path()).with_context(|| {
-                                    format!(
-                                        "Failed to read produced file ({handle}) from the local storage"
-                                    )
-                                })?;
+                            let iterator = handle.reader().with_context(|| {
+                                format!("Failed to read produced file ({handle}) from the local storage")
+                            })?;
                             process_provided_file(
                                 dag.file_callbacks(),
                                 uuid,
@@ -211,16 +280,55 @@ impl ExecutorClient {
                     break;
                 }
             }
+            // send to the server the late files the callbacks gave, unless the evaluation is
+            // already done
+            for (uuid, content) in provide_handle.take() {
+                if late_files.remove(&uuid).is_none() {
+                    warn!("File {uuid} is not a late file still to provide, ignoring it");
+                    continue;
+                }
+                if missing_files.is_some() {
+                    continue;
+                }
+                info!("Providing the late file {uuid}");
+                let _lock = file_mode
+                    .lock()
+                    .map_err(|_| anyhow!("Failed to obtain file_mode lock"))?;
+                process_provided_file(
+                    dag.file_callbacks(),
+                    uuid,
+                    true,
+                    vec![content.clone()],
+                    None,
+                )
+                .context("Failed to process late file")?;
+                let key = FileStoreKey::from_content(&content);
+                sender
+                    .send(ExecutorClientMessage::ProvideFile(uuid, key))
+                    .context("Failed to send ExecutorClientMessage::ProvideFile")?;
+                ChannelFileSender::send_data(content, &sender)
+                    .context("Failed to send the late file content")?;
+            }
+            // forward to the server the executions the callbacks asked to skip, unless the
+            // evaluation is already done
+            let to_skip = skip_handle.take();
+            if !to_skip.is_empty() && missing_files.is_none() {
+                sender
+                    .send(ExecutorClientMessage::SkipExecutions(to_skip))
+                    .context("Failed to send SkipExecutions to the server")?;
+            }
         }
-        Ok(())
+        Ok(cache_stats)
     }
 
     /// Start the evaluation calling the file callbacks on the input files and sending the start
-    /// message to the Executor.
+    /// message to the Executor. If the DAG is sent in chunks, only the first one is sent and the
+    /// messages with the next ones are returned, in order.
     fn start_evaluation(
         dag: &mut ExecutionDAG,
         sender: &ChannelSender<ExecutorClientMessage>,
-    ) -> Result<(), Error> {
+        file_store: &FileStore,
+    ) -> Result<VecDeque<ExecutorClientMessage>, Error> {
         // list all the files/executions that want callbacks
         let dag_callbacks = ExecutionDAGWatchSet {
             executions: dag.execution_callbacks().keys().cloned().collect(),
@@ -252,12 +360,86 @@ impl ExecutorClient {
                     )
                     .context("Failed to process file content")?;
                 }
+                // the callbacks are called when its content is given
+                ProvidedFile::Late { .. } => {}
+                ProvidedFile::StoredFile { file, key } => {
+                    let handle = file_store.get(key).ok_or_else(|| {
+                        anyhow!("The file {key} is not in the store ({})", file.description)
+                    })?;
+                    let iterator = handle.reader().context("Failed to read stored file")?;
+                    process_provided_file(
+                        &mut dag.callbacks.as_mut().unwrap().file_callbacks,
+                        *uuid,
+                        true,
+                        iterator,
+                        None,
+                    )
+                    .context("Failed to process stored file")?;
+                }
             }
         }
-        sender.send(ExecutorClientMessage::Evaluate {
-            dag: Box::new(dag.data.clone()),
-            callbacks: Box::new(dag_callbacks),
-        })
+        let chunk_size = match dag.data.config.stream_chunk_size {
+            Some(chunk_size) => chunk_size,
+            None => {
+                sender.send(ExecutorClientMessage::Evaluate {
+                    dag: Box::new(dag.data.clone()),
+                    callbacks: Box::new(dag_callbacks),
+                })?;
+                return Ok(VecDeque::new());
+            }
+        };
+        // the provided files are kept for when the server asks for them
+        let data = ExecutionDAGData {
+            provided_files: dag.data.provided_files.clone(),
+            execution_groups: std::mem::take(&mut dag.data.execution_groups),
+            config: dag.data.config.clone(),
+        };
+        let chunks = data.split_chunks(chunk_size);
+        let num_chunks = chunks.len();
+        let mut watched = dag_callbacks;
+        let mut messages: VecDeque<_> = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut callbacks = ExecutionDAGWatchSet::default();
+                for uuid in chunk.execution_groups.keys() {
+                    if watched.executions.remove(uuid) {
+                        callbacks.executions.insert(*uuid);
+                    }
+                }
+                let files = chunk
+                    .provided_files
+                    .keys()
+                    .cloned()
+                    .chain(chunk.execution_groups.values().flat_map(|g| g.outputs()));
+                for uuid in files {
+                    if watched.files.remove(&uuid) {
+                        callbacks.files.insert(uuid);
+                    }
+                    if watched.urgent_files.remove(&uuid) {
+                        callbacks.urgent_files.insert(uuid);
+                    }
+                }
+                let last = index + 1 == num_chunks;
+                if last {
+                    // the callbacks of the unknown files and executions are sent anyway, so that
+                    // the server rejects them
+                    callbacks.executions.extend(watched.executions.drain());
+                    callbacks.files.extend(watched.files.drain());
+                    callbacks.urgent_files.extend(watched.urgent_files.drain());
+                }
+                ExecutorClientMessage::EvaluateChunk {
+                    dag: Box::new(chunk),
+                    callbacks: Box::new(callbacks),
+                    last,
+                }
+            })
+            .collect();
+        let first = messages
+            .pop_front()
+            .expect("The DAG is split in at least a chunk");
+        sender.send(first)?;
+        Ok(messages)
     }
 
     /// Spawn a thread that will ask the server status every `STATUS_POLL_INTERVAL_MS`, making sure
@@ -297,6 +479,7 @@ impl ExecutorClient {
 fn handle_server_ask_file(
     uuid: FileUuid,
     provided_files: &HashMap<FileUuid, ProvidedFile>,
+    file_store: &FileStore,
     sender: &ChannelSender<ExecutorClientMessage>,
 ) -> Result<(), Error> {
     match &provided_files[&uuid] {
@@ -317,10 +500,45 @@ fn handle_server_ask_file(
             ChannelFileSender::send_data(content.clone(), sender)
                 .context("Failed to send file content")?;
         }
+        ProvidedFile::StoredFile { key, .. } => {
+            let handle = file_store
+                .get(key)
+                .ok_or_else(|| anyhow!("The file {key} is not in the store anymore"))?;
+            sender
+                .send(ExecutorClientMessage::ProvideFile(uuid, key.clone()))
+                .context("Failed to send ExecutorClientMessage::ProvideFile")?;
+            ChannelFileSender::send(handle.path(), sender).with_context(|| {
+                format!(
+                    "Failed to send stored file from {}",
+                    handle.path().display()
+                )
+            })?;
+        }
+        ProvidedFile::Late { .. } => {
+            bail!("The server asked for the late file {uuid}, that is sent without being asked")
+        }
     }
     Ok(())
 }
 
+/// The late files of the DAG, with the executions that depend on each of them.
+fn late_file_consumers(dag: &ExecutionDAGData) -> HashMap<FileUuid, Vec<ExecutionGroupUuid>> {
+    let mut consumers: HashMap<_, Vec<_>> = dag
+        .provided_files
+        .iter()
+        .filter(|(_, file)| matches!(file, ProvidedFile::Late { .. }))
+        .map(|(uuid, _)| (*uuid, Vec::new()))
+        .collect();
+    for group in dag.execution_groups.values() {
+        for dep in group.dependencies() {
+            if let Some(consumers) = consumers.get_mut(&dep) {
+                consumers.push(group.uuid);
+            }
+        }
+    }
+    consumers
+}
+
 /// Handle the server response to the status request.
 fn handle_server_status<F>(
     status: ExecutorStatus<Duration>,
@@ -9,11 +9,12 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
+use tabox::configuration::SandboxConfiguration;
 use tabox::result::SandboxExecutionResult;
 use task_maker_dag::*;
 use task_maker_store::*;
 
-use crate::execution_unit::sandbox::Sandbox;
+use crate::execution_unit::sandbox::{Sandbox, SandboxUser};
 use crate::execution_unit::typst::TypstCompiler;
 use crate::sandbox_runner::SandboxRunner;
 use crate::worker::OutputFile;
@@ -51,6 +52,26 @@ impl Default for SandboxResult {
     }
 }
 
+/// Request to the internal implementation of the sandbox: the configuration of tabox and the limits
+/// that tabox does not support, which the sandbox process applies to itself before starting tabox
+/// so that the sandboxed process inherits them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RawSandboxConfig {
+    /// The configuration of tabox.
+    pub tabox: SandboxConfiguration,
+    /// The limit on the number of file descriptors (`RLIMIT_NOFILE`).
+    pub nofile: Option<u32>,
+}
+
+impl From<SandboxConfiguration> for RawSandboxConfig {
+    fn from(tabox: SandboxConfiguration) -> Self {
+        RawSandboxConfig {
+            tabox,
+            nofile: None,
+        }
+    }
+}
+
 /// Response of the internal implementation of the sandbox.
 #[derive(Debug, Serialize, Deserialize)]
 pub enum RawSandboxResult {
@@ -85,12 +106,14 @@ impl ExecutionUnit {
         execution: &Execution,
         dep_keys: &HashMap<FileUuid, FileStoreHandle>,
         fifo_dir: Option<PathBuf>,
+        user: SandboxUser,
     ) -> Result<ExecutionUnit, Error> {
         if matches!(execution.command, ExecutionCommand::TypstCompilation { .. }) {
             TypstCompiler::new(Path::new("."), execution, dep_keys)
                 .map(|typst_compiler| ExecutionUnit::TypstCompilation(Box::new(typst_compiler)))
         } else {
-            Sandbox::new(sandboxes_dir, execution, dep_keys, fifo_dir).map(ExecutionUnit::Sandbox)
+            Sandbox::new(sandboxes_dir, execution, dep_keys, fifo_dir, user)
+                .map(ExecutionUnit::Sandbox)
         }
     }
 
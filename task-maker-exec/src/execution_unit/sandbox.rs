@@ -13,13 +13,13 @@ use anyhow::{bail, Context, Error};
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
 use tabox::configuration::SandboxConfiguration;
-use tabox::syscall_filter::SyscallFilter;
+use tabox::syscall_filter::{SyscallFilter, SyscallFilterAction};
 use task_maker_dag::*;
 use task_maker_store::*;
 use tempfile::TempDir;
 
 use crate::detect_exe::detect_exe;
-use crate::execution_unit::{RawSandboxResult, SandboxResult};
+use crate::execution_unit::{RawSandboxConfig, RawSandboxResult, SandboxResult};
 use crate::sandbox_runner::SandboxRunner;
 
 /// The list of all the system-wide readable directories inside the sandbox.
@@ -36,6 +36,55 @@ pub const READABLE_DIRS: &[&str] = &[
     "/var/lib/texmf/",
 ];
 
+/// The unprivileged user the processes are run as inside the sandbox.
+///
+/// The sandbox runs in a new user namespace where this user is mapped to the user running the
+/// worker, so it is always able to access the files of the sandbox directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxUser {
+    /// The user id of the processes inside the sandbox.
+    pub uid: u32,
+    /// The group id of the processes inside the sandbox.
+    pub gid: u32,
+}
+
+impl Default for SandboxUser {
+    fn default() -> Self {
+        SandboxUser {
+            uid: 1000,
+            gid: 1000,
+        }
+    }
+}
+
+impl SandboxUser {
+    /// Make a new `SandboxUser` with the specified uid and gid.
+    pub fn new(uid: u32, gid: u32) -> SandboxUser {
+        SandboxUser { uid, gid }
+    }
+
+    /// Check that the user can be used inside the sandbox: it must not be root and the ids must
+    /// not be the reserved `-1` value.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.uid == 0 || self.gid == 0 {
+            bail!(
+                "The sandbox user cannot be root (uid={}, gid={})",
+                self.uid,
+                self.gid
+            );
+        }
+        if self.uid == u32::MAX || self.gid == u32::MAX {
+            bail!(
+                "Invalid sandbox user (uid={}, gid={}): {} is a reserved id",
+                self.uid,
+                self.gid,
+                u32::MAX
+            );
+        }
+        Ok(())
+    }
+}
+
 /// Internals of the sandbox.
 #[derive(Debug)]
 struct SandboxData {
@@ -52,6 +101,8 @@ struct SandboxData {
     box_pid: Arc<AtomicU32>,
     /// Whether we tried to kill the sandbox.
     tried_to_kill: bool,
+    /// The user the process is run as inside the sandbox.
+    user: SandboxUser,
 }
 
 /// Wrapper around the sandbox. Cloning this struct will keep the reference of the same sandbox,
@@ -73,6 +124,7 @@ impl Sandbox {
         execution: &Execution,
         dep_keys: &HashMap<FileUuid, FileStoreHandle>,
         fifo_dir: Option<PathBuf>,
+        user: SandboxUser,
     ) -> Result<Sandbox, Error> {
         std::fs::create_dir_all(sandboxes_dir).with_context(|| {
             format!(
@@ -82,7 +134,7 @@ impl Sandbox {
         })?;
         let boxdir = TempDir::new_in(sandboxes_dir)
             .context("Failed to create sandbox temporary directory")?;
-        Sandbox::setup(boxdir.path(), execution, dep_keys).context("Sandbox setup failed")?;
+        Sandbox::setup(boxdir.path(), execution, dep_keys, user).context("Sandbox setup failed")?;
 
         Ok(Sandbox {
             data: Arc::new(Mutex::new(SandboxData {
@@ -92,6 +144,7 @@ impl Sandbox {
                 fifo_dir,
                 box_pid: Arc::new(AtomicU32::new(0)),
                 tried_to_kill: false,
+                user,
             })),
         })
     }
@@ -103,18 +156,20 @@ impl Sandbox {
         dag_config: &ExecutionDAGConfig,
     ) -> Result<SandboxResult, Error> {
         let mut config = SandboxConfiguration::default();
-        let (boxdir, pid, keep, cmd) = {
+        let (boxdir, pid, keep, nofile, cmd) = {
             let data = self.data.lock().unwrap();
             (
                 data.path().to_owned(),
                 data.box_pid.clone(),
                 data.keep_sandbox,
+                data.execution.limits.nofile,
                 self.build_command(
                     data.path(),
                     &data.execution,
                     &mut config,
                     dag_config,
                     data.fifo_dir.clone(),
+                    data.user,
                 ),
             )
         };
@@ -147,7 +202,11 @@ impl Sandbox {
                 .context("Cannot write command info inside sandbox")?;
         }
 
-        let raw_result = runner.run(config.build(), pid);
+        let raw_config = RawSandboxConfig {
+            tabox: config.build(),
+            nofile,
+        };
+        let raw_result = runner.run(raw_config, pid);
         if keep {
             let target = boxdir.join("result.txt");
             std::fs::write(&target, format!("{raw_result:#?}"))
@@ -160,11 +219,14 @@ impl Sandbox {
         };
         trace!("Sandbox output: {res:?}");
 
+        // the sandbox only reports the peak memory usage, so it's also used as the final one
+        let memory = res.resource_usage.memory_usage / 1024;
         let resources = ExecutionResourcesUsage {
             cpu_time: res.resource_usage.user_cpu_time,
             sys_time: res.resource_usage.system_cpu_time,
             wall_time: res.resource_usage.wall_time_usage,
-            memory: res.resource_usage.memory_usage / 1024,
+            memory,
+            final_memory: memory,
         };
 
         use tabox::result::ExitStatus::*;
@@ -179,7 +241,11 @@ impl Sandbox {
                 exit_status: 0,
                 signal: Some((
                     s as u32,
-                    res.status.signal_name().unwrap_or_else(|| "unknown".into()),
+                    describe_signal(
+                        s,
+                        res.status.signal_name().unwrap_or_else(|| "unknown".into()),
+                        config.syscall_filter.as_ref(),
+                    ),
                 )),
                 resources,
                 was_killed: s == 9 && self.data.lock().unwrap().tried_to_kill,
@@ -306,6 +372,7 @@ impl Sandbox {
         config: &mut SandboxConfiguration,
         dag_config: &ExecutionDAGConfig,
         fifo_dir: Option<PathBuf>,
+        user: SandboxUser,
     ) -> Result<(), Error> {
         let box_root = self.box_root(boxdir);
         config.working_directory(&box_root);
@@ -445,8 +512,8 @@ impl Sandbox {
             config.arg(arg);
         }
         // drop root privileges in the sandbox
-        config.uid(1000);
-        config.gid(1000);
+        config.uid(user.uid as usize);
+        config.gid(user.gid as usize);
         Ok(())
     }
 
@@ -455,6 +522,7 @@ impl Sandbox {
         box_dir: P,
         execution: &Execution,
         dep_keys: &HashMap<FileUuid, FileStoreHandle>,
+        user: SandboxUser,
     ) -> Result<(), Error> {
         let box_dir = box_dir.as_ref();
         trace!(
@@ -467,8 +535,11 @@ impl Sandbox {
         Self::create_sandbox_dir(box_dir, "etc")?;
         std::fs::write(
             box_dir.join("etc").join("passwd"),
-            "root::0:0::/:/bin/sh\n\
-            nobody::1000:1000::/:/bin/sh\n",
+            format!(
+                "root::0:0::/:/bin/sh\n\
+                nobody::{}:{}::/:/bin/sh\n",
+                user.uid, user.gid
+            ),
         )
         .with_context(|| {
             format!(
@@ -596,10 +667,40 @@ impl Drop for SandboxData {
     }
 }
 
+/// Describe the signal that killed the process. If the process has been killed by the syscall filter
+/// the description lists the syscalls that the filter denies: the sandbox does not report which one
+/// of them has been used, so the description does not pretend to know it.
+fn describe_signal(signal: i32, name: String, filter: Option<&SyscallFilter>) -> String {
+    if !cfg!(target_os = "linux") || signal != Signal::SIGSYS as i32 {
+        return name;
+    }
+    let Some(filter) = filter else {
+        return name;
+    };
+    if filter.default_action == SyscallFilterAction::Kill {
+        return format!("{name}: probably used a syscall not allowed by the sandbox");
+    }
+    let denied: Vec<_> = filter
+        .rules
+        .iter()
+        .filter(|(_, action)| *action == SyscallFilterAction::Kill)
+        .map(|(syscall, _)| syscall.as_str())
+        .collect();
+    if denied.is_empty() {
+        return name;
+    }
+    format!(
+        "{name}: probably used a syscall denied by the sandbox, one of: {}",
+        denied.join(", ")
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
     use std::path::Path;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::{Arc, Mutex};
 
     #[cfg(not(target_os = "macos"))]
     use tabox::configuration::{DirectoryMount, SandboxConfiguration};
@@ -607,8 +708,9 @@ mod tests {
     use tabox::syscall_filter::SyscallFilterAction;
     use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAGConfig};
 
-    use crate::execution_unit::Sandbox;
-    use crate::ErrorSandboxRunner;
+    use crate::execution_unit::sandbox::SandboxUser;
+    use crate::execution_unit::{RawSandboxConfig, RawSandboxResult, Sandbox};
+    use crate::{ErrorSandboxRunner, SandboxRunner};
 
     #[test]
     fn test_remove_sandbox_on_drop() {
@@ -616,7 +718,14 @@ mod tests {
         let mut exec = Execution::new("test", ExecutionCommand::system("true"));
         exec.output("fooo");
         exec.limits_mut().read_only(true);
-        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None).unwrap();
+        let sandbox = Sandbox::new(
+            tmpdir.path(),
+            &exec,
+            &HashMap::new(),
+            None,
+            SandboxUser::default(),
+        )
+        .unwrap();
         let outfile = sandbox.output_path(Path::new("fooo"));
         if let Err(e) = sandbox.run(&ErrorSandboxRunner, &ExecutionDAGConfig::new()) {
             assert!(e.to_string().contains("Nope"));
@@ -629,6 +738,35 @@ mod tests {
         assert!(!outfile.parent().unwrap().parent().unwrap().exists()); // the sandbox dir
     }
 
+    #[test]
+    fn test_nofile_limit() {
+        /// A runner that only records the limit on the file descriptors.
+        #[derive(Default)]
+        struct NofileRunner(Mutex<Option<u32>>);
+
+        impl SandboxRunner for NofileRunner {
+            fn run(&self, config: RawSandboxConfig, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+                *self.0.lock().unwrap() = config.nofile;
+                RawSandboxResult::Error("Nope".to_owned())
+            }
+        }
+
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let mut exec = Execution::new("test", ExecutionCommand::system("true"));
+        exec.limits_mut().nofile(42);
+        let sandbox = Sandbox::new(
+            tmpdir.path(),
+            &exec,
+            &HashMap::new(),
+            None,
+            SandboxUser::default(),
+        )
+        .unwrap();
+        let runner = NofileRunner::default();
+        assert!(sandbox.run(&runner, &ExecutionDAGConfig::new()).is_err());
+        assert_eq!(*runner.0.lock().unwrap(), Some(42));
+    }
+
     #[cfg(not(target_os = "macos"))]
     #[test]
     fn test_command_args() {
@@ -646,11 +784,25 @@ mod tests {
             .allow_multiprocess()
             .memory(1234);
         exec.env("foo", "bar");
-        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None).unwrap();
+        let sandbox = Sandbox::new(
+            tmpdir.path(),
+            &exec,
+            &HashMap::new(),
+            None,
+            SandboxUser::default(),
+        )
+        .unwrap();
         let mut config = SandboxConfiguration::default();
         let dag_config = ExecutionDAGConfig::new();
         sandbox
-            .build_command(tmpdir.path(), &exec, &mut config, &dag_config, None)
+            .build_command(
+                tmpdir.path(),
+                &exec,
+                &mut config,
+                &dag_config,
+                None,
+                SandboxUser::default(),
+            )
             .unwrap();
         let extra_time = dag_config.extra_time;
         let total_time = (1.0 + 2.6 + extra_time).ceil() as u64;
@@ -678,4 +830,59 @@ mod tests {
         assert_eq!(config.executable, Path::new("/bin/sh"));
         assert_eq!(config.args, vec!["bar", "baz"]);
     }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_sandbox_user() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let exec = Execution::new("test", ExecutionCommand::system("true"));
+        let user = SandboxUser::new(4242, 4343);
+        let sandbox = Sandbox::new(tmpdir.path(), &exec, &HashMap::new(), None, user).unwrap();
+        let mut config = SandboxConfiguration::default();
+        let boxdir = sandbox.data.lock().unwrap().path().to_owned();
+        sandbox
+            .build_command(
+                &boxdir,
+                &exec,
+                &mut config,
+                &ExecutionDAGConfig::new(),
+                None,
+                user,
+            )
+            .unwrap();
+        assert_eq!(config.uid, 4242);
+        assert_eq!(config.gid, 4343);
+        let passwd = std::fs::read_to_string(boxdir.join("etc/passwd")).unwrap();
+        assert!(passwd.contains("nobody::4242:4343::/:/bin/sh"));
+    }
+
+    #[test]
+    fn test_sandbox_user_validate() {
+        assert!(SandboxUser::default().validate().is_ok());
+        assert!(SandboxUser::new(1234, 1234).validate().is_ok());
+        assert!(SandboxUser::new(0, 1000).validate().is_err());
+        assert!(SandboxUser::new(1000, 0).validate().is_err());
+        assert!(SandboxUser::new(u32::MAX, 1000).validate().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_describe_signal_forbidden_syscall() {
+        use tabox::syscall_filter::SyscallFilter;
+
+        use crate::execution_unit::sandbox::describe_signal;
+
+        let filter = SyscallFilter::build(false, true);
+        let sigsys = nix::sys::signal::Signal::SIGSYS as i32;
+        let name = describe_signal(sigsys, "Bad system call".into(), Some(&filter));
+        assert!(name.starts_with("Bad system call: probably used a syscall denied by the sandbox"));
+        assert!(name.contains("one of: "));
+        assert!(name.contains("fork"));
+        // without a syscall filter the signal cannot come from the sandbox
+        let name = describe_signal(sigsys, "Bad system call".into(), None);
+        assert_eq!(name, "Bad system call");
+        // the other signals are reported as they are
+        let name = describe_signal(11, "Segmentation fault".into(), Some(&filter));
+        assert_eq!(name, "Segmentation fault");
+    }
 }
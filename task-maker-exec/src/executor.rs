@@ -8,7 +8,10 @@ use anyhow::{anyhow, Context, Error};
 use ductile::{ChannelReceiver, ChannelSender};
 use serde::{Deserialize, Serialize};
 use task_maker_cache::Cache;
-use task_maker_dag::{ExecutionGroup, ExecutionGroupUuid, FileUuid, ProvidedFile, WorkerUuid};
+use task_maker_dag::{
+    CacheMode, ExecutionDAGData, ExecutionGroup, ExecutionGroupUuid, FileUuid, ProvidedFile,
+    WorkerUuid,
+};
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 
 use crate::check_dag::check_dag;
@@ -44,6 +47,8 @@ pub struct WorkerJob {
     pub group: ExecutionGroup,
     /// The `FileStoreKey`s the worker has to know to start the evaluation.
     pub dep_keys: HashMap<FileUuid, FileStoreKey>,
+    /// The cache mode of the client, the worker uses its own cache only if it allows it.
+    pub cache_mode: CacheMode,
 }
 
 /// Information about the job the worker is currently doing.
@@ -132,24 +137,30 @@ pub(crate) struct Executor {
     /// flag is set to false, after the first client is done the Scheduler, the WorkerManager and
     /// this Executor will exit.
     long_running: bool,
+    /// The password the clients have to provide for aborting the evaluation of the other clients.
+    /// When it's not set nobody can abort the other clients.
+    operator_password: Option<String>,
 }
 
 impl Executor {
     /// Create a new `Executor` using the specified `FileStore` for the Scheduler and WorkerManager,
     /// the receiver for communicating with this Executor and if it should be "long running".
     /// When this flag is set to false, after the first client is done the Scheduler, the
-    /// WorkerManager and this Executor will exit.
+    /// WorkerManager and this Executor will exit. `operator_password` is the password required for
+    /// aborting the evaluation of a client.
     pub fn new(
         file_store: Arc<FileStore>,
         cache: Cache,
         receiver: Receiver<ExecutorInMessage>,
         long_running: bool,
+        operator_password: Option<String>,
     ) -> Executor {
         Executor {
             file_store,
             cache,
             receiver,
             long_running,
+            operator_password,
         }
     }
 
@@ -166,6 +177,7 @@ impl Executor {
             self.file_store.clone(),
             self.cache,
             scheduler_rx,
+            scheduler_tx.clone(),
             sched_executor_tx,
             worker_manager_tx.clone(),
         );
@@ -203,6 +215,7 @@ impl Executor {
                     let scheduler = scheduler_tx.clone();
                     let file_store = self.file_store.clone();
                     let long_running = self.long_running;
+                    let operator_password = self.operator_password.clone();
                     // handle the new client in a new thread called "Client Manager"
                     // FIXME: this thread is leaked, maybe we can join it as well
                     thread::Builder::new()
@@ -217,6 +230,7 @@ impl Executor {
                                 sender,
                                 receiver,
                                 scheduler.clone(),
+                                operator_password,
                             )
                             .unwrap();
                             // if not in long running mode, the first client should tear down the
@@ -279,6 +293,9 @@ impl Executor {
                 SchedulerExecutorMessageData::ExecutionSkipped { execution } => {
                     ExecutorServerMessage::NotifySkip(execution)
                 }
+                SchedulerExecutorMessageData::CacheMiss { execution, reason } => {
+                    ExecutorServerMessage::NotifyCacheMiss(execution, reason)
+                }
                 SchedulerExecutorMessageData::ExecutionDone { execution, result } => {
                     ExecutorServerMessage::NotifyDone(execution, result)
                 }
@@ -307,7 +324,23 @@ impl Executor {
                 SchedulerExecutorMessageData::Status { status } => {
                     ExecutorServerMessage::Status(status)
                 }
-                SchedulerExecutorMessageData::EvaluationDone => {
+                SchedulerExecutorMessageData::EvaluationAborted => {
+                    // the files will never be asked by the client
+                    ready_files.remove(&client_uuid);
+                    ExecutorServerMessage::Error(
+                        "The evaluation has been aborted by the server operator".into(),
+                    )
+                }
+                SchedulerExecutorMessageData::ClientAborted { found } => {
+                    ExecutorServerMessage::ClientAborted(found)
+                }
+                SchedulerExecutorMessageData::WaitingForClient => {
+                    ExecutorServerMessage::WaitingForClient
+                }
+                SchedulerExecutorMessageData::EvaluationDone { cache_stats } => {
+                    if let Err(e) = client.send(ExecutorServerMessage::CacheStats(cache_stats)) {
+                        warn!("Failed to send the cache stats to the client: {e:?}");
+                    }
                     let files = ready_files
                         .remove(&client_uuid)
                         .unwrap_or_default()
@@ -332,59 +365,46 @@ impl Executor {
         sender: ChannelSender<ExecutorServerMessage>,
         receiver: ChannelReceiver<ExecutorClientMessage>,
         scheduler: Sender<SchedulerInMessage>,
+        operator_password: Option<String>,
     ) -> Result<(), Error> {
+        enter_span!("client", uuid = %client.uuid, name = %client.name);
         let mut scheduler = Some(scheduler);
+        // the files of the chunks of the DAG received so far
+        let mut known_files = HashSet::new();
         while let Ok(message) = receiver.recv() {
             match message {
                 ExecutorClientMessage::Evaluate { dag, callbacks } => {
-                    if let Err(e) = check_dag(&dag, &callbacks) {
-                        warn!("Invalid DAG: {e:?}");
-                        sender
-                            .send(ExecutorServerMessage::Error(e.to_string()))
-                            .context("Failed to send Error message to client")?;
+                    let valid = Executor::handle_evaluate(
+                        &file_store,
+                        &client,
+                        &sender,
+                        scheduler.as_ref(),
+                        &mut known_files,
+                        dag,
+                        callbacks,
+                        true,
+                    )?;
+                    if !valid {
                         break;
-                    } else {
-                        trace!("DAG looks valid!");
-                    }
-                    // for each file marked as provided check if a local copy is present, otherwise
-                    // ask the client to send it.
-                    let mut ready_files = Vec::new();
-                    for (uuid, file) in dag.provided_files.iter() {
-                        let key = match file {
-                            ProvidedFile::Content { key, .. } => key,
-                            ProvidedFile::LocalFile { key, .. } => key,
-                        };
-                        let handle = file_store.get(key);
-                        if let Some(handle) = handle {
-                            ready_files.push((*uuid, handle));
-                        } else {
-                            sender
-                                .send(ExecutorServerMessage::AskFile(*uuid))
-                                .with_context(|| format!("Failed to send AskFile({uuid})"))?;
-                        }
                     }
-                    // tell the scheduler that a new DAG is ready to be executed.
-                    scheduler
-                        .as_ref()
-                        .ok_or_else(|| anyhow!("Stopped execution"))?
-                        .send(SchedulerInMessage::EvaluateDAG {
-                            client: client.clone(),
-                            dag,
-                            callbacks,
-                        })
-                        .context("Failed to send EvaluateDAG to the scheduler")?;
-                    // tell the scheduler the files that are already locally ready. The others will
-                    // be ready when the client will send them.
-                    for (uuid, handle) in ready_files.into_iter() {
-                        scheduler
-                            .as_ref()
-                            .ok_or_else(|| anyhow!("Stopped execution"))?
-                            .send(SchedulerInMessage::FileReady {
-                                client: client.uuid,
-                                uuid,
-                                handle,
-                            })
-                            .context("Failed to send FileReady to the scheduler")?;
+                }
+                ExecutorClientMessage::EvaluateChunk {
+                    dag,
+                    callbacks,
+                    last,
+                } => {
+                    let valid = Executor::handle_evaluate(
+                        &file_store,
+                        &client,
+                        &sender,
+                        scheduler.as_ref(),
+                        &mut known_files,
+                        dag,
+                        callbacks,
+                        last,
+                    )?;
+                    if !valid {
+                        break;
                     }
                 }
                 ExecutorClientMessage::ProvideFile(uuid, key) => {
@@ -434,6 +454,38 @@ impl Executor {
                         });
                     }
                 }
+                ExecutorClientMessage::AbortClient { target, password } => {
+                    info!("Client asking to abort the evaluation of {target}");
+                    if operator_password.as_ref() != Some(&password) {
+                        warn!("Client {} provided a wrong operator password", client.uuid);
+                        sender
+                            .send(ExecutorServerMessage::Error(
+                                "Wrong operator password, cannot abort the client".into(),
+                            ))
+                            .context("Failed to send Error to the client")?;
+                    } else if let Some(scheduler) = scheduler.as_ref() {
+                        scheduler
+                            .send(SchedulerInMessage::AbortClient {
+                                client: client.uuid,
+                                target,
+                            })
+                            .context("Failed to send AbortClient to the scheduler")?;
+                    } else {
+                        sender
+                            .send(ExecutorServerMessage::ClientAborted(false))
+                            .context("Failed to send ClientAborted to the client")?;
+                    }
+                }
+                ExecutorClientMessage::SkipExecutions(groups) => {
+                    info!("Client asking to skip {} executions", groups.len());
+                    // This may fail is the scheduler is gone.
+                    if let Some(scheduler) = scheduler.as_ref() {
+                        let _ = scheduler.send(SchedulerInMessage::SkipExecutions {
+                            client: client.uuid,
+                            groups,
+                        });
+                    }
+                }
                 ExecutorClientMessage::Stop => {
                     info!("Client asking to stop");
                     if let Some(scheduler) = scheduler.take() {
@@ -455,4 +507,76 @@ impl Executor {
         }
         Ok(())
     }
+
+    /// Handle a DAG, or a chunk of it, sent by a client: check it, ask the client the provided
+    /// files that are not stored locally and send it to the scheduler. `known_files` are the files
+    /// of the previous chunks, the ones of this chunk are added to it. Returns `false` if the DAG
+    /// is invalid, after telling the client.
+    #[allow(clippy::too_many_arguments)]
+    fn handle_evaluate(
+        file_store: &FileStore,
+        client: &ClientInfo,
+        sender: &ChannelSender<ExecutorServerMessage>,
+        scheduler: Option<&Sender<SchedulerInMessage>>,
+        known_files: &mut HashSet<FileUuid>,
+        dag: Box<ExecutionDAGData>,
+        callbacks: Box<ExecutionDAGWatchSet>,
+        last: bool,
+    ) -> Result<bool, Error> {
+        if let Err(e) = check_dag(&dag, &callbacks, known_files) {
+            warn!("Invalid DAG: {e:?}");
+            sender
+                .send(ExecutorServerMessage::Error(e.to_string()))
+                .context("Failed to send Error message to client")?;
+            return Ok(false);
+        } else {
+            trace!("DAG looks valid!");
+        }
+        known_files.extend(dag.provided_files.keys().cloned());
+        for group in dag.execution_groups.values() {
+            known_files.extend(group.outputs());
+        }
+        // for each file marked as provided check if a local copy is present, otherwise ask the
+        // client to send it.
+        let mut ready_files = Vec::new();
+        for (uuid, file) in dag.provided_files.iter() {
+            let key = match file {
+                ProvidedFile::Content { key, .. } => key,
+                ProvidedFile::LocalFile { key, .. } => key,
+                ProvidedFile::StoredFile { key, .. } => key,
+                // the client sends it during the evaluation, without being asked
+                ProvidedFile::Late { .. } => continue,
+            };
+            let handle = file_store.get(key);
+            if let Some(handle) = handle {
+                ready_files.push((*uuid, handle));
+            } else {
+                sender
+                    .send(ExecutorServerMessage::AskFile(*uuid))
+                    .with_context(|| format!("Failed to send AskFile({uuid})"))?;
+            }
+        }
+        let scheduler = scheduler.ok_or_else(|| anyhow!("Stopped execution"))?;
+        // tell the scheduler that a new DAG is ready to be executed.
+        scheduler
+            .send(SchedulerInMessage::EvaluateDAG {
+                client: client.clone(),
+                dag,
+                callbacks,
+                last,
+            })
+            .context("Failed to send EvaluateDAG to the scheduler")?;
+        // tell the scheduler the files that are already locally ready. The others will be ready
+        // when the client will send them.
+        for (uuid, handle) in ready_files.into_iter() {
+            scheduler
+                .send(SchedulerInMessage::FileReady {
+                    client: client.uuid,
+                    uuid,
+                    handle,
+                })
+                .context("Failed to send FileReady to the scheduler")?;
+        }
+        Ok(true)
+    }
 }
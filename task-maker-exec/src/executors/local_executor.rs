@@ -13,6 +13,7 @@ use crate::executor::{Executor, ExecutorInMessage};
 use crate::proto::{ExecutorClientMessage, ExecutorServerMessage};
 use crate::sandbox_runner::SandboxRunner;
 use crate::scheduler::ClientInfo;
+use crate::SandboxUser;
 use crate::Worker;
 
 /// An Executor that runs locally by spawning a number of threads with the workers inside.
@@ -42,7 +43,7 @@ impl LocalExecutor {
     {
         let sandbox_path = sandbox_path.into();
         let (executor_tx, executor_rx) = channel();
-        let executor = Executor::new(file_store.clone(), cache, executor_rx, false);
+        let executor = Executor::new(file_store.clone(), cache, executor_rx, false, None);
 
         // share the runner for all the workers
         let sandbox_runner = Arc::new(sandbox_runner);
@@ -58,6 +59,7 @@ impl LocalExecutor {
                 #[allow(clippy::needless_borrow)]
                 &sandbox_path,
                 runner,
+                SandboxUser::default(),
             )
             .context("Failed to start local worker")?;
             executor_tx
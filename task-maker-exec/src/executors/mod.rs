@@ -7,7 +7,7 @@
 //! # Example
 //!
 //! ```
-//! use task_maker_store::FileStore;
+//! use task_maker_store::{FileStore, DEFAULT_BLOCK_SIZE};
 //! use task_maker_exec::executors::LocalExecutor;
 //! use std::sync::{Arc, Mutex, mpsc::channel};
 //! # use std::thread;
@@ -18,7 +18,7 @@
 //!
 //! # let tmpdir = TempDir::new().unwrap();
 //! # let path = tmpdir.path();
-//! let store = FileStore::new(path, 1000, 1000).unwrap();
+//! let store = FileStore::new(path, 1000, 1000, Default::default(), None, DEFAULT_BLOCK_SIZE).unwrap();
 //! let cache = Cache::new(path).unwrap();
 //! let num_cores = 4;
 //! # let sandbox_runner = Arc::new(SuccessSandboxRunner::default());
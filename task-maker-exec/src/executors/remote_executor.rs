@@ -48,12 +48,15 @@ impl RemoteExecutor {
     }
 
     /// Start the executor binding the TCP sockets and waiting for clients and workers connections.
+    /// The clients can abort the evaluation of the other clients only by providing
+    /// `operator_password`, nobody can if it's not set.
     pub fn start<S: Into<String>, S2: Into<String>>(
         self,
         bind_client_addr: S,
         bind_worker_addr: S2,
         client_password: Option<String>,
         worker_password: Option<String>,
+        operator_password: Option<String>,
         cache: Cache,
     ) -> Result<(), Error> {
         let file_store = self.file_store;
@@ -61,7 +64,7 @@ impl RemoteExecutor {
         let bind_worker_addr = bind_worker_addr.into();
 
         let (executor_tx, executor_rx) = channel();
-        let executor = Executor::new(file_store, cache, executor_rx, true);
+        let executor = Executor::new(file_store, cache, executor_rx, true, operator_password);
 
         let client_executor_tx = executor_tx.clone();
         let client_listener_thread = std::thread::Builder::new()
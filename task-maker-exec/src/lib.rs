@@ -67,8 +67,12 @@
 #![deny(missing_docs)]
 #![allow(clippy::upper_case_acronyms)]
 
+#[cfg(not(feature = "tracing"))]
 #[macro_use]
 extern crate log;
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
 #[macro_use(defer)]
 extern crate scopeguard;
 
@@ -81,15 +85,20 @@ pub use client::ExecutorClient;
 /// Re-export `ductile` since it's sensible to any version change
 pub use ductile;
 use ductile::new_local_channel;
-pub use execution_unit::RawSandboxResult;
+pub use execution_unit::sandbox::SandboxUser;
+pub use execution_unit::{RawSandboxConfig, RawSandboxResult};
 pub use executor::{ExecutorStatus, ExecutorWorkerStatus, WorkerCurrentJobStatus};
 pub use sandbox_runner::{ErrorSandboxRunner, SandboxRunner, SuccessSandboxRunner};
-pub use scheduler::ClientInfo;
+pub use scheduler::{ClientInfo, ClientUuid};
 use task_maker_cache::Cache;
+pub use task_maker_cache::CacheStats;
 use task_maker_dag::ExecutionDAG;
-use task_maker_store::FileStore;
+use task_maker_store::{FileStore, DEFAULT_BLOCK_SIZE};
 pub use worker::{Worker, WorkerConn};
 
+#[macro_use]
+mod spans;
+
 mod check_dag;
 mod client;
 mod detect_exe;
@@ -131,7 +140,15 @@ pub fn eval_dag_locally<P: Into<PathBuf>, P2: Into<PathBuf>, R>(
     let store_dir = store_dir.into();
     let sandbox_path = sandbox_path.into();
     let file_store = Arc::new(
-        FileStore::new(&store_dir, max_cache, min_cache).expect("Cannot create the file store"),
+        FileStore::new(
+            &store_dir,
+            max_cache,
+            min_cache,
+            Default::default(),
+            None,
+            DEFAULT_BLOCK_SIZE,
+        )
+        .expect("Cannot create the file store"),
     );
     let server_file_store = file_store.clone();
     let server = thread::Builder::new()
@@ -252,4 +269,135 @@ mod tests {
         assert!(!cwd.path().join("stdout2").exists());
         assert!(!cwd.path().join("output3").exists());
     }
+
+    #[test]
+    fn test_local_evaluation_streamed() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().stream_chunk_size(Some(1));
+
+        let file = File::new("Input file");
+        dag.provide_content(file.clone(), b"ciao".to_vec());
+        let mut last = file;
+        let mut done = Vec::new();
+        for i in 0..3 {
+            let mut exec = Execution::new(format!("exec{i}"), ExecutionCommand::system("cat"));
+            exec.stdin(&last);
+            last = exec.capture_stdout(None);
+            let exec = exec.into_group();
+            let exec_done = Arc::new(AtomicBool::new(false));
+            let exec_done2 = exec_done.clone();
+            dag.on_execution_done(&exec.uuid, move |_res| {
+                exec_done2.store(true, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.add_execution_group(exec);
+            done.push(exec_done);
+        }
+        dag.write_file_to(&last, cwd.path().join("stdout"), false);
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            2,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert!(done.iter().all(|done| done.load(Ordering::Relaxed)));
+        assert_eq!(std::fs::read(cwd.path().join("stdout")).unwrap(), b"ciao");
+    }
+
+    #[test]
+    fn test_local_evaluation_streamed_skip() {
+        let cwd = TempDir::new().unwrap();
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().stream_chunk_size(Some(1));
+
+        let file = File::new("Input file");
+        dag.provide_content(file.clone(), b"ciao".to_vec());
+        let mut last = file;
+        let mut groups = Vec::new();
+        for i in 0..3 {
+            let mut exec = Execution::new(format!("exec{i}"), ExecutionCommand::system("cat"));
+            exec.stdin(&last);
+            last = exec.capture_stdout(None);
+            groups.push(exec.into_group());
+        }
+        // like --stop-after, the first execution skips the last one, that is in a chunk of the DAG
+        // that may not be sent yet
+        let skip_handle = dag.skip_handle();
+        let last_uuid = groups[2].uuid;
+        dag.on_execution_done(&groups[0].uuid, move |_res| {
+            skip_handle.skip(vec![last_uuid]);
+            Ok(())
+        });
+        let skipped = Arc::new(AtomicBool::new(false));
+        let skipped2 = skipped.clone();
+        dag.on_execution_skip(&last_uuid, move || {
+            skipped2.store(true, Ordering::Relaxed);
+            Ok(())
+        });
+        dag.on_execution_start(&last_uuid, |_w| panic!("exec2 has not been skipped"));
+        for group in groups {
+            dag.add_execution_group(group);
+        }
+
+        eval_dag_locally(
+            dag,
+            cwd.path(),
+            2,
+            cwd.path(),
+            1000,
+            1000,
+            UnsafeSandboxRunner,
+        );
+
+        assert!(skipped.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_non_cacheable_execution() {
+        let cwd = TempDir::new().unwrap();
+        let make_dag = |started: Arc<AtomicBool>, started_non_cacheable: Arc<AtomicBool>| {
+            let mut dag = ExecutionDAG::new();
+            let exec = Execution::new("Cacheable", ExecutionCommand::system("true"));
+            let mut non_cacheable =
+                Execution::new("Non cacheable", ExecutionCommand::system("true"));
+            non_cacheable.cacheable(false);
+            let exec = exec.into_group();
+            dag.on_execution_start(&exec.uuid, move |_w| {
+                started.store(true, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.add_execution_group(exec);
+            let non_cacheable = non_cacheable.into_group();
+            dag.on_execution_start(&non_cacheable.uuid, move |_w| {
+                started_non_cacheable.store(true, Ordering::Relaxed);
+                Ok(())
+            });
+            dag.add_execution_group(non_cacheable);
+            dag
+        };
+
+        for run in 0..2 {
+            let started = Arc::new(AtomicBool::new(false));
+            let started_non_cacheable = Arc::new(AtomicBool::new(false));
+            let dag = make_dag(started.clone(), started_non_cacheable.clone());
+            eval_dag_locally(
+                dag,
+                cwd.path(),
+                2,
+                cwd.path(),
+                1000,
+                1000,
+                UnsafeSandboxRunner,
+            );
+            // the second time the cacheable execution is fetched from the cache
+            assert_eq!(started.load(Ordering::Relaxed), run == 0);
+            assert!(started_non_cacheable.load(Ordering::Relaxed));
+        }
+    }
 }
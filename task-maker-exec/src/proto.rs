@@ -39,10 +39,12 @@ use anyhow::Context;
 use ductile::{ChannelReceiver, ChannelSender};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use task_maker_cache::CacheStats;
 use task_maker_dag::*;
 use task_maker_store::*;
 
 use crate::executor::{ExecutionDAGWatchSet, ExecutorStatus, WorkerJob};
+use crate::scheduler::ClientUuid;
 use crate::*;
 
 /// Messages that the client sends to the server.
@@ -55,6 +57,18 @@ pub enum ExecutorClientMessage {
         /// The list of the executions and files to keep track of.
         callbacks: Box<ExecutionDAGWatchSet>,
     },
+    /// The client is asking to evaluate a DAG sent in more chunks, see
+    /// [`ExecutionDAGData::split_chunks()`]. The first chunk starts the evaluation, the next ones
+    /// extend it, and the evaluation cannot complete before the `last` chunk is received. A chunk
+    /// can depend on the files of the previous ones.
+    EvaluateChunk {
+        /// The chunk of the DAG to evaluate.
+        dag: Box<ExecutionDAGData>,
+        /// The list of the executions and files of this chunk to keep track of.
+        callbacks: Box<ExecutionDAGWatchSet>,
+        /// Whether this is the last chunk of the DAG.
+        last: bool,
+    },
     /// The client is providing a file. After this message there is a protocol switch for the file
     /// transmission.
     ProvideFile(FileUuid, FileStoreKey),
@@ -67,6 +81,21 @@ pub enum ExecutorClientMessage {
     /// The client is asking for the server status. After this message the client should expect a
     /// [`Status`](enum.ExecutorServerMessage.html#variant.Status) message back.
     Status,
+    /// The client is asking to abort the evaluation of another client, identified by the uuid in
+    /// the server status. All its queued executions are dropped and the running ones killed. After
+    /// this message the client should expect a
+    /// [`ClientAborted`](enum.ExecutorServerMessage.html#variant.ClientAborted) message back, or an
+    /// [`Error`](enum.ExecutorServerMessage.html#variant.Error) if the password is wrong.
+    AbortClient {
+        /// The client to abort.
+        target: ClientUuid,
+        /// The operator password of the server.
+        password: String,
+    },
+    /// The client is asking to skip some of its executions that are not started yet. They are
+    /// notified as skipped, and so are the executions that depend on them. The executions already
+    /// running or completed are not affected.
+    SkipExecutions(Vec<ExecutionGroupUuid>),
 }
 
 /// Messages that the server sends to the client.
@@ -85,10 +114,21 @@ pub enum ExecutorServerMessage {
     NotifyDone(ExecutionGroupUuid, Vec<ExecutionResult>),
     /// The execution has been skipped.
     NotifySkip(ExecutionGroupUuid),
+    /// The execution has not been found in the cache, for the reason provided.
+    NotifyCacheMiss(ExecutionGroupUuid, String),
     /// There was an error during the evaluation.
     Error(String),
     /// The server status as asked by the client.
     Status(ExecutorStatus<Duration>),
+    /// The answer to `AbortClient`: whether the client had an evaluation in progress, which has
+    /// been aborted.
+    ClientAborted(bool),
+    /// The evaluation cannot proceed until the client provides some of its late files: the client
+    /// should skip the executions that depend on the ones it will never provide.
+    WaitingForClient,
+    /// The hits and misses of the cache lookups of the executions of the client. Sent just before
+    /// `Done`.
+    CacheStats(CacheStats),
     /// The evaluation of the DAG is complete, this message will close the connection.
     Done(Vec<(FileUuid, FileStoreKey, bool)>),
 }
@@ -110,6 +150,9 @@ pub enum WorkerClientMessage {
     /// The worker needs a file from the server. The server should send back that file in order to
     /// run the execution on the worker.
     AskFile(FileStoreKey),
+    /// The worker failed to send the outputs of its job after `WorkerDone`, it's sent in place of
+    /// the next `ProvideFile`. The server should discard the results and run the job again.
+    JobFailed(String),
 }
 
 /// Messages sent by the server to the worker.
@@ -176,6 +219,16 @@ impl ChannelFileSender {
         let path = path.as_ref();
         let iterator = ReadFileIterator::new(path)
             .with_context(|| format!("Failed to read file to send: {}", path.display()))?;
+        Self::send_iterator(iterator, sender)
+    }
+
+    /// Send the chunks of a file, for example of an already opened `ReadFileIterator`, to a
+    /// channel using `send_raw`.
+    pub fn send_iterator<I, T>(iterator: I, sender: &ChannelSender<T>) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+        T: 'static + Send + Sync + Serialize,
+    {
         for buf in iterator {
             sender.send_raw(&buf).context("Failed to send file chunk")?;
         }
@@ -1,16 +1,21 @@
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 
-use tabox::configuration::SandboxConfiguration;
 use tabox::result::{ExitStatus, ResourceUsage, SandboxExecutionResult};
 
-use crate::RawSandboxResult;
+use crate::{RawSandboxConfig, RawSandboxResult};
 
 /// Something able to spawn a sandbox, wait for it to exit and return the results.
 pub trait SandboxRunner: Send + Sync {
     /// Spawn a sandbox with the provided configuration, set the PID as soon as possible and wait
     /// for it to exit. Parse the outcome of the sandbox and return it.
-    fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult;
+    fn run(&self, config: RawSandboxConfig, pid: Arc<AtomicU32>) -> RawSandboxResult;
+}
+
+impl<R: SandboxRunner + ?Sized> SandboxRunner for Box<R> {
+    fn run(&self, config: RawSandboxConfig, pid: Arc<AtomicU32>) -> RawSandboxResult {
+        (**self).run(config, pid)
+    }
 }
 
 /// A fake sandbox that don't actually spawn anything and always return an error.
@@ -18,7 +23,7 @@ pub trait SandboxRunner: Send + Sync {
 pub struct ErrorSandboxRunner;
 
 impl SandboxRunner for ErrorSandboxRunner {
-    fn run(&self, _config: SandboxConfiguration, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(&self, _config: RawSandboxConfig, _pid: Arc<AtomicU32>) -> RawSandboxResult {
         RawSandboxResult::Error("Nope".to_owned())
     }
 }
@@ -29,7 +34,7 @@ impl SandboxRunner for ErrorSandboxRunner {
 pub struct SuccessSandboxRunner;
 
 impl SandboxRunner for SuccessSandboxRunner {
-    fn run(&self, _config: SandboxConfiguration, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(&self, _config: RawSandboxConfig, _pid: Arc<AtomicU32>) -> RawSandboxResult {
         RawSandboxResult::Success(SandboxExecutionResult {
             status: ExitStatus::ExitCode(0),
             resource_usage: ResourceUsage {
@@ -50,10 +55,11 @@ pub struct UnsafeSandboxRunner;
 
 #[cfg(test)]
 impl SandboxRunner for UnsafeSandboxRunner {
-    fn run(&self, config: SandboxConfiguration, _pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(&self, config: RawSandboxConfig, _pid: Arc<AtomicU32>) -> RawSandboxResult {
         use std::fs::{File, OpenOptions};
         use std::process::Stdio;
 
+        let config = config.tabox;
         let mut child = std::process::Command::new(config.executable);
         child.args(config.args);
         if let Some(path) = config.stdout {
@@ -85,7 +91,7 @@ impl SandboxRunner for UnsafeSandboxRunner {
 }
 
 impl<S: SandboxRunner> SandboxRunner for Arc<S> {
-    fn run(&self, conf: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(&self, conf: RawSandboxConfig, pid: Arc<AtomicU32>) -> RawSandboxResult {
         self.as_ref().run(conf, pid)
     }
 }
@@ -1,14 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
 use std::collections::{BinaryHeap, HashMap, HashSet};
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context, Error};
 use serde::{Deserialize, Serialize};
-use task_maker_cache::{Cache, CacheResult};
+use task_maker_cache::{
+    Cache, CacheResult, CacheStats, RemoteEntries, RemoteLookup, RemotePublish,
+};
 use task_maker_dag::{
     CacheMode, DagPriority, ExecutionDAGData, ExecutionGroup, ExecutionGroupUuid, ExecutionResult,
-    FileUuid, Priority, WorkerUuid, HIGH_PRIORITY,
+    FileUuid, Priority, ProvidedFile, WorkerUuid, HIGH_PRIORITY, MIN_RELATIVE_TIME_LIMIT,
 };
 use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey};
 use uuid::Uuid;
@@ -20,6 +25,9 @@ use crate::worker_manager::WorkerManagerInMessage;
 
 pub type ClientUuid = Uuid;
 
+/// The number of lookups in the remote cache that can run at the same time.
+const REMOTE_LOOKUP_THREADS: usize = 4;
+
 /// Information about a client of the scheduler.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ClientInfo {
@@ -31,7 +39,7 @@ pub struct ClientInfo {
 
 /// Message coming in for the `Scheduler` from either an `Executor` or a `WorkerManager`.
 pub(crate) enum SchedulerInMessage {
-    /// A client asked to evaluate a DAG.
+    /// A client asked to evaluate a DAG, or sent the next chunk of the DAG it's evaluating.
     EvaluateDAG {
         /// The information about the client issuing the request.
         client: ClientInfo,
@@ -39,6 +47,8 @@ pub(crate) enum SchedulerInMessage {
         dag: Box<ExecutionDAGData>,
         /// The set of callbacks the client is interested in.
         callbacks: Box<ExecutionDAGWatchSet>,
+        /// Whether this is the last chunk of the DAG, the evaluation is not completed before it.
+        last: bool,
     },
     /// A client has been disconnected, all the executions of that client should be removed and the
     /// involved workers stopped.
@@ -46,6 +56,21 @@ pub(crate) enum SchedulerInMessage {
         /// The identifier of the client.
         client: ClientUuid,
     },
+    /// A client asked to abort the evaluation of another client: its executions are removed and
+    /// the involved workers stopped, like if it disconnected.
+    AbortClient {
+        /// The identifier of the client asking for the abort.
+        client: ClientUuid,
+        /// The identifier of the client whose evaluation should be aborted.
+        target: ClientUuid,
+    },
+    /// A client asked to skip some of its executions that are not started yet.
+    SkipExecutions {
+        /// The identifier of the client.
+        client: ClientUuid,
+        /// The executions to skip.
+        groups: Vec<ExecutionGroupUuid>,
+    },
     /// A new file of the DAG of a client is ready.
     FileReady {
         /// The identifier of the client that owns the file.
@@ -76,6 +101,15 @@ pub(crate) enum SchedulerInMessage {
         /// The uuid of the worker that has disconnected.
         uuid: WorkerUuid,
     },
+    /// The lookup of an execution in the remote cache has completed.
+    RemoteCacheResult {
+        /// The identifier of the client that owns the execution.
+        client: ClientUuid,
+        /// The uuid of the execution.
+        group: ExecutionGroupUuid,
+        /// The entries found in the remote cache.
+        entries: RemoteEntries,
+    },
     /// The executor is asking for the status of the scheduler.
     Status { client: ClientUuid },
     /// The executor is asking to exit.
@@ -100,6 +134,14 @@ pub(crate) enum SchedulerExecutorMessageData {
         /// The result of the execution.
         result: Vec<ExecutionResult>,
     },
+    /// An execution has not been found in the cache. Sent only if the DAG asked to explain the
+    /// cache misses.
+    CacheMiss {
+        /// The uuid of the execution.
+        execution: ExecutionGroupUuid,
+        /// The explanation of why the execution is not in the cache.
+        reason: String,
+    },
     /// A watched execution has been skipped because one of its dependencies failed.
     ExecutionSkipped {
         /// The uuid of the execution that has been skipped.
@@ -117,11 +159,45 @@ pub(crate) enum SchedulerExecutorMessageData {
         urgent: bool,
     },
     /// The evaluation has been completed.
-    EvaluationDone,
+    EvaluationDone {
+        /// The outcomes of the cache lookups of the executions of the client.
+        cache_stats: CacheStats,
+    },
+    /// The evaluation has been aborted by another client.
+    EvaluationAborted,
+    /// The evaluation of the client is waiting only for the late files the client provides during
+    /// the evaluation.
+    WaitingForClient,
+    /// The answer to an `AbortClient` request.
+    ClientAborted {
+        /// Whether the client to abort had an evaluation in progress.
+        found: bool,
+    },
     /// The status of the execution.
     Status { status: ExecutorStatus<Duration> },
 }
 
+/// The lookup in the remote cache of an execution that missed the local cache, whose result is
+/// sent to the scheduler.
+struct RemoteLookupJob {
+    /// The identifier of the client that owns the execution.
+    client: ClientUuid,
+    /// The uuid of the execution.
+    group: ExecutionGroupUuid,
+    /// The lookup to run.
+    lookup: RemoteLookup,
+}
+
+/// The senders of the jobs to the threads that talk with the remote cache, so that the scheduler
+/// is not blocked by the slow remote requests. The publications have their own thread, so that a
+/// slow upload never delays the lookups.
+struct RemoteCacheThreads {
+    /// Sender of the lookups, run by a pool of `REMOTE_LOOKUP_THREADS` threads.
+    lookups: Sender<RemoteLookupJob>,
+    /// Sender of the publications, run one at a time.
+    publishes: Sender<RemotePublish>,
+}
+
 /// The actual message sent from the Scheduler to an Executor. Since all the fields of the
 /// enumeration would have got the client, it has been extracted here.
 pub(crate) type SchedulerExecutorMessage = (ClientUuid, SchedulerExecutorMessageData);
@@ -149,9 +225,18 @@ struct SchedulerClientData {
     /// The set of executions that depends on a file, this is a lookup table for when the files
     /// become ready.
     input_of: HashMap<FileUuid, HashSet<ExecutionGroupUuid>>,
-    /// The set of executions that are ready to be executed. Note that this is not the same as
-    /// `Scheduler::ready_execs`, it's just a fast lookup for known if there is still something to
-    /// do for this client.
+    /// The executions that became ready and still have to be looked up in the cache. They are
+    /// looked up in a batch by `Scheduler::schedule_cached`, the ones that miss the cache are moved
+    /// to `ready_queue` and are not looked up again.
+    cache_lookup_queue: Vec<(DagPriority, Priority, ExecutionGroupUuid)>,
+    /// The executions that missed the local cache and are being looked up in the remote cache,
+    /// with their priorities.
+    remote_lookups: HashMap<ExecutionGroupUuid, (DagPriority, Priority)>,
+    /// The priority queue of the ready executions of this client, waiting for the workers.
+    ready_queue: BinaryHeap<(DagPriority, Priority, ExecutionGroupUuid)>,
+    /// The set of executions that are ready to be executed. This contains the same executions of
+    /// `cache_lookup_queue`, `remote_lookups` and `ready_queue`, it's just a fast lookup for known if there is still
+    /// something to do for this client.
     ready_groups: HashSet<ExecutionGroupUuid>,
     /// The set of executions that are currently running in a worker.
     running_groups: HashSet<ExecutionGroupUuid>,
@@ -161,6 +246,25 @@ struct SchedulerClientData {
     /// The list of known [`FileStoreHandle`](../task_maker_store/struct.FileStoreHandle.html)s.
     /// Storing them here prevents the `FileStore` from flushing them away.
     file_handles: HashMap<FileUuid, FileStoreHandle>,
+    /// The value of `Scheduler::served_jobs` when a job of this client was last assigned to a
+    /// worker. Used to break the ties between the clients in round-robin order.
+    last_served: u64,
+    /// The outcomes of the cache lookups of the executions of this client.
+    cache_stats: CacheStats,
+    /// Whether the client sent the last chunk of its DAG.
+    dag_complete: bool,
+    /// The files that are already produced, with whether they are successful. The executions of
+    /// the next chunks of the DAG that depend on them don't have to wait for them.
+    resolved_files: HashMap<FileUuid, bool>,
+    /// The CPU time used by the execution that produced each file, needed for computing the
+    /// relative time limits of the executions that depend on them.
+    output_times: HashMap<FileUuid, f64>,
+    /// Whether the client has been told that the evaluation is waiting for its late files, and
+    /// none of them arrived since.
+    waiting_for_client: bool,
+    /// The executions the client asked to skip before sending the chunk of the DAG with them. They
+    /// are skipped as soon as they arrive.
+    skipped_groups: HashSet<ExecutionGroupUuid>,
 }
 
 impl SchedulerClientData {
@@ -175,20 +279,79 @@ impl SchedulerClientData {
             dag,
             callbacks,
             input_of: HashMap::new(),
+            cache_lookup_queue: Vec::new(),
+            remote_lookups: HashMap::new(),
+            ready_queue: BinaryHeap::new(),
             ready_groups: HashSet::new(),
             running_groups: HashSet::new(),
             missing_deps: HashMap::new(),
             file_handles: HashMap::new(),
+            last_served: 0,
+            cache_stats: CacheStats::default(),
+            dag_complete: false,
+            resolved_files: HashMap::new(),
+            output_times: HashMap::new(),
+            waiting_for_client: false,
+            skipped_groups: HashSet::new(),
         }
     }
 
-    /// True if the client has completed all the executions and there are no more ready nor running
-    /// ones.
+    /// True if the client has sent all its DAG and completed all the executions, and there are no
+    /// more ready nor running ones.
     fn is_done(&self) -> bool {
-        self.ready_groups.is_empty()
+        self.dag_complete
+            && self.ready_groups.is_empty()
             && self.running_groups.is_empty()
             && self.missing_deps.is_empty()
     }
+
+    /// True if the client has sent all its DAG and nothing can run until the client provides some
+    /// of its late files: the other provided files are not missing, and there are no ready nor
+    /// running executions.
+    fn is_waiting_for_late_files(&self) -> bool {
+        self.dag_complete
+            && self.ready_groups.is_empty()
+            && self.running_groups.is_empty()
+            && !self.missing_deps.is_empty()
+            && self.missing_deps.values().flatten().all(|file| {
+                !matches!(
+                    self.dag.provided_files.get(file),
+                    Some(provided) if !matches!(provided, ProvidedFile::Late { .. })
+                )
+            })
+    }
+
+    /// Lower the CPU time limit of the executions of a ready group that have a relative time
+    /// limit, now that the time of the execution they refer to is known. The relative limit is
+    /// never lower than `MIN_RELATIVE_TIME_LIMIT`.
+    fn resolve_relative_time_limits(&mut self, group_uuid: ExecutionGroupUuid) {
+        let group = match self.dag.execution_groups.get_mut(&group_uuid) {
+            Some(group) => group,
+            None => return,
+        };
+        for exec in group.executions.iter_mut() {
+            let relative = match exec.relative_time_limit {
+                Some(relative) => relative,
+                None => continue,
+            };
+            let reference = match self.output_times.get(&relative.reference) {
+                Some(reference) => *reference,
+                None => {
+                    warn!(
+                        "The time of the reference {} of {} is unknown, ignoring the relative time limit",
+                        relative.reference, exec.description
+                    );
+                    continue;
+                }
+            };
+            let limit = (relative.factor * reference).max(MIN_RELATIVE_TIME_LIMIT);
+            let cpu_time = exec
+                .limits
+                .cpu_time
+                .map_or(limit, |cpu_time| cpu_time.min(limit));
+            exec.limits.cpu_time(cpu_time);
+        }
+    }
 }
 
 /// A `Scheduler` is a service that is able to orchestrate the execution of the DAGs, sending the
@@ -198,6 +361,10 @@ impl SchedulerClientData {
 /// ask for the evaluation of a DAG, and sends messages to the clients via the Executor. It also
 /// communicates with the WorkerManager for sending messages to the workers and known when a worker
 /// connects or disconnects.
+///
+/// The workers are shared fairly between the clients: each free worker takes the next execution of
+/// the client with the fewest running executions, so a client with a big DAG cannot starve the
+/// others. The executions of the same client are still picked following their priorities.
 #[derive(Debug)]
 pub(crate) struct Scheduler {
     /// A reference to the local file store.
@@ -210,11 +377,13 @@ pub(crate) struct Scheduler {
     executor: Sender<SchedulerExecutorMessage>,
     /// Sender of the messages to the WorkerManager, aka the messages to the workers.
     worker_manager: Sender<WorkerManagerInMessage>,
+    /// Senders of the jobs to the threads that talk with the remote cache, if the cache has one.
+    remote_cache: Option<RemoteCacheThreads>,
 
-    /// The priority queue of the ready tasks, waiting for the workers.
-    ready_execs: BinaryHeap<(DagPriority, Priority, ExecutionGroupUuid, ClientUuid)>,
     /// The data about the clients currently working.
     clients: HashMap<ClientUuid, SchedulerClientData>,
+    /// The number of jobs assigned to the workers so far.
+    served_jobs: u64,
 
     /// The list of the workers that are either ready for some work or already working on a job.
     connected_workers: HashMap<WorkerUuid, ConnectedWorker>,
@@ -225,22 +394,51 @@ impl Scheduler {
     /// Make a new `Scheduler` based on the specified file store and cache. It will receive the
     /// messages using the provided channel and sends messages to the executor and worker manager
     /// with the specified channels.
+    ///
+    /// If the cache has a remote cache, some threads are spawned for talking with it: they send the
+    /// results of the lookups to the scheduler using `sender`.
     pub fn new(
         file_store: Arc<FileStore>,
         cache: Cache,
         receiver: Receiver<SchedulerInMessage>,
+        sender: Sender<SchedulerInMessage>,
         executor: Sender<SchedulerExecutorMessage>,
         worker_manager: Sender<WorkerManagerInMessage>,
     ) -> Scheduler {
+        let remote_cache = if cache.has_remote() {
+            let (lookups_tx, lookups_rx) = channel();
+            let lookups_rx = Arc::new(Mutex::new(lookups_rx));
+            for i in 0..REMOTE_LOOKUP_THREADS {
+                let file_store = file_store.clone();
+                let lookups_rx = lookups_rx.clone();
+                let sender = sender.clone();
+                thread::Builder::new()
+                    .name(format!("Remote cache lookup {i}"))
+                    .spawn(move || Scheduler::run_remote_lookups(file_store, lookups_rx, sender))
+                    .expect("Failed to spawn remote cache lookup thread");
+            }
+            let (publishes_tx, publishes_rx) = channel();
+            thread::Builder::new()
+                .name("Remote cache publish".to_string())
+                .spawn(move || Scheduler::run_remote_publishes(publishes_rx))
+                .expect("Failed to spawn remote cache publish thread");
+            Some(RemoteCacheThreads {
+                lookups: lookups_tx,
+                publishes: publishes_tx,
+            })
+        } else {
+            None
+        };
         Scheduler {
             file_store,
             cache,
             receiver,
             executor,
             worker_manager,
+            remote_cache,
 
-            ready_execs: BinaryHeap::new(),
             clients: HashMap::new(),
+            served_jobs: 0,
 
             connected_workers: HashMap::new(),
         }
@@ -249,6 +447,7 @@ impl Scheduler {
     /// Run the `Scheduler` listening for incoming messages and blocking util the scheduler is
     /// asked to exit. When the scheduler exits it will turn down the worker manager too.
     pub fn run(mut self) -> Result<(), Error> {
+        enter_span!("scheduler");
         while let Ok(message) = self.receiver.recv() {
             match message {
                 SchedulerInMessage::Exit => {
@@ -259,8 +458,9 @@ impl Scheduler {
                     client,
                     dag,
                     callbacks,
+                    last,
                 } => {
-                    self.handle_evaluate_dag(client, *dag, *callbacks)
+                    self.handle_evaluate_dag(client, *dag, *callbacks, last)
                         .context("Failed to handle EvaluateDAG")?;
                 }
                 SchedulerInMessage::FileReady {
@@ -291,6 +491,22 @@ impl Scheduler {
                     self.handle_client_disconnected(client)
                         .context("Failed to handle ClientDisconnected")?;
                 }
+                SchedulerInMessage::AbortClient { client, target } => {
+                    self.handle_abort_client(client, target)
+                        .context("Failed to handle AbortClient")?;
+                }
+                SchedulerInMessage::SkipExecutions { client, groups } => {
+                    self.handle_skip_executions(client, groups)
+                        .context("Failed to handle SkipExecutions")?;
+                }
+                SchedulerInMessage::RemoteCacheResult {
+                    client,
+                    group,
+                    entries,
+                } => {
+                    self.handle_remote_cache_result(client, group, entries)
+                        .context("Failed to handle RemoteCacheResult")?;
+                }
                 SchedulerInMessage::Status { client } => {
                     self.handle_status_request(client)
                         .context("Failed to handle Status")?;
@@ -304,34 +520,121 @@ impl Scheduler {
         Ok(())
     }
 
-    /// Handle the client request to evaluate a DAG.
+    /// Run the lookups in the remote cache until the scheduler is gone. The receiver is shared
+    /// between all the lookup threads, each taking the next lookup when it's free.
+    fn run_remote_lookups(
+        file_store: Arc<FileStore>,
+        receiver: Arc<Mutex<Receiver<RemoteLookupJob>>>,
+        scheduler: Sender<SchedulerInMessage>,
+    ) {
+        loop {
+            // the lock is released before running the lookup
+            let job = receiver.lock().unwrap().recv();
+            let Ok(job) = job else {
+                break;
+            };
+            let entries = job.lookup.run(file_store.as_ref());
+            let message = SchedulerInMessage::RemoteCacheResult {
+                client: job.client,
+                group: job.group,
+                entries,
+            };
+            if scheduler.send(message).is_err() {
+                break;
+            }
+        }
+        debug!("Remote cache lookup thread exiting");
+    }
+
+    /// Run the publications to the remote cache until the scheduler is gone.
+    fn run_remote_publishes(receiver: Receiver<RemotePublish>) {
+        while let Ok(publish) = receiver.recv() {
+            publish.run();
+        }
+        debug!("Remote cache publish thread exiting");
+    }
+
+    /// Handle the client request to evaluate a DAG. If the client is already evaluating a DAG, this
+    /// is the next chunk of it and its executions are added to the ones of the client.
     fn handle_evaluate_dag(
         &mut self,
         client: ClientInfo,
         dag: ExecutionDAGData,
         callbacks: ExecutionDAGWatchSet,
+        last: bool,
     ) -> Result<(), Error> {
-        info!("Client '{}' asked to evaluate a new DAG", client.name);
+        enter_span!("evaluate_dag", client = %client.uuid);
         // build the scheduler structures, insert the client in the list of working
         // clients and schedule all the already cached executions.
         let dag_priority = dag.config.priority;
-        let mut client_data = SchedulerClientData::new(client.name, dag, callbacks);
-        for group in client_data.dag.execution_groups.values() {
-            let missing_dep = client_data.missing_deps.entry(group.uuid).or_default();
-            for input in group.dependencies() {
-                let entry = client_data.input_of.entry(input).or_default();
+        let new_groups: Vec<_> = dag.execution_groups.keys().cloned().collect();
+        let client_data = match self.clients.entry(client.uuid) {
+            Entry::Occupied(entry) => {
+                info!(
+                    "Client '{}' sent {} more executions",
+                    client.name,
+                    new_groups.len()
+                );
+                let client_data = entry.into_mut();
+                client_data.dag.provided_files.extend(dag.provided_files);
+                client_data
+                    .dag
+                    .execution_groups
+                    .extend(dag.execution_groups);
+                client_data
+                    .callbacks
+                    .executions
+                    .extend(callbacks.executions);
+                client_data.callbacks.files.extend(callbacks.files);
+                client_data
+                    .callbacks
+                    .urgent_files
+                    .extend(callbacks.urgent_files);
+                client_data
+            }
+            Entry::Vacant(entry) => {
+                info!("Client '{}' asked to evaluate a new DAG", client.name);
+                entry.insert(SchedulerClientData::new(client.name, dag, callbacks))
+            }
+        };
+        client_data.dag_complete = last;
+        let mut skipped = Vec::new();
+        for group_uuid in new_groups {
+            let group = &client_data.dag.execution_groups[&group_uuid];
+            let dependencies = group.dependencies();
+            // the files produced by the previous chunks are already known
+            let missing_dep: HashSet<_> = dependencies
+                .iter()
+                .filter(|input| !client_data.resolved_files.contains_key(*input))
+                .cloned()
+                .collect();
+            for input in missing_dep.iter() {
+                let entry = client_data.input_of.entry(*input).or_default();
                 entry.insert(group.uuid);
-                missing_dep.insert(input);
             }
-            // if this execution does not have any dependency, schedule it immediately
-            if missing_dep.is_empty() {
-                client_data.missing_deps.remove(&group.uuid);
+            let asked_to_skip = client_data.skipped_groups.remove(&group_uuid);
+            if asked_to_skip
+                || dependencies
+                    .iter()
+                    .any(|input| client_data.resolved_files.get(input) == Some(&false))
+            {
+                skipped.push(group.uuid);
+                client_data.missing_deps.insert(group.uuid, missing_dep);
+            } else if missing_dep.is_empty() {
+                // if this execution does not have any dependency, schedule it immediately
                 client_data.ready_groups.insert(group.uuid);
-                self.ready_execs
-                    .push((dag_priority, group.priority, group.uuid, client.uuid));
+                client_data
+                    .cache_lookup_queue
+                    .push((dag_priority, group.priority, group.uuid));
+            } else {
+                client_data.missing_deps.insert(group.uuid, missing_dep);
             }
         }
-        self.clients.insert(client.uuid, client_data);
+        // the executions that depend on a file of the previous chunks that already failed, or that
+        // the client asked to skip before sending them, are skipped right away
+        if !skipped.is_empty() {
+            self.handle_skip_executions(client.uuid, skipped)?;
+        }
         // the client may have sent and empty DAG
         self.check_completion(client.uuid)?;
 
@@ -349,6 +652,7 @@ impl Scheduler {
     ) -> Result<(), Error> {
         if let Some(client) = self.clients.get_mut(&client_uuid) {
             client.file_handles.insert(uuid, handle);
+            client.waiting_for_client = false;
             self.file_success(client_uuid, uuid)?;
             self.check_completion(client_uuid)?;
         } else {
@@ -364,6 +668,7 @@ impl Scheduler {
         result: Vec<ExecutionResult>,
         outputs: HashMap<FileUuid, FileStoreHandle>,
     ) -> Result<(), Error> {
+        enter_span!("worker_result", worker = %worker);
         let worker = match self.connected_workers.remove(&worker) {
             Some(worker) => worker,
             None => {
@@ -381,6 +686,7 @@ impl Scheduler {
                 return Ok(());
             }
         };
+        enter_span!("execution_group", group = %group_uuid, client = %client_uuid);
         let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
             client
         } else {
@@ -435,8 +741,7 @@ impl Scheduler {
                     return Ok(());
                 };
                 let priority = client.dag.execution_groups[&job].priority;
-                self.ready_execs
-                    .push((HIGH_PRIORITY, priority, job, client_uuid));
+                client.ready_queue.push((HIGH_PRIORITY, priority, job));
                 client.ready_groups.insert(job);
                 client.running_groups.remove(&job);
             }
@@ -452,24 +757,107 @@ impl Scheduler {
                 warn!("The client's evaluation wasn't completed yet");
                 // Even if the computation has not been completed, send the EvaluationDone so that
                 // the executor can exit cleanly.
+                let message = SchedulerExecutorMessageData::EvaluationDone {
+                    cache_stats: client.cache_stats,
+                };
                 self.executor
-                    .send((client_uuid, SchedulerExecutorMessageData::EvaluationDone))
+                    .send((client_uuid, message))
                     .context("Failed to send EvaluationDone to the executor")?;
             }
         }
-        self.clients.remove(&client_uuid);
-        let mut remaining = BinaryHeap::new();
-        while let Some((dag_priority, priority, exec, client)) = self.ready_execs.pop() {
-            if self.clients.contains_key(&client) {
-                remaining.push((dag_priority, priority, exec, client));
+        self.remove_client(client_uuid)
+    }
+
+    /// Handle the request of a client to abort the evaluation of another client.
+    fn handle_abort_client(
+        &mut self,
+        client_uuid: ClientUuid,
+        target: ClientUuid,
+    ) -> Result<(), Error> {
+        let found = self.clients.contains_key(&target);
+        if found {
+            warn!("Client {client_uuid} asked to abort the evaluation of client {target}");
+            self.executor
+                .send((target, SchedulerExecutorMessageData::EvaluationAborted))
+                .context("Failed to send EvaluationAborted to the executor")?;
+            self.remove_client(target)?;
+        } else {
+            warn!("Client {client_uuid} asked to abort the unknown client {target}");
+        }
+        self.executor
+            .send((
+                client_uuid,
+                SchedulerExecutorMessageData::ClientAborted { found },
+            ))
+            .context("Failed to send ClientAborted to the executor")?;
+        Ok(())
+    }
+
+    /// Handle the request of a client to skip some of its executions. The ones that are waiting
+    /// for their dependencies or for a worker are removed, and all the executions that depend on
+    /// them are skipped too. The ones in the chunks of the DAG not sent yet are skipped when they
+    /// arrive.
+    fn handle_skip_executions(
+        &mut self,
+        client_uuid: ClientUuid,
+        groups: Vec<ExecutionGroupUuid>,
+    ) -> Result<(), Error> {
+        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
+            client
+        } else {
+            warn!("Client is gone");
+            return Ok(());
+        };
+        let mut failed_files = Vec::new();
+        for group_uuid in groups {
+            let pending = client.missing_deps.remove(&group_uuid).is_some()
+                || client.ready_groups.remove(&group_uuid);
+            if !pending {
+                if !client.dag.execution_groups.contains_key(&group_uuid) {
+                    client.skipped_groups.insert(group_uuid);
+                }
+                continue;
+            }
+            // the result of a running remote lookup is ignored
+            client.remote_lookups.remove(&group_uuid);
+            client
+                .cache_lookup_queue
+                .retain(|(_, _, uuid)| *uuid != group_uuid);
+            client
+                .ready_queue
+                .retain(|(_, _, uuid)| *uuid != group_uuid);
+            debug!("Skipping execution {group_uuid} as asked by client {client_uuid}");
+            if client.callbacks.executions.contains(&group_uuid) {
+                if let Err(e) = self.executor.send((
+                    client_uuid,
+                    SchedulerExecutorMessageData::ExecutionSkipped {
+                        execution: group_uuid,
+                    },
+                )) {
+                    warn!("Cannot tell the client the execution was skipped: {e:?}");
+                }
+            }
+            if let Some(group) = client.dag.execution_groups.get(&group_uuid) {
+                failed_files.extend(group.outputs());
             }
         }
-        self.ready_execs = remaining;
+        for output in failed_files {
+            self.file_failed(client_uuid, output)?;
+        }
+        self.check_completion(client_uuid)?;
+        Ok(())
+    }
+
+    /// Remove a client, dropping its ready executions and stopping the ones that are still
+    /// running in the workers. The workers will be free as soon as they report the killed job.
+    fn remove_client(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
+        // the ready executions of the client are dropped with it
+        self.clients.remove(&client_uuid);
         // stop the jobs that are still running in the workers
         for (uuid, worker) in self.connected_workers.iter() {
             if let Some((owner, exec, _)) = worker.current_job {
                 if owner == client_uuid {
-                    warn!("Worker {uuid} is doing {exec} owned by disconnected client, killing",);
+                    warn!("Worker {uuid} is doing {exec} owned by a removed client, killing");
                     self.worker_manager
                         .send(WorkerManagerInMessage::StopWorkerJob {
                             worker: *uuid,
@@ -526,9 +914,10 @@ impl Scheduler {
         Ok(())
     }
 
-    /// Check if the client has completed the evaluation, if so tell the client we are done.
-    fn check_completion(&self, client_uuid: ClientUuid) -> Result<(), Error> {
-        let client = if let Some(client) = self.clients.get(&client_uuid) {
+    /// Check if the client has completed the evaluation, if so tell the client we are done. If the
+    /// evaluation is waiting only for the late files of the client, tell it once.
+    fn check_completion(&mut self, client_uuid: ClientUuid) -> Result<(), Error> {
+        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
             client
         } else {
             // client is gone, dont worry to much about it
@@ -536,9 +925,18 @@ impl Scheduler {
         };
         if client.is_done() {
             debug!("Computation completed for client: {client_uuid}");
+            let message = SchedulerExecutorMessageData::EvaluationDone {
+                cache_stats: client.cache_stats,
+            };
             self.executor
-                .send((client_uuid, SchedulerExecutorMessageData::EvaluationDone))
+                .send((client_uuid, message))
                 .context("Failed to send EvaluationDone to the executor")?;
+        } else if !client.waiting_for_client && client.is_waiting_for_late_files() {
+            debug!("Client {client_uuid} has to provide its late files");
+            client.waiting_for_client = true;
+            self.executor
+                .send((client_uuid, SchedulerExecutorMessageData::WaitingForClient))
+                .context("Failed to send WaitingForClient to the executor")?;
         }
         Ok(())
     }
@@ -555,6 +953,7 @@ impl Scheduler {
             // client is gone, dont worry to much about it
             return Ok(());
         };
+        client.resolved_files.insert(file, false);
         if !client.input_of.contains_key(&file) {
             return Ok(());
         }
@@ -599,6 +998,7 @@ impl Scheduler {
             // client is gone, dont worry to much about it
             return Ok(());
         };
+        client.resolved_files.insert(file, true);
         if !client.input_of.contains_key(&file) {
             return Ok(());
         }
@@ -608,12 +1008,9 @@ impl Scheduler {
                 files.remove(&file);
                 if files.is_empty() {
                     client.missing_deps.remove(group_uuid);
-                    self.ready_execs.push((
-                        HIGH_PRIORITY,
-                        group.priority,
-                        *group_uuid,
-                        client_uuid,
-                    ));
+                    client
+                        .cache_lookup_queue
+                        .push((HIGH_PRIORITY, group.priority, *group_uuid));
                     client.ready_groups.insert(*group_uuid);
                 }
             }
@@ -683,10 +1080,17 @@ impl Scheduler {
         for (uuid, handle) in outputs.iter() {
             client.file_handles.insert(*uuid, handle.clone());
         }
+        for (exec, result) in group.executions.iter().zip(result.iter()) {
+            for output in exec.outputs() {
+                client
+                    .output_times
+                    .insert(output, result.resources.cpu_time);
+            }
+        }
 
         let successful = result.iter().all(|r| r.status.is_success());
         let internal_error = result.iter().any(|r| r.status.is_internal_error());
-        if !from_cache && !internal_error {
+        if !from_cache && !internal_error && group.is_cacheable() {
             self.cache_execution(client_uuid, group, outputs, result);
         }
         if successful {
@@ -729,49 +1133,92 @@ impl Scheduler {
             file_keys.insert(output, outputs[&output].key().clone());
         }
         self.cache.insert(group, &client.file_handles, result);
+        if let Some(remote_cache) = &self.remote_cache {
+            let publish =
+                self.cache
+                    .remote_publish(group, &client.file_handles, self.file_store.as_ref());
+            if let Some(publish) = publish {
+                if remote_cache.publishes.send(publish).is_err() {
+                    warn!("The remote cache publish thread is gone");
+                }
+            }
+        }
     }
 
-    /// Look at all the ready executions and mark as completed all the ones that are inside the
-    /// cache.
+    /// Look at the executions that became ready since the last call and mark as completed all the
+    /// ones that are inside the cache. The others are moved to the ready queue, waiting for a
+    /// worker.
     fn schedule_cached(&mut self) -> Result<(), Error> {
-        let mut not_cached = BinaryHeap::new();
         let mut cached = Vec::new();
 
-        for (dag_priority, priority, group_uuid, client_uuid) in self.ready_execs.iter() {
-            let client = if let Some(client) = self.clients.get_mut(client_uuid) {
-                client
-            } else {
-                // client is gone, dont worry to much about it
-                continue;
-            };
-            let dag = &client.dag;
-            let cache_mode = &dag.config.cache_mode;
+        for (client_uuid, client) in self.clients.iter_mut() {
+            let to_lookup = std::mem::take(&mut client.cache_lookup_queue);
+            // the limits are part of the cache key, so they are set before the lookup
+            for (_, _, group_uuid) in &to_lookup {
+                client.resolve_relative_time_limits(*group_uuid);
+            }
+            let cache_mode = &client.dag.config.cache_mode;
             // disable the cache for the execution
             if let CacheMode::Nothing = cache_mode {
-                not_cached.push((*dag_priority, *priority, *group_uuid, *client_uuid));
-                continue;
-            }
-            let group = dag.execution_groups[group_uuid].clone();
-            if !Scheduler::is_cacheable(&group, cache_mode) {
-                not_cached.push((*dag_priority, *priority, group.uuid, *client_uuid));
+                client.ready_queue.extend(to_lookup);
                 continue;
             }
-            let result = self
-                .cache
-                .get(&group, &client.file_handles, self.file_store.as_ref());
-            match result {
-                CacheResult::Hit { result, outputs } => {
-                    info!("Execution {} is a cache hit!", group.uuid);
-                    client.ready_groups.remove(&group.uuid);
-                    cached.push((*client_uuid, group, result, outputs));
+            for (dag_priority, priority, group_uuid) in to_lookup {
+                let group = &client.dag.execution_groups[&group_uuid];
+                if !Scheduler::is_cacheable(group, cache_mode) {
+                    client
+                        .ready_queue
+                        .push((dag_priority, priority, group_uuid));
+                    continue;
+                }
+                let result =
+                    self.cache
+                        .get_local(group, &client.file_handles, self.file_store.as_ref());
+                if let (CacheResult::Miss, Some(remote_cache)) = (&result, &self.remote_cache) {
+                    if let Some(lookup) = self.cache.remote_lookup(group, &client.file_handles) {
+                        let job = RemoteLookupJob {
+                            client: *client_uuid,
+                            group: group_uuid,
+                            lookup,
+                        };
+                        if remote_cache.lookups.send(job).is_ok() {
+                            client
+                                .remote_lookups
+                                .insert(group_uuid, (dag_priority, priority));
+                            continue;
+                        }
+                        warn!("The remote cache lookup threads are gone");
+                    }
                 }
-                CacheResult::Miss => {
-                    not_cached.push((*dag_priority, *priority, group.uuid, *client_uuid));
+                client.cache_stats.record(&result);
+                match result {
+                    CacheResult::Hit {
+                        result,
+                        outputs,
+                        reason,
+                    } => {
+                        enter_span!("execution_group", group = %group.uuid, client = %client_uuid);
+                        info!("Execution {} is a cache hit ({})!", group.uuid, reason);
+                        client.ready_groups.remove(&group.uuid);
+                        cached.push((*client_uuid, group.clone(), result, outputs));
+                    }
+                    CacheResult::Miss => {
+                        Scheduler::explain_miss(
+                            &self.cache,
+                            &self.file_store,
+                            &self.executor,
+                            *client_uuid,
+                            client,
+                            group,
+                        );
+                        client
+                            .ready_queue
+                            .push((dag_priority, priority, group_uuid));
+                    }
                 }
             }
         }
 
-        self.ready_execs = not_cached;
         for (client, exec, result, outputs) in cached.into_iter() {
             self.exec_completed(client, &exec, result, outputs, true)?;
         }
@@ -779,8 +1226,94 @@ impl Scheduler {
         Ok(())
     }
 
+    /// Handle the result of the lookup of an execution in the remote cache: the execution is
+    /// completed if it was found, otherwise it's moved to the ready queue.
+    fn handle_remote_cache_result(
+        &mut self,
+        client_uuid: ClientUuid,
+        group_uuid: ExecutionGroupUuid,
+        entries: RemoteEntries,
+    ) -> Result<(), Error> {
+        let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
+            client
+        } else {
+            // client is gone, dont worry to much about it
+            return Ok(());
+        };
+        let (dag_priority, priority) = match client.remote_lookups.remove(&group_uuid) {
+            Some(priorities) => priorities,
+            // the execution has been skipped in the meantime
+            None => return Ok(()),
+        };
+        let group = &client.dag.execution_groups[&group_uuid];
+        let result = self.cache.get_with_remote(
+            group,
+            &client.file_handles,
+            self.file_store.as_ref(),
+            entries,
+        );
+        client.cache_stats.record(&result);
+        match result {
+            CacheResult::Hit {
+                result,
+                outputs,
+                reason,
+            } => {
+                enter_span!("execution_group", group = %group_uuid, client = %client_uuid);
+                info!(
+                    "Execution {} is a remote cache hit ({})!",
+                    group_uuid, reason
+                );
+                client.ready_groups.remove(&group_uuid);
+                let group = group.clone();
+                self.exec_completed(client_uuid, &group, result, outputs, true)?;
+            }
+            CacheResult::Miss => {
+                Scheduler::explain_miss(
+                    &self.cache,
+                    &self.file_store,
+                    &self.executor,
+                    client_uuid,
+                    client,
+                    group,
+                );
+                client
+                    .ready_queue
+                    .push((dag_priority, priority, group_uuid));
+            }
+        }
+        self.assign_jobs()?;
+        self.check_completion(client_uuid)?;
+        Ok(())
+    }
+
+    /// Tell the client why an execution is not in the cache, if the DAG asked for it.
+    fn explain_miss(
+        cache: &Cache,
+        file_store: &FileStore,
+        executor: &Sender<SchedulerExecutorMessage>,
+        client_uuid: ClientUuid,
+        client: &SchedulerClientData,
+        group: &ExecutionGroup,
+    ) {
+        if !client.dag.config.explain_cache_miss {
+            return;
+        }
+        let reason = cache.explain_miss(group, &client.file_handles, file_store);
+        let mex = SchedulerExecutorMessageData::CacheMiss {
+            execution: group.uuid,
+            reason: reason.to_string(),
+        };
+        if let Err(e) = executor.send((client_uuid, mex)) {
+            warn!("Cannot tell the client the execution is not cached: {e:?}");
+        }
+    }
+
     /// Whether an execution is eligible to be fetch from the cache.
-    fn is_cacheable(group: &ExecutionGroup, cache_mode: &CacheMode) -> bool {
+    pub(crate) fn is_cacheable(group: &ExecutionGroup, cache_mode: &CacheMode) -> bool {
+        if !group.is_cacheable() || matches!(cache_mode, CacheMode::Nothing) {
+            return false;
+        }
         if let (CacheMode::Except(set), Some(tag)) = (cache_mode, group.tag.as_ref()) {
             if set.contains(tag) {
                 return false;
@@ -795,18 +1328,23 @@ impl Scheduler {
             if worker.current_job.is_some() {
                 continue;
             }
-            let (_, _, group_uuid, client_uuid) = match self.ready_execs.pop() {
+            let client_uuid = match Scheduler::next_client(&self.clients) {
+                Some(client_uuid) => client_uuid,
+                None => break,
+            };
+            let client = self
+                .clients
+                .get_mut(&client_uuid)
+                .ok_or_else(|| anyhow!("Client {client_uuid} is gone"))?;
+            let (_, _, group_uuid) = match client.ready_queue.pop() {
                 Some(exec) => exec,
                 None => break,
             };
+            enter_span!("execution_group", group = %group_uuid, client = %client_uuid);
             trace!("Assigning {group_uuid} to worker {worker_uuid}");
             worker.current_job = Some((client_uuid, group_uuid, Instant::now()));
-            let client = if let Some(client) = self.clients.get_mut(&client_uuid) {
-                client
-            } else {
-                // client is gone, dont worry to much about it
-                continue;
-            };
+            self.served_jobs += 1;
+            client.last_served = self.served_jobs;
             client.ready_groups.remove(&group_uuid);
             client.running_groups.insert(group_uuid);
             let group = &client.dag.execution_groups[&group_uuid];
@@ -823,6 +1361,7 @@ impl Scheduler {
             let job = WorkerJob {
                 group: group.clone(),
                 dep_keys,
+                cache_mode: client.dag.config.cache_mode.clone(),
             };
             self.worker_manager
                 .send(WorkerManagerInMessage::WorkerJob {
@@ -844,4 +1383,397 @@ impl Scheduler {
         }
         Ok(())
     }
+
+    /// Choose the client whose next ready execution should be assigned to a free worker.
+    ///
+    /// The clients with a higher DAG priority are served first. Between the clients with the same
+    /// priority, the one with the fewest running executions is chosen, breaking the ties in
+    /// round-robin order.
+    fn next_client(clients: &HashMap<ClientUuid, SchedulerClientData>) -> Option<ClientUuid> {
+        clients
+            .iter()
+            .filter(|(_, client)| !client.ready_queue.is_empty())
+            .min_by_key(|(_, client)| {
+                (
+                    Reverse(client.dag.config.priority),
+                    client.running_groups.len(),
+                    client.last_served,
+                )
+            })
+            .map(|(uuid, _)| *uuid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_dag::{Execution, ExecutionCommand, ExecutionDAG, File};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn client(priority: DagPriority, ready: usize, running: usize) -> SchedulerClientData {
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().priority(priority);
+        let mut client = SchedulerClientData::new("client".into(), dag.data, Default::default());
+        for i in 0..ready {
+            client.ready_queue.push((0, i as Priority, Uuid::new_v4()));
+        }
+        for _ in 0..running {
+            client.running_groups.insert(Uuid::new_v4());
+        }
+        client
+    }
+
+    /// Make a scheduler with an empty store and cache inside a temporary directory, returning the
+    /// receivers of its messages to the executor and to the worker manager.
+    fn new_scheduler() -> (
+        TempDir,
+        Scheduler,
+        Receiver<SchedulerExecutorMessage>,
+        Receiver<WorkerManagerInMessage>,
+    ) {
+        let cwd = TempDir::new().unwrap();
+        let file_store = FileStore::new(
+            cwd.path().join("store"),
+            1000,
+            1000,
+            Default::default(),
+            None,
+            1,
+        )
+        .unwrap();
+        let cache = Cache::new(cwd.path().join("cache")).unwrap();
+        let (scheduler_tx, scheduler_rx) = channel();
+        let (executor_tx, executor_rx) = channel();
+        let (worker_manager_tx, worker_manager_rx) = channel();
+        let scheduler = Scheduler::new(
+            Arc::new(file_store),
+            cache,
+            scheduler_rx,
+            scheduler_tx,
+            executor_tx,
+            worker_manager_tx,
+        );
+        (cwd, scheduler, executor_rx, worker_manager_rx)
+    }
+
+    #[test]
+    fn test_next_client_fewest_running() {
+        let busy = Uuid::new_v4();
+        let idle = Uuid::new_v4();
+        let mut clients = HashMap::new();
+        clients.insert(busy, client(0, 10, 4));
+        clients.insert(idle, client(0, 1, 1));
+        assert_eq!(Scheduler::next_client(&clients), Some(idle));
+    }
+
+    #[test]
+    fn test_next_client_round_robin() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let mut clients = HashMap::new();
+        clients.insert(first, client(0, 10, 0));
+        clients.insert(second, client(0, 10, 0));
+        clients.get_mut(&first).unwrap().last_served = 1;
+        assert_eq!(Scheduler::next_client(&clients), Some(second));
+        clients.get_mut(&second).unwrap().last_served = 2;
+        assert_eq!(Scheduler::next_client(&clients), Some(first));
+    }
+
+    #[test]
+    fn test_next_client_dag_priority() {
+        let low = Uuid::new_v4();
+        let high = Uuid::new_v4();
+        let mut clients = HashMap::new();
+        clients.insert(low, client(0, 10, 0));
+        clients.insert(high, client(1, 10, 4));
+        assert_eq!(Scheduler::next_client(&clients), Some(high));
+    }
+
+    #[test]
+    fn test_next_client_nothing_ready() {
+        let mut clients = HashMap::new();
+        clients.insert(Uuid::new_v4(), client(0, 0, 4));
+        assert_eq!(Scheduler::next_client(&clients), None);
+    }
+
+    #[test]
+    fn test_abort_client() {
+        let (_cwd, mut scheduler, executor_rx, worker_manager_rx) = new_scheduler();
+
+        let operator = Uuid::new_v4();
+        let target = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        scheduler.clients.insert(target, client(0, 10, 1));
+        scheduler.clients.insert(other, client(0, 10, 1));
+        let target_job = Uuid::new_v4();
+        let other_job = Uuid::new_v4();
+        let target_worker = Uuid::new_v4();
+        for (worker, owner, job) in [
+            (target_worker, target, target_job),
+            (Uuid::new_v4(), other, other_job),
+        ] {
+            scheduler.connected_workers.insert(
+                worker,
+                ConnectedWorker {
+                    uuid: worker,
+                    name: "worker".into(),
+                    current_job: Some((owner, job, Instant::now())),
+                },
+            );
+        }
+
+        scheduler.handle_abort_client(operator, target).unwrap();
+        assert!(!scheduler.clients.contains_key(&target));
+        assert!(scheduler.clients.contains_key(&other));
+        let messages: Vec<_> = executor_rx.try_iter().collect();
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(
+            messages[0],
+            (uuid, SchedulerExecutorMessageData::EvaluationAborted) if uuid == target
+        ));
+        assert!(matches!(
+            messages[1],
+            (uuid, SchedulerExecutorMessageData::ClientAborted { found: true }) if uuid == operator
+        ));
+        // only the worker of the aborted client is stopped
+        let stopped: Vec<_> = worker_manager_rx
+            .try_iter()
+            .map(|message| match message {
+                WorkerManagerInMessage::StopWorkerJob { worker, job } => (worker, job),
+                _ => panic!("Unexpected message to the worker manager"),
+            })
+            .collect();
+        assert_eq!(stopped, vec![(target_worker, target_job)]);
+
+        scheduler.handle_abort_client(operator, target).unwrap();
+        assert!(matches!(
+            executor_rx.try_recv().unwrap(),
+            (uuid, SchedulerExecutorMessageData::ClientAborted { found: false }) if uuid == operator
+        ));
+    }
+
+    #[test]
+    fn test_skip_executions() {
+        let (_cwd, mut scheduler, executor_rx, _worker_manager_rx) = new_scheduler();
+
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let stdout = exec.capture_stdout(None);
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::system("true"));
+        exec2.stdin(stdout);
+        let exec = dag.add_execution(exec);
+        let exec2 = dag.add_execution(exec2);
+        let callbacks = ExecutionDAGWatchSet {
+            executions: [exec, exec2].into_iter().collect(),
+            ..Default::default()
+        };
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(client.clone(), dag.data, callbacks, true)
+            .unwrap();
+        assert_eq!(scheduler.clients[&client.uuid].ready_queue.len(), 1);
+
+        scheduler
+            .handle_skip_executions(client.uuid, vec![exec])
+            .unwrap();
+        assert!(scheduler.clients[&client.uuid].ready_queue.is_empty());
+        let skipped: Vec<_> = executor_rx
+            .try_iter()
+            .filter_map(|(_, message)| match message {
+                SchedulerExecutorMessageData::ExecutionSkipped { execution } => Some(execution),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(skipped, vec![exec, exec2]);
+        assert!(scheduler.clients[&client.uuid].is_done());
+    }
+
+    #[test]
+    fn test_skip_executions_not_sent_yet() {
+        let (_cwd, mut scheduler, executor_rx, _worker_manager_rx) = new_scheduler();
+
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut().stream_chunk_size(Some(1));
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let stdout = exec.capture_stdout(None);
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::system("true"));
+        exec2.stdin(stdout);
+        let exec = dag.add_execution(exec);
+        let exec2 = dag.add_execution(exec2);
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        let mut chunks = dag.data.split_chunks(1).into_iter();
+        let watch = |uuid| ExecutionDAGWatchSet {
+            executions: [uuid].into_iter().collect(),
+            ..Default::default()
+        };
+        scheduler
+            .handle_evaluate_dag(client.clone(), chunks.next().unwrap(), watch(exec), false)
+            .unwrap();
+        // the client asks to skip an execution of the next chunk, before sending it
+        scheduler
+            .handle_skip_executions(client.uuid, vec![exec2])
+            .unwrap();
+        scheduler
+            .handle_evaluate_dag(client.clone(), chunks.next().unwrap(), watch(exec2), true)
+            .unwrap();
+
+        let data = &scheduler.clients[&client.uuid];
+        assert!(!data.missing_deps.contains_key(&exec2));
+        assert!(!data.ready_groups.contains(&exec2));
+        assert!(data.skipped_groups.is_empty());
+        let skipped: Vec<_> = executor_rx
+            .try_iter()
+            .filter_map(|(_, message)| match message {
+                SchedulerExecutorMessageData::ExecutionSkipped { execution } => Some(execution),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(skipped, vec![exec2]);
+    }
+
+    #[test]
+    fn test_waiting_for_late_files() {
+        let (_cwd, mut scheduler, executor_rx, _worker_manager_rx) = new_scheduler();
+
+        let mut dag = ExecutionDAG::new();
+        let late = File::new("late");
+        dag.provide_later(late.clone());
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        exec.stdin(&late);
+        dag.add_execution(exec);
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        scheduler
+            .handle_evaluate_dag(client.clone(), dag.data, Default::default(), true)
+            .unwrap();
+        let waiting = |executor_rx: &Receiver<SchedulerExecutorMessage>| {
+            executor_rx
+                .try_iter()
+                .filter(|(_, message)| {
+                    matches!(message, SchedulerExecutorMessageData::WaitingForClient)
+                })
+                .count()
+        };
+        assert_eq!(waiting(&executor_rx), 1);
+        // the client is told only once
+        scheduler.check_completion(client.uuid).unwrap();
+        assert_eq!(waiting(&executor_rx), 0);
+
+        let key = FileStoreKey::from_content(b"content");
+        let handle = scheduler
+            .file_store
+            .store(&key, [b"content".to_vec()])
+            .unwrap();
+        scheduler
+            .handle_file_ready(client.uuid, late.uuid, handle)
+            .unwrap();
+        assert_eq!(waiting(&executor_rx), 0);
+        assert_eq!(scheduler.clients[&client.uuid].ready_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_evaluate_chunks() {
+        let (_cwd, mut scheduler, executor_rx, _worker_manager_rx) = new_scheduler();
+
+        let mut dag = ExecutionDAG::new();
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let stdout = exec.capture_stdout(None);
+        let mut exec2 = Execution::new("exec2", ExecutionCommand::system("true"));
+        exec2.stdin(stdout);
+        let exec = dag.add_execution(exec);
+        let exec2 = dag.add_execution(exec2);
+        let mut chunks = dag.data.split_chunks(1);
+        assert_eq!(chunks.len(), 2);
+        let second = chunks.pop().unwrap();
+        let first = chunks.pop().unwrap();
+        let client = ClientInfo {
+            uuid: Uuid::new_v4(),
+            name: "client".into(),
+        };
+        let callbacks = ExecutionDAGWatchSet {
+            executions: [exec].into_iter().collect(),
+            ..Default::default()
+        };
+        scheduler
+            .handle_evaluate_dag(client.clone(), first, callbacks, false)
+            .unwrap();
+        scheduler
+            .handle_skip_executions(client.uuid, vec![exec])
+            .unwrap();
+        // the second chunk is still missing
+        assert!(!scheduler.clients[&client.uuid].is_done());
+
+        let callbacks = ExecutionDAGWatchSet {
+            executions: [exec2].into_iter().collect(),
+            ..Default::default()
+        };
+        scheduler
+            .handle_evaluate_dag(client.clone(), second, callbacks, true)
+            .unwrap();
+        let skipped: Vec<_> = executor_rx
+            .try_iter()
+            .filter_map(|(_, message)| match message {
+                SchedulerExecutorMessageData::ExecutionSkipped { execution } => Some(execution),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(skipped, vec![exec, exec2]);
+        assert!(scheduler.clients[&client.uuid].is_done());
+    }
+
+    #[test]
+    fn test_resolve_relative_time_limits() {
+        let mut dag = ExecutionDAG::new();
+        let mut official = Execution::new("official", ExecutionCommand::system("true"));
+        let official_output = official.capture_stdout(None);
+        let mut relative = Execution::new("relative", ExecutionCommand::system("true"));
+        relative.limits_mut().cpu_time(2.0);
+        relative.relative_time_limit(official_output.uuid, 3.0);
+        let mut absolute = Execution::new("absolute", ExecutionCommand::system("true"));
+        absolute.limits_mut().cpu_time(0.5);
+        absolute.relative_time_limit(official_output.uuid, 4.0);
+        dag.add_execution(official);
+        let relative = dag.add_execution(relative);
+        let absolute = dag.add_execution(absolute);
+        let mut client = SchedulerClientData::new("client".into(), dag.data, Default::default());
+        client.output_times.insert(official_output.uuid, 0.25);
+
+        client.resolve_relative_time_limits(relative);
+        client.resolve_relative_time_limits(absolute);
+        let cpu_time = |group| {
+            client.dag.execution_groups[&group].executions[0]
+                .limits
+                .cpu_time
+        };
+        assert_eq!(cpu_time(relative), Some(0.75));
+        // the absolute limit is lower than the relative one
+        assert_eq!(cpu_time(absolute), Some(0.5));
+    }
+
+    #[test]
+    fn test_resolve_relative_time_limits_floor() {
+        let mut dag = ExecutionDAG::new();
+        let mut official = Execution::new("official", ExecutionCommand::system("true"));
+        let official_output = official.capture_stdout(None);
+        let mut relative = Execution::new("relative", ExecutionCommand::system("true"));
+        relative.relative_time_limit(official_output.uuid, 3.0);
+        dag.add_execution(official);
+        let relative = dag.add_execution(relative);
+        let mut client = SchedulerClientData::new("client".into(), dag.data, Default::default());
+        // the official solution was too fast to be measured
+        client.output_times.insert(official_output.uuid, 0.0);
+
+        client.resolve_relative_time_limits(relative);
+        let limits = &client.dag.execution_groups[&relative].executions[0].limits;
+        assert_eq!(limits.cpu_time, Some(MIN_RELATIVE_TIME_LIMIT));
+    }
 }
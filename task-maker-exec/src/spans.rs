@@ -0,0 +1,37 @@
+//! Structured spans for correlating the logs of concurrent executions.
+//!
+//! With the `tracing` feature the logging macros of this crate emit `tracing` events, and the
+//! executor, the scheduler and the workers open spans carrying the uuids of the clients, the
+//! execution groups and the workers. When no `tracing` subscriber is installed the events are
+//! forwarded to `log`, so the output is unchanged. Without the feature the spans compile to nothing.
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::Span;
+
+/// Stand-in for `tracing::Span` used when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    /// Return the span the current thread is in. Useful for entering it again in a new thread.
+    pub(crate) fn current() -> Span {
+        Span
+    }
+
+    /// Enter this span, returning a guard that exits it when dropped.
+    pub(crate) fn entered(self) -> Span {
+        self
+    }
+}
+
+/// Enter a new span with the provided name and fields, until the end of the current scope.
+///
+/// The syntax is the same as `tracing::info_span!`, without the `tracing` feature it does nothing.
+macro_rules! enter_span {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($($args)*).entered();
+    };
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Error};
+use task_maker_cache::{Cache, CacheResult};
+use task_maker_dag::{CacheMode, ExecutionGroup, ExecutionResult, FileUuid};
+use task_maker_store::{FileStore, FileStoreHandle, FileStoreKey, ReadFileIterator};
+
+use crate::scheduler::Scheduler;
+use crate::worker::OutputFile;
+
+/// The cache of the executions kept by a worker, stored next to its `FileStore`.
+///
+/// The server only sends to the worker the jobs that missed its own cache, so this cache is
+/// consulted only after the server's one: it avoids running again the executions the worker already
+/// did for a server that lost its cache (e.g. a different server, or one restarted with a clean
+/// store). The results found here are sent to the server as if they were computed by the worker,
+/// so the server caches them as usual. Since the worker keeps the inputs of the executions in its
+/// `FileStore`, the server is asked only for the files the worker doesn't already have.
+#[derive(Clone)]
+pub(crate) struct WorkerCache {
+    /// The store with the files referenced by the cache entries.
+    file_store: Arc<FileStore>,
+    /// The cache with the executions run by the worker.
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl WorkerCache {
+    /// Make a new `WorkerCache` that references the files inside `file_store`.
+    pub(crate) fn new(file_store: Arc<FileStore>, cache: Arc<Mutex<Cache>>) -> Self {
+        Self { file_store, cache }
+    }
+
+    /// Whether the execution group can use the cache of the worker, with the cache mode of the
+    /// client. The same rules of the cache of the server apply.
+    fn is_cacheable(group: &ExecutionGroup, cache_mode: &CacheMode) -> bool {
+        Scheduler::is_cacheable(group, cache_mode) && group.controller_settings.is_none()
+    }
+
+    /// Search the execution group in the cache, given the handles of its inputs. Returns the
+    /// results of the executions and the handles of the outputs in case of a hit.
+    pub(crate) fn lookup(
+        &self,
+        group: &ExecutionGroup,
+        cache_mode: &CacheMode,
+        inputs: &HashMap<FileUuid, FileStoreHandle>,
+    ) -> Option<(Vec<ExecutionResult>, HashMap<FileUuid, FileStoreHandle>)> {
+        if !Self::is_cacheable(group, cache_mode) {
+            return None;
+        }
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(group, inputs, &self.file_store) {
+            CacheResult::Hit {
+                result, outputs, ..
+            } => Some((result, outputs)),
+            CacheResult::Miss => None,
+        }
+    }
+
+    /// Store the outputs of an execution group inside the `FileStore` and add its results to the
+    /// cache.
+    pub(crate) fn insert(
+        &self,
+        group: &ExecutionGroup,
+        cache_mode: &CacheMode,
+        inputs: &HashMap<FileUuid, FileStoreHandle>,
+        results: &[ExecutionResult],
+        outputs: &HashMap<FileUuid, FileStoreKey>,
+        output_paths: &HashMap<FileUuid, OutputFile>,
+    ) -> Result<(), Error> {
+        if !Self::is_cacheable(group, cache_mode) || !results.iter().all(Cache::is_cacheable) {
+            return Ok(());
+        }
+        // a flush while storing the outputs must not evict the outputs stored just before
+        let expected_bytes = outputs
+            .keys()
+            .map(|uuid| match &output_paths[uuid] {
+                OutputFile::OnDisk(path) => std::fs::metadata(path).map_or(0, |m| m.len()),
+                OutputFile::InMemory(content) => content.len() as u64,
+            })
+            .sum();
+        let _reservation = self.file_store.reserve(expected_bytes);
+        let mut file_keys = inputs.clone();
+        for (uuid, key) in outputs {
+            let handle = match &output_paths[uuid] {
+                OutputFile::OnDisk(path) => {
+                    let content = ReadFileIterator::new(path).with_context(|| {
+                        format!("Failed to read output file at {}", path.display())
+                    })?;
+                    self.file_store.store(key, content)
+                }
+                OutputFile::InMemory(content) => self.file_store.store(key, [content.clone()]),
+            }
+            .context("Failed to store output file in the worker store")?;
+            file_keys.insert(*uuid, handle);
+        }
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(group, &file_keys, results.to_vec());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_dag::{
+        CacheHitReason, Execution, ExecutionCommand, ExecutionResourcesUsage, ExecutionStatus, File,
+    };
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn result() -> ExecutionResult {
+        ExecutionResult {
+            status: ExecutionStatus::Success,
+            resources: ExecutionResourcesUsage::default(),
+            was_killed: false,
+            was_cached: false,
+            cache_hit: None,
+            missing_outputs: vec![],
+            stderr: None,
+            stdout: None,
+        }
+    }
+
+    #[test]
+    fn test_worker_cache_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let file_store = Arc::new(
+            FileStore::new(
+                dir.path().join("store"),
+                1000,
+                1000,
+                Default::default(),
+                None,
+                1,
+            )
+            .unwrap(),
+        );
+        let cache = Cache::new(dir.path().join("cache")).unwrap();
+        let cache = WorkerCache::new(file_store.clone(), Arc::new(Mutex::new(cache)));
+
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let input = File::new("input");
+        exec.input(&input, "input", false);
+        let output = exec.output("output");
+        let group: ExecutionGroup = exec.into();
+
+        let input_key = FileStoreKey::from_content(b"input");
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            input.uuid,
+            file_store.store(&input_key, [b"input".to_vec()]).unwrap(),
+        );
+        assert!(cache
+            .lookup(&group, &CacheMode::Everything, &inputs)
+            .is_none());
+
+        let output_key = FileStoreKey::from_content(b"output");
+        let outputs = HashMap::from([(output.uuid, output_key.clone())]);
+        let output_paths = HashMap::from([(output.uuid, OutputFile::InMemory(b"output".to_vec()))]);
+        cache
+            .insert(
+                &group,
+                &CacheMode::Everything,
+                &inputs,
+                &[result()],
+                &outputs,
+                &output_paths,
+            )
+            .unwrap();
+
+        let (results, outputs) = cache
+            .lookup(&group, &CacheMode::Everything, &inputs)
+            .unwrap();
+        assert_eq!(results[0].status, ExecutionStatus::Success);
+        assert!(results[0].was_cached);
+        assert_eq!(results[0].cache_hit, Some(CacheHitReason::Exact));
+        let handle = &outputs[&output.uuid];
+        assert_eq!(handle.key(), &output_key);
+        assert_eq!(handle.read_all().unwrap(), b"output");
+    }
+
+    #[test]
+    fn test_worker_cache_mode() {
+        let dir = TempDir::new().unwrap();
+        let file_store = Arc::new(
+            FileStore::new(
+                dir.path().join("store"),
+                1000,
+                1000,
+                Default::default(),
+                None,
+                1,
+            )
+            .unwrap(),
+        );
+        let cache = Cache::new(dir.path().join("cache")).unwrap();
+        let cache = WorkerCache::new(file_store.clone(), Arc::new(Mutex::new(cache)));
+
+        let mut exec = Execution::new("exec", ExecutionCommand::system("true"));
+        let output = exec.output("output");
+        let mut group: ExecutionGroup = exec.into();
+        group.tag = Some("tag".into());
+        let inputs = HashMap::new();
+        let outputs = HashMap::from([(output.uuid, FileStoreKey::from_content(b"output"))]);
+        let output_paths = HashMap::from([(output.uuid, OutputFile::InMemory(b"output".to_vec()))]);
+
+        // the executions with an excluded tag are not stored
+        let except = CacheMode::Except(["tag".into()].into_iter().collect());
+        cache
+            .insert(
+                &group,
+                &except,
+                &inputs,
+                &[result()],
+                &outputs,
+                &output_paths,
+            )
+            .unwrap();
+        assert!(cache
+            .lookup(&group, &CacheMode::Everything, &inputs)
+            .is_none());
+
+        // the cache is not used if the client disabled it
+        cache
+            .insert(
+                &group,
+                &CacheMode::Everything,
+                &inputs,
+                &[result()],
+                &outputs,
+                &output_paths,
+            )
+            .unwrap();
+        assert!(cache.lookup(&group, &CacheMode::Nothing, &inputs).is_none());
+        assert!(cache.lookup(&group, &except, &inputs).is_none());
+        assert!(cache
+            .lookup(&group, &CacheMode::Everything, &inputs)
+            .is_some());
+    }
+}
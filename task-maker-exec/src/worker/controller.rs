@@ -20,7 +20,6 @@ use nix::{
     fcntl::{fcntl, FcntlArg, FdFlag},
     unistd::pipe,
 };
-use tabox::configuration::SandboxConfiguration;
 use task_maker_dag::{
     ControllerSettings, Execution, ExecutionCommand, ExecutionInputBehaviour,
     ExecutionOutputBehaviour, ExecutionResult, FIFO_SANDBOX_DIR,
@@ -30,11 +29,15 @@ use tempfile::TempDir;
 use uuid::Uuid;
 
 use crate::{
-    execution_unit::{sandbox::Sandbox, ExecutionUnit, SandboxResult},
+    execution_unit::{
+        sandbox::{Sandbox, SandboxUser},
+        ExecutionUnit, SandboxResult,
+    },
     find_tools::find_tools_path,
     proto::WorkerClientMessage,
+    spans::Span,
     worker::{compute_execution_result, get_result_outputs, OutputFile, WorkerCurrentJob},
-    RawSandboxResult, SandboxRunner,
+    RawSandboxConfig, RawSandboxResult, SandboxRunner,
 };
 
 fn controller_keeper_inner(process_limit: usize, result_dir: &Path) -> Result<()> {
@@ -138,7 +141,7 @@ fn controller_keeper_inner(process_limit: usize, result_dir: &Path) -> Result<()
             continue;
         };
 
-        let config: SandboxConfiguration =
+        let config: RawSandboxConfig =
             serde_json::from_str(&line).context("while parsing sandbox configuration")?;
         let config = serde_json::to_string(&config).context("Failed to serialize config")?;
         let output_path = if num_started_processes == 0 {
@@ -330,7 +333,7 @@ impl ControllerKeeper {
 }
 
 impl SandboxRunner for ControllerKeeper {
-    fn run(&self, config: SandboxConfiguration, pid: Arc<AtomicU32>) -> RawSandboxResult {
+    fn run(&self, config: RawSandboxConfig, pid: Arc<AtomicU32>) -> RawSandboxResult {
         // try blocks at home
         let result = (|| -> Result<_> {
             let (sender, receiver) = channel();
@@ -372,6 +375,7 @@ pub(super) fn execute_controlled_job(
     sender: &ChannelSender<WorkerClientMessage>,
     sandbox_path: &Path,
     runner: Arc<dyn SandboxRunner>,
+    user: SandboxUser,
 ) -> Result<JoinHandle<()>, Error> {
     // We don't use the runner, but rather unconditionally use internal-sandbox.
     drop(runner);
@@ -428,6 +432,9 @@ pub(super) fn execute_controlled_job(
 
     let dag_config = group.config.clone();
     let description = group.description.clone();
+    // the span is entered again in the sandbox manager thread
+    enter_span!("execution_group", group = %group.uuid);
+    let span = Span::current();
     let sol_execution = group.executions[1].clone();
 
     let controller_keeper = Arc::new(ControllerKeeper::new(
@@ -461,6 +468,7 @@ pub(super) fn execute_controlled_job(
         &controller_execution,
         &deps,
         Some(fifo_dir.path().to_owned()),
+        user,
     )?);
 
     current_job.lock().unwrap().current_sandboxes = Some(vec![controller_sandbox.clone()]);
@@ -527,8 +535,10 @@ pub(super) fn execute_controlled_job(
                     sr.resources.sys_time += result.resources.sys_time;
                     if controller_settings.concurrent {
                         sr.resources.memory += result.resources.memory;
+                        sr.resources.final_memory += result.resources.final_memory;
                     } else {
                         sr.resources.memory = result.resources.memory.max(sr.resources.memory);
+                        sr.resources.final_memory = result.resources.final_memory;
                     }
                     if !controller_settings.concurrent {
                         sr.resources.wall_time += result.resources.wall_time;
@@ -622,7 +632,7 @@ pub(super) fn execute_controlled_job(
                         let sol_execution = sol_execution.clone();
 
                         let sol_sandbox =
-                            ExecutionUnit::new(&sandbox_path, &sol_execution, &deps, None)?;
+                            ExecutionUnit::new(&sandbox_path, &sol_execution, &deps, None, user)?;
 
                         {
                             let mut job = current_job.lock().unwrap();
@@ -743,6 +753,7 @@ pub(super) fn execute_controlled_job(
     Ok(std::thread::Builder::new()
         .name(format!("Sandbox manager for {description}"))
         .spawn(move || {
+            let _span = span.entered();
             sandbox_manager()
                 .with_context(|| format!("Sandbox group for {description} failed"))
                 // FIXME: find a better way to propagate the error to the server
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::Permissions;
 use std::io::Read;
 use std::os::unix::fs::PermissionsExt;
@@ -10,16 +10,21 @@ use std::{cmp, thread};
 
 use anyhow::{anyhow, bail, Context, Error};
 use ductile::{new_local_channel, ChannelReceiver, ChannelSender};
+use task_maker_cache::Cache;
 use task_maker_dag::*;
 use task_maker_store::*;
 use tempfile::TempDir;
 use uuid::Uuid;
 
+use crate::execution_unit::sandbox::SandboxUser;
 use crate::execution_unit::{ExecutionUnit, SandboxResult};
 use crate::executor::WorkerJob;
 use crate::proto::*;
 use crate::sandbox_runner::SandboxRunner;
+use crate::spans::Span;
+use crate::worker::cache::WorkerCache;
 
+mod cache;
 pub mod controller;
 
 /// The information about the current job the worker is doing.
@@ -34,6 +39,8 @@ struct WorkerCurrentJob {
     server_asked_files: Option<Sender<Vec<FileUuid>>>,
     /// State of the controller if this is a controlled execution.
     controller_state: Option<controller::State>,
+    /// The jobs whose outputs could not be sent from the cache, they are run again without it.
+    failed_cached_jobs: HashSet<ExecutionGroupUuid>,
 }
 
 /// The worker is the component that receives the work from the server and sends the results back.
@@ -56,8 +63,12 @@ pub struct Worker {
     sandbox_path: PathBuf,
     /// The function that spawns an actual sandbox.
     sandbox_runner: Arc<dyn SandboxRunner>,
+    /// The user the processes are run as inside the sandboxes.
+    sandbox_user: SandboxUser,
     /// The join handle of the currently running sandbox, if any.
     current_sandbox_thread: Option<JoinHandle<()>>,
+    /// The cache of the executions run by this worker, if enabled.
+    cache: Option<WorkerCache>,
 }
 
 /// An handle of the connection to the worker.
@@ -94,6 +105,7 @@ impl WorkerCurrentJob {
             missing_deps: HashMap::new(),
             server_asked_files: None,
             controller_state: None,
+            failed_cached_jobs: HashSet::new(),
         }
     }
 }
@@ -107,6 +119,7 @@ impl Worker {
         file_store: Arc<FileStore>,
         sandbox_path: P,
         sandbox_runner: R,
+        sandbox_user: SandboxUser,
     ) -> Result<(Worker, WorkerConn), Error>
     where
         R: SandboxRunner + 'static,
@@ -125,6 +138,7 @@ impl Worker {
                 tx_worker,
                 rx_worker,
                 sandbox_runner,
+                sandbox_user,
             )?,
             WorkerConn {
                 uuid,
@@ -136,6 +150,8 @@ impl Worker {
     }
 
     /// Make a new worker with an already connected channel.
+    ///
+    /// The worker fails to start if the sandbox cannot be used with the provided user.
     pub fn new_with_channel<S: Into<String>, P: Into<PathBuf>>(
         name: S,
         file_store: Arc<FileStore>,
@@ -143,9 +159,10 @@ impl Worker {
         sender: ChannelSender<WorkerClientMessage>,
         receiver: ChannelReceiver<WorkerServerMessage>,
         sandbox_runner: Arc<dyn SandboxRunner>,
+        sandbox_user: SandboxUser,
     ) -> Result<Worker, Error> {
         let sandbox_path = sandbox_path.into();
-        check_sandbox_is_supported(&sandbox_path, sandbox_runner.clone())?;
+        check_sandbox_is_supported(&sandbox_path, sandbox_runner.clone(), sandbox_user)?;
         let uuid = Uuid::new_v4();
         let name = name.into();
         Ok(Worker {
@@ -157,21 +174,118 @@ impl Worker {
             current_job: Arc::new(Mutex::new(WorkerCurrentJob::new())),
             sandbox_path,
             sandbox_runner,
+            sandbox_user,
             current_sandbox_thread: None,
+            cache: None,
         })
     }
 
+    /// Keep a cache of the executions run by this worker, referencing the files in its
+    /// `FileStore`. The same cache can be shared between the workers of the same machine, and
+    /// between the connections of the same worker, so that the executions are not run again if the
+    /// server lost its cache.
+    pub fn set_cache(&mut self, cache: Arc<Mutex<Cache>>) {
+        self.cache = Some(WorkerCache::new(self.file_store.clone(), cache));
+    }
+
     /// Start the sandbox thread for the current job.
     fn start_job(&mut self) -> Result<(), Error> {
+        if let Some(join_handle) = self.start_cached_job()? {
+            self.current_sandbox_thread = Some(join_handle);
+            return Ok(());
+        }
         self.current_sandbox_thread = Some(execute_job(
             self.current_job.clone(),
             &self.sender,
             &self.sandbox_path,
             self.sandbox_runner.clone(),
+            self.sandbox_user,
+            self.cache.clone(),
         )?);
         Ok(())
     }
 
+    /// If the current job is in the cache of the worker, send its results to the server without
+    /// running it. Returns the handle of the thread that sends the outputs to the server.
+    fn start_cached_job(&self) -> Result<Option<JoinHandle<()>>, Error> {
+        let Some(cache) = &self.cache else {
+            return Ok(None);
+        };
+        let (results, outputs, server_asked_files) = {
+            let mut current_job = self.current_job.lock().unwrap();
+            let (job, inputs) = current_job
+                .current_job
+                .as_ref()
+                .ok_or_else(|| anyhow!("Worker job is gone"))?;
+            if current_job.failed_cached_jobs.contains(&job.group.uuid) {
+                return Ok(None);
+            }
+            let Some((results, outputs)) = cache.lookup(&job.group, &job.cache_mode, inputs) else {
+                return Ok(None);
+            };
+            if let Some(handle) = outputs.values().find(|handle| !handle.path().is_file()) {
+                warn!(
+                    "Output {} of execution {} is missing from the worker store, running it again",
+                    handle.key(),
+                    job.group.uuid
+                );
+                return Ok(None);
+            }
+            info!("Execution {} is in the worker cache", job.group.uuid);
+            let (sender, receiver) = channel();
+            current_job.server_asked_files = Some(sender);
+            (results, outputs, receiver)
+        };
+        let output_keys: HashMap<_, _> = outputs
+            .iter()
+            .map(|(uuid, handle)| (*uuid, handle.key().clone()))
+            .collect();
+        let output_paths = outputs
+            .iter()
+            .map(|(uuid, handle)| (*uuid, OutputFile::OnDisk(handle.path().to_owned())))
+            .collect();
+        self.sender
+            .send(WorkerClientMessage::WorkerDone(
+                results,
+                output_keys.clone(),
+            ))
+            .context("Failed to send WorkerDone")?;
+        let current_job = self.current_job.clone();
+        let sender = self.sender.clone();
+        let join_handle = std::thread::Builder::new()
+            .name("Cached job sender".into())
+            .spawn(move || {
+                let group_uuid = current_job
+                    .lock()
+                    .unwrap()
+                    .current_job
+                    .as_ref()
+                    .map(|(job, _)| job.group.uuid);
+                let res = finalize_job(
+                    current_job.clone(),
+                    server_asked_files,
+                    output_keys,
+                    output_paths,
+                    &sender,
+                    None,
+                )
+                .context("Failed to send the cached outputs");
+                if let Err(e) = res {
+                    if let Some(group_uuid) = group_uuid {
+                        current_job
+                            .lock()
+                            .unwrap()
+                            .failed_cached_jobs
+                            .insert(group_uuid);
+                    }
+                    report_job_failure(&current_job, &sender, e);
+                }
+                // the handles keep the outputs in the store until they are sent
+                drop(outputs);
+            })?;
+        Ok(Some(join_handle))
+    }
+
     /// Wait for the sandbox thread to exit.
     fn wait_sandbox(&mut self) -> Result<(), Error> {
         if let Some(join_handle) = self.current_sandbox_thread.take() {
@@ -186,6 +300,7 @@ impl Worker {
     /// The worker body, this function will block until the worker disconnects.
     #[allow(clippy::cognitive_complexity)]
     pub fn work(mut self) -> Result<(), Error> {
+        enter_span!("worker", name = %self.name, uuid = %self.uuid);
         trace!("Worker {self} ready, asking for work");
         self.sender
             .send(WorkerClientMessage::GetWork)
@@ -324,18 +439,29 @@ fn finalize_job(
         Ok(missing_files) => {
             for uuid in missing_files {
                 if let Some(key) = outputs.get(&uuid) {
+                    // open the file before announcing it, so that if it fails the server can still
+                    // be told with JobFailed
+                    let on_disk = match &output_paths[&uuid] {
+                        OutputFile::OnDisk(path) => {
+                            Some(ReadFileIterator::new(path).with_context(|| {
+                                format!("Failed to open output file at {}", path.display())
+                            })?)
+                        }
+                        OutputFile::InMemory(_) => None,
+                    };
                     sender
                         .send(WorkerClientMessage::ProvideFile(uuid, key.clone()))
                         .context("Failed to send ProvideFile")?;
-                    match &output_paths[&uuid] {
-                        OutputFile::OnDisk(path) => {
-                            ChannelFileSender::send(path, sender)
+                    match (on_disk, &output_paths[&uuid]) {
+                        (Some(iterator), _) => {
+                            ChannelFileSender::send_iterator(iterator, sender)
                                 .context("Failed to send missing file")?;
                         }
-                        OutputFile::InMemory(content) => {
+                        (None, OutputFile::InMemory(content)) => {
                             ChannelFileSender::send_data(content.clone(), sender)
                                 .context("Failed to sent in-memory file")?;
                         }
+                        (None, OutputFile::OnDisk(_)) => unreachable!("the file is opened above"),
                     }
                 } else {
                     error!("Server asked for file {uuid}, which is not known to the worker");
@@ -366,12 +492,36 @@ fn finalize_job(
     Ok(())
 }
 
+/// Tell the server that the current job failed after its results were sent, so that it runs the
+/// job again, and ask for more work.
+fn report_job_failure(
+    current_job: &Mutex<WorkerCurrentJob>,
+    sender: &ChannelSender<WorkerClientMessage>,
+    error: Error,
+) {
+    error!("The job failed: {error:?}");
+    {
+        let mut job = current_job.lock().unwrap();
+        job.current_job = None;
+        job.current_sandboxes = None;
+        job.controller_state = None;
+        job.server_asked_files = None;
+    }
+    if let Err(e) = sender.send(WorkerClientMessage::JobFailed(format!("{error:#}"))) {
+        warn!("Cannot tell the server that the job failed: {e:?}");
+        return;
+    }
+    let _ = sender.send(WorkerClientMessage::GetWork);
+}
+
 /// Spawn a new thread that will start the sandbox and will send the results back to the server.
 fn execute_job(
     current_job: Arc<Mutex<WorkerCurrentJob>>,
     sender: &ChannelSender<WorkerClientMessage>,
     sandbox_path: &Path,
     runner: Arc<dyn SandboxRunner>,
+    user: SandboxUser,
+    cache: Option<WorkerCache>,
 ) -> Result<JoinHandle<()>, Error> {
     let controller_settings = current_job
         .lock()
@@ -389,6 +539,7 @@ fn execute_job(
             sender,
             sandbox_path,
             runner,
+            user,
         );
     }
 
@@ -425,6 +576,7 @@ fn execute_job(
                 exec,
                 &job.1,
                 fifo_dir.as_ref().map(|d| d.path().to_owned()),
+                user,
             )?;
             if keep_sandboxes {
                 sandbox.keep();
@@ -439,9 +591,13 @@ fn execute_job(
     };
     let sender = sender.clone();
     let description = job.group.description.clone();
+    // the span is entered again in the sandbox group manager thread
+    enter_span!("execution_group", group = %job.group.uuid);
+    let span = Span::current();
     let join_handle = std::thread::Builder::new()
         .name(format!("Sandbox group manager for {description}"))
         .spawn(move || {
+            let _span = span.entered();
             sandbox_group_manager(
                 current_job,
                 *job,
@@ -450,6 +606,7 @@ fn execute_job(
                 sandboxes,
                 runner,
                 fifo_dir,
+                cache,
             )
             .with_context(|| format!("Sandbox group for {description} failed"))
             // FIXME: find a better way to propagate the error to the server
@@ -472,6 +629,7 @@ fn sandbox_group_manager(
     mut sandboxes: Vec<ExecutionUnit>,
     runner: Arc<dyn SandboxRunner>,
     fifo_dir: Option<TempDir>,
+    cache: Option<WorkerCache>,
 ) -> Result<(), Error> {
     assert_eq!(sandboxes.len(), job.group.executions.len());
     let mut results = vec![None; job.group.executions.len()];
@@ -490,13 +648,7 @@ fn sandbox_group_manager(
         };
         let exec = &job.group.executions[0];
         let mut result = compute_execution_result(exec, result, &sandbox);
-        get_result_outputs(
-            exec,
-            &sandbox,
-            &mut outputs,
-            &mut output_paths,
-            &mut result.status,
-        );
+        get_result_outputs(exec, &sandbox, &mut outputs, &mut output_paths, &mut result);
 
         results[0] = Some(result);
     // this is the complex case: more than an execution (therefore more than a sandbox)
@@ -541,13 +693,7 @@ fn sandbox_group_manager(
                         }
                     }
 
-                    get_result_outputs(
-                        exec,
-                        sandbox,
-                        &mut outputs,
-                        &mut output_paths,
-                        &mut result.status,
-                    );
+                    get_result_outputs(exec, sandbox, &mut outputs, &mut output_paths, &mut result);
 
                     results[index] = Some(result);
                     missing -= 1;
@@ -562,12 +708,29 @@ fn sandbox_group_manager(
                 .context("Sandbox thread failed")?;
         }
     }
+    let results: Vec<_> = results.into_iter().map(Option::unwrap).collect();
+    if let Some(cache) = cache {
+        let inputs = current_job
+            .lock()
+            .unwrap()
+            .current_job
+            .as_ref()
+            .map(|(_, inputs)| inputs.clone())
+            .unwrap_or_default();
+        if let Err(e) = cache.insert(
+            &job.group,
+            &job.cache_mode,
+            &inputs,
+            &results,
+            &outputs,
+            &output_paths,
+        ) {
+            warn!("Failed to store the execution in the worker cache: {e:?}");
+        }
+    }
     // tell the server the results and the list of produced files
     sender
-        .send(WorkerClientMessage::WorkerDone(
-            results.into_iter().map(Option::unwrap).collect(),
-            outputs.clone(),
-        ))
+        .send(WorkerClientMessage::WorkerDone(results, outputs.clone()))
         .context("Failed to send WorkerDone")?;
 
     finalize_job(
@@ -590,9 +753,12 @@ fn spawn_sandbox(
     group_sender: Sender<(usize, SandboxResult)>,
     dag_config: ExecutionDAGConfig,
 ) -> Result<JoinHandle<Result<(), Error>>, Error> {
+    let span = Span::current();
     Ok(thread::Builder::new()
         .name(format!("Sandbox of {description}"))
         .spawn(move || {
+            let _span = span.entered();
+            enter_span!("sandbox", index);
             let res = match sandbox.run(runner.as_ref(), &dag_config) {
                 Ok(res) => res,
                 Err(e) => SandboxResult::Failed {
@@ -642,6 +808,8 @@ fn compute_execution_result(
                 stdout: stdout.ok().unwrap_or_default(),
                 was_killed,
                 was_cached: false,
+                cache_hit: None,
+                missing_outputs: vec![],
                 stderr: stderr.ok().unwrap_or_default(),
             }
         }
@@ -658,6 +826,8 @@ fn compute_execution_result(
                 resources: ExecutionResourcesUsage::default(),
                 was_killed: false,
                 was_cached: false,
+                cache_hit: None,
+                missing_outputs: vec![],
                 stdout: None,
                 stderr: None,
             }
@@ -666,17 +836,34 @@ fn compute_execution_result(
 }
 
 /// Extract the output files from the result of the sandbox and store them in the provided HashMaps.
+/// The output files that the execution didn't create are listed in the result.
 fn get_result_outputs(
     exec: &Execution,
     sandbox: &ExecutionUnit,
     outputs: &mut HashMap<FileUuid, FileStoreKey>,
     output_paths: &mut HashMap<FileUuid, OutputFile>,
-    status: &mut ExecutionStatus,
+    result: &mut ExecutionResult,
 ) {
-    let mut add_file = |file: FileUuid, out: OutputFile| match &out {
+    let ExecutionResult {
+        status,
+        missing_outputs,
+        ..
+    } = result;
+    let mut add_file = |file: FileUuid, mut out: OutputFile, normalize: bool| match &mut out {
         OutputFile::OnDisk(path) => {
             if path.exists() {
-                let key = FileStoreKey::from_file(path);
+                if normalize {
+                    if let Err(e) = normalize_output_file(path) {
+                        *status = ExecutionStatus::internal_error(format!(
+                            "Failed to normalize {} at {}: {:?}",
+                            file,
+                            path.display(),
+                            e
+                        ));
+                        return;
+                    }
+                }
+                let key = FileStoreKey::from_file(&*path);
                 match key {
                     Ok(key) => {
                         outputs.insert(file, key);
@@ -697,6 +884,11 @@ fn get_result_outputs(
             }
         }
         OutputFile::InMemory(content) => {
+            if normalize {
+                if let Some(normalized) = normalize_text(content) {
+                    *content = normalized;
+                }
+            }
             let key = FileStoreKey::from_content(content);
             outputs.insert(file, key);
             output_paths.insert(file, out);
@@ -704,16 +896,48 @@ fn get_result_outputs(
     };
 
     if let ExecutionOutputBehaviour::Capture { file: stdout, .. } = &exec.stdout {
-        add_file(stdout.uuid, sandbox.stdout_path());
+        add_file(stdout.uuid, sandbox.stdout_path(), exec.normalize_outputs);
     }
     if let ExecutionOutputBehaviour::Capture { file: stderr, .. } = &exec.stderr {
-        add_file(stderr.uuid, sandbox.stderr_path());
+        add_file(stderr.uuid, sandbox.stderr_path(), false);
     }
     for (path, file) in exec.output_files.iter() {
-        add_file(file.uuid, sandbox.output_path(path));
+        let output = sandbox.output_path(path);
+        if matches!(&output, OutputFile::OnDisk(host_path) if !host_path.exists()) {
+            missing_outputs.push(path.clone());
+        }
+        add_file(file.uuid, output, exec.normalize_outputs);
     }
 }
 
+/// Normalize the content of a text file: remove the UTF-8 BOM and convert the CRLF line endings to
+/// LF. Returns `None` if the content doesn't change, or if it looks binary (i.e. it contains a NUL
+/// byte) and it's left untouched.
+fn normalize_text(content: &[u8]) -> Option<Vec<u8>> {
+    if content.contains(&0) {
+        return None;
+    }
+    let stripped = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    let mut normalized = Vec::with_capacity(stripped.len());
+    let mut bytes = stripped.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'\r' && bytes.peek() == Some(&&b'\n') {
+            continue;
+        }
+        normalized.push(byte);
+    }
+    (normalized.len() != content.len()).then_some(normalized)
+}
+
+/// Normalize in place an output file of an execution, see [`normalize_text`].
+fn normalize_output_file(path: &Path) -> Result<(), Error> {
+    let content = std::fs::read(path).context("Failed to read the file")?;
+    if let Some(normalized) = normalize_text(&content) {
+        std::fs::write(path, normalized).context("Failed to write the normalized file")?;
+    }
+    Ok(())
+}
+
 fn capture_stream(
     file: &OutputFile,
     behaviour: &ExecutionOutputBehaviour,
@@ -762,12 +986,15 @@ impl std::fmt::Display for Worker {
 fn check_sandbox_is_supported(
     sandbox_path: &Path,
     runner: Arc<dyn SandboxRunner>,
+    user: SandboxUser,
 ) -> Result<(), Error> {
+    user.validate()?;
     let execution = Execution::new(
         "Execution to check if sandbox is supported",
         ExecutionCommand::system("true"),
     );
-    let mut sandbox = ExecutionUnit::new(sandbox_path, &execution, &Default::default(), None)?;
+    let mut sandbox =
+        ExecutionUnit::new(sandbox_path, &execution, &Default::default(), None, user)?;
     let result = sandbox.run(runner.as_ref(), &ExecutionDAGConfig::new())?;
     match result {
         SandboxResult::Failed { error } => bail!("Sandbox failed: {}", error),
@@ -786,3 +1013,20 @@ fn check_sandbox_is_supported(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(
+            normalize_text(b"\xEF\xBB\xBF1 2\r\n3\r\n").as_deref(),
+            Some(&b"1 2\n3\n"[..])
+        );
+        assert_eq!(normalize_text(b"lone\rcr\n").as_deref(), None);
+        assert_eq!(normalize_text(b"already\nnormalized\n"), None);
+        // binary files are left untouched
+        assert_eq!(normalize_text(b"\xEF\xBB\xBF\0\r\n"), None);
+    }
+}
@@ -135,6 +135,23 @@ impl WorkerManager {
         Ok(())
     }
 
+    /// The worker failed its job after sending the results: the results are discarded and the
+    /// scheduler is told that the worker is no longer doing the job, so that it's run again. The
+    /// worker will ask for more work with `GetWork`. Returns `false` if the scheduler is gone.
+    fn handle_job_failed(
+        worker: &WorkerConn,
+        scheduler: &Sender<SchedulerInMessage>,
+        error: String,
+    ) -> bool {
+        error!(
+            "Worker {} ({}) failed its job: {}",
+            worker.name, worker.uuid, error
+        );
+        scheduler
+            .send(SchedulerInMessage::WorkerDisconnected { uuid: worker.uuid })
+            .is_ok()
+    }
+
     /// Thread body that manages the actual connection with a worker. `worker_manager` will send
     /// messages back to the `WorkerManager` main thread for the notification about the
     /// disconnection of this worker.
@@ -174,6 +191,11 @@ impl WorkerManager {
                     // received
                     unreachable!("Unexpected ProvideFile from worker");
                 }
+                WorkerClientMessage::JobFailed(error) => {
+                    if !Self::handle_job_failed(&worker, &scheduler, error) {
+                        break;
+                    }
+                }
                 WorkerClientMessage::WorkerDone(result, outputs) => {
                     // the worker completed its job and will send the produced files
                     let mut output_handlers = HashMap::new();
@@ -194,20 +216,32 @@ impl WorkerManager {
                         .sender
                         .send(WorkerServerMessage::AskFiles(missing_files))
                         .context("Failed to send AskFiles to worker")?;
+                    let mut job_failed = None;
                     for _ in 0..num_missing {
                         let message = worker
                             .receiver
                             .recv()
                             .context("Failed to receive file from worker")?;
-                        if let WorkerClientMessage::ProvideFile(uuid, key) = message {
-                            let handle = file_store
-                                .store(&key, ChannelFileIterator::new(&worker.receiver))
-                                .context("Failed to store worker-provided file")?;
-                            output_handlers.insert(uuid, handle);
-                        } else {
-                            bail!("Unexpected message from worker: {:?}", message);
+                        match message {
+                            WorkerClientMessage::ProvideFile(uuid, key) => {
+                                let handle = file_store
+                                    .store(&key, ChannelFileIterator::new(&worker.receiver))
+                                    .context("Failed to store worker-provided file")?;
+                                output_handlers.insert(uuid, handle);
+                            }
+                            WorkerClientMessage::JobFailed(error) => {
+                                job_failed = Some(error);
+                                break;
+                            }
+                            _ => bail!("Unexpected message from worker: {:?}", message),
                         }
                     }
+                    if let Some(error) = job_failed {
+                        if !Self::handle_job_failed(&worker, &scheduler, error) {
+                            break;
+                        }
+                        continue;
+                    }
                     let mex = SchedulerInMessage::WorkerResult {
                         worker: worker.uuid,
                         result,
@@ -0,0 +1,109 @@
+//! Support for task formats defined outside of this crate.
+//!
+//! A library user can add a task format by implementing [`CustomTaskParser`] and registering it
+//! with [`register_task_format`]. The registered parsers are consulted by
+//! [`find_task`](crate::find_task), in registration order, before the built-in IOI and Terry
+//! formats. The parsed task is wrapped in [`TaskFormat::Custom`](crate::TaskFormat::Custom) and
+//! must implement [`CustomTask`], the minimal surface needed for building the DAG and showing the
+//! UI.
+
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use anyhow::Error;
+use task_maker_dag::ExecutionDAGConfig;
+
+use crate::ui::{UIType, UI};
+use crate::{EvaluationConfig, EvaluationData, TaskInfo};
+
+lazy_static! {
+    /// The parsers of the custom task formats, in registration order.
+    static ref CUSTOM_PARSERS: RwLock<Vec<Arc<dyn CustomTaskParser>>> = Default::default();
+}
+
+/// A parser of a task format defined outside of this crate.
+pub trait CustomTaskParser: Send + Sync {
+    /// The name of the format, used in the error messages.
+    fn name(&self) -> &str;
+
+    /// Whether the directory looks like a task of this format. This should be a cheap check, for
+    /// example looking for the presence of a file: the actual parsing is done by `parse`.
+    fn is_valid(&self, path: &Path) -> bool;
+
+    /// Parse the task at the given directory.
+    fn parse(
+        &self,
+        path: &Path,
+        eval_config: &EvaluationConfig,
+    ) -> Result<Box<dyn CustomTask>, Error>;
+}
+
+/// A task of a format defined outside of this crate.
+pub trait CustomTask: Debug + Send + Sync {
+    /// The name of the task.
+    fn name(&self) -> &str;
+
+    /// Get the root directory of the task.
+    fn path(&self) -> &Path;
+
+    /// Get an appropriate `UI` for this task.
+    fn ui(&self, ui_type: &UIType, config: ExecutionDAGConfig) -> Result<Box<dyn UI>, Error>;
+
+    /// Add the executions required for evaluating this task to the execution DAG.
+    fn build_dag(
+        &mut self,
+        eval: &mut EvaluationData,
+        config: &EvaluationConfig,
+    ) -> Result<(), Error>;
+
+    /// Get the task information.
+    fn task_info(&self) -> Result<TaskInfo, Error>;
+
+    /// Hook called after the execution completed. Does nothing by default.
+    fn sanity_check_post_hook(&self, _eval: &mut EvaluationData) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Run the sanity checks that do not require the evaluation of the task. Returns the names of
+    /// the sanity checks that have been skipped. Does nothing by default.
+    fn static_sanity_checks(
+        &self,
+        _eval: &mut EvaluationData,
+        _config: &EvaluationConfig,
+    ) -> Result<Vec<&'static str>, Error> {
+        Ok(vec![])
+    }
+
+    /// Clean the task folder removing the files that can be generated automatically. Does nothing
+    /// by default.
+    fn clean(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Make a boxed copy of this task.
+    fn clone_box(&self) -> Box<dyn CustomTask>;
+}
+
+impl Clone for Box<dyn CustomTask> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Register the parser of a custom task format. The parsers are consulted by
+/// [`find_task`](crate::find_task) in registration order, before the built-in formats.
+pub fn register_task_format<P: CustomTaskParser + 'static>(parser: P) {
+    CUSTOM_PARSERS
+        .write()
+        .expect("Custom task formats lock is poisoned")
+        .push(Arc::new(parser));
+}
+
+/// The parsers of the registered custom task formats, in registration order.
+pub(crate) fn custom_task_parsers() -> Vec<Arc<dyn CustomTaskParser>> {
+    CUSTOM_PARSERS
+        .read()
+        .expect("Custom task formats lock is poisoned")
+        .clone()
+}
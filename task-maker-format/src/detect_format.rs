@@ -1,40 +1,70 @@
 use std::fmt::Write;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
+use nix::errno::Errno;
 
+use crate::custom_format::custom_task_parsers;
 use crate::{ioi, terry, EvaluationConfig, TaskFormat};
 
 /// Search for a valid task directory, starting from base and going _at most_ `max_depth` times up.
+///
+/// In each directory the [custom task formats](crate::register_task_format) are tried first, in
+/// registration order, and then the built-in formats.
 pub fn find_task(
     base: Option<PathBuf>,
     max_depth: u32,
     eval_config: &EvaluationConfig,
+) -> Result<TaskFormat> {
+    find_task_with_retries(base, max_depth, eval_config, 0, Duration::ZERO)
+}
+
+/// Like [`find_task`], but the parsing of a task is attempted again, at most `retries` times and
+/// waiting `delay` before each attempt, if it fails because of a transient I/O error (for example
+/// a file vanishing while a networked filesystem is syncing). The other errors, like a malformed
+/// task, are not retried.
+pub fn find_task_with_retries(
+    base: Option<PathBuf>,
+    max_depth: u32,
+    eval_config: &EvaluationConfig,
+    retries: u32,
+    delay: Duration,
 ) -> Result<TaskFormat> {
     let mut base = base.unwrap_or_else(getcwd);
     if !base.is_absolute() {
         base = getcwd().join(base);
     }
+    let custom_parsers = custom_task_parsers();
     let mut fails = vec![];
     for _ in 0..max_depth {
+        // try to parse a custom task, the first one that succeeds wins
+        for parser in &custom_parsers {
+            if parser.is_valid(&base) {
+                match with_retries(retries, delay, || parser.parse(&base, eval_config)) {
+                    Ok(custom_task) => return Ok(TaskFormat::Custom(custom_task)),
+                    Err(err) => fails.push((parser.name().to_string(), base.clone(), err)),
+                }
+            }
+        }
         let mut task = None;
         // try to parse a IOI task
         if ioi::IOITask::is_valid(&base) {
-            match ioi::IOITask::new(&base, eval_config) {
+            match with_retries(retries, delay, || ioi::IOITask::new(&base, eval_config)) {
                 Ok(ioi_task) => task = Some(ioi_task.into()),
-                Err(err) => fails.push(("IOI", base.clone(), err)),
+                Err(err) => fails.push(("IOI".into(), base.clone(), err)),
             }
         }
         // try to parse a Terry task
         if terry::TerryTask::is_valid(&base) {
-            match terry::TerryTask::new(&base, eval_config) {
+            match with_retries(retries, delay, || terry::TerryTask::new(&base, eval_config)) {
                 Ok(terry_task) => {
                     if task.is_some() {
                         bail!("Ambiguous task directory, can be either IOI and terry")
                     }
                     task = Some(terry_task.into())
                 }
-                Err(err) => fails.push(("Terry", base.clone(), err)),
+                Err(err) => fails.push(("Terry".into(), base.clone(), err)),
             }
         }
         // if a task is found, return it
@@ -64,6 +94,47 @@ pub fn find_task(
     Err(anyhow!("{}", message)).context("Cannot find a valid task directory")
 }
 
+/// Call `parse` until it succeeds, fails with a non-transient error, or fails `retries + 1` times.
+fn with_retries<T, F>(retries: u32, delay: Duration, mut parse: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut attempt = 0;
+    loop {
+        match parse() {
+            Err(err) if attempt < retries && is_transient_error(&err) => {
+                attempt += 1;
+                warn!(
+                    "Transient error while parsing the task (attempt {attempt}/{retries}): {err:?}"
+                );
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether the error is caused by an I/O error that may go away by trying again.
+///
+/// Note that a missing file is transient only if it vanished while reading it: the required files
+/// of a task are checked for existence before reading them, and those errors are not I/O errors.
+fn is_transient_error(err: &Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|err| match err.raw_os_error() {
+            Some(errno) => matches!(
+                Errno::from_raw(errno),
+                Errno::ENOENT
+                    | Errno::EIO
+                    | Errno::ESTALE
+                    | Errno::EAGAIN
+                    | Errno::EINTR
+                    | Errno::ETIMEDOUT
+            ),
+            None => false,
+        })
+}
+
 /// Return the current working directory.
 ///
 /// `std::env::current_dir()` resolves the symlinks of the cwd's hierarchy, `$PWD` is used instead.
@@ -72,3 +143,56 @@ fn getcwd() -> PathBuf {
         .map(PathBuf::from)
         .unwrap_or_else(|_| std::env::current_dir().expect("Cannot get current working directory"))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_is_transient_error() {
+        let io = |errno| Error::from(std::io::Error::from_raw_os_error(errno as i32));
+        assert!(is_transient_error(&io(Errno::EIO)));
+        assert!(is_transient_error(
+            &io(Errno::ENOENT).context("Failed to read task.yaml")
+        ));
+        assert!(!is_transient_error(&io(Errno::EACCES)));
+        assert!(!is_transient_error(&Error::from(std::io::Error::from(
+            ErrorKind::InvalidData
+        ))));
+        assert!(!is_transient_error(&anyhow!("Missing task.yaml")));
+    }
+
+    #[test]
+    fn test_with_retries() {
+        let mut attempts = 0;
+        let result = with_retries(2, Duration::ZERO, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(Error::from(std::io::Error::from_raw_os_error(
+                    Errno::EIO as i32,
+                )))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: Result<()> = with_retries(2, Duration::ZERO, || {
+            attempts += 1;
+            bail!("Malformed task.yaml")
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result: Result<()> = with_retries(2, Duration::ZERO, || {
+            attempts += 1;
+            Err(std::io::Error::from_raw_os_error(Errno::EIO as i32).into())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+}
@@ -17,9 +17,12 @@ use crate::ui::curses::{
     compilation_status_text, draw_compilations, inner_block, render_block, render_server_status,
     CursesDrawer, CursesUI as GenericCursesUI, GREEN, ORANGE, RED, YELLOW,
 };
-use crate::ui::UIExecutionStatus;
+use crate::ui::{CompilationStatus, UIExecutionStatus};
 use crate::ScoreStatus;
 
+/// The number of characters of the progress bar of the solutions.
+const PROGRESS_BAR_WIDTH: usize = 8;
+
 /// An animated UI for IOI tasks, dynamically refreshing using curses as a backend.
 pub(crate) type CursesUI = GenericCursesUI<UIState, Drawer, FinishUI>;
 
@@ -260,6 +263,7 @@ fn draw_evaluations(frame: &mut Frame, rect: Rect, state: &UIState, loading: cha
             }
             spans.push(Span::raw(" "));
             spans.push(evaluation_score(state, solution, loading));
+            spans.push(evaluation_progress(state, solution));
             spans.append(&mut evaluation_line(state, solution, loading));
             spans.into()
         })
@@ -276,7 +280,9 @@ fn evaluation_score<'a>(state: &'a UIState, solution: &Path, loading: char) -> S
         return Span::raw("  ?  ");
     };
     if let Some(score) = sol_state.score {
-        match ScoreStatus::from_score(score, state.max_score) {
+        let status = ScoreStatus::from_score(score, state.max_score);
+        let score = state.task.score_rounding.round(score, 0);
+        match status {
             ScoreStatus::WrongAnswer => Span::styled(format!(" {score:>3.0} "), *RED),
             ScoreStatus::Accepted => Span::styled(format!(" {score:>3.0} "), *GREEN),
             ScoreStatus::PartialScore => Span::styled(format!(" {score:>3.0} "), *YELLOW),
@@ -294,6 +300,54 @@ fn evaluation_score<'a>(state: &'a UIState, solution: &Path, loading: char) -> S
     }
 }
 
+/// Get the progress bar of a solution, like `[####----] 12/20`, counting the testcases whose
+/// evaluation has completed. Solutions that are not evaluated because their compilation failed
+/// are marked as skipped.
+fn evaluation_progress<'a>(state: &'a UIState, solution: &Path) -> Span<'a> {
+    let Some(sol_state) = state.evaluations.get(solution) else {
+        return Span::raw("");
+    };
+    let total = sol_state.testcases.len();
+    let done = sol_state
+        .testcases
+        .values()
+        .filter(|tc| tc.status.has_completed())
+        .count();
+    let count_len = 2 * total.to_string().len() + 1;
+    let compilation_failed = matches!(
+        state.compilations.get(solution),
+        Some(CompilationStatus::Failed { .. } | CompilationStatus::Skipped)
+    );
+    let all_skipped = total > 0
+        && sol_state
+            .testcases
+            .values()
+            .all(|tc| tc.status == TestcaseEvaluationStatus::Skipped);
+    if compilation_failed || all_skipped {
+        return Span::styled(
+            format!("[{:^PROGRESS_BAR_WIDTH$}] {:count_len$} ", "skipped", ""),
+            *RED,
+        );
+    }
+    let filled = if total == 0 {
+        0
+    } else {
+        done * PROGRESS_BAR_WIDTH / total
+    };
+    let bar = format!(
+        "{}{}",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled)
+    );
+    let count = format!("{done}/{total}");
+    let style = if total > 0 && done == total {
+        *GREEN
+    } else {
+        Style::default()
+    };
+    Span::styled(format!("[{bar}] {count:>count_len$} "), style)
+}
+
 /// Get the line at the right of the score of a solution.
 fn evaluation_line<'a>(state: &'a UIState, solution: &Path, loading: char) -> Vec<Span<'a>> {
     state
@@ -1,14 +1,14 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use serde::{Deserialize, Serialize};
-use task_maker_dag::{Execution, ExecutionCommand, ExecutionStatus, FileUuid, Priority};
+use task_maker_dag::{Execution, ExecutionCommand, ExecutionStatus, File, FileUuid, Priority};
 use task_maker_diagnostics::Diagnostic;
 
 use crate::ioi::{SubtaskId, TestcaseId, EVALUATION_PRIORITY, STDERR_CONTENT_LENGTH};
-use crate::ui::UIMessage;
-use crate::{bind_exec_callbacks, EvaluationData, SourceFile, Tag, UISender};
+use crate::ui::{decode_exact, decode_output, is_binary, UIMessage};
+use crate::{bind_exec_callbacks, EvaluationData, SourceFile, Tag, UISender, DATA_DIR};
 
 /// Which tool to use to compute the score on a testcase given the input file, the _correct_ output
 /// file and the output file to evaluate.
@@ -17,25 +17,68 @@ pub enum Checker {
     /// Use a built-in white diff checker that scores 1.0 if the two output files are identical
     /// except for white spaces. It internally uses `diff --ignore-all-spaces`
     WhiteDiff,
+    /// Use a built-in checker that compares the two output files token by token, where the tokens
+    /// are separated by white spaces. The tokens that are both decimal numbers are compared with
+    /// the tolerance, all the others must be identical. It requires `python3`.
+    TokenDiff {
+        /// The maximum difference between two numbers for considering them equal.
+        float_tolerance: f64,
+        /// Whether the tolerance is absolute, relative to the correct number, or either of them.
+        tolerance_mode: ToleranceMode,
+    },
     /// Use a custom checker based on an executable that can output a score (from 0.0 to 1.0) to
     /// stdout as well as a custom message on stderr.
     ///
     /// The arguments are the paths of (input, correct_output, test_output). The checker should
-    /// output to stdout the score and to stderr a message for the user.
+    /// output to stdout the score and to stderr a message for the user. When checking a testcase of
+    /// a subtask, the `TM_SUBTASK` and `TM_SUBTASK_NAME` environment variables are set. When
+    /// checking an extra output file of the task, `TM_OUTPUT_FILE` is set to its name.
     Custom(Arc<SourceFile>),
 }
 
+/// How the `TokenDiff` checker compares two numbers with its tolerance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToleranceMode {
+    /// The numbers are equal if `|test - correct| <= tolerance`.
+    Absolute,
+    /// The numbers are equal if `|test - correct| <= tolerance * |correct|`.
+    Relative,
+    /// The numbers are equal if they are equal with either the absolute or the relative tolerance.
+    #[default]
+    Either,
+}
+
+impl ToleranceMode {
+    /// The name of the mode, as passed to the token diff checker.
+    fn as_str(&self) -> &'static str {
+        match self {
+            ToleranceMode::Absolute => "absolute",
+            ToleranceMode::Relative => "relative",
+            ToleranceMode::Either => "either",
+        }
+    }
+}
+
 impl Checker {
     /// Build the execution of the checker for the specified files, the callback will be called when
     /// the result is ready. The execution does not send UI messages yet and it's not added to the
     /// DAG.
+    ///
+    /// `subtask` is the id and the name of the subtask of the testcase, if any. `output_file` is the
+    /// name of the extra output file being checked, if it's not the main output file.
+    /// `generator_outputs` are the other files written by the generator of the input file, they are
+    /// placed in the sandbox of a custom checker with their names.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn check<F>(
         &self,
         eval: &mut EvaluationData,
         testcase_id: Option<TestcaseId>,
+        subtask: Option<(SubtaskId, Option<&str>)>,
+        output_file: Option<&Path>,
         description: String,
         input: FileUuid,
+        generator_outputs: &[(PathBuf, FileUuid)],
         correct_output: FileUuid,
         test_output: FileUuid,
         callback: F,
@@ -77,6 +120,51 @@ impl Checker {
                 });
                 Ok(group)
             }
+            Checker::TokenDiff {
+                float_tolerance,
+                tolerance_mode,
+            } => {
+                let mut exec = Execution::new(description, ExecutionCommand::system("python3"));
+                exec.args(vec![
+                    "token_diff.py".to_string(),
+                    "correct".to_string(),
+                    "test".to_string(),
+                    float_tolerance.to_string(),
+                    tolerance_mode.as_str().to_string(),
+                ])
+                .input(correct_output, "correct", false)
+                .input(test_output, "test", false);
+                let script = File::new("Token diff checker");
+                exec.input(&script, "token_diff.py", false);
+                eval.dag
+                    .provide_file(script, DATA_DIR.join("checkers/token_diff.py"))
+                    .context("Failed to provide the token diff checker")?;
+                exec.capture_stdout(Some(128));
+                let mut group = exec.into_group();
+                group.tag = Some(Tag::Checking.into());
+                group.priority = EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority;
+
+                eval.dag.on_execution_done(&group.uuid, move |results| {
+                    let result = &results[0];
+                    match result.status {
+                        ExecutionStatus::Success => callback(1.0, "Output is correct".into())
+                            .context("Checker callback failed")?,
+                        // the checker exits with 1 if the files are different, telling why
+                        ExecutionStatus::ReturnCode(1) => {
+                            let message = result
+                                .stdout
+                                .as_deref()
+                                .map(|stdout| decode_output(stdout).trim().to_string())
+                                .filter(|message| !message.is_empty())
+                                .unwrap_or_else(|| "Output is incorrect".into());
+                            callback(0.0, message).context("Checker callback failed")?
+                        }
+                        _ => bail!("The token diff checker failed: {:?}", result.status),
+                    };
+                    Ok(())
+                });
+                Ok(group)
+            }
             Checker::Custom(source_file) => {
                 let mut exec = source_file
                     .execute(
@@ -88,9 +176,24 @@ impl Checker {
                 exec.input(input, "input", false)
                     .input(correct_output, "correct_output", false)
                     .input(test_output, "test_output", false);
+                for (name, file) in generator_outputs {
+                    exec.input(*file, name, false);
+                }
+                // The environment is part of the cache key, so the checker is not cached across
+                // different subtasks.
+                if let Some((subtask_id, subtask_name)) = subtask {
+                    exec.env("TM_SUBTASK", subtask_id.to_string());
+                    if let Some(name) = subtask_name {
+                        exec.env("TM_SUBTASK_NAME", name);
+                    }
+                }
+                if let Some(output_file) = output_file {
+                    exec.env("TM_OUTPUT_FILE", output_file.to_string_lossy());
+                }
                 exec.capture_stdout(Some(128));
                 exec.capture_stderr(Some(STDERR_CONTENT_LENGTH));
                 exec.limits_mut().allow_multiprocess();
+                let command_line = exec.command_line();
                 let mut group = exec.into_group();
                 group.tag = Some(Tag::Checking.into());
                 group.priority = EVALUATION_PRIORITY - testcase_id.unwrap_or_default() as Priority;
@@ -114,13 +217,13 @@ impl Checker {
                             "Checker failed while computing a score for a testcase".into()
                         };
                         let diagnostic = Diagnostic::error(message)
-                            .with_note(description)
+                            .with_note(format!("{description}\nCommand line: {command_line}"))
                             .with_help(format!("The checker crashed with: {:?}", res.status))
                             .with_help_attachment(stderr.clone());
                         sender.add_diagnostic(diagnostic)?;
                         return Ok(());
                     }
-                    let message = str::from_utf8(stderr)
+                    let message = decode_exact(stderr)
                         .map(|s| Self::translate_checker_message(s.trim().to_string()));
                     let score = str::from_utf8(stdout)
                         .map(|s| s.trim().parse::<f64>());
@@ -133,11 +236,16 @@ impl Checker {
                         sender.add_diagnostic(diagnostic)?;
                         return Ok(());
                     };
-                    let Ok(message) = message else {
+                    let Some(message) = message else {
                         let message = "The checked return a non UTF-8 message".to_string();
-                        let diagnostic = Diagnostic::error(message)
-                            .with_note(description)
-                            .with_help_attachment(stdout.clone());
+                        let mut diagnostic = Diagnostic::error(message).with_note(description);
+                        diagnostic = if is_binary(stderr) {
+                            diagnostic.with_help("The message looks binary, so it's omitted")
+                        } else {
+                            diagnostic
+                                .with_help("Use --output-encoding if the checker writes in another encoding")
+                                .with_help_attachment(stderr.clone())
+                        };
                         sender.add_diagnostic(diagnostic)?;
                         return Ok(());
                     };
@@ -184,9 +292,12 @@ impl Checker {
         &self,
         eval: &mut EvaluationData,
         subtask_id: SubtaskId,
+        subtask_name: Option<&str>,
+        output_file: Option<&Path>,
         testcase_id: TestcaseId,
         solution: S,
         input: FileUuid,
+        generator_outputs: &[(PathBuf, FileUuid)],
         correct_output: FileUuid,
         test_output: FileUuid,
         callback: F,
@@ -198,13 +309,19 @@ impl Checker {
         let exec = self.check(
             eval,
             Some(testcase_id),
+            Some((subtask_id, subtask_name)),
+            output_file,
             format!(
-                "Checking output of {:?} of testcase {}, subtask {}",
+                "Checking {} of {:?} of testcase {}, subtask {}",
+                output_file
+                    .map(|file| format!("output file {}", file.display()))
+                    .unwrap_or_else(|| "output".into()),
                 solution.file_name().unwrap(),
                 testcase_id,
                 subtask_id
             ),
             input,
+            generator_outputs,
             correct_output,
             test_output,
             callback,
@@ -0,0 +1,259 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Error};
+use task_maker_dag::{Execution, ExecutionGroup, ExecutionStatus, Priority};
+use task_maker_diagnostics::Diagnostic;
+
+use crate::ioi::dag::task_type::SOLUTION_NOFILE_LIMIT;
+use crate::ioi::{
+    BatchTypeData, IOITask, InputGenerator, OutputGenerator, SubtaskInfo, TaskType, TestcaseInfo,
+    STDERR_CONTENT_LENGTH,
+};
+use crate::solution::{Solution, SolutionDiagnosticCase};
+use crate::ui::UIMessage;
+use crate::{bind_exec_io, EvaluationData, SourceFile, Tag, UISender};
+
+/// The priority of the executions of the diagnostic cases, they are run after all the executions
+/// of the official testcases.
+const DIAGNOSTIC_CASE_PRIORITY: Priority = 0;
+
+/// Add to the DAG the evaluation of the diagnostic cases of the solutions. Their outcomes are sent
+/// to the UI with `UIMessage::IOIDiagnosticCase` and do not affect the score of the solutions.
+///
+/// Only the Batch tasks support the diagnostic cases.
+pub(crate) fn evaluate_diagnostic_cases(
+    task: &IOITask,
+    eval: &mut EvaluationData,
+    solutions: &[Solution],
+) -> Result<(), Error> {
+    if solutions.iter().all(|sol| sol.diagnostic_cases.is_empty()) {
+        return Ok(());
+    }
+    let TaskType::Batch(data) = &task.task_type else {
+        eval.add_diagnostic(Diagnostic::warning(
+            "The diagnostic cases are supported only by Batch tasks, they will be ignored",
+        ))?;
+        return Ok(());
+    };
+    for solution in solutions {
+        for (index, case) in solution.diagnostic_cases.iter().enumerate() {
+            evaluate_diagnostic_case(task, eval, data, &solution.source_file, index, case)
+                .with_context(|| {
+                    format!(
+                        "Failed to bind diagnostic case {index} of {}",
+                        solution.source_file.name()
+                    )
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Add to the DAG the generation, the validation and the evaluation of a diagnostic case of a
+/// solution.
+///
+/// The input file is generated with the generator of the first generated testcase of the subtask,
+/// and the official output with its output generator. Only the main output file is checked.
+fn evaluate_diagnostic_case(
+    task: &IOITask,
+    eval: &mut EvaluationData,
+    data: &BatchTypeData,
+    source_file: &SourceFile,
+    index: usize,
+    case: &SolutionDiagnosticCase,
+) -> Result<(), Error> {
+    let name = source_file.name();
+    let description = format!("diagnostic case {index} of {name}");
+    let Some(subtask) = task
+        .subtasks
+        .values()
+        .find(|subtask| subtask.name.as_deref() == Some(case.subtask_name.as_str()))
+    else {
+        eval.add_diagnostic(
+            Diagnostic::error(format!(
+                "The {description} refers to the unknown subtask {}",
+                case.subtask_name
+            ))
+            .with_code_span(case.code_span.clone()),
+        )?;
+        return Ok(());
+    };
+    let Some((template, generator)) = find_template(task, subtask) else {
+        eval.add_diagnostic(
+            Diagnostic::warning(format!(
+                "The {description} is ignored since subtask {} has no generated testcases",
+                case.subtask_name
+            ))
+            .with_code_span(case.code_span.clone()),
+        )?;
+        return Ok(());
+    };
+
+    let generator = InputGenerator::Custom(generator, case.args.clone());
+    let (input, generator_outputs, gen) = generator.generate(
+        eval,
+        &task.path,
+        &task.generator_outputs,
+        format!("Generation of {description}"),
+        subtask.id,
+        template.id,
+    )?;
+    if let Some(gen) = gen {
+        let message = format!("Failed to generate the input of the {description}");
+        add_with_diagnostic(eval, gen, Tag::Generation, message);
+    }
+
+    let (val_handle, val) = template
+        .input_validator
+        .as_ref()
+        .unwrap_or(&subtask.input_validator)
+        .validate(
+            eval,
+            &task.path,
+            format!("Validation of {description}"),
+            subtask.id,
+            subtask.name.as_deref(),
+            template.id,
+            input,
+            &generator_outputs,
+        )?;
+    if let Some(val) = val {
+        let message = format!(
+            "The input of the {description} is not valid for subtask {}",
+            case.subtask_name
+        );
+        add_with_diagnostic(eval, val, Tag::Generation, message);
+    }
+
+    let (correct_output, _, sol) = template.output_generator.generate(
+        task,
+        eval,
+        format!("Generation of the output of {description}"),
+        subtask.id,
+        template.id,
+        input,
+        val_handle,
+    )?;
+    let correct_output = correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
+    if let Some(sol) = sol {
+        let message = format!("Failed to generate the output of the {description}");
+        add_with_diagnostic(eval, sol, Tag::Generation, message);
+    }
+
+    let mut exec = source_file
+        .execute(
+            eval,
+            format!("Evaluation of {description}"),
+            Vec::<String>::new(),
+        )
+        .context("Failed to execute solution source file")?;
+    let output = bind_exec_io!(exec, task, input, val_handle);
+    let limits = exec.limits_mut();
+    limits.nofile(SOLUTION_NOFILE_LIMIT);
+    if let Some(time_limit) = task.time_limit {
+        limits.cpu_time(time_limit);
+        limits.wall_time(time_limit * 1.5 + 1.0); // some margin
+    }
+    if let Some(memory_limit) = task.memory_limit {
+        limits.memory(memory_limit * 1024); // MiB -> KiB
+    }
+    let mut group = exec.into_group();
+    group.tag = Some(Tag::Evaluation.into());
+    group.priority = DIAGNOSTIC_CASE_PRIORITY;
+
+    let outcome = {
+        let sender = eval.sender.clone();
+        let solution = source_file.path.clone();
+        let subtask = subtask.id;
+        let args = case.args.clone();
+        move |score: f64, message: String| {
+            sender.send(UIMessage::IOIDiagnosticCase {
+                solution,
+                index,
+                subtask,
+                args,
+                score,
+                message,
+            })
+        }
+    };
+    // The outcome is sent by the solution if it fails, by the checker otherwise.
+    let outcome = Arc::new(Mutex::new(Some(outcome)));
+    let outcome_sol = outcome.clone();
+    eval.dag.on_execution_done(&group.uuid, move |results| {
+        let status = &results[0].status;
+        if status.is_success() {
+            return Ok(());
+        }
+        match outcome_sol.lock().unwrap().take() {
+            Some(outcome) => outcome(0.0, format!("{status:?}")),
+            None => Ok(()),
+        }
+    });
+    eval.dag.add_execution_group(group);
+
+    let mut checker = data.checker.check(
+        eval,
+        None,
+        Some((subtask.id, subtask.name.as_deref())),
+        None,
+        format!("Checking output of {description}"),
+        input,
+        &generator_outputs,
+        correct_output,
+        output.uuid,
+        move |score, message| match outcome.lock().unwrap().take() {
+            Some(outcome) => outcome(score, message),
+            None => Ok(()),
+        },
+    )?;
+    checker.tag = Some(Tag::Checking.into());
+    checker.priority = DIAGNOSTIC_CASE_PRIORITY;
+    eval.dag.add_execution_group(checker);
+    Ok(())
+}
+
+/// Find the first testcase of the subtask with both the input and the output generated, returning
+/// it together with its generator.
+fn find_template<'a>(
+    task: &'a IOITask,
+    subtask: &SubtaskInfo,
+) -> Option<(&'a TestcaseInfo, Arc<SourceFile>)> {
+    subtask
+        .testcases_owned
+        .iter()
+        .filter_map(|id| task.testcases.get(id))
+        .find_map(
+            |testcase| match (&testcase.input_generator, &testcase.output_generator) {
+                (InputGenerator::Custom(generator, _), OutputGenerator::Custom(..)) => {
+                    Some((testcase, generator.clone()))
+                }
+                _ => None,
+            },
+        )
+}
+
+/// Add an execution to the DAG, emitting an error diagnostic if it fails. The diagnostic includes
+/// the command line and the standard error of the execution, which must not be a solution of the
+/// contestants.
+fn add_with_diagnostic(eval: &mut EvaluationData, mut exec: Execution, tag: Tag, message: String) {
+    let command_line = exec.command_line();
+    exec.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+    let mut group: ExecutionGroup = exec.into_group();
+    group.tag = Some(tag.into());
+    group.priority = DIAGNOSTIC_CASE_PRIORITY;
+    let sender = eval.sender.clone();
+    eval.dag.on_execution_done(&group.uuid, move |results| {
+        let result = &results[0];
+        if result.status != ExecutionStatus::Success {
+            let mut diagnostic =
+                Diagnostic::error(message).with_note(format!("Command line: {command_line}"));
+            if let Some(stderr) = &result.stderr {
+                diagnostic = diagnostic.with_help_attachment(stderr.clone());
+            }
+            sender.add_diagnostic(diagnostic)?;
+        }
+        Ok(())
+    });
+    eval.dag.add_execution_group(group);
+}
@@ -2,14 +2,55 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{bail, Context, Error};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use task_maker_dag::{Execution, File, FileUuid, Priority};
+use task_maker_dag::{Execution, ExecutionStatus, File, FileUuid, Priority};
 use task_maker_diagnostics::Diagnostic;
+use task_maker_store::FileStoreKey;
 
-use crate::ioi::{SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{
+    SubtaskId, TestcaseId, INPUT_GENERATION_PRIORITY, STDERR_CONTENT_LENGTH,
+    TM_VALIDATION_FILE_NAME,
+};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, EvaluationData, SourceFile, Tag, UISender};
 
+/// The files written by a generator besides the input file, with their names.
+pub(crate) type GeneratorOutputs = Vec<(PathBuf, FileUuid)>;
+
+/// The names of the files that are placed next to the generator outputs in the sandboxes of the
+/// generator, of the validator and of the checker.
+const RESERVED_NAMES: &[&str] = &[
+    "input",
+    "correct_output",
+    "test_output",
+    TM_VALIDATION_FILE_NAME,
+    "limiti.yaml",
+    "constraints.yaml",
+];
+
+/// Parse the names of the files written by the generators besides the input file. They must be
+/// plain file names, without repetitions and different from the other files in the sandboxes.
+pub(crate) fn parse_generator_outputs(names: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut outputs: Vec<PathBuf> = Vec::with_capacity(names.len());
+    for name in names {
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            bail!("Invalid generator output {name:?}: it must be a file name, without directories");
+        }
+        if RESERVED_NAMES.contains(&name.as_str()) {
+            bail!("Invalid generator output {name:?}: the name is already used by task-maker");
+        }
+        if outputs
+            .iter()
+            .any(|output| output.as_os_str() == name.as_str())
+        {
+            bail!("The generator output {name:?} is listed more than once");
+        }
+        outputs.push(name.into());
+    }
+    Ok(outputs)
+}
+
 /// The source of the input files. It can either be a statically provided input file or a custom
 /// command that will generate an input file.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,20 +59,33 @@ pub enum InputGenerator {
     StaticFile(PathBuf),
     /// Use a custom command to generate the input file. The file has to be printed to stdout.
     Custom(Arc<SourceFile>, Vec<String>),
+    /// Use the file with this key from the store, for example an input file of a previous
+    /// evaluation. The evaluation fails if the file is not in the store anymore.
+    StoredFile(FileStoreKey),
 }
 
 impl InputGenerator {
     /// Build the execution for the generation of the input file. Return the handle to the input
-    /// file and the `Execution` if any. The execution does not send UI messages yet and it's not
-    /// added to the DAG.
+    /// file, the handles to the other `outputs` of the generator and the `Execution` if any. The
+    /// execution does not send UI messages yet and it's not added to the DAG.
+    ///
+    /// A static or stored input file has no other outputs, so it's an error if the generators of
+    /// the task have some.
     pub(crate) fn generate(
         &self,
         eval: &mut EvaluationData,
         task_path: &Path,
+        outputs: &[PathBuf],
         description: String,
         subtask_id: SubtaskId,
         testcase_id: TestcaseId,
-    ) -> Result<(FileUuid, Option<Execution>), Error> {
+    ) -> Result<(FileUuid, GeneratorOutputs, Option<Execution>), Error> {
+        if !outputs.is_empty() && !matches!(self, InputGenerator::Custom(..)) {
+            bail!(
+                "The input file of testcase {testcase_id} is not generated, but the generators write {}",
+                outputs.iter().map(|name| format!("{name:?}")).join(", ")
+            );
+        }
         match self {
             InputGenerator::StaticFile(path) => {
                 let path = &task_path.join(path);
@@ -48,14 +102,26 @@ impl InputGenerator {
                         path.display()
                     )
                 })?;
-                Ok((uuid, None))
+                Ok((uuid, vec![], None))
+            }
+            InputGenerator::StoredFile(key) => {
+                let file = File::new(format!(
+                    "Stored input file of testcase {testcase_id}, subtask {subtask_id} with hash {key}"
+                ));
+                let uuid = file.uuid;
+                eval.dag.provide_stored_file(file, key.clone());
+                Ok((uuid, vec![], None))
             }
             InputGenerator::Custom(source_file, args) => {
                 let mut exec = source_file
                     .execute(eval, description, args.clone())
                     .context("Failed to execute generator source file")?;
 
-                exec.limits_mut().allow_multiprocess();
+                let wall_time_limit = eval.dag.config_mut().generation_wall_time_limit;
+                exec.limits_mut()
+                    .allow_multiprocess()
+                    .wall_time(wall_time_limit);
+                exec.normalize_outputs(eval.dag.config_mut().normalize_generated_files);
 
                 // Add limiti.yaml and constraints.yaml file to the sandbox of the generator
                 for filename in &["limiti.yaml", "constraints.yaml"] {
@@ -71,23 +137,31 @@ impl InputGenerator {
                 }
 
                 let stdout = exec.capture_stdout(None);
-                Ok((stdout.uuid, Some(exec)))
+                let outputs = outputs
+                    .iter()
+                    .map(|name| (name.clone(), exec.output(name).uuid))
+                    .collect();
+                Ok((stdout.uuid, outputs, Some(exec)))
             }
         }
     }
 
     /// Add the generation of the input file to the DAG and the callbacks to the UI, returning the
-    /// handle to the input file.
+    /// handle to the input file and to the other `outputs` of the generator.
+    ///
+    /// The other outputs are stored next to the input file, as `input{testcase_id}.{name}`.
     pub(crate) fn generate_and_bind(
         &self,
         eval: &mut EvaluationData,
         task_path: &Path,
+        outputs: &[PathBuf],
         subtask_id: SubtaskId,
         testcase_id: TestcaseId,
-    ) -> Result<FileUuid, Error> {
-        let (input, gen) = self.generate(
+    ) -> Result<(FileUuid, GeneratorOutputs), Error> {
+        let (input, outputs, gen) = self.generate(
             eval,
             task_path,
+            outputs,
             format!("Generation of input file of testcase {testcase_id}, subtask {subtask_id}"),
             subtask_id,
             testcase_id,
@@ -99,25 +173,42 @@ impl InputGenerator {
                 .join(format!("input{testcase_id}.txt")),
             false,
         );
+        for (name, file) in &outputs {
+            eval.dag.write_file_to(
+                *file,
+                eval.task_root
+                    .join("input")
+                    .join(format!("input{testcase_id}.{}", name.to_string_lossy())),
+                false,
+            );
+        }
         // If there is an execution, bind its callbacks and store the input file.
         if let Some(mut gen) = gen {
             gen.capture_stderr(Some(STDERR_CONTENT_LENGTH));
-            let args = gen.args.join(" ");
+            let command_line = gen.command_line();
             let mut group = gen.into_group();
             group.tag = Some(Tag::Generation.into());
-            group.priority = GENERATION_PRIORITY - testcase_id as Priority;
+            group.priority = INPUT_GENERATION_PRIORITY - testcase_id as Priority;
             bind_exec_callbacks!(eval, group.uuid, |status| UIMessage::IOIGeneration {
                 subtask: subtask_id,
                 testcase: testcase_id,
                 status
             })?;
             let sender = eval.sender.clone();
+            let wall_time_limit = eval.dag.config_mut().generation_wall_time_limit;
             eval.dag.on_execution_done(&group.uuid, move |results| {
                 let result = &results[0];
                 if !result.status.is_success() {
-                    let mut diagnostic =
-                        Diagnostic::error(format!("Failed to generate input {testcase_id}"))
-                            .with_note(format!("Generator arguments are: {args}"));
+                    let mut diagnostic = match result.status {
+                        ExecutionStatus::WallTimeLimitExceeded => Diagnostic::error(format!(
+                            "Generation of input {testcase_id} timed out after {wall_time_limit}s"
+                        ))
+                        .with_help(
+                            "Raise the limit with generation_wall_time_limit in the task config",
+                        ),
+                        _ => Diagnostic::error(format!("Failed to generate input {testcase_id}")),
+                    }
+                    .with_note(format!("Command line: {command_line}"));
                     if let Some(stderr) = &result.stderr {
                         diagnostic = diagnostic.with_help_attachment(stderr.clone());
                     }
@@ -127,6 +218,6 @@ impl InputGenerator {
             });
             eval.dag.add_execution_group(group);
         }
-        Ok(input)
+        Ok((input, outputs))
     }
 }
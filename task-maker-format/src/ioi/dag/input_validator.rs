@@ -1,12 +1,12 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{Context, Error};
 use serde::{Deserialize, Serialize};
-use task_maker_dag::{Execution, File, FileUuid, Priority};
+use task_maker_dag::{Execution, ExecutionResult, ExecutionStatus, File, FileUuid, Priority};
 use task_maker_diagnostics::Diagnostic;
 
-use crate::ioi::{SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{SubtaskId, TestcaseId, INPUT_GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, EvaluationData, SourceFile, Tag, UISender};
 
@@ -30,6 +30,8 @@ impl InputValidator {
     /// Build the execution for the validation of the input file. Return the handle to the standard
     /// output of the validator, if any and the `Execution` if any. The execution does not send UI
     /// messages yet and it's not added to the DAG.
+    ///
+    /// The other outputs of the generator are placed in the sandbox with their names.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn validate(
         &self,
@@ -40,6 +42,7 @@ impl InputValidator {
         subtask_name: Option<&str>,
         testcase_id: TestcaseId,
         input: FileUuid,
+        generator_outputs: &[(PathBuf, FileUuid)],
     ) -> Result<(Option<FileUuid>, Option<Execution>), Error> {
         match self {
             InputValidator::AssumeValid => Ok((None, None)),
@@ -50,23 +53,18 @@ impl InputValidator {
                 exec.input(input, TM_VALIDATION_FILE_NAME, false)
                     .env("TM_SUBTASK", subtask_id.to_string())
                     .env("TM_TESTCASE", testcase_id.to_string());
+                for (name, file) in generator_outputs {
+                    exec.input(*file, name, false);
+                }
                 if let Some(name) = subtask_name {
                     exec.env("TM_SUBTASK_NAME", name);
                 }
-                exec.limits_mut().allow_multiprocess();
-
-                // Add limiti.yaml, constraints.yaml and gen.toml to the sandbox of the validator
-                for filename in &["limiti.yaml", "constraints.yaml", "gen.toml"] {
-                    let path = task_path.join("gen").join(filename);
+                let wall_time_limit = eval.dag.config_mut().generation_wall_time_limit;
+                exec.limits_mut()
+                    .allow_multiprocess()
+                    .wall_time(wall_time_limit);
 
-                    if !path.is_file() {
-                        continue;
-                    }
-
-                    let file = File::new(format!("Constraints file at {}", path.display()));
-                    exec.input(&file, filename, false);
-                    eval.dag.provide_file(file, path)?;
-                }
+                add_constraints_files(eval, &mut exec, task_path)?;
 
                 let stdout = exec.capture_stdout(None);
 
@@ -78,6 +76,7 @@ impl InputValidator {
     /// Add the validation of the input file to the DAG and the callbacks to the UI, optionally
     /// returning a fake file that blocks the usage of the actual input until the validation
     /// succeeds. If the validation is ignored, `None` is returned.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn validate_and_bind(
         &self,
         eval: &mut EvaluationData,
@@ -86,6 +85,7 @@ impl InputValidator {
         subtask_name: Option<&str>,
         testcase_id: TestcaseId,
         input: FileUuid,
+        generator_outputs: &[(PathBuf, FileUuid)],
     ) -> Result<Option<FileUuid>, Error> {
         let (handle, val) = self.validate(
             eval,
@@ -95,27 +95,27 @@ impl InputValidator {
             subtask_name,
             testcase_id,
             input,
+            generator_outputs,
         )?;
         if let Some(mut val) = val {
             val.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+            let command_line = val.command_line();
             let mut group = val.into_group();
             group.tag = Some(Tag::Generation.into());
-            group.priority = GENERATION_PRIORITY - testcase_id as Priority;
+            group.priority = INPUT_GENERATION_PRIORITY - testcase_id as Priority;
             bind_exec_callbacks!(eval, group.uuid, |status| UIMessage::IOIValidation {
                 subtask: subtask_id,
                 testcase: testcase_id,
                 status
             })?;
             let sender = eval.sender.clone();
+            let wall_time_limit = eval.dag.config_mut().generation_wall_time_limit;
             eval.dag.on_execution_done(&group.uuid, move |results| {
                 let result = &results[0];
                 if !result.status.is_success() {
-                    let mut diagnostic = Diagnostic::error(format!(
-                        "Failed to validate input {testcase_id} for subtask {subtask_id}"
-                    ));
-                    if let Some(stderr) = &result.stderr {
-                        diagnostic = diagnostic.with_help_attachment(stderr.clone());
-                    }
+                    let message = format!("input {testcase_id} for subtask {subtask_id}");
+                    let diagnostic = validation_failed(result, &message, wall_time_limit)
+                        .with_note(format!("Command line: {command_line}"));
                     sender.add_diagnostic(diagnostic)?;
                 }
                 Ok(())
@@ -124,4 +124,108 @@ impl InputValidator {
         }
         Ok(handle)
     }
+
+    /// Add to the DAG the validation of all the input files of a subtask at once, returning a
+    /// fake file that blocks the usage of the inputs until the validation succeeds. If the
+    /// validation is ignored, `None` is returned.
+    ///
+    /// The input files are placed in the sandbox as `tm_validation_file_<testcase id>` and their
+    /// names are passed to the validator after its arguments. The validation waits for all the
+    /// `val_handles`, so the returned file also implies that all of them succeeded.
+    pub(crate) fn validate_subtask_and_bind(
+        &self,
+        eval: &mut EvaluationData,
+        task_path: &Path,
+        subtask_id: SubtaskId,
+        subtask_name: Option<&str>,
+        inputs: &[(TestcaseId, FileUuid)],
+        val_handles: &[FileUuid],
+    ) -> Result<Option<FileUuid>, Error> {
+        let InputValidator::Custom(source_file, args) = self else {
+            return Ok(None);
+        };
+        let names = inputs
+            .iter()
+            .map(|(testcase_id, _)| format!("{TM_VALIDATION_FILE_NAME}_{testcase_id}"))
+            .collect::<Vec<_>>();
+        let mut exec = source_file
+            .execute(
+                eval,
+                format!("Validation of the input files of subtask {subtask_id}"),
+                args.iter().chain(names.iter()).cloned().collect::<Vec<_>>(),
+            )
+            .context("Failed to execute subtask validator source file")?;
+        for ((_, input), name) in inputs.iter().zip(names.iter()) {
+            exec.input(*input, name, false);
+        }
+        for (i, handle) in val_handles.iter().enumerate() {
+            exec.input(*handle, format!("wait_for_validation_{i}"), false);
+        }
+        exec.env("TM_SUBTASK", subtask_id.to_string());
+        if let Some(name) = subtask_name {
+            exec.env("TM_SUBTASK_NAME", name);
+        }
+        let wall_time_limit = eval.dag.config_mut().generation_wall_time_limit;
+        exec.limits_mut()
+            .allow_multiprocess()
+            .wall_time(wall_time_limit);
+        add_constraints_files(eval, &mut exec, task_path)?;
+        let stdout = exec.capture_stdout(None);
+        exec.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+        let command_line = exec.command_line();
+
+        let mut group = exec.into_group();
+        group.tag = Some(Tag::Generation.into());
+        group.priority = INPUT_GENERATION_PRIORITY;
+        let sender = eval.sender.clone();
+        eval.dag.on_execution_done(&group.uuid, move |results| {
+            let result = &results[0];
+            if !result.status.is_success() {
+                let message = format!("the input files of subtask {subtask_id}");
+                let note = "The solutions are not evaluated on this subtask";
+                let diagnostic = validation_failed(result, &message, wall_time_limit)
+                    .with_note(format!("{note}\nCommand line: {command_line}"));
+                sender.add_diagnostic(diagnostic)?;
+            }
+            Ok(())
+        });
+        eval.dag.add_execution_group(group);
+        Ok(Some(stdout.uuid))
+    }
+}
+
+/// The diagnostic of a failed validation of `what`, telling apart a validator killed by the wall
+/// time limit from a validator that rejected the input.
+fn validation_failed(result: &ExecutionResult, what: &str, wall_time_limit: f64) -> Diagnostic {
+    let mut diagnostic = match result.status {
+        ExecutionStatus::WallTimeLimitExceeded => Diagnostic::error(format!(
+            "Validation of {what} timed out after {wall_time_limit}s"
+        ))
+        .with_help("Raise the limit with generation_wall_time_limit in the task config"),
+        _ => Diagnostic::error(format!("Failed to validate {what}")),
+    };
+    if let Some(stderr) = &result.stderr {
+        diagnostic = diagnostic.with_help_attachment(stderr.clone());
+    }
+    diagnostic
+}
+
+/// Add limiti.yaml, constraints.yaml and gen.toml to the sandbox of a validator.
+fn add_constraints_files(
+    eval: &mut EvaluationData,
+    exec: &mut Execution,
+    task_path: &Path,
+) -> Result<(), Error> {
+    for filename in &["limiti.yaml", "constraints.yaml", "gen.toml"] {
+        let path = task_path.join("gen").join(filename);
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let file = File::new(format!("Constraints file at {}", path.display()));
+        exec.input(&file, filename, false);
+        eval.dag.provide_file(file, path)?;
+    }
+    Ok(())
 }
@@ -1,18 +1,30 @@
-pub use checker::Checker;
+pub use checker::{Checker, ToleranceMode};
+pub(crate) use diagnostic_case::evaluate_diagnostic_cases;
 pub use input_generator::InputGenerator;
+pub(crate) use input_generator::{parse_generator_outputs, GeneratorOutputs};
 pub use input_validator::{InputValidator, TM_VALIDATION_FILE_NAME};
 pub use output_generator::OutputGenerator;
+use std::sync::{Arc, Mutex};
+
 use serde::{Deserialize, Serialize};
-use task_maker_dag::Priority;
+use task_maker_dag::{Execution, ExecutionGroupUuid, FileUuid, Priority};
 pub use task_type::{BatchTypeData, CommunicationTypeData, InteractiveTypeData, TaskType, UserIo};
 
+use crate::ioi::{IOITask, ScoreManager, SubtaskId, TestcaseId};
+use crate::EvaluationData;
+
 mod checker;
+mod diagnostic_case;
 mod input_generator;
 mod input_validator;
 mod output_generator;
 mod task_type;
 
-/// Base priority for the generation executions.
+/// Base priority for the generation and the validation of the input files. It's higher than
+/// [`GENERATION_PRIORITY`] so that the independent generations are dispatched as soon as possible,
+/// without waiting for the generation of the outputs of the previous testcases.
+pub const INPUT_GENERATION_PRIORITY: Priority = 2_000_000;
+/// Base priority for the generation of the output files.
 pub const GENERATION_PRIORITY: Priority = 1_000_000;
 /// Base priority for the evaluation executions.
 pub const EVALUATION_PRIORITY: Priority = 1_000;
@@ -38,6 +50,19 @@ pub enum TestcaseScoreAggregator {
     Sum,
 }
 
+/// How the scores are rounded to the configured number of decimal digits when they are displayed.
+///
+/// The scores are always computed with full precision, only the displayed value is rounded.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreRounding {
+    /// Round to the nearest value, rounding the halves up.
+    #[default]
+    HalfUp,
+    /// Drop the extra digits.
+    Truncate,
+}
+
 /// Bind the input/output of an execution to the input and output file of a testcase. It correctly
 /// chooses if using stdin/stdout or using normal files by looking at the value set in the `Task`.
 ///
@@ -66,6 +91,37 @@ macro_rules! bind_exec_io {
     }};
 }
 
+/// Bind the extra output files of the task as outputs of an execution, returning their handles in
+/// the same order of `TaskType::extra_output_files`.
+pub(crate) fn bind_extra_outputs(exec: &mut Execution, task: &IOITask) -> Vec<FileUuid> {
+    task.task_type
+        .extra_output_files()
+        .iter()
+        .map(|path| exec.output(path).uuid)
+        .collect()
+}
+
+/// Register the evaluation of a solution on a testcase in its `ScoreManager`, so that it's skipped
+/// when the evaluation of the solution is stopped. The skipped testcase is scored zero.
+pub(crate) fn bind_stop_after(
+    eval: &mut EvaluationData,
+    subtask_id: SubtaskId,
+    testcase_id: TestcaseId,
+    execution: ExecutionGroupUuid,
+    score_manager: Arc<Mutex<ScoreManager>>,
+) {
+    score_manager.lock().unwrap().add_evaluation(execution);
+    let sender = eval.sender.clone();
+    eval.dag.on_execution_skip(&execution, move || {
+        let mut score_manager = score_manager.lock().unwrap();
+        // the execution may have been skipped for other reasons, e.g. a failed compilation
+        if !score_manager.is_stopped() {
+            return Ok(());
+        }
+        score_manager.score(subtask_id, testcase_id, 0.0, "Skipped".into(), sender)
+    });
+}
+
 impl TestcaseScoreAggregator {
     /// Aggregate the scores of a subtask from an iterator with the scores of the testcases.
     pub(crate) fn aggregate<I: IntoIterator<Item = f64>>(&self, iter: I) -> f64 {
@@ -87,6 +143,20 @@ impl TestcaseScoreAggregator {
     }
 }
 
+impl ScoreRounding {
+    /// Round a non-negative score to `precision` decimal digits.
+    pub fn round(&self, score: f64, precision: usize) -> f64 {
+        let factor = 10f64.powi(precision as i32);
+        // Remove the floating point noise before rounding, e.g. 0.285 * 100 = 28.499999999999996.
+        let scaled = (score * factor * 1e6).round() / 1e6;
+        let rounded = match self {
+            ScoreRounding::HalfUp => (scaled + 0.5).floor(),
+            ScoreRounding::Truncate => scaled.floor(),
+        };
+        rounded / factor
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -110,18 +180,28 @@ mod tests {
             task_type: TaskType::Batch(BatchTypeData {
                 output_generator: None,
                 checker: Checker::WhiteDiff,
+                extra_output_files: vec![],
             }),
             name: "".to_string(),
             title: "".to_string(),
             time_limit: None,
             memory_limit: None,
+            relative_time_limit: None,
+            compilation_time_limit: None,
+            compilation_memory_limit: None,
+            generation_wall_time_limit: None,
             infile: None,
             outfile: None,
+            generator_outputs: vec![],
+            normalize_files: false,
+            output_only: false,
             subtasks: Default::default(),
             testcases: Default::default(),
             input_validator_generator: Default::default(),
             testcase_score_aggregator: TestcaseScoreAggregator::Min,
+            scorer: None,
             score_precision: 0,
+            score_rounding: Default::default(),
             grader_map: Arc::new(GraderMap::new(Vec::<PathBuf>::new())),
             booklets: vec![],
             difficulty: None,
@@ -158,6 +238,22 @@ mod tests {
         assert_abs_diff_eq!(1.0, sum);
     }
 
+    #[test]
+    fn test_score_rounding_half_up() {
+        let rounding = ScoreRounding::HalfUp;
+        assert_abs_diff_eq!(0.29, rounding.round(0.285, 2));
+        assert_abs_diff_eq!(0.28, rounding.round(0.2849, 2));
+        assert_abs_diff_eq!(3.0, rounding.round(2.5, 0));
+    }
+
+    #[test]
+    fn test_score_rounding_truncate() {
+        let rounding = ScoreRounding::Truncate;
+        assert_abs_diff_eq!(0.28, rounding.round(0.2899, 2));
+        assert_abs_diff_eq!(0.29, rounding.round(0.29, 2));
+        assert_abs_diff_eq!(2.0, rounding.round(2.5, 0));
+    }
+
     #[test]
     fn test_input_generator_static() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -166,8 +262,9 @@ mod tests {
         let generator = InputGenerator::StaticFile(path);
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
         let out = generator
-            .generate_and_bind(&mut eval, &PathBuf::from("."), 0, 0)
-            .unwrap();
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &[], 0, 0)
+            .unwrap()
+            .0;
         assert!(eval.dag.data.provided_files.contains_key(&out));
         assert!(eval
             .dag
@@ -178,13 +275,48 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_input_generator_static_with_generator_outputs() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("input.txt");
+        std::fs::write(&path, "x").unwrap();
+        let generator = InputGenerator::StaticFile(path);
+        let (mut eval, _) = EvaluationData::new(tmpdir.path());
+        let outputs = vec![PathBuf::from("graph.bin")];
+        let err = generator
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &outputs, 0, 0)
+            .unwrap_err();
+        assert!(err.to_string().contains("graph.bin"));
+        assert!(eval.dag.data.provided_files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_generator_outputs() {
+        let names = |names: &[&str]| names.iter().map(|s| s.to_string()).collect_vec();
+        let outputs = parse_generator_outputs(&names(&["graph.bin", "extra.txt"])).unwrap();
+        assert_eq!(
+            outputs,
+            vec![PathBuf::from("graph.bin"), "extra.txt".into()]
+        );
+        for invalid in ["", "..", "dir/graph.bin", "dir\\graph.bin", "/graph.bin"] {
+            let err = parse_generator_outputs(&names(&[invalid])).unwrap_err();
+            assert!(err.to_string().contains("without directories"), "{invalid}");
+        }
+        for reserved in ["input", "correct_output", "test_output"] {
+            let err = parse_generator_outputs(&names(&[reserved])).unwrap_err();
+            assert!(err.to_string().contains("already used"), "{reserved}");
+        }
+        let err = parse_generator_outputs(&names(&["graph.bin", "graph.bin"])).unwrap_err();
+        assert!(err.to_string().contains("more than once"));
+    }
+
     #[test]
     fn test_input_generator_static_not_found() {
         let tmpdir = tempfile::TempDir::new().unwrap();
         let path = tmpdir.path().join("input.txt");
         let generator = InputGenerator::StaticFile(path.clone());
         let (mut eval, _) = EvaluationData::new(tmpdir.path());
-        let gen = generator.generate_and_bind(&mut eval, &PathBuf::from("."), 0, 0);
+        let gen = generator.generate_and_bind(&mut eval, &PathBuf::from("."), &[], 0, 0);
         assert!(gen.is_err());
         let err = gen.unwrap_err().to_string();
         assert!(err.contains("COPY"));
@@ -200,8 +332,9 @@ mod tests {
         let generator = InputGenerator::Custom(Arc::new(source), vec![]);
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
         let out = generator
-            .generate_and_bind(&mut eval, &PathBuf::from("."), 0, 0)
-            .unwrap();
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &[], 0, 0)
+            .unwrap()
+            .0;
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
         let group = eval.dag.data.execution_groups.values().next().unwrap();
@@ -219,13 +352,108 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_input_generator_wall_time_limit() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("gen.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = InputGenerator::Custom(Arc::new(source), vec![]);
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        eval.dag.config_mut().generation_wall_time_limit(123.0);
+        generator
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &[], 0, 0)
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        assert_eq!(group.executions[0].limits.wall_time, Some(123.0));
+    }
+
+    #[test]
+    fn test_input_generator_normalize_files() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("gen.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = InputGenerator::Custom(Arc::new(source), vec![]);
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        generator
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &[], 0, 0)
+            .unwrap();
+        eval.dag.config_mut().normalize_generated_files(true);
+        generator
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &[], 0, 1)
+            .unwrap();
+        let normalized: Vec<_> = eval
+            .dag
+            .data
+            .execution_groups
+            .values()
+            .map(|group| {
+                (
+                    group.description.clone(),
+                    group.executions[0].normalize_outputs,
+                )
+            })
+            .sorted()
+            .collect();
+        assert_eq!(
+            normalized,
+            vec![
+                (
+                    "Generation of input file of testcase 0, subtask 0".into(),
+                    false
+                ),
+                (
+                    "Generation of input file of testcase 1, subtask 0".into(),
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_generator_custom_outputs() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("gen.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = InputGenerator::Custom(Arc::new(source), vec![]);
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let (_, outputs) = generator
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &["graph.bin".into()], 0, 3)
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].0, PathBuf::from("graph.bin"));
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let graph = &group.executions[0].output_files[&PathBuf::from("graph.bin")];
+        assert_eq!(graph.uuid, outputs[0].1);
+        let write_to = eval.dag.file_callbacks()[&outputs[0].1].write_to.as_ref();
+        assert_eq!(
+            write_to.unwrap().dest,
+            tmpdir.path().join("input/input3.graph.bin")
+        );
+    }
+
+    #[test]
+    fn test_input_generator_static_outputs() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("input.txt");
+        std::fs::write(&path, "x").unwrap();
+        let generator = InputGenerator::StaticFile(path);
+        let (mut eval, _) = EvaluationData::new(tmpdir.path());
+        let (_, outputs) = generator
+            .generate_and_bind(&mut eval, &PathBuf::from("."), &["graph.bin".into()], 0, 0)
+            .unwrap();
+        assert!(outputs.is_empty());
+    }
+
     #[test]
     fn test_input_validator_assume_valid() {
         let validator = InputValidator::AssumeValid;
         let file = File::new("input");
         let (mut eval, _recv) = EvaluationData::new("");
         let out = validator
-            .validate_and_bind(&mut eval, &PathBuf::from("."), 0, None, 0, file.uuid)
+            .validate_and_bind(&mut eval, &PathBuf::from("."), 0, None, 0, file.uuid, &[])
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 0);
         assert_eq!(eval.dag.data.execution_groups.len(), 0);
@@ -242,7 +470,7 @@ mod tests {
         let file = File::new("input");
         let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
         let out = validator
-            .validate_and_bind(&mut eval, &PathBuf::from("."), 0, None, 0, file.uuid)
+            .validate_and_bind(&mut eval, &PathBuf::from("."), 0, None, 0, file.uuid, &[])
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
@@ -256,6 +484,33 @@ mod tests {
         assert_eq!(group.executions[0].env["TM_TESTCASE"], "0");
     }
 
+    #[test]
+    fn test_input_validator_custom_generator_outputs() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("val.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let validator = InputValidator::Custom(Arc::new(source), vec![]);
+        let file = File::new("input");
+        let graph = File::new("graph");
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        validator
+            .validate_and_bind(
+                &mut eval,
+                &PathBuf::from("."),
+                0,
+                None,
+                0,
+                file.uuid,
+                &[("graph.bin".into(), graph.uuid)],
+            )
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        assert!(group.dependencies().contains(&graph.uuid));
+        let input = &group.executions[0].input_files[&PathBuf::from("graph.bin")];
+        assert_eq!(input.file, graph.uuid);
+    }
+
     #[test]
     fn test_input_validator_custom_with_name() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -273,6 +528,7 @@ mod tests {
                 Some("name"),
                 0,
                 file.uuid,
+                &[],
             )
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
@@ -300,6 +556,7 @@ mod tests {
         let out = generator
             .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, None)
             .unwrap()
+            .0
             .unwrap();
         assert!(eval.dag.data.provided_files.contains_key(&out));
         assert!(eval
@@ -340,6 +597,7 @@ mod tests {
         let out = generator
             .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, Some(val.uuid))
             .unwrap()
+            .0
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
@@ -360,6 +618,33 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn test_output_generator_custom_extra_outputs() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("sol.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = OutputGenerator::Custom(Arc::new(source), vec![]);
+        let file = File::new("input");
+        let mut task = make_task(tmpdir.path());
+        if let TaskType::Batch(data) = &mut task.task_type {
+            data.extra_output_files = vec!["second.txt".into()];
+        }
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let (_, extra) = generator
+            .generate_and_bind(&task, &mut eval, 0, 0, file.uuid, None)
+            .unwrap();
+        assert_eq!(extra.len(), 1);
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        let second = &group.executions[0].output_files[&PathBuf::from("second.txt")];
+        assert_eq!(second.uuid, extra[0]);
+        let write_to = eval.dag.file_callbacks()[&extra[0]].write_to.as_ref();
+        assert_eq!(
+            write_to.unwrap().dest,
+            tmpdir.path().join("output/output0.second.txt")
+        );
+    }
+
     #[test]
     fn test_checker_whitediff() {
         let checker = Checker::WhiteDiff;
@@ -368,9 +653,19 @@ mod tests {
         let output = File::new("output").uuid;
         let test = File::new("test").uuid;
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, |_, _| {
-                panic!("the callback should not be called here")
-            })
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                |_, _| panic!("the callback should not be called here"),
+            )
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 0);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
@@ -402,7 +697,19 @@ mod tests {
             Ok(())
         };
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                cb,
+            )
             .unwrap();
         let callbacks = eval.dag.execution_callbacks().drain().next().unwrap().1;
         callbacks.on_done.into_iter().for_each(|cb| {
@@ -410,11 +717,14 @@ mod tests {
                 status: ExecutionStatus::Success,
                 was_killed: false,
                 was_cached: false,
+                cache_hit: None,
+                missing_outputs: vec![],
                 resources: ExecutionResourcesUsage {
                     cpu_time: 0.0,
                     sys_time: 0.0,
                     wall_time: 0.0,
                     memory: 0,
+                    final_memory: 0,
                 },
                 stdout: None,
                 stderr: None,
@@ -440,7 +750,19 @@ mod tests {
             Ok(())
         };
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                cb,
+            )
             .unwrap();
         let callbacks = eval.dag.execution_callbacks().drain().next().unwrap().1;
         callbacks.on_done.into_iter().for_each(|cb| {
@@ -448,11 +770,14 @@ mod tests {
                 status: ExecutionStatus::ReturnCode(1),
                 was_killed: false,
                 was_cached: false,
+                cache_hit: None,
+                missing_outputs: vec![],
                 resources: ExecutionResourcesUsage {
                     cpu_time: 0.0,
                     sys_time: 0.0,
                     wall_time: 0.0,
                     memory: 0,
+                    final_memory: 0,
                 },
                 stdout: None,
                 stderr: None,
@@ -462,6 +787,92 @@ mod tests {
         assert!(cb_called.load(Ordering::Relaxed));
     }
 
+    #[test]
+    fn test_checker_token_diff() {
+        let checker = Checker::TokenDiff {
+            float_tolerance: 1e-6,
+            tolerance_mode: ToleranceMode::Relative,
+        };
+        let (mut eval, _recv) = EvaluationData::new("");
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                |_, _| panic!("the callback should not be called here"),
+            )
+            .unwrap();
+        assert_eq!(eval.dag.data.provided_files.len(), 1);
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        assert_eq!(group.tag.as_ref().unwrap(), &Tag::Checking.into());
+        let args = &group.executions[0].args;
+        assert_eq!(args[0], "token_diff.py");
+        assert_eq!(args[3].parse::<f64>().unwrap(), 1e-6);
+        assert_eq!(args[4], "relative");
+        assert!(group.dependencies().contains(&output));
+        assert!(group.dependencies().contains(&test));
+    }
+
+    #[test]
+    fn test_checker_token_diff_incorrect() {
+        let checker = Checker::TokenDiff {
+            float_tolerance: 0.0,
+            tolerance_mode: ToleranceMode::default(),
+        };
+        let (mut eval, _recv) = EvaluationData::new("");
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        let cb_called = Arc::new(AtomicBool::new(false));
+        let cb_called2 = cb_called.clone();
+        let cb = move |score, mex| {
+            assert_abs_diff_eq!(score, 0.0);
+            assert_eq!(mex, "Output is incorrect: token 2 is wrong");
+            cb_called2.store(true, Ordering::Relaxed);
+            Ok(())
+        };
+        checker
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                cb,
+            )
+            .unwrap();
+        let callbacks = eval.dag.execution_callbacks().drain().next().unwrap().1;
+        callbacks.on_done.into_iter().for_each(|cb| {
+            cb(&[ExecutionResult {
+                status: ExecutionStatus::ReturnCode(1),
+                was_killed: false,
+                was_cached: false,
+                cache_hit: None,
+                missing_outputs: vec![],
+                resources: ExecutionResourcesUsage::default(),
+                stdout: Some(b"Output is incorrect: token 2 is wrong\n".to_vec()),
+                stderr: None,
+            }])
+            .unwrap();
+        });
+        assert!(cb_called.load(Ordering::Relaxed));
+    }
+
     #[test]
     fn test_checker_custom() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -474,9 +885,19 @@ mod tests {
         let output = File::new("output").uuid;
         let test = File::new("test").uuid;
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, |_, _| {
-                panic!("the callback should not be called here")
-            })
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                |_, _| panic!("the callback should not be called here"),
+            )
             .unwrap();
         assert_eq!(eval.dag.data.provided_files.len(), 1);
         assert_eq!(eval.dag.data.execution_groups.len(), 1);
@@ -485,6 +906,39 @@ mod tests {
         assert!(group.dependencies().contains(&input));
         assert!(group.dependencies().contains(&output));
         assert!(group.dependencies().contains(&test));
+        assert_eq!(group.executions[0].env["TM_SUBTASK"], "0");
+        assert!(!group.executions[0].env.contains_key("TM_SUBTASK_NAME"));
+    }
+
+    #[test]
+    fn test_checker_custom_with_subtask_name() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("check.py");
+        std::fs::write(&path, "x").unwrap();
+        let source = SourceFile::new(&path, "", "", None, None::<PathBuf>).unwrap();
+        let checker = Checker::Custom(Arc::new(source));
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let input = File::new("input").uuid;
+        let output = File::new("output").uuid;
+        let test = File::new("test").uuid;
+        checker
+            .check_and_bind(
+                &mut eval,
+                1,
+                Some("name"),
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                |_, _| panic!("the callback should not be called here"),
+            )
+            .unwrap();
+        let group = eval.dag.data.execution_groups.values().next().unwrap();
+        assert_eq!(group.executions[0].env["TM_SUBTASK"], "1");
+        assert_eq!(group.executions[0].env["TM_SUBTASK_NAME"], "name");
     }
 
     #[test]
@@ -507,7 +961,19 @@ mod tests {
             Ok(())
         };
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                cb,
+            )
             .unwrap();
         let group = eval.dag.data.execution_groups.values().next().unwrap();
         let exec = group.uuid;
@@ -516,6 +982,8 @@ mod tests {
             status: ExecutionStatus::Success,
             was_killed: false,
             was_cached: false,
+            cache_hit: None,
+            missing_outputs: vec![],
             resources: Default::default(),
             stdout: Some("1.0\n\n".into()),
             stderr: Some("Ok!\n\n".into()),
@@ -545,7 +1013,19 @@ mod tests {
             Ok(())
         };
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                cb,
+            )
             .unwrap();
         let group = eval.dag.data.execution_groups.values().next().unwrap();
         let exec = group.uuid;
@@ -554,6 +1034,8 @@ mod tests {
             status: ExecutionStatus::Success,
             was_killed: false,
             was_cached: false,
+            cache_hit: None,
+            missing_outputs: vec![],
             resources: Default::default(),
             stdout: Some("0.0\n\n".into()),
             stderr: Some("Ko!\n\n".into()),
@@ -576,7 +1058,19 @@ mod tests {
         let test = File::new("test").uuid;
         let cb = move |_, _| panic!("the callback should not be called here");
         checker
-            .check_and_bind(&mut eval, 0, 0, "sol", input, output, test, cb)
+            .check_and_bind(
+                &mut eval,
+                0,
+                None,
+                None,
+                0,
+                "sol",
+                input,
+                &[],
+                output,
+                test,
+                cb,
+            )
             .unwrap();
         let group = eval.dag.data.execution_groups.values().next().unwrap();
         let exec = group.uuid;
@@ -585,6 +1079,8 @@ mod tests {
             status: ExecutionStatus::Success,
             was_killed: false,
             was_cached: false,
+            cache_hit: None,
+            missing_outputs: vec![],
             resources: Default::default(),
             stdout: Some(":<\n\n".into()),
             stderr: Some("Ko!\n\n".into()),
@@ -5,8 +5,11 @@ use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Serialize};
 use task_maker_dag::{Execution, File, FileUuid, Priority};
 use task_maker_diagnostics::Diagnostic;
+use task_maker_store::FileStoreKey;
 
-use crate::ioi::{IOITask, SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::ioi::{
+    bind_extra_outputs, IOITask, SubtaskId, TestcaseId, GENERATION_PRIORITY, STDERR_CONTENT_LENGTH,
+};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, bind_exec_io, EvaluationData, SourceFile, Tag, UISender};
 
@@ -21,13 +24,18 @@ pub enum OutputGenerator {
     /// Use a custom command to generate the output file. The task specification for input/output
     /// files are used.
     Custom(Arc<SourceFile>, Vec<String>),
+    /// Use the file already present in the store with the given key.
+    StoredFile(FileStoreKey),
 }
 
 impl OutputGenerator {
     /// Build the execution for the generation of the output file. Return the handle to the output
-    /// file and the `Execution` if any. The execution does not send UI messages yet and it's not
-    /// added to the DAG.
-    #[allow(clippy::too_many_arguments)]
+    /// file, the handles to the extra output files of the task and the `Execution` if any. The
+    /// execution does not send UI messages yet and it's not added to the DAG.
+    ///
+    /// The static extra output files are searched next to the static output file, replacing its
+    /// extension with the name of the extra file (e.g. `output0.second.txt`).
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub(crate) fn generate(
         &self,
         task: &IOITask,
@@ -37,13 +45,19 @@ impl OutputGenerator {
         testcase_id: TestcaseId,
         input: FileUuid,
         validation_handle: Option<FileUuid>,
-    ) -> Result<(Option<FileUuid>, Option<Execution>), Error> {
+    ) -> Result<(Option<FileUuid>, Vec<FileUuid>, Option<Execution>), Error> {
+        let extra_output_files = task.task_type.extra_output_files();
         match self {
             OutputGenerator::NotAvailable => {
-                let file = File::new("Empty file");
-                let uuid = file.uuid;
-                eval.dag.provide_content(file, vec![]);
-                Ok((Some(uuid), None))
+                let mut empty_file = || {
+                    let file = File::new("Empty file");
+                    let uuid = file.uuid;
+                    eval.dag.provide_content(file, vec![]);
+                    uuid
+                };
+                let uuid = empty_file();
+                let extra = extra_output_files.iter().map(|_| empty_file()).collect();
+                Ok((Some(uuid), extra, None))
             }
             OutputGenerator::StaticFile(path) => {
                 if !path.exists() {
@@ -59,20 +73,50 @@ impl OutputGenerator {
                         path.display()
                     )
                 })?;
-                Ok((Some(uuid), None))
+                let mut extra = Vec::with_capacity(extra_output_files.len());
+                for name in extra_output_files {
+                    let path = path.with_extension(name);
+                    if !path.exists() {
+                        bail!("Static output file not found: {:?}", path);
+                    }
+                    let file = File::new(format!(
+                        "Static output file {name:?} of testcase {testcase_id} from {path:?}"
+                    ));
+                    extra.push(file.uuid);
+                    eval.dag.provide_file(file, &path).with_context(|| {
+                        format!(
+                            "Failed to provide static output file from {}",
+                            path.display()
+                        )
+                    })?;
+                }
+                Ok((Some(uuid), extra, None))
             }
             OutputGenerator::Custom(source_file, args) => {
                 let mut exec = source_file
                     .execute(eval, description, args.clone())
                     .context("Failed to execute output generator source file")?;
                 let output = bind_exec_io!(exec, task, input, validation_handle);
-                Ok((Some(output.uuid), Some(exec)))
+                let extra = bind_extra_outputs(&mut exec, task);
+                exec.normalize_outputs(eval.dag.config_mut().normalize_generated_files);
+                Ok((Some(output.uuid), extra, Some(exec)))
+            }
+            OutputGenerator::StoredFile(key) => {
+                if !extra_output_files.is_empty() {
+                    bail!("Stored output files are not supported by tasks with extra output files");
+                }
+                let file = File::new(format!(
+                    "Stored output file of testcase {testcase_id}, subtask {subtask_id} with hash {key}"
+                ));
+                let uuid = file.uuid;
+                eval.dag.provide_stored_file(file, key.clone());
+                Ok((Some(uuid), vec![], None))
             }
         }
     }
 
     /// Add the generation of the output file to the DAG and the callbacks to the UI, returning the
-    /// handle to the output file.
+    /// handle to the output file and to the extra output files of the task.
     pub(crate) fn generate_and_bind(
         &self,
         task: &IOITask,
@@ -81,8 +125,8 @@ impl OutputGenerator {
         testcase_id: TestcaseId,
         input: FileUuid,
         validation_handle: Option<FileUuid>,
-    ) -> Result<Option<FileUuid>, Error> {
-        let (output, sol) = self.generate(
+    ) -> Result<(Option<FileUuid>, Vec<FileUuid>), Error> {
+        let (output, extra, sol) = self.generate(
             task,
             eval,
             format!("Generation of output file of testcase {testcase_id}, subtask {subtask_id}"),
@@ -93,6 +137,7 @@ impl OutputGenerator {
         )?;
         if let Some(mut sol) = sol {
             sol.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+            let command_line = sol.command_line();
             let mut group = sol.into_group();
             group.tag = Some(Tag::Generation.into());
             group.priority = GENERATION_PRIORITY - testcase_id as Priority;
@@ -106,7 +151,8 @@ impl OutputGenerator {
                 let result = &results[0];
                 if !result.status.is_success() {
                     let mut diagnostic =
-                        Diagnostic::error(format!("Failed to generate output {testcase_id}"));
+                        Diagnostic::error(format!("Failed to generate output {testcase_id}"))
+                            .with_note(format!("Command line: {command_line}"));
                     if let Some(stderr) = &result.stderr {
                         diagnostic = diagnostic.with_help_attachment(stderr.clone());
                     }
@@ -116,15 +162,67 @@ impl OutputGenerator {
             });
             eval.dag.add_execution_group(group);
         }
+        let path = task
+            .path
+            .join("output")
+            .join(format!("output{testcase_id}.txt"));
+        for (name, file) in task.task_type.extra_output_files().iter().zip(&extra) {
+            eval.dag
+                .write_file_to(*file, path.with_extension(name), false);
+        }
         if let Some(output) = output {
-            eval.dag.write_file_to(
-                output,
-                task.path
-                    .join("output")
-                    .join(format!("output{testcase_id}.txt")),
-                false,
-            );
+            eval.dag.write_file_to(output, path, false);
         }
+        Ok((output, extra))
+    }
+
+    /// Add to the DAG an execution of this output generator whose only purpose is measuring its
+    /// time on the testcase, for the relative time limit of the solutions. The output files are
+    /// not used otherwise. Return the handle to the output file, if the generator is executed.
+    pub(crate) fn time_and_bind(
+        &self,
+        task: &IOITask,
+        eval: &mut EvaluationData,
+        subtask_id: SubtaskId,
+        testcase_id: TestcaseId,
+        input: FileUuid,
+        validation_handle: Option<FileUuid>,
+    ) -> Result<Option<FileUuid>, Error> {
+        let (output, _, sol) = self.generate(
+            task,
+            eval,
+            format!(
+                "Timing of the official solution on testcase {testcase_id}, subtask {subtask_id}"
+            ),
+            subtask_id,
+            testcase_id,
+            input,
+            validation_handle,
+        )?;
+        let Some(mut sol) = sol else {
+            return Ok(None);
+        };
+        sol.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+        let command_line = sol.command_line();
+        let mut group = sol.into_group();
+        group.tag = Some(Tag::Generation.into());
+        group.priority = GENERATION_PRIORITY - testcase_id as Priority;
+        let sender = eval.sender.clone();
+        eval.dag.on_execution_done(&group.uuid, move |results| {
+            let result = &results[0];
+            if !result.status.is_success() {
+                let mut diagnostic = Diagnostic::error(format!(
+                    "Failed to time the official solution on testcase {testcase_id}"
+                ))
+                .with_note(format!("Command line: {command_line}"));
+                if let Some(stderr) = &result.stderr {
+                    diagnostic = diagnostic.with_help_attachment(stderr.clone());
+                }
+                sender.add_diagnostic(diagnostic)?;
+            }
+            Ok(())
+        });
+        eval.dag.add_execution_group(group);
         Ok(output)
     }
 }
@@ -1,11 +1,17 @@
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use task_maker_dag::{ExecutionStatus, FileUuid, Priority};
+use task_maker_dag::{ExecutionGroupUuid, ExecutionStatus, FileUuid, Priority};
 
+use crate::ioi::dag::task_type::{
+    bind_sanitizer_reports, prepare_sanitized_execution, SOLUTION_NOFILE_LIMIT,
+};
 use crate::ioi::{
-    Checker, IOITask, OutputGenerator, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY,
+    bind_extra_outputs, Checker, IOITask, OutputGenerator, ScoreManager, SubtaskId, TestcaseId,
+    EVALUATION_PRIORITY,
 };
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, bind_exec_io, EvaluationData, SourceFile, Tag};
@@ -18,6 +24,11 @@ pub struct BatchTypeData {
     pub output_generator: Option<OutputGenerator>,
     /// The checker to use for this task.
     pub checker: Checker,
+    /// The other output files the solution should write, besides the main output file. Each of
+    /// them is checked separately and the score of the testcase is the average of the scores of
+    /// all the output files.
+    #[serde(default)]
+    pub extra_output_files: Vec<PathBuf>,
 }
 
 /// Evaluate a solution in a task of Batch type.
@@ -29,11 +40,14 @@ pub fn evaluate(
     testcase_id: TestcaseId,
     source_file: &SourceFile,
     input: FileUuid,
+    generator_outputs: &[(PathBuf, FileUuid)],
     validation_handle: Option<FileUuid>,
     correct_output: Option<FileUuid>,
+    extra_correct_outputs: Vec<FileUuid>,
+    reference_output: Option<FileUuid>,
     score_manager: Arc<Mutex<ScoreManager>>,
     data: &BatchTypeData,
-) -> Result<(), Error> {
+) -> Result<ExecutionGroupUuid, Error> {
     let correct_output = correct_output.ok_or_else(|| anyhow!("Missing official solution"))?;
     let mut exec = source_file
         .execute(
@@ -48,8 +62,13 @@ pub fn evaluate(
         )
         .context("Failed to execute solution source file")?;
     let output = bind_exec_io!(exec, task, input, validation_handle);
+    let extra_outputs = bind_extra_outputs(&mut exec, task);
+    if extra_outputs.len() != extra_correct_outputs.len() {
+        bail!("Missing the official extra output files");
+    }
     let path = source_file.path.clone();
     let limits = exec.limits_mut();
+    limits.nofile(SOLUTION_NOFILE_LIMIT);
     if let Some(time_limit) = task.time_limit {
         limits.cpu_time(time_limit);
         limits.wall_time(time_limit * 1.5 + 1.0); // some margin
@@ -57,9 +76,14 @@ pub fn evaluate(
     if let Some(memory_limit) = task.memory_limit {
         limits.memory(memory_limit * 1024); // MiB -> KiB
     }
+    if let (Some(factor), Some(reference_output)) = (task.relative_time_limit, reference_output) {
+        exec.relative_time_limit(reference_output, factor);
+    }
+    prepare_sanitized_execution(source_file, &mut exec);
     let mut group = exec.into_group();
     group.tag = Some(Tag::Evaluation.into());
     group.priority = EVALUATION_PRIORITY - testcase_id as Priority;
+    bind_sanitizer_reports(eval, &group.uuid, source_file, subtask_id, testcase_id);
     bind_exec_callbacks!(
         eval,
         group.uuid,
@@ -72,11 +96,25 @@ pub fn evaluate(
         },
         path
     )?;
+    let names: Arc<Vec<String>> = Arc::new(
+        std::iter::once(task.outfile.as_deref().unwrap_or(Path::new("stdout")))
+            .chain(data.extra_output_files.iter().map(PathBuf::as_path))
+            .map(|path| path.display().to_string())
+            .collect(),
+    );
+    // Which of the output files the solution didn't create, known when the solution completes.
+    let missing: Arc<Mutex<Vec<bool>>> = Arc::new(Mutex::new(vec![false; names.len()]));
+    let missing_done = missing.clone();
+    let extra_output_files = data.extra_output_files.clone();
     let sender = eval.sender.clone();
     let score_manager_err = score_manager.clone();
     eval.dag.on_execution_done(&group.uuid, move |results| {
         assert_eq!(results.len(), 1);
         let result = &results[0];
+        let mut missing = missing_done.lock().unwrap();
+        for (index, path) in extra_output_files.iter().enumerate() {
+            missing[index + 1] = result.missing_outputs.contains(path);
+        }
         match result.status {
             ExecutionStatus::Success => Ok(()),
             _ => score_manager_err.lock().unwrap().score(
@@ -88,23 +126,110 @@ pub fn evaluate(
             ),
         }
     });
-    eval.dag.add_execution_group(group);
+    let group_uuid = eval.dag.add_execution_group(group);
 
-    let sender = eval.sender.clone();
-    data.checker.check_and_bind(
-        eval,
-        subtask_id,
-        testcase_id,
-        source_file.path.clone(),
-        input,
-        correct_output,
-        output.uuid,
-        move |score, message| {
-            score_manager
-                .lock()
-                .unwrap()
-                .score(subtask_id, testcase_id, score, message, sender)
-        },
-    )?;
-    Ok(())
+    // The files to check: the main output and then the extra output files, with their names.
+    let outputs = std::iter::once((None, correct_output, output.uuid)).chain(
+        data.extra_output_files
+            .iter()
+            .zip(extra_correct_outputs)
+            .zip(extra_outputs)
+            .map(|((name, correct), output)| (Some(name.clone()), correct, output)),
+    );
+    // The outcome of the checker on each output file, the testcase is scored when all of them are
+    // known.
+    let outcomes: Arc<Mutex<Vec<Option<(f64, String)>>>> =
+        Arc::new(Mutex::new(vec![None; names.len()]));
+    let subtask_name = task
+        .subtasks
+        .get(&subtask_id)
+        .and_then(|subtask| subtask.name.as_deref());
+    for (index, (name, correct_output, output)) in outputs.enumerate() {
+        let sender = eval.sender.clone();
+        let names = names.clone();
+        let outcomes = outcomes.clone();
+        let missing = missing.clone();
+        let score_manager = score_manager.clone();
+        data.checker.check_and_bind(
+            eval,
+            subtask_id,
+            subtask_name,
+            name.as_deref(),
+            testcase_id,
+            source_file.path.clone(),
+            input,
+            generator_outputs,
+            correct_output,
+            output,
+            move |score, message| {
+                let mut outcomes = outcomes.lock().unwrap();
+                outcomes[index] = Some((score, message));
+                let Some(outcomes) = outcomes.iter().cloned().collect::<Option<Vec<_>>>() else {
+                    return Ok(());
+                };
+                let missing = missing.lock().unwrap();
+                let (score, message) = combine_outcomes(&names, outcomes, &missing);
+                score_manager
+                    .lock()
+                    .unwrap()
+                    .score(subtask_id, testcase_id, score, message, sender)
+            },
+        )?;
+    }
+    Ok(group_uuid)
+}
+
+/// Combine the outcomes of the checker on all the output files of a testcase, in the same order of
+/// `names`. The score is the average of the scores, the output files in `missing` score zero
+/// whatever the checker said.
+fn combine_outcomes(
+    names: &[String],
+    outcomes: Vec<(f64, String)>,
+    missing: &[bool],
+) -> (f64, String) {
+    let outcomes = outcomes
+        .into_iter()
+        .zip(missing)
+        .map(|(outcome, &missing)| {
+            if missing {
+                (0.0, "Missing output file".to_string())
+            } else {
+                outcome
+            }
+        })
+        .collect_vec();
+    if outcomes.len() == 1 {
+        return outcomes.into_iter().next().unwrap();
+    }
+    let score = outcomes.iter().map(|(score, _)| score).sum::<f64>() / outcomes.len() as f64;
+    let message = names
+        .iter()
+        .zip(outcomes)
+        .map(|(name, (_, message))| format!("{name}: {message}"))
+        .join("; ");
+    (score, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combine_outcomes() {
+        let names = vec!["output.txt".to_string(), "second.txt".to_string()];
+        let outcomes = vec![(1.0, "Ok".to_string()), (0.5, "Partial".to_string())];
+        let (score, message) = combine_outcomes(&names, outcomes, &[false, false]);
+        assert_eq!(score, 0.75);
+        assert_eq!(message, "output.txt: Ok; second.txt: Partial");
+    }
+
+    #[test]
+    fn test_combine_outcomes_missing_output() {
+        let names = vec!["output.txt".to_string(), "second.txt".to_string()];
+        // the checker accepted the empty file in place of the missing one
+        let outcomes = vec![(1.0, "Ok".to_string()), (1.0, "Ok".to_string())];
+        let (score, message) = combine_outcomes(&names, outcomes, &[false, true]);
+        assert_eq!(score, 0.5);
+        assert_eq!(message, "output.txt: Ok; second.txt: Missing output file");
+    }
 }
@@ -1,9 +1,14 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Context, Error};
 use serde::{Deserialize, Serialize};
-use task_maker_dag::{ExecutionGroup, FileUuid, Priority};
+use task_maker_dag::{ExecutionGroup, ExecutionGroupUuid, FileUuid, Priority};
+use task_maker_lang::sanitizers::relax_limits;
 
+use crate::ioi::dag::task_type::{
+    bind_sanitizer_reports, prepare_sanitized_execution, SOLUTION_NOFILE_LIMIT,
+};
 use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, bind_exec_io, EvaluationData, SourceFile, Tag};
@@ -52,11 +57,12 @@ pub fn evaluate(
     testcase_id: TestcaseId,
     source_file: &SourceFile,
     input: FileUuid,
+    generator_outputs: &[(PathBuf, FileUuid)],
     validation_handle: Option<FileUuid>,
     _correct_output: Option<FileUuid>,
     score_manager: Arc<Mutex<ScoreManager>>,
     data: &CommunicationTypeData,
-) -> Result<(), Error> {
+) -> Result<ExecutionGroupUuid, Error> {
     let mut group = ExecutionGroup::new(format!(
         "Evaluation of {} on testcase {}, subtask {}",
         source_file.name(),
@@ -119,6 +125,7 @@ pub fn evaluate(
             sol_exec.stdout_redirect_path(&fifo_sol2man[process_index]);
         }
         let limits = sol_exec.limits_mut();
+        limits.nofile(SOLUTION_NOFILE_LIMIT);
         if let Some(time_limit) = task.time_limit {
             limits.cpu_time(time_limit);
             limits.wall_time(time_limit * 1.5 + 1.0); // some margin
@@ -126,6 +133,7 @@ pub fn evaluate(
         if let Some(memory_limit) = task.memory_limit {
             limits.memory(memory_limit * 1024); // MiB -> KiB
         }
+        prepare_sanitized_execution(source_file, &mut sol_exec);
         group.add_execution(sol_exec);
     }
 
@@ -150,7 +158,12 @@ pub fn evaluate(
     manager_exec.capture_stdout(Some(128));
     manager_exec.capture_stderr(Some(1024));
     bind_exec_io!(manager_exec, task, input, validation_handle);
+    for (name, file) in generator_outputs {
+        manager_exec.input(*file, name, false);
+    }
     let limits = manager_exec.limits_mut();
+    // the manager keeps open two FIFOs for each process
+    limits.nofile(SOLUTION_NOFILE_LIMIT + 2 * num_processes as u32);
     if let Some(time_limit) = task.time_limit {
         let cpu_time = (time_limit + 1.0) * num_processes as f64;
         let wall_time = cpu_time * 1.5 + 1.0; // some margin
@@ -160,6 +173,11 @@ pub fn evaluate(
     if let Some(memory_limit) = task.memory_limit {
         limits.memory(memory_limit * 1024); // MiB -> KiB
     }
+    if source_file.sanitize {
+        // the manager waits for the slower solutions
+        relax_limits(limits);
+    }
+    bind_sanitizer_reports(eval, &group.uuid, source_file, subtask_id, testcase_id);
     bind_exec_callbacks!(
         eval,
         group.uuid,
@@ -191,14 +209,22 @@ pub fn evaluate(
         };
         for (i, result) in results.iter().enumerate() {
             if !result.status.is_success() {
-                send_score(
-                    0.0,
-                    if i == num_processes {
-                        "Manager failed".to_string()
-                    } else {
-                        format!("{:?}", result.status)
-                    },
-                )?;
+                let message = if i == num_processes {
+                    // the manager may explain why it failed, e.g. it doesn't support the number of
+                    // processes
+                    let stderr = result
+                        .stderr
+                        .as_deref()
+                        .map(String::from_utf8_lossy)
+                        .unwrap_or_default();
+                    match stderr.trim() {
+                        "" => "Manager failed".to_string(),
+                        stderr => format!("Manager failed: {stderr}"),
+                    }
+                } else {
+                    format!("{:?}", result.status)
+                };
+                send_score(0.0, message)?;
                 return Ok(());
             }
         }
@@ -218,6 +244,5 @@ pub fn evaluate(
         send_score(score, message)
     });
     group.add_execution(manager_exec);
-    eval.dag.add_execution_group(group);
-    Ok(())
+    Ok(eval.dag.add_execution_group(group))
 }
@@ -1,9 +1,14 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Context, Error};
 use serde::{Deserialize, Serialize};
-use task_maker_dag::{ControllerSettings, ExecutionGroup, FileUuid, Priority};
+use task_maker_dag::{ControllerSettings, ExecutionGroup, ExecutionGroupUuid, FileUuid, Priority};
+use task_maker_lang::sanitizers::relax_limits;
 
+use crate::ioi::dag::task_type::{
+    bind_sanitizer_reports, prepare_sanitized_execution, SOLUTION_NOFILE_LIMIT,
+};
 use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY};
 use crate::ui::UIMessage;
 use crate::{bind_exec_callbacks, EvaluationData, SourceFile, Tag};
@@ -35,11 +40,12 @@ pub fn evaluate(
     testcase_id: TestcaseId,
     source_file: &SourceFile,
     input: FileUuid,
+    generator_outputs: &[(PathBuf, FileUuid)],
     validation_handle: Option<FileUuid>,
     _correct_output: Option<FileUuid>,
     score_manager: Arc<Mutex<ScoreManager>>,
     data: &InteractiveTypeData,
-) -> Result<(), Error> {
+) -> Result<ExecutionGroupUuid, Error> {
     let mut group = ExecutionGroup::new(format!(
         "Evaluation of {} on testcase {}, subtask {}",
         source_file.name(),
@@ -74,6 +80,9 @@ pub fn evaluate(
         "input.txt".into()
     };
     controller_exec.input(input, infile_name, false);
+    for (name, file) in generator_outputs {
+        controller_exec.input(*file, name, false);
+    }
 
     if let Some(file) = validation_handle {
         controller_exec.input(file, "wait_for_validation", false);
@@ -93,6 +102,10 @@ pub fn evaluate(
     if let Some(memory_limit) = data.controller_memory_limit {
         limits.memory(memory_limit * 1024); // MiB -> KiB
     }
+    if source_file.sanitize {
+        // the controller waits for the slower solution
+        relax_limits(limits);
+    }
     group.add_execution(controller_exec);
 
     let mut sol_exec = source_file
@@ -113,6 +126,7 @@ pub fn evaluate(
     sol_exec.stderr = task_maker_dag::ExecutionOutputBehaviour::Ignored;
 
     let sol_limits = sol_exec.limits_mut();
+    sol_limits.nofile(SOLUTION_NOFILE_LIMIT);
     if let Some(time_limit) = task.time_limit {
         sol_limits.cpu_time(time_limit);
         sol_limits.wall_time(
@@ -123,7 +137,9 @@ pub fn evaluate(
     if let Some(memory_limit) = task.memory_limit {
         sol_limits.memory(memory_limit * 1024); // MiB -> KiB
     }
+    prepare_sanitized_execution(source_file, &mut sol_exec);
     group.add_execution(sol_exec);
+    bind_sanitizer_reports(eval, &group.uuid, source_file, subtask_id, testcase_id);
 
     let path = source_file.path.clone();
     bind_exec_callbacks!(
@@ -193,6 +209,5 @@ pub fn evaluate(
         send_score(score, message)
     });
 
-    eval.dag.add_execution_group(group);
-    Ok(())
+    Ok(eval.dag.add_execution_group(group))
 }
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Error;
@@ -5,15 +6,21 @@ pub use batch::BatchTypeData;
 pub use communication::{CommunicationTypeData, UserIo};
 pub use interactive::InteractiveTypeData;
 use serde::{Deserialize, Serialize};
-use task_maker_dag::FileUuid;
+use task_maker_dag::{Execution, ExecutionGroupUuid, FileUuid};
+use task_maker_diagnostics::Diagnostic;
+use task_maker_lang::sanitizers::{find_sanitizer_report, relax_limits};
 
-use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId};
-use crate::{EvaluationData, SourceFile};
+use crate::ioi::{Checker, IOITask, ScoreManager, SubtaskId, TestcaseId, STDERR_CONTENT_LENGTH};
+use crate::{EvaluationData, SourceFile, UISender};
 
 mod batch;
 mod communication;
 mod interactive;
 
+/// The maximum number of file descriptors a solution can keep open during the evaluation. It leaves
+/// room for the files the runtimes of the interpreted languages open.
+pub(crate) const SOLUTION_NOFILE_LIMIT: u32 = 256;
+
 /// The type of the task. This changes the behavior of the solutions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskType {
@@ -31,8 +38,24 @@ pub enum TaskType {
 }
 
 impl TaskType {
+    /// The output files the solutions should write besides the main output file. Only batch tasks
+    /// can have them.
+    pub fn extra_output_files(&self) -> &[PathBuf] {
+        match self {
+            TaskType::Batch(data) => &data.extra_output_files,
+            _ => &[],
+        }
+    }
+
     /// Evaluate a solution on a testcase, eventually adding to the `ScoreManager` the result of the
-    /// evaluation. This will add both the execution as well as the checking to the DAG.
+    /// evaluation. This will add both the execution as well as the checking to the DAG, returning
+    /// the execution group of the solution, if any.
+    ///
+    /// `generator_outputs` are the other files written by the generator of the input file.
+    /// `extra_correct_outputs` are the official versions of the extra output files of the task, in
+    /// the same order of `extra_output_files`. `reference_output` is produced by the execution of
+    /// the official solution on this testcase, whose time is the reference of the relative time
+    /// limit of the task.
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn evaluate(
         &self,
@@ -42,11 +65,14 @@ impl TaskType {
         testcase_id: TestcaseId,
         source_file: &SourceFile,
         input: FileUuid,
+        generator_outputs: &[(PathBuf, FileUuid)],
         validation_handle: Option<FileUuid>,
         correct_output: Option<FileUuid>,
+        extra_correct_outputs: Vec<FileUuid>,
+        reference_output: Option<FileUuid>,
         score_manager: Arc<Mutex<ScoreManager>>,
-    ) -> Result<(), Error> {
-        match self {
+    ) -> Result<Option<ExecutionGroupUuid>, Error> {
+        let group = match self {
             TaskType::Batch(data) => batch::evaluate(
                 task,
                 eval,
@@ -54,11 +80,14 @@ impl TaskType {
                 testcase_id,
                 source_file,
                 input,
+                generator_outputs,
                 validation_handle,
                 correct_output,
+                extra_correct_outputs,
+                reference_output,
                 score_manager,
                 data,
-            ),
+            )?,
             TaskType::Communication(data) => communication::evaluate(
                 task,
                 eval,
@@ -66,11 +95,12 @@ impl TaskType {
                 testcase_id,
                 source_file,
                 input,
+                generator_outputs,
                 validation_handle,
                 correct_output,
                 score_manager,
                 data,
-            ),
+            )?,
             TaskType::Interactive(data) => interactive::evaluate(
                 task,
                 eval,
@@ -78,13 +108,15 @@ impl TaskType {
                 testcase_id,
                 source_file,
                 input,
+                generator_outputs,
                 validation_handle,
                 correct_output,
                 score_manager,
                 data,
-            ),
-            TaskType::None => Ok(()),
-        }
+            )?,
+            TaskType::None => return Ok(None),
+        };
+        Ok(Some(group))
     }
 
     /// Add to the DAG more executions based on the current task type.
@@ -96,7 +128,7 @@ impl TaskType {
                 Checker::Custom(checker) => {
                     checker.prepare(eval)?;
                 }
-                Checker::WhiteDiff => {}
+                Checker::WhiteDiff | Checker::TokenDiff { .. } => {}
             },
             TaskType::Communication(communication) => {
                 communication.manager.prepare(eval)?;
@@ -109,3 +141,43 @@ impl TaskType {
         Ok(())
     }
 }
+
+/// Relax the limits of an execution of a solution compiled with the sanitizers, and capture its
+/// stderr for looking for the reports of the sanitizers.
+pub(crate) fn prepare_sanitized_execution(source_file: &SourceFile, exec: &mut Execution) {
+    if source_file.sanitize {
+        relax_limits(exec.limits_mut());
+        exec.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+    }
+}
+
+/// Emit an error for each execution of the group whose stderr contains the report of a sanitizer,
+/// if the solution is compiled with the sanitizers.
+pub(crate) fn bind_sanitizer_reports(
+    eval: &mut EvaluationData,
+    group: &ExecutionGroupUuid,
+    source_file: &SourceFile,
+    subtask_id: SubtaskId,
+    testcase_id: TestcaseId,
+) {
+    if !source_file.sanitize {
+        return;
+    }
+    let name = source_file.name();
+    let sender = eval.sender.clone();
+    eval.dag.on_execution_done(group, move |results| {
+        for report in results
+            .iter()
+            .filter_map(|result| result.stderr.as_deref().and_then(find_sanitizer_report))
+        {
+            sender.add_diagnostic(
+                Diagnostic::error(format!(
+                    "The sanitizers found an error in {name} on testcase {testcase_id}, subtask \
+                    {subtask_id}"
+                ))
+                .with_help_attachment(report.into_bytes()),
+            )?;
+        }
+        Ok(())
+    });
+}
@@ -2,16 +2,16 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use itertools::Itertools;
-use task_maker_dag::ExecutionStatus;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream};
+use task_maker_dag::{CacheHitReason, ExecutionStatus};
+use termcolor::{Color, ColorSpec, StandardStream};
 
 use crate::ioi::ui_state::{SolutionEvaluationState, TestcaseEvaluationStatus, UIState};
 use crate::ioi::{
     IOITask, SolutionCheckOutcome, SolutionTestcaseEvaluationState, SubtaskId, TestcaseId,
 };
 use crate::ui::{
-    FinishUI as FinishUITrait, FinishUIUtils, UIExecutionStatus, BLUE, BOLD, GREEN, ORANGE, RED,
-    YELLOW,
+    color_choice, decode_output, FinishUI as FinishUITrait, FinishUIUtils, UIExecutionStatus, BLUE,
+    BOLD, GREEN, ORANGE, RED, YELLOW,
 };
 use crate::{cwrite, cwriteln, ScoreStatus};
 
@@ -33,7 +33,7 @@ pub struct FinishUI {
 impl FinishUITrait<UIState> for FinishUI {
     fn print(state: &UIState) {
         let mut ui = FinishUI {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
         };
         ui.print_task_info(state);
         if !state.compilations.is_empty() {
@@ -55,7 +55,12 @@ impl FinishUITrait<UIState> for FinishUI {
                 ui.print_subtask_checks_table(state);
             }
             ui.print_summary(state);
+            ui.print_ranking(state);
         }
+        if !state.diagnostic_cases.is_empty() {
+            ui.print_diagnostic_cases(state);
+        }
+        FinishUIUtils::new(&mut ui.stream).print_cache_stats(state.cache_stats.as_ref());
         FinishUIUtils::new(&mut ui.stream).print_diagnostic_messages(&state.diagnostics);
     }
 }
@@ -165,7 +170,7 @@ impl FinishUI {
                 if gen_failed {
                     let stderr = testcase.generation.as_ref().and_then(|g| g.stderr.as_ref());
                     if let Some(stderr) = stderr {
-                        let stderr = String::from_utf8_lossy(stderr);
+                        let stderr = decode_output(stderr);
                         if !stderr.trim().is_empty() {
                             cwriteln!(self, BOLD, "Generation stderr:");
                             println!("{}", stderr.trim());
@@ -175,7 +180,7 @@ impl FinishUI {
                 if val_failed {
                     let stderr = testcase.validation.as_ref().and_then(|g| g.stderr.as_ref());
                     if let Some(stderr) = stderr {
-                        let stderr = String::from_utf8_lossy(stderr);
+                        let stderr = decode_output(stderr);
                         if !stderr.trim().is_empty() {
                             cwriteln!(self, BOLD, "Validation stderr:");
                             println!("{}", stderr.trim());
@@ -257,12 +262,13 @@ impl FinishUI {
         print!("{tc_num:3}) ");
         let score_precision = Self::score_precision(&state.task);
         if let Some(score) = testcase.score {
+            let rounded = state.task.score_rounding.round(score, score_precision);
             if abs_diff_eq!(score, 1.0) {
-                cwrite!(self, GREEN, "[{:.prec$}]", score, prec = score_precision);
+                cwrite!(self, GREEN, "[{:.prec$}]", rounded, prec = score_precision);
             } else if abs_diff_eq!(score, 0.0) {
-                cwrite!(self, RED, "[{:.prec$}]", score, prec = score_precision);
+                cwrite!(self, RED, "[{:.prec$}]", rounded, prec = score_precision);
             } else {
-                cwrite!(self, YELLOW, "[{:.prec$}]", score, prec = score_precision);
+                cwrite!(self, YELLOW, "[{:.prec$}]", rounded, prec = score_precision);
             }
         } else {
             print!("[X.{:X<prec$}]", "", prec = score_precision);
@@ -299,9 +305,13 @@ impl FinishUI {
         print!(" {}", testcase.status.message());
         let mut was_killed = false;
         let mut was_cached = true;
+        let mut cache_hit = CacheHitReason::Exact;
         for res in testcase.results.iter().flatten() {
             was_killed |= res.was_killed;
             was_cached &= res.was_cached;
+            if res.cache_hit == Some(CacheHitReason::LimitExtended) {
+                cache_hit = CacheHitReason::LimitExtended;
+            }
         }
         for result in testcase.results.iter().flatten() {
             match &result.status {
@@ -315,7 +325,15 @@ impl FinishUI {
             print!(" (killed)");
         }
         if was_cached {
-            print!(" (from cache)");
+            print!(" (from cache: {cache_hit})");
+        }
+        if state.config.show_workers {
+            // cached executions never start, so they don't have a worker
+            if let Some(worker) = testcase.worker {
+                print!(" [{}]", state.worker_name(worker));
+            } else if was_cached && !testcase.results.is_empty() {
+                print!(" [cached]");
+            }
         }
         if FinishUI::is_ansi() {
             self.print_right(format!("[{name}]"));
@@ -336,6 +354,7 @@ impl FinishUI {
 
     fn print_summary(&mut self, state: &UIState) {
         let score_precision = state.task.score_precision;
+        let rounding = state.task.score_rounding;
         let column_width = score_precision + 4;
         cwriteln!(self, BLUE, "Summary");
         let max_len = FinishUIUtils::get_max_len(&state.evaluations);
@@ -344,13 +363,18 @@ impl FinishUI {
             self,
             BOLD,
             "{:>width$.prec$} | ",
-            state.max_score,
+            rounding.round(state.max_score, score_precision),
             width = column_width,
             prec = score_precision
         );
         for st_num in state.task.subtasks.keys().sorted() {
             let subtask = &state.task.subtasks[st_num];
-            cwrite!(self, BOLD, " {:^3.0} ", subtask.max_score);
+            cwrite!(
+                self,
+                BOLD,
+                " {:^3.0} ",
+                rounding.round(subtask.max_score, 0)
+            );
         }
         println!();
         for path in state.evaluations.keys().sorted() {
@@ -363,6 +387,7 @@ impl FinishUI {
                 width = max_len
             );
             if let Some(score) = eval.score {
+                let score = rounding.round(score, score_precision);
                 print!("{score:>column_width$.score_precision$} | ");
             } else if score_precision == 0 {
                 print!("{:>width$} | ", "X", width = column_width);
@@ -381,7 +406,7 @@ impl FinishUI {
                 let normalized_score = subtask.normalized_score;
                 if let (Some(score), Some(normalized_score)) = (score, normalized_score) {
                     let color = self.score_color(normalized_score);
-                    cwrite!(self, color, " {:^3.0} ", score);
+                    cwrite!(self, color, " {:^3.0} ", rounding.round(score, 0));
                 } else {
                     print!(" {:^3} ", "X");
                 }
@@ -428,6 +453,74 @@ impl FinishUI {
         println!();
     }
 
+    /// Print the solutions ranked by score and then by time, followed by the ones that did not
+    /// complete the evaluation.
+    fn print_ranking(&mut self, state: &UIState) {
+        let ranking = state.solution_ranking();
+        let max_len = FinishUIUtils::get_max_len(&state.evaluations);
+        let score_precision = state.task.score_precision;
+        cwriteln!(self, BLUE, "Ranking");
+        for (position, solution) in ranking.ranked.iter().enumerate() {
+            let name = solution
+                .path
+                .file_name()
+                .expect("Invalid file name")
+                .to_string_lossy();
+            print!("{:>3}) {name:<max_len$}  ", position + 1);
+            let color = self.score_color(solution.score / state.max_score);
+            cwrite!(
+                self,
+                color,
+                "{:>width$.score_precision$}",
+                solution.score,
+                width = score_precision + 4
+            );
+            println!("  {:>8.3}s", solution.total_time);
+        }
+        for (path, reason) in &ranking.not_completed {
+            let name = path
+                .file_name()
+                .expect("Invalid file name")
+                .to_string_lossy();
+            print!("  -) {name:<max_len$}  ");
+            cwriteln!(self, RED, "{}", reason);
+        }
+        println!();
+    }
+
+    /// Print the outcomes of the diagnostic cases of the solutions, which are not part of the
+    /// score.
+    fn print_diagnostic_cases(&mut self, state: &UIState) {
+        cwriteln!(self, BLUE, "Diagnostic cases");
+        for path in state.diagnostic_cases.keys().sorted() {
+            let name = path
+                .file_name()
+                .expect("Invalid file name")
+                .to_string_lossy();
+            cwriteln!(self, BOLD, "{}", name);
+            for (index, outcome) in &state.diagnostic_cases[path] {
+                print!("{index:3}) ");
+                let color = self.score_color(outcome.score);
+                cwrite!(self, color, "[{:.2}]", outcome.score);
+                print!(" Subtask #{}", outcome.subtask);
+                if let Some(name) = state
+                    .task
+                    .subtasks
+                    .get(&outcome.subtask)
+                    .and_then(|st| st.name.as_ref())
+                {
+                    print!(" [{name}]");
+                }
+                println!(
+                    ", arguments {:?}: {}",
+                    outcome.args.join(" "),
+                    outcome.message
+                );
+            }
+        }
+        println!();
+    }
+
     /// Print the score fraction of a solution using colors.
     fn print_score_frac(
         &mut self,
@@ -436,13 +529,14 @@ impl FinishUI {
         max_score: f64,
         task: &IOITask,
     ) {
+        let max_score = task.score_rounding.round(max_score, task.score_precision);
         if let (Some(normalized_score), Some(score)) = (normalized_score, score) {
             let color = self.score_color(normalized_score);
             cwrite!(
                 self,
                 color,
                 "{:.prec$} / {:.prec$}",
-                score,
+                task.score_rounding.round(score, task.score_precision),
                 max_score,
                 prec = task.score_precision
             );
@@ -319,8 +319,10 @@ pub(super) fn parse(
                     id,
                     input_generator: InputGenerator::StaticFile(samples.path(&config.name, i)),
                     output_generator: output_generator.clone(),
+                    input_validator: None,
                     input_file: None,
                     official_output_file: None,
+                    generator_output_files: vec![],
                 },
             );
         }
@@ -351,8 +353,10 @@ pub(super) fn parse(
                     id,
                     input_generator: InputGenerator::StaticFile(c.clone()),
                     output_generator: output_generator.clone(),
+                    input_validator: None,
                     input_file: None,
                     official_output_file: None,
+                    generator_output_files: vec![],
                 },
             );
         }
@@ -380,8 +384,10 @@ pub(super) fn parse(
                     id,
                     input_generator,
                     output_generator: output_generator.clone(),
+                    input_validator: None,
                     input_file: None,
                     official_output_file: None,
+                    generator_output_files: vec![],
                 },
             );
             Ok(())
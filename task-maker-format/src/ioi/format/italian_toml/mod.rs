@@ -8,16 +8,31 @@ use itertools::Itertools;
 use task_maker_lang::GraderMap;
 
 use super::italian_yaml::TaskYAML;
-use crate::ioi::italian_yaml::{ScoreTypeGroupParameters, TaskYAMLOrig, TM_ALLOW_DELETE_COOKIE};
+use crate::ioi::italian_yaml::{
+    check_relative_time_limit, is_tm_deletable, ScoreTypeGroupParameters, TaskYAMLOrig,
+    TM_ALLOW_DELETE_COOKIE,
+};
 use crate::ioi::sanity_checks::get_sanity_checks;
+use crate::ioi::scorer::detect_scorer;
 use crate::ioi::{
-    make_task_booklets, BatchTypeData, Checker, CommunicationTypeData, IOITask,
-    InteractiveTypeData, OutputGenerator, TaskType, TestcaseScoreAggregator,
+    make_task_booklets, parse_generator_outputs, BatchTypeData, Checker, CommunicationTypeData,
+    IOITask, InteractiveTypeData, OutputGenerator, TaskType, TestcaseScoreAggregator,
 };
 use crate::{find_source_file, list_files, EvaluationConfig, WriteBinTo};
 
 pub(in crate::ioi) mod gen_toml;
 
+/// The files that `parse_task` writes back inside the task directory when not in dry-run mode: the
+/// `task.yaml` generated from `task.toml`, unless it doesn't contain `TM_ALLOW_DELETE_COOKIE`.
+pub(crate) fn planned_writes(task_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let task_yaml_path = task_dir.join("task.yaml");
+    if !task_yaml_path.exists() || is_tm_deletable(&task_yaml_path)? {
+        Ok(vec![task_yaml_path])
+    } else {
+        Ok(vec![])
+    }
+}
+
 pub fn parse_task<P: AsRef<Path>>(
     task_dir: P,
     eval_config: &EvaluationConfig,
@@ -39,6 +54,7 @@ pub fn parse_task<P: AsRef<Path>>(
     let config = config_orig.into_task_yaml(task_dir);
 
     debug!("The config is {config:#?}");
+    check_relative_time_limit(&config)?;
 
     let map_file = |file: String| -> Option<PathBuf> {
         match file.as_ref() {
@@ -65,7 +81,7 @@ pub fn parse_task<P: AsRef<Path>>(
     } else if let Some(comm) = communication {
         comm
     } else {
-        parse_batch_task_data(task_dir, grader_map.clone())?
+        parse_batch_task_data(task_dir, &config, grader_map.clone())?
     };
 
     let (subtasks, testcases) = gen_toml::parse(task_dir, &config, &task_type, grader_map.clone())?;
@@ -74,8 +90,14 @@ pub fn parse_task<P: AsRef<Path>>(
     let testcase_score_aggregator = config.score_type.unwrap_or(TestcaseScoreAggregator::Min);
     config.score_type = Some(testcase_score_aggregator);
 
+    // task.toml takes precedence over task.yaml, which is generated from it unless it has been
+    // written by hand.
     let task_yaml_path = task_dir.join("task.yaml");
-    if !eval_config.dry_run {
+    if task_yaml_path.exists() && !is_tm_deletable(&task_yaml_path)? {
+        warn!(
+            "Both task.toml and task.yaml are present, using task.toml. The task.yaml file does not contain {TM_ALLOW_DELETE_COOKIE}. Won't overwrite"
+        );
+    } else if !eval_config.dry_run {
         config.score_type_parameters = Some(
             subtasks
                 .iter()
@@ -118,10 +140,19 @@ pub fn parse_task<P: AsRef<Path>>(
         title: config.title,
         time_limit: config.time_limit,
         memory_limit: config.memory_limit,
+        relative_time_limit: config.relative_time_limit,
+        compilation_time_limit: config.compilation_time_limit,
+        compilation_memory_limit: config.compilation_memory_limit,
+        generation_wall_time_limit: config.generation_wall_time_limit,
         infile,
         outfile,
+        generator_outputs: parse_generator_outputs(&config.generator_outputs)?,
+        normalize_files: config.normalize_files,
+        output_only: config.output_only,
         testcase_score_aggregator,
+        scorer: detect_scorer(task_dir).context("Failed to detect the scorer")?,
         score_precision: config.score_precision,
+        score_rounding: config.score_rounding,
         subtasks,
         testcases,
         grader_map,
@@ -171,7 +202,11 @@ fn detect_output_generator(task_dir: &Path, grader_map: Arc<GraderMap>) -> Resul
 }
 
 /// Parse the task components relative to the batch task type.
-fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<TaskType, Error> {
+fn parse_batch_task_data(
+    task_dir: &Path,
+    config: &TaskYAML,
+    grader_map: Arc<GraderMap>,
+) -> Result<TaskType, Error> {
     let mut checkers = find_source_file(
         task_dir,
         vec!["check/checker.*"],
@@ -184,9 +219,11 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
         let paths = checkers.iter().map(|s| s.name()).collect::<Vec<_>>();
         bail!("Multiple checkers found: {:?}", paths)
     }
-    let checker = checkers
-        .pop()
-        .map(|mut c| {
+    let checker = match (checkers.pop(), config.checker) {
+        (Some(_), Some(_)) => {
+            bail!("The checker in task.toml cannot be used together with a custom checker")
+        }
+        (Some(mut c), None) => {
             // Always copy the custom checker.
             c.copy_exe();
 
@@ -194,14 +231,21 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
             c.link_static();
 
             Checker::Custom(Arc::new(c))
-        })
-        .unwrap_or(Checker::WhiteDiff);
+        }
+        (None, Some(checker)) => checker.to_checker()?,
+        (None, None) => Checker::WhiteDiff,
+    };
 
     let official_solution = detect_output_generator(task_dir, grader_map)
         .context("Failed to detect output generator")?;
     Ok(TaskType::Batch(BatchTypeData {
         output_generator: Some(official_solution),
         checker,
+        extra_output_files: config
+            .extra_output_files
+            .iter()
+            .map(PathBuf::from)
+            .collect(),
     }))
 }
 
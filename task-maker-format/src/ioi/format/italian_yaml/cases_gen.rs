@@ -5,9 +5,9 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
-use anyhow::{anyhow, bail, ensure, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use pest::Parser;
-use task_maker_diagnostics::CodeSpan;
+use task_maker_diagnostics::{CodeSpan, Diagnostic};
 
 use crate::ioi::italian_yaml::{is_tm_deletable, TaskInputEntry, TM_ALLOW_DELETE_COOKIE};
 use crate::ioi::{
@@ -98,7 +98,7 @@ where
     /// The resulting `TaskInputEntry` that will be produced after the parsing of the `cases.gen`
     /// file.
     result: Vec<TaskInputEntry>,
-    /// The list of constraints found in the file.
+    /// The list of global constraints found in the file, checked against all the testcases.
     constraints: Vec<Constraint>,
     /// The list of additional constraints for the current subtask.
     subtask_constraints: Vec<Vec<Constraint>>,
@@ -124,6 +124,9 @@ where
     st_deps: HashMap<SubtaskId, Vec<String>>,
     /// The identifier of the next testcase to process.
     testcase_id: TestcaseId,
+    /// The textual representation and the variables of the testcases generated so far, used for
+    /// checking the global constraints defined after them.
+    testcase_variables: Vec<(String, HashMap<String, String>)>,
 }
 
 impl<OutGen> CasesGen<OutGen>
@@ -165,6 +168,7 @@ where
             st_name_to_id: HashMap::new(),
             st_deps: HashMap::new(),
             testcase_id: 0,
+            testcase_variables: vec![],
         };
 
         for line in file.into_inner() {
@@ -183,7 +187,14 @@ where
                             cases.parse_command(command)?;
                         }
                         parser::Rule::testcase => {
-                            cases.parse_testcase(line.as_str(), cases.current_generator.clone())?;
+                            let mut line = line.into_inner();
+                            let args = line.next().ok_or_else(|| anyhow!("Corrupted parser"))?;
+                            let validator = testcase_validator(line.next())?;
+                            cases.parse_testcase(
+                                args.as_str(),
+                                cases.current_generator.clone(),
+                                validator,
+                            )?;
                         }
                         parser::Rule::comment => {}
                         parser::Rule::empty => {}
@@ -266,6 +277,9 @@ where
                         InputGenerator::Custom(source, args) => {
                             let _ = writeln!(gen, "{} {}", source.name(), args.join(" "));
                         }
+                        InputGenerator::StoredFile(key) => {
+                            let _ = writeln!(gen, "# Stored input file {key}");
+                        }
                     }
                 }
             }
@@ -286,13 +300,21 @@ where
                     .context("Failed to parse VAL command")?;
             }
             parser::Rule::CONSTRAINT => {
-                self.parse_constraint(line)
+                self.parse_constraint(line, false)
                     .context("Failed to parse CONSTRAINT command")?;
             }
+            parser::Rule::GLOBAL_CONSTRAINT => {
+                self.parse_constraint(line, true)
+                    .context("Failed to parse GLOBAL_CONSTRAINT command")?;
+            }
             parser::Rule::SUBTASK => {
                 self.parse_subtask(line)
                     .context("Failed to parse SUBTASK command")?;
             }
+            parser::Rule::STVAL => {
+                self.parse_st_val(line)
+                    .context("Failed to parse STVAL command")?;
+            }
             parser::Rule::STDEP => {
                 self.parse_st_dep(line)
                     .context("Failed to parse STDEP command")?;
@@ -301,6 +323,10 @@ where
                 self.parse_copy(line)
                     .context("Failed to parse COPY command")?;
             }
+            parser::Rule::RUN_FROM => {
+                self.parse_run_from(line)
+                    .context("Failed to parse RUN_FROM command")?;
+            }
             parser::Rule::RUN => {
                 self.parse_run(line)
                     .context("Failed to parse RUN command")?;
@@ -310,46 +336,57 @@ where
         Ok(())
     }
 
-    /// Parse a raw testcase, a line not starting with `:`.
+    /// Parse a raw testcase, a line not starting with `:`. If `validator` is set, it overrides the
+    /// validator of the subtask for this testcase only.
     fn parse_testcase(
         &mut self,
         line: &str,
         current_generator: Option<String>,
+        validator: Option<&str>,
     ) -> Result<(), Error> {
         if self.subtask_id == 0 {
             bail!("Cannot add a testcase outside a subtask");
         }
+        let args = shell_words::split(line)
+            .with_context(|| format!("Invalid command arguments for testcase '{line}'"))?;
+        self.add_testcase(line, args, current_generator, validator)
+    }
+
+    /// Add a testcase generated by the current generator with the specified arguments, checking
+    /// the constraints. `line` is the textual representation of the testcase, used in the errors.
+    fn add_testcase(
+        &mut self,
+        line: &str,
+        args: Vec<String>,
+        current_generator: Option<String>,
+        validator: Option<&str>,
+    ) -> Result<(), Error> {
         let current_generator = if let Some(gen) = current_generator {
             gen
         } else {
             bail!("Cannot generate testcase: no default generator set");
         };
-        let args = shell_words::split(line)
-            .with_context(|| format!("Invalid command arguments for testcase '{line}'"))?;
         let generator = &self
             .generators
             .get(&current_generator)
             .context("invalid current generator")?;
         let variables = self.get_variables(&generator.args, &args);
-        for constr in self
-            .constraints
-            .iter()
-            .chain(self.subtask_constraints.last().unwrap())
-        {
-            if let Some(false) = constr.is_valid(&variables) {
-                let mut error = format!("Testcase '{line}' violates constraint {constr:?}\nWith:");
-                for (var, val) in &variables {
-                    let _ = write!(error, "\n  ${var} = {val}");
-                }
-                bail!("{}", error)
-            }
+        for constr in &self.constraints {
+            constr.check(line, &variables, "global")?;
+        }
+        let scope = format!("subtask {}", self.subtask_id - 1);
+        for constr in self.subtask_constraints.last().unwrap() {
+            constr.check(line, &variables, &scope)?;
         }
         let generator = InputGenerator::Custom(generator.source.clone(), args);
-        self.result.push(TaskInputEntry::Testcase(TestcaseInfo::new(
+        let mut testcase = TestcaseInfo::new(
             self.testcase_id,
             generator,
             (self.get_output_gen)(self.testcase_id),
-        )));
+        );
+        testcase.input_validator = self.get_testcase_validator(validator, &variables)?;
+        self.testcase_variables.push((line.to_string(), variables));
+        self.result.push(TaskInputEntry::Testcase(testcase));
         self.testcase_id += 1;
         Ok(())
     }
@@ -450,8 +487,9 @@ where
         Ok(())
     }
 
-    /// Parse a `:CONSTRAINT` command.
-    fn parse_constraint(&mut self, line: Pair) -> Result<(), Error> {
+    /// Parse a `:CONSTRAINT` or a `:GLOBAL_CONSTRAINT` command. A global constraint is also checked
+    /// against the testcases defined before it.
+    fn parse_constraint(&mut self, line: Pair, global: bool) -> Result<(), Error> {
         let line_str = line.as_str().to_string();
         let line: Vec<_> = line.into_inner().collect();
         let mut constraint = Constraint::default();
@@ -504,8 +542,13 @@ where
         if constraint.operands.len() < 2 {
             bail!("Malformed constraint: too few operands");
         }
+        if global {
+            for (line, variables) in &self.testcase_variables {
+                constraint.check(line, variables, "global")?;
+            }
+        }
         // subtask_id = 0 means no subtask has been defined yet, so this constraint is global
-        if self.subtask_id == 0 {
+        if global || self.subtask_id == 0 {
             self.constraints.push(constraint);
         } else {
             self.subtask_constraints
@@ -539,9 +582,32 @@ where
             .as_deref()
             .map(|s| s.chars().filter(|&c| c != ' ' && c != '\t').collect());
         self.subtask_name.clone_from(&name);
+        let code_span = CodeSpan::from_str(
+            &self.file_path,
+            &self.file_content,
+            span.start(),
+            span.end() - span.start(),
+        )
+        .ok();
         if let Some(name) = &name {
             let old_id = self.st_name_to_id.insert(name.clone(), self.subtask_id);
-            ensure!(old_id.is_none(), "Duplicate subtask name '{}'", name);
+            if old_id.is_some() {
+                let mut diagnostic = Diagnostic::error(format!("Duplicate subtask name '{name}'"));
+                let first_span = self.result.iter().find_map(|entry| match entry {
+                    TaskInputEntry::Subtask(subtask) if subtask.name.as_ref() == Some(name) => {
+                        subtask.span.clone()
+                    }
+                    _ => None,
+                });
+                if let Some(first_span) = first_span {
+                    diagnostic =
+                        diagnostic.with_labeled_code_span(first_span, "first defined here");
+                }
+                if let Some(code_span) = code_span {
+                    diagnostic = diagnostic.with_labeled_code_span(code_span, "redefined here");
+                }
+                return Err(diagnostic.into());
+            }
         }
         self.subtask_id += 1;
         self.result.push(TaskInputEntry::Subtask(
@@ -551,13 +617,7 @@ where
                 name,
                 description,
                 max_score: score,
-                span: CodeSpan::from_str(
-                    &self.file_path,
-                    &self.file_content,
-                    span.start(),
-                    span.end() - span.start(),
-                )
-                .ok(),
+                span: code_span,
                 is_default: false,
                 input_validator: self.get_validator(
                     self.default_validator.as_deref(),
@@ -569,6 +629,24 @@ where
         Ok(())
     }
 
+    /// Parse a `:STVAL` command, setting the validator of all the inputs of the subtask at once.
+    fn parse_st_val(&mut self, line: Pair) -> Result<(), Error> {
+        let name = line
+            .into_inner()
+            .next()
+            .context("corrupted parser")?
+            .as_str();
+        let Some(validator) = self.validators.get(name) else {
+            bail!("unknown validator '{}'", name);
+        };
+        let validator = InputValidator::Custom(validator.source.clone(), vec![]);
+        let Some(TaskInputEntry::Subtask(subtask)) = self.result.last_mut() else {
+            bail!("The subtask validator must be set directly after a subtask");
+        };
+        subtask.subtask_validator = validator;
+        Ok(())
+    }
+
     /// Parse a `:STDEP` command.
     fn parse_st_dep(&mut self, line: Pair) -> Result<(), Error> {
         for dep in line.into_inner() {
@@ -588,11 +666,9 @@ where
         if self.subtask_id == 0 {
             bail!("Cannot add a COPY testcase outside a subtask");
         }
-        let path = line
-            .into_inner()
-            .next()
-            .context("corrupted parser")?
-            .as_str();
+        let mut line = line.into_inner();
+        let path = line.next().context("corrupted parser")?.as_str().trim_end();
+        let validator = testcase_validator(line.next())?;
         let path = self.task_dir.join(path);
         if !path.exists() {
             bail!(
@@ -600,15 +676,33 @@ where
                 path.display()
             );
         }
-        self.result.push(TaskInputEntry::Testcase(TestcaseInfo::new(
+        let mut testcase = TestcaseInfo::new(
             self.testcase_id,
             InputGenerator::StaticFile(path),
             (self.get_output_gen)(self.testcase_id),
-        )));
+        );
+        testcase.input_validator =
+            self.get_testcase_validator(validator, &self.get_auto_variables())?;
+        self.result.push(TaskInputEntry::Testcase(testcase));
         self.testcase_id += 1;
         Ok(())
     }
 
+    /// Get the validator that overrides the one of the subtask for a single testcase, if any.
+    fn get_testcase_validator(
+        &self,
+        validator: Option<&str>,
+        variables: &HashMap<String, String>,
+    ) -> Result<Option<InputValidator>, Error> {
+        let Some(validator) = validator else {
+            return Ok(None);
+        };
+        let validator = self
+            .get_validator(Some(validator), variables)
+            .context("Failed to get the validator of the testcase")?;
+        Ok(Some(validator))
+    }
+
     /// Get the current validator for the next testcase.
     fn get_validator(
         &self,
@@ -649,10 +743,38 @@ where
         let line: Vec<_> = line.into_inner().collect();
         let name = line[0].as_str();
         let args = line[1].as_str();
+        let validator = testcase_validator(line.get(2).cloned())?;
         if !self.generators.contains_key(name) {
             bail!("Generator '{}' not declared", name);
         }
-        self.parse_testcase(args, Some(name.into()))?;
+        self.parse_testcase(args, Some(name.into()), validator)?;
+        Ok(())
+    }
+
+    /// Parse a `:RUN_FROM` command: every row of the file is a testcase, generated by the current
+    /// generator with the values of the row as arguments.
+    ///
+    /// Since the arguments are part of the command of the generator, changing a row of the file
+    /// invalidates the cache of that testcase.
+    fn parse_run_from(&mut self, line: Pair) -> Result<(), Error> {
+        if self.subtask_id == 0 {
+            bail!("Cannot add a testcase outside a subtask");
+        }
+        let path = line
+            .into_inner()
+            .next()
+            .context("corrupted parser")?
+            .as_str()
+            .trim_end();
+        let path = self.task_dir.join(path);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let rows = parse_generator_args(&path, &content)?;
+        for (row, args) in rows {
+            let line = shell_words::join(&args);
+            self.add_testcase(&line, args, self.current_generator.clone(), None)
+                .with_context(|| format!("Invalid row {row} of {}", path.display()))?;
+        }
         Ok(())
     }
 
@@ -691,6 +813,95 @@ impl ConstraintOperator {
     }
 }
 
+/// Parse the rows of a file with the arguments of the generator, returning the row numbers (starting
+/// from 1) and the arguments. The file is either a JSON array of rows (`.json`), each one being an
+/// array of values, or a CSV file with one row per line.
+fn parse_generator_args(path: &Path, content: &str) -> Result<Vec<(usize, Vec<String>)>, Error> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        let rows: Vec<serde_json::Value> = serde_json::from_str(content)
+            .with_context(|| format!("{} is not a JSON array of rows", path.display()))?;
+        rows.into_iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let row_number = index + 1;
+                let serde_json::Value::Array(values) = row else {
+                    bail!(
+                        "Invalid row {row_number} of {}: expecting an array of values",
+                        path.display()
+                    );
+                };
+                let args = values
+                    .into_iter()
+                    .map(|value| match value {
+                        serde_json::Value::String(s) => Ok(s),
+                        serde_json::Value::Number(n) => Ok(n.to_string()),
+                        serde_json::Value::Bool(b) => Ok(b.to_string()),
+                        value => bail!(
+                            "Invalid row {row_number} of {}: unsupported value {value}",
+                            path.display()
+                        ),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok((row_number, args))
+            })
+            .collect()
+    } else {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let args = split_csv_row(line)
+                    .with_context(|| format!("Invalid row {} of {}", index + 1, path.display()))?;
+                Ok((index + 1, args))
+            })
+            .collect()
+    }
+}
+
+/// Split a row of a CSV file in its fields. The fields can be quoted with `"`, and inside a quoted
+/// field `""` is an escaped quote.
+fn split_csv_row(row: &str) -> Result<Vec<String>, Error> {
+    let mut fields = vec![];
+    let mut chars = row.chars().peekable();
+    loop {
+        while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => bail!("Unterminated quoted field"),
+                }
+            }
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if !matches!(chars.peek(), None | Some(',')) {
+                bail!("Unexpected character after a quoted field");
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+/// Extract the name of the validator from the `: VAL name` suffix of a testcase line, if present.
+fn testcase_validator(pair: Option<Pair>) -> Result<Option<&str>, Error> {
+    let Some(pair) = pair else {
+        return Ok(None);
+    };
+    let name = pair.into_inner().next().context("corrupted parser")?;
+    Ok(Some(name.as_str()))
+}
+
 impl FromStr for ConstraintOperator {
     type Err = Error;
 
@@ -760,6 +971,20 @@ impl Constraint {
         }
         Some(true)
     }
+
+    /// Fail if the variables of the testcase `line` violate this constraint. `scope` tells where
+    /// the constraint comes from (e.g. `global` or `subtask 2`), and is included in the error.
+    fn check(&self, line: &str, vars: &HashMap<String, String>, scope: &str) -> Result<(), Error> {
+        if let Some(false) = self.is_valid(vars) {
+            let mut error =
+                format!("Testcase '{line}' violates constraint {self:?} ({scope})\nWith:");
+            for (var, val) in vars {
+                let _ = write!(error, "\n  ${var} = {val}");
+            }
+            bail!("{}", error)
+        }
+        Ok(())
+    }
 }
 
 impl Debug for Constraint {
@@ -819,6 +1044,12 @@ mod tests {
             self
         }
 
+        fn write_file<P: AsRef<Path>>(&self, path: P, content: &str) -> &Self {
+            self.add_file(&path);
+            std::fs::write(self.0.path().join(path), content).unwrap();
+            self
+        }
+
         fn cases_gen<S: AsRef<str>>(
             &self,
             content: S,
@@ -1240,6 +1471,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_add_subtask_duplicate_name() {
+        let gen = TestHelper::new().cases_gen(":SUBTASK 42 the-name\n:SUBTASK 58 the-name");
+        assert!(gen.is_err());
+        let error = gen.unwrap_err();
+        assert_that(&error).has_error("Duplicate subtask name 'the-name'");
+        assert_that(&error).has_error("first defined here");
+        assert_that(&error).has_error("redefined here");
+    }
+
     #[test]
     fn test_add_subtask_space_in_name() {
         let gen = TestHelper::new()
@@ -1328,6 +1569,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_set_subtask_wide_validator() {
+        let gen = TestHelper::new()
+            .add_file("gen/val.py")
+            .add_file("gen/distinct.py")
+            .cases_gen(":VAL default gen/val.py\n:VAL distinct gen/distinct.py\n:SUBTASK 42\n:STVAL distinct")
+            .unwrap();
+        let TaskInputEntry::Subtask(subtask) = &gen.result[0] else {
+            panic!("Expecting a subtask, got: {:?}", gen.result[0]);
+        };
+        let InputValidator::Custom(source, args) = &subtask.subtask_validator else {
+            panic!(
+                "Expecting a custom validator, got: {:?}",
+                subtask.subtask_validator
+            );
+        };
+        assert_eq!(source.name(), "distinct.py");
+        assert!(args.is_empty());
+        // the validator of the single inputs is still the default one
+        let InputValidator::Custom(source, _) = &subtask.input_validator else {
+            panic!(
+                "Expecting a custom validator, got: {:?}",
+                subtask.input_validator
+            );
+        };
+        assert_eq!(source.name(), "val.py");
+    }
+
+    #[test]
+    fn test_set_subtask_wide_validator_outside_subtask() {
+        let gen = TestHelper::new()
+            .add_file("gen/distinct.py")
+            .cases_gen(":VAL distinct gen/distinct.py\n:STVAL distinct");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("directly after a subtask");
+    }
+
+    #[test]
+    fn test_set_subtask_wide_validator_unknown() {
+        let gen = TestHelper::new().cases_gen(":SUBTASK 42\n:STVAL lolnope");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("unknown validator");
+    }
+
     /**********************
      * : COPY
      *********************/
@@ -1575,6 +1860,43 @@ mod tests {
         assert_that(&gen.unwrap_err()).has_error("violates constraint");
     }
 
+    #[test]
+    fn test_testcase_invalid_subtask_constraints() {
+        let gen = TestHelper::new().add_file("gen/generator.py").cases_gen(
+            ":GEN default gen/generator.py N M\n:SUBTASK 42\n:CONSTRAINT $N < 10\n1 2\n:SUBTASK 58\n:CONSTRAINT $N < 5\n10 2",
+        );
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("violates constraint $N < 5 (subtask 1)");
+    }
+
+    #[test]
+    fn test_testcase_global_constraints() {
+        let gen = TestHelper::new().add_file("gen/generator.py").cases_gen(
+            ":GEN default gen/generator.py N M\n:SUBTASK 42\n1 2\n:GLOBAL_CONSTRAINT $N < $M\n:SUBTASK 58\n3 4",
+        );
+        let gen = gen.unwrap();
+        assert_eq!(gen.constraints.len(), 1);
+        assert!(gen.subtask_constraints.iter().all(|c| c.is_empty()));
+    }
+
+    #[test]
+    fn test_testcase_invalid_global_constraints() {
+        let gen = TestHelper::new().add_file("gen/generator.py").cases_gen(
+            ":GEN default gen/generator.py N M\n:SUBTASK 42\n:GLOBAL_CONSTRAINT $N < $M\n:CONSTRAINT $N < 10\n5 1",
+        );
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("violates constraint $N < $M (global)");
+    }
+
+    #[test]
+    fn test_testcase_invalid_global_constraints_after_testcase() {
+        let gen = TestHelper::new().add_file("gen/generator.py").cases_gen(
+            ":GEN default gen/generator.py N M\n:SUBTASK 42\n5 1\n:SUBTASK 58\n:GLOBAL_CONSTRAINT $N < $M",
+        );
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("Testcase '5 1' violates constraint");
+    }
+
     #[test]
     fn test_testcase_uncheckable_constraints() {
         let gen = TestHelper::new().add_file("gen/generator.py").cases_gen(
@@ -1607,4 +1929,181 @@ mod tests {
         assert!(gen.is_err());
         assert_that(&gen.unwrap_err()).has_error("outside a subtask");
     }
+
+    /**********************
+     * testcase : VAL
+     *********************/
+
+    /// Extract the testcases from the result of the parsing.
+    fn testcases<O>(gen: &CasesGen<O>) -> Vec<&crate::ioi::TestcaseInfo>
+    where
+        O: Fn(TestcaseId) -> OutputGenerator,
+    {
+        gen.result
+            .iter()
+            .filter_map(|entry| match entry {
+                TaskInputEntry::Testcase(testcase) => Some(testcase),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_testcase_validator_override() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .add_file("gen/val.py")
+            .add_file("gen/special.py")
+            .cases_gen(
+                ":GEN default gen/gen.py\n:VAL default gen/val.py\n:VAL special gen/special.py\n\
+                 :SUBTASK 42\n1 2 3 : VAL special\n4 5 6",
+            )
+            .unwrap();
+        let testcases = testcases(&gen);
+        assert_eq!(testcases.len(), 2);
+        if let InputGenerator::Custom(_, args) = &testcases[0].input_generator {
+            assert_eq!(args, &vec!["1", "2", "3"]);
+        } else {
+            panic!(
+                "Expecting a custom generator, got: {:?}",
+                testcases[0].input_generator
+            );
+        }
+        if let Some(InputValidator::Custom(source, args)) = &testcases[0].input_validator {
+            assert_eq!(source.name(), "special.py");
+            assert_eq!(args, &vec![TM_VALIDATION_FILE_NAME, "0"]);
+        } else {
+            panic!(
+                "Expecting a custom validator, got: {:?}",
+                testcases[0].input_validator
+            );
+        }
+        // the override does not leak to the following testcases
+        assert!(testcases[1].input_validator.is_none());
+    }
+
+    #[test]
+    fn test_testcase_validator_override_run_copy() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .add_file("gen/special.py")
+            .add_file("example.in")
+            .cases_gen(
+                ":GEN gen gen/gen.py\n:VAL special gen/special.py\n:SUBTASK 42\n\
+                 :RUN gen 1 2 : VAL special\n:COPY example.in : VAL special\n:COPY example.in",
+            )
+            .unwrap();
+        let testcases = testcases(&gen);
+        assert_eq!(testcases.len(), 3);
+        assert!(testcases[0].input_validator.is_some());
+        assert!(testcases[1].input_validator.is_some());
+        assert!(testcases[2].input_validator.is_none());
+        if let InputGenerator::StaticFile(path) = &testcases[1].input_generator {
+            assert!(path.ends_with("example.in"));
+        } else {
+            panic!(
+                "Expecting a static file, got: {:?}",
+                testcases[1].input_generator
+            );
+        }
+    }
+
+    #[test]
+    fn test_testcase_validator_override_unknown() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .cases_gen(":GEN default gen/gen.py\n:SUBTASK 42\n1 2 3 : VAL lolnope");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("unknown validator 'lolnope'");
+    }
+
+    /**********************
+     * : RUN_FROM
+     *********************/
+
+    /// Extract the arguments of the generators of the testcases.
+    fn testcase_args<O>(gen: &CasesGen<O>) -> Vec<Vec<String>>
+    where
+        O: Fn(TestcaseId) -> OutputGenerator,
+    {
+        testcases(gen)
+            .into_iter()
+            .map(|testcase| match &testcase.input_generator {
+                InputGenerator::Custom(_, args) => args.clone(),
+                gen => panic!("Expecting a custom generator, got: {gen:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_run_from_csv() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .write_file(
+                "gen/params.csv",
+                "1,2\n\n 3 , \"4, 5\"\n\"a \"\"b\"\"\",c\n",
+            )
+            .cases_gen(":GEN default gen/gen.py\n:SUBTASK 42\n: RUN_FROM gen/params.csv")
+            .unwrap();
+        assert_eq!(gen.testcase_id, 3);
+        assert_eq!(
+            testcase_args(&gen),
+            vec![vec!["1", "2"], vec!["3", "4, 5"], vec!["a \"b\"", "c"]]
+        );
+    }
+
+    #[test]
+    fn test_run_from_json() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .write_file("gen/params.json", r#"[[1, "2"], ["3 4", true]]"#)
+            .cases_gen(":GEN default gen/gen.py\n:SUBTASK 42\n:RUN_FROM gen/params.json")
+            .unwrap();
+        assert_eq!(
+            testcase_args(&gen),
+            vec![vec!["1", "2"], vec!["3 4", "true"]]
+        );
+    }
+
+    #[test]
+    fn test_run_from_malformed_row() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .write_file("gen/params.csv", "1,2\n3,\"4\n")
+            .cases_gen(":GEN default gen/gen.py\n:SUBTASK 42\n:RUN_FROM gen/params.csv");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("Invalid row 2");
+
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .write_file("gen/params.json", r#"[[1], {"a": 2}]"#)
+            .cases_gen(":GEN default gen/gen.py\n:SUBTASK 42\n:RUN_FROM gen/params.json");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("Invalid row 2");
+    }
+
+    #[test]
+    fn test_run_from_constraints() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .write_file("gen/params.csv", "1,2\n\n5,3\n")
+            .cases_gen(
+                ":GEN default gen/gen.py N M\n:CONSTRAINT $N < $M\n:SUBTASK 42\n\
+                 :RUN_FROM gen/params.csv",
+            );
+        assert!(gen.is_err());
+        let err = gen.unwrap_err();
+        assert_that(&err).has_error("Invalid row 3");
+        assert_that(&err).has_error("violates constraint");
+    }
+
+    #[test]
+    fn test_run_from_no_subtask() {
+        let gen = TestHelper::new()
+            .add_file("gen/gen.py")
+            .write_file("gen/params.csv", "1,2\n")
+            .cases_gen(":GEN default gen/gen.py\n:RUN_FROM gen/params.csv");
+        assert!(gen.is_err());
+        assert_that(&gen.unwrap_err()).has_error("outside a subtask");
+    }
 }
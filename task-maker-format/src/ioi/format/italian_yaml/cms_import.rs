@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Error};
+use itertools::Itertools;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::ioi::format::italian_yaml::{
+    default_infile, default_outfile, ScoreTypeGroupParameters, TaskYAML,
+};
+use crate::ioi::{ScoreRounding, TestcaseScoreAggregator, UserIo};
+
+/// The scores of the subtasks in gen/GEN must be integers, the scores that differ less than this
+/// from an integer are rounded.
+const EPSILON: f64 = 1e-6;
+
+/// The task.yaml of a task exported from cms.
+///
+/// Only the options that have an equivalent in task-maker are listed here, all the others are
+/// collected in `other` and dropped.
+#[derive(Debug, Deserialize)]
+struct CmsTaskYAML {
+    /// The name of the task (the short one).
+    #[serde(alias = "nome_breve")]
+    name: String,
+    /// The title of the task (the long one).
+    #[serde(alias = "nome")]
+    title: String,
+    /// The time limit for the execution of the solutions.
+    #[serde(alias = "timeout")]
+    time_limit: Option<f64>,
+    /// The memory limit in MiB of the execution of the solution.
+    #[serde(alias = "memlimit")]
+    memory_limit: Option<u64>,
+    /// The number of testcases of the task.
+    n_input: Option<usize>,
+    /// The name of the score type of cms, like `GroupMin`.
+    #[serde(default = "default_score_type")]
+    score_type: String,
+    /// The parameters of the score type, their format depends on the score type.
+    score_type_parameters: Option<serde_yaml::Value>,
+    /// The number of decimal digits when displaying the scores.
+    #[serde(default)]
+    score_precision: usize,
+    /// The primary language of the statement.
+    primary_language: Option<String>,
+    /// Whether this is an output only task.
+    #[serde(default)]
+    output_only: bool,
+    /// The input file for the solutions, empty for stdin.
+    #[serde(default = "default_infile")]
+    infile: String,
+    /// The output file for the solutions, empty for stdout.
+    #[serde(default = "default_outfile")]
+    outfile: String,
+    /// Compatibility with cms, kept as is.
+    score_mode: Option<String>,
+    /// Compatibility with cms, kept as is.
+    token_mode: Option<String>,
+    /// Compatibility with cms, kept as is.
+    public_testcases: Option<String>,
+    /// Compatibility with cms, kept as is.
+    feedback_level: Option<String>,
+    /// All the other options, without an equivalent in task-maker.
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// A group of testcases of a cms task, it becomes a subtask.
+#[derive(Debug, Clone, PartialEq)]
+struct CmsGroup {
+    /// The maximum score of the group.
+    max_score: f64,
+    /// The indices of the cms testcases inside this group.
+    testcases: Vec<usize>,
+}
+
+/// The default score type of cms.
+fn default_score_type() -> String {
+    "Sum".into()
+}
+
+/// The codename cms gives to the testcase with the specified index.
+fn codename(index: usize) -> String {
+    format!("{index:03}")
+}
+
+/// Import a task exported from cms (with a task.yaml and the `input/` and `output/` folders) into
+/// `task_dir`, writing the task.yaml, the gen/GEN and the input and output files that task-maker
+/// expects.
+///
+/// The groups of testcases of the score type become the subtasks, the testcases are copied with
+/// `#COPY` in the order of the groups. A testcase in more than one group is copied once for each
+/// group. The options of cms without a task-maker equivalent are dropped with a warning. The other
+/// files of the task (e.g. the checker and the statement) are not copied.
+pub fn import_cms_task(cms_dir: &Path, task_dir: &Path) -> Result<(), Error> {
+    let yaml_path = cms_dir.join("task.yaml");
+    let file = fs::File::open(&yaml_path)
+        .with_context(|| format!("Cannot open task.yaml from {}", yaml_path.display()))?;
+    let cms_yaml: CmsTaskYAML =
+        serde_yaml::from_reader(file).context("Failed to deserialize the cms task.yaml")?;
+    for key in cms_yaml.other.keys() {
+        warn!("The cms option '{key}' has no equivalent in task-maker, dropping it");
+    }
+
+    let n_input = match cms_yaml.n_input {
+        Some(n_input) => n_input,
+        None => (0..)
+            .take_while(|i| cms_dir.join("input").join(format!("input{i}.txt")).exists())
+            .count(),
+    };
+    if n_input == 0 {
+        bail!("No testcases found in {}", cms_dir.join("input").display());
+    }
+    let (score_type, groups) = parse_groups(
+        &cms_yaml.score_type,
+        cms_yaml.score_type_parameters.as_ref(),
+        n_input,
+    )?;
+
+    fs::create_dir_all(task_dir.join("gen"))
+        .with_context(|| format!("Failed to create {}", task_dir.join("gen").display()))?;
+    fs::create_dir_all(task_dir.join("input")).context("Failed to create input/")?;
+    fs::create_dir_all(task_dir.join("output")).context("Failed to create output/")?;
+
+    let mut gen = format!("# Imported from cms, from {}\n", cms_dir.display());
+    let mut score_type_parameters = vec![];
+    let mut next_testcase = 0;
+    for (index, group) in groups.iter().enumerate() {
+        let max_score = group.max_score.round();
+        if (group.max_score - max_score).abs() > EPSILON {
+            bail!(
+                "The score of group {index} is {}, but the scores of the subtasks must be integers",
+                group.max_score
+            );
+        }
+        writeln!(gen, "#ST: {max_score}")?;
+        let first_testcase = next_testcase;
+        for &testcase in &group.testcases {
+            for dir in ["input", "output"] {
+                let from = cms_dir.join(dir).join(format!("{dir}{testcase}.txt"));
+                let to = task_dir.join(dir).join(format!("{dir}{next_testcase}.txt"));
+                fs::copy(&from, &to).with_context(|| {
+                    format!("Failed to copy {} to {}", from.display(), to.display())
+                })?;
+            }
+            writeln!(gen, "#COPY: input/input{next_testcase}.txt")?;
+            next_testcase += 1;
+        }
+        score_type_parameters.push(ScoreTypeGroupParameters::List((
+            max_score,
+            (first_testcase..next_testcase).map(codename).join("|"),
+        )));
+    }
+    let gen_path = task_dir.join("gen").join("GEN");
+    fs::write(&gen_path, gen).with_context(|| format!("Failed to write {}", gen_path.display()))?;
+
+    let yaml = TaskYAML {
+        name: cms_yaml.name,
+        title: cms_yaml.title,
+        score_type: Some(score_type),
+        score_type_parameters: match score_type {
+            TestcaseScoreAggregator::Min => Some(score_type_parameters),
+            TestcaseScoreAggregator::Sum => None,
+        },
+        n_input: Some(next_testcase),
+        score_precision: cms_yaml.score_precision,
+        score_rounding: ScoreRounding::default(),
+        primary_language: cms_yaml.primary_language,
+        time_limit: cms_yaml.time_limit,
+        memory_limit: cms_yaml.memory_limit,
+        relative_time_limit: None,
+        compilation_time_limit: None,
+        compilation_memory_limit: None,
+        generation_wall_time_limit: None,
+        output_only: cms_yaml.output_only,
+        infile: cms_yaml.infile,
+        outfile: cms_yaml.outfile,
+        extra_output_files: vec![],
+        generator_outputs: vec![],
+        normalize_files: false,
+        checker: None,
+        difficulty: None,
+        syllabuslevel: None,
+        num_processes: None,
+        user_io: UserIo::fifo_io(),
+        controller_time_limit: None,
+        controller_wall_time_limit: None,
+        controller_memory_limit: None,
+        controller_process_limit: None,
+        interactive_concurrent: None,
+        score_mode: cms_yaml.score_mode,
+        token_mode: cms_yaml.token_mode,
+        public_testcases: cms_yaml.public_testcases,
+        feedback_level: cms_yaml.feedback_level,
+    };
+    let yaml_path = task_dir.join("task.yaml");
+    let file = fs::File::create(&yaml_path)
+        .with_context(|| format!("Cannot create task.yaml at {}", yaml_path.display()))?;
+    serde_yaml::to_writer(file, &yaml).context("Failed to serialize task.yaml")?;
+    Ok(())
+}
+
+/// Split the `n_input` testcases of a cms task in groups, following the score type and its
+/// parameters.
+fn parse_groups(
+    score_type: &str,
+    parameters: Option<&serde_yaml::Value>,
+    n_input: usize,
+) -> Result<(TestcaseScoreAggregator, Vec<CmsGroup>), Error> {
+    use serde_yaml::Value;
+
+    match score_type {
+        "Sum" => {
+            let score = match parameters {
+                Some(param) => param
+                    .as_f64()
+                    .context("The parameter of the Sum score type must be a number")?,
+                None => 100.0 / n_input as f64,
+            };
+            Ok((
+                TestcaseScoreAggregator::Sum,
+                vec![CmsGroup {
+                    max_score: score * n_input as f64,
+                    testcases: (0..n_input).collect(),
+                }],
+            ))
+        }
+        "GroupMin" | "GroupMul" | "GroupThreshold" => {
+            if score_type == "GroupMul" {
+                warn!("The GroupMul score type is not supported, using GroupMin instead");
+            } else if score_type == "GroupThreshold" {
+                warn!(
+                    "The GroupThreshold score type is not supported, using GroupMin and dropping \
+                     the thresholds"
+                );
+            }
+            let parameters = parameters
+                .and_then(Value::as_sequence)
+                .with_context(|| format!("The parameters of {score_type} must be a list"))?;
+            let mut groups = vec![];
+            let mut next_testcase = 0;
+            for (index, param) in parameters.iter().enumerate() {
+                let (max_score, selector) = match param.as_sequence().map(Vec::as_slice) {
+                    Some([max_score, selector, ..]) => (max_score, selector),
+                    _ => bail!("Group {index} of {score_type} must be a list"),
+                };
+                let max_score = max_score
+                    .as_f64()
+                    .with_context(|| format!("The score of group {index} must be a number"))?;
+                let testcases: Vec<_> = match selector {
+                    Value::Number(count) => {
+                        let count = count.as_u64().with_context(|| {
+                            format!("The number of testcases of group {index} must be positive")
+                        })? as usize;
+                        let testcases = (next_testcase..next_testcase + count).collect();
+                        next_testcase += count;
+                        testcases
+                    }
+                    Value::String(regex) => {
+                        let regex = Regex::new(&format!("^(?:{regex})$")).with_context(|| {
+                            format!("Invalid regex of the testcases of group {index}")
+                        })?;
+                        (0..n_input)
+                            .filter(|&tc| regex.is_match(&codename(tc)))
+                            .collect()
+                    }
+                    _ => bail!("The testcases of group {index} must be a number or a regex"),
+                };
+                if testcases.is_empty() || testcases.iter().any(|&tc| tc >= n_input) {
+                    bail!("Group {index} of {score_type} doesn't match the testcases of the task");
+                }
+                groups.push(CmsGroup {
+                    max_score,
+                    testcases,
+                });
+            }
+            let unused = (0..n_input)
+                .filter(|tc| !groups.iter().any(|g| g.testcases.contains(tc)))
+                .collect_vec();
+            if !unused.is_empty() {
+                warn!("The testcases {unused:?} are not in any group, dropping them");
+            }
+            Ok((TestcaseScoreAggregator::Min, groups))
+        }
+        _ => bail!("Unsupported cms score type: {score_type}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn groups(score_type: &str, parameters: &str, n_input: usize) -> Vec<CmsGroup> {
+        let parameters: serde_yaml::Value = serde_yaml::from_str(parameters).unwrap();
+        parse_groups(score_type, Some(&parameters), n_input)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn test_parse_groups_sum() {
+        let groups = groups("Sum", "5", 4);
+        assert_eq!(
+            groups,
+            vec![CmsGroup {
+                max_score: 20.0,
+                testcases: vec![0, 1, 2, 3]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_groups_count() {
+        let groups = groups("GroupMin", "[[10, 1], [90, 3]]", 4);
+        assert_eq!(groups[0].testcases, vec![0]);
+        assert_eq!(groups[1].testcases, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_groups_regex() {
+        let groups = groups("GroupMin", "[[10, \"000|003\"], [90, \".*\"]]", 4);
+        assert_eq!(groups[0].testcases, vec![0, 3]);
+        assert_eq!(groups[1].testcases, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_groups_too_many_testcases() {
+        let parameters = serde_yaml::from_str("[[10, 5]]").unwrap();
+        assert!(parse_groups("GroupMin", Some(&parameters), 4).is_err());
+    }
+
+    #[test]
+    fn test_import_cms_task() {
+        let cms_dir = TempDir::new().unwrap();
+        let cms = cms_dir.path();
+        fs::write(
+            cms.join("task.yaml"),
+            "name: task\ntitle: Task\ntime_limit: 1.0\nmemory_limit: 256\nn_input: 2\n\
+             score_type: GroupMin\nscore_type_parameters: [[40, \"000\"], [60, \"00[01]\"]]\n\
+             token_max_number: 3\n",
+        )
+        .unwrap();
+        fs::create_dir_all(cms.join("input")).unwrap();
+        fs::create_dir_all(cms.join("output")).unwrap();
+        for i in 0..2 {
+            fs::write(cms.join(format!("input/input{i}.txt")), format!("in{i}")).unwrap();
+            fs::write(cms.join(format!("output/output{i}.txt")), format!("out{i}")).unwrap();
+        }
+        let task_dir = TempDir::new().unwrap();
+        let task = task_dir.path();
+        import_cms_task(cms, task).unwrap();
+
+        let gen = fs::read_to_string(task.join("gen/GEN")).unwrap();
+        let gen = gen.lines().skip(1).collect_vec();
+        assert_eq!(
+            gen,
+            vec![
+                "#ST: 40",
+                "#COPY: input/input0.txt",
+                "#ST: 60",
+                "#COPY: input/input1.txt",
+                "#COPY: input/input2.txt"
+            ]
+        );
+        let input2 = fs::read_to_string(task.join("input/input2.txt")).unwrap();
+        let output2 = fs::read_to_string(task.join("output/output2.txt")).unwrap();
+        assert_eq!(input2, "in1");
+        assert_eq!(output2, "out1");
+        let yaml = fs::read_to_string(task.join("task.yaml")).unwrap();
+        assert!(yaml.contains("n_input: 3"));
+        assert!(!yaml.contains("token_max_number"));
+    }
+}
@@ -285,7 +285,7 @@ mod tests {
             assert_eq!(testcase.id, 0);
             match &testcase.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -301,7 +301,7 @@ mod tests {
             assert_eq!(testcase.id, 0);
             match &testcase.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -318,7 +318,7 @@ mod tests {
             assert_eq!(testcase.id, 0);
             match &testcase.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -334,7 +334,7 @@ mod tests {
             assert_eq!(testcase.id, 0);
             match &testcase.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -350,7 +350,7 @@ mod tests {
             assert_eq!(testcase.id, 0);
             match &testcase.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -367,11 +367,11 @@ mod tests {
             assert_eq!(testcase2.id, 1);
             match &testcase1.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase2.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["5678".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -388,11 +388,11 @@ mod tests {
             assert_eq!(testcase2.id, 1);
             match &testcase1.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase2.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["5678".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -412,11 +412,11 @@ mod tests {
                 InputGenerator::StaticFile(path) => {
                     assert_eq!(path, &task.path().join("random/file"))
                 }
-                InputGenerator::Custom(_, _) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase2.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["5678".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -440,15 +440,15 @@ mod tests {
                 InputGenerator::StaticFile(path) => {
                     assert_eq!(path, &task.path().join("random/file"))
                 }
-                InputGenerator::Custom(_, _) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase2.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["5678".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase3.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -502,11 +502,11 @@ mod tests {
             assert_eq!(testcase2.id, 1);
             match &testcase1.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase2.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["5678".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
@@ -522,7 +522,7 @@ mod tests {
             assert_eq!(testcase.id, 0);
             match &testcase.input_generator {
                 InputGenerator::Custom(_, args) => assert_eq!(args, &vec!["1234".to_string()]),
-                InputGenerator::StaticFile(_) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
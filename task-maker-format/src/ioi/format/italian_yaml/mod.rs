@@ -161,6 +161,13 @@
 //! Example: `:CONSTRAINT 0 <= $N < $M <= 1000000` will check that the variables `$N` and `$M` are
 //! between 0 and 1000000 and `$N` is smaller than `$M`.
 //!
+//! ### `: GLOBAL_CONSTRAINT operand (operator operand)+`
+//! Like `: CONSTRAINT`, but the constraint is used for all the testcases of the task, wherever it
+//! is defined. This includes the testcases defined before it.
+//!
+//! Example: `:GLOBAL_CONSTRAINT 1 <= $N <= 1000000` will check the variable `$N` of all the
+//! testcases, even if it's defined inside a subtask.
+//!
 //! ### `: SUBTASK score [name]`
 //! This command marks the start of a new subtask, just like how `#ST` in `gen/GEN` did. The score
 //! can be a simple floating point number (either an integer or an integer.integer). The name
@@ -269,17 +276,22 @@ use unic::normal::StrNormalForm;
 use unic::ucd::category::GeneralCategory;
 
 use crate::ioi::sanity_checks::get_sanity_checks;
+use crate::ioi::scorer::detect_scorer;
 use crate::ioi::{
-    make_task_booklets, BatchTypeData, Checker, CommunicationTypeData, IOITask, InputValidator,
-    InputValidatorGenerator, InteractiveTypeData, OutputGenerator, SubtaskId, SubtaskInfo,
-    TaskType, TestcaseId, TestcaseInfo, TestcaseScoreAggregator, UserIo, TM_VALIDATION_FILE_NAME,
+    make_task_booklets, parse_generator_outputs, BatchTypeData, Checker, CommunicationTypeData,
+    IOITask, InputValidator, InputValidatorGenerator, InteractiveTypeData, OutputGenerator,
+    ScoreRounding, SubtaskId, SubtaskInfo, TaskType, TestcaseId, TestcaseInfo,
+    TestcaseScoreAggregator, ToleranceMode, UserIo, TM_VALIDATION_FILE_NAME,
 };
 use crate::{find_source_file, list_files, EvaluationConfig, WriteBinTo};
 
 mod cases_gen;
+mod cms_import;
 mod gen_gen;
 mod static_inputs;
 
+pub use cms_import::import_cms_task;
+
 /// String placed in the auto-generated files marking them as safely deletable.
 pub(crate) const TM_ALLOW_DELETE_COOKIE: &str = "tm-allow-delete";
 
@@ -341,6 +353,45 @@ pub(super) enum ScoreTypeGroupParameters {
     },
 }
 
+/// The built-in checker configured in task.yaml, for example
+/// `checker: { type: token_diff, float_tolerance: 1e-6 }`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(super) enum CheckerYAML {
+    /// The token diff checker, see `Checker::TokenDiff`.
+    TokenDiff {
+        /// The tolerance for comparing the numbers, by default they must be equal.
+        #[serde(default)]
+        float_tolerance: f64,
+        /// Whether the tolerance is absolute, relative or either.
+        #[serde(default)]
+        tolerance_mode: ToleranceMode,
+    },
+}
+
+impl CheckerYAML {
+    /// The `Checker` configured by this entry of task.yaml.
+    pub(super) fn to_checker(self) -> Result<Checker, Error> {
+        match self {
+            CheckerYAML::TokenDiff {
+                float_tolerance,
+                tolerance_mode,
+            } => {
+                if !(float_tolerance >= 0.0 && float_tolerance.is_finite()) {
+                    bail!(
+                        "The float_tolerance of the checker must be a non-negative number, got \
+                        {float_tolerance}"
+                    );
+                }
+                Ok(Checker::TokenDiff {
+                    float_tolerance,
+                    tolerance_mode,
+                })
+            }
+        }
+    }
+}
+
 /// Deserialized data from the task.yaml of a IOI format task.
 #[derive(Debug, Serialize, Deserialize)]
 pub(super) struct TaskYAML {
@@ -362,6 +413,10 @@ pub(super) struct TaskYAML {
     /// The number of decimal digits when displaying the scores.
     #[serde(default)]
     pub score_precision: usize,
+    /// How the scores are rounded when displaying them, either "half_up" or "truncate". Not used
+    /// by cms.
+    #[serde(default, skip_serializing)]
+    pub score_rounding: ScoreRounding,
     /// The primary language used when importing the task.
     pub primary_language: Option<String>,
 
@@ -371,6 +426,19 @@ pub(super) struct TaskYAML {
     /// The memory limit in MiB of the execution of the solution, if not set it's unlimited.
     #[serde(alias = "memlimit")]
     pub memory_limit: Option<u64>,
+    /// The time limit on each testcase as a multiple of the time of the official solution. Not
+    /// used by cms.
+    #[serde(default, skip_serializing)]
+    pub relative_time_limit: Option<f64>,
+    /// The CPU time limit in seconds for compiling the source files. Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub compilation_time_limit: Option<f64>,
+    /// The memory limit in MiB for compiling the source files. Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub compilation_memory_limit: Option<u64>,
+    /// The wall time limit in seconds of the generators and of the validators. Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub generation_wall_time_limit: Option<f64>,
 
     /// Whether this is an output only task. Defaults to false.
     #[serde(default)]
@@ -381,6 +449,22 @@ pub(super) struct TaskYAML {
     /// The output file for the solutions, usually 'output.txt' or '' (stdout). Defaults to `''`.
     #[serde(default = "default_outfile")]
     pub outfile: String,
+    /// The other output files the solutions should write in a batch task, checked separately from
+    /// the main output. Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub extra_output_files: Vec<String>,
+    /// The files the generator writes besides the input file, which are given to the validator and
+    /// to the checker. Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub generator_outputs: Vec<String>,
+    /// Whether to remove the UTF-8 BOM and to convert CRLF to LF in the generated input and output
+    /// files. Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub normalize_files: bool,
+    /// The built-in checker to use instead of the white diff, when there is no custom checker.
+    /// Not used by cms.
+    #[serde(default, skip_serializing)]
+    pub checker: Option<CheckerYAML>,
 
     /// An integer that defines the difficulty of the task. Used only in booklet compilations.
     pub difficulty: Option<u8>,
@@ -432,6 +516,9 @@ pub(super) struct TaskYAMLOrig {
     /// The number of decimal digits when displaying the scores.
     #[serde(default)]
     pub score_precision: usize,
+    /// How the scores are rounded when displaying them, either "half_up" or "truncate".
+    #[serde(default)]
+    pub score_rounding: ScoreRounding,
     /// The primary language used when importing the task.
     pub primary_language: Option<String>,
 
@@ -439,6 +526,18 @@ pub(super) struct TaskYAMLOrig {
     pub time_limit: f64,
     /// The memory limit in MiB of the execution of the solution.
     pub memory_limit: u64,
+    /// The time limit on each testcase as a multiple of the time of the official solution.
+    #[serde(default)]
+    pub relative_time_limit: Option<f64>,
+    /// The CPU time limit in seconds for compiling the source files.
+    #[serde(default)]
+    pub compilation_time_limit: Option<f64>,
+    /// The memory limit in MiB for compiling the source files.
+    #[serde(default)]
+    pub compilation_memory_limit: Option<u64>,
+    /// The wall time limit in seconds of the generators and of the validators.
+    #[serde(default)]
+    pub generation_wall_time_limit: Option<f64>,
 
     /// Whether this is an output only task. Defaults to false.
     #[serde(default)]
@@ -449,6 +548,21 @@ pub(super) struct TaskYAMLOrig {
     /// The output file for the solutions, usually 'output.txt' or '' (stdout). Defaults to `''`.
     #[serde(default)]
     pub outfile: String,
+    /// The other output files the solutions should write in a batch task, checked separately from
+    /// the main output.
+    #[serde(default)]
+    pub extra_output_files: Vec<String>,
+    /// The files the generator writes besides the input file, which are given to the validator and
+    /// to the checker.
+    #[serde(default)]
+    pub generator_outputs: Vec<String>,
+    /// Whether to remove the UTF-8 BOM and to convert CRLF to LF in the generated input and output
+    /// files.
+    #[serde(default)]
+    pub normalize_files: bool,
+    /// The built-in checker to use instead of the white diff, when there is no custom checker.
+    #[serde(default)]
+    pub checker: Option<CheckerYAML>,
 
     /// An integer that defines the difficulty of the task. Used only in booklet compilations.
     pub difficulty: Option<u8>,
@@ -490,12 +604,21 @@ impl TaskYAMLOrig {
             score_type_parameters: None,
             n_input: None,
             score_precision: self.score_precision,
+            score_rounding: self.score_rounding,
             primary_language: Some(self.primary_language.unwrap_or_else(|| "en".into())),
             time_limit: Some(self.time_limit),
             memory_limit: Some(self.memory_limit),
+            relative_time_limit: self.relative_time_limit,
+            compilation_time_limit: self.compilation_time_limit,
+            compilation_memory_limit: self.compilation_memory_limit,
+            generation_wall_time_limit: self.generation_wall_time_limit,
             output_only: self.output_only,
             infile: self.infile,
             outfile: self.outfile,
+            extra_output_files: self.extra_output_files,
+            generator_outputs: self.generator_outputs,
+            normalize_files: self.normalize_files,
+            checker: self.checker,
             difficulty: self.difficulty,
             syllabuslevel: self.syllabuslevel,
             num_processes: self.num_processes,
@@ -580,6 +703,7 @@ pub fn parse_task<P: AsRef<Path>>(
         bail!("No task.yaml found in {}", task_dir.display());
     }
     debug!("The yaml is {yaml:#?}");
+    check_relative_time_limit(&yaml)?;
 
     let map_file = |file: String| -> Option<PathBuf> {
         match file.as_ref() {
@@ -606,7 +730,7 @@ pub fn parse_task<P: AsRef<Path>>(
     } else if let Some(comm) = communication {
         comm
     } else {
-        parse_batch_task_data(task_dir, grader_map.clone())?
+        parse_batch_task_data(task_dir, &yaml, grader_map.clone())?
     };
 
     let gen_gen = task_dir.join("gen").join("GEN");
@@ -746,10 +870,19 @@ pub fn parse_task<P: AsRef<Path>>(
         title: yaml.title,
         time_limit: yaml.time_limit,
         memory_limit: yaml.memory_limit,
+        relative_time_limit: yaml.relative_time_limit,
+        compilation_time_limit: yaml.compilation_time_limit,
+        compilation_memory_limit: yaml.compilation_memory_limit,
+        generation_wall_time_limit: yaml.generation_wall_time_limit,
         infile,
         outfile,
+        generator_outputs: parse_generator_outputs(&yaml.generator_outputs)?,
+        normalize_files: yaml.normalize_files,
+        output_only: yaml.output_only,
         testcase_score_aggregator,
+        scorer: detect_scorer(task_dir).context("Failed to detect the scorer")?,
         score_precision: yaml.score_precision,
+        score_rounding: yaml.score_rounding,
         subtasks,
         testcases,
         grader_map,
@@ -775,6 +908,34 @@ pub fn parse_task<P: AsRef<Path>>(
     Ok(task)
 }
 
+/// The files that `parse_task` writes back inside the task directory when not in dry-run mode:
+/// `gen/GEN` if the testcases are defined in `gen/cases.gen`, and `task.yaml` if the task has a
+/// `task.yaml.orig`. The files that don't contain `TM_ALLOW_DELETE_COOKIE` are not overwritten.
+pub(crate) fn planned_writes(task_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut writes = vec![];
+    let writebacks = [
+        ("gen/cases.gen", "gen/GEN"),
+        ("task.yaml.orig", "task.yaml"),
+    ];
+    for (source, dest) in writebacks {
+        let dest = task_dir.join(dest);
+        if task_dir.join(source).exists() && (!dest.exists() || is_tm_deletable(&dest)?) {
+            writes.push(dest);
+        }
+    }
+    Ok(writes)
+}
+
+/// Check that the relative time limit, if any, is a positive factor.
+pub(crate) fn check_relative_time_limit(yaml: &TaskYAML) -> Result<(), Error> {
+    match yaml.relative_time_limit {
+        Some(factor) if factor.is_nan() || factor <= 0.0 => {
+            bail!("The relative time limit must be positive, found {factor}")
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Check if the file is deletable, i.e. it contains the TM_ALLOW_DELETE_COOKIE
 /// Assumes the file exists.
 pub(crate) fn is_tm_deletable(path: &Path) -> Result<bool, Error> {
@@ -864,7 +1025,11 @@ fn detect_output_generator(
 }
 
 /// Parse the task components relative to the batch task type.
-fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<TaskType, Error> {
+fn parse_batch_task_data(
+    task_dir: &Path,
+    yaml: &TaskYAML,
+    grader_map: Arc<GraderMap>,
+) -> Result<TaskType, Error> {
     let mut checkers = find_source_file(
         task_dir,
         vec!["check/checker.*", "cor/correttore.*"],
@@ -877,9 +1042,11 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
         let paths = checkers.iter().map(|s| s.name()).collect::<Vec<_>>();
         bail!("Multiple checkers found: {:?}", paths)
     }
-    let checker = checkers
-        .pop()
-        .map(|mut c| {
+    let checker = match (checkers.pop(), yaml.checker) {
+        (Some(_), Some(_)) => {
+            bail!("The checker in task.yaml cannot be used together with a custom checker")
+        }
+        (Some(mut c), None) => {
             // Always copy the custom checker.
             c.copy_exe();
 
@@ -887,8 +1054,10 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
             c.link_static();
 
             Checker::Custom(Arc::new(c))
-        })
-        .unwrap_or(Checker::WhiteDiff);
+        }
+        (None, Some(checker)) => checker.to_checker()?,
+        (None, None) => Checker::WhiteDiff,
+    };
 
     let official_solution = detect_output_generator(task_dir.to_path_buf(), grader_map)
         .context("Failed to detect output generator")?;
@@ -899,6 +1068,7 @@ fn parse_batch_task_data(task_dir: &Path, grader_map: Arc<GraderMap>) -> Result<
     Ok(TaskType::Batch(BatchTypeData {
         output_generator: official_solution,
         checker,
+        extra_output_files: yaml.extra_output_files.iter().map(PathBuf::from).collect(),
     }))
 }
 
@@ -123,19 +123,19 @@ mod tests {
                 InputGenerator::StaticFile(path) => {
                     assert_eq!(path, &task.path().join("input/input0.txt"))
                 }
-                InputGenerator::Custom(_, _) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase1.input_generator {
                 InputGenerator::StaticFile(path) => {
                     assert_eq!(path, &task.path().join("input/input1.txt"))
                 }
-                InputGenerator::Custom(_, _) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
             match &testcase2.input_generator {
                 InputGenerator::StaticFile(path) => {
                     assert_eq!(path, &task.path().join("input/input2.txt"))
                 }
-                InputGenerator::Custom(_, _) => panic!("Invalid generator"),
+                _ => panic!("Invalid generator"),
             }
         } else {
             panic!("Wrong entries returned: {entries:?}");
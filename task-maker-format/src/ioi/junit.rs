@@ -0,0 +1,308 @@
+//! Support for writing a JUnit XML report with the results of the solutions of an IOI task.
+//!
+//! Each solution is reported as a test case, that passes or fails according to a
+//! [`JUnitCriterion`]. The failed test cases include the details of their subtasks.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use itertools::Itertools;
+use task_maker_dag::ExecutionDAGConfig;
+
+use crate::ioi::{IOITask, UIState};
+use crate::ui::{UIMessage, UIStateT, UI};
+
+/// When a solution is considered passed in the JUnit report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JUnitCriterion {
+    /// The solution passes if it gets the maximum score of the task.
+    #[default]
+    FullScore,
+    /// The solution passes if it satisfies all the `@check` annotations in its source file. The
+    /// solutions without annotations always pass.
+    Checks,
+}
+
+impl FromStr for JUnitCriterion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "full-score" => Ok(JUnitCriterion::FullScore),
+            "checks" => Ok(JUnitCriterion::Checks),
+            _ => Err(format!("Unknown JUnit criterion: {s}")),
+        }
+    }
+}
+
+/// A `UI` that collects the results of the evaluation and, at the end, writes them to a file as a
+/// JUnit XML report.
+pub struct JUnitReport {
+    /// The state of the evaluation.
+    state: UIState,
+    /// Where to write the report.
+    path: PathBuf,
+    /// When a solution is considered passed.
+    criterion: JUnitCriterion,
+}
+
+impl JUnitReport {
+    /// Make a new `JUnitReport` that will be written to `path`.
+    pub fn new<P: Into<PathBuf>>(
+        task: &IOITask,
+        config: ExecutionDAGConfig,
+        path: P,
+        criterion: JUnitCriterion,
+    ) -> JUnitReport {
+        JUnitReport {
+            state: UIState::new(task, config),
+            path: path.into(),
+            criterion,
+        }
+    }
+
+    /// Render the report as a JUnit XML document.
+    fn render(&self) -> String {
+        let task = escape_xml(&self.state.task.name);
+        let solutions = self
+            .state
+            .solutions
+            .iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(path, info)| (&info.name, self.failure(path)))
+            .collect_vec();
+        let failures = solutions.iter().filter(|(_, f)| f.is_some()).count();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{task}\" tests=\"{}\" failures=\"{failures}\">",
+            solutions.len()
+        );
+        for (name, failure) in solutions {
+            let name = escape_xml(name);
+            match failure {
+                None => {
+                    let _ = writeln!(xml, "    <testcase classname=\"{task}\" name=\"{name}\"/>");
+                }
+                Some((message, details)) => {
+                    let _ = writeln!(
+                        xml,
+                        "    <testcase classname=\"{task}\" name=\"{name}\">\n      \
+                        <failure message=\"{}\">{}</failure>\n    </testcase>",
+                        escape_xml(&message),
+                        escape_xml(&details)
+                    );
+                }
+            }
+        }
+        xml += "  </testsuite>\n</testsuites>\n";
+        xml
+    }
+
+    /// The message and the details of the failure of a solution, or `None` if it passed.
+    fn failure(&self, solution: &Path) -> Option<(String, String)> {
+        let state = &self.state;
+        let Some(evaluation) = state.evaluations.get(solution) else {
+            return Some(("The solution was not evaluated".into(), String::new()));
+        };
+        let precision = state.task.score_precision;
+        let rounding = state.task.score_rounding;
+        let subtasks = state
+            .task
+            .subtasks
+            .values()
+            .sorted_by_key(|subtask| subtask.id)
+            .map(|subtask| {
+                let score = evaluation
+                    .subtasks
+                    .get(&subtask.id)
+                    .and_then(|s| s.score)
+                    .map(|score| format!("{:.precision$}", rounding.round(score, precision)))
+                    .unwrap_or_else(|| "?".into());
+                let name = subtask.name.as_deref().unwrap_or_default();
+                format!(
+                    "Subtask {} ({name}): {score}/{:.precision$}",
+                    subtask.id, subtask.max_score
+                )
+            })
+            .join("\n");
+        let score = evaluation
+            .score
+            .map(|score| format!("{:.precision$}", rounding.round(score, precision)))
+            .unwrap_or_else(|| "?".into());
+        let max_score = format!("{:.precision$}", state.max_score);
+
+        match self.criterion {
+            JUnitCriterion::FullScore => match evaluation.score {
+                Some(score) if abs_diff_eq!(score, state.max_score, epsilon = 1e-6) => None,
+                _ => Some((format!("Scored {score}/{max_score}"), subtasks)),
+            },
+            JUnitCriterion::Checks => {
+                let failed = state
+                    .run_solution_checks()
+                    .into_iter()
+                    .filter(|outcome| outcome.solution == solution && !outcome.success)
+                    .sorted_by_key(|outcome| outcome.subtask_id)
+                    .map(|outcome| {
+                        let name = state.task.subtasks[&outcome.subtask_id]
+                            .name
+                            .as_deref()
+                            .unwrap_or_default();
+                        format!(
+                            "Subtask {} ({name}) should be {}",
+                            outcome.subtask_id,
+                            outcome.check.result.as_str()
+                        )
+                    })
+                    .collect_vec();
+                if failed.is_empty() {
+                    None
+                } else {
+                    Some((
+                        format!("{} checks failed, scored {score}/{max_score}", failed.len()),
+                        failed.into_iter().chain([subtasks]).join("\n"),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl UI for JUnitReport {
+    fn on_message(&mut self, message: UIMessage) {
+        self.state.apply(message);
+    }
+
+    fn finish(&mut self) {
+        if let Err(e) = std::fs::write(&self.path, self.render()) {
+            error!(
+                "Failed to write the JUnit report to {}: {e:?}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Escape a string to be used inside XML text and attributes. The characters that are not allowed
+/// in XML are replaced.
+fn escape_xml(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result += "&amp;",
+            '<' => result += "&lt;",
+            '>' => result += "&gt;",
+            '"' => result += "&quot;",
+            '\'' => result += "&apos;",
+            '\n' | '\r' | '\t' => result.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {
+                result.push(char::REPLACEMENT_CHARACTER)
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_diagnostics::CodeSpan;
+
+    use super::*;
+    use crate::ioi::{
+        SolutionEvaluationState, SolutionTestcaseEvaluationState, SubtaskInfo,
+        TestcaseEvaluationStatus,
+    };
+    use crate::solution::{SolutionCheck, SolutionCheckResult, SolutionInfo};
+
+    fn make_report(criterion: JUnitCriterion) -> JUnitReport {
+        let mut task = IOITask::fake();
+        task.name = "a<b".into();
+        task.subtasks.insert(
+            0,
+            SubtaskInfo {
+                id: 0,
+                name: Some("st0".into()),
+                max_score: 100.0,
+                ..Default::default()
+            },
+        );
+        let mut report =
+            JUnitReport::new(&task, ExecutionDAGConfig::new(), "report.xml", criterion);
+        report.state.apply(UIMessage::Solutions {
+            solutions: vec![
+                SolutionInfo {
+                    path: "sol/good.cpp".into(),
+                    name: "good.cpp".into(),
+                    language_name: "C++".into(),
+                    checks: vec![],
+                    score_checks: vec![],
+                },
+                SolutionInfo {
+                    path: "sol/bad\"&.cpp".into(),
+                    name: "bad\"&.cpp".into(),
+                    language_name: "C++".into(),
+                    checks: vec![SolutionCheck::new(
+                        SolutionCheckResult::Accepted,
+                        "st0",
+                        CodeSpan::from_str("sol/bad.cpp", "@check-accepted: st0", 0, 1).unwrap(),
+                    )],
+                    score_checks: vec![],
+                },
+            ],
+        });
+        for (path, score) in [("sol/good.cpp", 100.0), ("sol/bad\"&.cpp", 0.0)] {
+            let mut evaluation = SolutionEvaluationState::new(&task);
+            evaluation.score = Some(score);
+            evaluation.subtasks.get_mut(&0).unwrap().score = Some(score);
+            report.state.evaluations.insert(path.into(), evaluation);
+        }
+        report
+    }
+
+    #[test]
+    fn test_junit_full_score() {
+        let xml = make_report(JUnitCriterion::FullScore).render();
+        assert!(xml.contains("<testsuite name=\"a&lt;b\" tests=\"2\" failures=\"1\">"));
+        assert!(xml.contains("<testcase classname=\"a&lt;b\" name=\"good.cpp\"/>"));
+        assert!(xml.contains("name=\"bad&quot;&amp;.cpp\""));
+        assert!(xml.contains("<failure message=\"Scored 0/100\">Subtask 0 (st0): 0/100</failure>"));
+    }
+
+    #[test]
+    fn test_junit_checks() {
+        let mut report = make_report(JUnitCriterion::Checks);
+        // the bad solution is expected to be accepted, but it has no testcases so the check is
+        // satisfied
+        let xml = report.render();
+        assert!(xml.contains("failures=\"0\""));
+
+        report.state.task.subtasks.get_mut(&0).unwrap().testcases = vec![0];
+        for evaluation in report.state.evaluations.values_mut() {
+            evaluation.testcases.insert(
+                0,
+                SolutionTestcaseEvaluationState {
+                    score: Some(0.0),
+                    status: TestcaseEvaluationStatus::WrongAnswer("".into()),
+                    results: vec![],
+                    checker: None,
+                    worker: None,
+                },
+            );
+        }
+        let xml = report.render();
+        assert!(xml.contains("failures=\"1\""));
+        assert!(xml.contains("Subtask 0 (st0) should be accepted"));
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("<a href='x'>&\"</a>"),
+            "&lt;a href=&apos;x&apos;&gt;&amp;&quot;&lt;/a&gt;"
+        );
+        assert_eq!(escape_xml("a\u{1}b\nc"), "a\u{FFFD}b\nc");
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{bail, Error};
+use task_maker_dag::{
+    ExecutionCommand, ExecutionDAG, ExecutionGroup, ExecutionGroupUuid, ExecutionInputBehaviour,
+    ExecutionOutputBehaviour, ExecutionTag, FileUuid, ProvidedFile,
+};
+
+use crate::Tag;
+
+/// Directory, relative to the task root, where the Makefile runs the executions.
+const BUILD_DIR: &str = "build";
+
+/// Write a Makefile that generates the input and output files of the task running the same
+/// commands of the DAG, so that the task can be built with `make` from its root directory.
+///
+/// Only the executions needed for writing the files inside `input/` and `output/` are exported,
+/// together with the validations of the inputs. Each execution runs in its own directory inside
+/// `build/`, with its input files copied there with their names in the sandbox. The commands run
+/// outside the sandbox and without limits.
+///
+/// The DAG must not be built in dry-run mode, otherwise no file is written to disk.
+pub fn generate_makefile(task_path: &Path, dag: &mut ExecutionDAG) -> Result<String, Error> {
+    // the files written inside input/ and output/, with their destination relative to the task
+    let mut written: Vec<_> = dag
+        .file_callbacks()
+        .iter()
+        .filter_map(|(uuid, callbacks)| {
+            let dest = callbacks
+                .write_to
+                .as_ref()?
+                .dest
+                .strip_prefix(task_path)
+                .ok()?;
+            (dest.starts_with("input") || dest.starts_with("output"))
+                .then(|| make_path(dest).map(|dest| (*uuid, dest)))
+        })
+        .collect::<Result<_, _>>()?;
+    written.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let data = &dag.data;
+    let mut producers = HashMap::new();
+    for group in data.execution_groups.values() {
+        for output in group.outputs() {
+            producers.insert(output, group.uuid);
+        }
+    }
+    // the validations don't write any file, but the generated inputs must be validated anyway
+    let generation: ExecutionTag = Tag::Generation.into();
+    let mut stack: Vec<ExecutionGroupUuid> = data
+        .execution_groups
+        .values()
+        .filter(|group| group.tag.as_ref() == Some(&generation))
+        .map(|group| group.uuid)
+        .chain(
+            written
+                .iter()
+                .filter_map(|(file, _)| producers.get(file).copied()),
+        )
+        .collect();
+    let mut needed = HashSet::new();
+    while let Some(uuid) = stack.pop() {
+        if needed.insert(uuid) {
+            let group = &data.execution_groups[&uuid];
+            stack.extend(group.dependencies().iter().filter_map(|f| producers.get(f)));
+        }
+    }
+    let mut groups: Vec<&ExecutionGroup> = needed
+        .iter()
+        .map(|uuid| &data.execution_groups[uuid])
+        .collect();
+    groups.sort_by(|a, b| a.description.cmp(&b.description));
+
+    // where each file is in the Makefile, relative to the task root
+    let mut paths: HashMap<FileUuid, String> = HashMap::new();
+    let mut stamps = HashMap::new();
+    for (index, group) in groups.iter().enumerate() {
+        let dir = format!("{BUILD_DIR}/exec{index}");
+        let exec = match group.executions.as_slice() {
+            [exec] if group.fifo.is_empty() && group.controller_settings.is_none() => exec,
+            _ => bail!(
+                "'{}' cannot be exported: it's made of more than one process",
+                group.description
+            ),
+        };
+        if let ExecutionOutputBehaviour::Capture { file, .. } = &exec.stdout {
+            paths.insert(file.uuid, format!("{dir}/stdout"));
+        }
+        if let ExecutionOutputBehaviour::Capture { file, .. } = &exec.stderr {
+            paths.insert(file.uuid, format!("{dir}/stderr"));
+        }
+        for (path, file) in &exec.output_files {
+            paths.insert(file.uuid, format!("{dir}/{}", make_path(path)?));
+        }
+        stamps.insert(group.uuid, (dir, exec));
+    }
+
+    let mut makefile = String::new();
+    writeln!(
+        makefile,
+        "# Generated by task-maker-tools export-makefile, run it from the root of the task.\n\
+         # The commands are the ones task-maker runs, but outside the sandbox and without limits.\n"
+    )?;
+    let mut all: Vec<String> = written.iter().map(|(_, dest)| dest.clone()).collect();
+    all.extend(
+        groups
+            .iter()
+            .map(|group| format!("{}/.done", stamps[&group.uuid].0)),
+    );
+    writeln!(makefile, "all: {}\n", all.join(" "))?;
+    writeln!(
+        makefile,
+        "clean:\n\trm -rf {BUILD_DIR}\n\n.PHONY: all clean\n"
+    )?;
+
+    // the files provided by task-maker, that are not produced by any execution
+    let mut contents = vec![];
+    let provided: HashSet<_> = groups
+        .iter()
+        .flat_map(|group| group.dependencies())
+        .chain(written.iter().map(|(file, _)| *file))
+        .filter(|file| !producers.contains_key(file))
+        .collect();
+    let mut provided: Vec<_> = provided.into_iter().collect();
+    provided.sort_by_key(|file| {
+        (
+            data.provided_files.get(file).map(provided_description),
+            *file,
+        )
+    });
+    for file in provided {
+        let path = match data.provided_files.get(&file) {
+            Some(ProvidedFile::LocalFile { local_path, .. }) => {
+                match local_path.strip_prefix(task_path) {
+                    Ok(path) => make_path(path)?,
+                    Err(_) => make_path(local_path)?,
+                }
+            }
+            Some(ProvidedFile::Content { content, .. }) => {
+                let path = format!("{BUILD_DIR}/content{}", contents.len());
+                contents.push((path.clone(), content));
+                path
+            }
+            Some(ProvidedFile::StoredFile { file, .. }) => {
+                bail!(
+                    "'{}' is only in the store, it cannot be exported",
+                    file.description
+                )
+            }
+            Some(ProvidedFile::Late { file }) => {
+                bail!(
+                    "'{}' is known only during the evaluation, it cannot be exported",
+                    file.description
+                )
+            }
+            None => bail!("File {file} is neither provided nor generated"),
+        };
+        paths.insert(file, path);
+    }
+    for (path, content) in contents {
+        let escaped: String = content
+            .iter()
+            .map(|&byte| match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b' ' | b'.' | b'_' | b'-' | b'/' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("\\{byte:03o}"),
+            })
+            .collect();
+        writeln!(
+            makefile,
+            "{path}:\n\t@mkdir -p $(@D)\n\tprintf '{escaped}' > $@\n"
+        )?;
+    }
+
+    for group in &groups {
+        let (dir, exec) = &stamps[&group.uuid];
+        let mut deps: Vec<&str> = group
+            .dependencies()
+            .iter()
+            .map(|file| paths[file].as_str())
+            .collect();
+        deps.sort();
+        deps.dedup();
+        writeln!(makefile, "# {}", group.description)?;
+        writeln!(makefile, "{dir}/.done: {}", deps.join(" "))?;
+        writeln!(makefile, "\t@rm -rf {dir} && mkdir -p {dir}")?;
+        let mut inputs: Vec<_> = exec.input_files.iter().collect();
+        inputs.sort_by(|a, b| a.0.cmp(b.0));
+        for (sandbox_path, input) in inputs {
+            let dest = format!("{dir}/{}", make_path(sandbox_path)?);
+            if let Some(parent) = Path::new(&dest).parent() {
+                if parent != Path::new(dir) {
+                    writeln!(makefile, "\t@mkdir -p {}", parent.display())?;
+                }
+            }
+            writeln!(makefile, "\tcp {} {dest}", paths[&input.file])?;
+            if input.executable {
+                writeln!(makefile, "\tchmod +x {dest}")?;
+            }
+        }
+        let mut command = format!("\tcd {dir} &&");
+        let mut env: Vec<_> = exec.env.iter().collect();
+        env.sort();
+        if !env.is_empty() {
+            command += " env";
+            for (key, value) in env {
+                write!(command, " {}", shell_quote(&format!("{key}={value}")))?;
+            }
+        }
+        match &exec.command {
+            ExecutionCommand::System(path) => {
+                write!(command, " {}", shell_quote(&path.to_string_lossy()))?
+            }
+            ExecutionCommand::Local(path) => {
+                write!(command, " ./{}", shell_quote(&path.to_string_lossy()))?
+            }
+            ExecutionCommand::TypstCompilation { .. } => {
+                bail!("'{}' cannot be exported to a Makefile", group.description)
+            }
+        }
+        for arg in &exec.args {
+            write!(command, " {}", shell_quote(arg))?;
+        }
+        match &exec.stdin {
+            ExecutionInputBehaviour::File(file) => write!(command, " < $(CURDIR)/{}", paths[file])?,
+            ExecutionInputBehaviour::Path(path) => {
+                write!(command, " < {}", shell_quote(&path.to_string_lossy()))?
+            }
+            ExecutionInputBehaviour::Ignored => command += " < /dev/null",
+            ExecutionInputBehaviour::Inherit => {}
+        }
+        for (fd, output, name) in [(1, &exec.stdout, "stdout"), (2, &exec.stderr, "stderr")] {
+            match output {
+                ExecutionOutputBehaviour::Capture { .. } => write!(command, " {fd}> {name}")?,
+                ExecutionOutputBehaviour::Path(path) => {
+                    write!(command, " {fd}> {}", shell_quote(&path.to_string_lossy()))?
+                }
+                ExecutionOutputBehaviour::Ignored => write!(command, " {fd}> /dev/null")?,
+                ExecutionOutputBehaviour::Inherit => {}
+            }
+        }
+        writeln!(makefile, "{command}")?;
+        writeln!(makefile, "\t@touch $@")?;
+        let mut outputs: Vec<&str> = group
+            .outputs()
+            .iter()
+            .map(|file| paths[file].as_str())
+            .collect();
+        outputs.sort();
+        if !outputs.is_empty() {
+            writeln!(makefile, "{}: {dir}/.done ;", outputs.join(" "))?;
+        }
+        writeln!(makefile)?;
+    }
+
+    for (file, dest) in &written {
+        let source = &paths[file];
+        // a static file written to its own path
+        if source == dest {
+            continue;
+        }
+        writeln!(
+            makefile,
+            "{dest}: {source}\n\t@mkdir -p $(@D)\n\tcp {source} $@\n"
+        )?;
+    }
+    Ok(makefile)
+}
+
+/// A path usable as a target or a prerequisite of the Makefile.
+fn make_path(path: &Path) -> Result<String, Error> {
+    let path = path.to_string_lossy();
+    if path.contains(|c: char| c.is_whitespace() || c == ':' || c == '$' || c == '#') {
+        bail!("The path '{path}' cannot be used in a Makefile");
+    }
+    Ok(path.into_owned())
+}
+
+/// Quote a shell word inside a recipe of the Makefile.
+fn shell_quote(word: &str) -> String {
+    shell_words::quote(word).replace('$', "$$")
+}
+
+/// Sort key of the provided files, to write them in a stable order.
+fn provided_description(file: &ProvidedFile) -> String {
+    match file {
+        ProvidedFile::LocalFile { local_path, .. } => local_path.to_string_lossy().into_owned(),
+        ProvidedFile::Content { file, .. }
+        | ProvidedFile::StoredFile { file, .. }
+        | ProvidedFile::Late { file } => file.description.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ioi::{InputGenerator, InputValidator};
+    use crate::{EvaluationData, SourceFile};
+
+    #[test]
+    fn test_generate_makefile() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let task_path = tmpdir.path();
+        std::fs::create_dir(task_path.join("gen")).unwrap();
+        let gen_path = task_path.join("gen/gen.py");
+        std::fs::write(&gen_path, "print(42)").unwrap();
+        let val_path = task_path.join("gen/val.py");
+        std::fs::write(&val_path, "").unwrap();
+        let (mut eval, _recv) = EvaluationData::new(task_path);
+
+        let source = SourceFile::new(&gen_path, "", "", None, None::<PathBuf>).unwrap();
+        let generator = InputGenerator::Custom(Arc::new(source), vec!["1234".into()]);
+        let (input, _) = generator
+            .generate_and_bind(&mut eval, task_path, &[], 0, 0)
+            .unwrap();
+        let source = SourceFile::new(&val_path, "", "", None, None::<PathBuf>).unwrap();
+        let validator = InputValidator::Custom(Arc::new(source), vec![]);
+        validator
+            .validate_and_bind(&mut eval, task_path, 0, None, 0, input, &[])
+            .unwrap();
+
+        let makefile = generate_makefile(task_path, &mut eval.dag).unwrap();
+        assert!(makefile.contains("all: input/input0.txt build/exec0/.done build/exec1/.done"));
+        // the generator runs with its seed
+        assert!(makefile.contains("# Generation of input file of testcase 0, subtask 0"));
+        assert!(makefile.contains("cp gen/gen.py build/exec0/gen.py"));
+        assert!(makefile.contains(" 1234 < /dev/null 1> stdout 2> stderr"));
+        // the validator reads the generated input
+        assert!(makefile.contains("build/exec1/.done: build/exec0/stdout gen/val.py"));
+        assert!(makefile.contains("input/input0.txt: build/exec0/stdout"));
+    }
+
+    #[test]
+    fn test_generate_makefile_stored_file() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let key = task_maker_store::FileStoreKey::from_content(b"42");
+        let generator = InputGenerator::StoredFile(key);
+        let (input, _) = generator
+            .generate_and_bind(&mut eval, tmpdir.path(), &[], 0, 0)
+            .unwrap();
+        let val_path = tmpdir.path().join("val.py");
+        std::fs::write(&val_path, "").unwrap();
+        let source = SourceFile::new(&val_path, "", "", None, None::<PathBuf>).unwrap();
+        let validator = InputValidator::Custom(Arc::new(source), vec![]);
+        validator
+            .validate_and_bind(&mut eval, tmpdir.path(), 0, None, 0, input, &[])
+            .unwrap();
+        let err = generate_makefile(tmpdir.path(), &mut eval.dag).unwrap_err();
+        assert!(err.to_string().contains("only in the store"), "{err}");
+    }
+}
@@ -15,21 +15,31 @@
 //! a `Checker`, a program that computes the score of the testcase given the input file, the output
 //! file and the _correct_ output file (the one produced by the jury).
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use curses_ui::CursesUI;
 pub use dag::*;
 pub use format::italian_yaml;
 use itertools::Itertools;
+pub use junit::{JUnitCriterion, JUnitReport};
+pub use makefile::generate_makefile;
+pub use pinned_inputs::{parse_pinned_inputs, snapshot_dir, write_frozen_manifest, PinnedTestcase};
 use serde::{Deserialize, Serialize};
 pub use statement::*;
+pub use summary::{
+    CompilationSummary, EvaluationSummary, SolutionSummary, SubtaskSummary, SummaryReport,
+    TestcaseSummary, EVALUATION_SUMMARY_SCHEMA_VERSION,
+};
 pub use task_info::*;
-use task_maker_dag::{ExecutionDAGConfig, FileUuid};
-use task_maker_diagnostics::CodeSpan;
+use task_maker_dag::{
+    ExecutionDAGConfig, ExecutionGroupUuid, ExecutionResult, ExecutionStatus, FileUuid,
+    ProvideHandle, SkipHandle,
+};
+use task_maker_diagnostics::{CodeSpan, Diagnostic};
 use task_maker_lang::GraderMap;
 pub use ui_state::*;
 use unic::normal::StrNormalForm;
@@ -38,16 +48,21 @@ use wildmatch::WildMatch;
 use crate::ioi::format::italian_yaml::TM_ALLOW_DELETE_COOKIE;
 use crate::ioi::italian_yaml::is_tm_deletable;
 use crate::sanity_checks::SanityChecks;
-use crate::solution::SolutionInfo;
+use crate::solution::{SolutionInfo, SolutionScoreCheck};
 use crate::ui::*;
-use crate::{EvaluationConfig, EvaluationData, TaskInfo, UISender};
+use crate::{EvaluationConfig, EvaluationData, SourceFile, TaskInfo, UISender};
 
 mod curses_ui;
 mod dag;
 pub(crate) mod finish_ui;
 mod format;
+mod junit;
+mod makefile;
+mod pinned_inputs;
 pub mod sanity_checks;
+mod scorer;
 mod statement;
+mod summary;
 pub(crate) mod task_info;
 pub(crate) mod ui_state;
 
@@ -74,6 +89,22 @@ pub struct ScoreManager {
     subtask_testcases: HashMap<SubtaskId, Vec<TestcaseId>>,
     /// The aggregator to use for computing the subtask scores.
     aggregator: TestcaseScoreAggregator,
+    /// Whether the subtask scores are computed by the scoring script, replacing the aggregator.
+    uses_scorer: bool,
+    /// The late file with the input of the scoring script, provided with the handle when all the
+    /// testcases are scored.
+    scorer_input: Option<(FileUuid, ProvideHandle)>,
+    /// The checks on the score of the subtasks, with the subtask they apply to.
+    score_checks: Vec<(SubtaskId, SolutionScoreCheck)>,
+    /// Stop the evaluation of the solution after this number of testcases without full score,
+    /// skipping its pending executions with the handle.
+    stop_after: Option<(u64, SkipHandle)>,
+    /// The number of testcases without full score so far.
+    failed_testcases: u64,
+    /// The executions of the solution, skipped when the evaluation is stopped.
+    evaluations: Vec<ExecutionGroupUuid>,
+    /// Whether the evaluation of the solution has been stopped, making the scores lower bounds.
+    stopped: bool,
 }
 
 /// A simple struct that generates input validators for a given subtask.
@@ -119,10 +150,39 @@ pub struct IOITask {
     pub time_limit: Option<f64>,
     /// The memory limit in MiB of the execution of the solution, if `None` it's unlimited.
     pub memory_limit: Option<u64>,
+    /// If set, the time limit on each testcase is this multiple of the time taken by the official
+    /// solution on it. `time_limit` is still enforced.
+    #[serde(default)]
+    pub relative_time_limit: Option<f64>,
+    /// The CPU time limit in seconds for compiling the source files, if `None` the default one is
+    /// used.
+    #[serde(default)]
+    pub compilation_time_limit: Option<f64>,
+    /// The memory limit in MiB for compiling the source files, if `None` the default one is used.
+    #[serde(default)]
+    pub compilation_memory_limit: Option<u64>,
+    /// The wall time limit in seconds of the generators and of the validators, if `None` the
+    /// default one is used.
+    #[serde(default)]
+    pub generation_wall_time_limit: Option<f64>,
     /// The input file for the solutions, usually `Some("input.txt")` or `None` (stdin).
     pub infile: Option<PathBuf>,
     /// The output file for the solutions, usually `Some("output.txt")` or `None` (stdout).
     pub outfile: Option<PathBuf>,
+    /// The files the generator writes in its working directory besides the input file. They are
+    /// stored next to the input file and given, with the same names, to the validator and to the
+    /// checker.
+    #[serde(default)]
+    pub generator_outputs: Vec<PathBuf>,
+    /// Whether the generated input and output files are normalized: the UTF-8 BOM is removed and
+    /// the CRLF line endings are converted to LF, before validating and storing them. The files
+    /// that look binary are left untouched.
+    #[serde(default)]
+    pub normalize_files: bool,
+    /// Whether this is an output only task, whose contestants submit the output files instead of a
+    /// solution.
+    #[serde(default)]
+    pub output_only: bool,
     /// The list of the subtasks.
     pub subtasks: HashMap<SubtaskId, SubtaskInfo>,
     /// The list of the testcases.
@@ -133,9 +193,16 @@ pub struct IOITask {
     /// The aggregator to use to compute the score of the subtask based on the score of the
     /// testcases.
     pub testcase_score_aggregator: TestcaseScoreAggregator,
+    /// The script that computes the score of the subtasks from the scores of the testcases. If
+    /// present, `testcase_score_aggregator` is not used.
+    #[serde(default)]
+    pub scorer: Option<Arc<SourceFile>>,
     /// The number of decimal digits when displaying the scores.
     #[serde(default)]
     pub score_precision: usize,
+    /// How the scores are rounded to `score_precision` digits when displaying them.
+    #[serde(default)]
+    pub score_rounding: ScoreRounding,
     /// The graders registered for this task.
     pub grader_map: Arc<GraderMap>,
     /// The booklets to compile for this task.
@@ -172,6 +239,10 @@ pub struct SubtaskInfo {
     pub testcases_owned: Vec<TestcaseId>,
     /// The validator for the input files of this subtask.
     pub input_validator: InputValidator,
+    /// The validator that checks all the input files of this subtask at once, for the properties
+    /// that involve more than one testcase. If it fails, no solution is evaluated on the subtask.
+    #[serde(default)]
+    pub subtask_validator: InputValidator,
     /// The span of the definition of this subtask.
     pub span: Option<CodeSpan>,
     /// Whether this subtask was created automatically since no subtask was present in gen/GEN.
@@ -194,21 +265,38 @@ pub struct TestcaseInfo {
     pub input_generator: InputGenerator,
     /// The generator of the output file for this testcase.
     pub output_generator: OutputGenerator,
+    /// The validator to use for this testcase instead of the one of its subtask.
+    #[serde(default)]
+    pub input_validator: Option<InputValidator>,
     /// The generated input file UUID. This is set only after the DAG is built.
     pub input_file: Option<FileUuid>,
     /// The generated official output file UUID. This is set only after the DAG is built.
     pub official_output_file: Option<FileUuid>,
+    /// The other files written by the generator, with their names. This is set only after the DAG
+    /// is built.
+    #[serde(default)]
+    pub generator_output_files: Vec<(PathBuf, FileUuid)>,
 }
 
 impl IOITask {
     /// Try to make a `Task` from the specified path. Will return `Err` if the format of the task
     /// is not IOI or if the task is corrupted and cannot be parsed.
     pub fn new<P: AsRef<Path>>(path: P, eval_config: &EvaluationConfig) -> Result<IOITask, Error> {
-        if path.as_ref().join("task.toml").exists() {
-            format::italian_toml::parse_task(path, eval_config)
+        let mut task = if path.as_ref().join("task.toml").exists() {
+            format::italian_toml::parse_task(path, eval_config)?
         } else {
-            format::italian_yaml::parse_task(path, eval_config)
+            format::italian_yaml::parse_task(path, eval_config)?
+        };
+        if let Some(manifest) = &eval_config.pinned_inputs {
+            pinned_inputs::pin_inputs(&mut task, manifest)?;
         }
+        if let Some(num_processes) = eval_config.num_processes {
+            match &mut task.task_type {
+                TaskType::Communication(data) => data.num_processes = num_processes,
+                _ => bail!("The number of processes can be changed only in communication tasks"),
+            }
+        }
+        Ok(task)
     }
 
     /// Create a "fake" `IOITask` that will not contain any data.
@@ -223,13 +311,22 @@ impl IOITask {
             title: "".to_string(),
             time_limit: None,
             memory_limit: None,
+            relative_time_limit: None,
+            compilation_time_limit: None,
+            compilation_memory_limit: None,
+            generation_wall_time_limit: None,
             infile: None,
             outfile: None,
+            generator_outputs: vec![],
+            normalize_files: false,
+            output_only: false,
             subtasks: Default::default(),
             testcases: Default::default(),
             input_validator_generator: Default::default(),
             testcase_score_aggregator: TestcaseScoreAggregator::Min,
+            scorer: None,
             score_precision: 0,
+            score_rounding: Default::default(),
             grader_map: Arc::new(GraderMap::new::<&Path>(vec![])),
             booklets: vec![],
             difficulty: None,
@@ -265,6 +362,7 @@ impl IOITask {
             )),
             UIType::Json => Ok(Box::new(JsonUI::new())),
             UIType::Silent => Ok(Box::new(SilentUI::new())),
+            UIType::Tap => Ok(Box::new(TapUI::new())),
         }
     }
 
@@ -277,6 +375,27 @@ impl IOITask {
         eval.sender.send(UIMessage::IOITask {
             task: Box::new(self.clone()),
         })?;
+        // the limits must be set before any source file is compiled
+        if let Some(time_limit) = self.compilation_time_limit {
+            if time_limit <= 0.0 {
+                bail!("The compilation time limit must be positive, got {time_limit}");
+            }
+            eval.dag.config_mut().compilation_time_limit(time_limit);
+        }
+        if let Some(memory_limit) = self.compilation_memory_limit {
+            eval.dag
+                .config_mut()
+                .compilation_memory_limit(Some(memory_limit * 1024));
+        }
+        if let Some(time_limit) = self.generation_wall_time_limit {
+            if time_limit <= 0.0 {
+                bail!("The generation wall time limit must be positive, got {time_limit}");
+            }
+            eval.dag.config_mut().generation_wall_time_limit(time_limit);
+        }
+        eval.dag
+            .config_mut()
+            .normalize_generated_files(self.normalize_files);
         eval.solutions = config.find_solutions(
             &self.path,
             vec!["sol/*"],
@@ -290,16 +409,29 @@ impl IOITask {
             .into_iter()
             .map(|source| {
                 let path = source.source_file.path.clone();
-                Ok((
-                    source,
-                    Arc::new(Mutex::new(ScoreManager::new(
-                        self,
-                        path,
-                        eval.sender.clone(),
-                    )?)),
-                ))
+                let mut score_manager = ScoreManager::with_score_checks(
+                    self,
+                    path,
+                    &source.score_checks,
+                    eval.sender.clone(),
+                )?;
+                if let Some(limit) = config.stop_after {
+                    score_manager.stop_after(limit, eval.dag.skip_handle());
+                }
+                Ok((source, Arc::new(Mutex::new(score_manager))))
             })
             .collect::<Result<_, Error>>()?;
+        if let Some(scorer) = &self.scorer {
+            for (solution, score_manager) in &solutions {
+                scorer::bind_scorer(
+                    eval,
+                    scorer,
+                    &solution.source_file.path,
+                    score_manager.clone(),
+                )
+                .context("Failed to bind the scorer")?;
+            }
+        }
 
         let solution_info = solutions
             .iter()
@@ -313,7 +445,17 @@ impl IOITask {
             .prepare_dag(eval)
             .context("Failed to prepare DAG")?;
 
+        if self.relative_time_limit.is_some() && !matches!(self.task_type, TaskType::Batch(_)) {
+            eval.add_diagnostic(Diagnostic::warning(
+                "The relative time limit is supported only by Batch tasks, it will be ignored",
+            ))?;
+        }
+
         let mut generated_io: HashMap<_, _> = HashMap::new();
+        // The files needed for evaluating the solutions on each generated testcase.
+        let mut to_evaluate = vec![];
+        // The testcases on which the relative time limit cannot be computed.
+        let mut without_reference = vec![];
 
         for subtask in self.subtasks.values() {
             trace!("Executing the generation of subtask {}", subtask.id);
@@ -329,12 +471,20 @@ impl IOITask {
                     .testcases
                     .get(&testcase_id)
                     .expect("Testcase not found in the task");
-                let input = testcase
+                let (input, generator_outputs) = testcase
                     .input_generator
-                    .generate_and_bind(eval, &self.path, subtask.id, testcase.id)
+                    .generate_and_bind(
+                        eval,
+                        &self.path,
+                        &self.generator_outputs,
+                        subtask.id,
+                        testcase.id,
+                    )
                     .context("Failed to bind input generator")?;
-                let val_handle = subtask
+                let val_handle = testcase
                     .input_validator
+                    .as_ref()
+                    .unwrap_or(&subtask.input_validator)
                     .validate_and_bind(
                         eval,
                         &self.path,
@@ -342,47 +492,141 @@ impl IOITask {
                         subtask.name.as_deref(),
                         testcase.id,
                         input,
+                        &generator_outputs,
                     )
                     .context("Failed to bind validator")?;
-                let output = testcase
+                let (output, extra_outputs) = testcase
                     .output_generator
                     .generate_and_bind(self, eval, subtask.id, testcase.id, input, val_handle)
                     .context("Failed to bind output generator")?;
+                // The official solution is timed on the testcases with a static output too.
+                let reference_output = match (&self.task_type, self.relative_time_limit) {
+                    (TaskType::Batch(data), Some(_)) => {
+                        match (&testcase.output_generator, &data.output_generator) {
+                            (OutputGenerator::Custom(_, _), _) => output,
+                            (_, Some(official @ OutputGenerator::Custom(_, _))) => official
+                                .time_and_bind(
+                                    self,
+                                    eval,
+                                    subtask.id,
+                                    testcase.id,
+                                    input,
+                                    val_handle,
+                                )
+                                .context("Failed to bind the timing of the official solution")?,
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if self.relative_time_limit.is_some() && reference_output.is_none() {
+                    without_reference.push(testcase.id);
+                }
                 // Store the generated input and output files for setting them into the task
                 // outside the loop.
-                generated_io.insert(testcase.id, (input, output));
+                generated_io.insert(testcase.id, (input, generator_outputs.clone(), output));
+                to_evaluate.push((
+                    subtask.id,
+                    testcase.id,
+                    val_handle,
+                    extra_outputs,
+                    reference_output,
+                ));
+                // Stop early, before the DAG gets too big.
+                eval.check_executions_limit(config)?;
+            }
+        }
 
-                for (solution, score_manager) in solutions.iter() {
-                    trace!(
-                        "Evaluation of the solution {:?} against subtask {} / testcase {}",
-                        solution.source_file.name(),
-                        subtask.id,
-                        testcase.id
-                    );
+        // The subtask validators need all the inputs of the subtask, so they can be added only
+        // after all the testcases are generated. The solutions are evaluated only after them.
+        let mut subtask_val_handles = HashMap::new();
+        for subtask in self.subtasks.values() {
+            let inputs = subtask
+                .testcases
+                .iter()
+                .filter_map(|id| generated_io.get(id).map(|(input, _, _)| (*id, *input)))
+                .collect_vec();
+            let val_handles = to_evaluate
+                .iter()
+                .filter(|(st, ..)| *st == subtask.id)
+                .filter_map(|(_, _, val_handle, ..)| *val_handle)
+                .collect_vec();
+            let handle = subtask
+                .subtask_validator
+                .validate_subtask_and_bind(
+                    eval,
+                    &self.path,
+                    subtask.id,
+                    subtask.name.as_deref(),
+                    &inputs,
+                    &val_handles,
+                )
+                .context("Failed to bind subtask validator")?;
+            if let Some(handle) = handle {
+                subtask_val_handles.insert(subtask.id, handle);
+            }
+        }
 
-                    self.task_type
-                        .evaluate(
-                            self,
-                            eval,
-                            subtask.id,
-                            testcase.id,
-                            &solution.source_file,
-                            input,
-                            val_handle,
-                            output,
-                            score_manager.clone(),
-                        )
-                        .context("Failed to bind evaluation")?;
+        if matches!(self.task_type, TaskType::Batch(_)) && !without_reference.is_empty() {
+            without_reference.sort_unstable();
+            eval.add_diagnostic(
+                Diagnostic::warning(
+                    "The official solution is missing, the relative time limit is ignored",
+                )
+                .with_note(format!(
+                    "Only the time limit is enforced on the testcases {}",
+                    without_reference.iter().join(", ")
+                )),
+            )?;
+        }
+
+        for (subtask_id, testcase_id, val_handle, extra_outputs, reference_output) in to_evaluate {
+            let (input, generator_outputs, output) = &generated_io[&testcase_id];
+            // The subtask validator waits for all the testcase validators of the subtask.
+            let val_handle = subtask_val_handles.get(&subtask_id).copied().or(val_handle);
+            for (solution, score_manager) in solutions.iter() {
+                trace!(
+                    "Evaluation of the solution {:?} against subtask {} / testcase {}",
+                    solution.source_file.name(),
+                    subtask_id,
+                    testcase_id
+                );
+
+                let group = self
+                    .task_type
+                    .evaluate(
+                        self,
+                        eval,
+                        subtask_id,
+                        testcase_id,
+                        &solution.source_file,
+                        *input,
+                        generator_outputs,
+                        val_handle,
+                        *output,
+                        extra_outputs.clone(),
+                        reference_output,
+                        score_manager.clone(),
+                    )
+                    .context("Failed to bind evaluation")?;
+                if let (Some(group), Some(_)) = (group, config.stop_after) {
+                    bind_stop_after(eval, subtask_id, testcase_id, group, score_manager.clone());
                 }
             }
+            // Stop early, before the DAG gets too big.
+            eval.check_executions_limit(config)?;
         }
         // Store inside the task the FileUuid of the input and official output files. This cannot
         // be done while generating because task cannot be borrowed mutably in the loop.
-        for (testcase_id, (input, output)) in generated_io {
+        for (testcase_id, (input, generator_outputs, output)) in generated_io {
             let testcase = self.testcases.get_mut(&testcase_id).unwrap();
             testcase.input_file = Some(input);
             testcase.official_output_file = output;
+            testcase.generator_output_files = generator_outputs;
         }
+        evaluate_diagnostic_cases(self, eval, &eval.solutions.clone())
+            .context("Failed to bind diagnostic cases")?;
+        eval.check_executions_limit(config)?;
 
         for subtask in self.subtasks.values() {
             for &testcase_id in subtask.testcases.iter() {
@@ -392,8 +636,10 @@ impl IOITask {
                         .get(&testcase_id)
                         .expect("Testcase not found in the task");
 
-                    let _val_handle = subtask
+                    let _val_handle = testcase
                         .input_validator
+                        .as_ref()
+                        .unwrap_or(&subtask.input_validator)
                         .validate_and_bind(
                             eval,
                             &self.path,
@@ -401,15 +647,22 @@ impl IOITask {
                             subtask.name.as_deref(),
                             testcase.id,
                             testcase.input_file.unwrap(),
+                            &testcase.generator_output_files,
                         )
                         .context("Failed to bind validator")?;
                 }
             }
         }
         for booklet in self.booklets.iter() {
-            booklet
-                .build(eval)
-                .context("Failed to bind booklet compilation")?;
+            if config.check_statement {
+                booklet
+                    .validate(eval)
+                    .context("Failed to validate booklet statements")?;
+            } else {
+                booklet
+                    .build(eval)
+                    .context("Failed to bind booklet compilation")?;
+            }
         }
         self.sanity_checks
             .pre_hook(self, eval)
@@ -417,22 +670,83 @@ impl IOITask {
         Ok(())
     }
 
+    /// The files that an evaluation of the task with the provided configuration would write, sorted
+    /// and without duplicates. They include the files written back while parsing the task
+    /// (`gen/GEN` and `task.yaml`) and the ones written by the DAG: input and output files,
+    /// statements and the binaries that are always copied. The binaries copied only because of
+    /// the `copy_exe` option of the DAG are not included.
+    ///
+    /// Nothing is written in dry-run mode.
+    pub fn planned_writes(&self, eval_config: &EvaluationConfig) -> Result<Vec<PathBuf>, Error> {
+        if eval_config.dry_run {
+            return Ok(vec![]);
+        }
+        let mut writes = if self.path.join("task.toml").exists() {
+            format::italian_toml::planned_writes(&self.path)?
+        } else {
+            format::italian_yaml::planned_writes(&self.path)?
+        };
+        // The receiver is kept alive for the UI messages sent while building the DAG.
+        let (mut eval, _receiver) = EvaluationData::new(&self.path);
+        self.clone()
+            .build_dag(&mut eval, eval_config)
+            .context("Failed to build the DAG of the task")?;
+        writes.extend(
+            eval.dag
+                .file_callbacks()
+                .values()
+                .filter_map(|callbacks| callbacks.write_to.as_ref())
+                .map(|write_to| write_to.dest.clone()),
+        );
+        writes.sort();
+        writes.dedup();
+        Ok(writes)
+    }
+
     /// Hook called after the execution completed, useful for sending messages to the UI about the
     /// results of the sanity checks with data available only after the evaluation.
     pub fn sanity_check_post_hook(&self, eval: &mut EvaluationData) -> Result<(), Error> {
         self.sanity_checks.post_hook(self, eval)
     }
 
+    /// Run only the sanity checks that do not require the evaluation of the task, returning the
+    /// names of the skipped ones.
+    pub fn static_sanity_checks(
+        &self,
+        eval: &mut EvaluationData,
+        config: &EvaluationConfig,
+    ) -> Result<Vec<&'static str>, Error> {
+        eval.solutions = config.find_solutions(
+            &self.path,
+            vec!["sol/*"],
+            Some(self.grader_map.clone()),
+            eval,
+        );
+        self.sanity_checks.static_checks(self, eval)
+    }
+
     /// Clean the task folder removing the files that can be generated automatically.
     pub fn clean(&self) -> Result<(), Error> {
         for dir in &["input", "output"] {
+            let mut patterns = vec!["*.txt".to_string()];
+            if *dir == "input" {
+                // the other outputs of the generators are stored next to the input files
+                patterns.extend(
+                    self.generator_outputs
+                        .iter()
+                        .map(|name| format!("input*.{}", name.to_string_lossy())),
+                );
+            }
             let dir = self.path.join(dir);
             if !dir.exists() {
                 continue;
             }
-            for file in glob::glob(dir.join("*.txt").to_string_lossy().as_ref())
-                .context("Invalid glob pattern")?
-            {
+            let files = patterns
+                .iter()
+                .map(|pattern| glob::glob(dir.join(pattern).to_string_lossy().as_ref()))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Invalid glob pattern")?;
+            for file in files.into_iter().flatten() {
                 let file = match file {
                     Ok(file) => file,
                     Err(e) => {
@@ -487,6 +801,17 @@ impl IOITask {
                 }
             }
         }
+        // remove the compiled scorer
+        if let Some(scorer) = &self.scorer {
+            if let Some(path) = scorer.write_bin_to() {
+                if path.exists() {
+                    info!("Removing {}", path.display());
+                    std::fs::remove_file(&path).with_context(|| {
+                        format!("Failed to remove compiled scorer at {}", path.display())
+                    })?;
+                }
+            }
+        }
         // remove the gen/GEN if there is cases.gen
         let gen_gen_path = self.path.join("gen/GEN");
         let cases_gen_path = self.path.join("gen/cases.gen");
@@ -561,8 +886,10 @@ impl TestcaseInfo {
             id,
             input_generator,
             output_generator,
+            input_validator: None,
             input_file: None,
             official_output_file: None,
+            generator_output_files: vec![],
         }
     }
 }
@@ -574,6 +901,25 @@ impl ScoreManager {
         solution: PathBuf,
         sender: Arc<Mutex<UIMessageSender>>,
     ) -> Result<ScoreManager, Error> {
+        Self::with_score_checks(task, solution, &[], sender)
+    }
+
+    /// Make a new `ScoreManager` that also verifies the score checks of the solution, emitting an
+    /// error diagnostic for each subtask whose score doesn't satisfy them.
+    pub fn with_score_checks(
+        task: &IOITask,
+        solution: PathBuf,
+        score_checks: &[SolutionScoreCheck],
+        sender: Arc<Mutex<UIMessageSender>>,
+    ) -> Result<ScoreManager, Error> {
+        let score_checks = score_checks
+            .iter()
+            .flat_map(|check| {
+                task.find_subtasks_by_pattern_name(&check.subtask_name_pattern)
+                    .into_iter()
+                    .map(move |subtask| (subtask.id, check.clone()))
+            })
+            .collect();
         let mut ret = ScoreManager {
             solution,
             subtask_scores: task.subtasks.keys().map(|st_num| (*st_num, None)).collect(),
@@ -589,8 +935,18 @@ impl ScoreManager {
                 .map(|(st_num, st)| (*st_num, st.testcases.clone()))
                 .collect(),
             aggregator: task.testcase_score_aggregator,
+            uses_scorer: task.scorer.is_some(),
+            scorer_input: None,
+            score_checks,
+            stop_after: None,
+            failed_testcases: 0,
+            evaluations: Vec::new(),
+            stopped: false,
         };
 
+        if ret.uses_scorer {
+            return Ok(ret);
+        }
         for (st_num, st) in &task.subtasks {
             if st.testcases.is_empty() {
                 ret.score_subtask(*st_num, sender.clone())?;
@@ -599,6 +955,22 @@ impl ScoreManager {
         Ok(ret)
     }
 
+    /// Stop the evaluation of the solution after `limit` testcases without full score, asking
+    /// `skip_handle` to skip the executions added with `add_evaluation`.
+    pub fn stop_after(&mut self, limit: u64, skip_handle: SkipHandle) {
+        self.stop_after = Some((limit, skip_handle));
+    }
+
+    /// Add the execution of the solution on a testcase, to be skipped if the evaluation is stopped.
+    pub fn add_evaluation(&mut self, execution: ExecutionGroupUuid) {
+        self.evaluations.push(execution);
+    }
+
+    /// Whether the evaluation of the solution has been stopped by `stop_after`.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
     /// Store the score of the testcase and eventually compute the score of the subtask and of the
     /// task.
     pub fn score(
@@ -617,7 +989,25 @@ impl ScoreManager {
             score,
             message,
         })?;
+        if let Some((limit, skip_handle)) = &self.stop_after {
+            if score < 1.0 && !self.stopped {
+                self.failed_testcases += 1;
+                if self.failed_testcases >= *limit {
+                    self.stopped = true;
+                    skip_handle.skip(std::mem::take(&mut self.evaluations));
+                    let diagnostic = Diagnostic::warning(format!(
+                        "Stopped the evaluation of {} after {limit} testcases without full score",
+                        self.solution.display()
+                    ))
+                    .with_note("Its score is a lower bound: the skipped testcases are scored zero");
+                    sender.add_diagnostic(diagnostic)?;
+                }
+            }
+        }
 
+        if self.uses_scorer {
+            return self.provide_scorer_input();
+        }
         for (subtask_id, subtask) in self
             .subtask_scores
             .iter()
@@ -631,6 +1021,119 @@ impl ScoreManager {
         Ok(())
     }
 
+    /// Set the late file with the input of the scoring script, it's provided with `provide_handle`
+    /// as soon as all the testcases are scored.
+    pub fn set_scorer_input(&mut self, input: FileUuid, provide_handle: ProvideHandle) {
+        self.scorer_input = Some((input, provide_handle));
+        // the scores may be already known, for example if there are no testcases
+        if let Err(e) = self.provide_scorer_input() {
+            warn!("Failed to provide the input of the scorer: {e:?}");
+        }
+    }
+
+    /// Provide the input of the scoring script, if all the testcases are scored.
+    fn provide_scorer_input(&mut self) -> Result<(), Error> {
+        if !self.testcase_scores.values().all(Option::is_some) {
+            return Ok(());
+        }
+        if let Some((input, provide_handle)) = self.scorer_input.take() {
+            let content = scorer::scorer_input(&self.scorer_subtasks())?;
+            provide_handle.provide(input, content);
+        }
+        Ok(())
+    }
+
+    /// The subtasks with the scores of their testcases, as given to the scoring script.
+    fn scorer_subtasks(&self) -> BTreeMap<SubtaskId, scorer::ScorerSubtask> {
+        self.subtask_testcases
+            .iter()
+            .map(|(&subtask_id, testcases)| {
+                let testcases = testcases
+                    .iter()
+                    .map(|tc| (*tc, self.testcase_scores[tc].unwrap_or(0.0)))
+                    .collect();
+                let max_score = self.max_subtask_scores[&subtask_id];
+                (
+                    subtask_id,
+                    scorer::ScorerSubtask {
+                        max_score,
+                        testcases,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Compute the scores of all the subtasks from the result of the scoring script. If the scorer
+    /// failed, all the subtasks get zero points.
+    pub fn scorer_done(
+        &mut self,
+        result: &ExecutionResult,
+        sender: Arc<Mutex<UIMessageSender>>,
+    ) -> Result<(), Error> {
+        let subtasks = self.scorer_subtasks();
+        let scores = match result.status {
+            ExecutionStatus::Success => {
+                let output = result
+                    .stdout
+                    .as_ref()
+                    .map(|stdout| String::from_utf8_lossy(stdout).to_string())
+                    .unwrap_or_default();
+                scorer::parse_scorer_output(&output, &subtasks)
+            }
+            _ => {
+                let stderr = result
+                    .stderr
+                    .as_ref()
+                    .map(|stderr| String::from_utf8_lossy(stderr).to_string())
+                    .unwrap_or_default();
+                Err(anyhow!(
+                    "The scorer exited with {:?}: {}",
+                    result.status,
+                    stderr.trim()
+                ))
+            }
+        };
+        let scores = match scores {
+            Ok(scores) => scores,
+            Err(e) => {
+                sender.add_diagnostic(
+                    Diagnostic::error(format!(
+                        "The scorer failed on {}, all its subtasks are scored zero",
+                        self.solution.display()
+                    ))
+                    .with_note(format!("{e:#}")),
+                )?;
+                subtasks.keys().map(|st| (*st, 0.0)).collect()
+            }
+        };
+        for (subtask_id, score) in scores.iter().sorted_by_key(|(st, _)| **st) {
+            let max_score = self.max_subtask_scores[subtask_id];
+            let normalized_score = if max_score > 0.0 {
+                score / max_score
+            } else {
+                self.aggregator.aggregate(
+                    self.subtask_testcases[subtask_id]
+                        .iter()
+                        .map(|tc| self.testcase_scores[tc].unwrap_or(0.0)),
+                )
+            };
+            self.subtask_scores.insert(*subtask_id, Some(*score));
+            sender.send(UIMessage::IOISubtaskScore {
+                subtask: *subtask_id,
+                solution: self.solution.clone(),
+                score: *score,
+                normalized_score,
+            })?;
+            self.run_score_checks(*subtask_id, *score, &sender)?;
+        }
+        sender.send(UIMessage::IOITaskScore {
+            solution: self.solution.clone(),
+            score: scores.values().sum(),
+        })?;
+        Ok(())
+    }
+
     fn score_subtask(
         &mut self,
         subtask_id: SubtaskId,
@@ -653,6 +1156,7 @@ impl ScoreManager {
                 score: subtask_score,
                 normalized_score,
             })?;
+            self.run_score_checks(subtask_id, subtask_score, &sender)?;
             if self.subtask_scores.values().all(Option::is_some) {
                 let task_score: f64 = self
                     .subtask_scores
@@ -667,4 +1171,32 @@ impl ScoreManager {
         }
         Ok(())
     }
+
+    /// Verify the score checks that apply to a subtask, now that its score is known.
+    fn run_score_checks(
+        &self,
+        subtask_id: SubtaskId,
+        score: f64,
+        sender: &Arc<Mutex<UIMessageSender>>,
+    ) -> Result<(), Error> {
+        // the scores of a stopped solution are only lower bounds
+        if self.stopped {
+            return Ok(());
+        }
+        let max_score = self.max_subtask_scores[&subtask_id];
+        for (_, check) in self.score_checks.iter().filter(|(st, _)| *st == subtask_id) {
+            if check.expectation.check(score, max_score) {
+                continue;
+            }
+            sender.add_diagnostic(
+                Diagnostic::error(format!(
+                    "Solution {} scored {score} points in subtask {subtask_id}, but {} was expected",
+                    self.solution.display(),
+                    check.expectation
+                ))
+                .with_code_span(check.code_span.clone()),
+            )?;
+        }
+        Ok(())
+    }
 }
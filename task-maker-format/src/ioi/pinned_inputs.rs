@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Error};
+use task_maker_store::FileStoreKey;
+
+use crate::ioi::{IOITask, InputGenerator, InputValidator, OutputGenerator, TestcaseId};
+
+/// A testcase whose files are taken from the store instead of being generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedTestcase {
+    /// The key of the input file in the store.
+    pub input: FileStoreKey,
+    /// Whether the testcase comes from a frozen snapshot: its input file has already been
+    /// validated, so the validation is skipped.
+    pub frozen: bool,
+    /// The key of the output file in the store, if it's pinned as well.
+    pub output: Option<FileStoreKey>,
+}
+
+/// Parse a manifest of pinned input files. Each line contains the id of a testcase and the hash of
+/// its input file in the store, separated by spaces. Empty lines and the ones starting with `#` are
+/// ignored.
+///
+/// The lines of a frozen snapshot, as written by `task-maker-tools freeze`, have a third column with
+/// the hash of the output file, or `-` if the testcase has no output file.
+pub fn parse_pinned_inputs(manifest: &str) -> Result<HashMap<TestcaseId, PinnedTestcase>, Error> {
+    let mut pinned = HashMap::new();
+    for (index, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let lineno = index + 1;
+        let (testcase, hash, output) = match line.split_whitespace().collect::<Vec<_>>().as_slice()
+        {
+            [testcase, hash] => (*testcase, *hash, None),
+            [testcase, hash, output] => (*testcase, *hash, Some(*output)),
+            _ => bail!(
+                "Invalid line {lineno} of the manifest: expecting 'testcase hash [output hash]'"
+            ),
+        };
+        let testcase: TestcaseId = testcase
+            .parse()
+            .with_context(|| format!("Invalid testcase id at line {lineno} of the manifest"))?;
+        let input: FileStoreKey = hash
+            .parse()
+            .with_context(|| format!("Invalid hash at line {lineno} of the manifest"))?;
+        let frozen = output.is_some();
+        let output = match output {
+            None | Some("-") => None,
+            Some(output) => Some(output.parse().with_context(|| {
+                format!("Invalid output hash at line {lineno} of the manifest")
+            })?),
+        };
+        let testcase_info = PinnedTestcase {
+            input,
+            frozen,
+            output,
+        };
+        if pinned.insert(testcase, testcase_info).is_some() {
+            bail!("Testcase {testcase} is pinned more than once in the manifest");
+        }
+    }
+    Ok(pinned)
+}
+
+/// The directory with the files of the frozen snapshot whose manifest is at `manifest`. It's next
+/// to the manifest, with the `files` extension (e.g. `frozen.files` for `frozen.txt`), and each
+/// file in it is named after its hash.
+pub fn snapshot_dir(manifest: &Path) -> PathBuf {
+    manifest.with_extension("files")
+}
+
+/// The path of the file with the given hash inside the snapshot directory, checking that it has not
+/// been modified after the snapshot was frozen.
+fn snapshot_file(dir: &Path, key: &FileStoreKey) -> Result<PathBuf, Error> {
+    let path = dir.join(key.to_string());
+    let actual = FileStoreKey::from_file(&path)
+        .with_context(|| format!("Failed to read frozen file {}", path.display()))?;
+    if &actual != key {
+        bail!(
+            "The frozen file {} has been modified after the snapshot was frozen",
+            path.display()
+        );
+    }
+    Ok(path)
+}
+
+/// Replace the generators of the testcases listed in the manifest at `path` with the pinned files,
+/// without running the generators. The plain pinned inputs are taken from the store, while the
+/// files of a frozen snapshot are taken from its [`snapshot_dir`]. The frozen testcases are not
+/// validated again, and the subtask validator is skipped for the subtasks with only frozen
+/// testcases.
+pub(crate) fn pin_inputs(task: &mut IOITask, path: &Path) -> Result<(), Error> {
+    let manifest = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read pinned inputs from {}", path.display()))?;
+    let pinned = parse_pinned_inputs(&manifest)
+        .with_context(|| format!("Invalid pinned inputs manifest {}", path.display()))?;
+    let dir = snapshot_dir(path);
+    for (testcase_id, pinned) in &pinned {
+        let Some(testcase) = task.testcases.get_mut(testcase_id) else {
+            bail!("Testcase {testcase_id} is pinned, but it's not in the task");
+        };
+        if !pinned.frozen {
+            testcase.input_generator = InputGenerator::StoredFile(pinned.input.clone());
+            continue;
+        }
+        testcase.input_generator = InputGenerator::StaticFile(snapshot_file(&dir, &pinned.input)?);
+        testcase.input_validator = Some(InputValidator::AssumeValid);
+        if let Some(output) = &pinned.output {
+            testcase.output_generator = OutputGenerator::StaticFile(snapshot_file(&dir, output)?);
+        }
+    }
+    for subtask in task.subtasks.values_mut() {
+        let all_frozen = subtask
+            .testcases
+            .iter()
+            .all(|tc| pinned.get(tc).is_some_and(|pinned| pinned.frozen));
+        if all_frozen && !subtask.testcases.is_empty() {
+            subtask.subtask_validator = InputValidator::AssumeValid;
+        }
+    }
+    Ok(())
+}
+
+/// Write the manifest of a frozen snapshot, with the hashes of the input and output files of each
+/// testcase. `None` is used for the testcases without an output file.
+pub fn write_frozen_manifest(
+    testcases: &BTreeMap<TestcaseId, (FileStoreKey, Option<FileStoreKey>)>,
+) -> String {
+    let mut manifest = String::from("# frozen snapshot: testcase input-hash output-hash\n");
+    for (testcase, (input, output)) in testcases {
+        let output = output
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |output| output.to_string());
+        manifest += &format!("{testcase} {input} {output}\n");
+    }
+    manifest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pinned_inputs() {
+        let key = FileStoreKey::from_content(b"5\n");
+        let manifest = format!("# pinned inputs\n\n0 {key}\n  3   {key}  \n");
+        let pinned = parse_pinned_inputs(&manifest).unwrap();
+        assert_eq!(pinned.len(), 2);
+        assert_eq!(pinned[&0].input, key);
+        assert_eq!(pinned[&3].input, key);
+        assert!(!pinned[&0].frozen);
+        assert_eq!(pinned[&0].output, None);
+    }
+
+    #[test]
+    fn test_parse_frozen_manifest() {
+        let input = FileStoreKey::from_content(b"5\n");
+        let output = FileStoreKey::from_content(b"25\n");
+        let testcases = BTreeMap::from([
+            (0, (input.clone(), Some(output.clone()))),
+            (1, (input.clone(), None)),
+        ]);
+        let manifest = write_frozen_manifest(&testcases);
+        let pinned = parse_pinned_inputs(&manifest).unwrap();
+        assert_eq!(pinned.len(), 2);
+        assert!(pinned[&0].frozen);
+        assert_eq!(pinned[&0].input, input);
+        assert_eq!(pinned[&0].output, Some(output));
+        assert!(pinned[&1].frozen);
+        assert_eq!(pinned[&1].output, None);
+    }
+
+    #[test]
+    fn test_snapshot_file() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let dir = snapshot_dir(&tmpdir.path().join("frozen.txt"));
+        assert_eq!(dir, tmpdir.path().join("frozen.files"));
+        std::fs::create_dir(&dir).unwrap();
+        let key = FileStoreKey::from_content(b"5\n");
+        assert!(snapshot_file(&dir, &key).is_err());
+        std::fs::write(dir.join(key.to_string()), "5\n").unwrap();
+        assert_eq!(
+            snapshot_file(&dir, &key).unwrap(),
+            dir.join(key.to_string())
+        );
+        std::fs::write(dir.join(key.to_string()), "6\n").unwrap();
+        assert!(snapshot_file(&dir, &key).is_err());
+    }
+
+    #[test]
+    fn test_parse_pinned_inputs_invalid() {
+        let key = FileStoreKey::from_content(b"5\n");
+        assert!(parse_pinned_inputs("0").is_err());
+        assert!(parse_pinned_inputs(&format!("zero {key}")).is_err());
+        assert!(parse_pinned_inputs("0 nope").is_err());
+        assert!(parse_pinned_inputs(&format!("0 {key}\n0 {key}")).is_err());
+        assert!(parse_pinned_inputs(&format!("0 {key} nope")).is_err());
+        assert!(parse_pinned_inputs(&format!("0 {key} {key} {key}")).is_err());
+    }
+}
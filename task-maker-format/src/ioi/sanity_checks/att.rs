@@ -15,7 +15,7 @@ use super::io::CheckEndWithNewLine;
 use crate::ioi::sanity_checks::check_missing_graders;
 use crate::ioi::{IOITask, InputGenerator, TaskType, TestcaseId};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
-use crate::{list_files, EvaluationData, SolutionCheck, SourceFile, UISender};
+use crate::{list_files, EvaluationData, SolutionCheck, SolutionScoreCheck, SourceFile, UISender};
 
 /// Check that all the graders are present inside att.
 #[derive(Debug, Default)]
@@ -96,7 +96,7 @@ impl AttSampleFiles {
                     testcases.push(path);
                 }
                 // This subtask is not with the sample cases.
-                InputGenerator::Custom(_, _) => return vec![],
+                InputGenerator::Custom(_, _) | InputGenerator::StoredFile(_) => return vec![],
             }
         }
         testcases
@@ -114,6 +114,10 @@ impl SanityCheck for AttSampleFiles {
         SanityCheckCategory::Attachments
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn post_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         let mut no_sample = true;
         let samples_from_task = Self::extract_sample_files_from_task(task);
@@ -195,6 +199,10 @@ impl SanityCheck for AttSampleFilesValid {
         SanityCheckCategory::Attachments
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         let validator = &task.input_validator_generator;
         let task_type = if let TaskType::Batch(data) = &task.task_type {
@@ -223,6 +231,7 @@ impl SanityCheck for AttSampleFilesValid {
                     Some("att"),
                     0,
                     input_uuid,
+                    &[],
                 )
                 .context("Failed to validate sample input file")?;
             if let Some(mut val) = val {
@@ -259,8 +268,8 @@ impl SanityCheck for AttSampleFilesValid {
                     .provide_file(output_handle, output)
                     .context("Failed to provide sample output file")?;
 
-                // generate the output file
-                let (correct_output, sol) = solution
+                // generate the output file, the sample contains only the main output
+                let (correct_output, _, sol) = solution
                     .generate(
                         task,
                         eval,
@@ -307,8 +316,11 @@ impl SanityCheck for AttSampleFilesValid {
                     .check(
                         eval,
                         None,
+                        None,
+                        None,
                         format!("Checking sample output {}", output_name.display()),
                         input_uuid,
+                        &[],
                         correct_output,
                         output_uuid,
                         move |score, message| {
@@ -428,14 +440,18 @@ impl SanityCheck for AttWithNoCheck {
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         for att in list_files(&task.path, vec!["att/*"]) {
             let path = task.path_of(&att);
-            if let Ok(checks) = SolutionCheck::extract_check_list(&att, eval) {
-                if let Some(check) = checks.first() {
+            let checks = SolutionCheck::extract_check_list(&att, eval)
+                .map(|checks| checks.into_iter().map(|c| c.code_span).collect_vec());
+            let score_checks = SolutionScoreCheck::extract_list(&att, eval)
+                .map(|checks| checks.into_iter().map(|c| c.code_span).collect_vec());
+            if let (Ok(checks), Ok(score_checks)) = (checks, score_checks) {
+                if let Some(span) = checks.into_iter().chain(score_checks).next() {
                     eval.add_diagnostic(
                         Diagnostic::error(format!(
                             "@check rule found in an attachment: {}",
                             path.display()
                         ))
-                        .with_code_span(check.code_span.clone()),
+                        .with_code_span(span),
                     )?;
                 }
             }
@@ -544,6 +560,10 @@ impl SanityCheck for AttTemplatesShouldCompile {
         SanityCheckCategory::Attachments
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         for grader in task.grader_map.all_paths() {
             let ext = grader
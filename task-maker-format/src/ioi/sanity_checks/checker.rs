@@ -32,6 +32,10 @@ impl SanityCheck for FuzzCheckerWithJunkOutput {
         SanityCheckCategory::Checker
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn pre_hook(&self, task: &IOITask, eval: &mut crate::EvaluationData) -> Result<(), Error> {
         // Only tasks with a custom checker are supported.
         let checker = match &task.task_type {
@@ -66,6 +70,8 @@ impl SanityCheck for FuzzCheckerWithJunkOutput {
                 .check(
                     eval,
                     None,
+                    None,
+                    None,
                     format!(
                         "Fuzzing checker with junk input '{}' (\"{}\")",
                         description,
@@ -77,6 +83,7 @@ impl SanityCheck for FuzzCheckerWithJunkOutput {
                         )
                     ),
                     input,
+                    &testcase.generator_output_files,
                     official_output,
                     test_output_uuid,
                     move |score, outcome| {
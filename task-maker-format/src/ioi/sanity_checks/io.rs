@@ -99,6 +99,10 @@ impl SanityCheck for IOEndWithNewLine {
         SanityCheckCategory::Io
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         for (&testcase_id, testcase) in &task.testcases {
             if let Some(input_file) = testcase.input_file {
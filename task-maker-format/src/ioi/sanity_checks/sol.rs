@@ -1,10 +1,14 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
 use anyhow::{anyhow, Error};
 use task_maker_diagnostics::Diagnostic;
 
 use crate::ioi::sanity_checks::check_missing_graders;
 use crate::ioi::IOITask;
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
-use crate::{list_files, EvaluationData};
+use crate::{list_files, EvaluationData, Tag};
 
 /// Check that all the graders inside sol are present.
 #[derive(Debug, Default)]
@@ -160,3 +164,126 @@ impl SanityCheck for SolutionsWithSamplesCheck {
         Ok(())
     }
 }
+
+/// The maximum resources used by a solution across all the testcases.
+#[derive(Debug, Default, Clone, Copy)]
+struct MaxResourceUsage {
+    /// The maximum CPU time (in seconds).
+    time: f64,
+    /// The maximum memory (in KiB).
+    memory: u64,
+}
+
+/// Check that the solutions don't use more resources than the bounds specified with `@max-time`
+/// and `@max-memory` inside their source file.
+#[derive(Debug, Default)]
+pub struct SolutionResourceBounds {
+    /// The maximum resources used by each solution, indexed by the path of the solution.
+    usage: Arc<Mutex<HashMap<PathBuf, MaxResourceUsage>>>,
+}
+make_sanity_check!(SolutionResourceBounds);
+
+impl SanityCheck for SolutionResourceBounds {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "SolutionResourceBounds"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Solutions
+    }
+
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
+    fn pre_hook(&self, _task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let evaluation_tag = Some(Tag::Evaluation.into());
+        for solution in eval.solutions.iter() {
+            if solution.resource_bounds.is_empty() {
+                continue;
+            }
+            let executable = solution.source_file.executable.lock().unwrap().clone();
+            let Some(executable) = executable else {
+                continue;
+            };
+            // The executions of each group that run the solution, the others (e.g. the managers)
+            // are not relevant.
+            let mut groups = vec![];
+            for group in eval.dag.data.execution_groups.values() {
+                if group.tag != evaluation_tag {
+                    continue;
+                }
+                let indices: Vec<_> = group
+                    .executions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, exec)| {
+                        exec.input_files
+                            .values()
+                            .any(|input| input.file == executable.uuid)
+                    })
+                    .map(|(index, _)| index)
+                    .collect();
+                if !indices.is_empty() {
+                    groups.push((group.uuid, indices));
+                }
+            }
+            for (group, indices) in groups {
+                let usage = self.usage.clone();
+                let path = solution.source_file.path.clone();
+                eval.dag.on_execution_done(&group, move |results| {
+                    let mut usage = usage.lock().unwrap();
+                    let usage = usage.entry(path).or_default();
+                    for index in indices {
+                        if let Some(result) = results.get(index) {
+                            usage.time = usage.time.max(result.resources.cpu_time);
+                            usage.memory = usage.memory.max(result.resources.memory);
+                        }
+                    }
+                    Ok(())
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn post_hook(&self, _task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let usage = self.usage.lock().unwrap();
+        for solution in eval.solutions.iter() {
+            let Some(usage) = usage.get(&solution.source_file.path) else {
+                continue;
+            };
+            let bounds = &solution.resource_bounds;
+            let name = solution.source_file.relative_path().display();
+            if let Some(max_time) = bounds.max_time {
+                if usage.time > max_time {
+                    let mut diagnostic = Diagnostic::error(format!(
+                        "Solution '{}' used {:.3}s of CPU time, but at most {:.3}s are expected",
+                        name, usage.time, max_time
+                    ));
+                    if let Some(span) = bounds.time_span.clone() {
+                        diagnostic = diagnostic.with_code_span(span);
+                    }
+                    eval.add_diagnostic(diagnostic)?;
+                }
+            }
+            if let Some(max_memory) = bounds.max_memory {
+                if usage.memory > max_memory {
+                    let mut diagnostic = Diagnostic::error(format!(
+                        "Solution '{}' used {:.1}MiB of memory, but at most {:.1}MiB are expected",
+                        name,
+                        usage.memory as f64 / 1024.0,
+                        max_memory as f64 / 1024.0
+                    ));
+                    if let Some(span) = bounds.memory_span.clone() {
+                        diagnostic = diagnostic.with_code_span(span);
+                    }
+                    eval.add_diagnostic(diagnostic)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
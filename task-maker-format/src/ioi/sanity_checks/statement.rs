@@ -130,6 +130,10 @@ impl SanityCheck for StatementValid {
         SanityCheckCategory::Statement
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn post_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         let mut found_valid_statement = false;
 
@@ -203,6 +207,10 @@ impl SanityCheck for StatementCompiledOrGit {
         SanityCheckCategory::Statement
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn post_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         // the statements compiled by us
         let booklet_dest = task
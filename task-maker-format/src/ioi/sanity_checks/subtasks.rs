@@ -181,6 +181,10 @@ impl SanityCheck for AllOutputsEqual {
         SanityCheckCategory::Io
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         let mut outputs = self.outputs.lock().unwrap();
         for subtask in task.subtasks.values() {
@@ -4,7 +4,7 @@ use anyhow::Error;
 use regex::Regex;
 use task_maker_diagnostics::{CodeSpan, Diagnostic};
 
-use crate::ioi::IOITask;
+use crate::ioi::{IOITask, TaskType};
 use crate::sanity_checks::{make_sanity_check, SanityCheck, SanityCheckCategory};
 use crate::{list_files, EvaluationData};
 
@@ -38,6 +38,47 @@ impl SanityCheck for TaskMaxScore {
     }
 }
 
+/// Check that the scores of the subtasks sum to the usual maximum score, listing them if they don't.
+#[derive(Debug, Default)]
+pub struct SubtaskScoreSum;
+make_sanity_check!(SubtaskScoreSum);
+
+impl SanityCheck for SubtaskScoreSum {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "SubtaskScoreSum"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Task
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let total: f64 = task.subtasks.values().map(|st| st.max_score).sum();
+        if approx::abs_diff_eq!(total, DEFAULT_TASK_MAX_SCORE, epsilon = 1e-6) {
+            return Ok(());
+        }
+        let mut subtasks: Vec<_> = task.subtasks.values().collect();
+        subtasks.sort_by_key(|st| st.id);
+        let scores = subtasks
+            .iter()
+            .map(|st| match &st.name {
+                Some(name) => format!("Subtask {} ({name}): {}", st.id, st.max_score),
+                None => format!("Subtask {}: {}", st.id, st.max_score),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        eval.add_diagnostic(
+            Diagnostic::warning(format!(
+                "The scores of the subtasks sum to {total} (not {DEFAULT_TASK_MAX_SCORE})"
+            ))
+            .with_note(format!("The scores of the subtasks are:\n{scores}")),
+        )?;
+        Ok(())
+    }
+}
+
 /// Check that there are no broken links.
 #[derive(Debug, Default)]
 pub struct BrokenSymlinks;
@@ -54,6 +95,10 @@ impl SanityCheck for BrokenSymlinks {
         SanityCheckCategory::Task
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn post_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
         for file in list_files(&task.path, vec!["**/*"]) {
             if !file.exists() {
@@ -151,3 +196,41 @@ impl SanityCheck for EmptyTitle {
         Ok(())
     }
 }
+
+/// Check that the task has the metadata required to be used in a contest: the time and memory
+/// limits and at least one subtask. The limits are not required for the output only tasks, since
+/// no solution is executed. The title is checked by `EmptyTitle` and the statement by
+/// `StatementValid`.
+#[derive(Debug, Default)]
+pub struct TaskMetadata;
+make_sanity_check!(TaskMetadata);
+
+impl SanityCheck for TaskMetadata {
+    type Task = IOITask;
+
+    fn name(&self) -> &'static str {
+        "TaskMetadata"
+    }
+
+    fn category(&self) -> SanityCheckCategory {
+        SanityCheckCategory::Task
+    }
+
+    fn pre_hook(&self, task: &IOITask, eval: &mut EvaluationData) -> Result<(), Error> {
+        let runs_solutions = !task.output_only && !matches!(task.task_type, TaskType::None);
+        if runs_solutions && task.time_limit.is_none() {
+            eval.add_diagnostic(Diagnostic::error("Missing time limit").with_help(
+                "Set time_limit in task.yaml, or output_only if it's an output only task",
+            ))?;
+        }
+        if runs_solutions && task.memory_limit.is_none() {
+            eval.add_diagnostic(Diagnostic::error("Missing memory limit").with_help(
+                "Set memory_limit in task.yaml, or output_only if it's an output only task",
+            ))?;
+        }
+        if task.subtasks.is_empty() {
+            eval.add_diagnostic(Diagnostic::error("The task has no subtasks"))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Support for the custom scoring scripts of the IOI tasks.
+//!
+//! A scoring script replaces the `TestcaseScoreAggregator` of the task: it is run once per solution,
+//! after all its testcases have been scored, and it computes the score of all the subtasks. It's a
+//! normal execution of the DAG, whose input is a late file given by the `ScoreManager` of the
+//! solution when the scores of all the testcases are known.
+//!
+//! The script receives on its standard input a JSON object like:
+//!
+//! ```json
+//! {"subtasks": {"0": {"max_score": 10.0, "testcases": {"0": 1.0, "1": 0.5}}}}
+//! ```
+//!
+//! and it has to print on its standard output a JSON object mapping each subtask to its score,
+//! between 0 and the maximum score of the subtask, like `{"0": 5.0}`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Error};
+use serde::Serialize;
+use task_maker_dag::File;
+
+use crate::ioi::{ScoreManager, SubtaskId, TestcaseId, EVALUATION_PRIORITY, STDERR_CONTENT_LENGTH};
+use crate::{find_source_file, EvaluationData, SourceFile, Tag, WriteBinTo};
+
+/// Maximum number of bytes of the output of the scoring script.
+const SCORER_OUTPUT_LENGTH: usize = 64 * 1024;
+
+/// The input of a scoring script for a subtask.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ScorerSubtask {
+    /// The maximum score of the subtask.
+    pub max_score: f64,
+    /// The score of each testcase of the subtask, from 0.0 to 1.0.
+    pub testcases: BTreeMap<TestcaseId, f64>,
+}
+
+/// The input of a scoring script.
+#[derive(Debug, Clone, Serialize)]
+struct ScorerInput<'a> {
+    /// The subtasks of the task, with the scores of their testcases.
+    subtasks: &'a BTreeMap<SubtaskId, ScorerSubtask>,
+}
+
+/// Search the scoring script of the task, `check/scorer.*`.
+pub(crate) fn detect_scorer(task_dir: &Path) -> Result<Option<Arc<SourceFile>>, Error> {
+    let mut scorers = find_source_file(
+        task_dir,
+        vec!["check/scorer.*"],
+        task_dir,
+        "Scorer at",
+        None,
+        WriteBinTo::WithoutExtension,
+    );
+    if scorers.len() > 1 {
+        let paths = scorers.iter().map(|s| s.name()).collect::<Vec<_>>();
+        bail!("Multiple scorers found: {:?}", paths);
+    }
+    Ok(scorers.pop().map(Arc::new))
+}
+
+/// Add to the DAG the execution of the scoring script for a solution. Its input is given by the
+/// `ScoreManager` of the solution when all the testcases are scored, and its output is given back
+/// to it.
+pub(crate) fn bind_scorer(
+    eval: &mut EvaluationData,
+    scorer: &SourceFile,
+    solution: &Path,
+    score_manager: Arc<Mutex<ScoreManager>>,
+) -> Result<(), Error> {
+    let solution_name = solution
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let input = File::new(format!("Input of the scorer for {solution_name}"));
+    eval.dag.provide_later(input.clone());
+    let mut exec = scorer
+        .execute(
+            eval,
+            format!("Scoring of {solution_name}"),
+            Vec::<String>::new(),
+        )
+        .context("Failed to execute scorer source file")?;
+    exec.stdin(&input);
+    exec.capture_stdout(Some(SCORER_OUTPUT_LENGTH));
+    exec.capture_stderr(Some(STDERR_CONTENT_LENGTH));
+    let mut group = exec.into_group();
+    group.tag = Some(Tag::Checking.into());
+    group.priority = EVALUATION_PRIORITY;
+    let sender = eval.sender.clone();
+    let score_manager_done = score_manager.clone();
+    eval.dag.on_execution_done(&group.uuid, move |results| {
+        score_manager_done
+            .lock()
+            .unwrap()
+            .scorer_done(&results[0], sender)
+    });
+    eval.dag.add_execution_group(group);
+    let provide_handle = eval.dag.provide_handle();
+    score_manager
+        .lock()
+        .unwrap()
+        .set_scorer_input(input.uuid, provide_handle);
+    Ok(())
+}
+
+/// Serialize the input of the scoring script with the scores of the testcases.
+pub(crate) fn scorer_input(
+    subtasks: &BTreeMap<SubtaskId, ScorerSubtask>,
+) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(&ScorerInput { subtasks }).context("Failed to serialize the scorer input")
+}
+
+/// Parse and validate the output of the scoring script: it must contain the score of each subtask,
+/// within the limits of the subtask.
+pub(crate) fn parse_scorer_output(
+    output: &str,
+    subtasks: &BTreeMap<SubtaskId, ScorerSubtask>,
+) -> Result<HashMap<SubtaskId, f64>, Error> {
+    let scores: HashMap<String, f64> = serde_json::from_str(output.trim())
+        .with_context(|| format!("The scorer output is not a JSON object of scores: {output:?}"))?;
+    let mut result = HashMap::new();
+    for (subtask, score) in scores {
+        let subtask_id: SubtaskId = subtask
+            .parse()
+            .with_context(|| format!("Invalid subtask id in the scorer output: {subtask:?}"))?;
+        let Some(info) = subtasks.get(&subtask_id) else {
+            bail!("The scorer output contains the unknown subtask {subtask_id}");
+        };
+        if !score.is_finite() || score < 0.0 || score > info.max_score {
+            bail!(
+                "The scorer gave {score} points to subtask {subtask_id}, expecting a score between 0 and {}",
+                info.max_score
+            );
+        }
+        result.insert(subtask_id, score);
+    }
+    if let Some(missing) = subtasks.keys().find(|st| !result.contains_key(st)) {
+        bail!("The scorer output does not contain the score of subtask {missing}");
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subtasks() -> BTreeMap<SubtaskId, ScorerSubtask> {
+        let mut subtasks = BTreeMap::new();
+        subtasks.insert(
+            0,
+            ScorerSubtask {
+                max_score: 10.0,
+                testcases: vec![(0, 1.0), (1, 0.5)].into_iter().collect(),
+            },
+        );
+        subtasks.insert(
+            1,
+            ScorerSubtask {
+                max_score: 90.0,
+                testcases: vec![(2, 0.0)].into_iter().collect(),
+            },
+        );
+        subtasks
+    }
+
+    #[test]
+    fn test_parse_scorer_output() {
+        let scores = parse_scorer_output(r#"{"0": 7.5, "1": 0}"#, &subtasks()).unwrap();
+        assert_eq!(scores[&0], 7.5);
+        assert_eq!(scores[&1], 0.0);
+    }
+
+    #[test]
+    fn test_parse_scorer_output_not_json() {
+        let err = parse_scorer_output("10 20", &subtasks()).unwrap_err();
+        assert!(err.to_string().contains("not a JSON object"));
+    }
+
+    #[test]
+    fn test_parse_scorer_output_unknown_subtask() {
+        let err = parse_scorer_output(r#"{"0": 1, "1": 1, "2": 1}"#, &subtasks()).unwrap_err();
+        assert!(err.to_string().contains("unknown subtask 2"));
+    }
+
+    #[test]
+    fn test_parse_scorer_output_missing_subtask() {
+        let err = parse_scorer_output(r#"{"0": 1}"#, &subtasks()).unwrap_err();
+        assert!(err.to_string().contains("score of subtask 1"));
+    }
+
+    #[test]
+    fn test_parse_scorer_output_out_of_range() {
+        let err = parse_scorer_output(r#"{"0": 11, "1": 0}"#, &subtasks()).unwrap_err();
+        assert!(err.to_string().contains("between 0 and 10"));
+    }
+
+    #[test]
+    fn test_scorer_input() {
+        let input = String::from_utf8(scorer_input(&subtasks()).unwrap()).unwrap();
+        assert_eq!(
+            input,
+            r#"{"subtasks":{"0":{"max_score":10.0,"testcases":{"0":1.0,"1":0.5}},"1":{"max_score":90.0,"testcases":{"2":0.0}}}}"#
+        );
+    }
+}
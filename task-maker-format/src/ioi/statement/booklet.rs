@@ -115,6 +115,23 @@ impl Booklet {
 
         builder.create_execution(self, booklet_name, eval)
     }
+
+    /// Validate the sources of the statements of this booklet without compiling it, emitting the
+    /// problems found as diagnostics.
+    pub fn validate(&self, eval: &mut EvaluationData) -> Result<(), Error> {
+        let Some(lang) = &self.lang else {
+            return Ok(());
+        };
+        let validator = get_language_from_extension(lang)?;
+        for statement in &self.statements {
+            validator
+                .validate_statement(statement, eval)
+                .with_context(|| {
+                    format!("Failed to validate statement {}", statement.path.display())
+                })?;
+        }
+        Ok(())
+    }
 }
 
 impl BookletConfig {
@@ -1,9 +1,12 @@
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{bail, Context, Error};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use task_maker_dag::File;
+use task_maker_diagnostics::{CodeSpan, Diagnostic};
 use tex::Tex;
 use typst::Typst;
 
@@ -16,6 +19,10 @@ use crate::EvaluationData;
 mod tex;
 mod typst;
 
+/// The extensions of the figures. A figure in the directory of the statement is added to the
+/// compilation only if the statement references it.
+const FIGURE_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "svg", "eps", "gif"];
+
 /// The configuration of a `Statement`.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct StatementConfig {
@@ -63,6 +70,20 @@ pub trait Language {
     fn build_statement_source(&self, statement: &Statement) -> String;
     /// Builds the source of a booklet
     fn build_booklet_source(&self, booklet: &Booklet) -> String;
+    /// Validate the source of a single statement without compiling it, emitting a diagnostic for
+    /// each problem found
+    fn validate_statement(&self, statement: &Statement, eval: &EvaluationData)
+        -> Result<(), Error>;
+    /// Find the paths of the files referenced by a statement source, as they are written in it.
+    /// If a reference cannot be resolved without compiling the source (e.g. a path built with a
+    /// macro), it's returned as the error
+    fn referenced_files(&self, content: &str) -> Result<Vec<String>, String>;
+    /// Remove the comments from a source, so that the names mentioned only in the comments are not
+    /// considered used
+    fn strip_comments(&self, content: &str) -> String;
+    /// Whether the paths referenced by an included source are relative to the directory of the
+    /// included source, instead of the one of the statement
+    fn references_relative_to_source(&self) -> bool;
     /// Emit warnings taken from the compilation stderr
     fn emit_warnings(
         &self,
@@ -114,12 +135,34 @@ impl Statement {
             .as_ref()
             .and_then(|p| Path::new(p).file_name())
             .map(PathBuf::from);
+        let referenced = match self.referenced_files(&logo) {
+            Ok(referenced) => Some(referenced),
+            Err(reference) => {
+                let path = self
+                    .path
+                    .strip_prefix(&eval.task_root)
+                    .unwrap_or(&self.path);
+                eval.add_diagnostic(
+                    Diagnostic::warning(format!(
+                        "Cannot find the figures used by the statement at {}, including all of them",
+                        path.display()
+                    ))
+                    .with_note(format!("This reference cannot be resolved: {reference}")),
+                )?;
+                None
+            }
+        };
         let mut deps = vec![];
         for path in glob::glob(&glob_pattern).context("Invalid glob pattern")? {
             let path = path.context("Failed to iterate statement files")?;
             if !path.is_file() {
                 continue;
             }
+            if let Some(referenced) = &referenced {
+                if Statement::is_unused_figure(base_dir, &path, referenced, &logo) {
+                    continue;
+                }
+            }
             self.process_possible_dependency(base_dir, &path, &mut deps, eval, booklet_name, &logo)
                 .with_context(|| {
                     format!(
@@ -202,6 +245,204 @@ impl Statement {
         Ok(())
     }
 
+    /// Find the files referenced by the statement, following the sources it includes, as paths
+    /// relative to the directory of the statement. The files outside of that directory are
+    /// ignored. If a reference cannot be resolved statically, it's returned as the error.
+    ///
+    /// A figure that is not referenced, but whose name is an argument of a command that is not
+    /// recognized (e.g. a custom macro) or is used by a non-statement source (e.g. a `.sty` file),
+    /// is considered a reference that cannot be resolved, since it may be used in a way that cannot
+    /// be detected.
+    fn referenced_files(&self, logo: &Option<PathBuf>) -> Result<HashSet<PathBuf>, String> {
+        let base_dir = self.path.parent().ok_or_else(String::new)?;
+        let ext = self
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let language = get_language_from_extension(&ext).map_err(|e| e.to_string())?;
+
+        let mut referenced = HashSet::new();
+        let mut visited = HashSet::new();
+        // the statement sources, without comments, to search for the unrecognized references
+        let mut sources = vec![];
+        let name = self.path.file_name().map(PathBuf::from).unwrap_or_default();
+        // the sources to process, with the directory their references are relative to and their
+        // path
+        let mut pending = vec![(PathBuf::new(), name, self.content.clone())];
+        while let Some((dir, source_path, content)) = pending.pop() {
+            sources.push((source_path, language.strip_comments(&content)));
+            for reference in language.referenced_files(&content)? {
+                let Some(path) = normalize_reference(&dir.join(&reference)) else {
+                    // absolute paths are relative to the root of the booklet
+                    if Path::new(&reference).has_root() {
+                        return Err(reference);
+                    }
+                    continue;
+                };
+                let source = if path.extension().is_none() {
+                    path.with_extension(&ext)
+                } else {
+                    path.clone()
+                };
+                referenced.insert(path);
+                let is_source = source.extension().is_some_and(|e| e == ext.as_str());
+                if !is_source || !visited.insert(source.clone()) {
+                    continue;
+                }
+                let Ok(content) = std::fs::read_to_string(base_dir.join(&source)) else {
+                    continue;
+                };
+                let dir = if language.references_relative_to_source() {
+                    source.parent().map(Path::to_path_buf).unwrap_or_default()
+                } else {
+                    PathBuf::new()
+                };
+                pending.push((dir, source, content));
+            }
+        }
+        // the other sources in the directory are not statement sources, like a `.sty` file
+        for path in
+            glob::glob(&format!("{}/**/*", base_dir.display())).map_err(|e| e.to_string())?
+        {
+            let Ok(path) = path else {
+                continue;
+            };
+            let Ok(suffix) = path.strip_prefix(base_dir) else {
+                continue;
+            };
+            let source_ext = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let skip = FIGURE_EXTENSIONS.contains(&source_ext.as_str())
+                || source_ext == "asy"
+                || get_language_from_extension(&source_ext).is_ok();
+            if skip || !path.is_file() {
+                continue;
+            }
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                sources.push((suffix.to_path_buf(), content));
+            }
+        }
+        if let Some(reference) =
+            Statement::unrecognized_reference(base_dir, &referenced, &sources, logo)
+        {
+            return Err(reference);
+        }
+        Ok(referenced)
+    }
+
+    /// Search the sources for a figure that is not referenced, but whose name is an argument of a
+    /// command or a string (e.g. `\myfigure{graph}` or `"graph.png"`), returning where it's used.
+    /// The figures with the same name of a referenced file (e.g. in another directory) are not
+    /// searched, since they cannot be told apart.
+    fn unrecognized_reference(
+        base_dir: &Path,
+        referenced: &HashSet<PathBuf>,
+        sources: &[(PathBuf, String)],
+        logo: &Option<PathBuf>,
+    ) -> Option<String> {
+        let referenced_stems: HashSet<_> =
+            referenced.iter().filter_map(|p| p.file_stem()).collect();
+        let figures = glob::glob(&format!("{}/**/*", base_dir.display())).ok()?;
+        for path in figures.flatten() {
+            if !path.is_file() || !Statement::is_unused_figure(base_dir, &path, referenced, logo) {
+                continue;
+            }
+            let Some(stem) = path.file_stem() else {
+                continue;
+            };
+            if referenced_stems.contains(stem) {
+                continue;
+            }
+            let pattern = format!(
+                r#"[{{"'/]{}(\.[A-Za-z]+)?[}}"']"#,
+                regex::escape(&stem.to_string_lossy())
+            );
+            let Ok(regex) = Regex::new(&pattern) else {
+                continue;
+            };
+            for (source, content) in sources {
+                if let Some(found) = regex.find(content) {
+                    return Some(format!("{} in {}", found.as_str(), source.display()));
+                }
+            }
+        }
+        None
+    }
+
+    /// Check if the file is a figure that is not referenced by the statement, and so it can be
+    /// left out of the compilation. The logo of the contest is always used.
+    fn is_unused_figure(
+        base_dir: &Path,
+        path: &Path,
+        referenced: &HashSet<PathBuf>,
+        logo: &Option<PathBuf>,
+    ) -> bool {
+        let is_figure = path
+            .extension()
+            .is_some_and(|ext| FIGURE_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()));
+        let is_logo = match (path.file_name(), logo) {
+            (Some(name), Some(logo)) => name == logo,
+            _ => false,
+        };
+        if !is_figure || is_logo {
+            return false;
+        }
+        let Ok(suffix) = path.strip_prefix(base_dir) else {
+            return false;
+        };
+        // the extension of a figure can be omitted
+        !referenced.contains(suffix) && !referenced.contains(&suffix.with_extension(""))
+    }
+
+    /// Emit an error found while validating the statement, pointing to the part of the source
+    /// starting at `offset` and long `len` bytes.
+    fn emit_validation_error(
+        &self,
+        eval: &EvaluationData,
+        message: impl Into<String>,
+        offset: usize,
+        len: usize,
+    ) -> Result<(), Error> {
+        let path = self
+            .path
+            .strip_prefix(&eval.task_root)
+            .unwrap_or(&self.path);
+        let mut diagnostic = Diagnostic::error(format!(
+            "Invalid statement at {}: {}",
+            path.display(),
+            message.into()
+        ));
+        if let Ok(span) = CodeSpan::from_str(path, &self.content, offset, len) {
+            diagnostic = diagnostic.with_code_span(span);
+        }
+        eval.add_diagnostic(diagnostic)
+    }
+
+    /// Check if an asset referenced by the statement is present. The path is relative to the
+    /// directory of the statement and, if it has no extension, all the `extensions` are tried.
+    ///
+    /// Assets that are compiled from an `.asy` file, or that are taken from the `gen/` folder
+    /// during the compilation, are considered present.
+    fn asset_exists(&self, asset: &str, extensions: &[&str]) -> bool {
+        let Some(base_dir) = self.path.parent() else {
+            return false;
+        };
+        let path = base_dir.join(asset);
+        let mut candidates = vec![path.clone()];
+        if path.extension().is_none() {
+            candidates.extend(extensions.iter().map(|ext| path.with_extension(ext)));
+        }
+        if let Some(file_name) = path.file_name() {
+            candidates.push(base_dir.join("../gen").join(file_name));
+        }
+        candidates
+            .iter()
+            .any(|path| path.is_file() || path.with_extension("asy").is_file())
+    }
+
     /// Check if this pdf file should be considered a valid dependency.
     /// There are some cases where the .pdf should not be considered a dependency:
     /// - The file is the output of an asy compilation: asy will build it from scratch (or use the
@@ -230,6 +471,25 @@ impl Statement {
     }
 }
 
+/// Normalize a path referenced by a statement, relative to its directory, removing the `.` and `..`
+/// components. Returns `None` if the path is absolute or it's outside of the directory.
+fn normalize_reference(path: &Path) -> Option<PathBuf> {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => result.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !result.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(result)
+}
+
 impl StatementConfig {
     /// Make a new `StatementConfig` from an instance of a `ioi::IOITask`.
     pub fn from_task(task: &IOITask) -> Self {
@@ -260,7 +520,8 @@ mod tests {
 
     use tempfile::TempDir;
 
-    use crate::ioi::{Statement, StatementConfig};
+    use crate::ioi::{BookletConfig, Statement, StatementConfig};
+    use crate::ui::UIMessage;
     use crate::EvaluationData;
 
     #[test]
@@ -294,6 +555,27 @@ mod tests {
         assert!(Statement::is_valid_pdf_dependency(Path::new("/do/not/exists")).is_err());
     }
 
+    #[test]
+    fn test_asset_exists() {
+        let tmpdir = TempDir::new().unwrap();
+        let statement_dir = tmpdir.path().join("statement");
+        std::fs::create_dir_all(&statement_dir).unwrap();
+        std::fs::create_dir_all(tmpdir.path().join("gen")).unwrap();
+        let path = statement_dir.join("statement.tex");
+        std::fs::write(&path, "").unwrap();
+        std::fs::write(statement_dir.join("image.png"), "").unwrap();
+        std::fs::write(statement_dir.join("figure.asy"), "").unwrap();
+        std::fs::write(tmpdir.path().join("gen/constraints.yaml"), "").unwrap();
+        let statement = Statement::new(&path, StatementConfig::default()).unwrap();
+
+        assert!(statement.asset_exists("image.png", &[]));
+        assert!(statement.asset_exists("image", &["pdf", "png"]));
+        assert!(!statement.asset_exists("image", &["pdf"]));
+        assert!(statement.asset_exists("figure.pdf", &[]));
+        assert!(statement.asset_exists("constraints.yaml", &[]));
+        assert!(!statement.asset_exists("missing.png", &[]));
+    }
+
     #[test]
     fn test_process_possible_dependency() {
         let tmpdir = TempDir::new().unwrap();
@@ -331,4 +613,129 @@ mod tests {
             .collect();
         assert_eq!(deps, in_files);
     }
+
+    fn build_deps_names(statement: &Statement, eval: &mut EvaluationData) -> Vec<String> {
+        let mut names: Vec<_> = statement
+            .build_deps(eval, "name", &BookletConfig::default())
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path.to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_build_deps_only_referenced_figures() {
+        let tmpdir = TempDir::new().unwrap();
+        let dir = tmpdir.path().join("statement");
+        std::fs::create_dir_all(dir.join("figures")).unwrap();
+        let path = dir.join("statement.tex");
+        std::fs::write(
+            &path,
+            "\\includegraphics{figures/used}\n% \\includegraphics{commented.png}\n\\input{part}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("part.tex"),
+            "\\includegraphics[width=3cm]{./other.jpg}",
+        )
+        .unwrap();
+        for file in [
+            "figures/used.png",
+            "other.jpg",
+            "commented.png",
+            "unused.pdf",
+            "style.sty",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let statement = Statement::new(&path, StatementConfig::default()).unwrap();
+        let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+
+        let names = build_deps_names(&statement, &mut eval);
+        assert_eq!(
+            names,
+            [
+                "figures/used.png",
+                "other.jpg",
+                "part.tex",
+                "statement.tex",
+                "style.sty"
+            ]
+        );
+        drop(eval);
+        assert!(recv
+            .try_iter()
+            .all(|m| !matches!(m, UIMessage::Diagnostic { .. })));
+    }
+
+    #[test]
+    fn test_build_deps_typst_relative_to_source() {
+        let tmpdir = TempDir::new().unwrap();
+        let dir = tmpdir.path().join("statement");
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        let path = dir.join("statement.typ");
+        std::fs::write(&path, "#include \"parts/part.typ\"").unwrap();
+        std::fs::write(dir.join("parts/part.typ"), "#image(\"figure.svg\")").unwrap();
+        std::fs::write(dir.join("parts/figure.svg"), "").unwrap();
+        std::fs::write(dir.join("figure.svg"), "").unwrap();
+        let statement = Statement::new(&path, StatementConfig::default()).unwrap();
+        let mut eval = EvaluationData::new(tmpdir.path()).0;
+
+        let names = build_deps_names(&statement, &mut eval);
+        assert_eq!(
+            names,
+            ["parts/figure.svg", "parts/part.typ", "statement.typ"]
+        );
+    }
+
+    #[test]
+    fn test_build_deps_dynamic_reference() {
+        let tmpdir = TempDir::new().unwrap();
+        let dir = tmpdir.path().join("statement");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("statement.tex");
+        std::fs::write(&path, "\\includegraphics{\\figure}").unwrap();
+        std::fs::write(dir.join("unused.png"), "").unwrap();
+        let statement = Statement::new(&path, StatementConfig::default()).unwrap();
+        let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+
+        let names = build_deps_names(&statement, &mut eval);
+        assert_eq!(names, ["statement.tex", "unused.png"]);
+        drop(eval);
+        let warning = recv
+            .try_iter()
+            .find_map(|m| match m {
+                UIMessage::Diagnostic { diagnostic } => Some(diagnostic),
+                _ => None,
+            })
+            .unwrap();
+        assert!(warning.to_string().contains("\\includegraphics{\\figure}"));
+    }
+
+    #[test]
+    fn test_build_deps_unrecognized_reference() {
+        for (statement_source, style) in [
+            ("\\myfigure{graph}", ""),
+            ("\\usepackage{style}", "\\includegraphics{graph.png}"),
+        ] {
+            let tmpdir = TempDir::new().unwrap();
+            let dir = tmpdir.path().join("statement");
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("statement.tex");
+            std::fs::write(&path, statement_source).unwrap();
+            std::fs::write(dir.join("style.sty"), style).unwrap();
+            std::fs::write(dir.join("graph.png"), "").unwrap();
+            let statement = Statement::new(&path, StatementConfig::default()).unwrap();
+            let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+
+            let names = build_deps_names(&statement, &mut eval);
+            assert_eq!(names, ["graph.png", "statement.tex", "style.sty"]);
+            drop(eval);
+            assert!(recv
+                .try_iter()
+                .any(|m| matches!(m, UIMessage::Diagnostic { .. })));
+        }
+    }
 }
@@ -17,6 +17,14 @@ use crate::{bind_exec_callbacks, EvaluationData, Tag, UISender, DATA_DIR};
 lazy_static! {
     /// This regex will match all the `\usepackage` inside a latex file.
     static ref USE_PACKAGE_REGEX: Regex = Regex::new(r"\\usepackage.+").expect("Invalid regex");
+    /// This regex will match all the `\begin{env}` and `\end{env}` inside a latex file.
+    static ref ENVIRONMENT_REGEX: Regex =
+        Regex::new(r"\\(?P<kind>begin|end)\s*\{(?P<env>[^}]*)\}").expect("Invalid regex");
+    /// This regex will match all the commands that include an external file inside a latex file.
+    static ref INCLUDE_REGEX: Regex = Regex::new(
+        r"\\(?P<command>includegraphics|input|include)\s*(?:\[[^\]]*\])?\s*\{(?P<path>[^}]+)\}"
+    )
+    .expect("Invalid regex");
 }
 
 /// Template to use to render the `statement.tex` file.
@@ -254,6 +262,117 @@ impl Language for Tex {
         .to_string()
     }
 
+    fn validate_statement(
+        &self,
+        statement: &Statement,
+        eval: &EvaluationData,
+    ) -> Result<(), Error> {
+        let content = strip_comments(&statement.content);
+
+        let mut open_braces = vec![];
+        let mut chars = content.char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                // skip the escaped character, like \{ or \%
+                '\\' => {
+                    chars.next();
+                }
+                '{' => open_braces.push(offset),
+                '}' => {
+                    if open_braces.pop().is_none() {
+                        statement.emit_validation_error(eval, "unmatched '}'", offset, 1)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        for offset in open_braces {
+            statement.emit_validation_error(eval, "unclosed '{'", offset, 1)?;
+        }
+
+        let mut environments: Vec<(&str, usize, usize)> = vec![];
+        for cap in ENVIRONMENT_REGEX.captures_iter(&content) {
+            let whole = cap.get(0).unwrap();
+            let (offset, len) = (whole.start(), whole.len());
+            let env = cap.name("env").unwrap().as_str();
+            if &cap["kind"] == "begin" {
+                environments.push((env, offset, len));
+            } else if let Some((open, _, _)) = environments.pop() {
+                if open != env {
+                    statement.emit_validation_error(
+                        eval,
+                        format!("\\end{{{env}}} does not match \\begin{{{open}}}"),
+                        offset,
+                        len,
+                    )?;
+                }
+            } else {
+                statement.emit_validation_error(
+                    eval,
+                    format!("\\end{{{env}}} without a matching \\begin"),
+                    offset,
+                    len,
+                )?;
+            }
+        }
+        for (env, offset, len) in environments {
+            statement.emit_validation_error(
+                eval,
+                format!("\\begin{{{env}}} is never closed"),
+                offset,
+                len,
+            )?;
+        }
+
+        for cap in INCLUDE_REGEX.captures_iter(&content) {
+            let path = cap["path"].trim();
+            // paths built with macros cannot be checked
+            if path.contains('\\') || path.contains('#') {
+                continue;
+            }
+            let extensions: &[&str] = match &cap["command"] {
+                "includegraphics" => &["pdf", "png", "jpg", "jpeg", "eps"],
+                _ => &["tex"],
+            };
+            if !statement.asset_exists(path, extensions) {
+                let whole = cap.get(0).unwrap();
+                statement.emit_validation_error(
+                    eval,
+                    format!("the referenced file '{path}' does not exist"),
+                    whole.start(),
+                    whole.len(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn referenced_files(&self, content: &str) -> Result<Vec<String>, String> {
+        let content = strip_comments(content);
+        // \graphicspath adds more directories where the figures are searched
+        if content.contains("\\graphicspath") {
+            return Err("\\graphicspath".into());
+        }
+        let mut files = vec![];
+        for cap in INCLUDE_REGEX.captures_iter(&content) {
+            let path = cap["path"].trim();
+            // paths built with macros cannot be resolved
+            if path.contains('\\') || path.contains('#') {
+                return Err(cap[0].to_string());
+            }
+            files.push(path.to_string());
+        }
+        Ok(files)
+    }
+
+    fn strip_comments(&self, content: &str) -> String {
+        strip_comments(content)
+    }
+
+    fn references_relative_to_source(&self) -> bool {
+        false
+    }
+
     fn emit_warnings(
         &self,
         booklet_name: PathBuf,
@@ -305,6 +424,36 @@ fn find_packages(statement: &Statement) -> Vec<String> {
     packages
 }
 
+/// Replace the comments of a latex source with spaces, keeping the offsets of the rest of the
+/// source unchanged.
+fn strip_comments(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        let mut escaped = false;
+        let mut comment_start = None;
+        for (offset, c) in line.char_indices() {
+            match c {
+                '%' if !escaped => {
+                    comment_start = Some(offset);
+                    break;
+                }
+                '\\' => escaped = !escaped,
+                _ => escaped = false,
+            }
+        }
+        match comment_start {
+            Some(start) => {
+                let comment = line[start..].trim_end_matches('\n');
+                result.push_str(&line[..start]);
+                result.push_str(&" ".repeat(comment.len()));
+                result.push_str(&line[start + comment.len()..]);
+            }
+            None => result.push_str(line),
+        }
+    }
+    result
+}
+
 /// Return a string which is `if_true` if `b` is true, otherwise an empty string.
 fn bool_to_tpl_string(b: bool, if_true: &str) -> String {
     if b { if_true } else { "" }.to_string()
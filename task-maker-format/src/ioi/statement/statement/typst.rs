@@ -3,6 +3,7 @@ use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Error};
 use itertools::Itertools;
+use regex::Regex;
 use task_maker_dag::{Execution, ExecutionCommand, ExecutionStatus, File};
 use task_maker_diagnostics::Diagnostic;
 
@@ -11,6 +12,18 @@ use crate::ioi::{Booklet, BOOKLET_PRIORITY};
 use crate::ui::{UIMessage, UIMessageSender};
 use crate::{bind_exec_callbacks, EvaluationData, Tag, UISender};
 
+lazy_static! {
+    /// This regex will match all the functions and statements that read an external file inside a
+    /// typst file.
+    static ref INCLUDE_REGEX: Regex = Regex::new(
+        r#"(?:\b(?:image|read|json|yaml|toml|csv)\(\s*|#(?:include|import)\s+)"(?P<path>[^"]+)""#
+    )
+    .expect("Invalid regex");
+    /// This regex will match the images whose path is not a string literal, like `image(path)`.
+    static ref DYNAMIC_IMAGE_REGEX: Regex =
+        Regex::new(r#"\bimage\(\s*[^"\s]"#).expect("Invalid regex");
+}
+
 #[derive(Debug)]
 pub(super) struct Typst;
 
@@ -165,6 +178,151 @@ impl Language for Typst {
         }
     }
 
+    fn validate_statement(
+        &self,
+        statement: &Statement,
+        eval: &EvaluationData,
+    ) -> Result<(), Error> {
+        let content = &statement.content;
+        let bytes = content.as_bytes();
+
+        let mut open_brackets: Vec<(u8, usize)> = vec![];
+        let mut in_math = false;
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            // string literals are only allowed in code, i.e. inside parentheses or braces
+            let in_code = matches!(open_brackets.last(), Some((b'(' | b'{', _)));
+            match c {
+                // skip the escaped character, like \[ or \$
+                b'\\' => i += 1,
+                b'/' if bytes.get(i + 1) == Some(&b'/') && (i == 0 || bytes[i - 1] != b':') => {
+                    i = find_from(bytes, i, b"\n").unwrap_or(bytes.len());
+                    continue;
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    i = find_from(bytes, i + 2, b"*/").map_or(bytes.len(), |end| end + 2);
+                    continue;
+                }
+                b'`' => {
+                    let fence = bytes[i..].iter().take_while(|&&c| c == b'`').count();
+                    let fence = if fence >= 3 {
+                        &bytes[i..i + fence]
+                    } else {
+                        b"`" as &[u8]
+                    };
+                    i = find_from(bytes, i + fence.len(), fence)
+                        .map_or(bytes.len(), |end| end + fence.len());
+                    continue;
+                }
+                b'"' if in_code => {
+                    let mut j = i + 1;
+                    while j < bytes.len() && bytes[j] != b'"' {
+                        j += if bytes[j] == b'\\' { 2 } else { 1 };
+                    }
+                    i = j;
+                }
+                b'$' => in_math = !in_math,
+                // brackets inside formulas do not need to be balanced, like in $[0, N)$
+                _ if in_math => {}
+                b'(' | b'[' | b'{' => open_brackets.push((c, i)),
+                b')' | b']' | b'}' => {
+                    let expected = match c {
+                        b')' => b'(',
+                        b']' => b'[',
+                        _ => b'{',
+                    };
+                    match open_brackets.pop() {
+                        Some((open, _)) if open == expected => {}
+                        Some((open, offset)) => {
+                            statement.emit_validation_error(
+                                eval,
+                                format!("'{}' closed by '{}'", open as char, c as char),
+                                offset,
+                                1,
+                            )?;
+                        }
+                        None => {
+                            statement.emit_validation_error(
+                                eval,
+                                format!("unmatched '{}'", c as char),
+                                i,
+                                1,
+                            )?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        for (open, offset) in open_brackets {
+            statement.emit_validation_error(
+                eval,
+                format!("unclosed '{}'", open as char),
+                offset,
+                1,
+            )?;
+        }
+
+        for cap in INCLUDE_REGEX.captures_iter(content) {
+            let path = &cap["path"];
+            // absolute paths are relative to the compilation root, @ is for typst packages
+            if path.starts_with('/') || path.starts_with('@') {
+                continue;
+            }
+            if !statement.asset_exists(path, &[]) {
+                let whole = cap.get(0).unwrap();
+                statement.emit_validation_error(
+                    eval,
+                    format!("the referenced file '{path}' does not exist"),
+                    whole.start(),
+                    whole.len(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn referenced_files(&self, content: &str) -> Result<Vec<String>, String> {
+        if let Some(m) = DYNAMIC_IMAGE_REGEX.find(content) {
+            let line = content[m.start()..].lines().next().unwrap_or_default();
+            return Err(line.trim().to_string());
+        }
+        Ok(INCLUDE_REGEX
+            .captures_iter(content)
+            .map(|cap| cap["path"].to_string())
+            .collect())
+    }
+
+    fn strip_comments(&self, content: &str) -> String {
+        let mut result = String::with_capacity(content.len());
+        for line in content.lines() {
+            // the // inside a string (e.g. an url) do not start a comment
+            let mut in_string = false;
+            let mut escaped = false;
+            let mut end = line.len();
+            for (offset, c) in line.char_indices() {
+                match c {
+                    '"' if !escaped => in_string = !in_string,
+                    '/' if !in_string && line[offset..].starts_with("//") => {
+                        end = offset;
+                        break;
+                    }
+                    _ => {}
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            result.push_str(&line[..end]);
+            result.push('\n');
+        }
+        result
+    }
+
+    fn references_relative_to_source(&self) -> bool {
+        true
+    }
+
     fn emit_warnings(
         &self,
         _booklet_name: PathBuf,
@@ -174,3 +332,12 @@ impl Language for Typst {
         Ok(())
     }
 }
+
+/// Find the first occurrence of `needle` in `haystack` starting from `start`.
+fn find_from(haystack: &[u8], start: usize, needle: &[u8]) -> Option<usize> {
+    haystack
+        .get(start..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + start)
+}
@@ -0,0 +1,376 @@
+//! A consolidated, machine-readable summary of the results of the evaluation of an IOI task.
+//!
+//! Differently from the streaming `JsonUI`, the summary is a single JSON document written at the
+//! end of the evaluation, with a stable schema versioned by [`EVALUATION_SUMMARY_SCHEMA_VERSION`].
+
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use task_maker_dag::ExecutionDAGConfig;
+
+use crate::ioi::{
+    IOITask, SolutionTestcaseEvaluationState, SubtaskId, TestcaseEvaluationStatus, TestcaseId,
+    UIState,
+};
+use crate::ui::{CompilationStatus, UIMessage, UIStateT, UI};
+
+/// The version of the schema of the [`EvaluationSummary`]. It's bumped every time the schema
+/// changes in an incompatible way; adding new fields is not considered incompatible.
+pub const EVALUATION_SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+/// The summary of the results of the evaluation of a task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationSummary {
+    /// The version of the schema of this document.
+    pub schema_version: u32,
+    /// The name of the task.
+    pub task: String,
+    /// The maximum score of the task.
+    pub max_score: f64,
+    /// The outcome of the compilations, sorted by path.
+    pub compilations: Vec<CompilationSummary>,
+    /// The results of the solutions, sorted by path.
+    pub solutions: Vec<SolutionSummary>,
+}
+
+/// The outcome of the compilation of a source file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompilationSummary {
+    /// The path of the source file, relative to the task directory.
+    pub path: PathBuf,
+    /// The outcome of the compilation: `success`, `failed`, `skipped` or `pending` if it did not
+    /// complete.
+    pub status: String,
+    /// The CPU time used by the compiler, in seconds.
+    pub cpu_time: Option<f64>,
+    /// The peak memory used by the compiler, in KiB.
+    pub memory: Option<u64>,
+    /// The standard error of the compiler, if the compilation failed.
+    pub stderr: Option<String>,
+}
+
+/// The results of a solution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolutionSummary {
+    /// The path of the solution, relative to the task directory.
+    pub path: PathBuf,
+    /// The name of the solution.
+    pub name: String,
+    /// The name of the language of the solution.
+    pub language: String,
+    /// The total score of the solution, rounded like in the UI. `None` if the evaluation did not
+    /// complete.
+    pub score: Option<f64>,
+    /// The results of the solution on each subtask, sorted by id.
+    pub subtasks: Vec<SubtaskSummary>,
+}
+
+/// The results of a solution on a subtask.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtaskSummary {
+    /// The id of the subtask.
+    pub id: SubtaskId,
+    /// The name of the subtask, if any.
+    pub name: Option<String>,
+    /// The score of the solution on the subtask, rounded like in the UI. `None` if the evaluation
+    /// did not complete.
+    pub score: Option<f64>,
+    /// The maximum score of the subtask.
+    pub max_score: f64,
+    /// The results of the solution on each testcase of the subtask, sorted by id.
+    pub testcases: Vec<TestcaseSummary>,
+}
+
+/// The results of a solution on a testcase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestcaseSummary {
+    /// The id of the testcase.
+    pub id: TestcaseId,
+    /// The outcome of the testcase, one of: `accepted`, `partial`, `wrong_answer`,
+    /// `time_limit_exceeded`, `wall_time_limit_exceeded`, `memory_limit_exceeded`,
+    /// `runtime_error`, `failed`, `skipped`, `pending`.
+    pub status: String,
+    /// The message of the checker, or a description of the outcome.
+    pub message: String,
+    /// The score of the solution on the testcase, from 0.0 to 1.0.
+    pub score: Option<f64>,
+    /// The CPU time used by the solution, in seconds, summed over all its processes.
+    pub cpu_time: Option<f64>,
+    /// The wall time used by the solution, in seconds, the maximum among its processes.
+    pub wall_time: Option<f64>,
+    /// The peak memory used by the solution, in KiB, the maximum among its processes.
+    pub memory: Option<u64>,
+}
+
+impl UIState {
+    /// Build the [`EvaluationSummary`] of this evaluation.
+    ///
+    /// This function should be called only after all the executions have completed.
+    pub fn summary(&self) -> EvaluationSummary {
+        let precision = self.task.score_precision;
+        let rounding = self.task.score_rounding;
+        let round = |score: Option<f64>| score.map(|score| rounding.round(score, precision));
+
+        let compilations = self
+            .compilations
+            .iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(path, status)| {
+                let (status, result, stderr) = match status {
+                    CompilationStatus::Pending | CompilationStatus::Running => {
+                        ("pending", None, None)
+                    }
+                    CompilationStatus::Done { result, .. } => ("success", Some(result), None),
+                    CompilationStatus::Failed { result, stderr, .. } => {
+                        ("failed", Some(result), stderr.clone())
+                    }
+                    CompilationStatus::Skipped => ("skipped", None, None),
+                };
+                CompilationSummary {
+                    path: self.task.path_of(path).into(),
+                    status: status.into(),
+                    cpu_time: result.map(|r| r.resources.cpu_time),
+                    memory: result.map(|r| r.resources.memory),
+                    stderr,
+                }
+            })
+            .collect();
+
+        let solutions = self
+            .solutions
+            .iter()
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(path, info)| {
+                let evaluation = self.evaluations.get(path);
+                let subtasks = self
+                    .task
+                    .subtasks
+                    .values()
+                    .sorted_by_key(|subtask| subtask.id)
+                    .map(|subtask| SubtaskSummary {
+                        id: subtask.id,
+                        name: subtask.name.clone(),
+                        score: round(
+                            evaluation
+                                .and_then(|e| e.subtasks.get(&subtask.id))
+                                .and_then(|s| s.score),
+                        ),
+                        max_score: subtask.max_score,
+                        testcases: subtask
+                            .testcases
+                            .iter()
+                            .sorted()
+                            .map(|&id| {
+                                testcase_summary(id, evaluation.and_then(|e| e.testcases.get(&id)))
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                SolutionSummary {
+                    path: self.task.path_of(path).into(),
+                    name: info.name.clone(),
+                    language: info.language_name.clone(),
+                    score: round(evaluation.and_then(|e| e.score)),
+                    subtasks,
+                }
+            })
+            .collect();
+
+        EvaluationSummary {
+            schema_version: EVALUATION_SUMMARY_SCHEMA_VERSION,
+            task: self.task.name.clone(),
+            max_score: self.max_score,
+            compilations,
+            solutions,
+        }
+    }
+}
+
+/// Build the summary of a testcase from the state of its evaluation, if any.
+fn testcase_summary(
+    id: TestcaseId,
+    state: Option<&SolutionTestcaseEvaluationState>,
+) -> TestcaseSummary {
+    use TestcaseEvaluationStatus::*;
+    let Some(state) = state else {
+        return TestcaseSummary {
+            id,
+            status: "pending".into(),
+            message: Pending.message(),
+            score: None,
+            cpu_time: None,
+            wall_time: None,
+            memory: None,
+        };
+    };
+    let status = match &state.status {
+        Pending | Solving | Solved | Checking => "pending",
+        Accepted(_) => "accepted",
+        Partial(_) => "partial",
+        WrongAnswer(_) => "wrong_answer",
+        TimeLimitExceeded => "time_limit_exceeded",
+        WallTimeLimitExceeded => "wall_time_limit_exceeded",
+        MemoryLimitExceeded => "memory_limit_exceeded",
+        RuntimeError => "runtime_error",
+        Failed => "failed",
+        Skipped => "skipped",
+    };
+    let results = state.results.iter().flatten().collect_vec();
+    TestcaseSummary {
+        id,
+        status: status.into(),
+        message: state.status.message(),
+        score: state.score,
+        cpu_time: (!results.is_empty()).then(|| results.iter().map(|r| r.resources.cpu_time).sum()),
+        wall_time: results
+            .iter()
+            .map(|r| r.resources.wall_time)
+            .reduce(f64::max),
+        memory: results.iter().map(|r| r.resources.memory).max(),
+    }
+}
+
+/// A `UI` that collects the results of the evaluation and, at the end, writes their
+/// [`EvaluationSummary`] to a file as JSON.
+pub struct SummaryReport {
+    /// The state of the evaluation.
+    state: UIState,
+    /// Where to write the summary.
+    path: PathBuf,
+}
+
+impl SummaryReport {
+    /// Make a new `SummaryReport` that will be written to `path`.
+    pub fn new<P: Into<PathBuf>>(
+        task: &IOITask,
+        config: ExecutionDAGConfig,
+        path: P,
+    ) -> SummaryReport {
+        SummaryReport {
+            state: UIState::new(task, config),
+            path: path.into(),
+        }
+    }
+
+    /// Write the summary to the file.
+    fn write(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let summary = serde_json::to_string_pretty(&self.state.summary())?;
+        std::fs::write(path, summary + "\n")?;
+        Ok(())
+    }
+}
+
+impl UI for SummaryReport {
+    fn on_message(&mut self, message: UIMessage) {
+        self.state.apply(message);
+    }
+
+    fn finish(&mut self) {
+        if let Err(e) = self.write(&self.path) {
+            error!(
+                "Failed to write the result summary to {}: {e:?}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus};
+
+    use super::*;
+    use crate::ioi::{SolutionEvaluationState, SubtaskInfo};
+    use crate::solution::SolutionInfo;
+
+    fn result(cpu_time: f64, memory: u64) -> ExecutionResult {
+        ExecutionResult {
+            status: ExecutionStatus::Success,
+            was_killed: false,
+            was_cached: false,
+            cache_hit: None,
+            missing_outputs: vec![],
+            resources: ExecutionResourcesUsage {
+                cpu_time,
+                wall_time: cpu_time + 0.5,
+                memory,
+                ..Default::default()
+            },
+            stdout: None,
+            stderr: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluation_summary() {
+        let mut task = IOITask::fake();
+        task.name = "task".into();
+        task.subtasks.insert(
+            0,
+            SubtaskInfo {
+                id: 0,
+                name: Some("st0".into()),
+                max_score: 100.0,
+                testcases: vec![1, 0],
+                ..Default::default()
+            },
+        );
+        let mut state = UIState::new(&task, ExecutionDAGConfig::new());
+        let sol = task.path.join("sol/sol.cpp");
+        state.apply(UIMessage::Solutions {
+            solutions: vec![SolutionInfo {
+                path: sol.clone(),
+                name: "sol.cpp".into(),
+                language_name: "C++".into(),
+                checks: vec![],
+                score_checks: vec![],
+            }],
+        });
+        state.compilations.insert(
+            sol.clone(),
+            CompilationStatus::Done {
+                result: result(1.0, 100),
+                stdout: None,
+                stderr: None,
+            },
+        );
+        let mut evaluation = SolutionEvaluationState::new(&task);
+        evaluation.score = Some(50.0);
+        evaluation.subtasks.get_mut(&0).unwrap().score = Some(50.0);
+        evaluation.testcases.insert(
+            0,
+            SolutionTestcaseEvaluationState {
+                score: Some(1.0),
+                status: TestcaseEvaluationStatus::Accepted("".into()),
+                results: vec![Some(result(0.25, 10)), Some(result(0.5, 20))],
+                checker: None,
+                worker: None,
+            },
+        );
+        state.evaluations.insert(sol, evaluation);
+
+        let summary = state.summary();
+        assert_eq!(summary.schema_version, EVALUATION_SUMMARY_SCHEMA_VERSION);
+        assert_eq!(summary.compilations.len(), 1);
+        assert_eq!(summary.compilations[0].path, Path::new("sol/sol.cpp"));
+        assert_eq!(summary.compilations[0].status, "success");
+        assert_eq!(summary.solutions.len(), 1);
+        let solution = &summary.solutions[0];
+        assert_eq!(solution.path, Path::new("sol/sol.cpp"));
+        assert_eq!(solution.score, Some(50.0));
+        assert_eq!(solution.subtasks[0].score, Some(50.0));
+        let testcases = &solution.subtasks[0].testcases;
+        assert_eq!(testcases.iter().map(|tc| tc.id).collect_vec(), vec![0, 1]);
+        assert_eq!(testcases[0].status, "accepted");
+        assert_eq!(testcases[0].cpu_time, Some(0.75));
+        assert_eq!(testcases[0].wall_time, Some(1.0));
+        assert_eq!(testcases[0].memory, Some(20));
+        assert_eq!(testcases[1].status, "pending");
+        assert_eq!(testcases[1].cpu_time, None);
+
+        let json = serde_json::to_string(&summary).unwrap();
+        let parsed: EvaluationSummary = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, summary);
+    }
+}
@@ -1,10 +1,11 @@
+use std::collections::HashSet;
 use std::path::PathBuf;
 
 use anyhow::Error;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::ioi::IOITask;
+use crate::ioi::{IOITask, SubtaskId, TaskType};
 
 /// Task information structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +16,9 @@ pub struct IOITaskInfo {
     pub name: String,
     /// Title of the task.
     pub title: String,
+    /// Type of the task: `batch`, `communication`, `interactive` or `none`.
+    #[serde(default)]
+    pub task_type: String,
     /// Scoring info.
     pub scoring: TaskInfoScoring,
     /// Limits of the task.
@@ -52,6 +56,12 @@ pub struct TaskInfoSubtask {
     pub max_score: f64,
     /// Number of testcases for this subtask.
     pub testcases: u64,
+    /// Name of this subtask.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The subtasks whose testcases are included in this subtask.
+    #[serde(default)]
+    pub dependencies: Vec<SubtaskId>,
 }
 
 /// Scoring for the task.
@@ -63,6 +73,17 @@ pub struct TaskInfoScoring {
     pub subtasks: Vec<TaskInfoSubtask>,
 }
 
+/// The tree of the dependencies between the subtasks, made by
+/// [`TaskInfoScoring::dependency_tree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtaskDependencyTree {
+    /// The lines of the tree, already indented. A subtask is expanded only the first time it's
+    /// found, the next times it's marked as already shown.
+    pub lines: Vec<String>,
+    /// The cycles found between the dependencies, each one as the list of the subtasks in it.
+    pub cycles: Vec<Vec<SubtaskId>>,
+}
+
 /// Statement of the task.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskInfoStatement {
@@ -81,6 +102,13 @@ impl IOITaskInfo {
             version: 1,
             name: task.name.clone(),
             title: task.title.clone(),
+            task_type: match task.task_type {
+                TaskType::Batch(_) => "batch",
+                TaskType::Communication(_) => "communication",
+                TaskType::Interactive(_) => "interactive",
+                TaskType::None => "none",
+            }
+            .into(),
             scoring: TaskInfoScoring {
                 max_score: task
                     .subtasks
@@ -93,6 +121,8 @@ impl IOITaskInfo {
                     .map(|(_, subtask)| TaskInfoSubtask {
                         max_score: subtask.max_score,
                         testcases: subtask.testcases.len() as u64,
+                        name: subtask.name.clone(),
+                        dependencies: subtask.dependencies.clone(),
                     })
                     .collect(),
             },
@@ -134,3 +164,131 @@ impl IOITaskInfo {
         })
     }
 }
+
+impl TaskInfoScoring {
+    /// Build the tree of the dependencies between the subtasks. The roots are the subtasks that no
+    /// other subtask depends on, and the children of a subtask are its dependencies.
+    pub fn dependency_tree(&self) -> SubtaskDependencyTree {
+        let depended: HashSet<SubtaskId> = self
+            .subtasks
+            .iter()
+            .flat_map(|subtask| subtask.dependencies.iter().copied())
+            .collect();
+        let ids = 0..self.subtasks.len() as SubtaskId;
+        let roots = ids
+            .clone()
+            .filter(|id| !depended.contains(id))
+            .collect_vec();
+        let mut tree = SubtaskDependencyTree::default();
+        let mut visited = HashSet::new();
+        // the subtasks in a cycle may not be reachable from any root
+        for id in roots.into_iter().chain(ids) {
+            if !visited.contains(&id) {
+                self.add_to_tree(id, "", "", &mut vec![], &mut visited, &mut tree);
+            }
+        }
+        tree
+    }
+
+    /// Add to the tree the subtask and, if not already shown, its dependencies. `path` contains the
+    /// subtasks from the root to this one, for detecting the cycles.
+    fn add_to_tree(
+        &self,
+        id: SubtaskId,
+        prefix: &str,
+        child_prefix: &str,
+        path: &mut Vec<SubtaskId>,
+        visited: &mut HashSet<SubtaskId>,
+        tree: &mut SubtaskDependencyTree,
+    ) {
+        let mut line = format!("{prefix}Subtask {id}");
+        let Some(subtask) = self.subtasks.get(id as usize) else {
+            tree.lines.push(format!("{line} (unknown subtask)"));
+            return;
+        };
+        if let Some(name) = &subtask.name {
+            line += &format!(" [{name}]");
+        }
+        if let Some(start) = path.iter().position(|&st| st == id) {
+            tree.lines.push(format!("{line} (cycle)"));
+            tree.cycles.push(path[start..].to_vec());
+            return;
+        }
+        if !visited.insert(id) {
+            tree.lines.push(format!("{line} (see above)"));
+            return;
+        }
+        tree.lines.push(format!(
+            "{line}: {} points, {} testcases",
+            subtask.max_score, subtask.testcases
+        ));
+        path.push(id);
+        for (i, &dep) in subtask.dependencies.iter().enumerate() {
+            let (branch, indent) = if i + 1 == subtask.dependencies.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            self.add_to_tree(
+                dep,
+                &format!("{child_prefix}{branch}"),
+                &format!("{child_prefix}{indent}"),
+                path,
+                visited,
+                tree,
+            );
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoring(dependencies: Vec<Vec<SubtaskId>>) -> TaskInfoScoring {
+        TaskInfoScoring {
+            max_score: 0.0,
+            subtasks: dependencies
+                .into_iter()
+                .map(|dependencies| TaskInfoSubtask {
+                    max_score: 10.0,
+                    testcases: 2,
+                    name: None,
+                    dependencies,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_dependency_tree_diamond() {
+        let tree = scoring(vec![vec![], vec![0], vec![0], vec![1, 2]]).dependency_tree();
+        assert_eq!(
+            tree.lines,
+            vec![
+                "Subtask 3: 10 points, 2 testcases",
+                "├── Subtask 1: 10 points, 2 testcases",
+                "│   └── Subtask 0: 10 points, 2 testcases",
+                "└── Subtask 2: 10 points, 2 testcases",
+                "    └── Subtask 0 (see above)",
+            ]
+        );
+        assert!(tree.cycles.is_empty());
+    }
+
+    #[test]
+    fn test_dependency_tree_cycle() {
+        let tree = scoring(vec![vec![1], vec![2], vec![0]]).dependency_tree();
+        assert_eq!(
+            tree.lines,
+            vec![
+                "Subtask 0: 10 points, 2 testcases",
+                "└── Subtask 1: 10 points, 2 testcases",
+                "    └── Subtask 2: 10 points, 2 testcases",
+                "        └── Subtask 0 (cycle)",
+            ]
+        );
+        assert_eq!(tree.cycles, vec![vec![0, 1, 2]]);
+    }
+}
@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
 use task_maker_dag::*;
 use task_maker_diagnostics::DiagnosticContext;
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{CacheStats, ExecutorStatus};
 
 use crate::ioi::*;
 use crate::solution::{SolutionCheck, SolutionInfo, TestcaseEvaluationResult};
@@ -112,6 +112,10 @@ pub struct SolutionTestcaseEvaluationState {
     pub results: Vec<Option<ExecutionResult>>,
     /// The result of the checker.
     pub checker: Option<ExecutionResult>,
+    /// The worker that executed the solution. If the execution has been rescheduled this is the
+    /// last worker that started it, and it's `None` if the execution has not started or it was
+    /// cached.
+    pub worker: Option<WorkerUuid>,
 }
 
 impl SolutionTestcaseEvaluationState {
@@ -204,6 +208,7 @@ impl SolutionEvaluationState {
                             status: TestcaseEvaluationStatus::Pending,
                             results: Vec::new(),
                             checker: None,
+                            worker: None,
                         },
                     )
                 })
@@ -228,6 +233,39 @@ pub struct BookletState {
     pub dependencies: HashMap<String, Vec<BookletDependencyState>>,
 }
 
+/// The outcome of a diagnostic case of a solution.
+#[derive(Debug, Clone)]
+pub struct DiagnosticCaseOutcome {
+    /// The id of the subtask the input file is generated for.
+    pub subtask: SubtaskId,
+    /// The arguments passed to the generator.
+    pub args: Vec<String>,
+    /// The score of the solution on the diagnostic case, from 0.0 to 1.0.
+    pub score: f64,
+    /// The message associated with the score.
+    pub message: String,
+}
+
+/// The ranking of the solutions by score and then by time.
+#[derive(Debug, Clone, Default)]
+pub struct SolutionRanking {
+    /// The solutions that completed the evaluation, from the best to the worst.
+    pub ranked: Vec<RankedSolution>,
+    /// The solutions that did not complete the evaluation, with the reason.
+    pub not_completed: Vec<(PathBuf, String)>,
+}
+
+/// A solution in the [`SolutionRanking`].
+#[derive(Debug, Clone)]
+pub struct RankedSolution {
+    /// The path of the solution.
+    pub path: PathBuf,
+    /// The score of the solution.
+    pub score: f64,
+    /// The total CPU time used by the solution on all the testcases, in seconds.
+    pub total_time: f64,
+}
+
 /// The state of a IOI task, all the information for the UI are stored here.
 #[derive(Debug, Clone)]
 pub struct UIState {
@@ -245,8 +283,14 @@ pub struct UIState {
     pub generations: HashMap<SubtaskId, SubtaskGenerationState>,
     /// The status of the evaluations of the solutions.
     pub evaluations: HashMap<PathBuf, SolutionEvaluationState>,
+    /// The outcomes of the diagnostic cases of the solutions, indexed by the index of the case.
+    pub diagnostic_cases: HashMap<PathBuf, BTreeMap<usize, DiagnosticCaseOutcome>>,
     /// The status of the executor.
     pub executor_status: Option<ExecutorStatus<SystemTime>>,
+    /// The hits and misses of the cache lookups, known at the end of the evaluation.
+    pub cache_stats: Option<CacheStats>,
+    /// The names of all the workers ever seen in the status of the executor.
+    pub worker_names: HashMap<WorkerUuid, String>,
     /// The status of the booklets
     pub booklets: HashMap<String, BookletState>,
     /// Diagnostic context.
@@ -364,12 +408,24 @@ impl UIState {
             compilations: HashMap::new(),
             generations,
             evaluations: HashMap::new(),
+            diagnostic_cases: HashMap::new(),
             executor_status: None,
+            cache_stats: None,
+            worker_names: HashMap::new(),
             booklets: HashMap::new(),
             diagnostics: Default::default(),
         }
     }
 
+    /// The name of a worker, falling back to its UUID if the worker has never been seen in the
+    /// status of the executor.
+    pub fn worker_name(&self, worker: WorkerUuid) -> String {
+        self.worker_names
+            .get(&worker)
+            .cloned()
+            .unwrap_or_else(|| worker.to_string())
+    }
+
     /// Evaluate the checks of all the solutions.
     ///
     /// This function should be called only after all the executions have completed.
@@ -412,6 +468,50 @@ impl UIState {
         }
         result
     }
+
+    /// Rank the solutions by score, breaking the ties by the total CPU time they used. The scores
+    /// are compared after the rounding, so the ties are the ones visible to the user. The
+    /// solutions that failed to compile or whose score is unknown are not ranked.
+    ///
+    /// This function should be called only after all the executions have completed.
+    pub fn solution_ranking(&self) -> SolutionRanking {
+        let precision = self.task.score_precision;
+        let rounding = self.task.score_rounding;
+        let mut ranking = SolutionRanking::default();
+        for (path, evaluation) in self.evaluations.iter() {
+            if let Some(CompilationStatus::Failed { .. }) = self.compilations.get(path) {
+                ranking
+                    .not_completed
+                    .push((path.clone(), "Compilation failed".into()));
+                continue;
+            }
+            let Some(score) = evaluation.score else {
+                ranking
+                    .not_completed
+                    .push((path.clone(), "Evaluation not completed".into()));
+                continue;
+            };
+            let total_time = evaluation
+                .testcases
+                .values()
+                .flat_map(|testcase| testcase.results.iter().flatten())
+                .map(|result| result.resources.cpu_time)
+                .sum();
+            ranking.ranked.push(RankedSolution {
+                path: path.clone(),
+                score: rounding.round(score, precision),
+                total_time,
+            });
+        }
+        ranking.ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(a.total_time.total_cmp(&b.total_time))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        ranking.not_completed.sort();
+        ranking
+    }
 }
 
 impl UIStateT for UIState {
@@ -419,7 +519,13 @@ impl UIStateT for UIState {
     fn apply(&mut self, message: UIMessage) {
         match message {
             UIMessage::StopUI => {}
-            UIMessage::ServerStatus { status } => self.executor_status = Some(status),
+            UIMessage::ServerStatus { status } => {
+                // keep the names of the workers that disconnected, they may have executed something
+                for worker in &status.connected_workers {
+                    self.worker_names.insert(worker.uuid, worker.name.clone());
+                }
+                self.executor_status = Some(status);
+            }
             UIMessage::Solutions { solutions } => {
                 self.solutions = solutions
                     .into_iter()
@@ -547,8 +653,9 @@ impl UIStateT for UIState {
                 let testcase = eval.testcases.get_mut(&testcase).expect("Missing testcase");
                 match status {
                     UIExecutionStatus::Pending => {}
-                    UIExecutionStatus::Started { .. } => {
-                        testcase.status = TestcaseEvaluationStatus::Solving
+                    UIExecutionStatus::Started { worker } => {
+                        testcase.status = TestcaseEvaluationStatus::Solving;
+                        testcase.worker = Some(worker);
                     }
                     UIExecutionStatus::Done { result } => {
                         testcase.status = result
@@ -672,6 +779,24 @@ impl UIStateT for UIState {
                     .or_insert_with(|| SolutionEvaluationState::new(task));
                 eval.score = Some(score);
             }
+            UIMessage::IOIDiagnosticCase {
+                solution,
+                index,
+                subtask,
+                args,
+                score,
+                message,
+            } => {
+                self.diagnostic_cases.entry(solution).or_default().insert(
+                    index,
+                    DiagnosticCaseOutcome {
+                        subtask,
+                        args,
+                        score,
+                        message,
+                    },
+                );
+            }
             UIMessage::IOIBooklet { name, status } => {
                 self.booklets
                     .entry(name)
@@ -710,6 +835,7 @@ impl UIStateT for UIState {
             UIMessage::Diagnostic { diagnostic } => {
                 self.diagnostics.add_diagnostic(diagnostic);
             }
+            UIMessage::CacheStats { stats } => self.cache_stats = Some(stats),
             UIMessage::TerryTask { .. }
             | UIMessage::TerryGeneration { .. }
             | UIMessage::TerryValidation { .. }
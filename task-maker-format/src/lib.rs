@@ -24,8 +24,9 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::Error;
-pub use detect_format::find_task;
+use anyhow::{bail, Error};
+pub use custom_format::{register_task_format, CustomTask, CustomTaskParser};
+pub use detect_format::{find_task, find_task_with_retries};
 use itertools::Itertools;
 pub use sanity_checks::{get_sanity_check_list, SanityCheckCategory};
 use serde::{Deserialize, Serialize};
@@ -43,6 +44,7 @@ pub use crate::solution::*;
 use crate::terry::{Seed, TerryTask};
 use crate::ui::UI;
 
+mod custom_format;
 mod detect_format;
 pub mod ioi;
 mod sanity_checks;
@@ -88,15 +90,35 @@ pub struct EvaluationConfig {
     pub booklet_solutions: bool,
     /// Do not build the statement files and the booklets.
     pub no_statement: bool,
+    /// Only validate the statement sources, without compiling the booklets.
+    pub check_statement: bool,
     /// Execute only the solution with the specified paths, that can reside anywhere in the
     /// filesystem.
     pub solution_paths: Vec<PathBuf>,
+    /// Evaluate only the solutions written in one of these languages, matched against
+    /// `Language::name()`. If left empty the solutions in all the languages are evaluated.
+    pub allowed_languages: Vec<String>,
     /// List of disabled sanity check names.
     pub disabled_sanity_checks: Vec<String>,
     /// Force this seed in terry evaluations.
     pub seed: Option<Seed>,
     /// Do not write any file inside the task directory.
     pub dry_run: bool,
+    /// The maximum number of executions the evaluation can add to the DAG, `None` for no limit.
+    pub max_executions: Option<usize>,
+    /// Path to a manifest of testcases whose input files are taken from the store, by hash,
+    /// instead of being generated.
+    pub pinned_inputs: Option<PathBuf>,
+    /// Compile the solutions whose names start with one of these with the sanitizers.
+    pub sanitized_solutions: Vec<String>,
+    /// Override the number of processes of the solutions of a communication task.
+    pub num_processes: Option<u8>,
+    /// Stop evaluating a solution after this number of testcases without full score, skipping the
+    /// others. Only IOI tasks support it.
+    pub stop_after: Option<u64>,
+    /// Compile the solutions with this optimization level (e.g. `0` for `-O0`) instead of the
+    /// default one, if their language supports it.
+    pub opt_level: Option<u8>,
 }
 
 /// The data for an evaluation, including the DAG and the UI channel.
@@ -130,6 +152,21 @@ impl EvaluationData {
     pub fn add_diagnostic(&self, diagnostic: Diagnostic) -> Result<(), Error> {
         self.sender.add_diagnostic(diagnostic)
     }
+
+    /// Fail if the DAG contains more executions than allowed by the configuration. This stops a
+    /// misconfigured task from exhausting the memory while its DAG is being built.
+    pub fn check_executions_limit(&self, config: &EvaluationConfig) -> Result<(), Error> {
+        let Some(limit) = config.max_executions else {
+            return Ok(());
+        };
+        if self.dag.data.execution_groups.len() > limit {
+            bail!(
+                "The evaluation requires more than {limit} executions, use --max-executions to \
+                raise the limit"
+            );
+        }
+        Ok(())
+    }
 }
 
 /// What can send [`UIMessage`](ui/enum.UIMessage.html)s.
@@ -170,17 +207,31 @@ impl EvaluationConfig {
     }
 
     /// Returns the fixed solutions in the config or, if none is specified, all the ones matching
-    /// the provided pattern in the provided base directory.
-    fn solution_paths(&self, base_dir: &Path, patterns: Vec<&str>) -> Vec<PathBuf> {
-        if self.solution_paths.is_empty() {
-            list_files(base_dir, patterns)
-        } else {
-            self.solution_paths.clone()
+    /// the provided pattern in the provided base directory, except the ones excluded by the
+    /// `.tmignore` files.
+    fn solution_paths(
+        &self,
+        base_dir: &Path,
+        patterns: Vec<&str>,
+        eval: &EvaluationData,
+    ) -> Vec<PathBuf> {
+        if !self.solution_paths.is_empty() {
+            return self.solution_paths.clone();
         }
+        let ignore = SolutionIgnore::load(base_dir, &patterns, eval);
+        list_files(base_dir, patterns)
+            .into_iter()
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| name != SOLUTIONS_IGNORE_FILE)
+            })
+            .filter(|path| !ignore.is_ignored(path.strip_prefix(base_dir).unwrap_or(path)))
+            .collect()
     }
 
     /// Search all the solutions matching the provided pattern in the provided base directory,
-    /// excluding all the graders in the grader_map, if provided.
+    /// excluding all the graders in the grader_map, if provided, and the files matched by the
+    /// `.tmignore` file in the folder of the solutions.
     ///
     /// If the configuration is set with a filter, it is applied.
     ///
@@ -192,14 +243,14 @@ impl EvaluationConfig {
         grader_map: Option<Arc<GraderMap>>,
         eval: &mut EvaluationData,
     ) -> Vec<Solution> {
-        let solutions_paths = self.solution_paths(base_dir, patterns);
+        let solutions_paths = self.solution_paths(base_dir, patterns, eval);
         let filter = self.solution_filters();
         let graders: HashSet<PathBuf> = if let Some(grader_map) = &grader_map {
             grader_map.all_paths().map(|p| p.to_path_buf()).collect()
         } else {
             HashSet::new()
         };
-        solutions_paths
+        let solutions: Vec<_> = solutions_paths
             .into_iter()
             .filter(|p| !graders.contains(p)) // the graders are not solutions
             .filter(|p| p.exists())
@@ -213,8 +264,80 @@ impl EvaluationConfig {
                     .any(|filter| name.starts_with(filter.as_str()))
             })
             .filter_map(|path| Solution::new(&path, base_dir, grader_map.clone(), eval))
+            .map(|solution| self.sanitize_if_needed(solution, eval))
+            .map(|solution| self.set_opt_level_if_needed(solution, eval))
+            .collect();
+        // the languages are checked only on the solutions, the graders are allowed in any language
+        solutions
+            .into_iter()
+            .filter(|solution| self.is_language_allowed(solution, eval))
             .collect()
     }
+
+    /// Compile the solution with the sanitizers if its name matches one of `sanitized_solutions`,
+    /// emitting a warning if its language doesn't support them.
+    fn sanitize_if_needed(&self, mut solution: Solution, eval: &EvaluationData) -> Solution {
+        let name = solution.source_file.name();
+        if !self
+            .sanitized_solutions
+            .iter()
+            .any(|prefix| name.starts_with(prefix.as_str()))
+        {
+            return solution;
+        }
+        if !Arc::make_mut(&mut solution.source_file).sanitize() {
+            let _ = eval.add_diagnostic(Diagnostic::warning(format!(
+                "Solution {} is written in {}, which doesn't support the sanitizers",
+                name,
+                solution.source_file.language().name()
+            )));
+        }
+        solution
+    }
+
+    /// Compile the solution with `opt_level`, if set, emitting a warning if its language doesn't
+    /// support it.
+    fn set_opt_level_if_needed(&self, mut solution: Solution, eval: &EvaluationData) -> Solution {
+        let Some(opt_level) = self.opt_level else {
+            return solution;
+        };
+        if !Arc::make_mut(&mut solution.source_file).set_opt_level(opt_level) {
+            let _ = eval.add_diagnostic(Diagnostic::warning(format!(
+                "Solution {} is written in {}, which doesn't support choosing the optimization level",
+                solution.source_file.name(),
+                solution.source_file.language().name()
+            )));
+        }
+        solution
+    }
+
+    /// Check if the language of the solution is allowed by the configuration, emitting a warning
+    /// if it's not.
+    fn is_language_allowed(&self, solution: &Solution, eval: &EvaluationData) -> bool {
+        if self.allowed_languages.is_empty() {
+            return true;
+        }
+        let language = solution.source_file.language().name();
+        if self
+            .allowed_languages
+            .iter()
+            .any(|allowed| allowed == language)
+        {
+            return true;
+        }
+        let _ = eval.add_diagnostic(
+            Diagnostic::warning(format!(
+                "Solution {} is written in {}, which is not an allowed language. Skipping it.",
+                solution.source_file.name(),
+                language
+            ))
+            .with_note(format!(
+                "The allowed languages are: {}",
+                self.allowed_languages.join(", ")
+            )),
+        );
+        false
+    }
 }
 
 /// List all the files inside `cwd` that matches a list of glob patterns. The results are in the
@@ -378,6 +501,23 @@ mod tests {
         assert!(files.contains(&tmpdir.path().join("foo/baz/uuu.bbb")));
     }
 
+    #[test]
+    fn test_find_solutions_tmignore() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(tmpdir.path().join("sol")).unwrap();
+        std::fs::write(tmpdir.path().join("sol/solution.py"), "").unwrap();
+        std::fs::write(tmpdir.path().join("sol/draft.py"), "").unwrap();
+        std::fs::write(tmpdir.path().join("sol/.tmignore"), "sol/draft.py\n").unwrap();
+        let (mut eval, _recv) = EvaluationData::new(tmpdir.path());
+        let config = EvaluationConfig::default();
+        let solutions = config.find_solutions(tmpdir.path(), vec!["sol/*"], None, &mut eval);
+        let paths = solutions
+            .iter()
+            .map(|solution| solution.source_file.path.clone())
+            .collect_vec();
+        assert_eq!(paths, vec![tmpdir.path().join("sol/solution.py")]);
+    }
+
     #[test]
     fn test_find_source_file() {
         let tmpdir = tempfile::TempDir::new().unwrap();
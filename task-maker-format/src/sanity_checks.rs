@@ -61,6 +61,12 @@ pub trait SanityCheck: Send + Sync + std::fmt::Debug {
     /// The category of the sanity check.
     fn category(&self) -> SanityCheckCategory;
 
+    /// Whether this sanity check needs the evaluation of the task, i.e. it adds some executions to
+    /// the DAG or it looks at the results of the evaluation.
+    fn requires_evaluation(&self) -> bool {
+        false
+    }
+
     /// This function will be called before the actual execution of the DAG. It can add new
     /// executions to the DAG.
     fn pre_hook(&self, _task: &Self::Task, _eval: &mut EvaluationData) -> Result<(), Error> {
@@ -157,6 +163,32 @@ impl<Task> SanityChecks<Task> {
         Ok(())
     }
 
+    /// Run the `pre_hook` of the sanity checks that do not require the evaluation of the task,
+    /// without building nor running the DAG. Returns the names of the sanity checks that have been
+    /// skipped.
+    pub fn static_checks(
+        &self,
+        task: &Task,
+        eval: &mut EvaluationData,
+    ) -> Result<Vec<&'static str>, Error> {
+        let mut state = self.state.lock().unwrap();
+        let mut skipped = vec![];
+        for check in state.sanity_checks.iter_mut() {
+            if check.requires_evaluation() {
+                skipped.push(check.name());
+                continue;
+            }
+            if let Err(e) = check.pre_hook(task, eval) {
+                eval.add_diagnostic(Diagnostic::warning(format!(
+                    "Sanity check {} failed: {}",
+                    check.name(),
+                    e
+                )))?;
+            }
+        }
+        Ok(skipped)
+    }
+
     /// Function called after the evaluation completes. This will check that the produced assets are
     /// valid and the executions added by the pre_hook produced the correct results.
     pub fn post_hook(&self, task: &Task, eval: &mut EvaluationData) -> Result<(), Error> {
@@ -6,6 +6,8 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::{bail, Context, Error};
+use glob::{MatchOptions, Pattern};
+use itertools::Itertools;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use task_maker_diagnostics::{CodeSpan, Diagnostic};
@@ -20,6 +22,12 @@ pub struct Solution {
     pub source_file: Arc<SourceFile>,
     /// The set of checks to perform on the solution.
     pub checks: Vec<SolutionCheck>,
+    /// The set of checks on the score of the solution in the subtasks.
+    pub score_checks: Vec<SolutionScoreCheck>,
+    /// The bounds on the resources the solution is expected to use.
+    pub resource_bounds: SolutionResourceBounds,
+    /// The additional testcases to evaluate the solution on, outside of the official testset.
+    pub diagnostic_cases: Vec<SolutionDiagnosticCase>,
 }
 
 impl Solution {
@@ -43,13 +51,146 @@ impl Solution {
             grader_map,
             Some(write_to),
         )?;
+        for warning in &source_file.warnings {
+            let mut diagnostic = Diagnostic::warning(format!(
+                "Solution {}: {}",
+                source_file.name(),
+                warning.message
+            ));
+            if let Some(note) = &warning.note {
+                diagnostic = diagnostic.with_note(note);
+            }
+            let _ = eval.add_diagnostic(diagnostic);
+        }
         Some(Self {
             source_file: Arc::new(source_file),
             checks: SolutionCheck::extract_check_list(path, eval).ok()?,
+            score_checks: SolutionScoreCheck::extract_list(path, eval).ok()?,
+            resource_bounds: SolutionResourceBounds::extract(path, eval).ok()?,
+            diagnostic_cases: SolutionDiagnosticCase::extract_list(path, eval).ok()?,
         })
     }
 }
 
+/// The name of the file, inside the folders of the solutions, with the patterns of the files that
+/// are not solutions (e.g. notes or drafts).
+pub const SOLUTIONS_IGNORE_FILE: &str = ".tmignore";
+
+/// The patterns of the files to exclude from the solutions, read from the `.tmignore` files.
+///
+/// The syntax is a subset of the one of `.gitignore`: each line is a glob pattern, the empty lines
+/// and the ones starting with `#` are skipped. A pattern containing a `/` matches the path relative
+/// to the task root (e.g. `sol/draft*.cpp`), otherwise it matches the name of the file (e.g.
+/// `*.txt`). A pattern starting with `!` includes back the files excluded by the previous ones.
+#[derive(Debug, Default)]
+pub(crate) struct SolutionIgnore {
+    /// The patterns, in the order they are written.
+    patterns: Vec<IgnorePattern>,
+}
+
+/// A pattern of a `.tmignore` file.
+#[derive(Debug)]
+struct IgnorePattern {
+    /// The glob pattern to match.
+    pattern: Pattern,
+    /// Whether the pattern starts with `!`, i.e. the matching files are not ignored.
+    negated: bool,
+    /// Whether the pattern matches the path relative to the task root, instead of the file name.
+    anchored: bool,
+}
+
+impl SolutionIgnore {
+    /// Load the `.tmignore` files in the folders of the provided patterns (e.g. `sol/.tmignore`
+    /// for `sol/*`), emitting a warning for each invalid pattern.
+    pub fn load(base_dir: &Path, patterns: &[&str], eval: &EvaluationData) -> Self {
+        let mut ignore = SolutionIgnore::default();
+        let dirs = patterns
+            .iter()
+            .filter_map(|pattern| Path::new(pattern).parent())
+            .unique();
+        for dir in dirs {
+            let path = base_dir.join(dir).join(SOLUTIONS_IGNORE_FILE);
+            if !path.exists() {
+                continue;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(content) => ignore.add_patterns(&content, &path, eval),
+                Err(e) => {
+                    let _ = eval.add_diagnostic(
+                        Diagnostic::warning(format!("Failed to read {}", path.display()))
+                            .with_note(e.to_string()),
+                    );
+                }
+            }
+        }
+        ignore
+    }
+
+    /// Add the patterns in the content of the `.tmignore` file at the given path.
+    fn add_patterns(&mut self, content: &str, path: &Path, eval: &EvaluationData) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line),
+            };
+            // a trailing slash matches only directories, they are matched as any other path
+            let pattern = pattern.trim_end_matches('/');
+            let anchored = pattern.contains('/');
+            let pattern = pattern.trim_start_matches('/');
+            match Pattern::new(pattern) {
+                Ok(pattern) => self.patterns.push(IgnorePattern {
+                    pattern,
+                    negated,
+                    anchored,
+                }),
+                Err(e) => {
+                    let _ = eval.add_diagnostic(
+                        Diagnostic::warning(format!(
+                            "Invalid pattern in {}: {line}",
+                            path.display()
+                        ))
+                        .with_note(e.to_string()),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check if the file at the given path, relative to the task root, should not be considered a
+    /// solution. A file inside an ignored folder is ignored as well.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let mut ignored = false;
+        for pattern in &self.patterns {
+            let matches = path
+                .ancestors()
+                .filter(|path| !path.as_os_str().is_empty())
+                .any(|path| {
+                    if pattern.anchored {
+                        pattern.pattern.matches_path_with(path, options)
+                    } else {
+                        path.file_name().is_some_and(|name| {
+                            pattern
+                                .pattern
+                                .matches_with(&name.to_string_lossy(), options)
+                        })
+                    }
+                });
+            if matches {
+                ignored = !pattern.negated;
+            }
+        }
+        ignored
+    }
+}
+
 /// Some information about a solution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SolutionInfo {
@@ -61,6 +202,9 @@ pub struct SolutionInfo {
     pub language_name: String,
     /// The list of checks specified inside the source file.
     pub checks: Vec<SolutionCheck>,
+    /// The list of checks on the subtask scores specified inside the source file.
+    #[serde(default)]
+    pub score_checks: Vec<SolutionScoreCheck>,
 }
 
 impl From<&Solution> for SolutionInfo {
@@ -70,6 +214,7 @@ impl From<&Solution> for SolutionInfo {
             name: solution.source_file.name(),
             language_name: solution.source_file.language().name().into(),
             checks: solution.checks.clone(),
+            score_checks: solution.score_checks.clone(),
         }
     }
 }
@@ -101,6 +246,315 @@ impl SolutionCheck {
     }
 }
 
+/// The maximum amount of resources a solution is expected to use on any testcase, as specified
+/// with the `@max-time` and `@max-memory` annotations inside its source file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SolutionResourceBounds {
+    /// The maximum CPU time (in seconds) the solution is expected to use.
+    pub max_time: Option<f64>,
+    /// The maximum memory (in KiB) the solution is expected to use.
+    pub max_memory: Option<u64>,
+    /// Span of the annotation specifying the time bound.
+    pub time_span: Option<CodeSpan>,
+    /// Span of the annotation specifying the memory bound.
+    pub memory_span: Option<CodeSpan>,
+}
+
+impl SolutionResourceBounds {
+    /// Whether at least one of the bounds is specified.
+    pub fn is_empty(&self) -> bool {
+        self.max_time.is_none() && self.max_memory.is_none()
+    }
+
+    /// Try to extract the resource bounds from a file.
+    ///
+    /// The supported annotations are `@max-time: <time>` (with the time in `ms` or `s`) and
+    /// `@max-memory: <memory>` (with the memory in `KiB`, `MiB` or `GiB`). If an annotation is
+    /// specified more than once, the last one is used.
+    pub fn extract<P: AsRef<Path>>(path: P, eval: &mut EvaluationData) -> Result<Self, Error> {
+        lazy_static! {
+            static ref FIND_BOUNDS: Regex =
+                Regex::new(r"@max-(?:time|memory)\b[^@\n]*").expect("Invalid regex");
+            static ref EXTRACT_TIME: Regex =
+                Regex::new(r"^@max-time:\s*(?P<value>\d+(?:\.\d+)?)\s*(?P<unit>ms|s)\b")
+                    .expect("Invalid regex");
+            static ref EXTRACT_MEMORY: Regex =
+                Regex::new(r"^@max-memory:\s*(?P<value>\d+)\s*(?P<unit>KiB|MiB|GiB)\b")
+                    .expect("Invalid regex");
+        }
+
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let rel_path = path.strip_prefix(&eval.task_root).unwrap_or(path);
+
+        let mut bounds = Self::default();
+        for found in FIND_BOUNDS.find_iter(&content) {
+            let annotation = found.as_str().trim_end();
+            let span = |len: usize| CodeSpan::from_str(rel_path, &content, found.start(), len);
+            if let Some(captures) = EXTRACT_TIME.captures(annotation) {
+                let value: f64 = captures["value"].parse()?;
+                let value = match &captures["unit"] {
+                    "ms" => value / 1000.0,
+                    _ => value,
+                };
+                bounds.max_time = Some(value);
+                bounds.time_span = span(captures.get(0).unwrap().end()).ok();
+            } else if let Some(captures) = EXTRACT_MEMORY.captures(annotation) {
+                let value: u64 = captures["value"].parse()?;
+                let value = match &captures["unit"] {
+                    "KiB" => value,
+                    "MiB" => value * 1024,
+                    _ => value * 1024 * 1024,
+                };
+                bounds.max_memory = Some(value);
+                bounds.memory_span = span(captures.get(0).unwrap().end()).ok();
+            } else {
+                let mut diagnostic = Diagnostic::error(format!(
+                    "In '{}' the resource bound '{}' is not valid",
+                    rel_path.display(),
+                    annotation
+                ))
+                .with_help("Use '@max-time: 800ms' or '@max-memory: 200MiB'");
+                if let Ok(span) = span(annotation.len()) {
+                    diagnostic = diagnostic.with_code_span(span);
+                }
+                let _ = eval.add_diagnostic(diagnostic);
+            }
+        }
+        Ok(bounds)
+    }
+}
+
+/// An additional testcase to evaluate a solution on, as specified with the
+/// `@diagnostic-case: <subtask> <arguments>` annotation inside its source file.
+///
+/// The input file is generated passing the arguments to the generator of the subtask, and it's
+/// validated with the validator of the subtask. The diagnostic cases are not part of the testset of
+/// the task and do not affect the score of the solution, their outcomes are reported separately.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SolutionDiagnosticCase {
+    /// The name of the subtask the input file is generated for.
+    pub subtask_name: String,
+    /// The arguments to pass to the generator.
+    pub args: Vec<String>,
+    /// Span of the annotation.
+    pub code_span: CodeSpan,
+}
+
+impl SolutionDiagnosticCase {
+    /// Try to extract the list of [`SolutionDiagnosticCase`] from a file.
+    pub fn extract_list<P: AsRef<Path>>(
+        path: P,
+        eval: &mut EvaluationData,
+    ) -> Result<Vec<Self>, Error> {
+        lazy_static! {
+            static ref FIND_CASES: Regex =
+                Regex::new(r"@diagnostic-case\b[^\n]*").expect("Invalid regex");
+            static ref EXTRACT_CASE: Regex =
+                Regex::new(r"^@diagnostic-case:\s*(?P<subtask>\S+)(?P<args>.*)$")
+                    .expect("Invalid regex");
+        }
+
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let rel_path = path.strip_prefix(&eval.task_root).unwrap_or(path);
+
+        let mut cases = vec![];
+        for found in FIND_CASES.find_iter(&content) {
+            let annotation = found.as_str().trim_end();
+            let span = CodeSpan::from_str(rel_path, &content, found.start(), annotation.len());
+            if let Some(captures) = EXTRACT_CASE.captures(annotation) {
+                cases.push(Self {
+                    subtask_name: captures["subtask"].to_string(),
+                    args: split_patterns(&captures["args"])
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    code_span: span.context("Failed to build CodeSpan for diagnostic case")?,
+                });
+            } else {
+                let mut diagnostic = Diagnostic::error(format!(
+                    "In '{}' the diagnostic case '{}' is not valid",
+                    rel_path.display(),
+                    annotation
+                ))
+                .with_help("Use '@diagnostic-case: <subtask name> <generator arguments>'");
+                if let Ok(span) = span {
+                    diagnostic = diagnostic.with_code_span(span);
+                }
+                let _ = eval.add_diagnostic(diagnostic);
+            }
+        }
+        Ok(cases)
+    }
+}
+
+/// The score a solution is expected to get in a subtask.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SubtaskScoreExpectation {
+    /// The solution should get the full score of the subtask.
+    Pass,
+    /// The solution should not get the full score of the subtask.
+    Fail,
+    /// The score of the solution should be between `min` and `max` (inclusive), in points.
+    Range {
+        /// The minimum expected score.
+        min: f64,
+        /// The maximum expected score.
+        max: f64,
+    },
+}
+
+impl SubtaskScoreExpectation {
+    /// Check if the score of a subtask with the given maximum score satisfies this expectation.
+    pub fn check(&self, score: f64, max_score: f64) -> bool {
+        const EPSILON: f64 = 1e-6;
+        match self {
+            Self::Pass => score >= max_score - EPSILON,
+            Self::Fail => score < max_score - EPSILON,
+            Self::Range { min, max } => score >= min - EPSILON && score <= max + EPSILON,
+        }
+    }
+
+    /// The strictest expectation satisfied by a solution that got `score` points in a subtask with
+    /// the given maximum score.
+    pub fn from_score(score: f64, max_score: f64) -> Self {
+        if Self::Pass.check(score, max_score) {
+            Self::Pass
+        } else if score <= 0.0 {
+            Self::Fail
+        } else {
+            Self::Range {
+                min: score,
+                max: score,
+            }
+        }
+    }
+
+    /// The annotation that sets this expectation for the subtasks in `subtasks`, like
+    /// `@check-score: 10-20 st1 st2`.
+    pub fn annotation(&self, subtasks: &str) -> String {
+        match self {
+            Self::Pass => format!("@check-pass: {subtasks}"),
+            Self::Fail => format!("@check-fail: {subtasks}"),
+            Self::Range { min, max } => format!("@check-score: {min}-{max} {subtasks}"),
+        }
+    }
+
+    /// The range of the scores that satisfy this expectation, for a subtask with the given maximum
+    /// score.
+    pub fn score_range(&self, max_score: f64) -> (f64, f64) {
+        match self {
+            Self::Pass => (max_score, max_score),
+            Self::Fail => (0.0, max_score),
+            Self::Range { min, max } => (min.max(0.0), max.min(max_score)),
+        }
+    }
+}
+
+impl std::fmt::Display for SubtaskScoreExpectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pass => write!(f, "the full score"),
+            Self::Fail => write!(f, "less than the full score"),
+            Self::Range { min, max } => write!(f, "a score between {min} and {max}"),
+        }
+    }
+}
+
+/// A check on the score of a solution in a set of subtasks, as specified with the
+/// `@check-pass: <subtasks>`, `@check-fail: <subtasks>` and `@check-score: <min>-<max> <subtasks>`
+/// annotations inside its source file.
+///
+/// Unlike [`SolutionCheck`], these checks look at the score of the subtask instead of the outcomes
+/// of its testcases, so they are suited for the solutions that are expected to get partial scores.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SolutionScoreCheck {
+    /// The expected score of the solution.
+    pub expectation: SubtaskScoreExpectation,
+    /// The pattern that should match the name of the subtask to check.
+    pub subtask_name_pattern: String,
+    /// Span of this check.
+    pub code_span: CodeSpan,
+}
+
+impl SolutionScoreCheck {
+    /// Try to extract the list of [`SolutionScoreCheck`] from a file.
+    pub fn extract_list<P: AsRef<Path>>(
+        path: P,
+        eval: &mut EvaluationData,
+    ) -> Result<Vec<Self>, Error> {
+        lazy_static! {
+            static ref EXTRACT_PASS_FAIL: Regex =
+                Regex::new(r"^@check-(?P<kind>pass|fail):(?P<subtasks>.*)$")
+                    .expect("Invalid regex");
+            static ref EXTRACT_RANGE: Regex = Regex::new(
+                r"^@check-score:\s*(?P<min>\d+(?:\.\d+)?)-(?P<max>\d+(?:\.\d+)?)(?P<subtasks>(?:\s+\S+)*)$"
+            )
+            .expect("Invalid regex");
+        }
+
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let rel_path = path.strip_prefix(&eval.task_root).unwrap_or(path);
+
+        let mut checks = vec![];
+        for found in FIND_SCORE_CHECKS.find_iter(&content) {
+            let annotation = found.as_str().trim_end();
+            let span = CodeSpan::from_str(rel_path, &content, found.start(), annotation.len());
+            let parsed = if let Some(captures) = EXTRACT_PASS_FAIL.captures(annotation) {
+                let expectation = match &captures["kind"] {
+                    "pass" => SubtaskScoreExpectation::Pass,
+                    _ => SubtaskScoreExpectation::Fail,
+                };
+                Some((expectation, captures.name("subtasks").unwrap().as_str()))
+            } else if let Some(captures) = EXTRACT_RANGE.captures(annotation) {
+                let min: f64 = captures["min"].parse()?;
+                let max: f64 = captures["max"].parse()?;
+                if min <= max {
+                    let expectation = SubtaskScoreExpectation::Range { min, max };
+                    Some((expectation, captures.name("subtasks").unwrap().as_str()))
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+            if let Some((expectation, patterns)) = parsed {
+                let code_span = span.context("Failed to build CodeSpan for score check")?;
+                for pattern in split_patterns(patterns) {
+                    checks.push(Self {
+                        expectation,
+                        subtask_name_pattern: pattern.into(),
+                        code_span: code_span.clone(),
+                    });
+                }
+            } else {
+                let mut diagnostic = Diagnostic::error(format!(
+                    "In '{}' the score check '{}' is not valid",
+                    rel_path.display(),
+                    annotation
+                ))
+                .with_help(
+                    "Use '@check-pass: <subtasks>', '@check-fail: <subtasks>' or \
+                    '@check-score: <min>-<max> <subtasks>'",
+                );
+                if let Ok(span) = span {
+                    diagnostic = diagnostic.with_code_span(span);
+                }
+                let _ = eval.add_diagnostic(diagnostic);
+            }
+        }
+        Ok(checks)
+    }
+}
+
 /// Result of the evaluation of a solution on a testcase.
 ///
 /// We define a partial order used to determine the correctness of solution checks.
@@ -322,6 +776,10 @@ impl SolutionCheck {
                 None => continue,
                 Some(found) => found,
             };
+            // The checks on the score are handled by `SolutionScoreCheck`.
+            if FIND_SCORE_CHECKS.is_match(found.as_str()) {
+                continue;
+            }
             let captures = EXTRACT_CHECKS.captures_iter(line).next();
             let path = path.strip_prefix(&eval.task_root).unwrap_or(path);
             // file_offset includes the current line length.
@@ -355,6 +813,12 @@ impl SolutionCheck {
     }
 }
 
+lazy_static! {
+    /// Find the `@check-pass`, `@check-fail` and `@check-score` annotations.
+    static ref FIND_SCORE_CHECKS: Regex =
+        Regex::new(r"@check-(?:pass|fail|score)\b[^\n]*").expect("Invalid regex");
+}
+
 /// Split the patterns by whitespace.
 fn split_patterns(patterns: &str) -> Vec<&str> {
     let mut result = vec![];
@@ -368,9 +832,15 @@ fn split_patterns(patterns: &str) -> Vec<&str> {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use anyhow::Error;
 
-    use crate::solution::{SolutionCheck, SolutionCheckResult};
+    use crate::solution::{
+        Solution, SolutionCheck, SolutionCheckResult, SolutionDiagnosticCase, SolutionIgnore,
+        SolutionResourceBounds, SolutionScoreCheck, SubtaskScoreExpectation,
+    };
+    use crate::ui::UIMessage;
     use crate::EvaluationData;
 
     fn get_checks(source: &str) -> Result<Vec<SolutionCheck>, Error> {
@@ -473,4 +943,174 @@ mod tests {
         assert_eq!(checks[1].subtask_name_pattern, "st2");
         assert_eq!(checks[1].code_span.as_str(), "@check-accepted: \tst1 \t\u{000B}\u{000C}\u{00A0}\u{1680}\u{2000}\u{2001}\u{2002}\u{2003}\u{2004}\u{2005}\u{2006}\u{200A} st2\t  \t   ");
     }
+
+    fn get_bounds(source: &str) -> SolutionResourceBounds {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("source.txt");
+        std::fs::write(&path, source).unwrap();
+        let mut eval = EvaluationData::new(tmpdir.path()).0;
+        SolutionResourceBounds::extract(path, &mut eval).unwrap()
+    }
+
+    #[test]
+    fn test_extract_resource_bounds() {
+        let bounds = get_bounds("// @max-time: 800ms @max-memory: 200MiB\n");
+        assert_eq!(bounds.max_time, Some(0.8));
+        assert_eq!(bounds.max_memory, Some(200 * 1024));
+        assert_eq!(bounds.time_span.unwrap().as_str(), "@max-time: 800ms");
+        assert_eq!(bounds.memory_span.unwrap().as_str(), "@max-memory: 200MiB");
+    }
+
+    #[test]
+    fn test_extract_resource_bounds_separate_lines() {
+        let bounds = get_bounds("/*\n * @max-time: 1.5s\n * @max-memory: 1GiB\n */\n");
+        assert_eq!(bounds.max_time, Some(1.5));
+        assert_eq!(bounds.max_memory, Some(1024 * 1024));
+    }
+
+    #[test]
+    fn test_extract_resource_bounds_invalid() {
+        let bounds = get_bounds("// @max-time: lots @max-memory: 12 bytes\n");
+        assert!(bounds.is_empty());
+    }
+
+    #[test]
+    fn test_extract_resource_bounds_missing() {
+        let bounds = get_bounds("// @check-accepted: st1\n");
+        assert!(bounds.is_empty());
+    }
+
+    fn get_diagnostic_cases(source: &str) -> Vec<SolutionDiagnosticCase> {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("source.txt");
+        std::fs::write(&path, source).unwrap();
+        let mut eval = EvaluationData::new(tmpdir.path()).0;
+        SolutionDiagnosticCase::extract_list(path, &mut eval).unwrap()
+    }
+
+    #[test]
+    fn test_extract_diagnostic_cases() {
+        let cases = get_diagnostic_cases(
+            "/*\n * @diagnostic-case: st2 1000 42 worst\n * @diagnostic-case: st1\n */\n",
+        );
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].subtask_name, "st2");
+        assert_eq!(cases[0].args, vec!["1000", "42", "worst"]);
+        assert_eq!(
+            cases[0].code_span.as_str(),
+            "@diagnostic-case: st2 1000 42 worst"
+        );
+        assert_eq!(cases[1].subtask_name, "st1");
+        assert!(cases[1].args.is_empty());
+    }
+
+    #[test]
+    fn test_extract_diagnostic_cases_invalid() {
+        let cases = get_diagnostic_cases("// @diagnostic-case:\n// @diagnostic-cases: st1 1\n");
+        assert!(cases.is_empty());
+    }
+
+    fn get_score_checks(source: &str) -> Vec<SolutionScoreCheck> {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("source.txt");
+        std::fs::write(&path, source).unwrap();
+        let mut eval = EvaluationData::new(tmpdir.path()).0;
+        SolutionScoreCheck::extract_list(path, &mut eval).unwrap()
+    }
+
+    #[test]
+    fn test_extract_score_checks() {
+        let checks = get_score_checks(
+            "/*\n * @check-pass: st1 st2\n * @check-fail: st3\n * @check-score: 20-42.5 st4\n */\n",
+        );
+        assert_eq!(checks.len(), 4);
+        assert_eq!(checks[0].expectation, SubtaskScoreExpectation::Pass);
+        assert_eq!(checks[0].subtask_name_pattern, "st1");
+        assert_eq!(checks[0].code_span.as_str(), "@check-pass: st1 st2");
+        assert_eq!(checks[1].subtask_name_pattern, "st2");
+        assert_eq!(checks[2].expectation, SubtaskScoreExpectation::Fail);
+        assert_eq!(checks[2].subtask_name_pattern, "st3");
+        assert_eq!(
+            checks[3].expectation,
+            SubtaskScoreExpectation::Range {
+                min: 20.0,
+                max: 42.5
+            }
+        );
+        assert_eq!(checks[3].subtask_name_pattern, "st4");
+    }
+
+    #[test]
+    fn test_extract_score_checks_invalid() {
+        let checks = get_score_checks(
+            "// @check-score: 42 st1\n// @check-score: 50-20 st1\n// @check-score:\n",
+        );
+        assert!(checks.is_empty());
+    }
+
+    #[test]
+    fn test_score_checks_ignored_by_check_list() {
+        let checks =
+            get_checks("// @check-pass: st1\n// @check-score: 1-2 st2\n// @check-zero: st3\n")
+                .unwrap();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].result, SolutionCheckResult::Zero);
+    }
+
+    #[test]
+    fn test_subtask_score_expectation_check() {
+        assert!(SubtaskScoreExpectation::Pass.check(10.0, 10.0));
+        assert!(!SubtaskScoreExpectation::Pass.check(9.5, 10.0));
+        assert!(SubtaskScoreExpectation::Fail.check(9.5, 10.0));
+        assert!(!SubtaskScoreExpectation::Fail.check(10.0, 10.0));
+        let range = SubtaskScoreExpectation::Range { min: 3.0, max: 5.0 };
+        assert!(range.check(3.0, 10.0));
+        assert!(range.check(5.0, 10.0));
+        assert!(!range.check(2.9, 10.0));
+        assert!(!range.check(5.1, 10.0));
+    }
+
+    #[test]
+    fn test_solution_source_warnings() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("sol.py");
+        std::fs::write(&path, "#!/usr/bin/python2\nprint 42\n").unwrap();
+        let (mut eval, recv) = EvaluationData::new(tmpdir.path());
+        assert!(Solution::new(&path, tmpdir.path(), None, &mut eval).is_some());
+        drop(eval);
+
+        let diagnostic = recv
+            .into_iter()
+            .find_map(|m| match m {
+                UIMessage::Diagnostic { diagnostic } => Some(diagnostic),
+                _ => None,
+            })
+            .unwrap();
+        assert!(diagnostic.message().contains("sol.py"));
+        assert!(diagnostic.to_string().contains("#!/usr/bin/python2"));
+    }
+
+    #[test]
+    fn test_solution_ignore() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let (eval, recv) = EvaluationData::new(tmpdir.path());
+        let mut ignore = SolutionIgnore::default();
+        ignore.add_patterns(
+            "# notes\n\n*.txt\nsol/draft*\n!sol/draft_ok.cpp\ndrafts/\nsol/[\n",
+            Path::new("sol/.tmignore"),
+            &eval,
+        );
+        assert!(ignore.is_ignored(Path::new("sol/notes.txt")));
+        assert!(ignore.is_ignored(Path::new("sol/draft.cpp")));
+        assert!(!ignore.is_ignored(Path::new("sol/draft_ok.cpp")));
+        assert!(ignore.is_ignored(Path::new("sol/drafts/sol.cpp")));
+        assert!(!ignore.is_ignored(Path::new("sol/solution.cpp")));
+        assert!(!ignore.is_ignored(Path::new("sol/other/draft.cpp")));
+        drop(eval);
+        let warnings = recv
+            .try_iter()
+            .filter(|m| matches!(m, UIMessage::Diagnostic { .. }))
+            .count();
+        assert_eq!(warnings, 1);
+    }
 }
@@ -101,9 +101,28 @@ impl SourceFile {
             eval.dag.on_execution_done(&comp_uuid, move |results| {
                 let result = &results[0];
                 if !result.status.is_success() {
-                    let mut diagnostic =
-                        Diagnostic::error(format!("Failed to compile {}", path.display()))
-                            .with_note(description);
+                    let mut diagnostic = match result.status {
+                        ExecutionStatus::TimeLimitExceeded
+                        | ExecutionStatus::SysTimeLimitExceeded
+                        | ExecutionStatus::WallTimeLimitExceeded => Diagnostic::error(format!(
+                            "Compilation of {} timed out",
+                            path.display()
+                        ))
+                        .with_note(description)
+                        .with_help(
+                            "Raise the limit with compilation_time_limit in the task config",
+                        ),
+                        ExecutionStatus::MemoryLimitExceeded => Diagnostic::error(format!(
+                            "Compilation of {} exceeded the memory limit",
+                            path.display()
+                        ))
+                        .with_note(description)
+                        .with_help(
+                            "Raise the limit with compilation_memory_limit in the task config",
+                        ),
+                        _ => Diagnostic::error(format!("Failed to compile {}", path.display()))
+                            .with_note(description),
+                    };
                     if let Some(stderr) = result.stderr.as_ref() {
                         diagnostic = diagnostic.with_help_attachment(stderr.clone());
                     }
@@ -1,10 +1,11 @@
 use std::path::Path;
 
-use anyhow::Error;
+use anyhow::{bail, Error};
 use serde::{Deserialize, Serialize};
 use task_maker_dag::ExecutionDAGConfig;
 
-use crate::{ui, EvaluationConfig, EvaluationData, IOITask, TaskInfo, TerryTask, UI};
+use crate::ioi::{JUnitCriterion, JUnitReport, SummaryReport};
+use crate::{ui, CustomTask, EvaluationConfig, EvaluationData, IOITask, TaskInfo, TerryTask, UI};
 
 /// The format of the task.
 /// A task format, providing a UI and the parsing and execution abilities.
@@ -15,6 +16,10 @@ pub enum TaskFormat {
     IOI(IOITask),
     /// The task is Terry-like.
     Terry(TerryTask),
+    /// The task has a format defined outside of this crate, see
+    /// [`register_task_format`](crate::register_task_format). It cannot be serialized.
+    #[serde(skip)]
+    Custom(Box<dyn CustomTask>),
 }
 
 impl TaskFormat {
@@ -23,6 +28,7 @@ impl TaskFormat {
         match self {
             TaskFormat::IOI(task) => task.path(),
             TaskFormat::Terry(task) => task.path(),
+            TaskFormat::Custom(task) => task.path(),
         }
     }
 
@@ -35,6 +41,34 @@ impl TaskFormat {
         match self {
             TaskFormat::IOI(task) => task.ui(ui_type, config),
             TaskFormat::Terry(task) => task.ui(ui_type, config),
+            TaskFormat::Custom(task) => task.ui(ui_type, config),
+        }
+    }
+
+    /// Get a `UI` that writes the results of the solutions to `path` as a JUnit XML report at the
+    /// end of the evaluation.
+    pub fn junit_report(
+        &self,
+        path: &Path,
+        criterion: JUnitCriterion,
+        config: ExecutionDAGConfig,
+    ) -> Result<Box<dyn UI>, Error> {
+        match self {
+            TaskFormat::IOI(task) => Ok(Box::new(JUnitReport::new(task, config, path, criterion))),
+            _ => bail!("The JUnit report is supported only by IOI tasks"),
+        }
+    }
+
+    /// Get a `UI` that writes the summary of the results of the evaluation to `path` as JSON at
+    /// the end of the evaluation.
+    pub fn summary_report(
+        &self,
+        path: &Path,
+        config: ExecutionDAGConfig,
+    ) -> Result<Box<dyn UI>, Error> {
+        match self {
+            TaskFormat::IOI(task) => Ok(Box::new(SummaryReport::new(task, config, path))),
+            _ => bail!("The result summary is supported only by IOI tasks"),
         }
     }
 
@@ -45,9 +79,11 @@ impl TaskFormat {
         config: &EvaluationConfig,
     ) -> Result<(), Error> {
         match self {
-            TaskFormat::IOI(task) => task.build_dag(eval, config),
-            TaskFormat::Terry(task) => task.build_dag(eval, config),
+            TaskFormat::IOI(task) => task.build_dag(eval, config)?,
+            TaskFormat::Terry(task) => task.build_dag(eval, config)?,
+            TaskFormat::Custom(task) => task.build_dag(eval, config)?,
         }
+        eval.check_executions_limit(config)
     }
 
     /// Hook called after the execution completed, useful for sending messages to the UI about the
@@ -56,6 +92,21 @@ impl TaskFormat {
         match self {
             TaskFormat::IOI(task) => task.sanity_check_post_hook(eval),
             TaskFormat::Terry(task) => task.sanity_check_post_hook(eval),
+            TaskFormat::Custom(task) => task.sanity_check_post_hook(eval),
+        }
+    }
+
+    /// Run only the sanity checks that do not require the evaluation of the task, without building
+    /// the DAG. Returns the names of the sanity checks that have been skipped.
+    pub fn static_sanity_checks(
+        &self,
+        eval: &mut EvaluationData,
+        config: &EvaluationConfig,
+    ) -> Result<Vec<&'static str>, Error> {
+        match self {
+            TaskFormat::IOI(task) => task.static_sanity_checks(eval, config),
+            TaskFormat::Terry(task) => task.static_sanity_checks(eval, config),
+            TaskFormat::Custom(task) => task.static_sanity_checks(eval, config),
         }
     }
 
@@ -64,6 +115,7 @@ impl TaskFormat {
         match self {
             TaskFormat::IOI(task) => task.clean(),
             TaskFormat::Terry(task) => task.clean(),
+            TaskFormat::Custom(task) => task.clean(),
         }
     }
 
@@ -72,6 +124,7 @@ impl TaskFormat {
         match self {
             TaskFormat::IOI(task) => task.task_info(),
             TaskFormat::Terry(task) => task.task_info(),
+            TaskFormat::Custom(task) => task.task_info(),
         }
     }
 }
@@ -2,11 +2,14 @@ use std::path::Path;
 
 use itertools::Itertools;
 use task_maker_dag::{ExecutionResult, ExecutionStatus};
-use termcolor::{ColorChoice, StandardStream};
+use termcolor::StandardStream;
 
 use crate::terry::ui_state::{SolutionState, SolutionStatus, UIState};
 use crate::terry::CaseStatus;
-use crate::ui::{FinishUI as FinishUITrait, FinishUIUtils, BLUE, BOLD, GREEN, RED, YELLOW};
+use crate::ui::{
+    color_choice, decode_output, FinishUI as FinishUITrait, FinishUIUtils, BLUE, BOLD, GREEN, RED,
+    YELLOW,
+};
 use crate::{cwrite, cwriteln};
 
 /// UI that prints to `stdout` the ending result of the evaluation of a IOI task.
@@ -18,7 +21,7 @@ pub struct FinishUI {
 impl FinishUITrait<UIState> for FinishUI {
     fn print(state: &UIState) {
         let mut ui = FinishUI {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
         };
 
         ui.print_task_info(state);
@@ -28,6 +31,7 @@ impl FinishUITrait<UIState> for FinishUI {
         ui.print_evaluations(state);
         ui.print_summary(state);
         println!();
+        FinishUIUtils::new(&mut ui.stream).print_cache_stats(state.cache_stats.as_ref());
         FinishUIUtils::new(&mut ui.stream).print_diagnostic_messages(&state.diagnostics);
     }
 }
@@ -82,8 +86,8 @@ impl FinishUI {
                             FinishUIUtils::display_fail_execution_status(&result.status)
                         );
                     }
-                    if result.was_cached {
-                        print!(" (cached)");
+                    if let Some(reason) = result.cache_hit {
+                        print!(" (cached: {reason})");
                     }
                     if result.was_killed {
                         print!(" (killed)");
@@ -124,7 +128,7 @@ impl FinishUI {
     fn print_stderr(&mut self, result: &Option<ExecutionResult>) {
         if let Some(res) = result {
             if let Some(content) = &res.stderr {
-                let content = String::from_utf8_lossy(content);
+                let content = decode_output(content);
                 let content = content.trim();
                 if !content.is_empty() {
                     cwriteln!(self, YELLOW, "Stderr:");
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Error;
+use anyhow::{bail, Error};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 pub use task_info::*;
@@ -172,6 +172,7 @@ impl TerryTask {
             UIType::Silent => Ok(Box::new(SilentUI::new())),
             UIType::Print => Ok(Box::new(PrintUI::new(UIState::new(self)))),
             UIType::Curses => Ok(Box::new(CursesUI::new(UIState::new(self))?)),
+            UIType::Tap => bail!("The TAP UI is supported only by IOI tasks"),
         }
     }
 
@@ -247,6 +248,17 @@ impl TerryTask {
         self.sanity_checks.post_hook(self, eval)
     }
 
+    /// Run only the sanity checks that do not require the evaluation of the task, returning the
+    /// names of the skipped ones.
+    pub fn static_sanity_checks(
+        &self,
+        eval: &mut EvaluationData,
+        config: &EvaluationConfig,
+    ) -> Result<Vec<&'static str>, Error> {
+        eval.solutions = config.find_solutions(&self.path, vec!["solutions/*"], None, eval);
+        self.sanity_checks.static_checks(self, eval)
+    }
+
     /// Clean the task folder removing the files that can be generated automatically.
     pub fn clean(&self) -> Result<(), Error> {
         let all_managers: HashSet<PathBuf> = list_files(&self.path, vec!["managers/*.*"])
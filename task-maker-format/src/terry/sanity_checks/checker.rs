@@ -26,6 +26,10 @@ impl SanityCheck for FuzzChecker {
         SanityCheckCategory::Checker
     }
 
+    fn requires_evaluation(&self) -> bool {
+        true
+    }
+
     fn pre_hook(&self, task: &TerryTask, eval: &mut EvaluationData) -> Result<(), Error> {
         let outputs_dir = DATA_DIR.join("bad_outputs");
         if !outputs_dir.exists() {
@@ -4,7 +4,7 @@ use std::time::SystemTime;
 
 use task_maker_dag::{ExecutionResult, ExecutionStatus};
 use task_maker_diagnostics::DiagnosticContext;
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{CacheStats, ExecutorStatus};
 
 use crate::terry::{finish_ui, Seed, SolutionOutcome, TerryTask};
 use crate::ui::{CompilationStatus, FinishUI, UIExecutionStatus, UIMessage, UIStateT};
@@ -20,6 +20,8 @@ pub struct UIState {
     pub solutions: HashMap<PathBuf, SolutionState>,
     /// The status of the executor.
     pub executor_status: Option<ExecutorStatus<SystemTime>>,
+    /// The hits and misses of the cache lookups, known at the end of the evaluation.
+    pub cache_stats: Option<CacheStats>,
     /// Diagnostics context.
     pub diagnostics: DiagnosticContext,
 }
@@ -93,6 +95,7 @@ impl UIState {
             compilations: HashMap::new(),
             solutions: HashMap::new(),
             executor_status: None,
+            cache_stats: None,
             diagnostics: Default::default(),
         }
     }
@@ -205,6 +208,7 @@ impl UIStateT for UIState {
             UIMessage::Diagnostic { diagnostic } => {
                 self.diagnostics.add_diagnostic(diagnostic);
             }
+            UIMessage::CacheStats { stats } => self.cache_stats = Some(stats),
             UIMessage::IOITask { .. }
             | UIMessage::IOIGeneration { .. }
             | UIMessage::IOIValidation { .. }
@@ -214,6 +218,7 @@ impl UIStateT for UIState {
             | UIMessage::IOITestcaseScore { .. }
             | UIMessage::IOISubtaskScore { .. }
             | UIMessage::IOITaskScore { .. }
+            | UIMessage::IOIDiagnosticCase { .. }
             | UIMessage::IOIBooklet { .. }
             | UIMessage::IOIBookletDependency { .. } => unreachable!("IOI message on Terry UI"),
         }
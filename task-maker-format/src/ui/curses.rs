@@ -36,7 +36,11 @@ macro_rules! define_color_inner {
         $color
     };
     ($color:expr, basic($basic:ident), $($tt:tt)*) => {
-        define_color_inner!($color.fg(Color::$basic), $($tt)*)
+        define_color_inner!(if $crate::ui::color_mode() == $crate::ui::ColorMode::Never {
+            $color
+        } else {
+            $color.fg(Color::$basic)
+        }, $($tt)*)
     };
     ($color:expr, rgb($r:expr, $g:expr, $b:expr), $($tt:tt)*) => {
         define_color_inner!(if *$crate::ui::HAS_TRUECOLOR {
@@ -257,7 +261,10 @@ pub(crate) fn compilation_status_text(status: &CompilationStatus, loading: char)
         CompilationStatus::Pending => Span::raw("... "),
         CompilationStatus::Running => Span::raw(format!("{loading}   ")),
         CompilationStatus::Done { .. } => Span::styled("OK  ", *GREEN),
-        CompilationStatus::Failed { .. } => Span::styled("FAIL", *RED),
+        CompilationStatus::Failed { .. } => Span::styled(
+            format!("{:<4}", status.failure_reason().unwrap_or("FAIL")),
+            *RED,
+        ),
         CompilationStatus::Skipped => Span::styled("skip", *YELLOW),
     }
 }
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use task_maker_diagnostics::DiagnosticContext;
+
+use crate::ui::*;
+
+/// A `UI` that collects the diagnostics emitted during the evaluation and, at the end, writes them
+/// to a file as JSON, with the positions of their spans of code. This is meant to be consumed by
+/// the editors to show the diagnostics inline.
+pub struct DiagnosticsReport {
+    /// The diagnostics emitted so far.
+    diagnostics: DiagnosticContext,
+    /// Where to write the diagnostics.
+    path: PathBuf,
+}
+
+impl DiagnosticsReport {
+    /// Make a new `DiagnosticsReport` that will be written to `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> DiagnosticsReport {
+        DiagnosticsReport {
+            diagnostics: DiagnosticContext::new(),
+            path: path.into(),
+        }
+    }
+
+    /// Write the diagnostics to the file.
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(&self.diagnostics.to_json())?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+impl UI for DiagnosticsReport {
+    fn on_message(&mut self, message: UIMessage) {
+        if let UIMessage::Diagnostic { diagnostic } = message {
+            self.diagnostics.add_diagnostic(diagnostic);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Err(e) = self.write(&self.path) {
+            error!(
+                "Failed to write the diagnostics to {}: {e:?}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use task_maker_diagnostics::Diagnostic;
+
+    use super::*;
+
+    #[test]
+    fn test_diagnostics_report() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("diagnostics.json");
+        let mut report = DiagnosticsReport::new(&path);
+        report.on_message(UIMessage::Diagnostic {
+            diagnostic: Diagnostic::error("Something failed").with_note("A note"),
+        });
+        report.on_message(UIMessage::StopUI);
+        report.finish();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["diagnostics"][0]["level"], "Error");
+        assert_eq!(json["diagnostics"][0]["message"], "Something failed");
+        assert_eq!(json["diagnostics"][0]["note"], "A note");
+        assert!(json["diagnostics"][0]["spans"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+}
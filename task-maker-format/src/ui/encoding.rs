@@ -0,0 +1,121 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The encoding to try for the output of the compilers and of the checkers that is not valid
+/// UTF-8, before falling back to the lossy conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputEncoding {
+    /// Only UTF-8, the invalid sequences are replaced with the replacement character.
+    #[default]
+    Utf8 = 0,
+    /// ISO-8859-1, where every byte is the code point with the same value.
+    Latin1 = 1,
+}
+
+impl FromStr for OutputEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Self::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Self::Latin1),
+            _ => Err(format!("Invalid output encoding: {s}")),
+        }
+    }
+}
+
+/// The `OutputEncoding` of the process, stored as its discriminant.
+static OUTPUT_ENCODING: AtomicU8 = AtomicU8::new(OutputEncoding::Utf8 as u8);
+
+/// Set the encoding to try for the output that is not valid UTF-8.
+pub fn set_output_encoding(encoding: OutputEncoding) {
+    OUTPUT_ENCODING.store(encoding as u8, Ordering::Relaxed);
+}
+
+/// The current `OutputEncoding` of the process.
+pub fn output_encoding() -> OutputEncoding {
+    match OUTPUT_ENCODING.load(Ordering::Relaxed) {
+        1 => OutputEncoding::Latin1,
+        _ => OutputEncoding::Utf8,
+    }
+}
+
+/// Whether the content looks like binary data rather than text: it contains a NUL byte or too many
+/// control characters.
+pub fn is_binary(content: &[u8]) -> bool {
+    if content.contains(&0) {
+        return true;
+    }
+    let control = content
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > content.len()
+}
+
+/// Decode the content without losing information: it's either valid UTF-8 or text in the
+/// configured `OutputEncoding`. Returns `None` for the binary content and when no encoding is
+/// configured for the content that is not valid UTF-8.
+pub fn decode_exact(content: &[u8]) -> Option<String> {
+    decode_with(content, output_encoding())
+}
+
+/// Decode the content like [`decode_exact`], with the specified encoding.
+fn decode_with(content: &[u8], encoding: OutputEncoding) -> Option<String> {
+    if is_binary(content) {
+        return None;
+    }
+    if let Ok(content) = std::str::from_utf8(content) {
+        return Some(content.to_string());
+    }
+    match encoding {
+        OutputEncoding::Utf8 => None,
+        OutputEncoding::Latin1 => Some(content.iter().map(|&b| b as char).collect()),
+    }
+}
+
+/// Decode the output of a process to show it in the UI. The configured `OutputEncoding` is tried
+/// before the lossy UTF-8 conversion, while the binary content is replaced by a note.
+pub fn decode_output(content: &[u8]) -> String {
+    if is_binary(content) {
+        return format!("<binary output omitted, {} bytes>", content.len());
+    }
+    decode_exact(content).unwrap_or_else(|| String::from_utf8_lossy(content).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b""));
+        assert!(!is_binary(b"error: expected ';'\n\tfoo();\n"));
+        assert!(is_binary(b"ELF\0\0\0"));
+        assert!(is_binary(b"\x01\x02\x03\x04abc"));
+    }
+
+    #[test]
+    fn test_decode_output() {
+        assert_eq!(decode_output("caffè".as_bytes()), "caffè");
+        assert_eq!(decode_output(b"caff\xe8"), "caff\u{FFFD}");
+        assert_eq!(decode_output(b"a\0b"), "<binary output omitted, 3 bytes>");
+        assert_eq!(decode_exact(b"caff\xe8"), None);
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        let decoded = decode_with(b"caff\xe8", OutputEncoding::Latin1);
+        assert_eq!(decoded.as_deref(), Some("caffè"));
+        let decoded = decode_with("caffè".as_bytes(), OutputEncoding::Latin1);
+        assert_eq!(decoded.as_deref(), Some("caffè"));
+        assert_eq!(decode_with(b"\xe8\0", OutputEncoding::Latin1), None);
+    }
+
+    #[test]
+    fn test_parse_output_encoding() {
+        assert_eq!("UTF-8".parse(), Ok(OutputEncoding::Utf8));
+        assert_eq!("latin1".parse(), Ok(OutputEncoding::Latin1));
+        assert!("ebcdic".parse::<OutputEncoding>().is_err());
+    }
+}
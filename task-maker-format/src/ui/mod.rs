@@ -2,29 +2,41 @@
 
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 
 use anyhow::Error;
 pub use curses::{inner_block, render_block, render_server_status, CursesDrawer, CursesUI};
+pub use diagnostics_report::DiagnosticsReport;
+pub use encoding::{
+    decode_exact, decode_output, is_binary, output_encoding, set_output_encoding, OutputEncoding,
+};
 use itertools::Itertools;
 pub use json::JsonUI;
 pub use print::PrintUI;
 pub use raw::RawUI;
 use serde::{Deserialize, Serialize};
 pub use silent::SilentUI;
+pub use tap::TapUI;
 use task_maker_dag::{ExecutionResourcesUsage, ExecutionResult, ExecutionStatus, WorkerUuid};
 use task_maker_diagnostics::DiagnosticContext;
+use task_maker_exec::CacheStats;
 pub use termcolor::WriteColor;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream};
+pub use transform::{SolutionAnonymizer, UIMessageTransform};
 pub use ui_message::UIMessage;
 
 use crate::{cwrite, cwriteln};
 
 pub mod curses;
+mod diagnostics_report;
+mod encoding;
 mod json;
 mod print;
 mod raw;
 mod silent;
+mod tap;
+mod transform;
 mod ui_message;
 
 /// Channel type for sending `UIMessage`s.
@@ -32,10 +44,56 @@ pub type UIChannelSender = Sender<UIMessage>;
 /// Channel type for receiving `UIMessage`s.
 pub type UIChannelReceiver = Receiver<UIMessage>;
 
+/// When to use the colors in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Use the colors if the terminal supports them.
+    #[default]
+    Auto = 0,
+    /// Always use the colors, even if the output is not a terminal.
+    Always = 1,
+    /// Never use the colors.
+    Never = 2,
+}
+
+/// The `ColorMode` of the process, stored as its discriminant.
+static COLOR_MODE: AtomicU8 = AtomicU8::new(ColorMode::Auto as u8);
+
+/// Set when to use the colors in the UIs and in the diagnostics. This should be called before
+/// printing anything, since the color support of the terminal is detected only once.
+pub fn set_color_mode(mode: ColorMode) {
+    COLOR_MODE.store(mode as u8, Ordering::Relaxed);
+    task_maker_diagnostics::set_color_override(match mode {
+        ColorMode::Auto => None,
+        ColorMode::Always => Some(true),
+        ColorMode::Never => Some(false),
+    });
+}
+
+/// The current `ColorMode` of the process.
+pub fn color_mode() -> ColorMode {
+    match COLOR_MODE.load(Ordering::Relaxed) {
+        1 => ColorMode::Always,
+        2 => ColorMode::Never,
+        _ => ColorMode::Auto,
+    }
+}
+
+/// The `ColorChoice` to use for the streams that print with colors, following the `ColorMode`.
+pub fn color_choice() -> ColorChoice {
+    match color_mode() {
+        ColorMode::Auto => ColorChoice::Auto,
+        ColorMode::Always => ColorChoice::Always,
+        ColorMode::Never => ColorChoice::Never,
+    }
+}
+
 lazy_static! {
     /// Whether the terminal supports ANSI 256 colors.
     static ref HAS_ANSI256: bool = {
-        if std::env::var("TM_ANSI256").as_deref() == Ok("true") {
+        if color_mode() == ColorMode::Never {
+            false
+        } else if std::env::var("TM_ANSI256").as_deref() == Ok("true") {
             if let Some(support) = supports_color::on(supports_color::Stream::Stdout) {
                 support.has_256
             } else {
@@ -47,7 +105,9 @@ lazy_static! {
     };
     /// Whetner the terminal supports 24-bit Truecolor.
     static ref HAS_TRUECOLOR: bool = {
-        if let Some(support) = supports_color::on(supports_color::Stream::Stdout) {
+        if color_mode() == ColorMode::Never {
+            false
+        } else if let Some(support) = supports_color::on(supports_color::Stream::Stdout) {
             support.has_16m
         } else {
             false
@@ -165,14 +225,8 @@ impl CompilationStatus {
             UIExecutionStatus::Done { result } => {
                 assert_eq!(result.len(), 1);
                 let result = result.into_iter().next().unwrap();
-                let stdout = result
-                    .stdout
-                    .as_ref()
-                    .map(|s| String::from_utf8_lossy(s).into());
-                let stderr = result
-                    .stderr
-                    .as_ref()
-                    .map(|s| String::from_utf8_lossy(s).into());
+                let stdout = result.stdout.as_deref().map(decode_output);
+                let stderr = result.stderr.as_deref().map(decode_output);
                 if let ExecutionStatus::Success = result.status {
                     *self = CompilationStatus::Done {
                         result,
@@ -190,6 +244,21 @@ impl CompilationStatus {
             UIExecutionStatus::Skipped => *self = CompilationStatus::Skipped,
         }
     }
+
+    /// A short text describing why the compilation failed: whether it was killed for exceeding
+    /// the time or memory limit, or it failed due to an error.
+    pub fn failure_reason(&self) -> Option<&'static str> {
+        match self {
+            CompilationStatus::Failed { result, .. } => match result.status {
+                ExecutionStatus::TimeLimitExceeded
+                | ExecutionStatus::SysTimeLimitExceeded
+                | ExecutionStatus::WallTimeLimitExceeded => Some("TLE"),
+                ExecutionStatus::MemoryLimitExceeded => Some("MLE"),
+                _ => Some("FAIL"),
+            },
+            _ => None,
+        }
+    }
 }
 
 /// The state of a task, all the information for the UI are stored here.
@@ -247,7 +316,8 @@ impl<'a> FinishUIUtils<'a> {
                     stdout,
                     stderr,
                 } => {
-                    cwrite!(self, RED, "FAIL ");
+                    let reason = status.failure_reason().unwrap_or("FAIL");
+                    cwrite!(self, RED, "{reason:<5}");
                     FinishUIUtils::print_time_memory(&result.resources);
                     if let Some(stdout) = stdout {
                         if !stdout.trim().is_empty() {
@@ -305,6 +375,14 @@ impl<'a> FinishUIUtils<'a> {
             .unwrap_or(0)
     }
 
+    /// Print the hits and misses of the cache lookups, if known.
+    pub fn print_cache_stats(&mut self, cache_stats: Option<&CacheStats>) {
+        if let Some(stats) = cache_stats {
+            cwrite!(self, BOLD, "Cache: ");
+            println!("{stats}");
+        }
+    }
+
     /// Print the diagnostics.
     pub fn print_diagnostic_messages(&mut self, diagnostics: &DiagnosticContext) {
         let diagnostics = diagnostics.diagnostics();
@@ -356,6 +434,8 @@ pub enum UIType {
     Json,
     /// The `SilentUI`.
     Silent,
+    /// The `TapUI`.
+    Tap,
 }
 
 impl std::str::FromStr for UIType {
@@ -368,11 +448,27 @@ impl std::str::FromStr for UIType {
             "curses" => Ok(UIType::Curses),
             "json" => Ok(UIType::Json),
             "silent" => Ok(UIType::Silent),
+            "tap" => Ok(UIType::Tap),
             _ => Err(format!("Unknown ui: {s}")),
         }
     }
 }
 
+impl std::str::FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorMode, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!(
+                "Unknown color mode: {s} (valid are: auto, always, never)"
+            )),
+        }
+    }
+}
+
 /// A simple printer that outputs to stdout. This can be used with `cwrite!` and `cwriteln!`.
 #[allow(dead_code)]
 pub struct StdoutPrinter {
@@ -383,7 +479,7 @@ pub struct StdoutPrinter {
 impl Default for StdoutPrinter {
     fn default() -> Self {
         Self {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
         }
     }
 }
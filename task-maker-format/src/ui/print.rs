@@ -1,6 +1,6 @@
 use itertools::Itertools;
 use task_maker_dag::ExecutionStatus;
-use termcolor::{ColorChoice, ColorSpec, StandardStream};
+use termcolor::{ColorSpec, StandardStream};
 
 use crate::cwrite;
 use crate::terry::CaseStatus;
@@ -23,7 +23,7 @@ impl<State: UIStateT> PrintUI<State> {
     /// Make a new PrintUI.
     pub fn new(state: State) -> Self {
         PrintUI {
-            stream: StandardStream::stdout(ColorChoice::Auto),
+            stream: StandardStream::stdout(color_choice()),
             state,
         }
     }
@@ -54,6 +54,9 @@ impl<State: UIStateT> PrintUI<State> {
             UIExecutionStatus::Done { result } => {
                 for r in result {
                     self.write_execution_status(&r.status);
+                    if let Some(reason) = r.cache_hit {
+                        print!(" (cached: {reason})");
+                    }
                 }
             }
             UIExecutionStatus::Skipped => {}
@@ -106,12 +109,12 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 self.write_status_details(&status);
                 if let UIExecutionStatus::Done { result } = status {
                     if let Some(stderr) = &result[0].stderr {
-                        let stderr = String::from_utf8_lossy(stderr);
+                        let stderr = decode_output(stderr);
                         println!("\n[STDERR]  Compilation stderr of {file:?}");
                         print!("{}", stderr.trim());
                     }
                     if let Some(stdout) = &result[0].stdout {
-                        let stdout = String::from_utf8_lossy(stdout);
+                        let stdout = decode_output(stdout);
                         println!("\n[STDOUT]  Compilation stdout of {file:?}");
                         print!("{}", stdout.trim());
                     }
@@ -142,7 +145,7 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 self.write_status_details(&status);
                 if let UIExecutionStatus::Done { result } = status {
                     if let Some(stderr) = &result[0].stderr {
-                        let stderr = String::from_utf8_lossy(stderr);
+                        let stderr = decode_output(stderr);
                         println!(
                             "\n[STDERR]  Generation stderr of testcase {testcase} of subtask {subtask}"
                         );
@@ -162,7 +165,7 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 self.write_status_details(&status);
                 if let UIExecutionStatus::Done { result } = status {
                     if let Some(stderr) = &result[0].stderr {
-                        let stderr = String::from_utf8_lossy(stderr);
+                        let stderr = decode_output(stderr);
                         println!(
                             "\n[STDERR]  Validation stderr of testcase {testcase} of subtask {subtask}"
                         );
@@ -233,6 +236,19 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 print!("[TASK]    ");
                 self.write_message(format!("Solution {solution:?} scored {score} "));
             }
+            UIMessage::IOIDiagnosticCase {
+                solution,
+                index,
+                subtask,
+                args,
+                score,
+                message,
+            } => {
+                print!("[DIAGNOS] ");
+                self.write_message(format!(
+                    "Solution {solution:?} scored {score} on diagnostic case {index} of subtask {subtask} (arguments {args:?}): {message}"
+                ));
+            }
             UIMessage::IOIBooklet { name, status } => {
                 self.write_status(&status);
                 self.write_message(format!("Compilation of booklet {name}"));
@@ -257,6 +273,9 @@ impl<State: UIStateT + Send> UI for PrintUI<State> {
                 println!("[DIAGNOSTIC]");
                 println!("{diagnostic}");
             }
+            UIMessage::CacheStats { stats } => {
+                println!("[CACHE]   Cache: {stats}");
+            }
             UIMessage::TerryTask { .. } => {}
             UIMessage::TerryGeneration {
                 solution,
@@ -1,23 +1,114 @@
+use serde::Serialize;
+
 use crate::ui::*;
 
-/// This UI will print to stdout all the raw information it gets, it's very
-/// verbose and useful only for debug purpuses.
+/// The version of the protocol of the `RawUI`. It's bumped every time the lines change in an
+/// incompatible way, including the changes to the serialization of the existing `UIMessage`s.
+pub const RAW_UI_PROTOCOL_VERSION: u32 = 1;
+
+/// A line printed by the `RawUI`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RawUILine<'a> {
+    /// The first line, with the version of the protocol.
+    Header { version: u32 },
+    /// A message, with its position in the stream, starting from zero.
+    Message { seq: u64, message: &'a UIMessage },
+    /// The last line, printed when the evaluation is done, with the number of messages.
+    Finished { finished: bool, messages: u64 },
+}
+
+/// This UI will print to stdout all the raw information it gets, with a stable line protocol that
+/// is simple to consume from scripts. Each line is a JSON object:
+///
+/// - the first line is `{"version": N}`, with the version of the protocol
+///   ([`RAW_UI_PROTOCOL_VERSION`]);
+/// - then there is a line `{"seq": N, "message": {...}}` for each `UIMessage`, in the order they
+///   are received;
+/// - the last line is `{"finished": true, "messages": N}`.
 #[derive(Default)]
-pub struct RawUI;
+pub struct RawUI {
+    /// Whether the header has already been printed.
+    started: bool,
+    /// The number of messages printed so far.
+    messages: u64,
+}
 
 impl RawUI {
     /// Make a new RawUI.
     pub fn new() -> RawUI {
-        RawUI {}
+        Default::default()
+    }
+
+    /// Print a line of the protocol, preceded by the header if it's the first one.
+    fn print(&mut self, line: RawUILine) {
+        if !self.started {
+            self.started = true;
+            print_line(&RawUILine::Header {
+                version: RAW_UI_PROTOCOL_VERSION,
+            });
+        }
+        print_line(&line);
     }
 }
 
 impl UI for RawUI {
     fn on_message(&mut self, message: UIMessage) {
-        println!("{message:?}");
+        let seq = self.messages;
+        self.messages += 1;
+        self.print(RawUILine::Message {
+            seq,
+            message: &message,
+        });
     }
 
     fn finish(&mut self) {
-        println!("UI finished");
+        let messages = self.messages;
+        self.print(RawUILine::Finished {
+            finished: true,
+            messages,
+        });
+    }
+}
+
+/// Print a line of the protocol to stdout.
+fn print_line(line: &RawUILine) {
+    let line = serde_json::to_string(line).expect("Failed to serialize message");
+    println!("{line}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_ui_lines() {
+        let header = RawUILine::Header {
+            version: RAW_UI_PROTOCOL_VERSION,
+        };
+        assert_eq!(
+            serde_json::to_string(&header).unwrap(),
+            format!(r#"{{"version":{RAW_UI_PROTOCOL_VERSION}}}"#)
+        );
+        let message = UIMessage::StopUI;
+        let line = RawUILine::Message {
+            seq: 3,
+            message: &message,
+        };
+        assert_eq!(
+            serde_json::to_string(&line).unwrap(),
+            format!(
+                r#"{{"seq":3,"message":{}}}"#,
+                serde_json::to_string(&message).unwrap()
+            )
+        );
+        let finished = RawUILine::Finished {
+            finished: true,
+            messages: 4,
+        };
+        assert_eq!(
+            serde_json::to_string(&finished).unwrap(),
+            r#"{"finished":true,"messages":4}"#
+        );
     }
 }
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+
+use crate::ioi::{IOITask, SubtaskId};
+use crate::solution::SolutionInfo;
+use crate::ui::*;
+
+/// This UI prints the results of the evaluation of an IOI task using the Test Anything Protocol
+/// (TAP). Nothing is printed during the evaluation; at the end there is a test line for each
+/// solution and subtask, `ok` if the subtask got its maximum score and `not ok` otherwise, followed
+/// by a diagnostic line with the score. The last line is the plan, `1..N`.
+#[derive(Default)]
+pub struct TapUI {
+    /// The task being evaluated.
+    task: Option<Box<IOITask>>,
+    /// The solutions being evaluated.
+    solutions: Vec<SolutionInfo>,
+    /// The scores of the subtasks of each solution.
+    scores: HashMap<(PathBuf, SubtaskId), f64>,
+}
+
+impl TapUI {
+    /// Make a new `TapUI`.
+    pub fn new() -> TapUI {
+        Default::default()
+    }
+
+    /// The lines of the TAP output.
+    fn lines(&self) -> Vec<String> {
+        let Some(task) = &self.task else {
+            return vec!["1..0 # SKIP no task was evaluated".into()];
+        };
+        let precision = task.score_precision;
+        let mut lines = vec![];
+        let mut count = 0;
+        for solution in self.solutions.iter().sorted_by(|a, b| a.path.cmp(&b.path)) {
+            for subtask in task.subtasks.values().sorted_by_key(|subtask| subtask.id) {
+                count += 1;
+                let score = self.scores.get(&(solution.path.clone(), subtask.id));
+                let ok = match score {
+                    Some(&score) => abs_diff_eq!(score, subtask.max_score, epsilon = 1e-6),
+                    None => false,
+                };
+                let mut description = format!("{} subtask {}", solution.name, subtask.id);
+                if let Some(name) = &subtask.name {
+                    description += &format!(" ({name})");
+                }
+                lines.push(format!(
+                    "{} {count} - {}",
+                    if ok { "ok" } else { "not ok" },
+                    escape_tap(&description)
+                ));
+                let max_score = subtask.max_score;
+                lines.push(match score {
+                    Some(score) => {
+                        let score = task.score_rounding.round(*score, precision);
+                        format!("# score: {score:.precision$}/{max_score:.precision$}")
+                    }
+                    None => format!("# score: ?/{max_score:.precision$}"),
+                });
+            }
+        }
+        lines.push(format!("1..{count}"));
+        lines
+    }
+}
+
+impl UI for TapUI {
+    fn on_message(&mut self, message: UIMessage) {
+        match message {
+            UIMessage::IOITask { task } => self.task = Some(task),
+            UIMessage::Solutions { solutions } => self.solutions = solutions,
+            UIMessage::IOISubtaskScore {
+                subtask,
+                solution,
+                score,
+                ..
+            } => {
+                self.scores.insert((solution, subtask), score);
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(&mut self) {
+        for line in self.lines() {
+            println!("{line}");
+        }
+    }
+}
+
+/// Escape the description of a test line: `#` starts a directive and `\` is the escape character.
+/// Newlines are not allowed inside a line.
+fn escape_tap(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ioi::SubtaskInfo;
+
+    fn solution(name: &str) -> SolutionInfo {
+        SolutionInfo {
+            path: format!("sol/{name}").into(),
+            name: name.into(),
+            language_name: "C++".into(),
+            checks: vec![],
+            score_checks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_tap_ui_lines() {
+        let mut task = IOITask::fake();
+        for (id, max_score) in [(0, 0.0), (1, 100.0)] {
+            task.subtasks.insert(
+                id,
+                SubtaskInfo {
+                    id,
+                    name: Some(format!("st#{id}")),
+                    max_score,
+                    ..Default::default()
+                },
+            );
+        }
+        let mut ui = TapUI::new();
+        ui.on_message(UIMessage::IOITask {
+            task: Box::new(task),
+        });
+        ui.on_message(UIMessage::Solutions {
+            solutions: vec![
+                solution("wrong.cpp"),
+                solution("good.cpp"),
+                solution("crash.cpp"),
+            ],
+        });
+        for (name, score) in [("good.cpp", 100.0), ("wrong.cpp", 42.0)] {
+            for (subtask, score) in [(0, 0.0), (1, score)] {
+                ui.on_message(UIMessage::IOISubtaskScore {
+                    subtask,
+                    solution: format!("sol/{name}").into(),
+                    normalized_score: 0.0,
+                    score,
+                });
+            }
+        }
+        assert_eq!(
+            ui.lines(),
+            vec![
+                "not ok 1 - crash.cpp subtask 0 (st\\#0)",
+                "# score: ?/0",
+                "not ok 2 - crash.cpp subtask 1 (st\\#1)",
+                "# score: ?/100",
+                "ok 3 - good.cpp subtask 0 (st\\#0)",
+                "# score: 0/0",
+                "ok 4 - good.cpp subtask 1 (st\\#1)",
+                "# score: 100/100",
+                "ok 5 - wrong.cpp subtask 0 (st\\#0)",
+                "# score: 0/0",
+                "not ok 6 - wrong.cpp subtask 1 (st\\#1)",
+                "# score: 42/100",
+                "1..6",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tap_ui_no_task() {
+        assert_eq!(
+            TapUI::new().lines(),
+            vec!["1..0 # SKIP no task was evaluated"]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ui::UIMessage;
+
+/// A transformation applied to each `UIMessage` before it reaches the UI.
+///
+/// The transformations are applied in the UI thread, in the same order the messages are received,
+/// so they can keep some state between the messages.
+pub trait UIMessageTransform: Send {
+    /// Transform a message before sending it to the UI.
+    fn transform(&mut self, message: UIMessage) -> UIMessage;
+}
+
+impl<F> UIMessageTransform for F
+where
+    F: FnMut(UIMessage) -> UIMessage + Send,
+{
+    fn transform(&mut self, message: UIMessage) -> UIMessage {
+        self(message)
+    }
+}
+
+/// A `UIMessageTransform` that replaces the paths of the solutions with anonymized names, like
+/// `solution-1.cpp`.
+///
+/// The same solution is always mapped to the same name, and the path of the compilation of a
+/// solution is mapped like the solution itself, so the UIs can still correlate the messages. The
+/// files that are not solutions (e.g. the checker) are left untouched. Note that the paths
+/// mentioned in free-form text (e.g. the diagnostics) are not anonymized.
+#[derive(Debug, Default)]
+pub struct SolutionAnonymizer {
+    /// The anonymized path of each solution seen so far.
+    names: HashMap<PathBuf, PathBuf>,
+}
+
+impl SolutionAnonymizer {
+    /// Make a new `SolutionAnonymizer`.
+    pub fn new() -> SolutionAnonymizer {
+        Default::default()
+    }
+
+    /// Get the anonymized path of a solution, allocating a new name if this solution was never
+    /// seen before.
+    fn anonymize(&mut self, path: PathBuf) -> PathBuf {
+        let next_id = self.names.len() + 1;
+        self.names
+            .entry(path)
+            .or_insert_with_key(|path| {
+                let mut name = format!("solution-{next_id}");
+                if let Some(ext) = path.extension() {
+                    name += ".";
+                    name += &ext.to_string_lossy();
+                }
+                match path.parent() {
+                    Some(parent) => parent.join(name),
+                    None => PathBuf::from(name),
+                }
+            })
+            .clone()
+    }
+
+    /// Get the anonymized path of a file, if it's a known solution.
+    fn anonymize_known(&self, path: PathBuf) -> PathBuf {
+        self.names.get(&path).cloned().unwrap_or(path)
+    }
+}
+
+impl UIMessageTransform for SolutionAnonymizer {
+    fn transform(&mut self, message: UIMessage) -> UIMessage {
+        match message {
+            UIMessage::Solutions { mut solutions } => {
+                for solution in solutions.iter_mut() {
+                    let path = self.anonymize(std::mem::take(&mut solution.path));
+                    solution.name = file_name(&path);
+                    solution.path = path;
+                }
+                UIMessage::Solutions { solutions }
+            }
+            UIMessage::Compilation { file, status } => UIMessage::Compilation {
+                file: self.anonymize_known(file),
+                status,
+            },
+            UIMessage::IOIEvaluation {
+                subtask,
+                testcase,
+                solution,
+                status,
+                manager_index,
+            } => UIMessage::IOIEvaluation {
+                subtask,
+                testcase,
+                solution: self.anonymize(solution),
+                status,
+                manager_index,
+            },
+            UIMessage::IOIChecker {
+                subtask,
+                testcase,
+                solution,
+                status,
+            } => UIMessage::IOIChecker {
+                subtask,
+                testcase,
+                solution: self.anonymize(solution),
+                status,
+            },
+            UIMessage::IOITestcaseScore {
+                subtask,
+                testcase,
+                solution,
+                score,
+                message,
+            } => UIMessage::IOITestcaseScore {
+                subtask,
+                testcase,
+                solution: self.anonymize(solution),
+                score,
+                message,
+            },
+            UIMessage::IOISubtaskScore {
+                subtask,
+                solution,
+                normalized_score,
+                score,
+            } => UIMessage::IOISubtaskScore {
+                subtask,
+                solution: self.anonymize(solution),
+                normalized_score,
+                score,
+            },
+            UIMessage::IOITaskScore { solution, score } => UIMessage::IOITaskScore {
+                solution: self.anonymize(solution),
+                score,
+            },
+            UIMessage::IOIDiagnosticCase {
+                solution,
+                index,
+                subtask,
+                args,
+                score,
+                message,
+            } => UIMessage::IOIDiagnosticCase {
+                solution: self.anonymize(solution),
+                index,
+                subtask,
+                args,
+                score,
+                message,
+            },
+            UIMessage::TerryGeneration {
+                solution,
+                seed,
+                status,
+            } => UIMessage::TerryGeneration {
+                solution: self.anonymize(solution),
+                seed,
+                status,
+            },
+            UIMessage::TerryValidation { solution, status } => UIMessage::TerryValidation {
+                solution: self.anonymize(solution),
+                status,
+            },
+            UIMessage::TerrySolution { solution, status } => UIMessage::TerrySolution {
+                solution: self.anonymize(solution),
+                status,
+            },
+            UIMessage::TerryChecker { solution, status } => UIMessage::TerryChecker {
+                solution: self.anonymize(solution),
+                status,
+            },
+            UIMessage::TerrySolutionOutcome { solution, outcome } => {
+                UIMessage::TerrySolutionOutcome {
+                    solution: self.anonymize(solution),
+                    outcome,
+                }
+            }
+            message => message,
+        }
+    }
+}
+
+/// The file name of a path, as a string.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::UIExecutionStatus;
+
+    use super::*;
+
+    fn score(solution: &str) -> UIMessage {
+        UIMessage::IOITaskScore {
+            solution: solution.into(),
+            score: 0.0,
+        }
+    }
+
+    fn solution_of(message: UIMessage) -> PathBuf {
+        match message {
+            UIMessage::IOITaskScore { solution, .. } => solution,
+            UIMessage::Compilation { file, .. } => file,
+            _ => panic!("Unexpected message: {message:?}"),
+        }
+    }
+
+    #[test]
+    fn test_anonymizer_consistent() {
+        let mut anonymizer = SolutionAnonymizer::new();
+        let first = solution_of(anonymizer.transform(score("sol/secret.cpp")));
+        let second = solution_of(anonymizer.transform(score("sol/other.py")));
+        let again = solution_of(anonymizer.transform(score("sol/secret.cpp")));
+        assert_eq!(first, Path::new("sol/solution-1.cpp"));
+        assert_eq!(second, Path::new("sol/solution-2.py"));
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn test_anonymizer_compilation() {
+        let mut anonymizer = SolutionAnonymizer::new();
+        let solution = solution_of(anonymizer.transform(score("sol/secret.cpp")));
+        let compilation = anonymizer.transform(UIMessage::Compilation {
+            file: "sol/secret.cpp".into(),
+            status: UIExecutionStatus::Pending,
+        });
+        assert_eq!(solution_of(compilation), solution);
+        let checker = anonymizer.transform(UIMessage::Compilation {
+            file: "check/checker.cpp".into(),
+            status: UIExecutionStatus::Pending,
+        });
+        assert_eq!(solution_of(checker), Path::new("check/checker.cpp"));
+    }
+}
@@ -3,7 +3,7 @@ use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
 use task_maker_diagnostics::Diagnostic;
-use task_maker_exec::ExecutorStatus;
+use task_maker_exec::{CacheStats, ExecutorStatus};
 
 use crate::ioi::{SubtaskId, TestcaseId};
 use crate::solution::SolutionInfo;
@@ -135,6 +135,23 @@ pub enum UIMessage {
         score: f64,
     },
 
+    /// The outcome of a diagnostic case of a solution is ready. The diagnostic cases are not part
+    /// of the testset and do not affect the score of the solution.
+    IOIDiagnosticCase {
+        /// The path of the solution.
+        solution: PathBuf,
+        /// The index of the diagnostic case among the ones of the solution.
+        index: usize,
+        /// The id of the subtask the input file is generated for.
+        subtask: SubtaskId,
+        /// The arguments passed to the generator.
+        args: Vec<String>,
+        /// The score of the solution on the diagnostic case, from 0.0 to 1.0.
+        score: f64,
+        /// The message associated with the score.
+        message: String,
+    },
+
     /// The compilation of a booklet.
     IOIBooklet {
         /// The name of the booklet.
@@ -211,4 +228,10 @@ pub enum UIMessage {
         /// The diagnostic message.
         diagnostic: Diagnostic,
     },
+
+    /// The hits and misses of the cache lookups, sent once at the end of the evaluation.
+    CacheStats {
+        /// The outcomes of the cache lookups.
+        stats: CacheStats,
+    },
 }
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Error};
+use task_maker_dag::ExecutionDAGConfig;
+use task_maker_format::ui::{RawUI, UIType, UI};
+use task_maker_format::{
+    find_task, register_task_format, CustomTask, CustomTaskParser, EvaluationConfig,
+    EvaluationData, TaskFormat, TaskInfo,
+};
+
+#[derive(Debug, Clone)]
+struct MarkerTask {
+    path: PathBuf,
+}
+
+impl CustomTask for MarkerTask {
+    fn name(&self) -> &str {
+        "marker"
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn ui(&self, _ui_type: &UIType, _config: ExecutionDAGConfig) -> Result<Box<dyn UI>, Error> {
+        Ok(Box::new(RawUI::new()))
+    }
+
+    fn build_dag(
+        &mut self,
+        _eval: &mut EvaluationData,
+        _config: &EvaluationConfig,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn task_info(&self) -> Result<TaskInfo, Error> {
+        bail!("No task info")
+    }
+
+    fn clone_box(&self) -> Box<dyn CustomTask> {
+        Box::new(self.clone())
+    }
+}
+
+struct MarkerParser;
+
+impl CustomTaskParser for MarkerParser {
+    fn name(&self) -> &str {
+        "Marker"
+    }
+
+    fn is_valid(&self, path: &Path) -> bool {
+        path.join("marker.txt").exists()
+    }
+
+    fn parse(
+        &self,
+        path: &Path,
+        _eval_config: &EvaluationConfig,
+    ) -> Result<Box<dyn CustomTask>, Error> {
+        if std::fs::read_to_string(path.join("marker.txt"))?.trim() == "broken" {
+            bail!("Broken marker");
+        }
+        Ok(Box::new(MarkerTask { path: path.into() }))
+    }
+}
+
+#[test]
+fn test_custom_task_format() {
+    register_task_format(MarkerParser);
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task_dir = tmpdir.path().join("task");
+    std::fs::create_dir_all(task_dir.join("sol")).unwrap();
+    std::fs::write(task_dir.join("marker.txt"), "ok").unwrap();
+    // the custom format takes the precedence over the built-in ones
+    std::fs::write(task_dir.join("task.yaml"), "name: task\n").unwrap();
+
+    let config = EvaluationConfig::default();
+    let task = find_task(Some(task_dir.join("sol")), 2, &config).unwrap();
+    let TaskFormat::Custom(mut task) = task else {
+        panic!("Expecting a custom task, got {task:?}");
+    };
+    assert_eq!(task.name(), "marker");
+    assert_eq!(task.path(), task_dir);
+    let (mut eval, _receiver) = EvaluationData::new(&task_dir);
+    task.build_dag(&mut eval, &config).unwrap();
+
+    std::fs::write(task_dir.join("marker.txt"), "broken").unwrap();
+    std::fs::remove_file(task_dir.join("task.yaml")).unwrap();
+    let err = find_task(Some(task_dir), 1, &config).unwrap_err();
+    assert!(format!("{err:?}").contains("Not a valid Marker task"));
+}
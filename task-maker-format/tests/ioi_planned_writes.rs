@@ -0,0 +1,59 @@
+use task_maker_format::EvaluationConfig;
+
+mod utils;
+
+#[test]
+fn test_ioi_planned_writes() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task = utils::new_task_with_context(tmpdir.path());
+    let writes = task.planned_writes(&EvaluationConfig::default()).unwrap();
+    let mut expected = vec![];
+    for i in 0..3 {
+        expected.push(tmpdir.path().join(format!("input/input{i}.txt")));
+        expected.push(tmpdir.path().join(format!("output/output{i}.txt")));
+    }
+    expected.sort();
+    assert_eq!(writes, expected);
+}
+
+#[test]
+fn test_ioi_planned_writes_dry_run() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task = utils::new_task_with_context(tmpdir.path());
+    let config = EvaluationConfig {
+        dry_run: true,
+        ..Default::default()
+    };
+    assert!(task.planned_writes(&config).unwrap().is_empty());
+}
+
+#[test]
+fn test_ioi_planned_writes_writebacks() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task = utils::new_task_with_context(tmpdir.path());
+    std::fs::create_dir(tmpdir.path().join("gen")).unwrap();
+    std::fs::write(tmpdir.path().join("gen/cases.gen"), "").unwrap();
+    std::fs::write(tmpdir.path().join("gen/GEN"), "# handwritten").unwrap();
+    std::fs::write(tmpdir.path().join("task.yaml.orig"), "").unwrap();
+    std::fs::write(tmpdir.path().join("task.yaml"), "# tm-allow-delete").unwrap();
+
+    let writes = task.planned_writes(&EvaluationConfig::default()).unwrap();
+    assert!(writes.contains(&tmpdir.path().join("task.yaml")));
+    assert!(!writes.contains(&tmpdir.path().join("gen/GEN")));
+}
+
+#[test]
+fn test_ioi_planned_writes_task_toml() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let task = utils::new_task_with_context(tmpdir.path());
+    std::fs::write(tmpdir.path().join("task.toml"), "").unwrap();
+    let task_yaml = tmpdir.path().join("task.yaml");
+
+    let writes = task.planned_writes(&EvaluationConfig::default()).unwrap();
+    assert!(writes.contains(&task_yaml));
+
+    // a handwritten task.yaml is not overwritten
+    std::fs::write(&task_yaml, "# handwritten").unwrap();
+    let writes = task.planned_writes(&EvaluationConfig::default()).unwrap();
+    assert!(!writes.contains(&task_yaml));
+}
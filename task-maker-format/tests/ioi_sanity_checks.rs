@@ -1,6 +1,7 @@
 use std::process::Command;
 use std::sync::Arc;
 
+use task_maker_format::ioi::sanity_checks::get_sanity_checks;
 use task_maker_format::ioi::{Booklet, BookletConfig, IOITask, Statement, StatementConfig};
 use task_maker_format::ui::UIMessage;
 use task_maker_format::EvaluationData;
@@ -57,6 +58,47 @@ fn test_sanity_checks_max_score() {
     has_warning(&warnings, "The score of the task");
 }
 
+/// A task with two subtasks whose scores sum to 90.
+fn task_with_score_90() -> IOITask {
+    let mut task = utils::new_task();
+    let mut subtask = task.subtasks[&0].clone();
+    task.subtasks.get_mut(&0).unwrap().max_score = 40.0;
+    subtask.id = 1;
+    subtask.name = Some("big".into());
+    subtask.max_score = 50.0;
+    task.subtasks.insert(1, subtask);
+    task
+}
+
+#[test]
+fn test_sanity_checks_max_score_disabled() {
+    let mut task = task_with_score_90();
+    task.sanity_checks = Arc::new(get_sanity_checks(&["TaskMaxScore"]));
+    let warnings = get_warnings(&task);
+    does_not_have_warning(&warnings, "The score of the task");
+}
+
+#[test]
+fn test_sanity_checks_subtask_score_sum() {
+    let warnings = get_warnings(&task_with_score_90());
+    let warning = warnings
+        .iter()
+        .find(|w| w.contains("The scores of the subtasks sum to 90 (not 100)"))
+        .expect("SubtaskScoreSum did not fire");
+    assert!(warning.contains("Warning"));
+    assert!(warning.contains("Subtask 0: 40"));
+    assert!(warning.contains("Subtask 1 (big): 50"));
+}
+
+#[test]
+fn test_sanity_checks_subtask_score_sum_disabled() {
+    let mut task = task_with_score_90();
+    task.sanity_checks = Arc::new(get_sanity_checks(&["SubtaskScoreSum"]));
+    let warnings = get_warnings(&task);
+    does_not_have_warning(&warnings, "The scores of the subtasks sum to");
+    has_warning(&warnings, "The score of the task is 90 (not 100)");
+}
+
 #[test]
 fn test_sanity_checks_att_graders() {
     let tmpdir = tempfile::TempDir::new().unwrap();
@@ -420,3 +462,54 @@ fn test_sanity_checks_statement_git_known() {
     let warnings = get_post_warnings(&task);
     does_not_have_warning(&warnings, "git");
 }
+
+#[test]
+fn test_sanity_checks_static_only() {
+    let mut task = utils::new_task();
+    task.subtasks.get_mut(&0).unwrap().max_score = 111.0;
+    let (mut eval, recv) = EvaluationData::new("");
+    let skipped = task.sanity_checks.static_checks(&task, &mut eval).unwrap();
+    let mut warnings = vec![];
+    while let Ok(mex) = recv.try_recv() {
+        if let UIMessage::Diagnostic { diagnostic } = mex {
+            warnings.push(diagnostic.to_string())
+        }
+    }
+    has_warning(&warnings, "The score of the task");
+    assert!(skipped.contains(&"AllOutputsEqual"));
+    assert!(skipped.contains(&"FuzzCheckerWithJunkOutput"));
+    assert!(!skipped.contains(&"TaskMaxScore"));
+    assert!(eval.dag.data.execution_groups.is_empty());
+}
+
+#[test]
+fn test_sanity_checks_task_metadata_missing() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.subtasks.clear();
+    let warnings = get_warnings(&task);
+    has_warning(&warnings, "Missing time limit");
+    has_warning(&warnings, "Missing memory limit");
+    has_warning(&warnings, "The task has no subtasks");
+}
+
+#[test]
+fn test_sanity_checks_task_metadata_present() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    task.time_limit = Some(1.0);
+    task.memory_limit = Some(256);
+    let warnings = get_warnings(&task);
+    does_not_have_warning(&warnings, "Missing time limit");
+    does_not_have_warning(&warnings, "Missing memory limit");
+    does_not_have_warning(&warnings, "The task has no subtasks");
+}
+
+#[test]
+fn test_sanity_checks_task_metadata_output_only() {
+    let mut task = utils::new_task();
+    task.output_only = true;
+    let warnings = get_warnings(&task);
+    does_not_have_warning(&warnings, "Missing time limit");
+    does_not_have_warning(&warnings, "Missing memory limit");
+}
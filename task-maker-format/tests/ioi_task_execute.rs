@@ -88,6 +88,31 @@ fn test_ioi_task_execute_eval() {
     assert_eq!(eval.dag.data.execution_groups.len(), 3 + 3); // eval + checker
 }
 
+#[test]
+fn test_ioi_task_execute_max_executions() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let mut task = utils::new_task_with_context(tmpdir.path());
+
+    std::fs::create_dir(tmpdir.path().join("sol")).unwrap();
+    std::fs::write(tmpdir.path().join("sol").join("sol.py"), "foo").unwrap();
+
+    let config = EvaluationConfig {
+        max_executions: Some(6),
+        ..Default::default()
+    };
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    task.build_dag(&mut eval, &config).unwrap();
+
+    let mut task = utils::new_task_with_context(tmpdir.path());
+    let config = EvaluationConfig {
+        max_executions: Some(5),
+        ..Default::default()
+    };
+    let (mut eval, _receiver) = EvaluationData::new(tmpdir.path());
+    let err = task.build_dag(&mut eval, &config).unwrap_err();
+    assert!(err.to_string().contains("more than 5 executions"));
+}
+
 #[test]
 fn test_ioi_task_execute_booklet() {
     let tmpdir = tempfile::TempDir::new().unwrap();
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
-use task_maker_dag::{ExecutionResult, ExecutionStatus};
-use task_maker_exec::ExecutorStatus;
+use task_maker_dag::{ExecutionResult, ExecutionStatus, WorkerUuid};
+use task_maker_exec::{ExecutorStatus, ExecutorWorkerStatus};
 use task_maker_format::ioi::{TestcaseEvaluationStatus, TestcaseGenerationStatus, UIState};
 use task_maker_format::ui::{CompilationStatus, UIExecutionStatus, UIMessage, UIStateT};
 
@@ -79,6 +79,22 @@ fn test_ui_state_compilation_failure() {
     );
 }
 
+#[test]
+fn test_ui_state_compilation_timeout() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let file = PathBuf::from("file");
+    let mut result = utils::bad_result();
+    result.status = ExecutionStatus::TimeLimitExceeded;
+    ui.apply(UIMessage::Compilation {
+        file: file.clone(),
+        status: UIExecutionStatus::Done {
+            result: vec![result],
+        },
+    });
+    assert_eq!(ui.compilations[&file].failure_reason(), Some("TLE"));
+}
+
 #[test]
 fn test_ui_state_compilation_stdout() {
     let task = utils::new_task();
@@ -718,3 +734,112 @@ fn test_ui_state_booklet_dep() {
         UIExecutionStatus::Pending
     );
 }
+
+#[test]
+fn test_ui_state_diagnostic_case() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let solution = PathBuf::from("sol.cpp");
+    ui.apply(UIMessage::IOIDiagnosticCase {
+        solution: solution.clone(),
+        index: 1,
+        subtask: 0,
+        args: vec!["42".into()],
+        score: 0.5,
+        message: "Partial".into(),
+    });
+    let outcome = &ui.diagnostic_cases[&solution][&1];
+    assert_eq!(outcome.subtask, 0);
+    assert_eq!(outcome.args, vec!["42".to_string()]);
+    assert_eq!(outcome.score, 0.5);
+    assert_eq!(outcome.message, "Partial");
+    // the diagnostic cases do not affect the evaluations
+    assert!(ui.evaluations.is_empty());
+}
+
+#[test]
+fn test_ui_state_solution_ranking() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    for (name, cpu_time, score) in [
+        ("slow", 2.0, Some(100.0)),
+        ("fast", 1.0, Some(100.0)),
+        ("wrong", 0.1, Some(50.0)),
+        ("partial", 0.1, None),
+    ] {
+        let mut result = utils::good_result();
+        result.resources.cpu_time = cpu_time;
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase: 0,
+            solution: name.into(),
+            status: UIExecutionStatus::Done {
+                result: vec![result],
+            },
+            manager_index: None,
+        });
+        if let Some(score) = score {
+            ui.apply(UIMessage::IOITaskScore {
+                solution: name.into(),
+                score,
+            });
+        }
+    }
+    ui.apply(UIMessage::Compilation {
+        file: "wrong".into(),
+        status: UIExecutionStatus::Done {
+            result: vec![utils::bad_result()],
+        },
+    });
+
+    let ranking = ui.solution_ranking();
+    let ranked: Vec<_> = ranking
+        .ranked
+        .iter()
+        .map(|s| (s.path.to_str().unwrap(), s.score, s.total_time))
+        .collect();
+    assert_eq!(ranked, vec![("fast", 100.0, 1.0), ("slow", 100.0, 2.0)]);
+    assert_eq!(
+        ranking.not_completed,
+        vec![
+            (
+                PathBuf::from("partial"),
+                "Evaluation not completed".to_string()
+            ),
+            (PathBuf::from("wrong"), "Compilation failed".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_ui_state_evaluation_worker() {
+    let task = utils::new_task();
+    let mut ui = UIState::new(&task, Default::default());
+    let file = PathBuf::from("file");
+    let worker1: WorkerUuid = "00000000-0000-0000-0000-000000000001".parse().unwrap();
+    let worker2: WorkerUuid = "00000000-0000-0000-0000-000000000002".parse().unwrap();
+    ui.apply(UIMessage::ServerStatus {
+        status: ExecutorStatus {
+            connected_workers: vec![ExecutorWorkerStatus {
+                uuid: worker1,
+                name: "worker1".into(),
+                current_job: None,
+            }],
+            ready_execs: 0,
+            waiting_execs: 0,
+        },
+    });
+    // the execution is rescheduled on another worker, the last one is kept
+    for worker in [worker1, worker2] {
+        ui.apply(UIMessage::IOIEvaluation {
+            subtask: 0,
+            testcase: 0,
+            solution: file.clone(),
+            status: UIExecutionStatus::Started { worker },
+            manager_index: None,
+        });
+    }
+    assert_eq!(ui.evaluations[&file].testcases[&0].worker, Some(worker2));
+    assert_eq!(ui.worker_name(worker1), "worker1");
+    assert_eq!(ui.worker_name(worker2), worker2.to_string());
+}
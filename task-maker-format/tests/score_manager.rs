@@ -4,8 +4,13 @@ extern crate approx;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use task_maker_dag::{
+    Execution, ExecutionCommand, ExecutionResult, ExecutionStatus, File, FileUuid, ProvideHandle,
+    SkipHandle,
+};
 use task_maker_format::ioi::*;
 use task_maker_format::ui::{UIMessage, UIMessageSender};
+use task_maker_format::SourceFile;
 
 mod utils;
 
@@ -223,3 +228,174 @@ fn test_score_manager_empty_subtask() {
     }
     assert!(receiver.try_recv().is_err());
 }
+
+/// Make a task with a scorer, and the score manager of a solution with the input of the scorer.
+fn scorer_manager(
+    dir: &std::path::Path,
+    sender: Arc<Mutex<UIMessageSender>>,
+) -> (ScoreManager, FileUuid, ProvideHandle) {
+    let path = dir.join("scorer.py");
+    std::fs::write(&path, "").unwrap();
+    let scorer = SourceFile::new(&path, dir, "scorer", None, None::<PathBuf>).unwrap();
+    let mut task = utils::new_task();
+    task.scorer = Some(Arc::new(scorer));
+    let mut manager = ScoreManager::new(&task, "sol".into(), sender).unwrap();
+    let input = File::new("scorer input");
+    let provide_handle = ProvideHandle::default();
+    manager.set_scorer_input(input.uuid, provide_handle.clone());
+    (manager, input.uuid, provide_handle)
+}
+
+/// The result of a scorer that printed `output`.
+fn scorer_result(output: &str) -> ExecutionResult {
+    ExecutionResult {
+        status: ExecutionStatus::Success,
+        stdout: Some(output.as_bytes().to_vec()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_score_manager_scorer() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let (mut manager, input, provide_handle) = scorer_manager(tmpdir.path(), sender.clone());
+    for (subtask, testcase) in [(0, 0), (1, 1), (1, 2)] {
+        // the input of the scorer is given only when all the testcases are known
+        assert!(provide_handle.take().is_empty());
+        manager
+            .score(subtask, testcase, 1.0, "foo".into(), sender.clone())
+            .unwrap();
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(UIMessage::IOITestcaseScore { .. })
+        ));
+    }
+    assert!(receiver.try_recv().is_err());
+    let provided = provide_handle.take();
+    assert_eq!(provided.len(), 1);
+    assert_eq!(provided[0].0, input);
+    let scorer_input: serde_json::Value = serde_json::from_slice(&provided[0].1).unwrap();
+    assert_eq!(
+        scorer_input["subtasks"]["1"]["testcases"]["2"].as_f64(),
+        Some(1.0)
+    );
+
+    manager
+        .scorer_done(&scorer_result(r#"{"0": 5, "1": 45}"#), sender.clone())
+        .unwrap();
+    for expected in [(0, 5.0), (1, 45.0)] {
+        match receiver.try_recv() {
+            Ok(UIMessage::IOISubtaskScore {
+                subtask,
+                score,
+                normalized_score,
+                ..
+            }) => {
+                assert_eq!(subtask, expected.0);
+                assert_abs_diff_eq!(score, expected.1);
+                assert_abs_diff_eq!(normalized_score, 0.5);
+            }
+            mex => panic!("Expecting UIMessage::IOISubtaskScore but was {mex:?}"),
+        }
+    }
+    match receiver.try_recv() {
+        Ok(UIMessage::IOITaskScore { score, .. }) => assert_abs_diff_eq!(score, 50.0),
+        mex => panic!("Expecting UIMessage::IOITaskScore but was {mex:?}"),
+    }
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn test_score_manager_scorer_malformed_output() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let (mut manager, _, _) = scorer_manager(tmpdir.path(), sender.clone());
+    for (subtask, testcase) in [(0, 0), (1, 1), (1, 2)] {
+        manager
+            .score(subtask, testcase, 1.0, "foo".into(), sender.clone())
+            .unwrap();
+    }
+    manager
+        .scorer_done(&scorer_result("100"), sender.clone())
+        .unwrap();
+    let messages: Vec<_> = receiver.try_iter().collect();
+    assert!(messages.iter().any(|mex| matches!(
+        mex,
+        UIMessage::Diagnostic { diagnostic } if diagnostic.message().contains("scorer failed")
+    )));
+    assert!(messages.iter().any(|mex| matches!(
+        mex,
+        UIMessage::IOITaskScore { score, .. } if *score == 0.0
+    )));
+}
+
+#[test]
+fn test_score_manager_scorer_failed() {
+    let tmpdir = tempfile::TempDir::new().unwrap();
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let (mut manager, _, _) = scorer_manager(tmpdir.path(), sender.clone());
+    let result = ExecutionResult {
+        status: ExecutionStatus::ReturnCode(1),
+        stderr: Some(b"boom".to_vec()),
+        ..Default::default()
+    };
+    manager.scorer_done(&result, sender.clone()).unwrap();
+    let messages: Vec<_> = receiver.try_iter().collect();
+    assert!(messages.iter().any(|mex| matches!(
+        mex,
+        UIMessage::Diagnostic { diagnostic } if diagnostic.message().contains("scorer failed")
+    )));
+}
+
+#[test]
+fn test_score_manager_stop_after() {
+    let task = utils::new_task();
+    let (sender, receiver) = UIMessageSender::new();
+    let sender = Arc::new(Mutex::new(sender));
+    let mut manager = ScoreManager::new(&task, "sol".into(), sender.clone()).unwrap();
+    let skip_handle = SkipHandle::default();
+    manager.stop_after(2, skip_handle.clone());
+    let evaluations: Vec<_> = (0..3)
+        .map(|_| {
+            Execution::new("sol", ExecutionCommand::local("sol"))
+                .into_group()
+                .uuid
+        })
+        .collect();
+    for evaluation in &evaluations {
+        manager.add_evaluation(*evaluation);
+    }
+
+    manager
+        .score(0, 0, 0.0, "foo".into(), sender.clone())
+        .unwrap();
+    assert!(!manager.is_stopped());
+    assert!(skip_handle.take().is_empty());
+    manager
+        .score(1, 1, 0.5, "foo".into(), sender.clone())
+        .unwrap();
+    assert!(manager.is_stopped());
+    assert_eq!(skip_handle.take(), evaluations);
+
+    // the skipped testcase is scored zero, without stopping the solution again
+    manager
+        .score(1, 2, 0.0, "Skipped".into(), sender.clone())
+        .unwrap();
+    let messages: Vec<_> = receiver.try_iter().collect();
+    let stopped = messages
+        .iter()
+        .filter(|mex| match mex {
+            UIMessage::Diagnostic { diagnostic } => diagnostic.message().starts_with("Stopped"),
+            _ => false,
+        })
+        .count();
+    assert_eq!(stopped, 1);
+    assert!(messages.iter().any(|mex| matches!(
+        mex,
+        UIMessage::IOITaskScore { score, .. } if *score == 0.0
+    )));
+}
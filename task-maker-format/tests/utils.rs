@@ -23,18 +23,28 @@ pub fn new_task_with_context(path: &Path) -> IOITask {
         task_type: TaskType::Batch(BatchTypeData {
             output_generator: None,
             checker: Checker::WhiteDiff,
+            extra_output_files: vec![],
         }),
         name: "task".to_string(),
         title: "The Task".to_string(),
         time_limit: None,
         memory_limit: None,
+        relative_time_limit: None,
+        compilation_time_limit: None,
+        compilation_memory_limit: None,
+        generation_wall_time_limit: None,
         infile: None,
         outfile: None,
+        generator_outputs: vec![],
+        normalize_files: false,
+        output_only: false,
         subtasks: HashMap::new(),
         testcases: HashMap::new(),
         input_validator_generator: Default::default(),
         testcase_score_aggregator: TestcaseScoreAggregator::Min,
+        scorer: None,
         score_precision: 0,
+        score_rounding: Default::default(),
         grader_map: Arc::new(GraderMap::new(Vec::<PathBuf>::new())),
         booklets: vec![],
         difficulty: None,
@@ -80,11 +90,14 @@ pub fn good_result() -> ExecutionResult {
         status: ExecutionStatus::Success,
         was_killed: false,
         was_cached: false,
+        cache_hit: None,
+        missing_outputs: vec![],
         resources: ExecutionResourcesUsage {
             cpu_time: 0.0,
             sys_time: 0.0,
             wall_time: 0.0,
             memory: 0,
+            final_memory: 0,
         },
         stdout: None,
         stderr: None,
@@ -96,11 +109,14 @@ pub fn bad_result() -> ExecutionResult {
         status: ExecutionStatus::ReturnCode(123),
         was_killed: false,
         was_cached: false,
+        cache_hit: None,
+        missing_outputs: vec![],
         resources: ExecutionResourcesUsage {
             cpu_time: 0.0,
             sys_time: 0.0,
             wall_time: 0.0,
             memory: 0,
+            final_memory: 0,
         },
         stdout: None,
         stderr: None,
@@ -5,7 +5,7 @@ use task_maker_dag::{
     Execution, ExecutionCommand, ExecutionDAG, ExecutionGroup, ExecutionLimits, File,
 };
 
-use crate::{Dependency, GraderMap};
+use crate::{Dependency, GraderMap, SourceWarning};
 
 /// Trait that defines the properties of the supported languages. Most of the methods have a safe
 /// blanket implementation, note that not all of them are _really_ optional: based on the value
@@ -77,10 +77,37 @@ pub trait Language: std::fmt::Debug + Send + Sync {
         vec![]
     }
 
+    /// The host paths needed by the runtime of this language (e.g. the standard library of an
+    /// interpreter) that are not mounted in the sandbox by default. They are mounted read-only
+    /// inside the sandbox when running a program, and they must exist.
+    fn runtime_mounts(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Whether the compiler of this language supports the address and undefined behaviour
+    /// sanitizers, enabled with `CompilationSettings::sanitize`.
+    fn supports_sanitizers(&self) -> bool {
+        false
+    }
+
+    /// Whether the compiler of this language supports choosing the optimization level, with
+    /// `CompilationSettings::opt_level`.
+    fn supports_opt_level(&self) -> bool {
+        false
+    }
+
     /// Update the limits for some language-specific requirements. For example the executable may
     /// need to fork (hence use more processes).
     fn custom_limits(&self, _limits: &mut ExecutionLimits) {}
 
+    /// Look for the problems in the source file that will not prevent it from running, but that
+    /// likely make it behave differently from what its author expects (e.g. asking for a different
+    /// version of the interpreter). This is called once per source file and must never fail: an
+    /// unreadable file has no warnings.
+    fn source_warnings(&self, _path: &Path) -> Vec<SourceWarning> {
+        vec![]
+    }
+
     /// The name of the executable inside the sandbox. If this binary will be written elsewhere in
     /// the system, use the same name. Otherwise fallback to the original file name, without
     /// extension.
@@ -102,6 +129,11 @@ pub struct CompilationSettings {
     pub copy_exe: bool,
     /// Whether to try to link statically the binary.
     pub list_static: bool,
+    /// Whether to compile the binary with the sanitizers, if supported by the language.
+    pub sanitize: bool,
+    /// The optimization level to compile the binary with (e.g. `0` for `-O0`) instead of the
+    /// default one, if supported by the language.
+    pub opt_level: Option<u8>,
 }
 
 /// This trait describes the API of a "compiled language builder", a component that builds the DAG
@@ -6,6 +6,8 @@ use crate::language::{
     CompilationSettings, CompiledLanguageBuilder, SimpleCompiledLanguageBuilder,
 };
 use crate::languages::cpp::find_cpp_deps;
+use crate::languages::extra_flags_from_env;
+use crate::sanitizers::SANITIZER_FLAGS;
 use crate::Language;
 
 /// Configuration of the C language to use.
@@ -15,7 +17,8 @@ pub struct LanguageCConfiguration {
     pub compiler: ExecutionCommand,
     /// Version of the C standard library to use (e.g. c11).
     pub std_version: String,
-    /// Extra flags to pass to the compiler.
+    /// Extra flags to pass to the compiler. They are added after the default ones, so they can
+    /// override them (e.g. `-O0` or `-std=c99`).
     pub extra_flags: Vec<String>,
 }
 
@@ -33,16 +36,14 @@ impl LanguageC {
 }
 
 impl LanguageCConfiguration {
-    /// Get the configuration of C from the environment variables.
+    /// Get the configuration of C from the environment variables. The extra flags are read from
+    /// `$TM_CFLAGS`, or from `$CFLAGS` if it's not set.
     pub fn from_env() -> LanguageCConfiguration {
         let compiler = std::env::var_os("TM_CC")
             .or_else(|| std::env::var_os("CC"))
             .unwrap_or_else(|| "gcc".into());
         let std_version = std::env::var("TM_CC_STD_VERSION").unwrap_or_else(|_| "c11".into());
-        let extra_flags = std::env::var("TM_CFLAGS")
-            .or_else(|_| std::env::var("CFLAGS"))
-            .unwrap_or_else(|_| String::new());
-        let extra_flags = shell_words::split(&extra_flags).expect("Invalid $TM_CFLAGS");
+        let extra_flags = extra_flags_from_env(&["TM_CFLAGS", "CFLAGS"]);
         LanguageCConfiguration {
             compiler: ExecutionCommand::System(compiler.into()),
             std_version,
@@ -68,6 +69,14 @@ impl Language for LanguageC {
         Some("//")
     }
 
+    fn supports_sanitizers(&self) -> bool {
+        true
+    }
+
+    fn supports_opt_level(&self) -> bool {
+        true
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
@@ -93,7 +102,16 @@ impl Language for LanguageC {
         for arg in &self.config.extra_flags {
             metadata.add_arg(arg);
         }
-        if metadata.settings.list_static {
+        // after the extra flags, since the last -O wins
+        if let Some(opt_level) = metadata.settings.opt_level {
+            metadata.add_arg(format!("-O{opt_level}"));
+        }
+        if metadata.settings.sanitize {
+            for flag in SANITIZER_FLAGS {
+                metadata.add_arg(*flag);
+            }
+        } else if metadata.settings.list_static {
+            // the sanitizers' runtime cannot be linked statically
             metadata.add_arg("-static");
         }
 
@@ -164,4 +182,28 @@ mod tests {
         assert_that(args).contains("-lfoobar".to_string());
         assert_that(args).contains("-static".to_string());
     }
+
+    #[test]
+    fn test_compilation_args_sanitize() {
+        let tmp = setup();
+
+        let lang = LanguageC::new(LanguageCConfiguration {
+            compiler: ExecutionCommand::System("gcc".into()),
+            std_version: "c11".to_string(),
+            extra_flags: vec![],
+        });
+        let settings = CompilationSettings {
+            list_static: true,
+            sanitize: true,
+            ..Default::default()
+        };
+        let mut builder = lang
+            .compilation_builder(&tmp.path().join("foo.c"), settings)
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+
+        let args = &comp.executions[0].args;
+        assert_that(args).contains("-fsanitize=address,undefined".to_string());
+        assert_that(args).does_not_contain("-static".to_string());
+    }
 }
@@ -6,7 +6,8 @@ use task_maker_dag::*;
 use crate::language::{
     CompilationSettings, CompiledLanguageBuilder, Language, SimpleCompiledLanguageBuilder,
 };
-use crate::languages::find_dependencies;
+use crate::languages::{extra_flags_from_env, find_dependencies};
+use crate::sanitizers::SANITIZER_FLAGS;
 use crate::Dependency;
 
 /// Configuration of the C++ language to use.
@@ -16,7 +17,8 @@ pub struct LanguageCppConfiguration {
     pub compiler: ExecutionCommand,
     /// Version of the C++ standard library to use (e.g. c++11).
     pub std_version: String,
-    /// Extra flags to pass to the compiler.
+    /// Extra flags to pass to the compiler. They are added after the default ones, so they can
+    /// override them (e.g. `-O0` or `-std=c++20`).
     pub extra_flags: Vec<String>,
 }
 
@@ -27,16 +29,14 @@ pub struct LanguageCpp {
 }
 
 impl LanguageCppConfiguration {
-    /// Get the configuration of C++ from the environment variables.
+    /// Get the configuration of C++ from the environment variables. The extra flags are read from
+    /// `$TM_CXXFLAGS`, or from `$CXXFLAGS` if it's not set.
     pub fn from_env() -> LanguageCppConfiguration {
         let compiler = std::env::var_os("TM_CXX")
             .or_else(|| std::env::var_os("CXX"))
             .unwrap_or_else(|| "g++".into());
         let std_version = std::env::var("TM_CXX_STD_VERSION").unwrap_or_else(|_| "c++17".into());
-        let extra_flags = std::env::var("TM_CXXFLAGS")
-            .or_else(|_| std::env::var("CXXFLAGS"))
-            .unwrap_or_else(|_| String::new());
-        let extra_flags = shell_words::split(&extra_flags).expect("Invalid $TM_CXXFLAGS");
+        let extra_flags = extra_flags_from_env(&["TM_CXXFLAGS", "CXXFLAGS"]);
         LanguageCppConfiguration {
             compiler: ExecutionCommand::System(compiler.into()),
             std_version,
@@ -69,6 +69,14 @@ impl Language for LanguageCpp {
         Some("//")
     }
 
+    fn supports_sanitizers(&self) -> bool {
+        true
+    }
+
+    fn supports_opt_level(&self) -> bool {
+        true
+    }
+
     fn compilation_builder(
         &self,
         source: &Path,
@@ -93,7 +101,16 @@ impl Language for LanguageCpp {
         for arg in &self.config.extra_flags {
             metadata.add_arg(arg);
         }
-        if metadata.settings.list_static {
+        // after the extra flags, since the last -O wins
+        if let Some(opt_level) = metadata.settings.opt_level {
+            metadata.add_arg(format!("-O{opt_level}"));
+        }
+        if metadata.settings.sanitize {
+            for flag in SANITIZER_FLAGS {
+                metadata.add_arg(*flag);
+            }
+        } else if metadata.settings.list_static {
+            // the sanitizers' runtime cannot be linked statically
             metadata.add_arg("-static");
         }
 
@@ -222,6 +239,26 @@ mod tests {
         assert_that(args).contains("-static".to_string());
     }
 
+    #[test]
+    fn test_compilation_args_extra_flags_after_defaults() {
+        let tmp = setup();
+
+        let lang = LanguageCpp::new(LanguageCppConfiguration {
+            compiler: ExecutionCommand::System("g++".into()),
+            std_version: "c++14".to_string(),
+            extra_flags: vec!["-O0".into(), "-std=c++20".into()],
+        });
+        let mut builder = lang
+            .compilation_builder(&tmp.path().join("foo.cpp"), CompilationSettings::default())
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+
+        let args = &comp.executions[0].args;
+        let position = |arg: &str| args.iter().position(|a| a == arg).unwrap();
+        assert!(position("-O0") > position("-O2"));
+        assert!(position("-std=c++20") > position("-std=c++14"));
+    }
+
     #[test]
     fn test_extract_imports() {
         let tmpdir = setup();
@@ -288,4 +325,52 @@ mod tests {
         assert_that(&deps[0].local_path).is_equal_to(foo_path);
         assert_that(&deps[0].sandbox_path).is_equal_to(PathBuf::from("foo.hpp"));
     }
+
+    #[test]
+    fn test_compilation_args_sanitize() {
+        let tmp = setup();
+
+        let lang = LanguageCpp::new(LanguageCppConfiguration {
+            compiler: ExecutionCommand::System("g++".into()),
+            std_version: "c++14".to_string(),
+            extra_flags: vec![],
+        });
+        let settings = CompilationSettings {
+            list_static: true,
+            sanitize: true,
+            ..Default::default()
+        };
+        let mut builder = lang
+            .compilation_builder(&tmp.path().join("foo.cpp"), settings)
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+
+        let args = &comp.executions[0].args;
+        assert_that(args).contains("-fsanitize=address,undefined".to_string());
+        assert_that(args).does_not_contain("-static".to_string());
+    }
+
+    #[test]
+    fn test_compilation_args_opt_level() {
+        let tmp = setup();
+
+        let lang = LanguageCpp::new(LanguageCppConfiguration {
+            compiler: ExecutionCommand::System("g++".into()),
+            std_version: "c++14".to_string(),
+            extra_flags: vec!["-O3".into()],
+        });
+        let settings = CompilationSettings {
+            opt_level: Some(0),
+            ..Default::default()
+        };
+        let mut builder = lang
+            .compilation_builder(&tmp.path().join("foo.cpp"), settings)
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+
+        // the last -O wins, so the requested level must come after the extra flags
+        let args = &comp.executions[0].args;
+        let last_opt = args.iter().rev().find(|arg| arg.starts_with("-O"));
+        assert_that(&last_opt).is_equal_to(Some(&"-O0".to_string()));
+    }
 }
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use task_maker_dag::*;
 
@@ -6,14 +6,47 @@ use crate::language::{
     CompilationSettings, CompiledLanguageBuilder, Language, SimpleCompiledLanguageBuilder,
 };
 
+/// Configuration of the Java language to use.
+#[derive(Clone, Debug)]
+pub struct LanguageJavaConfiguration {
+    /// The compiler to use (e.g. `javac`), it's searched in the `$PATH` of the worker if it's not
+    /// absolute.
+    pub javac: PathBuf,
+    /// The tool for packaging the compiled classes (e.g. `jar`).
+    pub jar: PathBuf,
+    /// The virtual machine that runs the compiled programs (e.g. `java`).
+    pub java: PathBuf,
+}
+
+impl LanguageJavaConfiguration {
+    /// Get the configuration of Java from the environment variables.
+    pub fn from_env() -> LanguageJavaConfiguration {
+        let tool = |var: &str, default: &str| -> PathBuf {
+            std::env::var_os(var)
+                .unwrap_or_else(|| default.into())
+                .into()
+        };
+        LanguageJavaConfiguration {
+            javac: tool("TM_JAVAC", "javac"),
+            jar: tool("TM_JAR", "jar"),
+            java: tool("TM_JAVA", "java"),
+        }
+    }
+}
+
 /// The Java language.
 #[derive(Debug)]
-pub struct LanguageJava;
+pub struct LanguageJava {
+    /// The configuration of Java.
+    pub config: LanguageJavaConfiguration,
+}
 
 impl LanguageJava {
     /// Make a new LanguageJava
     pub fn new() -> LanguageJava {
-        LanguageJava
+        LanguageJava {
+            config: LanguageJavaConfiguration::from_env(),
+        }
     }
 }
 
@@ -52,8 +85,10 @@ impl Language for LanguageJava {
             .to_string_lossy()
             .to_string();
 
+        let javac = shell_words::quote(&self.config.javac.to_string_lossy()).to_string();
+        let jar = shell_words::quote(&self.config.jar.to_string_lossy()).to_string();
         metadata.add_arg("-c").add_arg(format!(
-            "javac -encoding UTF-8 -d . *.java && jar cfe {binary_name} {main_class} *.class"
+            "{javac} -encoding UTF-8 -d . *.java && {jar} cfe {binary_name} {main_class} *.class"
         ));
 
         metadata.grader_only();
@@ -62,7 +97,7 @@ impl Language for LanguageJava {
     }
 
     fn runtime_command(&self, _path: &Path, _write_to: Option<&Path>) -> ExecutionCommand {
-        ExecutionCommand::system("java")
+        ExecutionCommand::System(self.config.java.clone())
     }
 
     fn runtime_args(
@@ -92,3 +127,41 @@ impl Language for LanguageJava {
             .add_extra_readable_dir("/etc");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use speculoos::prelude::*;
+    use task_maker_dag::{ExecutionCommand, ExecutionDAG};
+    use tempfile::TempDir;
+
+    use super::{LanguageJava, LanguageJavaConfiguration};
+    use crate::language::{CompilationSettings, Language};
+
+    #[test]
+    fn test_custom_tools() {
+        let tmp = TempDir::new().unwrap();
+        let source = tmp.path().join("Foo.java");
+        std::fs::write(&source, "public class Foo {}").unwrap();
+        let lang = LanguageJava {
+            config: LanguageJavaConfiguration {
+                javac: "/opt/jdk/bin/javac".into(),
+                jar: "/opt/jdk/bin/jar".into(),
+                java: "/opt/jdk/bin/java".into(),
+            },
+        };
+
+        let mut builder = lang
+            .compilation_builder(&source, CompilationSettings::default())
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+        let script = &comp.executions[0].args[1];
+        assert_that(script).starts_with("/opt/jdk/bin/javac ");
+        assert_that(script).contains(" && /opt/jdk/bin/jar cfe ");
+        assert_that(script).contains(" Foo *.class");
+
+        assert_eq!(
+            lang.runtime_command(&source, None),
+            ExecutionCommand::system("/opt/jdk/bin/java")
+        );
+    }
+}
@@ -16,6 +16,21 @@ pub(crate) mod python;
 pub(crate) mod rust;
 pub(crate) mod shell;
 
+/// Read the extra flags to pass to a compiler from the first of the given environment variables
+/// that is set, for example `TM_CXXFLAGS` and then `CXXFLAGS`. The value is split in arguments
+/// following the quoting rules of the shell, so `-DNAME="a b"` is a single argument.
+///
+/// Panics if the value is not properly quoted.
+pub(crate) fn extra_flags_from_env(vars: &[&str]) -> Vec<String> {
+    let Some((var, value)) = vars
+        .iter()
+        .find_map(|var| std::env::var(var).ok().map(|value| (var, value)))
+    else {
+        return vec![];
+    };
+    shell_words::split(&value).unwrap_or_else(|e| panic!("Invalid ${var}: {e}"))
+}
+
 /// Extract all the dependencies of a source file recursively. The file can include/import many
 /// other files, even cyclically. Each import is included only once in the result.
 ///
@@ -61,3 +76,29 @@ where
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_flags_from_env() {
+        std::env::set_var(
+            "TM_TEST_EXTRA_FLAGS_FIRST",
+            r#"-Wall  -DNAME="a b" '-I dir'"#,
+        );
+        std::env::set_var("TM_TEST_EXTRA_FLAGS_SECOND", "-Wextra");
+        let flags = extra_flags_from_env(&[
+            "TM_TEST_EXTRA_FLAGS_MISSING",
+            "TM_TEST_EXTRA_FLAGS_FIRST",
+            "TM_TEST_EXTRA_FLAGS_SECOND",
+        ]);
+        assert_eq!(flags, vec!["-Wall", "-DNAME=a b", "-I dir"]);
+    }
+
+    #[test]
+    fn test_extra_flags_from_env_missing() {
+        let flags = extra_flags_from_env(&["TM_TEST_EXTRA_FLAGS_NOT_SET"]);
+        assert!(flags.is_empty());
+    }
+}
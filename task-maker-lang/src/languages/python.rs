@@ -1,3 +1,4 @@
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
 use regex::Regex;
@@ -5,7 +6,7 @@ use task_maker_dag::ExecutionCommand;
 
 use crate::language::Language;
 use crate::languages::find_dependencies;
-use crate::Dependency;
+use crate::{Dependency, SourceWarning};
 
 /// The Python language
 #[derive(Debug)]
@@ -58,6 +59,44 @@ impl Language for LanguagePython {
     fn runtime_dependencies(&self, path: &Path) -> Vec<Dependency> {
         find_python_deps(path)
     }
+
+    fn source_warnings(&self, path: &Path) -> Vec<SourceWarning> {
+        check_shebang(path).into_iter().collect()
+    }
+}
+
+/// Check whether the shebang of the file asks for Python 2. The programs are always run with
+/// python3, so they may fail in cryptic ways (e.g. with a `SyntaxError` on `print x`).
+///
+/// The `from __future__` imports of the features that are the default in Python 3 are mentioned
+/// in the note, since they confirm that the file has been written for Python 2.
+fn check_shebang(path: &Path) -> Option<SourceWarning> {
+    lazy_static! {
+        static ref PYTHON2: Regex =
+            Regex::new(r"^#!.*\bpython2(\.\d+)?\s*$").expect("Invalid regex");
+        static ref FUTURE: Regex = Regex::new(
+            r"(?m)^\s*from\s+__future__\s+import\s+.*\b(print_function|division|unicode_literals)\b",
+        )
+        .expect("Invalid regex");
+    }
+    let file = std::fs::File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+    let mut shebang = String::new();
+    reader.read_line(&mut shebang).ok()?;
+    let shebang = shebang.trim_end();
+    if !PYTHON2.is_match(shebang) {
+        return None;
+    }
+    let mut note = format!("The shebang is `{shebang}`, but the program is run with python3.");
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    if FUTURE.is_match(&content) {
+        note +=
+            " The file also uses `from __future__` imports, so it was likely written for Python 2.";
+    }
+    Some(SourceWarning {
+        message: "the shebang asks for Python 2, which is not supported".into(),
+        note: Some(note),
+    })
 }
 
 /// Extract all the dependencies of a python file recursively.
@@ -117,6 +156,46 @@ mod tests {
         assert_that(&args).is_equal_to(vec!["script.boh".to_string(), "arg".to_string()]);
     }
 
+    #[test]
+    fn test_check_shebang_python2() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("script.py");
+        write(&path, "#!/usr/bin/env python2.7\nprint 42\n").unwrap();
+        let warnings = LanguagePython::new().source_warnings(&path);
+        assert_that(&warnings).has_length(1);
+        let note = warnings[0].note.as_ref().unwrap();
+        assert_that(note).contains("`#!/usr/bin/env python2.7`");
+        assert_that(note).does_not_contain("__future__");
+    }
+
+    #[test]
+    fn test_check_shebang_python2_future() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("script.py");
+        write(
+            &path,
+            "#!/usr/bin/python2\nfrom __future__ import print_function\nprint(42)\n",
+        )
+        .unwrap();
+        let warnings = LanguagePython::new().source_warnings(&path);
+        assert_that(&warnings).has_length(1);
+        let note = warnings[0].note.as_ref().unwrap();
+        assert_that(note).contains("`#!/usr/bin/python2`");
+        assert_that(note).contains("__future__");
+    }
+
+    #[test]
+    fn test_check_shebang_python3() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let path = tmpdir.path().join("script.py");
+        write(&path, "#!/usr/bin/env python3\nprint(42)\n").unwrap();
+        assert_that(&LanguagePython::new().source_warnings(&path)).is_empty();
+        write(&path, "print(42)\n").unwrap();
+        assert_that(&LanguagePython::new().source_warnings(&path)).is_empty();
+        let missing = tmpdir.path().join("missing.py");
+        assert_that(&LanguagePython::new().source_warnings(&missing)).is_empty();
+    }
+
     #[test]
     fn test_extract_imports() {
         let tmpdir = tempfile::TempDir::new().unwrap();
@@ -5,10 +5,18 @@ use task_maker_dag::ExecutionCommand;
 use crate::language::{
     CompilationSettings, CompiledLanguageBuilder, Language, SimpleCompiledLanguageBuilder,
 };
+use crate::languages::extra_flags_from_env;
 
 /// Configuration of the Rust language to use.
 #[derive(Clone, Debug)]
 pub struct LanguageRustConfiguration {
+    /// Compiler to use (e.g. ExecutionCommand::system("rustc") ).
+    pub compiler: ExecutionCommand,
+    /// Edition of the language to compile the sources with (e.g. 2021).
+    pub edition: String,
+    /// Extra flags to pass to the compiler. They are added after the default ones, so they can
+    /// override them (e.g. `-C opt-level=3`).
+    pub extra_flags: Vec<String>,
     /// Path to the rustup home in the worker.
     ///
     /// We cannot know if and where rustup is installed in the worker machines, and we cannot simply
@@ -22,11 +30,23 @@ pub struct LanguageRustConfiguration {
 }
 
 impl LanguageRustConfiguration {
-    fn from_env() -> Self {
+    /// Get the configuration of Rust from the environment variables. Unlike the other languages,
+    /// `$RUSTC` and `$RUSTFLAGS` are not used: cargo sets them for the processes it runs, and they
+    /// are meant for building task-maker, not the solutions. The extra flags are read only from
+    /// `$TM_RUSTFLAGS`.
+    pub fn from_env() -> Self {
+        let compiler = std::env::var_os("TM_RUSTC").unwrap_or_else(|| "rustc".into());
+        let edition = std::env::var("TM_RUST_EDITION").unwrap_or_else(|_| "2021".into());
+        let extra_flags = extra_flags_from_env(&["TM_RUSTFLAGS"]);
         let rustup_home_path = std::env::var_os("TM_RUSTUP_HOME")
             .or_else(|| std::env::var_os("RUSTUP_HOME"))
             .map(Into::into);
-        Self { rustup_home_path }
+        Self {
+            compiler: ExecutionCommand::System(compiler.into()),
+            edition,
+            extra_flags,
+            rustup_home_path,
+        }
     }
 }
 
@@ -72,7 +92,7 @@ impl Language for LanguageRust {
             self,
             source,
             settings,
-            ExecutionCommand::system("rustc"),
+            self.config.compiler.clone(),
         );
         metadata.grader_only();
         // Use a fixed name for the source file, so that the grader can import it.
@@ -80,10 +100,15 @@ impl Language for LanguageRust {
         let binary_name = metadata.binary_name.clone();
         metadata
             .add_arg("-O")
+            .add_arg("--edition")
+            .add_arg(self.config.edition.clone())
             .add_arg("--cfg")
             .add_arg("EVAL")
             .add_arg("-o")
             .add_arg(binary_name);
+        for arg in &self.config.extra_flags {
+            metadata.add_arg(arg);
+        }
         if metadata.settings.list_static {
             metadata
                 .add_arg("--target")
@@ -132,7 +157,26 @@ mod tests {
             .unwrap();
         let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
 
-        assert_that(&comp.executions[0].args).contains("source.rs".to_string());
+        let args = &comp.executions[0].args;
+        assert_that(args).contains("source.rs".to_string());
+        assert_that(args).contains(lang.config.edition.clone());
+    }
+
+    #[test]
+    fn test_compilation_args_extra_flags() {
+        let tmp = setup();
+
+        let mut lang = LanguageRust::new();
+        lang.config.extra_flags = vec!["-C".into(), "opt-level=3".into()];
+        let mut builder = lang
+            .compilation_builder(&tmp.path().join("foo.rs"), CompilationSettings::default())
+            .unwrap();
+        let (comp, _exec) = builder.finalize(&mut ExecutionDAG::new()).unwrap();
+
+        let args = &comp.executions[0].args;
+        let position = |arg: &str| args.iter().position(|a| a == arg).unwrap();
+        assert!(position("opt-level=3") > position("-O"));
+        assert_eq!(position("opt-level=3"), position("-C") + 1);
     }
 
     #[test]
@@ -42,6 +42,7 @@ use crate::language::Language;
 mod grader_map;
 mod language;
 mod languages;
+pub mod sanitizers;
 mod source_file;
 
 /// A dependency of an execution, all the sandbox paths must be relative and inside of the sandbox.
@@ -58,6 +59,16 @@ pub struct Dependency {
     pub executable: bool,
 }
 
+/// A problem found in a source file that doesn't prevent it from running, but that likely makes it
+/// behave differently from what its author expects.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SourceWarning {
+    /// The description of the problem.
+    pub message: String,
+    /// Additional details about the problem, if any.
+    pub note: Option<String>,
+}
+
 /// Manager of all the known languages, you should use this to get
 /// [`Language`](languages/trait.Language.html) instances.
 pub struct LanguageManager {
@@ -147,6 +158,7 @@ mod tests {
 
     use super::*;
     use crate::languages::cpp::{LanguageCpp, LanguageCppConfiguration};
+    use crate::languages::rust::LanguageRust;
 
     #[test]
     fn test_detect_language() {
@@ -162,6 +174,12 @@ mod tests {
         assert_that(&lang.name()).is_equal_to(name);
     }
 
+    #[test]
+    fn test_detect_language_rust() {
+        let lang = LanguageManager::detect_language("x.rs").unwrap();
+        assert_that(&lang.name()).is_equal_to(LanguageRust::new().name());
+    }
+
     #[test]
     fn test_detect_language_unknown() {
         let lang = LanguageManager::detect_language("foo.blah");
@@ -0,0 +1,72 @@
+//! Support for running the C/C++ programs compiled with the address and undefined behaviour
+//! sanitizers.
+
+use task_maker_dag::ExecutionLimits;
+
+/// The flags to pass to the C/C++ compilers for enabling the sanitizers.
+pub const SANITIZER_FLAGS: &[&str] = &["-fsanitize=address,undefined", "-fno-omit-frame-pointer"];
+
+/// How many times the time limits are increased for a program compiled with the sanitizers.
+pub const SANITIZER_TIME_MULTIPLIER: f64 = 3.0;
+
+/// The lines that mark the start of a report of one of the sanitizers.
+const SANITIZER_REPORT_MARKERS: &[&str] = &[
+    "ERROR: AddressSanitizer",
+    "ERROR: LeakSanitizer",
+    "runtime error:",
+];
+
+/// Relax the limits of a program compiled with the sanitizers, which is slower and uses a lot of
+/// virtual memory. The time limits are multiplied by `SANITIZER_TIME_MULTIPLIER` and the memory
+/// limit is removed.
+pub fn relax_limits(limits: &mut ExecutionLimits) {
+    limits.cpu_time = limits.cpu_time.map(|t| t * SANITIZER_TIME_MULTIPLIER);
+    limits.wall_time = limits.wall_time.map(|t| t * SANITIZER_TIME_MULTIPLIER);
+    limits.memory = None;
+}
+
+/// Search in the stderr of a program compiled with the sanitizers the report of a sanitizer,
+/// returning it starting from the first line that reports an error.
+pub fn find_sanitizer_report(stderr: &[u8]) -> Option<String> {
+    let stderr = String::from_utf8_lossy(stderr);
+    let start = stderr
+        .lines()
+        .position(|line| SANITIZER_REPORT_MARKERS.iter().any(|m| line.contains(m)))?;
+    Some(stderr.lines().skip(start).collect::<Vec<_>>().join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relax_limits() {
+        let mut limits = ExecutionLimits::unrestricted();
+        limits.cpu_time(1.0).wall_time(2.0).memory(1024);
+        relax_limits(&mut limits);
+        assert_eq!(limits.cpu_time, Some(SANITIZER_TIME_MULTIPLIER));
+        assert_eq!(limits.wall_time, Some(2.0 * SANITIZER_TIME_MULTIPLIER));
+        assert_eq!(limits.memory, None);
+    }
+
+    #[test]
+    fn test_find_sanitizer_report_asan() {
+        let stderr =
+            b"debug line\n==42==ERROR: AddressSanitizer: heap-buffer-overflow\n#0 0x1 in main";
+        let report = find_sanitizer_report(stderr).unwrap();
+        assert!(report.starts_with("==42==ERROR: AddressSanitizer"));
+        assert!(report.ends_with("in main"));
+    }
+
+    #[test]
+    fn test_find_sanitizer_report_ubsan() {
+        let stderr = b"sol.cpp:5:7: runtime error: signed integer overflow";
+        let report = find_sanitizer_report(stderr).unwrap();
+        assert!(report.contains("signed integer overflow"));
+    }
+
+    #[test]
+    fn test_find_sanitizer_report_none() {
+        assert!(find_sanitizer_report(b"just some debug output").is_none());
+    }
+}
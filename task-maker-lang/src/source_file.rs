@@ -1,14 +1,14 @@
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use task_maker_dag::{
     Execution, ExecutionDAG, ExecutionGroupUuid, ExecutionTag, File, FileUuid, Priority,
 };
 
 use crate::language::{CompilationSettings, Language};
-use crate::{GraderMap, LanguageManager};
+use crate::{GraderMap, LanguageManager, SourceWarning};
 
 /// Length of the stdout/stderr of the compilers to capture.
 const COMPILATION_CONTENT_LENGTH: usize = 10 * 1024;
@@ -39,6 +39,15 @@ pub struct SourceFile {
     pub write_bin_to: Option<PathBuf>,
     /// Whether this source file should be statically linked.
     pub link_static: bool,
+    /// Whether this source file should be compiled with the sanitizers.
+    #[serde(default)]
+    pub sanitize: bool,
+    /// The optimization level to compile this source file with, instead of the default one.
+    #[serde(default)]
+    pub opt_level: Option<u8>,
+    /// The problems found in the source file by its language when it has been created.
+    #[serde(default)]
+    pub warnings: Vec<SourceWarning>,
 }
 
 impl SourceFile {
@@ -58,16 +67,20 @@ impl SourceFile {
     ) -> Option<SourceFile> {
         let path = path.into();
         let base_path = base_path.into();
-        let lang = LanguageManager::detect_language(&path);
+        let lang = LanguageManager::detect_language(&path)?;
+        let warnings = lang.source_warnings(&path);
         Some(SourceFile {
             path,
             base_path,
-            language: lang?,
+            language: lang,
             executable: Arc::new(Mutex::new(None)),
             grader_map,
             write_bin_to: write_bin_to.map(|p| p.into()),
             copy_exe: false,
             link_static: false,
+            sanitize: false,
+            opt_level: None,
+            warnings,
         })
     }
 
@@ -178,6 +191,16 @@ impl SourceFile {
                     .context("Failed to provide grader dependency")?;
             }
         }
+        for mount in self.language.runtime_mounts() {
+            if !mount.exists() {
+                bail!(
+                    "The {} runtime requires {}, which does not exist",
+                    self.language.name(),
+                    mount.display()
+                );
+            }
+            exec.limits_mut().add_extra_readable_dir(mount);
+        }
         self.language.custom_limits(exec.limits_mut());
         // some languages (e.g. Python) may behave strangely with LC_ALL=C
         exec.copy_env("LANG");
@@ -198,6 +221,26 @@ impl SourceFile {
         }
     }
 
+    /// Compile the source file with the address and undefined behaviour sanitizers. Returns `false`
+    /// if the language doesn't support them. The limits of the executions of the binary should be
+    /// relaxed with [`sanitizers::relax_limits`](sanitizers/fn.relax_limits.html).
+    pub fn sanitize(&mut self) -> bool {
+        if self.language.supports_sanitizers() {
+            self.sanitize = true;
+        }
+        self.sanitize
+    }
+
+    /// Compile the source file with the given optimization level (e.g. `0` for `-O0`) instead of
+    /// the default one. Returns `false`, leaving the default, if the language doesn't support it.
+    pub fn set_opt_level(&mut self, opt_level: u8) -> bool {
+        if !self.language.supports_opt_level() {
+            return false;
+        }
+        self.opt_level = Some(opt_level);
+        true
+    }
+
     /// Prepare the source file if needed and return the executable file. If the compilation step
     /// was not executed yet the handle to the compilation execution is also returned.
     pub fn executable(
@@ -259,6 +302,8 @@ impl SourceFile {
         let settings = CompilationSettings {
             write_to: write_to.map(Into::into),
             list_static: self.link_static,
+            sanitize: self.sanitize,
+            opt_level: self.opt_level,
             copy_exe: dag.config_mut().copy_exe || self.copy_exe,
         };
         if let Some(mut metadata) = self.language.compilation_builder(&self.path, settings) {
@@ -268,13 +313,20 @@ impl SourceFile {
             let (mut comp, exec) = metadata.finalize(dag)?;
             comp.priority = COMPILATION_PRIORITY;
             comp.tag = Some(ExecutionTag::from("compilation"));
+            let time_limit = dag.config_mut().compilation_time_limit;
+            let memory_limit = dag.config_mut().compilation_memory_limit;
             for exec in &mut comp.executions {
                 exec.limits
+                    .cpu_time(time_limit)
+                    .wall_time(time_limit * 1.5 + 1.0) // some margin
                     .allow_multiprocess()
                     // the compilers may need to store some temp files
                     .read_only(false)
                     .mount_tmpfs(true)
                     .mount_proc(true);
+                if let Some(memory_limit) = memory_limit {
+                    exec.limits.memory(memory_limit);
+                }
                 self.language.custom_limits(exec.limits_mut());
                 exec.capture_stdout(Some(COMPILATION_CONTENT_LENGTH));
                 exec.capture_stderr(Some(COMPILATION_CONTENT_LENGTH));
@@ -388,4 +440,89 @@ mod tests {
         assert!(!exec_skipped.load(Ordering::Relaxed));
         assert!(cwd.path().join("bin").exists());
     }
+
+    #[test]
+    fn test_source_file_compilation_limits() {
+        let cwd = TempDir::new().unwrap();
+
+        let mut dag = ExecutionDAG::new();
+        dag.config_mut()
+            .compilation_time_limit(10.0)
+            .compilation_memory_limit(Some(1024));
+
+        let source_path = cwd.path().join("source.cpp");
+        std::fs::File::create(&source_path)
+            .unwrap()
+            .write_all(b"int main() {return 0;}")
+            .unwrap();
+        let source = SourceFile::new(&source_path, "", None, None::<PathBuf>).unwrap();
+        let comp = source.prepare(&mut dag).unwrap().unwrap();
+
+        let group = &dag.data.execution_groups[&comp];
+        for exec in &group.executions {
+            assert_eq!(exec.limits.cpu_time, Some(10.0));
+            assert_eq!(exec.limits.wall_time, Some(16.0));
+            assert_eq!(exec.limits.memory, Some(1024));
+        }
+    }
+
+    /// A non-compiled language that needs some extra paths mounted in the sandbox.
+    #[derive(Debug)]
+    struct LanguageWithMounts(Vec<PathBuf>);
+
+    impl Language for LanguageWithMounts {
+        fn name(&self) -> &'static str {
+            "WithMounts"
+        }
+
+        fn extensions(&self) -> Vec<&'static str> {
+            vec!["mnt"]
+        }
+
+        fn need_compilation(&self) -> bool {
+            false
+        }
+
+        fn inline_comment_prefix(&self) -> Option<&'static str> {
+            None
+        }
+
+        fn runtime_mounts(&self) -> Vec<PathBuf> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_source_file_runtime_mounts() {
+        let cwd = TempDir::new().unwrap();
+        let source_path = cwd.path().join("source.sh");
+        std::fs::write(&source_path, "true").unwrap();
+        let mut source = SourceFile::new(&source_path, "", None, None::<PathBuf>).unwrap();
+        source.language = Arc::new(LanguageWithMounts(vec![cwd.path().to_owned()]));
+
+        let mut dag = ExecutionDAG::new();
+        let (_, exec) = source.execute(&mut dag, "Testing exec", vec![]).unwrap();
+        assert!(exec
+            .limits
+            .extra_readable_dirs
+            .contains(&cwd.path().to_owned()));
+    }
+
+    #[test]
+    fn test_source_file_runtime_mounts_missing() {
+        let cwd = TempDir::new().unwrap();
+        let source_path = cwd.path().join("source.sh");
+        std::fs::write(&source_path, "true").unwrap();
+        let mut source = SourceFile::new(&source_path, "", None, None::<PathBuf>).unwrap();
+        let missing = cwd.path().join("missing");
+        source.language = Arc::new(LanguageWithMounts(vec![missing.clone()]));
+
+        let mut dag = ExecutionDAG::new();
+        let err = source
+            .execute(&mut dag, "Testing exec", vec![])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("WithMounts"));
+        assert!(err.contains(&missing.display().to_string()));
+    }
 }
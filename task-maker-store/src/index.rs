@@ -6,19 +6,29 @@ use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::time::SystemTime;
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
 use const_format::formatcp;
 use serde::{Deserialize, Serialize};
 
-use crate::{FileStore, FileStoreKey, LockedFiles};
+use crate::{FileStore, FileStoreKey, IntegrityMode, LockedFiles, DEFAULT_BLOCK_SIZE};
+
+/// The version of the format of the index file. It must be bumped every time the serialization of
+/// `FileStoreIndex` changes (including the types it contains), so that the indices written with the
+/// old format are discarded instead of failing to load.
+const INDEX_FORMAT_VERSION: u32 = 1;
 
 /// Magic string that is prepended to the index file to avoid accidental loading of invalid index
-/// files.
+/// files: the version of task-maker followed by the version of the format.
 ///
 /// The newline at the end of the string is required. For example, let's say there are 2 versions:
 /// v0.1 and v0.11; running v0.11 first, and then v0.1, without the newline the magic of the old
 /// version is a prefix of the magic of the new version.
-const MAGIC: &[u8] = formatcp!("task-maker-store v{}\n", env!("CARGO_PKG_VERSION")).as_bytes();
+const MAGIC: &[u8] = formatcp!(
+    "task-maker-store v{} format {}\n",
+    env!("CARGO_PKG_VERSION"),
+    INDEX_FORMAT_VERSION
+)
+.as_bytes();
 
 /// An entry of a file inside the file store.
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
@@ -27,6 +37,11 @@ struct FileStoreIndexItem {
     size: u64,
     /// Time of the last read/write of this file.
     last_access: SystemTime,
+    /// The xxHash checksum of the file, if computed when the file was stored.
+    checksum: Option<u64>,
+    /// Whether the file is stored compressed. In this case `size` is the size of the compressed
+    /// file.
+    compressed: bool,
 }
 
 impl Ord for FileStoreIndexItem {
@@ -41,12 +56,17 @@ impl PartialOrd for FileStoreIndexItem {
 }
 
 /// Index with all the files known, allowing efficient LRU file flushing.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct FileStoreIndex {
-    /// The sum of the size of all the files in the index.
+    /// The space on disk used by all the files in the index, with the size of each file rounded up
+    /// to a multiple of `block_size`.
     total_size: u64,
     /// The list of all the files known in the index.
     known_files: HashMap<FileStoreKey, FileStoreIndexItem>,
+    /// The integrity mode the store was last opened with.
+    pub(crate) integrity_mode: IntegrityMode,
+    /// The size of the blocks of the file system, a file occupies at least one block on disk.
+    block_size: u64,
 }
 
 impl FileStoreIndex {
@@ -58,6 +78,8 @@ impl FileStoreIndex {
             return Ok(FileStoreIndex {
                 total_size: 0,
                 known_files: HashMap::new(),
+                integrity_mode: IntegrityMode::default(),
+                block_size: DEFAULT_BLOCK_SIZE,
             });
         }
 
@@ -67,11 +89,14 @@ impl FileStoreIndex {
         let mut reader = BufReader::new(file);
         let mut magic = [0u8; MAGIC.len()];
 
-        if reader.read_exact(&mut magic).is_ok_and(|_| magic != MAGIC) {
+        // an index shorter than the magic has been written by an older version
+        if reader.read_exact(&mut magic).is_err() || magic != MAGIC {
             info!("FileStore version mismatch:\nExpected: {MAGIC:?}\nFound: {magic:?}");
             return Ok(FileStoreIndex {
                 total_size: 0,
                 known_files: HashMap::new(),
+                integrity_mode: IntegrityMode::default(),
+                block_size: DEFAULT_BLOCK_SIZE,
             });
         }
 
@@ -89,16 +114,92 @@ impl FileStoreIndex {
 
         let file = File::create(&tmp)
             .with_context(|| format!("Failed to create index file at {}", tmp.display()))?;
-        let mut writer = BufWriter::new(file);
+        self.write_to(BufWriter::new(file))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to move {} -> {}", tmp.display(), path.display()))?;
+        Ok(())
+    }
 
+    /// Write a dump of this index to a stream.
+    pub(crate) fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
         writer
             .write_all(MAGIC)
             .context("Failed to write cache magic number")?;
+        bincode::serialize_into(writer, &self).context("Failed to write index")
+    }
 
-        bincode::serialize_into(writer, &self).context("Failed to write index")?;
-        std::fs::rename(&tmp, path)
-            .with_context(|| format!("Failed to move {} -> {}", tmp.display(), path.display()))?;
-        Ok(())
+    /// Read an index written by `write_to`. Unlike `load`, an index of another version of the store
+    /// is an error.
+    pub(crate) fn read_from<R: Read>(mut reader: R) -> Result<FileStoreIndex, Error> {
+        let mut magic = [0u8; MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .context("Failed to read cache magic number")?;
+        if magic != MAGIC {
+            bail!("The index is from a different version of task-maker");
+        }
+        bincode::deserialize_from(reader).context("Failed to deserialize index")
+    }
+
+    /// The keys of all the files in the index.
+    pub(crate) fn keys(&self) -> Vec<FileStoreKey> {
+        self.known_files.keys().cloned().collect()
+    }
+
+    /// Make a new index with only the specified files of this index.
+    pub(crate) fn subset(&self, keys: &[FileStoreKey]) -> FileStoreIndex {
+        let known_files: HashMap<_, _> = keys
+            .iter()
+            .filter_map(|key| Some((key.clone(), self.known_files.get(key)?.clone())))
+            .collect();
+        FileStoreIndex {
+            total_size: known_files
+                .values()
+                .map(|file| self.disk_size(file.size))
+                .sum(),
+            known_files,
+            integrity_mode: self.integrity_mode,
+            block_size: self.block_size,
+        }
+    }
+
+    /// The block size used for computing the space used by the files.
+    pub(crate) fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Change the block size used for computing the space used by the files, recomputing the size
+    /// of the index.
+    pub(crate) fn set_block_size(&mut self, block_size: u64) {
+        self.block_size = block_size;
+        self.total_size = self
+            .known_files
+            .values()
+            .map(|file| self.disk_size(file.size))
+            .sum();
+    }
+
+    /// The space on disk used by a file of the given size: the size rounded up to a multiple of the
+    /// block size.
+    pub(crate) fn disk_size(&self, size: u64) -> u64 {
+        size.div_ceil(self.block_size) * self.block_size
+    }
+
+    /// Use the last access time of the files in `other`, for the files known to both the indices.
+    pub(crate) fn restore_access_times(&mut self, other: &FileStoreIndex) {
+        for (key, file) in self.known_files.iter_mut() {
+            if let Some(other) = other.known_files.get(key) {
+                file.last_access = other.last_access;
+            }
+        }
+    }
+
+    /// The keys of the files stored compressed.
+    pub(crate) fn compressed_keys(&self) -> impl Iterator<Item = &FileStoreKey> {
+        self.known_files
+            .iter()
+            .filter(|(_, file)| file.compressed)
+            .map(|(key, _)| key)
     }
 
     /// Mark a file as accessed, bumping its position in the LRU.
@@ -108,12 +209,41 @@ impl FileStoreIndex {
         }
     }
 
-    /// Add a file in the index if not already present.
-    pub(crate) fn add<P: AsRef<Path>>(&mut self, key: FileStoreKey, path: P) -> Result<(), Error> {
+    /// The size and the checksum of a file, if it's in the index.
+    pub(crate) fn checksum(&self, key: &FileStoreKey) -> Option<(u64, Option<u64>)> {
+        self.known_files
+            .get(key)
+            .map(|file| (file.size, file.checksum))
+    }
+
+    /// The size of a file as stored on disk, if it's in the index.
+    pub(crate) fn size(&self, key: &FileStoreKey) -> Option<u64> {
+        self.known_files.get(key).map(|file| file.size)
+    }
+
+    /// Remove a file from the index, for example because it's corrupted.
+    pub(crate) fn remove(&mut self, key: &FileStoreKey) {
+        if let Some(file) = self.known_files.remove(key) {
+            self.total_size -= self.disk_size(file.size);
+        }
+    }
+
+    /// Add a file in the index if not already present, with its checksum if computed. `path` is the
+    /// file as stored on disk, compressed or not.
+    ///
+    /// Returns the space used by the file that has been added to the size of the index.
+    pub(crate) fn add<P: AsRef<Path>>(
+        &mut self,
+        key: FileStoreKey,
+        path: P,
+        checksum: Option<u64>,
+        compressed: bool,
+    ) -> Result<u64, Error> {
         let path = path.as_ref();
         match self.known_files.entry(key) {
             Entry::Occupied(mut entry) => {
                 entry.get_mut().last_access = SystemTime::now();
+                Ok(0)
             }
             Entry::Vacant(entry) => {
                 let metadata = std::fs::metadata(path)
@@ -121,11 +251,14 @@ impl FileStoreIndex {
                 entry.insert(FileStoreIndexItem {
                     size: metadata.len(),
                     last_access: SystemTime::now(),
+                    checksum,
+                    compressed,
                 });
-                self.total_size += metadata.len();
+                let disk_size = self.disk_size(metadata.len());
+                self.total_size += disk_size;
+                Ok(disk_size)
             }
         }
-        Ok(())
     }
 
     /// Whether this file store needs to flush away some files to free space.
@@ -164,13 +297,23 @@ impl FileStoreIndex {
             if locked_files.ref_counts.contains_key(&key) {
                 surviving.push((key, entry));
             } else {
-                self.total_size -= entry.size;
-                removed += entry.size;
+                let disk_size = self.disk_size(entry.size);
+                self.total_size -= disk_size;
+                removed += disk_size;
 
                 let path = file_store.key_to_path(&key);
-                debug!("Removing file {:?} claiming {}KiB", path, entry.size / 1024);
-                if let Err(e) = FileStore::remove_file(&path) {
-                    warn!("Cannot flush file {path:?}: {e}");
+                let stored_path = if entry.compressed {
+                    file_store.key_to_compressed_path(&key)
+                } else {
+                    path.clone()
+                };
+                debug!(
+                    "Removing file {:?} claiming {}KiB",
+                    stored_path,
+                    disk_size / 1024
+                );
+                if let Err(e) = FileStore::remove_file(&stored_path) {
+                    warn!("Cannot flush file {stored_path:?}: {e}");
                 }
                 let base_path = file_store.base_path.canonicalize().with_context(|| {
                     format!(
@@ -216,7 +359,8 @@ mod tests {
     use pretty_assertions::{assert_eq, assert_ne};
     use tempfile::TempDir;
 
-    use crate::{FileStore, FileStoreHandle, FileStoreKey, ReadFileIterator};
+    use crate::index::FileStoreIndex;
+    use crate::{FileStore, FileStoreHandle, FileStoreKey, IntegrityMode, ReadFileIterator};
 
     fn get_cwd() -> TempDir {
         TempDir::new().unwrap()
@@ -238,10 +382,27 @@ mod tests {
         store.store(&key, iter).unwrap()
     }
 
+    #[test]
+    fn test_load_index_old_format() {
+        let cwd = get_cwd();
+        let path = cwd.path().join("index.bin");
+        // the magic used before the format version was introduced, followed by an index without
+        // some of the current fields
+        let mut content = format!("task-maker-store v{}\n", env!("CARGO_PKG_VERSION")).into_bytes();
+        content.extend_from_slice(&[0; 16]);
+        std::fs::write(&path, content).unwrap();
+        let index = FileStoreIndex::load(&path).unwrap();
+        assert_eq!(index.total_size, 0);
+        assert_eq!(index.known_files.len(), 0);
+
+        std::fs::write(&path, b"short").unwrap();
+        assert_eq!(FileStoreIndex::load(&path).unwrap().known_files.len(), 0);
+    }
+
     #[test]
     fn test_empty_index() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         assert_eq!(store.max_store_size, 200);
         assert_eq!(store.min_store_size, 100);
         let index = store.index.lock().unwrap();
@@ -253,14 +414,14 @@ mod tests {
     fn test_load_index() {
         let cwd = get_cwd();
         {
-            let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+            let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
             add_file_to_store(&store, 50);
             let index = store.index.lock().unwrap();
             assert_eq!(index.total_size, 50);
             assert_eq!(index.known_files.len(), 1);
             // store index on drop
         }
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         let index = store.index.lock().unwrap();
         assert_eq!(index.total_size, 50);
         assert_eq!(index.known_files.len(), 1);
@@ -269,7 +430,7 @@ mod tests {
     #[test]
     fn test_no_flush() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         add_file_to_store(&store, 10);
         add_file_to_store(&store, 20);
         add_file_to_store(&store, 30);
@@ -282,7 +443,7 @@ mod tests {
     #[test]
     fn test_no_duplicates() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         add_file_to_store(&store, 10);
         add_file_to_store(&store, 20);
         add_file_to_store(&store, 20);
@@ -295,7 +456,7 @@ mod tests {
     #[test]
     fn test_flush() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         let key1 = add_file_to_store(&store, 90).key.clone();
         let key2 = add_file_to_store(&store, 95).key.clone();
         store.maybe_flush(&mut store.index.lock().unwrap()).unwrap();
@@ -313,7 +474,7 @@ mod tests {
     #[test]
     fn test_flush_locked() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         let handle1 = add_file_to_store(&store, 90);
         let key2 = add_file_to_store(&store, 95).key.clone();
         store.maybe_flush(&mut store.index.lock().unwrap()).unwrap();
@@ -335,7 +496,7 @@ mod tests {
     #[test]
     fn test_flush_touch() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 200, 100).unwrap();
+        let store = FileStore::new(cwd.path(), 200, 100, IntegrityMode::Off, None, 1).unwrap();
         let handle = add_file_to_store(&store, 10);
         let mut index = store.index.lock().unwrap();
         let before = index.known_files[&handle.key].last_access;
@@ -346,4 +507,46 @@ mod tests {
         let after2 = index.known_files[&handle.key].last_access;
         assert_ne!(before, after2);
     }
+
+    #[test]
+    fn test_block_size() {
+        let cwd = get_cwd();
+        let store = FileStore::new(
+            cwd.path(),
+            4 * 4096,
+            2 * 4096,
+            IntegrityMode::Off,
+            None,
+            4096,
+        )
+        .unwrap();
+        add_file_to_store(&store, 4);
+        add_file_to_store(&store, 5000);
+        assert_eq!(store.index.lock().unwrap().total_size, 3 * 4096);
+        // the store is full, even if the files are much smaller than its size
+        let key3 = add_file_to_store(&store, 6).key.clone();
+        let index = store.index.lock().unwrap();
+        assert_eq!(index.total_size, 2 * 4096);
+        assert_eq!(index.known_files.len(), 2);
+        assert!(store.key_to_path(&key3).exists());
+    }
+
+    #[test]
+    fn test_block_size_reopen() {
+        let cwd = get_cwd();
+        {
+            let store =
+                FileStore::new(cwd.path(), 20000, 10000, IntegrityMode::Off, None, 1).unwrap();
+            add_file_to_store(&store, 10);
+            add_file_to_store(&store, 20);
+            assert_eq!(store.index.lock().unwrap().total_size, 30);
+        }
+        let store =
+            FileStore::new(cwd.path(), 20000, 10000, IntegrityMode::Off, None, 4096).unwrap();
+        let key = fake_file(cwd.path().join("temp.txt"), 123, 20);
+        let mut index = store.index.lock().unwrap();
+        assert_eq!(index.total_size, 2 * 4096);
+        index.remove(&key);
+        assert_eq!(index.total_size, 4096);
+    }
 }
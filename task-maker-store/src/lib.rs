@@ -2,7 +2,8 @@
 //!
 //! The files are stored in a read-only manner (removing the write bit permission) and their access
 //! is granted via their hash. The size of the store folder is limited to a specific amount and the
-//! least-recently-used files are removed automatically.
+//! least-recently-used files are removed automatically. Optionally, the files can be kept on disk
+//! compressed with zstd.
 //!
 //! The access to the store directory via this crate is exclusive even between processes.
 //!
@@ -11,7 +12,9 @@
 //! Storing a file into the store and getting it back later.
 //!
 //! ```
-//! use task_maker_store::{FileStore, FileStoreKey, ReadFileIterator};
+//! use task_maker_store::{
+//!     FileStore, FileStoreKey, IntegrityMode, ReadFileIterator, DEFAULT_BLOCK_SIZE,
+//! };
 //!
 //! # use anyhow::Error;
 //! # use std::fs;
@@ -21,8 +24,11 @@
 //! # let store_dir = tmp.path().join("store");
 //! # let path = tmp.path().join("file.txt");
 //! # fs::write(&path, "hello world")?;
-//! // make a new store based on a directory, this will lock if the store is already in use
-//! let store = FileStore::new(store_dir, 1000, 1000)?;
+//! // make a new store based on a directory, this will lock if the store is already in use. The
+//! // files are checked with a cheap checksum before getting them
+//! let store = FileStore::new(
+//!     store_dir, 1000, 1000, IntegrityMode::Fast, None, DEFAULT_BLOCK_SIZE
+//! )?;
 //! // compute the key of a file and make an iterator over its content
 //! let key = FileStoreKey::from_file(&path)?;
 //! let iter = ReadFileIterator::new(&path)?;
@@ -41,36 +47,72 @@
 extern crate log;
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Formatter;
-use std::io::Write;
+use std::io::{ErrorKind, Read, Write};
 use std::os::unix::prelude::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
-use anyhow::{bail, Context, Error};
+use anyhow::{anyhow, bail, Context, Error};
 use blake3::{hash, Hash, Hasher};
 use fslock::LockFile;
 pub use read_file_iterator::ReadFileIterator;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use xxhash_rust::xxh3::Xxh3;
 
 use crate::index::FileStoreIndex;
 
 mod index;
 mod read_file_iterator;
 
-/// Whether to check the file integrity on the store before getting it.
-const INTEGRITY_CHECKS_ENABLED: bool = false;
 /// The name of the lock of the file store.
 const STORE_LOCK_FILE: &str = "exclusive.lock";
 /// The name of the index of the file store.
 const STORE_INDEX_FILE: &str = "index.bin";
+/// Extension appended to the path of the files stored compressed.
+const COMPRESSED_EXTENSION: &str = ".zst";
+/// The default block size of the file system, used when computing the space used by the store.
+pub const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+/// How much the [`FileStore`] checks that a file is not corrupted before getting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum IntegrityMode {
+    /// Trust the files on disk.
+    #[default]
+    Off,
+    /// Compare the size and an xxHash checksum of the file with the ones computed when the file
+    /// was stored. The files stored without a checksum are checked only by their size.
+    Fast,
+    /// Compute again the hash of the file and compare it with its key.
+    Full,
+}
+
+impl std::str::FromStr for IntegrityMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "fast" => Ok(Self::Fast),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("Invalid integrity mode: {s}")),
+        }
+    }
+}
 
 /// Container with the ref counts of all the handles still alive.
 #[derive(Debug)]
 struct LockedFiles {
     /// Map from a `FileStoreKey` to the number of handles alive.
     ref_counts: HashMap<FileStoreKey, usize>,
+    /// The space still reserved by each [`Reservation`] alive, indexed by its id.
+    reservations: BTreeMap<usize, u64>,
+    /// The id of the next reservation.
+    next_reservation: usize,
+    /// The space on disk used by the uncompressed copies of the compressed files, indexed by their
+    /// key. A copy is removed when the last handle to its file is dropped.
+    materialized: HashMap<FileStoreKey, u64>,
 }
 
 /// A file store will manage all the files in the store directory.
@@ -94,6 +136,31 @@ pub struct FileStore {
     max_store_size: u64,
     /// Target size of the file store after the flush.
     min_store_size: u64,
+    /// How the files are checked before getting them.
+    integrity_mode: IntegrityMode,
+    /// The zstd level used to compress the new files, `None` to store them uncompressed.
+    compression: Option<i32>,
+}
+
+/// Some space of a `FileStore` reserved with [`FileStore::reserve`]. The files stored while the
+/// reservation is alive use its space, until it's exhausted. The space left is released when the
+/// reservation is dropped.
+#[derive(Debug)]
+pub struct Reservation {
+    /// The id of the reservation inside `LockedFiles`.
+    id: usize,
+    /// A reference to the locked files, where the reserved space is tracked.
+    locked_files: Arc<Mutex<LockedFiles>>,
+}
+
+/// Summary of an export of a `FileStore`, made with [`FileStore::export`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileStoreExport {
+    /// The files written to the stream.
+    pub exported: Vec<FileStoreKey>,
+    /// The files that were in the index when the export started, but that have been removed from
+    /// the store before being written to the stream.
+    pub vanished: Vec<FileStoreKey>,
 }
 
 /// Handle of a file in the `FileStore`, this must be computable given the content of the file, i.e.
@@ -113,6 +180,8 @@ pub struct FileStoreHandle {
     key: FileStoreKey,
     /// The path to the file on disk.
     path: PathBuf,
+    /// The path where the compressed copy of the file is, if the file is stored compressed.
+    compressed_path: PathBuf,
     /// A reference to the locked files. Will be used to remove self from the ref counts.
     locked_files: Arc<Mutex<LockedFiles>>,
 }
@@ -122,8 +191,17 @@ impl FileStore {
     /// `FileStore` is locking the directory. The locking is implemented via platform-specific file
     /// locking. Having two instances of the file store running concurrently is not safe.
     ///
+    /// If `compression` is set, the new files are stored compressed with zstd at that level. While
+    /// there are handles to a compressed file, an uncompressed copy of it is kept on disk at
+    /// [`FileStoreHandle::path`], and its size is counted in the size of the store. The files
+    /// already in the store are kept as they are, so the setting can be changed between runs.
+    ///
+    /// The size of the store is the space used on disk by its files: the size of each file is
+    /// rounded up to a multiple of `block_size`, which must be the block size of the file system
+    /// (usually [`DEFAULT_BLOCK_SIZE`]).
+    ///
     /// ```
-    /// use task_maker_store::FileStore;
+    /// use task_maker_store::{FileStore, IntegrityMode, DEFAULT_BLOCK_SIZE};
     ///
     /// # use anyhow::Error;
     /// # use std::fs;
@@ -133,7 +211,9 @@ impl FileStore {
     /// # let store_dir = dir.path();
     /// // make a new store based on a directory, this will lock if the store is already in use
     /// // somewhere
-    /// let store = FileStore::new(store_dir, 1000, 1000)?;
+    /// let store = FileStore::new(
+    ///     store_dir, 1000, 1000, IntegrityMode::Off, None, DEFAULT_BLOCK_SIZE
+    /// )?;
     /// // let store2 = FileStore::new(store_dir) // this will lock!!
     /// # Ok(())
     /// # }
@@ -142,19 +222,12 @@ impl FileStore {
         base_path: P,
         max_store_size: u64,
         min_store_size: u64,
+        integrity_mode: IntegrityMode,
+        compression: Option<i32>,
+        block_size: u64,
     ) -> Result<FileStore, Error> {
         let base_path = base_path.into();
-        debug!("Opening file store at {}", base_path.display());
-        std::fs::create_dir_all(&base_path).with_context(|| {
-            format!(
-                "Failed to create storage directory at {}",
-                base_path.display()
-            )
-        })?;
-        let lock_path = base_path.join(STORE_LOCK_FILE);
-        let mut lock = LockFile::open(&lock_path)
-            .with_context(|| format!("Failed to create lock file at {}", lock_path.display()))?;
-
+        let mut lock = FileStore::open_lock(&base_path)?;
         if !lock
             .try_lock()
             .context("Failed to obtain exclusive lock on storage")?
@@ -163,17 +236,117 @@ impl FileStore {
             lock.lock()
                 .context("Failed to obtain exclusive lock on storage")?;
         }
+        FileStore::with_lock(
+            base_path,
+            lock,
+            max_store_size,
+            min_store_size,
+            integrity_mode,
+            compression,
+            block_size,
+        )
+    }
+
+    /// Make a new `FileStore` in the specified base directory, like [`FileStore::new`], but return
+    /// `None` instead of waiting if another instance of a `FileStore` is locking the directory.
+    pub fn try_new<P: Into<PathBuf>>(
+        base_path: P,
+        max_store_size: u64,
+        min_store_size: u64,
+        integrity_mode: IntegrityMode,
+        compression: Option<i32>,
+        block_size: u64,
+    ) -> Result<Option<FileStore>, Error> {
+        let base_path = base_path.into();
+        let mut lock = FileStore::open_lock(&base_path)?;
+        if !lock
+            .try_lock()
+            .context("Failed to obtain exclusive lock on storage")?
+        {
+            return Ok(None);
+        }
+        FileStore::with_lock(
+            base_path,
+            lock,
+            max_store_size,
+            min_store_size,
+            integrity_mode,
+            compression,
+            block_size,
+        )
+        .map(Some)
+    }
 
-        let index = FileStoreIndex::load(base_path.join(STORE_INDEX_FILE))
+    /// Create the store directory and open, without locking it, its lock file.
+    fn open_lock(base_path: &Path) -> Result<LockFile, Error> {
+        debug!("Opening file store at {}", base_path.display());
+        std::fs::create_dir_all(base_path).with_context(|| {
+            format!(
+                "Failed to create storage directory at {}",
+                base_path.display()
+            )
+        })?;
+        let lock_path = base_path.join(STORE_LOCK_FILE);
+        LockFile::open(&lock_path)
+            .with_context(|| format!("Failed to create lock file at {}", lock_path.display()))
+    }
+
+    /// Make the `FileStore` in the directory whose lock has been acquired.
+    fn with_lock(
+        base_path: PathBuf,
+        lock: LockFile,
+        max_store_size: u64,
+        min_store_size: u64,
+        integrity_mode: IntegrityMode,
+        compression: Option<i32>,
+        block_size: u64,
+    ) -> Result<FileStore, Error> {
+        if block_size == 0 {
+            bail!("The block size of the store cannot be zero");
+        }
+        let mut index = FileStoreIndex::load(base_path.join(STORE_INDEX_FILE))
             .context("Failed to load storage index")?;
-        Ok(FileStore {
+        if index.integrity_mode != integrity_mode {
+            debug!(
+                "Switching the integrity mode of the store from {:?} to {integrity_mode:?}",
+                index.integrity_mode
+            );
+            index.integrity_mode = integrity_mode;
+        }
+        if index.block_size() != block_size {
+            debug!(
+                "Switching the block size of the store from {} to {block_size}",
+                index.block_size()
+            );
+            index.set_block_size(block_size);
+        }
+        let store = FileStore {
             base_path,
             _lock: lock,
             locked_files: Arc::new(Mutex::new(LockedFiles::new())),
             index: Arc::new(Mutex::new(index)),
             max_store_size,
             min_store_size,
-        })
+            integrity_mode,
+            compression,
+        };
+        store.remove_stale_copies();
+        Ok(store)
+    }
+
+    /// Remove the uncompressed copies of the compressed files left on disk by a previous instance
+    /// of the store that didn't drop all its handles, for example because it crashed.
+    fn remove_stale_copies(&self) {
+        let index = self.index.lock().unwrap();
+        for key in index.compressed_keys() {
+            let path = self.key_to_path(key);
+            if path.exists() {
+                debug!("Removing stale uncompressed copy {path:?}");
+                if let Err(e) = FileStore::remove_file(&path) {
+                    warn!("Cannot remove the uncompressed copy {path:?}: {e:?}");
+                }
+            }
+        }
     }
 
     /// Given an iterator of `Vec<u8>` consume all of it writing the content to the disk if the file
@@ -185,7 +358,9 @@ impl FileStore {
     /// Will return an handle to that file, keeping the file alive.
     ///
     /// ```
-    /// use task_maker_store::{FileStore, FileStoreKey, ReadFileIterator};
+    /// use task_maker_store::{
+    ///     FileStore, FileStoreKey, IntegrityMode, ReadFileIterator, DEFAULT_BLOCK_SIZE,
+    /// };
     ///
     /// # use anyhow::Error;
     /// # use std::fs;
@@ -195,7 +370,9 @@ impl FileStore {
     /// # let store_dir = tmp.path().join("store");
     /// # let path = tmp.path().join("file.txt");
     /// # fs::write(&path, "hello world")?;
-    /// let store = FileStore::new(store_dir, 1000, 1000)?;
+    /// let store = FileStore::new(
+    ///     store_dir, 1000, 1000, IntegrityMode::Off, None, DEFAULT_BLOCK_SIZE
+    /// )?;
     /// // compute the key of a file and make an iterator over its content
     /// let key = FileStoreKey::from_file(&path)?;
     /// let iter = ReadFileIterator::new(&path)?;
@@ -213,42 +390,41 @@ impl FileStore {
         I: IntoIterator<Item = Vec<u8>>,
     {
         let path = self.key_to_path(key);
+        let compressed_path = self.key_to_compressed_path(key);
         trace!("Storing {path:?}");
         // make the key to avoid racing while writing
         let handle = FileStoreHandle::new(self, key);
         if path.exists() {
             trace!("File {path:?} already exists");
             content.into_iter().last(); // consume all the iterator
+        } else if compressed_path.exists() {
+            // the file is already stored, but there is no uncompressed copy of it to point to
+            trace!("File {compressed_path:?} already exists");
+            FileStore::write_file(&path, content, false)?;
+            self.add_materialized(key, &path)?;
         } else {
-            // assuming moving files is atomic this should be MT-safe
-            let dir = path.parent().unwrap();
-            std::fs::create_dir_all(dir)
-                .with_context(|| format!("Cannot create directory at {}", dir.display()))?;
-            let tmpdir = tempfile::TempDir::new_in(path.parent().unwrap())
-                .context("Failed to create temporary directory for storing the file")?;
-            let tmpfile_path = tmpdir.path().join("file");
-            let mut tmpfile =
-                std::fs::File::create(&tmpfile_path).context("Failed to create temporary file")?;
-            if !content
-                .into_iter()
-                .map(|data| tmpfile.write_all(&data))
-                .all(|r| r.is_ok())
-            {
-                bail!("Failed to store file");
-            }
-            std::fs::rename(&tmpfile_path, &path).with_context(|| {
-                format!(
-                    "Failed to rename {} -> {}",
-                    tmpfile_path.display(),
-                    path.display()
-                )
-            })?;
-            FileStore::mark_readonly(&path).context("Failed to mark file as readonly")?;
+            // the checksum is cheap, but it's useless if it's never checked
+            let checksum =
+                FileStore::write_file(&path, content, self.integrity_mode != IntegrityMode::Off)?;
+            let stored_path = match self.compression {
+                Some(level) => {
+                    FileStore::compress_file(&path, &compressed_path, level)?;
+                    self.add_materialized(key, &path)?;
+                    compressed_path
+                }
+                None => path,
+            };
             {
                 let mut index = self.index.lock().unwrap();
-                index
-                    .add(key.clone(), path)
+                let added = index
+                    .add(
+                        key.clone(),
+                        stored_path,
+                        checksum,
+                        self.compression.is_some(),
+                    )
                     .context("Failed to add file to index")?;
+                self.locked_files.lock().unwrap().use_reserved(added);
                 self.maybe_flush(&mut index)?;
                 // FIXME: maybe this can be done less frequently
                 index
@@ -259,16 +435,57 @@ impl FileStore {
         Ok(handle)
     }
 
+    /// Reserve `expected_bytes` of the store for a batch of files that are going to be stored. While
+    /// the returned [`Reservation`] is alive, the flushes don't bring the store below
+    /// `min_store_size` plus the space still reserved, and the files stored use the reserved space.
+    ///
+    /// This avoids a flush in the middle of the batch evicting the files stored just before by
+    /// the batch itself.
+    ///
+    /// ```
+    /// use task_maker_store::{FileStore, FileStoreKey, IntegrityMode, DEFAULT_BLOCK_SIZE};
+    ///
+    /// # use anyhow::Error;
+    /// # use tempfile::TempDir;
+    /// # fn main() -> Result<(), Error> {
+    /// # let tmp = TempDir::new().unwrap();
+    /// # let store_dir = tmp.path().join("store");
+    /// let store = FileStore::new(
+    ///     store_dir, 100000, 50000, IntegrityMode::Off, None, DEFAULT_BLOCK_SIZE
+    /// )?;
+    /// let reservation = store.reserve(2 * DEFAULT_BLOCK_SIZE);
+    /// for content in [b"first".to_vec(), b"second".to_vec()] {
+    ///     let key = FileStoreKey::from_content(&content);
+    ///     store.store(&key, [content])?;
+    /// }
+    /// // the two files used all the reserved space
+    /// assert_eq!(reservation.remaining(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reserve(&self, expected_bytes: u64) -> Reservation {
+        let mut locked_files = self.locked_files.lock().unwrap();
+        let id = locked_files.next_reservation;
+        locked_files.next_reservation += 1;
+        locked_files.reservations.insert(id, expected_bytes);
+        Reservation {
+            id,
+            locked_files: self.locked_files.clone(),
+        }
+    }
+
     /// Returns an handle to the file with that key or `None` if it's not in the
     /// [`FileStore`](struct.FileStore.html).
     ///
-    /// This requires mutability because it will actively fix any corrupted or missing files in the
-    /// store.
+    /// Depending on the `IntegrityMode` of the store, the file is checked before returning the
+    /// handle: a corrupted file is removed from the store and `None` is returned.
     ///
     /// The file is guaranteed to not be flushed until all the handles to it get dropped.
     ///
     /// ```
-    /// use task_maker_store::{FileStore, FileStoreKey, ReadFileIterator};
+    /// use task_maker_store::{
+    ///     FileStore, FileStoreKey, IntegrityMode, ReadFileIterator, DEFAULT_BLOCK_SIZE,
+    /// };
     ///
     /// # use anyhow::Error;
     /// # use std::fs;
@@ -278,7 +495,9 @@ impl FileStore {
     /// # let store_dir = tmp.path().join("store");
     /// # let path = tmp.path().join("file.txt");
     /// # fs::write(&path, "hello world")?;
-    /// let store = FileStore::new(store_dir, 1000, 1000)?;
+    /// let store = FileStore::new(
+    ///     store_dir, 1000, 1000, IntegrityMode::Off, None, DEFAULT_BLOCK_SIZE
+    /// )?;
     /// let key = FileStoreKey::from_file(&path)?;
     /// # let iter = ReadFileIterator::new(&path)?;
     /// # let handle = store.store(&key, iter)?;
@@ -292,21 +511,135 @@ impl FileStore {
     /// ```
     pub fn get(&self, key: &FileStoreKey) -> Option<FileStoreHandle> {
         let path = self.key_to_path(key);
-        if !path.exists() {
+        let compressed_path = self.key_to_compressed_path(key);
+        if !path.exists() && !compressed_path.exists() {
             return None;
         }
-        if INTEGRITY_CHECKS_ENABLED && !self.check_integrity(key) {
+        if !self.check_integrity(key) {
             warn!("File {path:?} failed the integrity check");
-            if let Err(e) = FileStore::remove_file(&path) {
-                warn!("Cannot remove corrupted file: {e:?}");
+            for path in [&path, &compressed_path] {
+                if path.exists() {
+                    if let Err(e) = FileStore::remove_file(path) {
+                        warn!("Cannot remove corrupted file: {e:?}");
+                    }
+                }
             }
+            self.index.lock().unwrap().remove(key);
             return None;
         }
         {
             let mut index = self.index.lock().unwrap();
             index.touch(key);
         }
-        Some(FileStoreHandle::new(self, key))
+        let handle = FileStoreHandle::new(self, key);
+        match self.materialize(&handle) {
+            Ok(true) => Some(handle),
+            Ok(false) => None,
+            Err(e) => {
+                warn!("Cannot decompress {compressed_path:?}: {e:?}");
+                None
+            }
+        }
+    }
+
+    /// The size of a file as stored on disk (compressed, if it's stored compressed), without
+    /// marking it as used. Returns `None` if the file is not in the store.
+    pub fn stored_size(&self, key: &FileStoreKey) -> Option<u64> {
+        self.index.lock().unwrap().size(key)
+    }
+
+    /// Write a snapshot of the store to a tar stream: all the files, followed by the index.
+    ///
+    /// Only the index is locked, for the time needed to copy it, so the store can still be used
+    /// while the files are being written. The files that are removed from the store after the
+    /// index has been copied are skipped, and listed in the returned `FileStoreExport`.
+    pub fn export<W: Write>(&self, writer: W) -> Result<FileStoreExport, Error> {
+        let index = self.index.lock().unwrap().clone();
+        let mut builder = tar::Builder::new(writer);
+        let mut summary = FileStoreExport::default();
+        for key in index.keys() {
+            // the handle prevents the file from being flushed while it's being written
+            let handle = FileStoreHandle::new(self, &key);
+            let file = match self.materialize(&handle) {
+                Ok(true) => std::fs::File::open(handle.path()),
+                Ok(false) => Err(ErrorKind::NotFound.into()),
+                Err(e) => return Err(e.context(format!("Failed to decompress {key}"))),
+            };
+            let file = match file {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    warn!("File {key} has been removed from the store during the export");
+                    summary.vanished.push(key);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to open {}", handle.path().display()))
+                }
+            };
+            let mut header = tar::Header::new_gnu();
+            header.set_size(
+                file.metadata()
+                    .context("Failed to get file metadata")?
+                    .len(),
+            );
+            header.set_mode(0o444);
+            builder
+                .append_data(&mut header, key.suffix(), file)
+                .with_context(|| format!("Failed to export {key}"))?;
+            summary.exported.push(key);
+        }
+        let mut data = vec![];
+        index.subset(&summary.exported).write_to(&mut data)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, STORE_INDEX_FILE, data.as_slice())
+            .context("Failed to export the index")?;
+        builder.finish().context("Failed to finish the export")?;
+        Ok(summary)
+    }
+
+    /// Add to the store all the files of a tar stream written by [`FileStore::export`], returning
+    /// the number of files read. The content of each file is checked against its key.
+    pub fn import<R: Read>(&self, reader: R) -> Result<usize, Error> {
+        let mut archive = tar::Archive::new(reader);
+        let mut imported = 0;
+        let mut exported_index = None;
+        for entry in archive.entries().context("Failed to read the export")? {
+            let mut entry = entry.context("Failed to read the export")?;
+            let path = entry
+                .path()
+                .context("Invalid path in the export")?
+                .into_owned();
+            if path == Path::new(STORE_INDEX_FILE) {
+                exported_index = Some(FileStoreIndex::read_from(&mut entry)?);
+                continue;
+            }
+            let key = path
+                .file_name()
+                .and_then(|name| Hash::from_hex(name.to_string_lossy().as_bytes()).ok())
+                .map(|hash| FileStoreKey { hash })
+                .with_context(|| format!("Invalid file in the export: {}", path.display()))?;
+            let mut tmpfile = tempfile::NamedTempFile::new_in(&self.base_path)
+                .context("Failed to create temporary file")?;
+            std::io::copy(&mut entry, &mut tmpfile)
+                .with_context(|| format!("Failed to read {key} from the export"))?;
+            if FileStoreKey::from_file(tmpfile.path())? != key {
+                bail!("The content of {key} in the export is corrupted");
+            }
+            self.store(&key, ReadFileIterator::new(tmpfile.path())?)?;
+            imported += 1;
+        }
+        if let Some(exported_index) = exported_index {
+            let mut index = self.index.lock().unwrap();
+            index.restore_access_times(&exported_index);
+            index
+                .store(self.base_path.join(STORE_INDEX_FILE))
+                .context("Failed to store the index to file")?;
+        }
+        Ok(imported)
     }
 
     /// Path of the file to disk.
@@ -314,6 +647,131 @@ impl FileStore {
         self.base_path.join(key.suffix())
     }
 
+    /// Path of the compressed file to disk.
+    fn key_to_compressed_path(&self, key: &FileStoreKey) -> PathBuf {
+        let mut path = self.key_to_path(key).into_os_string();
+        path.push(COMPRESSED_EXTENSION);
+        path.into()
+    }
+
+    /// Make sure the uncompressed copy of the file of the handle is on disk, decompressing it if
+    /// needed. Returns `false` if the file is not in the store.
+    fn materialize(&self, handle: &FileStoreHandle) -> Result<bool, Error> {
+        if handle.path.exists() {
+            return Ok(true);
+        }
+        let compressed_path = self.key_to_compressed_path(&handle.key);
+        if !compressed_path.exists() {
+            return Ok(false);
+        }
+        trace!("Decompressing {compressed_path:?}");
+        let mut content = self.open_content(&handle.key)?;
+        let mut tmpfile = tempfile::NamedTempFile::new_in(compressed_path.parent().unwrap())
+            .context("Failed to create temporary file for decompressing the file")?;
+        std::io::copy(&mut content, tmpfile.as_file_mut())
+            .with_context(|| format!("Failed to decompress {}", compressed_path.display()))?;
+        tmpfile
+            .persist(&handle.path)
+            .with_context(|| format!("Failed to persist {}", handle.path.display()))?;
+        FileStore::mark_readonly(&handle.path).context("Failed to mark file as readonly")?;
+        self.add_materialized(&handle.key, &handle.path)?;
+        let mut index = self.index.lock().unwrap();
+        self.maybe_flush(&mut index)?;
+        Ok(true)
+    }
+
+    /// Count the space used by the uncompressed copy at `path` of a compressed file in the size of
+    /// the store, until the copy is removed.
+    fn add_materialized(&self, key: &FileStoreKey, path: &Path) -> Result<(), Error> {
+        let size = std::fs::metadata(path)
+            .with_context(|| format!("Cannot get file metadata of {}", path.display()))?
+            .len();
+        let disk_size = self.index.lock().unwrap().disk_size(size);
+        self.locked_files
+            .lock()
+            .unwrap()
+            .materialized
+            .insert(key.clone(), disk_size);
+        Ok(())
+    }
+
+    /// Write the content to `path`, atomically. Returns the checksum of the content, if requested.
+    fn write_file<I>(path: &Path, content: I, checksum: bool) -> Result<Option<u64>, Error>
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+    {
+        // assuming moving files is atomic this should be MT-safe
+        let dir = path.parent().unwrap();
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create directory at {}", dir.display()))?;
+        let tmpdir = tempfile::TempDir::new_in(dir)
+            .context("Failed to create temporary directory for storing the file")?;
+        let tmpfile_path = tmpdir.path().join("file");
+        let mut tmpfile =
+            std::fs::File::create(&tmpfile_path).context("Failed to create temporary file")?;
+        let mut checksum = checksum.then(Xxh3::new);
+        for data in content {
+            if let Some(checksum) = &mut checksum {
+                checksum.update(&data);
+            }
+            if tmpfile.write_all(&data).is_err() {
+                bail!("Failed to store file");
+            }
+        }
+        std::fs::rename(&tmpfile_path, path).with_context(|| {
+            format!(
+                "Failed to rename {} -> {}",
+                tmpfile_path.display(),
+                path.display()
+            )
+        })?;
+        FileStore::mark_readonly(path).context("Failed to mark file as readonly")?;
+        Ok(checksum.map(|c| c.digest()))
+    }
+
+    /// Write to `dest` the content of `source` compressed with zstd, atomically.
+    fn compress_file(source: &Path, dest: &Path, level: i32) -> Result<(), Error> {
+        let dir = dest.parent().unwrap();
+        let tmpfile = tempfile::NamedTempFile::new_in(dir)
+            .context("Failed to create temporary file for compressing the file")?;
+        let source_file = std::fs::File::open(source)
+            .with_context(|| format!("Failed to open {}", source.display()))?;
+        zstd::stream::copy_encode(source_file, tmpfile.as_file(), level)
+            .with_context(|| format!("Failed to compress {}", source.display()))?;
+        tmpfile
+            .persist(dest)
+            .with_context(|| format!("Failed to persist {}", dest.display()))?;
+        FileStore::mark_readonly(dest).context("Failed to mark file as readonly")?;
+        Ok(())
+    }
+
+    /// Open a reader over the uncompressed content of the file stored with that key.
+    fn open_content(&self, key: &FileStoreKey) -> Result<Box<dyn Read>, Error> {
+        let compressed_path = self.key_to_compressed_path(key);
+        if compressed_path.exists() {
+            let file = std::fs::File::open(&compressed_path)
+                .with_context(|| format!("Failed to open {}", compressed_path.display()))?;
+            Ok(Box::new(
+                zstd::Decoder::new(file).context("Failed to create zstd decoder")?,
+            ))
+        } else {
+            let path = self.key_to_path(key);
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            Ok(Box::new(file))
+        }
+    }
+
+    /// Path of the persistent copy of the file on disk: the compressed one, if present.
+    fn stored_path(&self, key: &FileStoreKey) -> PathBuf {
+        let compressed_path = self.key_to_compressed_path(key);
+        if compressed_path.exists() {
+            compressed_path
+        } else {
+            self.key_to_path(key)
+        }
+    }
+
     /// Mark a file as readonly.
     fn mark_readonly(path: &Path) -> Result<(), Error> {
         let mut perms = std::fs::metadata(path)
@@ -338,9 +796,46 @@ impl FileStore {
         Ok(())
     }
 
-    /// Check if the file is not corrupted.
+    /// Check if the file is not corrupted, as configured by the `IntegrityMode` of the store.
     fn check_integrity(&self, key: &FileStoreKey) -> bool {
-        let path = self.key_to_path(key);
+        match self.integrity_mode {
+            IntegrityMode::Off => true,
+            IntegrityMode::Fast => self.check_checksum(key),
+            IntegrityMode::Full => self.check_hash(key),
+        }
+    }
+
+    /// Check the size and the checksum of the file against the ones in the index. The files not in
+    /// the index cannot be checked and are assumed not to be corrupted.
+    fn check_checksum(&self, key: &FileStoreKey) -> bool {
+        let Some((size, checksum)) = self.index.lock().unwrap().checksum(key) else {
+            return true;
+        };
+        match std::fs::metadata(self.stored_path(key)) {
+            Ok(metadata) if metadata.len() == size => {}
+            _ => return false,
+        }
+        let Some(checksum) = checksum else {
+            return true;
+        };
+        let Ok(mut content) = self.open_content(key) else {
+            return false;
+        };
+        let mut hasher = Xxh3::new();
+        let mut buf = [0; 8 * 1024];
+        loop {
+            match content.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => hasher.update(&buf[..n]),
+                Err(_) => return false,
+            }
+        }
+        hasher.digest() == checksum
+    }
+
+    /// Check the hash of the file against its key.
+    fn check_hash(&self, key: &FileStoreKey) -> bool {
+        let path = self.stored_path(key);
         let metadata = std::fs::metadata(&path);
         // if the last modified time is the same of creation time assume it's
         // not corrupted
@@ -353,18 +848,24 @@ impl FileStore {
                 }
             }
         }
-        match FileStoreKey::from_file(&path) {
+        match self.open_content(key).and_then(FileStoreKey::from_reader) {
             Ok(key2) => key2.hash == key.hash,
             Err(_) => false,
         }
     }
 
     /// Check if the file store needs flushing, and do so if needed.
+    ///
+    /// The uncompressed copies of the compressed files cannot be flushed while they are in use, but
+    /// the space they use is taken from the one available to the files in the index.
     fn maybe_flush(&self, index: &mut FileStoreIndex) -> Result<(), Error> {
-        if index.need_flush(self.max_store_size) {
-            let locked = self.locked_files.lock().unwrap();
+        let locked = self.locked_files.lock().unwrap();
+        let materialized = locked.materialized();
+        if index.need_flush(self.max_store_size.saturating_sub(materialized)) {
+            let target_size =
+                (self.min_store_size + locked.reserved()).saturating_sub(materialized);
             index
-                .flush(self, &locked, self.min_store_size)
+                .flush(self, &locked, target_size)
                 .context("Failed to flush index")?;
         }
         Ok(())
@@ -414,17 +915,19 @@ impl FileStoreKey {
     /// Make a new `FileStoreKey` from a file on disk. The file must exist and be readable.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<FileStoreKey, Error> {
         let path = path.as_ref();
-        let mut hasher = Hasher::new();
         if !path.exists() {
             bail!("Cannot read {}, maybe broken symlink?", path.display())
         }
-        let file_reader = ReadFileIterator::new(path)
-            .with_context(|| format!("Cannot make file iterator of {}", path.display()))?;
-        file_reader
-            .map(|buf| {
-                hasher.update(&buf);
-            })
-            .last();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        FileStoreKey::from_reader(file).with_context(|| format!("Cannot read {}", path.display()))
+    }
+
+    /// Make a new `FileStoreKey` from a stream, hashing its content while it's consumed. The key
+    /// is the same as the one of a file with the same content.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<FileStoreKey, Error> {
+        let mut hasher = Hasher::new();
+        std::io::copy(&mut reader, &mut hasher).context("Failed to read the content")?;
         Ok(FileStoreKey {
             hash: hasher.finalize(),
         })
@@ -444,6 +947,17 @@ impl std::fmt::Display for FileStoreKey {
     }
 }
 
+impl std::str::FromStr for FileStoreKey {
+    type Err = Error;
+
+    /// Parse a `FileStoreKey` from its hexadecimal representation, as printed by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hash = blake3::Hash::from_hex(s.trim())
+            .map_err(|e| anyhow!("Invalid file store key '{s}': {e}"))?;
+        Ok(FileStoreKey { hash })
+    }
+}
+
 impl std::fmt::Debug for FileStoreKey {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
         write!(f, "{}", self.hash.to_hex())
@@ -487,10 +1001,12 @@ impl FileStoreHandle {
     /// Make a new handle to a file on disk.
     fn new(store: &FileStore, key: &FileStoreKey) -> FileStoreHandle {
         let path = store.key_to_path(key);
+        let compressed_path = store.key_to_compressed_path(key);
         let mut locked_files = store.locked_files.lock().unwrap();
         *locked_files.ref_counts.entry(key.clone()).or_default() += 1;
         FileStoreHandle {
             path,
+            compressed_path,
             locked_files: store.locked_files.clone(),
             key: key.clone(),
         }
@@ -505,6 +1021,26 @@ impl FileStoreHandle {
     pub fn key(&self) -> &FileStoreKey {
         &self.key
     }
+
+    /// Make an iterator over the content of the file. The iterator keeps the file in the store
+    /// until it's dropped.
+    pub fn reader(&self) -> Result<ReadFileIterator, Error> {
+        let mut reader = ReadFileIterator::new(&self.path)
+            .with_context(|| format!("Failed to open the file {} of the store", self.key))?;
+        reader.handle = Some(self.clone());
+        Ok(reader)
+    }
+
+    /// Read the whole content of the file.
+    pub fn read_all(&self) -> Result<Vec<u8>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                bail!("The file {} has been removed from the store", self.key)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        }
+    }
 }
 
 impl PartialEq for FileStoreHandle {
@@ -520,6 +1056,7 @@ impl Clone for FileStoreHandle {
 
         FileStoreHandle {
             path: self.path.clone(),
+            compressed_path: self.compressed_path.clone(),
             locked_files: self.locked_files.clone(),
             key: self.key.clone(),
         }
@@ -544,6 +1081,15 @@ impl Drop for FileStoreHandle {
             .expect("Ref counts are broken") -= 1;
         if locked_files.ref_counts[&self.key] == 0 {
             locked_files.ref_counts.remove(&self.key);
+            // the uncompressed copy of a compressed file is needed only while there are handles
+            if self.compressed_path.exists() && self.path.exists() {
+                match FileStore::remove_file(&self.path) {
+                    Ok(()) => {
+                        locked_files.materialized.remove(&self.key);
+                    }
+                    Err(e) => warn!("Cannot remove the uncompressed copy {:?}: {e:?}", self.path),
+                }
+            }
         }
     }
 }
@@ -553,10 +1099,62 @@ impl LockedFiles {
     fn new() -> LockedFiles {
         LockedFiles {
             ref_counts: HashMap::new(),
+            reservations: BTreeMap::new(),
+            next_reservation: 0,
+            materialized: HashMap::new(),
+        }
+    }
+
+    /// The space used on disk by the uncompressed copies of the compressed files.
+    fn materialized(&self) -> u64 {
+        self.materialized.values().sum()
+    }
+
+    /// The total space reserved by the reservations alive.
+    fn reserved(&self) -> u64 {
+        self.reservations.values().sum()
+    }
+
+    /// Account `size` bytes of newly stored files to the reservations alive, starting from the
+    /// oldest one.
+    fn use_reserved(&mut self, mut size: u64) {
+        for remaining in self.reservations.values_mut() {
+            if size == 0 {
+                break;
+            }
+            let used = size.min(*remaining);
+            *remaining -= used;
+            size -= used;
         }
     }
 }
 
+impl Reservation {
+    /// The space of this reservation not used yet by the stored files.
+    pub fn remaining(&self) -> u64 {
+        let locked_files = self
+            .locked_files
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        locked_files
+            .reservations
+            .get(&self.id)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        // the space must be released even if a thread panicked while holding the lock
+        let mut locked_files = self
+            .locked_files
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        locked_files.reservations.remove(&self.id);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::*;
@@ -604,7 +1202,7 @@ mod tests {
     #[test]
     fn test_new_filestore() {
         let cwd = get_cwd();
-        let _store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let _store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         assert!(cwd.path().join(STORE_LOCK_FILE).exists());
     }
 
@@ -614,10 +1212,11 @@ mod tests {
 
         let cwd = get_cwd();
         let store_dir = cwd.path().to_owned();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let thr = std::thread::spawn(move || {
             let start = Instant::now();
-            let _store = FileStore::new(store_dir, 1000, 1000).unwrap();
+            let _store =
+                FileStore::new(store_dir, 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
             let end = Instant::now();
             assert!(end - start >= Duration::from_millis(300));
         });
@@ -626,10 +1225,29 @@ mod tests {
         thr.join().unwrap();
     }
 
+    #[test]
+    fn test_try_new_filestore_locked() {
+        let cwd = get_cwd();
+        let store =
+            FileStore::try_new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        assert!(store.is_some());
+        assert!(
+            FileStore::try_new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1)
+                .unwrap()
+                .is_none()
+        );
+        drop(store);
+        assert!(
+            FileStore::try_new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1)
+                .unwrap()
+                .is_some()
+        );
+    }
+
     #[test]
     fn test_store() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "test", &store);
         let path_in_store = store.key_to_path(&handle.key);
         assert!(path_in_store.exists());
@@ -650,7 +1268,7 @@ mod tests {
     #[test]
     fn test_get() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciao", &store);
 
         let handle = store.get(&handle.key).unwrap();
@@ -659,10 +1277,20 @@ mod tests {
         assert_eq!(path_in_store, path);
     }
 
+    #[test]
+    fn test_stored_size() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciao", &store);
+        assert_eq!(store.stored_size(&handle.key), Some(4));
+        let missing = FileStoreKey::from_content(b"missing");
+        assert_eq!(store.stored_size(&missing), None);
+    }
+
     #[test]
     fn test_get_removed() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciao", &store);
         let path_in_store = store.key_to_path(&handle.key);
 
@@ -675,7 +1303,7 @@ mod tests {
     #[test]
     fn test_get_not_known() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let key = fake_file(cwd.path().join("test.txt"), "ciao");
         let handle = store.get(&key);
         assert!(handle.is_none());
@@ -683,22 +1311,56 @@ mod tests {
 
     #[test]
     fn test_corrupted_file() {
-        if !INTEGRITY_CHECKS_ENABLED {
-            return;
-        }
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Fast, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciao", &store);
         let path_in_store = store.key_to_path(&handle.key);
         corrupt_file(&path_in_store);
-        let handle = store.get(&handle.key);
-        assert!(handle.is_none());
+        assert!(store.get(&handle.key).is_none());
+        assert!(!path_in_store.exists());
+        assert!(store.index.lock().unwrap().checksum(&handle.key).is_none());
+    }
+
+    #[test]
+    fn test_corrupted_file_integrity_off() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciao", &store);
+        corrupt_file(&store.key_to_path(&handle.key));
+        assert!(store.get(&handle.key).is_some());
+    }
+
+    #[test]
+    fn test_corrupted_file_after_reopen() {
+        let cwd = get_cwd();
+        let key = {
+            let store =
+                FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Fast, None, 1).unwrap();
+            let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciao", &store);
+            handle.key.clone()
+        };
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Fast, None, 1).unwrap();
+        assert_eq!(
+            store.index.lock().unwrap().integrity_mode,
+            IntegrityMode::Fast
+        );
+        assert!(store.get(&key).is_some());
+        corrupt_file(&store.key_to_path(&key));
+        assert!(store.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_parse_integrity_mode() {
+        assert_eq!("off".parse(), Ok(IntegrityMode::Off));
+        assert_eq!("Fast".parse(), Ok(IntegrityMode::Fast));
+        assert_eq!("full".parse(), Ok(IntegrityMode::Full));
+        assert!("paranoid".parse::<IntegrityMode>().is_err());
     }
 
     #[test]
     fn test_key_to_path() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let key = fake_file(cwd.path().join("test.txt"), "ciao");
         let path = store.key_to_path(&key);
         assert!(path.starts_with(&store.base_path));
@@ -737,7 +1399,7 @@ mod tests {
             return;
         }
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Full, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store);
         let path = store.key_to_path(&handle.key);
         corrupt_file(&path);
@@ -747,7 +1409,7 @@ mod tests {
     #[test]
     fn test_locked_files() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store);
         let key = handle.key.clone();
         assert_eq!(store.locked_files.lock().unwrap().ref_counts[&key], 1);
@@ -767,7 +1429,7 @@ mod tests {
     #[test]
     fn test_locked_files_different_means() {
         let cwd = get_cwd();
-        let store = FileStore::new(cwd.path(), 1000, 1000).unwrap();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
         let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store);
         let key = handle.key.clone();
         assert_eq!(store.locked_files.lock().unwrap().ref_counts[&key], 1);
@@ -788,6 +1450,225 @@ mod tests {
             .contains_key(&key));
     }
 
+    #[test]
+    fn test_handle_reader() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store);
+        let key = handle.key.clone();
+        let reader = handle.reader().unwrap();
+        drop(handle);
+        // the reader keeps the file locked
+        assert_eq!(store.locked_files.lock().unwrap().ref_counts[&key], 1);
+        assert_eq!(reader.flatten().collect::<Vec<u8>>(), b"ciaone");
+        assert!(!store
+            .locked_files
+            .lock()
+            .unwrap()
+            .ref_counts
+            .contains_key(&key));
+    }
+
+    #[test]
+    fn test_handle_read_all() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store);
+        assert_eq!(handle.read_all().unwrap(), b"ciaone");
+        FileStore::remove_file(handle.path()).unwrap();
+        let err = handle.read_all().unwrap_err().to_string();
+        assert!(err.contains("has been removed from the store"), "{err}");
+    }
+
+    #[test]
+    fn test_export_import() {
+        let cwd = get_cwd();
+        let store = FileStore::new(
+            cwd.path().join("store"),
+            1000,
+            1000,
+            IntegrityMode::Off,
+            None,
+            1,
+        )
+        .unwrap();
+        let file1 = add_file_to_store(&cwd.path().join("file1"), "hello", &store);
+        let file2 = add_file_to_store(&cwd.path().join("file2"), "world", &store);
+        let mut export = vec![];
+        let summary = store.export(&mut export).unwrap();
+        assert_eq!(summary.exported.len(), 2);
+        assert!(summary.vanished.is_empty());
+
+        let store2 = FileStore::new(
+            cwd.path().join("store2"),
+            1000,
+            1000,
+            IntegrityMode::Off,
+            None,
+            1,
+        )
+        .unwrap();
+        assert_eq!(store2.import(export.as_slice()).unwrap(), 2);
+        let handle1 = store2.get(file1.key()).unwrap();
+        let handle2 = store2.get(file2.key()).unwrap();
+        assert_eq!(read_to_string(handle1.path()).unwrap(), "hello");
+        assert_eq!(read_to_string(handle2.path()).unwrap(), "world");
+    }
+
+    #[test]
+    fn test_export_vanished() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let key1 = add_file_to_store(&cwd.path().join("file1"), "hello", &store)
+            .key()
+            .clone();
+        let key2 = add_file_to_store(&cwd.path().join("file2"), "world", &store)
+            .key()
+            .clone();
+        FileStore::remove_file(&store.key_to_path(&key1)).unwrap();
+        let summary = store.export(std::io::sink()).unwrap();
+        assert_eq!(summary.exported, vec![key2]);
+        assert_eq!(summary.vanished, vec![key1]);
+    }
+
+    #[test]
+    fn test_import_corrupted() {
+        let cwd = get_cwd();
+        let store = FileStore::new(
+            cwd.path().join("store"),
+            1000,
+            1000,
+            IntegrityMode::Off,
+            None,
+            1,
+        )
+        .unwrap();
+        let file = add_file_to_store(&cwd.path().join("file"), "hello", &store);
+        corrupt_file(file.path());
+        let mut export = vec![];
+        store.export(&mut export).unwrap();
+
+        let store2 = FileStore::new(
+            cwd.path().join("store2"),
+            1000,
+            1000,
+            IntegrityMode::Off,
+            None,
+            1,
+        )
+        .unwrap();
+        assert!(store2.import(export.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_compressed_store_get() {
+        let cwd = get_cwd();
+        let store =
+            FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Fast, Some(3), 1).unwrap();
+        let content = "ciao".repeat(100);
+        let handle = add_file_to_store(&cwd.path().join("test.txt"), &content, &store);
+        let key = handle.key().clone();
+        let compressed_path = store.key_to_compressed_path(&key);
+        assert!(compressed_path.exists());
+        assert_eq!(read_to_string(handle.path()).unwrap(), content);
+        drop(handle);
+        // the uncompressed copy is removed when the last handle is dropped
+        assert!(!store.key_to_path(&key).exists());
+        assert!(compressed_path.exists());
+
+        let handle = store.get(&key).unwrap();
+        assert_eq!(read_to_string(handle.path()).unwrap(), content);
+        // the index keeps the size of the file on disk
+        let (size, _) = store.index.lock().unwrap().checksum(&key).unwrap();
+        assert_eq!(size, metadata(&compressed_path).unwrap().len());
+        assert!(size < content.len() as u64);
+    }
+
+    #[test]
+    fn test_compressed_store_counts_copies() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 950, 100, IntegrityMode::Off, Some(3), 1).unwrap();
+        let big = add_file_to_store(&cwd.path().join("big.txt"), &"a".repeat(900), &store);
+        assert_eq!(store.locked_files.lock().unwrap().materialized(), 900);
+        let small = add_file_to_store(&cwd.path().join("small.txt"), &"b".repeat(50), &store)
+            .key()
+            .clone();
+        assert_eq!(store.locked_files.lock().unwrap().materialized(), 900);
+        // the uncompressed copies of big and of the new file fill the store, small is flushed
+        let _handle = add_file_to_store(&cwd.path().join("new.txt"), &"c".repeat(50), &store);
+        assert!(!store.key_to_compressed_path(&small).exists());
+        assert!(store.key_to_compressed_path(big.key()).exists());
+        drop(big);
+        assert_eq!(store.locked_files.lock().unwrap().materialized(), 50);
+    }
+
+    #[test]
+    fn test_compressed_store_remove_stale_copies() {
+        let cwd = get_cwd();
+        let key = {
+            let store =
+                FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, Some(3), 1).unwrap();
+            let handle = add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store);
+            let key = handle.key().clone();
+            // simulate a crash that leaves the uncompressed copy on disk
+            std::mem::forget(handle);
+            key
+        };
+        assert!(cwd.path().join(key.suffix()).exists());
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        assert!(!store.key_to_path(&key).exists());
+        assert!(store.key_to_compressed_path(&key).exists());
+    }
+
+    #[test]
+    fn test_compressed_store_reopen_uncompressed() {
+        let cwd = get_cwd();
+        let key = {
+            let store =
+                FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Full, Some(3), 1).unwrap();
+            add_file_to_store(&cwd.path().join("test.txt"), "ciaone", &store)
+                .key()
+                .clone()
+        };
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Full, None, 1).unwrap();
+        let handle = store.get(&key).unwrap();
+        assert_eq!(read_to_string(handle.path()).unwrap(), "ciaone");
+        assert!(store.key_to_compressed_path(&key).exists());
+    }
+
+    #[test]
+    fn test_compressed_store_export() {
+        let cwd = get_cwd();
+        let store = FileStore::new(
+            cwd.path().join("store"),
+            1000,
+            1000,
+            IntegrityMode::Off,
+            Some(3),
+            1,
+        )
+        .unwrap();
+        let key = add_file_to_store(&cwd.path().join("file"), "hello", &store)
+            .key()
+            .clone();
+        let mut export = vec![];
+        store.export(&mut export).unwrap();
+        assert!(!store.key_to_path(&key).exists());
+
+        let store2 = FileStore::new(
+            cwd.path().join("store2"),
+            1000,
+            1000,
+            IntegrityMode::Off,
+            None,
+            1,
+        )
+        .unwrap();
+        assert_eq!(store2.import(export.as_slice()).unwrap(), 1);
+        let handle = store2.get(&key).unwrap();
+        assert_eq!(read_to_string(handle.path()).unwrap(), "hello");
+    }
+
     #[test]
     fn test_file_store_key_from_file() {
         let cwd = get_cwd();
@@ -803,4 +1684,95 @@ mod tests {
         assert_ne!(key1a, key2);
         assert_ne!(key1b, key2);
     }
+
+    #[test]
+    fn test_file_store_key_from_reader() {
+        let cwd = get_cwd();
+        let content = "ciao".repeat(10_000);
+        fake_file(cwd.path().join("file.txt"), &content);
+
+        let from_file = FileStoreKey::from_file(cwd.path().join("file.txt")).unwrap();
+        let from_reader = FileStoreKey::from_reader(content.as_bytes()).unwrap();
+        let from_content = FileStoreKey::from_content(content.as_bytes());
+        assert_eq!(from_file, from_reader);
+        assert_eq!(from_file, from_content);
+        assert_ne!(
+            from_reader,
+            FileStoreKey::from_reader(&b"ciao"[..]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_file_store_key_from_str() {
+        let key = FileStoreKey::from_content(b"ciao");
+        assert_eq!(key.to_string().parse::<FileStoreKey>().unwrap(), key);
+        assert!("ciao".parse::<FileStoreKey>().is_err());
+    }
+
+    #[test]
+    fn test_reserve() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let reservation1 = store.reserve(10);
+        let reservation2 = store.reserve(20);
+        assert_eq!(store.locked_files.lock().unwrap().reserved(), 30);
+        let _handle = add_file_to_store(&cwd.path().join("file.txt"), "hello world", &store);
+        assert_eq!(reservation1.remaining(), 0);
+        assert_eq!(reservation2.remaining(), 19);
+        drop(reservation1);
+        assert_eq!(store.locked_files.lock().unwrap().reserved(), 19);
+        drop(reservation2);
+        assert_eq!(store.locked_files.lock().unwrap().reserved(), 0);
+    }
+
+    #[test]
+    fn test_reserve_flush() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 100, 10, IntegrityMode::Off, None, 1).unwrap();
+        // the handles are dropped right away, so x and y can be flushed
+        let x = add_file_to_store(&cwd.path().join("x.txt"), &"x".repeat(40), &store)
+            .path
+            .clone();
+        let y = add_file_to_store(&cwd.path().join("y.txt"), &"y".repeat(40), &store)
+            .path
+            .clone();
+        let reservation = store.reserve(90);
+        let _handle = add_file_to_store(&cwd.path().join("c.txt"), &"c".repeat(25), &store);
+        assert_eq!(reservation.remaining(), 65);
+        // the flush stopped at 10 + 65 bytes, without the reservation both x and y would have been
+        // removed
+        assert!(x.exists() ^ y.exists());
+    }
+
+    #[test]
+    fn test_reserve_concurrent() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    let reservations: Vec<_> = (0..100).map(|_| store.reserve(1)).collect();
+                    drop(reservations);
+                });
+            }
+            let _reservation = store.reserve(5);
+        });
+        assert_eq!(store.locked_files.lock().unwrap().reserved(), 0);
+        let _reservation1 = store.reserve(5);
+        let reservation2 = std::thread::scope(|scope| scope.spawn(|| store.reserve(7)).join());
+        let _reservation2 = reservation2.unwrap();
+        assert_eq!(store.locked_files.lock().unwrap().reserved(), 12);
+    }
+
+    #[test]
+    fn test_reserve_panic() {
+        let cwd = get_cwd();
+        let store = FileStore::new(cwd.path(), 1000, 1000, IntegrityMode::Off, None, 1).unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _reservation = store.reserve(100);
+            panic!("failed while storing the files");
+        }));
+        assert!(result.is_err());
+        assert_eq!(store.locked_files.lock().unwrap().reserved(), 0);
+    }
 }
@@ -4,6 +4,8 @@ use std::path::Path;
 
 use anyhow::{Context, Error};
 
+use crate::FileStoreHandle;
+
 /// Buffer size when reading a file
 const READ_FILE_BUFFER_SIZE: usize = 8 * 1024;
 /// Type of the reading buffer
@@ -34,6 +36,9 @@ pub struct ReadFileIterator {
     buf_reader: BufReader<File>,
     /// Current read buffer
     buf: ReadFileBuffer,
+    /// The handle of the file if it's inside a `FileStore`, kept so that the file is not flushed
+    /// while it's being read.
+    pub(crate) handle: Option<FileStoreHandle>,
 }
 
 impl ReadFileIterator {
@@ -45,6 +50,7 @@ impl ReadFileIterator {
         Ok(ReadFileIterator {
             buf_reader: BufReader::new(file),
             buf: [0; READ_FILE_BUFFER_SIZE],
+            handle: None,
         })
     }
 }
@@ -136,10 +136,18 @@ impl TestInterface {
                 solution_filter: vec![],
                 booklet_solutions: false,
                 no_statement: false,
+                check_statement: false,
                 solution_paths: vec![],
+                allowed_languages: vec![],
                 disabled_sanity_checks: vec![],
                 seed: None,
                 dry_run: false,
+                max_executions: None,
+                pinned_inputs: None,
+                sanitized_solutions: vec![],
+                num_processes: None,
+                stop_after: None,
+                opt_level: None,
             },
         )
         .unwrap();
@@ -155,6 +163,9 @@ impl TestInterface {
             Evaluation::Clean => {
                 panic!("Unexpected task cleaning");
             }
+            Evaluation::SanityChecks => {
+                panic!("Unexpected sanity checks only run");
+            }
         }
     }
 
@@ -15,9 +15,9 @@ syntax keyword GenRunK contained RUN nextgroup=GenRunV skipwhite
 syntax keyword GenCopyK contained COPY nextgroup=GenExe skipwhite
 syntax keyword GenSubtaskK contained SUBTASK nextgroup=GenScore skipwhite
 syntax keyword GenSubtaskK contained STDEP skipwhite
-syntax keyword GenConstraintK contained CONSTRAINT nextgroup=GenNumber
+syntax keyword GenConstraintK contained CONSTRAINT GLOBAL_CONSTRAINT nextgroup=GenNumber
 syntax match GenCommand   /^:.*/ contains=GenGeneratorK,GenSubtaskK,GenRunK,GenCopyK
-syntax match GenConstraintCommand   /^:\s*CONSTRAINT.*/ contains=GenConstraintK,GenNumber,GenVariable
+syntax match GenConstraintCommand   /^:\s*\(GLOBAL_\)\=CONSTRAINT.*/ contains=GenConstraintK,GenNumber,GenVariable
 syntax match GenComment   /^#.*/
 
 hi def link GenComment Comment